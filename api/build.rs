@@ -0,0 +1,60 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Embeds the git commit, build timestamp and iroh crate versions for the `/version` endpoint.
+fn main() {
+    let git_commit = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // Honour SOURCE_DATE_EPOCH so reproducible builds get a stable timestamp
+    let build_timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0)
+        });
+
+    let lock = std::fs::read_to_string("../Cargo.lock").unwrap_or_default();
+
+    println!("cargo:rustc-env=STARTER_KIT_GIT_COMMIT={}", git_commit);
+    println!("cargo:rustc-env=STARTER_KIT_BUILD_TIMESTAMP={}", build_timestamp);
+    println!("cargo:rustc-env=STARTER_KIT_IROH_VERSION={}", locked_version(&lock, "iroh"));
+    println!("cargo:rustc-env=STARTER_KIT_IROH_DOCS_VERSION={}", locked_version(&lock, "iroh-docs"));
+    println!("cargo:rustc-env=STARTER_KIT_IROH_BLOBS_VERSION={}", locked_version(&lock, "iroh-blobs"));
+    println!("cargo:rerun-if-changed=../Cargo.lock");
+    // Only watch git metadata when building from a checkout, otherwise cargo reruns every build
+    for path in ["../.git/HEAD", "../.git/refs/heads"] {
+        if std::path::Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
+
+// Finds the version of a package in Cargo.lock
+fn locked_version(lock: &str, package: &str) -> String {
+    let name_line = format!("name = \"{}\"", package);
+    let mut lines = lock.lines();
+
+    while let Some(line) = lines.next() {
+        if line.trim() == name_line {
+            if let Some(version) = lines
+                .next()
+                .and_then(|line| line.trim().strip_prefix("version = "))
+            {
+                return version.trim_matches('"').to_string();
+            }
+        }
+    }
+
+    "unknown".to_string()
+}
@@ -0,0 +1,58 @@
+use helpers::{
+    access_log::{AccessFilter, AccessRecord},
+    pagination::{Paginated, PaginationParams},
+    state::AppState,
+};
+use gateway::access_control::check_node_id_and_domain_header;
+use crate::backups_handler::check_default_author;
+
+use axum::{extract::{Query, State}, Json, http::{HeaderMap, StatusCode}};
+use serde::Deserialize;
+
+// Query parameters
+// 1. access log, along with `PaginationParams`
+#[derive(Deserialize, Default)]
+pub struct AccessLogQuery {
+    pub doc_id: Option<String>,
+    /// Only reads of keys starting with this.
+    pub key_prefix: Option<String>,
+    /// Blob hash, as hex.
+    pub hash: Option<String>,
+    /// e.g. `author:<id>`, `node:<id>` or `origin:<domain>`.
+    pub caller: Option<String>,
+    pub tenant: Option<String>,
+    /// Unix time in seconds.
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+}
+
+// Response bodies
+// 1. access log
+// Paginated<AccessRecord>
+
+// Handler for listing the recorded reads, oldest first. They show who read which records, so
+// like backups only the default author reviews them.
+pub async fn access_log_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AccessLogQuery>,
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<AccessRecord>>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let filter = AccessFilter {
+        doc_id: query.doc_id,
+        key_prefix: query.key_prefix,
+        hash: query.hash,
+        caller: query.caller,
+        tenant: query.tenant,
+        since: query.since,
+        until: query.until,
+    };
+
+    match state.access_log.query(&filter) {
+        Ok(records) => Ok(Json(Paginated::from_items(records, &pagination)?)),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
@@ -0,0 +1,266 @@
+use helpers::{
+    approvals::{GuardedOperation, PendingOperation, GUARDED_OPERATIONS},
+    events::NodeEvent,
+    state::AppState,
+    utils::get_author_id_from_headers,
+};
+use gateway::access_control::check_node_id_and_domain_header;
+use crate::{request_schema, validation::{self, ValidatedJson}};
+
+use core::{authors::{delete_author, get_default_author, list_authors}, blobs::delete_tag, docs::drop_doc, sharding::drop_sharded_doc, trash::empty_trash};
+use axum::{extract::State, Json, http::{HeaderMap, StatusCode}};
+use serde::{Deserialize, Serialize};
+
+// Request bodies
+// 1. propose operation
+#[derive(Deserialize)]
+pub struct ProposeOperationRequest {
+    /// One of `GUARDED_OPERATIONS`.
+    pub operation: String,
+    /// The document ID, author ID or tag name the operation applies to.
+    pub target: String,
+    /// The author whose trash `empty_trash` purges.
+    #[serde(default)]
+    pub author_id: Option<String>,
+    /// Only purge the entry deleted at this key, for `empty_trash`.
+    #[serde(default)]
+    pub key: Option<String>,
+}
+request_schema!(ProposeOperationRequest, {
+    "operation": validation::one_of(&GUARDED_OPERATIONS),
+    "target": validation::non_empty(),
+});
+
+// 2. approve operation
+#[derive(Deserialize)]
+pub struct ApproveOperationRequest {
+    pub id: String,
+    /// SS58 public key of the approving admin.
+    pub admin: String,
+    /// 0x-prefixed hex signature of the admin over the operation's `message`.
+    pub signature: String,
+}
+request_schema!(ApproveOperationRequest, {
+    "id": validation::non_empty(),
+    "admin": validation::ss58(),
+    "signature": validation::non_empty(),
+});
+
+// 3. list pending operations
+// No request body
+
+// Response bodies
+// 1. propose operation
+#[derive(Serialize)]
+pub struct PendingOperationResponse {
+    #[serde(flatten)]
+    pub pending: PendingOperation,
+    /// The message admins sign to approve the operation.
+    pub message: String,
+    pub approvals_required: usize,
+}
+
+// 2. approve operation
+#[derive(Serialize)]
+pub struct ApproveOperationResponse {
+    pub id: String,
+    pub approvals: usize,
+    pub approvals_required: usize,
+    /// Whether this approval reached the threshold and the operation ran.
+    pub executed: bool,
+}
+
+// 3. list pending operations
+#[derive(Serialize)]
+pub struct ListPendingOperationsResponse {
+    pub operations: Vec<PendingOperationResponse>,
+}
+
+fn to_response(pending: PendingOperation, approvals_required: usize) -> PendingOperationResponse {
+    let message = pending.message();
+    PendingOperationResponse { pending, message, approvals_required }
+}
+
+fn approvals_required(state: &AppState) -> usize {
+    state.approvals.policy().map_or(0, |policy| policy.threshold())
+}
+
+// Runs an approved operation, with the same events as its direct endpoint
+async fn execute(state: &AppState, operation: &GuardedOperation) -> Result<(), String> {
+    match operation {
+        GuardedOperation::DropDoc { doc_id } => {
+            drop_doc(state.docs.clone(), doc_id.clone()).await.map_err(|e| e.to_string())?;
+            state.events.publish(NodeEvent::DocDropped { doc_id: doc_id.clone() });
+        }
+        GuardedOperation::DropShardedDoc { doc_id } => {
+            let sharded = drop_sharded_doc(state.docs.clone(), state.blobs.clone(), doc_id)
+                .await
+                .map_err(|e| e.to_string())?;
+            for doc_id in std::iter::once(&sharded.doc_id).chain(&sharded.shards) {
+                state.events.publish(NodeEvent::DocDropped { doc_id: doc_id.clone() });
+            }
+        }
+        GuardedOperation::DeleteAuthor { author_id } => {
+            delete_author(state.docs.clone(), author_id.clone()).await.map_err(|e| e.to_string())?;
+            state.events.publish(NodeEvent::AuthorDeleted { author_id: author_id.clone() });
+        }
+        GuardedOperation::DeleteTag { tag_name } => {
+            delete_tag(state.blobs.clone(), tag_name.clone()).await.map_err(|e| e.to_string())?;
+            state.events.publish(NodeEvent::TagDeleted { tag: tag_name.clone() });
+        }
+        GuardedOperation::EmptyTrash { doc_id, author_id, key } => {
+            empty_trash(state.docs.clone(), doc_id.clone(), author_id.clone(), key.clone())
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+// handler for proposing a destructive operation for admin approval
+pub async fn propose_operation_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<ProposeOperationRequest>,
+) -> Result<Json<PendingOperationResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    let operation = match payload.operation.as_str() {
+        "drop_doc" => GuardedOperation::DropDoc { doc_id: payload.target },
+        "drop_sharded_doc" => GuardedOperation::DropShardedDoc { doc_id: payload.target },
+        "delete_author" => GuardedOperation::DeleteAuthor { author_id: payload.target },
+        "delete_tag" => GuardedOperation::DeleteTag { tag_name: payload.target },
+        "empty_trash" => GuardedOperation::EmptyTrash {
+            doc_id: payload.target,
+            author_id: payload.author_id
+                .ok_or((StatusCode::BAD_REQUEST, "empty_trash needs the author_id of the trash".to_string()))?,
+            key: payload.key,
+        },
+        other => return Err((StatusCode::BAD_REQUEST, format!("Unknown operation '{}'", other))),
+    };
+
+    // Proposing needs the same rights as running the operation directly
+    if let GuardedOperation::DeleteAuthor { .. } = operation {
+        let default_author = get_default_author(state.docs.clone())
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if caller_author_id != default_author {
+            return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
+        }
+    } else {
+        let authors = list_authors(state.docs.clone())
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if !authors.contains(&caller_author_id) {
+            return Err((StatusCode::FORBIDDEN, "Only a registered author can perform this action".to_string()));
+        }
+    }
+
+    match state.approvals.propose(uuid::Uuid::new_v4().to_string(), operation, caller_author_id).await {
+        Ok(pending) => Ok(Json(to_response(pending, approvals_required(&state)))),
+        Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+    }
+}
+
+// handler for approving a pending operation, which runs it once enough admins approved
+pub async fn approve_operation_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<ApproveOperationRequest>,
+) -> Result<Json<ApproveOperationResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    // The admin's signature is the authorization here, so no author is needed
+    let (pending, ready) = state
+        .approvals
+        .approve(&payload.id, &payload.admin, &payload.signature)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    if ready {
+        // `approve` claimed the operation, so it is released for a retry if it fails
+        if let Err(e) = execute(&state, &pending.operation).await {
+            state.approvals.release(&pending.id);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, e));
+        }
+        state
+            .approvals
+            .remove(&pending.id)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+
+    Ok(Json(ApproveOperationResponse {
+        id: pending.id,
+        approvals: pending.approvals.len(),
+        approvals_required: approvals_required(&state),
+        executed: ready,
+    }))
+}
+
+// handler for listing the operations waiting for approval
+pub async fn list_pending_operations_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ListPendingOperationsResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let approvals_required = approvals_required(&state);
+    Ok(Json(ListPendingOperationsResponse {
+        operations: state
+            .approvals
+            .list()
+            .into_iter()
+            .map(|pending| to_response(pending, approvals_required))
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GUARDED_OPERATIONS;
+    use std::collections::BTreeSet;
+
+    // Handlers that can run a guarded operation directly. `execute` above runs them once approved.
+    const HANDLER_SOURCES: [(&str, &str); 5] = [
+        ("authors_handler.rs", include_str!("authors_handler.rs")),
+        ("blobs_handler.rs", include_str!("blobs_handler.rs")),
+        ("docs_handler.rs", include_str!("docs_handler.rs")),
+        ("sharding_handler.rs", include_str!("sharding_handler.rs")),
+        ("../../grpc/src/service.rs", include_str!("../../grpc/src/service.rs")),
+    ];
+
+    // Whether `body` calls `name(` as a function, not as part of a longer identifier or a definition
+    fn calls(body: &str, name: &str) -> bool {
+        let call = format!("{}(", name);
+        body.match_indices(&call).any(|(at, _)| {
+            let before = &body[..at];
+            !before.ends_with(|c: char| c.is_alphanumeric() || c == '_') && !before.ends_with("fn ")
+        })
+    }
+
+    #[test]
+    fn test_every_guarded_handler_checks_the_approval_policy() {
+        let mut guarded = BTreeSet::new();
+        for (file, source) in HANDLER_SOURCES {
+            for function in source.split("async fn ").skip(1) {
+                let name = function.split('(').next().unwrap_or_default();
+                for operation in GUARDED_OPERATIONS {
+                    if calls(function, operation) {
+                        assert!(
+                            function.contains("approvals.check_direct()"),
+                            "{} in {} runs {} without checking the approval policy",
+                            name,
+                            file,
+                            operation
+                        );
+                        guarded.insert(operation);
+                    }
+                }
+            }
+        }
+        assert_eq!(guarded, BTreeSet::from(GUARDED_OPERATIONS));
+    }
+}
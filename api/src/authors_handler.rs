@@ -1,13 +1,16 @@
-use helpers::{state::AppState, utils::get_author_id_from_headers};
+use helpers::{authorizations::AuthorDidLink, state::AppState, utils::get_author_id_from_headers, events::NodeEvent, pagination::{ndjson_response, Paginated, PaginationParams}, tenants::{retain_tenant_authors, Tenant}};
 use gateway::access_control::check_node_id_and_domain_header;
+use crate::{keystore_handler::record_signature, request_schema, validation::{self, ValidatedJson}};
+use keystore::keystore::{decode_public, StarterkitKeystore};
+use keystore::registry::KeyPurpose;
 
 use core::authors::*;
-use axum::{extract::State, Json, http::{HeaderMap, StatusCode}};
+use axum::{extract::{Query, State}, Extension, Json, http::{HeaderMap, StatusCode}, response::Response};
 use serde::{Deserialize, Serialize};
 
 // Request bodies
 // 1. list author
-// no request body needed
+// no request body needed, pagination via `PaginationParams` query
 
 // 2. get default author
 // no request body needed
@@ -17,6 +20,9 @@ use serde::{Deserialize, Serialize};
 pub struct SetDefaultAuthorRequest {
     pub author_id: String,
 }
+request_schema!(SetDefaultAuthorRequest, {
+    "author_id": validation::ss58(),
+});
 
 // 4. create author
 // no request body needed
@@ -26,19 +32,70 @@ pub struct SetDefaultAuthorRequest {
 pub struct DeleteAuthorRequest {
     pub author_id: String,
 }
+request_schema!(DeleteAuthorRequest, {
+    "author_id": validation::ss58(),
+});
 
 // 6. verify author
 #[derive(Deserialize)]
 pub struct VerifyAuthorRequest {
     pub author_id: String,
 }
+request_schema!(VerifyAuthorRequest, {
+    "author_id": validation::ss58(),
+});
+
+// 7. create author from keystore
+#[derive(Deserialize)]
+pub struct CreateAuthorFromKeystoreRequest {
+    /// Name of an ed25519 or ecdsa key type, e.g. `skit` or `ecdsa`.
+    pub key_type: String,
+    /// SS58 or 0x-prefixed hex public key. Omit to use the node's key of `key_type`.
+    pub public: Option<String>,
+}
+request_schema!(CreateAuthorFromKeystoreRequest, {
+    "key_type": validation::non_empty(),
+});
+
+// 8. link did
+#[derive(Deserialize)]
+pub struct LinkDidRequest {
+    pub author_id: String,
+    /// `did:cord:<ss58>`.
+    pub did: String,
+    /// Identifier of the DID's authorization on a CORD registry.
+    pub authorization: String,
+    /// 0x-prefixed hex signature of the DID's account over `starter-kit-link-did:<author_id>:<authorization>`.
+    pub signature: String,
+}
+request_schema!(LinkDidRequest, {
+    "author_id": validation::ss58(),
+    "did": validation::non_empty(),
+    "authorization": validation::non_empty(),
+    "signature": validation::non_empty(),
+});
+
+// 9. unlink did
+#[derive(Deserialize)]
+pub struct UnlinkDidRequest {
+    pub author_id: String,
+}
+request_schema!(UnlinkDidRequest, {
+    "author_id": validation::ss58(),
+});
+
+// 10. get linked did
+#[derive(Deserialize)]
+pub struct LinkedDidQuery {
+    pub author_id: String,
+}
+
+// 11. stream authors
+// no request body needed
 
 // Response bodies
 // 1. List authors
-#[derive(Serialize)]
-pub struct AuthorsListResponse {
-    pub authors: Vec<String>,
-}
+// Paginated<String>
 
 // 2. Get default author
 #[derive(Serialize)]
@@ -70,16 +127,53 @@ pub struct VerifyAuthorResponse {
     pub is_valid: bool,
 }
 
+// 7. Create author from keystore
+// CreateAuthorResponse
+
+// 8. Link did
+// AuthorDidLink
+
+// 9. Unlink did
+#[derive(Serialize)]
+pub struct UnlinkDidResponse {
+    pub message: String,
+}
+
+// 10. Get linked did
+// AuthorDidLink
+
+// 11. Stream authors
+// NDJSON, one author ID string per line
+
 
 // handler for listing authors
 pub async fn list_authors_handler(
     State(state): State<AppState>,
+    tenant: Option<Extension<Tenant>>,
     headers: HeaderMap,
-) -> Result<Json<AuthorsListResponse>, (StatusCode, String)> {
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<String>>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
     match list_authors(state.docs.clone()).await {
-        Ok(authors) => Ok(Json(AuthorsListResponse { authors })),
+        Ok(mut authors) => {
+            // A tenant only sees its own authors
+            retain_tenant_authors(tenant.as_deref(), &mut authors);
+            Ok(Json(Paginated::from_items(authors, &pagination)?))
+        },
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// handler for streaming every author as NDJSON, without pagination
+pub async fn stream_authors_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    match stream_authors(state.docs.clone()).await {
+        Ok(authors) => Ok(ndjson_response(authors)),
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
 }
@@ -101,7 +195,7 @@ pub async fn get_default_author_handler(
 pub async fn set_default_author_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<SetDefaultAuthorRequest>,
+    ValidatedJson(payload): ValidatedJson<SetDefaultAuthorRequest>,
 ) -> Result<Json<SetDefaultAuthorResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -145,8 +239,11 @@ pub async fn create_author_handler(
         return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
     }
 
-    match create_author(state.docs.clone(), state.cord_client.clone(), state.cord_signer.clone()).await {
-        Ok(author_id) => Ok(Json(CreateAuthorResponse { author_id })),
+    match create_author(state.docs.clone(), state.chain.clone()).await {
+        Ok(author_id) => {
+            state.events.publish(NodeEvent::AuthorCreated { author_id: author_id.clone() });
+            Ok(Json(CreateAuthorResponse { author_id }))
+        },
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
 }
@@ -155,7 +252,7 @@ pub async fn create_author_handler(
 pub async fn delete_author_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<DeleteAuthorRequest>,
+    ValidatedJson(payload): ValidatedJson<DeleteAuthorRequest>,
 ) -> Result<Json<DeleteAuthorResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -169,15 +266,21 @@ pub async fn delete_author_handler(
         return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
     }
 
+    // With an approval policy, authors are only deleted through the approvals API
+    state.approvals.check_direct().map_err(|e| (StatusCode::FORBIDDEN, e))?;
+
     // request body checks
     if payload.author_id.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "author_id cannot be empty".to_string()));
     }
 
-    match delete_author(state.docs.clone(), payload.author_id).await {
-        Ok(()) => Ok(Json(DeleteAuthorResponse { 
-            message: "Author deleted successfully".to_string()
-        })),
+    match delete_author(state.docs.clone(), payload.author_id.clone()).await {
+        Ok(()) => {
+            state.events.publish(NodeEvent::AuthorDeleted { author_id: payload.author_id });
+            Ok(Json(DeleteAuthorResponse { 
+                message: "Author deleted successfully".to_string()
+            }))
+        },
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
 }
@@ -186,7 +289,7 @@ pub async fn delete_author_handler(
 pub async fn verify_author_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<VerifyAuthorRequest>,
+    ValidatedJson(payload): ValidatedJson<VerifyAuthorRequest>,
 ) -> Result<Json<VerifyAuthorResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -199,4 +302,117 @@ pub async fn verify_author_handler(
         Ok(is_valid) => Ok(Json(VerifyAuthorResponse { is_valid })),
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
-}
\ No newline at end of file
+}
+
+// handler for creating an author backed by a keystore key
+pub async fn create_author_from_keystore_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<CreateAuthorFromKeystoreRequest>,
+) -> Result<Json<CreateAuthorResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Only default author can create authors
+    let default_author = get_default_author(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if caller_author_id != default_author {
+        return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
+    }
+
+    let key_type = state.key_types.get(&payload.key_type).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let keystore = StarterkitKeystore::from_inner(state.cord_signer.keystore.clone());
+    let public = match &payload.public {
+        Some(public) => decode_public(public).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
+        None => keystore
+            .get_public_key(&key_type)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+    };
+
+    // deriving the author signs with the key, which the audit log records like any signature
+    let created = create_author_from_keystore(state.docs.clone(), &keystore, &key_type, &public).await;
+    let signed = !matches!(created, Err(AuthorError::FailedToDeriveAuthor(_)));
+    record_signature(&state, &headers, &key_type, KeyPurpose::DocSigning, &public, signed)?;
+
+    match created {
+        Ok(author_id) => {
+            state.events.publish(NodeEvent::AuthorCreated { author_id: author_id.clone() });
+            Ok(Json(CreateAuthorResponse { author_id }))
+        },
+        Err(e @ AuthorError::FailedToDeriveAuthor(_)) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Only the author itself or the default author may change an author's DID link
+async fn check_link_caller(state: &AppState, headers: &HeaderMap, author_id: &str) -> Result<(), (StatusCode, String)> {
+    let caller_author_id = get_author_id_from_headers(headers)?;
+    if caller_author_id == author_id {
+        return Ok(());
+    }
+    let default_author = get_default_author(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if caller_author_id != default_author {
+        return Err((StatusCode::FORBIDDEN, "Only the author or the default author can perform this action".to_string()));
+    }
+    Ok(())
+}
+
+// handler for linking an author to a CORD DID and its registry authorization
+pub async fn link_did_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<LinkDidRequest>,
+) -> Result<Json<AuthorDidLink>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_link_caller(&state, &headers, &payload.author_id).await?;
+
+    let authors = list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&payload.author_id) {
+        return Err((StatusCode::NOT_FOUND, AuthorError::AuthorNotFound.to_string()));
+    }
+
+    // The DID's signature shows its holder agrees to the author writing under its authorization
+    state
+        .authorizations
+        .link(&payload.author_id, &payload.did, &payload.authorization, &payload.signature)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+// handler for removing an author's DID link
+pub async fn unlink_did_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<UnlinkDidRequest>,
+) -> Result<Json<UnlinkDidResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_link_caller(&state, &headers, &payload.author_id).await?;
+
+    match state.authorizations.unlink(&payload.author_id).await {
+        Ok(true) => Ok(Json(UnlinkDidResponse { message: "DID unlinked successfully".to_string() })),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "The author has no linked DID".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// handler for getting the DID an author is linked to
+pub async fn get_linked_did_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<LinkedDidQuery>,
+) -> Result<Json<AuthorDidLink>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    state
+        .authorizations
+        .get(&query.author_id)
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "The author has no linked DID".to_string()))
+}
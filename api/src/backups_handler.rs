@@ -0,0 +1,116 @@
+use helpers::{state::AppState, utils::get_author_id_from_headers};
+use gateway::access_control::check_node_id_and_domain_header;
+use core::backups::{create_snapshot, list_snapshots, prune_snapshots, BackupError, SnapshotInfo};
+use crate::{request_schema, validation::ValidatedJson};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use axum::{extract::{Query, State}, Json};
+use axum::http::{StatusCode, HeaderMap};
+
+// Request bodies
+// 1. list backups
+// No request body
+
+// 2. create backup
+#[derive(Deserialize)]
+pub struct CreateBackupQuery {
+    /// Take a full snapshot even if an incremental one is due.
+    #[serde(default)]
+    pub full: bool,
+}
+
+// 3. prune backups
+#[derive(Deserialize)]
+pub struct PruneBackupsRequest {
+    /// Number of newest snapshots to keep.
+    pub keep: usize,
+}
+request_schema!(PruneBackupsRequest, {
+    "keep": json!({ "type": "integer", "minimum": 1 }),
+});
+
+// Response bodies
+// 1. list backups
+#[derive(Serialize)]
+pub struct ListBackupsResponse {
+    /// Snapshots in the backup directory, newest first.
+    pub snapshots: Vec<SnapshotInfo>,
+}
+
+// 2. create backup
+// SnapshotInfo
+
+// 3. prune backups
+#[derive(Serialize)]
+pub struct PruneBackupsResponse {
+    /// Names of the deleted snapshots.
+    pub removed: Vec<String>,
+}
+
+// Maps a backup error to the response status
+fn backup_error_response(e: BackupError) -> (StatusCode, String) {
+    let status = match &e {
+        BackupError::NotConfigured => StatusCode::NOT_FOUND,
+        BackupError::FailedToUploadSnapshot => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+// Snapshots hold every author secret of the node, so only the default author can manage them
+pub(crate) async fn check_default_author(state: &AppState, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let caller_author_id = get_author_id_from_headers(headers)?;
+    let default_author = core::authors::get_default_author(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if caller_author_id != default_author {
+        return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
+    }
+    Ok(())
+}
+
+// Handler for listing the snapshots in the backup directory
+pub async fn list_backups_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ListBackupsResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let snapshots = list_snapshots(&state.backups).await.map_err(backup_error_response)?;
+
+    Ok(Json(ListBackupsResponse { snapshots }))
+}
+
+// Handler for taking a snapshot now, incremental unless a full one is due or requested
+pub async fn create_backup_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<CreateBackupQuery>,
+) -> Result<Json<SnapshotInfo>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let snapshot = create_snapshot(state.docs.clone(), state.blobs.clone(), &state.backups, query.full)
+        .await
+        .map_err(backup_error_response)?;
+
+    Ok(Json(snapshot))
+}
+
+// Handler for deleting all but the newest snapshots
+pub async fn prune_backups_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<PruneBackupsRequest>,
+) -> Result<Json<PruneBackupsResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let removed = prune_snapshots(&state.backups, payload.keep)
+        .await
+        .map_err(backup_error_response)?;
+
+    Ok(Json(PruneBackupsResponse { removed }))
+}
@@ -0,0 +1,316 @@
+use core::{blobs::{add_blob_bytes, add_blob_named}, docs::{add_doc_schema, check_author_authorization, create_doc, delete_entries_by_prefix, set_entries, set_entry}};
+use helpers::{state::AppState, utils::get_author_id_from_headers, events::NodeEvent};
+use gateway::access_control::check_node_id_and_domain_header;
+use crate::{blobs_handler::AddBlobResponse, validation::{self, RequestSchema, ValidatedJson, Validator}};
+
+use axum::{extract::State, Json};
+use axum::http::{StatusCode, HeaderMap};
+use bytes::Bytes;
+use iroh_blobs::util::Tag;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+
+/// Maximum number of operations accepted in a single batch.
+pub const MAX_BATCH_OPERATIONS: usize = 100;
+
+/// Maximum number of entries accepted in a single `set_entries` operation.
+pub const MAX_BATCH_ENTRIES: usize = 1000;
+
+// Request bodies
+// 1. batch
+#[derive(Deserialize)]
+pub struct BatchRequest {
+    pub operations: Vec<BatchOperation>,
+    /// Keep executing after a failed operation instead of skipping the rest.
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+/// A single operation in a batch. `doc_id` fields may be `"$<index>"` to refer to the
+/// document created by an earlier `create_doc` operation in the same batch.
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperation {
+    CreateDoc,
+    AddDocSchema {
+        doc_id: String,
+        author_id: String,
+        schema: String,
+    },
+    SetEntry {
+        doc_id: String,
+        author_id: String,
+        key: String,
+        value: String,
+    },
+    AddBlob {
+        content: String,
+        name: Option<String>,
+    },
+    /// Several entries of one author, written with a single schema check and pipelined writes.
+    SetEntries {
+        doc_id: String,
+        author_id: String,
+        entries: Vec<BatchEntry>,
+    },
+    /// Deletes every entry of an author under a key prefix.
+    DeletePrefix {
+        doc_id: String,
+        author_id: String,
+        prefix: String,
+    },
+}
+
+#[derive(Deserialize)]
+pub struct BatchEntry {
+    pub key: String,
+    pub value: String,
+}
+
+impl BatchOperation {
+    fn name(&self) -> &'static str {
+        match self {
+            BatchOperation::CreateDoc => "create_doc",
+            BatchOperation::AddDocSchema { .. } => "add_doc_schema",
+            BatchOperation::SetEntry { .. } => "set_entry",
+            BatchOperation::AddBlob { .. } => "add_blob",
+            BatchOperation::SetEntries { .. } => "set_entries",
+            BatchOperation::DeletePrefix { .. } => "delete_prefix",
+        }
+    }
+}
+
+// The operation list is checked per op, since each op has its own fields
+impl RequestSchema for BatchRequest {
+    fn validator() -> &'static Validator {
+        static VALIDATOR: OnceLock<Validator> = OnceLock::new();
+        VALIDATOR.get_or_init(|| {
+            let doc_ref = json!({ "type": "string", "pattern": "^(d[0-9a-f]{64}|\\$[0-9]+)$" });
+            let op = |name: &str, fields: Vec<(&str, Value)>| {
+                let mut properties = serde_json::Map::new();
+                properties.insert("op".to_string(), json!({ "const": name }));
+                let mut required = vec!["op".to_string()];
+                for (field, schema) in fields {
+                    properties.insert(field.to_string(), schema);
+                    required.push(field.to_string());
+                }
+                json!({ "type": "object", "properties": properties, "required": required })
+            };
+
+            let schema = json!({
+                "type": "object",
+                "properties": {
+                    "operations": {
+                        "type": "array",
+                        "minItems": 1,
+                        "maxItems": MAX_BATCH_OPERATIONS,
+                        "items": {
+                            "oneOf": [
+                                op("create_doc", vec![]),
+                                op("add_doc_schema", vec![
+                                    ("doc_id", doc_ref.clone()),
+                                    ("author_id", validation::ss58()),
+                                    ("schema", validation::non_empty()),
+                                ]),
+                                op("set_entry", vec![
+                                    ("doc_id", doc_ref.clone()),
+                                    ("author_id", validation::ss58()),
+                                    ("key", validation::key()),
+                                    ("value", validation::non_empty()),
+                                ]),
+                                op("add_blob", vec![
+                                    ("content", validation::non_empty()),
+                                ]),
+                                op("set_entries", vec![
+                                    ("doc_id", doc_ref.clone()),
+                                    ("author_id", validation::ss58()),
+                                    ("entries", json!({
+                                        "type": "array",
+                                        "minItems": 1,
+                                        "maxItems": MAX_BATCH_ENTRIES,
+                                        "items": {
+                                            "type": "object",
+                                            "properties": {
+                                                "key": validation::key(),
+                                                "value": validation::non_empty(),
+                                            },
+                                            "required": ["key", "value"],
+                                        },
+                                    })),
+                                ]),
+                                op("delete_prefix", vec![
+                                    ("doc_id", doc_ref.clone()),
+                                    ("author_id", validation::ss58()),
+                                    ("prefix", validation::key()),
+                                ]),
+                            ]
+                        }
+                    },
+                    "continue_on_error": validation::boolean(),
+                },
+                "required": ["operations"],
+            });
+
+            jsonschema::validator_for(&schema).expect("request schemas are static and must compile")
+        })
+    }
+}
+
+// Response bodies
+// 1. batch
+#[derive(Serialize)]
+pub struct BatchResponse {
+    pub results: Vec<BatchOperationResult>,
+}
+
+#[derive(Serialize)]
+pub struct BatchOperationResult {
+    pub index: usize,
+    pub op: &'static str,
+    /// One of "ok", "error" or "skipped".
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Handler for executing a batch of operations in order with a single auth check
+pub async fn batch_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<BatchRequest>,
+) -> Result<Json<BatchResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Check if the calling author is in the list of authors
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((
+            axum::http::StatusCode::FORBIDDEN,
+            "Only a registered author can perform this action".to_string(),
+        ));
+    }
+
+    let mut results: Vec<BatchOperationResult> = Vec::with_capacity(payload.operations.len());
+    let mut failed = false;
+
+    for (index, operation) in payload.operations.into_iter().enumerate() {
+        let op = operation.name();
+
+        if failed && !payload.continue_on_error {
+            results.push(BatchOperationResult { index, op, status: "skipped", result: None, error: None });
+            continue;
+        }
+
+        match run_operation(&state, operation, &results).await {
+            Ok(result) => results.push(BatchOperationResult {
+                index,
+                op,
+                status: "ok",
+                result: Some(result),
+                error: None,
+            }),
+            Err(error) => {
+                failed = true;
+                results.push(BatchOperationResult { index, op, status: "error", result: None, error: Some(error) });
+            }
+        }
+    }
+
+    Ok(Json(BatchResponse { results }))
+}
+
+// Runs a single operation, returning its result body or an error message
+async fn run_operation(
+    state: &AppState,
+    operation: BatchOperation,
+    previous: &[BatchOperationResult],
+) -> Result<Value, String> {
+    match operation {
+        BatchOperation::CreateDoc => {
+            let doc_id = create_doc(state.docs.clone()).await.map_err(|e| e.to_string())?;
+            state.events.publish(NodeEvent::DocCreated { doc_id: doc_id.clone() });
+            Ok(json!({ "doc_id": doc_id }))
+        }
+        BatchOperation::AddDocSchema { doc_id, author_id, schema } => {
+            let doc_id = resolve_doc_id(doc_id, previous)?;
+            let updated_hash = add_doc_schema(state.docs.clone(), state.blobs.clone(), author_id, doc_id, schema)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(json!({ "updated_hash": updated_hash }))
+        }
+        BatchOperation::SetEntry { doc_id, author_id, key, value } => {
+            let doc_id = resolve_doc_id(doc_id, previous)?;
+            check_author_authorization(state.docs.clone(), state.cord_client.clone(), &state.authorizations, &doc_id, &author_id)
+                .await
+                .map_err(|e| e.to_string())?;
+            let hash = set_entry(state.docs.clone(), state.blobs.clone(), doc_id, author_id, key, value)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(json!({ "hash": hash }))
+        }
+        BatchOperation::SetEntries { doc_id, author_id, entries } => {
+            let doc_id = resolve_doc_id(doc_id, previous)?;
+            check_author_authorization(state.docs.clone(), state.cord_client.clone(), &state.authorizations, &doc_id, &author_id)
+                .await
+                .map_err(|e| e.to_string())?;
+            let entries = entries.into_iter().map(|entry| (entry.key, entry.value)).collect();
+            let hashes = set_entries(state.docs.clone(), state.blobs.clone(), doc_id, author_id, entries)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(json!({ "hashes": hashes }))
+        }
+        BatchOperation::DeletePrefix { doc_id, author_id, prefix } => {
+            let doc_id = resolve_doc_id(doc_id, previous)?;
+            let deleted_count = delete_entries_by_prefix(state.docs.clone(), doc_id, author_id, prefix)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(json!({ "deleted_count": deleted_count }))
+        }
+        BatchOperation::AddBlob { content, name } => {
+            let bytes = Bytes::from(content);
+            let outcome = match name {
+                Some(name) => add_blob_named(state.blobs.clone(), bytes, Tag::from(name)).await,
+                None => add_blob_bytes(state.blobs.clone(), bytes).await,
+            }
+            .map_err(|e| format!("Failed to add blob: {}", e))?;
+
+            state.events.publish(NodeEvent::BlobAdded {
+                hash: outcome.hash.to_string(),
+                size: outcome.size,
+            });
+            serde_json::to_value(AddBlobResponse {
+                hash: outcome.hash.to_string(),
+                format: format!("{:?}", outcome.format),
+                size: outcome.size,
+                tag: outcome.tag.to_string(),
+            })
+            .map_err(|e| e.to_string())
+        }
+    }
+}
+
+// Resolves a "$<index>" reference to the doc ID created by an earlier operation
+fn resolve_doc_id(doc_id: String, previous: &[BatchOperationResult]) -> Result<String, String> {
+    let Some(reference) = doc_id.strip_prefix('$') else {
+        return Ok(doc_id);
+    };
+
+    let index: usize = reference
+        .parse()
+        .map_err(|_| format!("Invalid document reference {}", doc_id))?;
+
+    previous
+        .get(index)
+        .and_then(|op| op.result.as_ref())
+        .and_then(|result| result.get("doc_id"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| format!("{} does not refer to an earlier successful create_doc operation", doc_id))
+}
@@ -1,22 +1,31 @@
 use core::blobs::*;
-use helpers::{state::AppState, utils::get_author_id_from_headers};
+use helpers::{downloads::DownloadJob, state::AppState, utils::get_author_id_from_headers, events::NodeEvent, pagination::{ndjson_response, Paginated, PaginationParams}};
 use iroh_blobs::{
-    BlobFormat,
+    BlobFormat, Hash,
     net_protocol::DownloadMode,
     util::SetTagOption,
     rpc::client::blobs::DownloadOptions,
 };
 use gateway::access_control::check_node_id_and_domain_header;
+use crate::{request_schema, validation::{self, ValidatedJson}};
 
 use iroh::NodeAddr;
-use axum::{extract::State, Json, http::HeaderMap};
+use axum::{
+    body::Body,
+    extract::{OriginalUri, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+    Json,
+};
 use bytes::Bytes;
+use futures::TryStreamExt;
 use serde::Deserialize;
 use serde::Serialize;
 use iroh_blobs::util::Tag;
 use std::str::FromStr;
 use iroh_base::PublicKey;
 use std::path::PathBuf;
+use std::collections::HashMap;
 
 // Request bodies
 // 1. add_blob_bytes
@@ -24,6 +33,9 @@ use std::path::PathBuf;
 pub struct AddBlobBytesRequest {
     pub content: String, 
 }
+request_schema!(AddBlobBytesRequest, {
+    "content": validation::non_empty(),
+});
 
 // 2. add_blob_named
 #[derive(Deserialize)]
@@ -31,37 +43,49 @@ pub struct AddBlobNamedRequest {
     pub content: String,
     pub name: String,
 }
+request_schema!(AddBlobNamedRequest, {
+    "content": validation::non_empty(),
+    "name": validation::non_empty(),
+});
 
 // 3. add_blob_from_path
 #[derive(Deserialize)]
 pub struct AddBlobFromPathRequest {
     pub file_path: String,
 }
+request_schema!(AddBlobFromPathRequest, {
+    "file_path": validation::non_empty(),
+});
 
 // 4. list_blobs
-#[derive(Deserialize)]
-pub struct ListBlobsRequest {
-    pub page: usize,
-    pub page_size: usize,
-}
+// no request body, pagination via `PaginationParams` query
 
 // 5. get_blob
 #[derive(Deserialize)]
 pub struct GetBlobRequest {
     pub hash: String,
 }
+request_schema!(GetBlobRequest, {
+    "hash": validation::hash(),
+});
 
 // 6. status_blob
 #[derive(Deserialize)]
 pub struct StatusBlobRequest {
     pub hash: String,
 }
+request_schema!(StatusBlobRequest, {
+    "hash": validation::hash(),
+});
 
 // 7. has_blob
 #[derive(Deserialize)]
 pub struct HasBlobRequest {
     pub hash: String,
 }
+request_schema!(HasBlobRequest, {
+    "hash": validation::hash(),
+});
 
 // 8. download_blob
 #[derive(Deserialize)]
@@ -69,6 +93,10 @@ pub struct DownloadRequest {
     pub hash: String,
     pub node_id: String,
 }
+request_schema!(DownloadRequest, {
+    "hash": validation::hash(),
+    "node_id": validation::node_id(),
+});
 
 // 9. download_hash_sequence
 // same as DownloadRequest
@@ -91,6 +119,13 @@ pub struct DownloadWithOptionsRequest {
     pub nodes: Vec<String>,
     pub tag: String,
 }
+request_schema!(DownloadWithOptionsRequest, {
+    "hash": validation::hash(),
+    "format": validation::one_of(&["Raw", "HashSeq"]),
+    "mode": validation::one_of(&["Direct", "Queued"]),
+    "nodes": validation::non_empty_array(validation::node_id()),
+    "tag": validation::non_empty(),
+});
 
 // 11. list_tags
 // no request body
@@ -100,6 +135,9 @@ pub struct DownloadWithOptionsRequest {
 pub struct DeleteTagRequest {
     pub tag_name: String,
 }
+request_schema!(DeleteTagRequest, {
+    "tag_name": validation::non_empty(),
+});
 
 // 13. export_blob_to_file
 #[derive(Deserialize)]
@@ -107,6 +145,48 @@ pub struct ExportBlobRequest {
     pub hash: String,
     pub destination: String,
 }
+request_schema!(ExportBlobRequest, {
+    "hash": validation::hash(),
+    "destination": validation::non_empty(),
+});
+
+// 14. download_blob_file (query parameters)
+#[derive(Deserialize)]
+pub struct DownloadBlobFileQuery {
+    pub filename: Option<String>,
+}
+
+// 15. stream_blobs
+// No request body
+
+// 16. start_download
+/* example request body:
+{
+  "hash": "hash_as_string",
+  "format": "HashSeq",
+  "nodes": ["node_id1", "node_id2"],
+  "tag": "Auto"
+}
+*/
+#[derive(Deserialize)]
+pub struct StartDownloadRequest {
+    pub hash: String,
+    pub format: String,
+    pub nodes: Vec<String>,
+    pub tag: String,
+}
+request_schema!(StartDownloadRequest, {
+    "hash": validation::hash(),
+    "format": validation::one_of(&["Raw", "HashSeq"]),
+    "nodes": validation::non_empty_array(validation::node_id()),
+    "tag": validation::non_empty(),
+});
+
+// 17. list_downloads
+// No request body
+
+// 18. get_download
+// No request body, download ID in the path
 
 // Response bodies
 // 1. add_blob_bytes
@@ -184,11 +264,30 @@ pub struct ExportBlobResponse {
     pub message: String,
 }
 
+// 15. stream_blobs
+// NDJSON, one BlobInfoResponse per line
+
+// 16. start_download
+#[derive(Serialize)]
+pub struct StartDownloadResponse {
+    pub id: String,
+}
+
+// 17. list_downloads
+#[derive(Serialize)]
+pub struct ListDownloadsResponse {
+    /// Downloads, the most recent first.
+    pub downloads: Vec<DownloadJob>,
+}
+
+// 18. get_download
+// DownloadJob
+
 // Handler to add blob bytes
 pub async fn add_blob_bytes_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<AddBlobBytesRequest>,
+    ValidatedJson(payload): ValidatedJson<AddBlobBytesRequest>,
 ) -> Result<Json<AddBlobResponse>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -213,12 +312,18 @@ pub async fn add_blob_bytes_handler(
     let bytes = Bytes::from(payload.content);
 
     match add_blob_bytes(state.blobs.clone(), bytes).await {
-        Ok(outcome) => Ok(Json(AddBlobResponse {
-            hash: outcome.hash.to_string(),
-            format: format!("{:?}", outcome.format),
-            size: outcome.size,
-            tag: outcome.tag.to_string(),
-        })),
+        Ok(outcome) => {
+            state.events.publish(NodeEvent::BlobAdded {
+                hash: outcome.hash.to_string(),
+                size: outcome.size,
+            });
+            Ok(Json(AddBlobResponse {
+                hash: outcome.hash.to_string(),
+                format: format!("{:?}", outcome.format),
+                size: outcome.size,
+                tag: outcome.tag.to_string(),
+            }))
+        },
         Err(e) => Err((
             axum::http::StatusCode::INTERNAL_SERVER_ERROR,
             format!("Failed to add blob: {}", e),
@@ -230,7 +335,7 @@ pub async fn add_blob_bytes_handler(
 pub async fn add_blob_named_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<AddBlobNamedRequest>,
+    ValidatedJson(payload): ValidatedJson<AddBlobNamedRequest>,
 ) -> Result<Json<AddBlobResponse>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -259,12 +364,18 @@ pub async fn add_blob_named_handler(
     let tag = Tag::from(payload.name);
 
     match add_blob_named(state.blobs.clone(), bytes, tag).await {
-        Ok(outcome) => Ok(Json(AddBlobResponse {
-            hash: outcome.hash.to_string(),
-            format: format!("{:?}", outcome.format),
-            size: outcome.size,
-            tag: outcome.tag.to_string(),
-        })),
+        Ok(outcome) => {
+            state.events.publish(NodeEvent::BlobAdded {
+                hash: outcome.hash.to_string(),
+                size: outcome.size,
+            });
+            Ok(Json(AddBlobResponse {
+                hash: outcome.hash.to_string(),
+                format: format!("{:?}", outcome.format),
+                size: outcome.size,
+                tag: outcome.tag.to_string(),
+            }))
+        },
         Err(e) => Err((
             axum::http::StatusCode::INTERNAL_SERVER_ERROR,
             format!("Failed to add named blob: {}", e),
@@ -276,7 +387,7 @@ pub async fn add_blob_named_handler(
 pub async fn add_blob_from_path_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<AddBlobFromPathRequest>,
+    ValidatedJson(payload): ValidatedJson<AddBlobFromPathRequest>,
 ) -> Result<Json<AddBlobResponse>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -304,12 +415,18 @@ pub async fn add_blob_from_path_handler(
     }
 
     match add_blob_from_path(state.blobs.clone(), path).await {
-        Ok(outcome) => Ok(Json(AddBlobResponse {
-            hash: outcome.hash.to_string(),
-            format: format!("{:?}", outcome.format),
-            size: outcome.size,
-            tag: outcome.tag.to_string(),
-        })),
+        Ok(outcome) => {
+            state.events.publish(NodeEvent::BlobAdded {
+                hash: outcome.hash.to_string(),
+                size: outcome.size,
+            });
+            Ok(Json(AddBlobResponse {
+                hash: outcome.hash.to_string(),
+                format: format!("{:?}", outcome.format),
+                size: outcome.size,
+                tag: outcome.tag.to_string(),
+            }))
+        },
         Err(e) => Err((
             axum::http::StatusCode::INTERNAL_SERVER_ERROR,
             format!("Failed to add blob from path: {}", e),
@@ -321,27 +438,48 @@ pub async fn add_blob_from_path_handler(
 pub async fn list_blobs_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<ListBlobsRequest>,
-) -> Result<Json<Vec<BlobInfoResponse>>, (axum::http::StatusCode, String)> {
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<BlobInfoResponse>>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
-    // request body checks
-    if payload.page_size == 0 {
-        return Err((axum::http::StatusCode::BAD_REQUEST, "Page size must be greater than 0".to_string()));
-    }
+    // The cursor is the hash of the last blob of the previous page
+    let after = pagination.hash_cursor()?;
+    let limit = pagination.limit()?;
 
-    match list_blobs(state.blobs.clone(), payload.page, payload.page_size).await {
-        Ok(blobs) => {
-            let response = blobs
+    match list_blobs_after(state.blobs.clone(), after, limit).await {
+        Ok(page) => Ok(Json(Paginated {
+            items: page
+                .blobs
                 .into_iter()
                 .map(|blob| BlobInfoResponse {
                     path: blob.path,
                     hash: blob.hash.to_string(),
                     size: blob.size,
                 })
-                .collect();
-            Ok(Json(response))
-        }
+                .collect(),
+            total: page.total,
+            next_cursor: page.next.map(|hash| hash.to_string()),
+        })),
+        Err(e) => Err((
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to list blobs: {}", e),
+        )),
+    }
+}
+
+// Handler to stream every blob in the store as NDJSON, without pagination
+pub async fn stream_blobs_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, (axum::http::StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    match stream_blobs(state.blobs.clone()).await {
+        Ok(blobs) => Ok(ndjson_response(blobs.map_ok(|blob| BlobInfoResponse {
+            path: blob.path,
+            hash: blob.hash.to_string(),
+            size: blob.size,
+        }))),
         Err(e) => Err((
             axum::http::StatusCode::INTERNAL_SERVER_ERROR,
             format!("Failed to list blobs: {}", e),
@@ -353,7 +491,7 @@ pub async fn list_blobs_handler(
 pub async fn get_blob_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<GetBlobRequest>,
+    ValidatedJson(payload): ValidatedJson<GetBlobRequest>,
 ) -> Result<Json<GetBlobResponse>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -375,7 +513,7 @@ pub async fn get_blob_handler(
 pub async fn status_blob_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<StatusBlobRequest>,
+    ValidatedJson(payload): ValidatedJson<StatusBlobRequest>,
 ) -> Result<Json<StatusBlobResponse>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -397,7 +535,7 @@ pub async fn status_blob_handler(
 pub async fn has_blob_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<HasBlobRequest>,
+    ValidatedJson(payload): ValidatedJson<HasBlobRequest>,
 ) -> Result<Json<HasBlobResponse>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -419,7 +557,7 @@ pub async fn has_blob_handler(
 pub async fn download_blob_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<DownloadRequest>,
+    ValidatedJson(payload): ValidatedJson<DownloadRequest>,
 ) -> Result<Json<DownloadOutcomeResponse>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -449,7 +587,7 @@ pub async fn download_blob_handler(
 pub async fn download_hash_sequence_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<DownloadRequest>,
+    ValidatedJson(payload): ValidatedJson<DownloadRequest>,
 ) -> Result<Json<DownloadOutcomeResponse>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -478,7 +616,7 @@ pub async fn download_hash_sequence_handler(
 pub async fn download_with_options_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(req): Json<DownloadWithOptionsRequest>,
+    ValidatedJson(req): ValidatedJson<DownloadWithOptionsRequest>,
 ) -> Result<Json<DownloadOutcomeResponse>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -550,17 +688,72 @@ pub async fn download_with_options_handler(
     }
 }
 
+// Handler to start downloading a blob or collection from several peers at once
+// Returns as soon as the download is started, its progress is read through `GET /blobs/downloads/:id`.
+pub async fn start_download_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(req): ValidatedJson<StartDownloadRequest>,
+) -> Result<Json<StartDownloadResponse>, (axum::http::StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let format = match req.format.as_str() {
+        "Raw" => BlobFormat::Raw,
+        "HashSeq" => BlobFormat::HashSeq,
+        _ => return Err((axum::http::StatusCode::BAD_REQUEST, format!("Invalid format: {}", req.format))),
+    };
+
+    let tag = if req.tag == "Auto" {
+        SetTagOption::Auto
+    } else {
+        SetTagOption::Named(Tag(Bytes::from(req.tag.clone())))
+    };
+
+    match start_download(state.blobs.clone(), state.downloads.clone(), req.hash, format, req.nodes, tag) {
+        Ok(id) => Ok(Json(StartDownloadResponse { id })),
+        Err(e @ (BlobError::InvalidBlobHashFormat | BlobError::InvalidNodeIdFormat | BlobError::NoDownloadPeers)) => {
+            Err((axum::http::StatusCode::BAD_REQUEST, e.to_string()))
+        }
+        Err(e) => Err((axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler to list the downloads started through `POST /blobs/downloads`
+pub async fn list_downloads_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ListDownloadsResponse>, (axum::http::StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    Ok(Json(ListDownloadsResponse { downloads: state.downloads.list() }))
+}
+
+// Handler to get the merged progress of a download
+pub async fn get_download_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<DownloadJob>, (axum::http::StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    state
+        .downloads
+        .get(&id)
+        .map(Json)
+        .ok_or((axum::http::StatusCode::NOT_FOUND, format!("Download {} not found", id)))
+}
+
 // Handler to list tags
 pub async fn list_tags_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-) -> Result<Json<Vec<TagInfoResponse>>, (axum::http::StatusCode, String)> {
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<TagInfoResponse>>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
     match list_tags(state.blobs.clone()).await {
         Ok(tags) => {
-            let response = tags
-                .into_iter()
+            let response = Paginated::from_items(tags, &pagination)?
                 .map(|tag_info| TagInfoResponse {
                     name: tag_info.name.to_string(),
                     format: match tag_info.format {
@@ -568,8 +761,7 @@ pub async fn list_tags_handler(
                         BlobFormat::HashSeq => "HashSeq".to_string(),
                     },
                     hash: tag_info.hash.to_string(),
-                })
-                .collect();
+                });
 
             Ok(Json(response))
         }
@@ -581,7 +773,7 @@ pub async fn list_tags_handler(
 pub async fn delete_tag_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(req): Json<DeleteTagRequest>,
+    ValidatedJson(req): ValidatedJson<DeleteTagRequest>,
 ) -> Result<Json<DeleteTagResponse>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -598,15 +790,21 @@ pub async fn delete_tag_handler(
         ));
     }
 
+    // With an approval policy, tags are only deleted through the approvals API
+    state.approvals.check_direct().map_err(|e| (axum::http::StatusCode::FORBIDDEN, e))?;
+
     // request body checks
     if req.tag_name.is_empty() {
         return Err((axum::http::StatusCode::BAD_REQUEST, "Tag name cannot be empty".to_string()));
     }
 
     match delete_tag(state.blobs.clone(), req.tag_name.clone()).await {
-        Ok(_) => Ok(Json(DeleteTagResponse {
-            message: "Tag deleted successfully".to_string(),
-        })),
+        Ok(_) => {
+            state.events.publish(NodeEvent::TagDeleted { tag: req.tag_name });
+            Ok(Json(DeleteTagResponse {
+                message: "Tag deleted successfully".to_string(),
+            }))
+        },
         Err(e) => Err((axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
 }
@@ -615,7 +813,7 @@ pub async fn delete_tag_handler(
 pub async fn export_blob_to_file_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(req): Json<ExportBlobRequest>,
+    ValidatedJson(req): ValidatedJson<ExportBlobRequest>,
 ) -> Result<Json<ExportBlobResponse>, (axum::http::StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -637,4 +835,392 @@ pub async fn export_blob_to_file_handler(
         })),
         Err(e) => Err((axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
-}
\ No newline at end of file
+}
+
+// REST handlers
+// These map resource-style routes onto the same core functions as the endpoints above.
+
+// Handler to get a blob via `GET /blobs/:hash`
+pub async fn get_blob_rest_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(hash): Path<String>,
+) -> Result<Json<GetBlobResponse>, (axum::http::StatusCode, String)> {
+    get_blob_handler(State(state), headers, ValidatedJson(GetBlobRequest { hash })).await
+}
+
+// Handler to download a blob as a file via `GET /blobs/:hash/download`
+// The body is streamed from the store with Content-Disposition set, so browsers save it as `filename`.
+// Blobs are content-addressed, so the hash doubles as a strong ETag and responses can be cached forever.
+pub async fn download_blob_file_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(hash): Path<String>,
+    Query(query): Query<DownloadBlobFileQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let (hash, reader) = match read_blob_stream(state.blobs.clone(), hash).await {
+        Ok(blob) => blob,
+        Err(BlobError::InvalidBlobHashFormat) => {
+            return Err((StatusCode::BAD_REQUEST, "Invalid hash format".to_string()))
+        },
+        Err(BlobError::BlobNotComplete) => {
+            return Err((StatusCode::NOT_FOUND, "Blob not found".to_string()))
+        },
+        Err(e) => {
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read blob: {}", e)))
+        },
+    };
+
+    let hex_hash = hash.to_hex();
+    let etag = format!("\"{}\"", hex_hash);
+    if is_not_modified(&headers, &etag) {
+        return not_modified_response(&etag);
+    }
+
+    let filename = query
+        .filename
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| hex_hash.clone());
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_LENGTH, reader.size())
+        .header(header::CONTENT_DISPOSITION, content_disposition(&filename))
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, IMMUTABLE)
+        .body(Body::from_stream(reader))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// Handler to serve a blob's raw bytes via `GET /blobs/:hash/raw`
+// The body is streamed from the store reader as is, without the UTF-8/base64 and JSON encoding of
+// `GET /blobs/:hash`, and a single `Range` is served with `206 Partial Content`.
+pub async fn raw_blob_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(hash): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let (hash, size) = match complete_blob_size(state.blobs.clone(), hash).await {
+        Ok(blob) => blob,
+        Err(BlobError::InvalidBlobHashFormat) => {
+            return Err((StatusCode::BAD_REQUEST, "Invalid hash format".to_string()))
+        },
+        Err(BlobError::BlobNotComplete) => {
+            return Err((StatusCode::NOT_FOUND, "Blob not found".to_string()))
+        },
+        Err(e) => {
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read blob: {}", e)))
+        },
+    };
+
+    blob_range_response(&state, &headers, hash, size, "application/octet-stream").await
+}
+
+// Serves a complete blob of `size` bytes as `content_type`, or the single `Range` asked for with
+// `206 Partial Content`, streamed from the store
+pub(crate) async fn blob_range_response(
+    state: &AppState,
+    headers: &HeaderMap,
+    hash: Hash,
+    size: u64,
+    content_type: &str,
+) -> Result<Response, (StatusCode, String)> {
+    let etag = format!("\"{}\"", hash.to_hex());
+    if is_not_modified(headers, &etag) {
+        return not_modified_response(&etag);
+    }
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| parse_range(value, size));
+    let (status, offset, len) = match range {
+        None | Some(Ok(None)) => (StatusCode::OK, 0, size),
+        Some(Ok(Some((start, end)))) => (StatusCode::PARTIAL_CONTENT, start, end - start + 1),
+        Some(Err(())) => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", size))
+                .body(Body::empty())
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+        },
+    };
+
+    let body = if len == 0 {
+        Body::empty()
+    } else {
+        let reader = read_blob_range(state.blobs.clone(), hash, offset, len)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read blob: {}", e)))?;
+        Body::from_stream(reader)
+    };
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, len)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, IMMUTABLE);
+    if status == StatusCode::PARTIAL_CONTENT {
+        response = response.header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", offset, offset + len - 1, size));
+    }
+    response
+        .body(body)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+
+// Handler to serve a blob or a member of a collection by hash via `GET /gw/:hash[/path]`, the way
+// content-addressed gateways do, so links to registry artifacts can be embedded in web pages.
+// The content type is guessed from the member's name or the first bytes of the blob, and content
+// is sandboxed so that pages served from here cannot script the API.
+pub async fn gateway_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    OriginalUri(uri): OriginalUri,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<Response, (StatusCode, String)> {
+    if !state.public_gw {
+        check_node_id_and_domain_header(&headers)?;
+    }
+    let hash = params.get("hash").cloned().unwrap_or_default();
+    let path = params.get("path").map(String::as_str).unwrap_or_default();
+
+    let target = match resolve_gateway_path(state.blobs.clone(), hash.clone(), path).await {
+        Ok(target) => target,
+        Err(BlobError::InvalidBlobHashFormat) => {
+            return Err((StatusCode::BAD_REQUEST, "Invalid hash format".to_string()))
+        },
+        Err(BlobError::BlobNotComplete) | Err(BlobError::PathNotInCollection) => {
+            return Err((StatusCode::NOT_FOUND, "Not found".to_string()))
+        },
+        Err(e) => {
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read blob: {}", e)))
+        },
+    };
+
+    // Directories and their index.html are served under a trailing slash, so relative links
+    // in them resolve within the collection
+    let is_directory_index = match &target {
+        GatewayTarget::Directory(_) => true,
+        GatewayTarget::Blob { name: Some(name), .. } => name.as_str() != path.trim_matches('/'),
+        GatewayTarget::Blob { name: None, .. } => false,
+    };
+    if is_directory_index && !uri.path().ends_with('/') {
+        return Response::builder()
+            .status(StatusCode::MOVED_PERMANENTLY)
+            .header(header::LOCATION, format!("{}/", uri.path()))
+            .body(Body::empty())
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+    }
+
+    let mut response = match target {
+        GatewayTarget::Blob { hash, size, name } => {
+            let content_type = match name.as_deref().and_then(content_type_of_name) {
+                Some(content_type) => content_type,
+                None => sniff_content_type(&state, hash, size).await,
+            };
+            blob_range_response(&state, &headers, hash, size, content_type).await?
+        },
+        GatewayTarget::Directory(members) => {
+            let base = uri.path();
+            let mut names: Vec<String> = members
+                .into_iter()
+                .map(|(name, _)| match name.split_once('/') {
+                    Some((dir, _)) => format!("{}/", dir),
+                    None => name,
+                })
+                .collect();
+            names.dedup();
+            let items: String = names
+                .iter()
+                .map(|name| format!("<li><a href=\"{}{}\">{}</a></li>", html_escape(base), html_escape(name), html_escape(name)))
+                .collect();
+            let page = format!(
+                "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title></head><body><h1>{}</h1><ul>{}</ul></body></html>",
+                html_escape(base),
+                html_escape(base),
+                items,
+            );
+            Response::builder()
+                .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+                .header(header::CACHE_CONTROL, IMMUTABLE)
+                .body(Body::from(page))
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        },
+    };
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CONTENT_SECURITY_POLICY, HeaderValue::from_static("sandbox"));
+    response_headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    Ok(response)
+}
+
+// The content type of a file named `name`, by its extension
+pub(crate) fn content_type_of_name(name: &str) -> Option<&'static str> {
+    let (_, extension) = name.rsplit_once('.')?;
+    let content_type = match extension.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "jsonld" => "application/ld+json",
+        "txt" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "wasm" => "application/wasm",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+// The content type of a blob without a name, by its first bytes: the common binary formats, and
+// text for anything else that is UTF-8
+async fn sniff_content_type(state: &AppState, hash: Hash, size: u64) -> &'static str {
+    const SNIFF_LEN: u64 = 512;
+    if size == 0 {
+        return "application/octet-stream";
+    }
+    let Ok(mut reader) = read_blob_range(state.blobs.clone(), hash, 0, size.min(SNIFF_LEN)).await else {
+        return "application/octet-stream";
+    };
+    let Ok(head) = reader.read_to_bytes().await else {
+        return "application/octet-stream";
+    };
+    let signatures: [(&[u8], &str); 8] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"\0asm", "application/wasm"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"ID3", "audio/mpeg"),
+    ];
+    if let Some((_, content_type)) = signatures.iter().find(|(signature, _)| head.starts_with(signature)) {
+        return content_type;
+    }
+    if head.len() >= 12 && head.starts_with(b"RIFF") && &head[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+    // a multi-byte character may be cut off at the end of the bytes read
+    match std::str::from_utf8(&head) {
+        Ok(_) => "text/plain; charset=utf-8",
+        Err(e) if e.error_len().is_none() && size > SNIFF_LEN => "text/plain; charset=utf-8",
+        Err(_) => "application/octet-stream",
+    }
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// Blobs are content-addressed, so their responses can be cached forever
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+// Whether the client already has the blob tagged `etag`
+pub(crate) fn is_not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/") == etag
+        }))
+}
+
+fn not_modified_response(etag: &str) -> Result<Response, (StatusCode, String)> {
+    Response::builder()
+        .status(StatusCode::NOT_MODIFIED)
+        .header(header::ETAG, etag)
+        .header(header::CACHE_CONTROL, IMMUTABLE)
+        .body(Body::empty())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// Parses a `Range` header into the first and last byte to send of a blob of `size` bytes.
+// `None` for ranges that are ignored and answered with the whole blob (other units, several
+// ranges, malformed values), an error for a range outside of the blob.
+pub(crate) fn parse_range(value: &str, size: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else { return Ok(None) };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else { return Ok(None) };
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", "") => return Ok(None),
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return Err(()),
+            Ok(suffix) => (size.saturating_sub(suffix), size.saturating_sub(1)),
+            Err(_) => return Ok(None),
+        },
+        (start, end) => {
+            let Ok(start) = start.parse::<u64>() else { return Ok(None) };
+            let end = match end {
+                "" => size.saturating_sub(1),
+                end => match end.parse::<u64>() {
+                    Ok(end) if end >= start => end.min(size.saturating_sub(1)),
+                    _ => return Ok(None),
+                },
+            };
+            (start, end)
+        },
+    };
+    if size == 0 || start >= size {
+        return Err(());
+    }
+    Ok(Some((start, end)))
+}
+
+// Builds an `attachment` Content-Disposition value with an ASCII fallback name and an
+// RFC 5987 `filename*` for names that are not plain ASCII
+fn content_disposition(filename: &str) -> String {
+    // Drop any client-supplied path so only the final component is used
+    let filename = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+
+    let fallback: String = filename
+        .chars()
+        .map(|c| if (c.is_ascii_graphic() || c == ' ') && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+
+    let encoded: String = filename
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'!' | b'#' | b'$' | b'&' | b'+' | b'-' | b'.'
+            | b'^' | b'_' | b'`' | b'|' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
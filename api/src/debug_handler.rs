@@ -0,0 +1,138 @@
+use helpers::{limits::{OperationCounts, OperationKind}, state::AppState};
+
+use serde::Serialize;
+use axum::{extract::State, Json};
+use futures::StreamExt;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+// These handlers are only routed on the `--admin-listen` address, which is not exposed to clients
+
+// How long a probe waits for the docs actor or the blob store before reporting a stall
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Response bodies
+// 1. runtime
+#[derive(Serialize)]
+pub struct WorkerStats {
+    /// Time the worker spent running tasks since the node started.
+    pub busy_ms: u64,
+    /// Times the worker parked for lack of work. A count that stops increasing under load
+    /// points to a task blocking the worker.
+    pub parks: u64,
+}
+
+#[derive(Serialize)]
+pub struct RuntimeResponse {
+    pub workers: usize,
+    pub alive_tasks: usize,
+    /// Tasks waiting in the runtime's global queue for a free worker.
+    pub global_queue_depth: usize,
+    pub worker_stats: Vec<WorkerStats>,
+    /// Expensive operations running and waiting, by kind.
+    pub operations: BTreeMap<&'static str, OperationCounts>,
+    /// Downloads started with `POST /blobs/downloads` that are still running.
+    pub downloads_running: usize,
+}
+
+// 2. memory
+#[derive(Serialize)]
+pub struct MemoryResponse {
+    /// Resident memory, from `/proc/self/status`. `None` where it is not available.
+    pub resident_bytes: Option<u64>,
+    /// Highest resident memory since the process started.
+    pub peak_resident_bytes: Option<u64>,
+    pub virtual_bytes: Option<u64>,
+    pub threads: Option<u64>,
+    pub blob_cache_blobs: usize,
+    pub blob_cache_bytes: usize,
+    pub open_doc_handles: usize,
+}
+
+// 3. probe
+#[derive(Serialize)]
+pub struct ProbeResult {
+    /// Round trip in milliseconds, `None` if it failed or timed out.
+    pub latency_ms: Option<f64>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ProbeResponse {
+    /// A request to the docs actor, which serves every document and author operation.
+    pub docs_actor: ProbeResult,
+    /// A listing of the blob store.
+    pub blob_store: ProbeResult,
+}
+
+// Handler for the state of the async runtime
+pub async fn runtime_handler(State(state): State<AppState>) -> Json<RuntimeResponse> {
+    let metrics = tokio::runtime::Handle::current().metrics();
+    let worker_stats = (0..metrics.num_workers())
+        .map(|worker| WorkerStats {
+            busy_ms: metrics.worker_total_busy_duration(worker).as_millis() as u64,
+            parks: metrics.worker_park_count(worker),
+        })
+        .collect();
+
+    Json(RuntimeResponse {
+        workers: metrics.num_workers(),
+        alive_tasks: metrics.num_alive_tasks(),
+        global_queue_depth: metrics.global_queue_depth(),
+        worker_stats,
+        operations: OperationKind::ALL.into_iter().map(|kind| (kind.as_str(), state.limits.counts(kind))).collect(),
+        downloads_running: state.downloads.list().iter().filter(|job| job.finished_at.is_none()).count(),
+    })
+}
+
+// Handler for the memory used by the process and its caches
+pub async fn memory_handler(State(state): State<AppState>) -> Json<MemoryResponse> {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+    // Lines such as `VmRSS:     52344 kB`
+    let field = |name: &str| -> Option<u64> {
+        let line = status.lines().find(|line| line.starts_with(name))?;
+        let mut parts = line[name.len()..].split_whitespace();
+        let value: u64 = parts.next()?.parse().ok()?;
+        Some(match parts.next() {
+            Some("kB") => value * 1024,
+            _ => value,
+        })
+    };
+    let cache = state.blobs.cache.stats();
+
+    Json(MemoryResponse {
+        resident_bytes: field("VmRSS:"),
+        peak_resident_bytes: field("VmHWM:"),
+        virtual_bytes: field("VmSize:"),
+        threads: field("Threads:"),
+        blob_cache_blobs: cache.blobs,
+        blob_cache_bytes: cache.bytes,
+        open_doc_handles: state.docs.handles.open_handles(),
+    })
+}
+
+// Handler for timing a round trip to the docs actor and the blob store
+pub async fn probe_handler(State(state): State<AppState>) -> Json<ProbeResponse> {
+    let docs_actor = probe(async {
+        state.docs.client().authors().default().await?;
+        anyhow::Ok(())
+    });
+    let blob_store = probe(async {
+        let mut tags = state.blobs.client().tags().list().await?;
+        if let Some(tag) = tags.next().await {
+            tag?;
+        }
+        anyhow::Ok(())
+    });
+    let (docs_actor, blob_store) = futures::join!(docs_actor, blob_store);
+    Json(ProbeResponse { docs_actor, blob_store })
+}
+
+async fn probe(request: impl std::future::Future<Output = anyhow::Result<()>>) -> ProbeResult {
+    let started = Instant::now();
+    match tokio::time::timeout(PROBE_TIMEOUT, request).await {
+        Ok(Ok(())) => ProbeResult { latency_ms: Some(started.elapsed().as_secs_f64() * 1000.0), error: None },
+        Ok(Err(e)) => ProbeResult { latency_ms: None, error: Some(e.to_string()) },
+        Err(_) => ProbeResult { latency_ms: None, error: Some(format!("no answer within {}s", PROBE_TIMEOUT.as_secs())) },
+    }
+}
@@ -0,0 +1,92 @@
+use helpers::{state::AppState, utils::SS58AuthorId};
+use keystore::did::{parse_did_key, DidDocument};
+use keystore::keystore::{KeyScheme, StarterkitKeystore};
+
+use core::authors::list_authors;
+use axum::{extract::{Query, State}, Json, http::StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use iroh::PublicKey;
+use iroh_docs::AuthorId;
+
+// Request bodies
+// 1. resolve did
+#[derive(Deserialize)]
+pub struct ResolveDidQuery {
+    pub did: String,
+}
+
+// Response bodies
+// 1. resolve did
+#[derive(Serialize)]
+pub struct ResolveDidResponse {
+    pub did_document: DidDocument,
+    /// What the DID is to this node: `node`, `peer` and/or `author`.
+    pub known_as: Vec<&'static str>,
+}
+
+// Handler for the node's DID document: its keystore keys and how to reach it over iroh
+pub async fn did_document_handler(
+    State(state): State<AppState>,
+) -> Result<Json<DidDocument>, (StatusCode, String)> {
+    node_document(&state).await.map(Json)
+}
+
+// Handler for resolving the DID of the node, a peer in the gateway allowlist or a local author
+pub async fn resolve_did_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ResolveDidQuery>,
+) -> Result<Json<ResolveDidResponse>, (StatusCode, String)> {
+    let (scheme, public) = parse_did_key(&query.did).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let node = node_document(&state).await?;
+    if node.id == query.did {
+        return Ok(Json(ResolveDidResponse { did_document: node, known_as: vec!["node"] }));
+    }
+
+    let mut did_document = DidDocument::for_did_key(&query.did).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let mut known_as = Vec::new();
+
+    // node IDs and author IDs are both ed25519 keys
+    if let (KeyScheme::Ed25519, Ok(bytes)) = (scheme, <[u8; 32]>::try_from(public.as_slice())) {
+        if let Ok(node_id) = PublicKey::from_bytes(&bytes) {
+            if gateway::access_control::is_node_id_allowed(&node_id.to_string()) {
+                did_document.add_service("iroh", "IrohNode", json!({ "nodeId": node_id.to_string() }));
+                known_as.push("peer");
+            }
+        }
+
+        let author_id = SS58AuthorId::from_author_id(&AuthorId::from(&bytes))
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        let authors = list_authors(state.docs.clone())
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if authors.iter().any(|author| author == author_id.as_ss58()) {
+            known_as.push("author");
+        }
+    }
+
+    if known_as.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "DID is not the node, a known peer or a local author".to_string()));
+    }
+    Ok(Json(ResolveDidResponse { did_document, known_as }))
+}
+
+async fn node_document(state: &AppState) -> Result<DidDocument, (StatusCode, String)> {
+    let keystore = StarterkitKeystore::from_inner(state.cord_signer.keystore.clone());
+    let mut document = DidDocument::for_node(&keystore, &state.key_types.list())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let node_addr = state
+        .blobs
+        .endpoint()
+        .node_addr()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    document.add_service("iroh", "IrohNode", json!({
+        "nodeId": node_addr.node_id.to_string(),
+        "relayUrl": node_addr.relay_url.map(|url| url.to_string()),
+        "directAddresses": node_addr.direct_addresses.iter().map(|addr| addr.to_string()).collect::<Vec<_>>(),
+    }));
+    Ok(document)
+}
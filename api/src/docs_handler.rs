@@ -1,11 +1,26 @@
 use core::docs::*;
-use helpers::{state::AppState, utils::get_author_id_from_headers};
+use core::filter::Filter;
+use core::trash::{empty_trash, list_trash, restore_entry, TrashedEntry};
+use core::history::get_entries_at;
+use core::inclusion::{entry_inclusion_proof, verify_inclusion_proof, EntryInclusionProof, InclusionVerification};
+use core::archives::{export_doc_archive, import_doc_archive, ArchiveError, DocArchiveImport};
+use core::tables::{doc_table, TableError, TableFormat};
+use core::verification::{verify_replica, ReplicaReport, VerificationError};
+use core::pinning::{apply_content_policy, get_content_policy, set_content_policy, ContentMode, ContentPolicy, PinningError};
+use helpers::{state::AppState, utils::{decode_doc_id, get_author_id_from_headers, EntryResource}, events::NodeEvent, pagination::{ndjson_response, Paginated, PaginationParams}, sync_controls::{SyncControlStatus, SyncWindow}, replication::SyncMetrics, entry_index::{DocStats, IndexedEntry}, search::{SearchError, SearchHit}, tenants::{retain_tenant_docs, Tenant, TenantError}};
 use gateway::access_control::check_node_id_and_domain_header;
+use crate::{keystore_handler::record_signature, request_schema, validation::{self, ValidatedJson}};
+use keystore::{keystore::{decode_public, StarterkitKeystore, SIGNING_KEY_TYPE}, registry::{builtin_key_type, KeyPurpose}};
+use cord::registry::ChainDefinitionKind;
 
 use serde::{Deserialize, Serialize};
-use axum::{extract::State, Json};
-use axum::http::{StatusCode, HeaderMap};
-use std::str::FromStr;
+use axum::{body::Body, extract::{Path, Query, State}, Extension, Json};
+use axum::http::{header, StatusCode, HeaderMap, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::{Stream, StreamExt, TryStreamExt};
+use tokio_util::io::{ReaderStream, StreamReader};
+use std::{collections::HashSet, str::FromStr};
 use iroh_docs::{NamespaceId, CapabilityKind};
 use iroh_docs::rpc::client::docs::ShareMode;
 use iroh_docs::rpc::AddrInfoOptions;
@@ -16,12 +31,22 @@ use iroh_docs::rpc::AddrInfoOptions;
 pub struct GetDocumentRequest {
     pub doc_id: String,
 }
+request_schema!(GetDocumentRequest, {
+    "doc_id": validation::doc_id(),
+});
 
 // 2. get blob entry
 #[derive(Deserialize)]
 pub struct GetEntryBlobRequest {
     pub hash: String,
+    /// A document with an entry of this content. Required with an API key, as the blob
+    /// store is shared by all tenants.
+    #[serde(default)]
+    pub doc_id: Option<String>,
 }
+request_schema!(GetEntryBlobRequest, {
+    "hash": validation::hash(),
+});
 
 // 3. create document
 // No request body
@@ -34,6 +59,9 @@ pub struct GetEntryBlobRequest {
 pub struct DropDocRequest {
     pub doc_id: String,
 }
+request_schema!(DropDocRequest, {
+    "doc_id": validation::doc_id(),
+});
 
 // 6. share doc
 #[derive(Deserialize)]
@@ -42,18 +70,32 @@ pub struct ShareDocRequest {
     pub mode: String,
     pub addr_options: String,
 }
+request_schema!(ShareDocRequest, {
+    "doc_id": validation::doc_id(),
+    "mode": validation::non_empty(),
+    "addr_options": validation::non_empty(),
+});
 
 // 7. join doc
 #[derive(Deserialize)]
 pub struct JoinDocRequest {
     pub ticket: String,
+    /// Only download the content of entries under these key prefixes, see `set_sync_prefixes`.
+    #[serde(default)]
+    pub sync_prefixes: Vec<String>,
 }
+request_schema!(JoinDocRequest, {
+    "ticket": validation::non_empty(),
+});
 
 // 8. close document
 #[derive(Deserialize)]
 pub struct CloseDocRequest {
     pub doc_id: String,
 }
+request_schema!(CloseDocRequest, {
+    "doc_id": validation::doc_id(),
+});
 
 // 9. add document schema
 #[derive(Deserialize)]
@@ -62,6 +104,11 @@ pub struct AddDocSchemaRequest {
     pub doc_id: String,
     pub schema: String, // Should be a valid JSON string
 }
+request_schema!(AddDocSchemaRequest, {
+    "author_id": validation::ss58(),
+    "doc_id": validation::doc_id(),
+    "schema": validation::non_empty(),
+});
 
 // 10. set entry
 #[derive(Debug, Deserialize)]
@@ -70,8 +117,30 @@ pub struct SetEntryRequest {
     pub author_id: String,
     pub key: String,
     pub value: String,
+    /// Also store a detached signature of the value, made with the key chosen by `sign_with`.
+    #[serde(default)]
+    pub sign: bool,
+    #[serde(default)]
+    pub sign_with: SignWith,
 }
 
+/// The key that signs the value of a signed entry.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignWith {
+    /// The node's document signing key (sr25519), the first key of the `sign` key type.
+    #[default]
+    Keystore,
+    /// The key of the entry's author (ed25519).
+    Author,
+}
+request_schema!(SetEntryRequest, {
+    "doc_id": validation::doc_id(),
+    "author_id": validation::ss58(),
+    "key": validation::key(),
+    "value": validation::non_empty(),
+});
+
 // 11. set entry file
 #[derive(Debug, Deserialize)]
 pub struct SetEntryFileRequest {
@@ -80,6 +149,12 @@ pub struct SetEntryFileRequest {
     pub key: String,
     pub file_path: String,
 }
+request_schema!(SetEntryFileRequest, {
+    "doc_id": validation::doc_id(),
+    "author_id": validation::ss58(),
+    "key": validation::key(),
+    "file_path": validation::non_empty(),
+});
 
 // 12. get entry
 #[derive(Debug, Deserialize)]
@@ -88,14 +163,39 @@ pub struct GetEntryRequest {
     pub author_id: String,
     pub key: String,
     pub include_empty: bool,
+    /// Include the latest anchor that covers the entry.
+    #[serde(default)]
+    pub include_anchor: bool,
 }
+request_schema!(GetEntryRequest, {
+    "doc_id": validation::doc_id(),
+    "author_id": validation::ss58(),
+    "key": validation::key(),
+    "include_empty": validation::boolean(),
+});
 
 // 13. get entries
 #[derive(Deserialize)]
 pub struct GetEntriesRequest {
     pub doc_id: String,
     pub query_params: String, // JSON string from user
+    /// Include the latest anchor that covers each entry.
+    #[serde(default)]
+    pub include_anchor: bool,
+    /// Return one entry per key, picked by this conflict resolution, see `CONFLICT_RESOLUTION_NAMES`.
+    #[serde(default)]
+    pub resolution: Option<String>,
+    /// Author order of the `author_priority` resolution.
+    #[serde(default)]
+    pub author_priority: Vec<String>,
+    /// Only return the entries matching this expression, see `core::filter`.
+    #[serde(default)]
+    pub filter: Option<String>,
 }
+request_schema!(GetEntriesRequest, {
+    "doc_id": validation::doc_id(),
+    "query_params": validation::non_empty(),
+});
 
 // 14. delete entry
 #[derive(Deserialize)]
@@ -104,18 +204,29 @@ pub struct DeleteEntryRequest {
     pub author_id: String,
     pub key: String,
 }
+request_schema!(DeleteEntryRequest, {
+    "doc_id": validation::doc_id(),
+    "author_id": validation::ss58(),
+    "key": validation::key(),
+});
 
 // 15. leave document
 #[derive(Deserialize)]
 pub struct LeaveRequest {
     pub doc_id: String,
 }
+request_schema!(LeaveRequest, {
+    "doc_id": validation::doc_id(),
+});
 
 // 16. status
 #[derive(Deserialize)]
 pub struct StatusRequest {
     pub doc_id: String,
 }
+request_schema!(StatusRequest, {
+    "doc_id": validation::doc_id(),
+});
 
 // 17. set download policy
 #[derive(Deserialize)]
@@ -123,12 +234,429 @@ pub struct SetDownloadPolicyRequest {
     pub doc_id: String,
     pub download_policy: String, // JSON as string input
 }
+request_schema!(SetDownloadPolicyRequest, {
+    "doc_id": validation::doc_id(),
+    "download_policy": validation::non_empty(),
+});
 
 // 18. get download policy
 #[derive(Deserialize)]
 pub struct GetDownloadPolicyRequest {
     pub doc_id: String,
 }
+request_schema!(GetDownloadPolicyRequest, {
+    "doc_id": validation::doc_id(),
+});
+
+// 19. get entry (REST)
+// doc_id and key are taken from the path, the rest from the query string
+#[derive(Debug, Deserialize)]
+pub struct GetEntryQuery {
+    pub author_id: String,
+    #[serde(default)]
+    pub include_empty: bool,
+    #[serde(default)]
+    pub include_anchor: bool,
+}
+
+// 20. anchor doc
+#[derive(Debug, Deserialize)]
+pub struct AnchorDocRequest {
+    pub doc_id: String,
+    /// Anchor only the entries at these keys. Omit to anchor the whole document.
+    pub keys: Option<Vec<String>>,
+    /// `merkle` to anchor a Merkle root, which entries can be proven against one by one.
+    #[serde(default)]
+    pub digest_kind: DocDigestKind,
+}
+request_schema!(AnchorDocRequest, {
+    "doc_id": validation::doc_id(),
+});
+
+// 21. list doc anchors
+#[derive(Debug, Deserialize)]
+pub struct ListDocAnchorsQuery {
+    pub doc_id: String,
+}
+
+// 22. issue credential
+#[derive(Debug, Deserialize)]
+pub struct IssueCredentialRequest {
+    pub doc_id: String,
+    /// Author of the entry, whose schema it is checked against.
+    pub author_id: String,
+    pub key: String,
+    /// Name of an ed25519 or sr25519 key type, e.g. `skit` or `sign`.
+    pub key_type: String,
+    /// SS58 or 0x-prefixed hex public key. Omit to use the node's key of `key_type`.
+    pub public: Option<String>,
+    /// DID or URI of the credential subject.
+    pub holder: Option<String>,
+}
+request_schema!(IssueCredentialRequest, {
+    "doc_id": validation::doc_id(),
+    "author_id": validation::ss58(),
+    "key": validation::key(),
+    "key_type": validation::non_empty(),
+});
+
+// 23. sync cord definitions
+#[derive(Debug, Deserialize)]
+pub struct SyncCordDefinitionsRequest {
+    pub doc_id: String,
+    /// The definitions to mirror. Omit to refresh those already mirrored in the document.
+    pub definitions: Option<Vec<CordDefinitionRef>>,
+}
+request_schema!(SyncCordDefinitionsRequest, {
+    "doc_id": validation::doc_id(),
+});
+
+#[derive(Debug, Deserialize)]
+pub struct CordDefinitionRef {
+    /// `space`, `schema` or `registry`.
+    pub kind: String,
+    /// The CORD identifier, e.g. the space ID.
+    pub id: String,
+}
+
+// 24. push schema to cord
+#[derive(Debug, Deserialize)]
+pub struct PushSchemaToCordRequest {
+    pub doc_id: String,
+    /// Identifier of the space authorization the schema is created under.
+    pub authorization: String,
+}
+request_schema!(PushSchemaToCordRequest, {
+    "doc_id": validation::doc_id(),
+    "authorization": validation::non_empty(),
+});
+
+// 25. revoke statement
+#[derive(Debug, Deserialize)]
+pub struct RevokeStatementRequest {
+    pub doc_id: String,
+    /// `{"type": "anchor", "extrinsic_hash"}` or `{"type": "credential", "id"}`.
+    pub statement: RevokedStatement,
+    pub reason: Option<String>,
+}
+request_schema!(RevokeStatementRequest, {
+    "doc_id": validation::doc_id(),
+    "statement": serde_json::json!({ "type": "object" }),
+});
+
+// 26. statement status
+#[derive(Debug, Deserialize)]
+pub struct StatementStatusQuery {
+    pub doc_id: String,
+    /// Extrinsic hash of an anchor, or ID of a credential.
+    pub statement_id: String,
+}
+
+// 27. import cord schema
+#[derive(Debug, Deserialize)]
+pub struct ImportCordSchemaRequest {
+    pub doc_id: String,
+    pub author_id: String,
+    /// The CORD schema, or its schema details, as JSON or as a JSON string.
+    pub schema: Option<serde_json::Value>,
+    /// Identifier of a schema registered on CORD, read from the chain instead of `schema`.
+    pub schema_id: Option<String>,
+}
+request_schema!(ImportCordSchemaRequest, {
+    "doc_id": validation::doc_id(),
+    "author_id": validation::non_empty(),
+});
+
+// 28. export cord schema
+#[derive(Debug, Deserialize)]
+pub struct ExportCordSchemaQuery {
+    pub doc_id: String,
+    pub author_id: String,
+    /// Title of the CORD schema if the schema has none. Defaults to the document ID.
+    pub title: Option<String>,
+}
+
+// 29. set sync prefixes
+#[derive(Deserialize)]
+pub struct SetSyncPrefixesRequest {
+    pub doc_id: String,
+    /// Key prefixes to download content for, e.g. `public/`. Empty to download all content.
+    pub prefixes: Vec<String>,
+}
+request_schema!(SetSyncPrefixesRequest, {
+    "doc_id": validation::doc_id(),
+    "prefixes": serde_json::json!({ "type": "array", "items": validation::non_empty() }),
+});
+
+// 30. get sync prefixes
+#[derive(Deserialize)]
+pub struct SyncPrefixesQuery {
+    pub doc_id: String,
+}
+
+// 31. pause sync
+#[derive(Deserialize)]
+pub struct PauseSyncRequest {
+    pub doc_id: String,
+}
+request_schema!(PauseSyncRequest, {
+    "doc_id": validation::doc_id(),
+});
+
+// 32. resume sync
+#[derive(Deserialize)]
+pub struct ResumeSyncRequest {
+    pub doc_id: String,
+}
+request_schema!(ResumeSyncRequest, {
+    "doc_id": validation::doc_id(),
+});
+
+// 33. set sync controls
+#[derive(Deserialize)]
+pub struct SetSyncControlsRequest {
+    pub doc_id: String,
+    /// UTC `HH:MM` ranges to sync in. Empty to sync at any time.
+    pub windows: Vec<SyncWindow>,
+    /// Content bytes a document may download per minute. Omit for no limit.
+    #[serde(default)]
+    pub max_bytes_per_minute: Option<u64>,
+}
+request_schema!(SetSyncControlsRequest, {
+    "doc_id": validation::doc_id(),
+    "windows": serde_json::json!({
+        "type": "array",
+        "items": {
+            "type": "object",
+            "properties": {
+                "start": { "type": "string", "pattern": "^[0-9]{2}:[0-9]{2}$" },
+                "end": { "type": "string", "pattern": "^[0-9]{2}:[0-9]{2}$" },
+            },
+            "required": ["start", "end"],
+        },
+    }),
+});
+
+// 34. get sync controls
+#[derive(Deserialize)]
+pub struct SyncControlsQuery {
+    pub doc_id: String,
+}
+
+// 35. list conflicts
+#[derive(Deserialize)]
+pub struct ListConflictsQuery {
+    pub doc_id: String,
+    pub key_prefix: Option<String>,
+}
+
+// 36. get resolved entry
+#[derive(Deserialize)]
+pub struct GetResolvedEntryRequest {
+    pub doc_id: String,
+    pub key: String,
+    /// One of `CONFLICT_RESOLUTION_NAMES`.
+    pub resolution: String,
+    /// Author order of the `author_priority` resolution.
+    #[serde(default)]
+    pub author_priority: Vec<String>,
+}
+request_schema!(GetResolvedEntryRequest, {
+    "doc_id": validation::doc_id(),
+    "key": validation::key(),
+    "resolution": validation::one_of(CONFLICT_RESOLUTION_NAMES),
+});
+
+// 37. export doc archive
+// No request body
+
+// 38. import doc archive
+// The request body is the archive
+#[derive(Deserialize)]
+pub struct ImportDocArchiveQuery {
+    /// An empty document to import into. A new document is created if unset.
+    pub doc_id: Option<String>,
+}
+
+// 39. share doc bundle
+#[derive(Deserialize)]
+pub struct ShareDocBundleRequest {
+    pub doc_ids: Vec<String>,
+    pub mode: String,
+    pub addr_options: String,
+    /// A name for the suite of documents.
+    #[serde(default)]
+    pub label: Option<String>,
+}
+request_schema!(ShareDocBundleRequest, {
+    "doc_ids": validation::non_empty_array(validation::doc_id()),
+    "mode": validation::non_empty(),
+    "addr_options": validation::non_empty(),
+});
+
+// 40. join doc bundle
+#[derive(Deserialize)]
+pub struct JoinDocBundleRequest {
+    pub ticket: String,
+    /// Only join if the bundle is signed by this author.
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// Only download the content of entries under these key prefixes, in every document.
+    #[serde(default)]
+    pub sync_prefixes: Vec<String>,
+}
+request_schema!(JoinDocBundleRequest, {
+    "ticket": validation::non_empty(),
+});
+
+// 41. set content policy
+#[derive(Deserialize)]
+pub struct SetContentPolicyRequest {
+    pub doc_id: String,
+    pub policy: ContentPolicy,
+}
+request_schema!(SetContentPolicyRequest, {
+    "doc_id": validation::doc_id(),
+    "policy": serde_json::json!({ "type": "object", "required": ["default"] }),
+});
+
+// 42. get content policy
+#[derive(Deserialize)]
+pub struct ContentPolicyQuery {
+    pub doc_id: String,
+}
+
+// 43. doc metrics
+// No request body
+
+// 44. verify replica
+#[derive(Deserialize)]
+pub struct VerifyReplicaRequest {
+    pub doc_id: String,
+    pub peer_node_id: String,
+}
+request_schema!(VerifyReplicaRequest, {
+    "doc_id": validation::doc_id(),
+    "peer_node_id": validation::node_id(),
+});
+
+// 45. stream docs
+// No request body
+
+// 46. doc stats
+// No request body
+
+// 47. find entries by field
+#[derive(Deserialize)]
+pub struct FindEntriesByFieldRequest {
+    pub doc_id: String,
+    /// One of the fields set with `--index-field`.
+    pub field: String,
+    pub value: String,
+}
+request_schema!(FindEntriesByFieldRequest, {
+    "doc_id": validation::doc_id(),
+    "field": validation::non_empty(),
+});
+
+// 48. search entries
+#[derive(Deserialize)]
+pub struct SearchEntriesQuery {
+    /// Query in tantivy's query language.
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+// 49. entry attachments
+#[derive(Deserialize)]
+pub struct EntryAttachmentsQuery {
+    pub author_id: String,
+}
+
+// 50. restore entry
+#[derive(Deserialize)]
+pub struct RestoreEntryRequest {
+    pub doc_id: String,
+    pub author_id: String,
+    pub key: String,
+}
+request_schema!(RestoreEntryRequest, {
+    "doc_id": validation::doc_id(),
+    "author_id": validation::ss58(),
+    "key": validation::key(),
+});
+
+// 51. empty trash
+#[derive(Deserialize)]
+pub struct EmptyTrashRequest {
+    pub doc_id: String,
+    pub author_id: String,
+    /// Only remove the entry deleted at this key.
+    #[serde(default)]
+    pub key: Option<String>,
+}
+request_schema!(EmptyTrashRequest, {
+    "doc_id": validation::doc_id(),
+    "author_id": validation::ss58(),
+});
+
+// 52. list trash
+#[derive(Deserialize)]
+pub struct ListTrashQuery {
+    pub author_id: Option<String>,
+}
+
+// 53. verify entry
+#[derive(Deserialize)]
+pub struct VerifyEntryQuery {
+    /// Check this author's version of the entry instead of the latest one.
+    pub author_id: Option<String>,
+}
+
+// 54. entry inclusion proof
+#[derive(Deserialize)]
+pub struct InclusionProofQuery {
+    pub author_id: String,
+    /// Extrinsic hash of a Merkle anchor to prove the entry against, instead of the current state.
+    pub anchor: Option<String>,
+}
+
+// 55. verify inclusion proof
+// The EntryInclusionProof itself
+request_schema!(EntryInclusionProof, {
+    "doc_id": validation::doc_id(),
+    "key": validation::key(),
+    "author": validation::ss58(),
+    "hash": validation::hash(),
+    "root": validation::non_empty(),
+});
+
+// 56. get entries at
+#[derive(Deserialize)]
+pub struct EntriesAtQuery {
+    /// Microseconds since the Unix epoch, like entry timestamps.
+    pub timestamp: u64,
+    pub key_prefix: Option<String>,
+    pub author_id: Option<String>,
+}
+
+// 57. export doc table
+#[derive(Deserialize)]
+pub struct ExportDocTableQuery {
+    /// `csv` or `parquet`.
+    #[serde(default = "default_table_format")]
+    pub format: String,
+    pub key_prefix: Option<String>,
+    /// One of `CONFLICT_RESOLUTION_NAMES`, `last_writer_wins` if unset.
+    pub resolution: Option<String>,
+    /// Comma-separated author order of the `author_priority` resolution.
+    pub author_priority: Option<String>,
+    pub filter: Option<String>,
+}
+
+fn default_table_format() -> String {
+    "csv".to_string()
+}
 
 // Response bodies
 // 1. get document
@@ -191,6 +719,9 @@ pub struct AddDocSchemaResponse {
 #[derive(Debug, Serialize)]
 pub struct SetEntryResponse {
     pub hash: String,
+    /// Present when the request set `sign`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<EntrySignature>,
 }
 
 // 11. set entry file
@@ -210,6 +741,9 @@ pub struct GetEntryResponse {
     pub hash: String,
     pub len: u64,
     pub timestamp: u64,
+    /// Present when the request set `include_anchor` and an anchor covers the entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<EntryAnchor>,
 }
 
 // 13. get entries
@@ -250,11 +784,220 @@ pub struct GetDownloadPolicyResponse {
     pub download_policy: String, // Return JSON as string
 }
 
+// 20. anchor doc
+// Responds with the stored `DocAnchor`
+
+// 21. list doc anchors
+#[derive(Serialize)]
+pub struct ListDocAnchorsResponse {
+    pub doc_id: String,
+    pub anchors: Vec<DocAnchorStatus>,
+}
+
+#[derive(Serialize)]
+pub struct DocAnchorStatus {
+    #[serde(flatten)]
+    pub anchor: DocAnchor,
+    /// Whether the anchored entries are unchanged since, i.e. their digest still matches.
+    pub matches_current: bool,
+}
+
+// 22. issue credential
+// Responds with the signed credential JSON
+
+// 23. sync cord definitions
+#[derive(Serialize)]
+pub struct SyncCordDefinitionsResponse {
+    pub doc_id: String,
+    pub definitions: Vec<DefinitionSync>,
+}
+
+// 24. push schema to cord
+// Responds with the `DefinitionSync` of the new schema
+
+// 25. revoke statement
+// Responds with the stored `StatementRevocation`
+
+// 26. statement status
+#[derive(Serialize)]
+pub struct StatementStatusResponse {
+    pub statement_id: String,
+    /// `active` or `revoked`.
+    pub status: String,
+    pub revocation: Option<StatementRevocation>,
+    /// Whether the revocation digest is the remark of the extrinsic in `revocation`. Only set
+    /// when revoked.
+    pub revoked_on_chain: Option<bool>,
+}
+
+// 27. import cord schema
+#[derive(Serialize)]
+pub struct ImportCordSchemaResponse {
+    pub updated_hash: String,
+    /// The schema as stored in the document.
+    pub schema: serde_json::Value,
+}
+
+// 28. export cord schema
+// Responds with the CORD schema JSON
+
+// 29. set sync prefixes
+// 30. get sync prefixes
+#[derive(Serialize)]
+pub struct SyncPrefixesResponse {
+    pub doc_id: String,
+    /// Empty if all content is downloaded. `null` if the download policy was set with
+    /// `/docs/set-download-policy` and is not a list of prefixes.
+    pub prefixes: Option<Vec<String>>,
+}
+
+// 31. pause sync
+// 32. resume sync
+// 33. set sync controls
+// 34. get sync controls
+// Respond with `SyncControlStatus`
+
+// 35. list conflicts
+#[derive(Serialize)]
+pub struct KeyConflictResponse {
+    pub key: String,
+    /// The live version of each author, newest first.
+    pub versions: Vec<GetEntryResponse>,
+}
+
+#[derive(Serialize)]
+pub struct ListConflictsResponse {
+    pub doc_id: String,
+    pub conflicts: Vec<KeyConflictResponse>,
+}
+
+// 36. get resolved entry
+#[derive(Serialize)]
+pub struct GetResolvedEntryResponse {
+    #[serde(flatten)]
+    pub entry: GetEntryResponse,
+    /// Number of authors with a live version of the key.
+    pub versions: usize,
+}
+
+// 37. export doc archive
+// Responds with the CAR archive
+
+// 38. import doc archive
+// Responds with `DocArchiveImport`
+
+// 39. share doc bundle
+#[derive(Serialize)]
+pub struct ShareDocBundleResponse {
+    pub ticket: String,
+}
+
+// 40. join doc bundle
+#[derive(Serialize)]
+pub struct JoinDocBundleResponse {
+    /// The author that signed the bundle.
+    pub issuer: String,
+    pub label: Option<String>,
+    /// The joined documents, in the order of the bundle.
+    pub doc_ids: Vec<String>,
+}
+
+// 41. set content policy
+// 42. get content policy
+#[derive(Serialize)]
+pub struct ContentPolicyResponse {
+    pub doc_id: String,
+    /// ID of this node, as listed in `policy.peers`.
+    pub node_id: String,
+    pub policy: Option<ContentPolicy>,
+    /// The mode the policy sets for this node.
+    pub mode: Option<ContentMode>,
+}
+
+// 43. doc metrics
+#[derive(Serialize)]
+pub struct DocMetricsResponse {
+    pub doc_id: String,
+    /// Unix time in seconds the counters started from, when the node started.
+    pub since: u64,
+    #[serde(flatten)]
+    pub metrics: SyncMetrics,
+}
+
+// 44. verify replica
+// The report itself, see `ReplicaReport`
+
+// 45. stream docs
+// NDJSON, one ListDocsResponse per line
+
+// 46. doc stats
+#[derive(Serialize)]
+pub struct DocStatsResponse {
+    pub doc_id: String,
+    #[serde(flatten)]
+    pub stats: DocStats,
+}
+
+// 47. find entries by field
+#[derive(Serialize)]
+pub struct FindEntriesByFieldResponse {
+    pub entries: Vec<IndexedEntry>,
+}
+
+// 48. search entries
+#[derive(Serialize)]
+pub struct SearchEntriesResponse {
+    pub results: Vec<SearchHit>,
+}
+
+// 49. entry attachments
+#[derive(Serialize)]
+pub struct EntryAttachmentsResponse {
+    pub attachments: Vec<EntryAttachment>,
+}
+
+// 50. restore entry
+#[derive(Serialize)]
+pub struct RestoreEntryResponse {
+    pub hash: String,
+}
+
+// 51. empty trash
+#[derive(Serialize)]
+pub struct EmptyTrashResponse {
+    pub deleted_count: usize,
+}
+
+// 52. list trash
+#[derive(Serialize)]
+pub struct ListTrashResponse {
+    pub entries: Vec<TrashedEntry>,
+}
+
+// 53. verify entry
+// The EntryVerification itself
+
+// 54. entry inclusion proof
+// The EntryInclusionProof itself
+
+// 55. verify inclusion proof
+// The InclusionVerification itself
+
+// 56. get entries at
+#[derive(Serialize)]
+pub struct EntriesAtResponse {
+    pub timestamp: u64,
+    pub entries: Vec<EntryDetails>,
+}
+
+// 57. export doc table
+// The table, as CSV or Parquet
+
 // Handler for getting a document
 pub async fn get_document_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<GetDocumentRequest>,
+    ValidatedJson(payload): ValidatedJson<GetDocumentRequest>,
 ) -> Result<Json<GetDocumentResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -278,8 +1021,9 @@ pub async fn get_document_handler(
 // Handler for getting a blob entry
 pub async fn get_entry_blob_handler(
     State(state): State<AppState>,
+    tenant: Option<Extension<Tenant>>,
     headers: HeaderMap,
-    Json(payload): Json<GetEntryBlobRequest>,
+    ValidatedJson(payload): ValidatedJson<GetEntryBlobRequest>,
 ) -> Result<Json<GetEntryBlobResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -288,6 +1032,22 @@ pub async fn get_entry_blob_handler(
         return Err((StatusCode::BAD_REQUEST, "hash cannot be empty".to_string()));
     }
 
+    // A tenant only reads the content of its own documents, whose `doc_id` `resolve_tenant` checked
+    if tenant.is_some() && payload.doc_id.is_none() {
+        return Err((StatusCode::BAD_REQUEST, "doc_id is required with an API key".to_string()));
+    }
+    if let Some(doc_id) = payload.doc_id {
+        match doc_references_hash(state.docs.clone(), doc_id, &payload.hash).await {
+            Ok(true) => {}
+            Ok(false) => return Err((StatusCode::NOT_FOUND, "No entry of the document has this content".to_string())),
+            Err(e @ DocError::DocumentNotFound) => return Err((StatusCode::NOT_FOUND, e.to_string())),
+            Err(e @ (DocError::InvalidDocumentIdFormat | DocError::FailedToParseHash(_))) => {
+                return Err((StatusCode::BAD_REQUEST, e.to_string()))
+            }
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        }
+    }
+
     match get_entry_blob(state.blobs.clone(), payload.hash).await {
         Ok(content) => Ok(Json(GetEntryBlobResponse { content })),
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
@@ -297,6 +1057,7 @@ pub async fn get_entry_blob_handler(
 // Handler for creating a new document
 pub async fn create_doc_handler(
     State(state): State<AppState>,
+    tenant: Option<Extension<Tenant>>,
     headers: HeaderMap,
 ) -> Result<Json<CreateDocResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
@@ -314,23 +1075,41 @@ pub async fn create_doc_handler(
         ));
     }
 
-    match create_doc(state.docs.clone()).await {
-        Ok(doc_id) => Ok(Json(CreateDocResponse { doc_id })),
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    // A tenant's document counts towards its quota
+    if let Some(Extension(tenant)) = &tenant {
+        if tenant.quota.max_docs.is_some_and(|max| tenant.docs.len() >= max) {
+            return Err((StatusCode::FORBIDDEN, TenantError::QuotaExceeded("documents").to_string()));
+        }
+    }
+
+    match create_doc(state.docs.clone()).await {
+        Ok(doc_id) => {
+            if let Some(Extension(tenant)) = &tenant {
+                state.tenants.assign_doc(&tenant.id, &doc_id)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            }
+            state.events.publish(NodeEvent::DocCreated { doc_id: doc_id.clone() });
+            Ok(Json(CreateDocResponse { doc_id }))
+        },
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
 }
 
 // Handler for listing documents
 pub async fn list_docs_handler(
     State(state): State<AppState>,
+    tenant: Option<Extension<Tenant>>,
     headers: HeaderMap,
-) -> Result<Json<Vec<ListDocsResponse>>, (StatusCode, String)> {
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<ListDocsResponse>>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
     match list_docs(state.docs.clone()).await {
-        Ok(docs) => {
-            let response = docs
-                .into_iter()
+        Ok(mut docs) => {
+            // A tenant only sees its own documents
+            retain_tenant_docs(tenant.as_deref(), &mut docs, |(doc_id, _)| doc_id);
+            let response = Paginated::from_items(docs, &pagination)?
                 .map(|(doc_id, capability)| {
                     let capability_str = match capability {
                         CapabilityKind::Write => "Write".to_string(),
@@ -341,8 +1120,7 @@ pub async fn list_docs_handler(
                         doc_id,
                         capability: capability_str,
                     }
-                })
-                .collect();
+                });
 
             Ok(Json(response))
         }
@@ -350,11 +1128,30 @@ pub async fn list_docs_handler(
     }
 }
 
+// Handler for streaming every document as NDJSON, without pagination
+pub async fn stream_docs_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    match stream_docs(state.docs.clone()).await {
+        Ok(docs) => Ok(ndjson_response(docs.map_ok(|(doc_id, capability)| ListDocsResponse {
+            doc_id,
+            capability: match capability {
+                CapabilityKind::Write => "Write".to_string(),
+                CapabilityKind::Read => "Read".to_string(),
+            },
+        }))),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
 // Handler for dropping a document
 pub async fn drop_doc_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<DropDocRequest>,
+    ValidatedJson(payload): ValidatedJson<DropDocRequest>,
 ) -> Result<Json<DropDocResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -371,24 +1168,50 @@ pub async fn drop_doc_handler(
         ));
     }
 
+    // With an approval policy, documents are only dropped through the approvals API
+    state.approvals.check_direct().map_err(|e| (StatusCode::FORBIDDEN, e))?;
+
     // request body checks
     if payload.doc_id.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "doc_id cannot be empty".to_string()));
     }
 
-    match drop_doc(state.docs.clone(), payload.doc_id).await {
-        Ok(_) => Ok(Json(DropDocResponse {
-            message: "Document dropped successfully".to_string(),
-        })),
+    match drop_doc(state.docs.clone(), payload.doc_id.clone()).await {
+        Ok(_) => {
+            state.events.publish(NodeEvent::DocDropped { doc_id: payload.doc_id });
+            Ok(Json(DropDocResponse {
+                message: "Document dropped successfully".to_string(),
+            }))
+        },
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
 }
 
+// Matches a share mode
+pub(crate) fn parse_share_mode(mode: &str) -> Result<ShareMode, (StatusCode, String)> {
+    match mode.to_lowercase().as_str() {
+        "read" => Ok(ShareMode::Read),
+        "write" => Ok(ShareMode::Write),
+        _ => Err((StatusCode::BAD_REQUEST, format!("Invalid share mode: {}", mode))),
+    }
+}
+
+// Matches address options
+pub(crate) fn parse_addr_options(addr_options: &str) -> Result<AddrInfoOptions, (StatusCode, String)> {
+    match addr_options.to_lowercase().as_str() {
+        "id" => Ok(AddrInfoOptions::Id),
+        "relayandaddresses" => Ok(AddrInfoOptions::RelayAndAddresses),
+        "relay" => Ok(AddrInfoOptions::Relay),
+        "addresses" => Ok(AddrInfoOptions::Addresses),
+        _ => Err((StatusCode::BAD_REQUEST, format!("Invalid addr_options: {}", addr_options))),
+    }
+}
+
 // Handler for sharing a document
 pub async fn share_doc_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<ShareDocRequest>,
+    ValidatedJson(payload): ValidatedJson<ShareDocRequest>,
 ) -> Result<Json<ShareDocResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -403,21 +1226,8 @@ pub async fn share_doc_handler(
         return Err((StatusCode::BAD_REQUEST, "addr_options cannot be empty".to_string()));
     }
 
-    // Match share mode
-    let mode = match payload.mode.to_lowercase().as_str() {
-        "read" => ShareMode::Read,
-        "write" => ShareMode::Write,
-        _ => return Err((StatusCode::BAD_REQUEST, format!("Invalid share mode: {}", payload.mode))),
-    };
-
-    // Match address options
-    let addr_options = match payload.addr_options.to_lowercase().as_str() {
-        "id" => AddrInfoOptions::Id,
-        "relayandaddresses" => AddrInfoOptions::RelayAndAddresses,
-        "relay" => AddrInfoOptions::Relay,
-        "addresses" => AddrInfoOptions::Addresses,
-        _ => return Err((StatusCode::BAD_REQUEST, format!("Invalid addr_options: {}", payload.addr_options))),
-    };
+    let mode = parse_share_mode(&payload.mode)?;
+    let addr_options = parse_addr_options(&payload.addr_options)?;
 
     match share_doc(state.docs.clone(), payload.doc_id, mode, addr_options).await {
         Ok(ticket) => Ok(Json(ShareDocResponse { ticket })),
@@ -429,7 +1239,7 @@ pub async fn share_doc_handler(
 pub async fn join_doc_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<JoinDocRequest>,
+    ValidatedJson(payload): ValidatedJson<JoinDocRequest>,
 ) -> Result<Json<JoinDocResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -438,8 +1248,12 @@ pub async fn join_doc_handler(
         return Err((StatusCode::BAD_REQUEST, "ticket cannot be empty".to_string()));
     }
 
-    match join_doc(state.docs.clone(), payload.ticket).await {
-        Ok(doc_id) => Ok(Json(JoinDocResponse { doc_id })),
+    match join_doc(state.docs.clone(), payload.ticket, payload.sync_prefixes).await {
+        Ok(doc_id) => {
+            state.events.publish(NodeEvent::DocJoined { doc_id: doc_id.clone() });
+            Ok(Json(JoinDocResponse { doc_id }))
+        },
+        Err(e @ DocError::InvalidSyncPrefix) => Err((StatusCode::BAD_REQUEST, e.to_string())),
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
 }
@@ -448,7 +1262,7 @@ pub async fn join_doc_handler(
 pub async fn close_doc_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<CloseDocRequest>,
+    ValidatedJson(payload): ValidatedJson<CloseDocRequest>,
 ) -> Result<Json<CloseDocResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -497,7 +1311,7 @@ pub async fn close_doc_handler(
 pub async fn add_doc_schema_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<AddDocSchemaRequest>,
+    ValidatedJson(payload): ValidatedJson<AddDocSchemaRequest>,
 ) -> Result<Json<AddDocSchemaResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -527,6 +1341,7 @@ pub async fn add_doc_schema_handler(
 
     match add_doc_schema(
         state.docs.clone(),
+        state.blobs.clone(),
         payload.author_id,
         payload.doc_id,
         payload.schema,
@@ -543,10 +1358,19 @@ pub async fn add_doc_schema_handler(
 pub async fn set_entry_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<SetEntryRequest>,
+    tenant: Option<Extension<Tenant>>,
+    ValidatedJson(payload): ValidatedJson<SetEntryRequest>,
 ) -> Result<Json<SetEntryResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
+    // The node's keys speak for the node, so a tenant can only sign with its own author
+    if tenant.is_some() && payload.sign && matches!(payload.sign_with, SignWith::Keystore) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Tenants cannot sign with the node's keys. Use \"sign_with\": \"author\"".to_string(),
+        ));
+    }
+
     let caller_author_id = get_author_id_from_headers(&headers)?;
 
     // Check if the calling author is in the list of authors
@@ -574,6 +1398,54 @@ pub async fn set_entry_handler(
         return Err((StatusCode::BAD_REQUEST, "value cannot be empty".to_string()));
     }
 
+    check_entry_authorization(&state, &payload.doc_id, &payload.author_id).await?;
+
+    if payload.sign && matches!(payload.sign_with, SignWith::Author) {
+        return match set_entry_signed(
+            state.docs.clone(),
+            state.blobs.clone(),
+            payload.doc_id,
+            payload.author_id,
+            payload.key,
+            payload.value,
+            EntrySigner::Author,
+        )
+        .await
+        {
+            Ok((hash, signature)) => Ok(Json(SetEntryResponse { hash, signature: Some(signature) })),
+            Err(e @ DocError::AuthorNotFound) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+            Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+    }
+
+    if payload.sign {
+        let keystore = StarterkitKeystore::from_inner(state.cord_signer.keystore.clone());
+        let public = keystore
+            .get_doc_signing_public_key()
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        let result = set_entry_signed(
+            state.docs.clone(),
+            state.blobs.clone(),
+            payload.doc_id,
+            payload.author_id,
+            payload.key,
+            payload.value,
+            EntrySigner::Keystore(&keystore),
+        )
+        .await;
+
+        // the entry is signed with the node's document signing key
+        let key_type = builtin_key_type(SIGNING_KEY_TYPE)
+            .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "FailedToSignEntry".to_string()))?;
+        let signed = !matches!(result, Err(DocError::FailedToSignEntry(_)));
+        record_signature(&state, &headers, &key_type, KeyPurpose::DocSigning, &public.0, signed)?;
+
+        return match result {
+            Ok((hash, signature)) => Ok(Json(SetEntryResponse { hash, signature: Some(signature) })),
+            Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+    }
+
     match set_entry(
         state.docs.clone(),
         state.blobs.clone(),
@@ -584,16 +1456,28 @@ pub async fn set_entry_handler(
     )
     .await
     {
-        Ok(hash) => Ok(Json(SetEntryResponse { hash })),
+        Ok(hash) => Ok(Json(SetEntryResponse { hash, signature: None })),
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
 }
 
+// With `--require-chain-authorization`, fails unless `author_id` is authorized on the document's
+// CORD registry
+pub(crate) async fn check_entry_authorization(state: &AppState, doc_id: &str, author_id: &str) -> Result<(), (StatusCode, String)> {
+    check_author_authorization(state.docs.clone(), state.cord_client.clone(), &state.authorizations, doc_id, author_id)
+        .await
+        .map_err(|e| match e {
+            DocError::AuthorHasNoLinkedDid | DocError::AuthorNotAuthorized => (StatusCode::FORBIDDEN, e.to_string()),
+            DocError::InvalidDocumentIdFormat => (StatusCode::BAD_REQUEST, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })
+}
+
 // Handler for setting an entry in a document from a file
 pub async fn set_entry_file_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<SetEntryFileRequest>,
+    ValidatedJson(payload): ValidatedJson<SetEntryFileRequest>,
 ) -> Result<Json<SetEntryFileResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -624,6 +1508,8 @@ pub async fn set_entry_file_handler(
         return Err((StatusCode::BAD_REQUEST, "file_path cannot be empty".to_string()));
     }
 
+    check_entry_authorization(&state, &payload.doc_id, &payload.author_id).await?;
+
     match set_entry_file(
         state.docs.clone(),
         payload.doc_id,
@@ -646,7 +1532,7 @@ pub async fn set_entry_file_handler(
 pub async fn get_entry_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<GetEntryRequest>,
+    ValidatedJson(payload): ValidatedJson<GetEntryRequest>,
 ) -> Result<Json<GetEntryResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -661,14 +1547,21 @@ pub async fn get_entry_handler(
         return Err((StatusCode::BAD_REQUEST, "key cannot be empty".to_string()));
     }
 
-    match get_entry(
+    let entry = get_entry(
         state.docs.clone(),
-        payload.doc_id,
+        payload.doc_id.clone(),
         payload.author_id,
         payload.key,
         payload.include_empty,
-    ).await {
-        Ok(Some(details)) => {
+    ).await;
+
+    match entry {
+        Ok(Some(mut details)) => {
+            if payload.include_anchor {
+                attach_entry_anchors(state.docs.clone(), state.blobs.clone(), payload.doc_id, std::slice::from_mut(&mut details))
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            }
             Ok(Json(GetEntryResponse {
                 doc: details.namespace.doc,
                 key: details.namespace.key,
@@ -676,6 +1569,7 @@ pub async fn get_entry_handler(
                 hash: details.record.hash,
                 len: details.record.len,
                 timestamp: details.record.timestamp,
+                anchor: details.record.anchor,
             }))
         },
         Ok(None) => Err((StatusCode::NOT_FOUND, "Entry not found".to_string())),
@@ -687,8 +1581,9 @@ pub async fn get_entry_handler(
 pub async fn get_entries_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<GetEntriesRequest>,
-) -> Result<Json<Vec<GetEntryResponse>>, (StatusCode, String)> {
+    Query(pagination): Query<PaginationParams>,
+    ValidatedJson(payload): ValidatedJson<GetEntriesRequest>,
+) -> Result<Json<Paginated<GetEntryResponse>>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
     // request body checks
@@ -703,11 +1598,34 @@ pub async fn get_entries_handler(
     let query_params: serde_json::Value = serde_json::from_str(&payload.query_params)
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid query_params: {}", e)))?;
 
+    let resolution = payload.resolution
+        .map(|name| ConflictResolution::from_name(&name, payload.author_priority))
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let filter = payload.filter
+        .as_deref()
+        .map(Filter::parse)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
     // Fetch entries
     match get_entries(state.docs.clone(), payload.doc_id.clone(), query_params).await {
-        Ok(entry_details_vec) => {
-            let response_vec = entry_details_vec
-                .into_iter()
+        Ok(mut entry_details_vec) => {
+            if let Some(resolution) = &resolution {
+                entry_details_vec = resolve_entries(entry_details_vec, resolution);
+            }
+            if let Some(filter) = &filter {
+                entry_details_vec = filter_entries(state.blobs.clone(), entry_details_vec, filter)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            }
+            if payload.include_anchor {
+                attach_entry_anchors(state.docs.clone(), state.blobs.clone(), payload.doc_id, &mut entry_details_vec)
+                    .await
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            }
+            let response = Paginated::from_items(entry_details_vec, &pagination)?
                 .map(|entry| GetEntryResponse {
                     doc: entry.namespace.doc,
                     key: entry.namespace.key,
@@ -715,10 +1633,10 @@ pub async fn get_entries_handler(
                     hash: entry.record.hash,
                     len: entry.record.len,
                     timestamp: entry.record.timestamp,
-                })
-                .collect();
+                    anchor: entry.record.anchor,
+                });
 
-            Ok(Json(response_vec))
+            Ok(Json(response))
         }
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
@@ -728,7 +1646,7 @@ pub async fn get_entries_handler(
 pub async fn delete_entry_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<DeleteEntryRequest>,
+    ValidatedJson(payload): ValidatedJson<DeleteEntryRequest>,
 ) -> Result<Json<DeleteEntryResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -771,7 +1689,7 @@ pub async fn delete_entry_handler(
 pub async fn leave_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<LeaveRequest>,
+    ValidatedJson(payload): ValidatedJson<LeaveRequest>,
 ) -> Result<Json<LeaveResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -805,7 +1723,7 @@ pub async fn leave_handler(
 pub async fn status_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<StatusRequest>,
+    ValidatedJson(payload): ValidatedJson<StatusRequest>,
 ) -> Result<Json<StatusResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -828,7 +1746,7 @@ pub async fn status_handler(
 pub async fn set_download_policy_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<SetDownloadPolicyRequest>,
+    ValidatedJson(payload): ValidatedJson<SetDownloadPolicyRequest>,
 ) -> Result<Json<SetDownloadPolicyResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -870,7 +1788,7 @@ pub async fn set_download_policy_handler(
 pub async fn get_download_policy_handler(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<GetDownloadPolicyRequest>,
+    ValidatedJson(payload): ValidatedJson<GetDownloadPolicyRequest>,
 ) -> Result<Json<GetDownloadPolicyResponse>, (StatusCode, String)> {
     check_node_id_and_domain_header(&headers)?;
 
@@ -890,4 +1808,1278 @@ pub async fn get_download_policy_handler(
         }
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
-}
\ No newline at end of file
+}
+
+// REST handlers
+// These map resource-style routes onto the same core functions as the POST endpoints above.
+
+// Handler for `GET /docs/:id/entries/*key`. Keys may contain `/`, so the attachments, signature
+// check and inclusion proof of an entry are served here too, by the end of the key, see
+// `EntryResource`
+pub async fn entry_resource_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((doc_id, path)): Path<(String, String)>,
+    uri: Uri,
+) -> Result<Response, (StatusCode, String)> {
+    let (key, resource) = EntryResource::split(&path);
+    let path = Path((doc_id, key.to_string()));
+    let response = match resource {
+        EntryResource::Entry => get_entry_rest_handler(State(state), headers, path, entry_query(&uri)?).await?.into_response(),
+        EntryResource::Attachments => entry_attachments_handler(State(state), headers, path, entry_query(&uri)?).await?.into_response(),
+        EntryResource::Verify => verify_entry_handler(State(state), headers, path, entry_query(&uri)?).await?.into_response(),
+        EntryResource::InclusionProof => entry_inclusion_proof_handler(State(state), headers, path, entry_query(&uri)?).await?.into_response(),
+    };
+    Ok(response)
+}
+
+// The query of an entry resource, see `entry_resource_handler`
+fn entry_query<T: serde::de::DeserializeOwned>(uri: &Uri) -> Result<Query<T>, (StatusCode, String)> {
+    Query::try_from_uri(uri).map_err(|e| (StatusCode::BAD_REQUEST, e.body_text()))
+}
+
+// Handler for getting an entry via `GET /docs/:id/entries/*key?author_id=...`
+pub async fn get_entry_rest_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((doc_id, key)): Path<(String, String)>,
+    Query(query): Query<GetEntryQuery>,
+) -> Result<Json<GetEntryResponse>, (StatusCode, String)> {
+    get_entry_handler(
+        State(state),
+        headers,
+        ValidatedJson(GetEntryRequest {
+            doc_id,
+            author_id: query.author_id,
+            key,
+            include_empty: query.include_empty,
+            include_anchor: query.include_anchor,
+        }),
+    ).await
+}
+
+// Handler for dropping a document via `DELETE /docs/:id`
+pub async fn drop_doc_rest_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(doc_id): Path<String>,
+) -> Result<Json<DropDocResponse>, (StatusCode, String)> {
+    drop_doc_handler(State(state), headers, ValidatedJson(DropDocRequest { doc_id })).await
+}
+
+// Handler for the sync counters of a document via `GET /docs/:id/metrics`
+pub async fn doc_metrics_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(doc_id): Path<String>,
+) -> Result<Json<DocMetricsResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let namespace_id = decode_doc_id(&doc_id)
+        .map_err(|_| (StatusCode::BAD_REQUEST, DocError::InvalidDocumentIdFormat.to_string()))?;
+    get_document(state.docs.clone(), NamespaceId::from(namespace_id))
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+
+    Ok(Json(DocMetricsResponse {
+        metrics: state.replication.metrics(&doc_id),
+        since: state.replication.started_at(),
+        doc_id,
+    }))
+}
+
+// Message of the index endpoints while the document is still scanned
+const INDEX_NOT_READY: &str = "The document is still being indexed, retry shortly";
+
+// Handler for the entry counts of a document via `GET /docs/:id/stats`
+pub async fn doc_stats_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(doc_id): Path<String>,
+) -> Result<Json<DocStatsResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let namespace_id = decode_doc_id(&doc_id)
+        .map_err(|_| (StatusCode::BAD_REQUEST, DocError::InvalidDocumentIdFormat.to_string()))?;
+    get_document(state.docs.clone(), NamespaceId::from(namespace_id))
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+
+    let stats = state.entry_index.stats(&doc_id)
+        .ok_or((StatusCode::SERVICE_UNAVAILABLE, INDEX_NOT_READY.to_string()))?;
+    Ok(Json(DocStatsResponse { doc_id, stats }))
+}
+
+// Handler for looking up entries by the value of an indexed field
+pub async fn find_entries_by_field_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<FindEntriesByFieldRequest>,
+) -> Result<Json<FindEntriesByFieldResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    if !state.entry_index.fields().contains(&payload.field) {
+        return Err((StatusCode::BAD_REQUEST, format!("The field '{}' is not indexed, see --index-field", payload.field)));
+    }
+
+    let namespace_id = decode_doc_id(&payload.doc_id)
+        .map_err(|_| (StatusCode::BAD_REQUEST, DocError::InvalidDocumentIdFormat.to_string()))?;
+    get_document(state.docs.clone(), NamespaceId::from(namespace_id))
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+
+    let entries = state.entry_index.find_by_field(&payload.doc_id, &payload.field, &payload.value)
+        .ok_or((StatusCode::SERVICE_UNAVAILABLE, INDEX_NOT_READY.to_string()))?;
+    Ok(Json(FindEntriesByFieldResponse { entries }))
+}
+
+// Results of a search when no limit is given, and the most a search returns
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+const MAX_SEARCH_LIMIT: usize = 100;
+
+// Handler for full-text search over a document's entries via `GET /docs/:id/search`
+pub async fn search_entries_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(doc_id): Path<String>,
+    Query(query): Query<SearchEntriesQuery>,
+) -> Result<Json<SearchEntriesResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    if !state.search.is_enabled() {
+        return Err((StatusCode::NOT_FOUND, "Full-text search is not enabled, see --search".to_string()));
+    }
+    if query.q.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "The query `q` must not be empty".to_string()));
+    }
+
+    let namespace_id = decode_doc_id(&doc_id)
+        .map_err(|_| (StatusCode::BAD_REQUEST, DocError::InvalidDocumentIdFormat.to_string()))?;
+    get_document(state.docs.clone(), NamespaceId::from(namespace_id))
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+    let results = match state.search.search(&doc_id, &query.q, limit) {
+        Ok(Some(results)) => results,
+        Ok(None) => return Err((StatusCode::SERVICE_UNAVAILABLE, INDEX_NOT_READY.to_string())),
+        Err(e @ SearchError::InvalidQuery(_)) => return Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    };
+    Ok(Json(SearchEntriesResponse { results }))
+}
+
+// Handler for listing the attachments an entry links to via
+// `GET /docs/:id/entries/*key/attachments?author_id=...`
+pub async fn entry_attachments_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((doc_id, key)): Path<(String, String)>,
+    Query(query): Query<EntryAttachmentsQuery>,
+) -> Result<Json<EntryAttachmentsResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    match entry_attachments(state.docs.clone(), state.blobs.clone(), doc_id, query.author_id, key).await {
+        Ok(attachments) => Ok(Json(EntryAttachmentsResponse { attachments })),
+        Err(e @ (DocError::InvalidDocumentIdFormat | DocError::InvalidAuthorIdFormat | DocError::FailedToValidateKey(_))) => {
+            Err((StatusCode::BAD_REQUEST, e.to_string()))
+        }
+        Err(e @ DocError::FailedToConvertValueJson(_)) => Err((StatusCode::UNPROCESSABLE_ENTITY, e.to_string())),
+        Err(e @ (DocError::DocumentNotFound | DocError::EntryNotFound)) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Maps a trash error to the response status
+fn trash_error_response(e: DocError) -> (StatusCode, String) {
+    let status = match &e {
+        DocError::InvalidDocumentIdFormat | DocError::InvalidAuthorIdFormat | DocError::FailedToValidateKey(_) => {
+            StatusCode::BAD_REQUEST
+        }
+        DocError::DocumentNotFound | DocError::TrashedEntryNotFound => StatusCode::NOT_FOUND,
+        DocError::EntryAlreadyExists => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+// Handler for putting a deleted entry back from the trash
+pub async fn restore_entry_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<RestoreEntryRequest>,
+) -> Result<Json<RestoreEntryResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Check if the calling author is in the list of authors
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((StatusCode::FORBIDDEN, "Only a registered author can perform this action".to_string()));
+    }
+
+    let hash = restore_entry(state.docs.clone(), payload.doc_id, payload.author_id, payload.key)
+        .await
+        .map_err(trash_error_response)?;
+    Ok(Json(RestoreEntryResponse { hash }))
+}
+
+// Handler for deleting for good the entries an author keeps in the trash of a document
+pub async fn empty_trash_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<EmptyTrashRequest>,
+) -> Result<Json<EmptyTrashResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Check if the calling author is in the list of authors
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((StatusCode::FORBIDDEN, "Only a registered author can perform this action".to_string()));
+    }
+
+    // With an approval policy, the trash is only purged through the approvals API
+    state.approvals.check_direct().map_err(|e| (StatusCode::FORBIDDEN, e))?;
+
+    let deleted_count = empty_trash(state.docs.clone(), payload.doc_id, payload.author_id, payload.key)
+        .await
+        .map_err(trash_error_response)?;
+    Ok(Json(EmptyTrashResponse { deleted_count }))
+}
+
+// Handler for listing the deleted entries kept in the trash via
+// `GET /docs/:id/trash?author_id=...`
+pub async fn list_trash_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(doc_id): Path<String>,
+    Query(query): Query<ListTrashQuery>,
+) -> Result<Json<ListTrashResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let entries = list_trash(state.docs.clone(), doc_id, query.author_id)
+        .await
+        .map_err(trash_error_response)?;
+    Ok(Json(ListTrashResponse { entries }))
+}
+
+// Handler for re-checking the detached signature of an entry via
+// `GET /docs/:id/entries/*key/verify?author_id=...`
+pub async fn verify_entry_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((doc_id, key)): Path<(String, String)>,
+    Query(query): Query<VerifyEntryQuery>,
+) -> Result<Json<EntryVerification>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    match verify_entry(state.docs.clone(), state.blobs.clone(), doc_id, query.author_id, key).await {
+        Ok(verification) => Ok(Json(verification)),
+        Err(e @ (DocError::InvalidDocumentIdFormat | DocError::InvalidAuthorIdFormat | DocError::FailedToValidateKey(_))) => {
+            Err((StatusCode::BAD_REQUEST, e.to_string()))
+        }
+        Err(e @ (DocError::DocumentNotFound | DocError::EntryNotFound | DocError::EntrySignatureNotFound)) => {
+            Err((StatusCode::NOT_FOUND, e.to_string()))
+        }
+        Err(e @ DocError::FailedToParseEntrySignature(_)) => Err((StatusCode::UNPROCESSABLE_ENTITY, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Maps an inclusion proof error to the response status
+fn inclusion_error_response(e: DocError) -> (StatusCode, String) {
+    let status = match &e {
+        DocError::InvalidDocumentIdFormat
+        | DocError::InvalidAuthorIdFormat
+        | DocError::FailedToValidateKey(_)
+        | DocError::AnchorNotMerkle
+        | DocError::EntryNotAnchored => StatusCode::BAD_REQUEST,
+        DocError::DocumentNotFound | DocError::EntryNotFound | DocError::AnchorNotFound => StatusCode::NOT_FOUND,
+        DocError::AnchorNoLongerMatches => StatusCode::CONFLICT,
+        DocError::FailedToReadAnchor(_) => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+// Handler for proving an entry is included in the Merkle root of its document via
+// `GET /docs/:id/entries/*key/inclusion-proof?author_id=...&anchor=...`
+pub async fn entry_inclusion_proof_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((doc_id, key)): Path<(String, String)>,
+    Query(query): Query<InclusionProofQuery>,
+) -> Result<Json<EntryInclusionProof>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    entry_inclusion_proof(state.docs.clone(), state.blobs.clone(), doc_id, query.author_id, key, query.anchor)
+        .await
+        .map(Json)
+        .map_err(inclusion_error_response)
+}
+
+// Handler for checking an inclusion proof, including one made by another node
+pub async fn verify_inclusion_proof_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(proof): ValidatedJson<EntryInclusionProof>,
+) -> Result<Json<InclusionVerification>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    verify_inclusion_proof(&proof, state.anchor.clone())
+        .await
+        .map(Json)
+        .map_err(inclusion_error_response)
+}
+
+// Handler for reading the entries of a document as they were at a past time via
+// `GET /docs/:id/entries-at?timestamp=...&key_prefix=...&author_id=...`
+pub async fn get_entries_at_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(doc_id): Path<String>,
+    Query(query): Query<EntriesAtQuery>,
+) -> Result<Json<EntriesAtResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    match get_entries_at(state.docs.clone(), doc_id, query.timestamp, query.key_prefix, query.author_id).await {
+        Ok(entries) => Ok(Json(EntriesAtResponse { timestamp: query.timestamp, entries })),
+        Err(e @ (DocError::InvalidDocumentIdFormat | DocError::InvalidAuthorIdFormat)) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e @ DocError::DocumentNotFound) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+fn table_error_response(e: TableError) -> (StatusCode, String) {
+    let status = match &e {
+        TableError::InvalidDocumentIdFormat | TableError::UnsupportedFormat => StatusCode::BAD_REQUEST,
+        TableError::DocumentNotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+// Handler for exporting the latest entries of a document as a CSV or Parquet table
+pub async fn export_doc_table_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(doc_id): Path<String>,
+    Query(query): Query<ExportDocTableQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let format = TableFormat::from_name(&query.format).map_err(table_error_response)?;
+    let author_priority = query.author_priority
+        .map(|authors| authors.split(',').map(|author| author.trim().to_string()).collect())
+        .unwrap_or_default();
+    let resolution = ConflictResolution::from_name(query.resolution.as_deref().unwrap_or("last_writer_wins"), author_priority)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let filter = query.filter
+        .as_deref()
+        .map(Filter::parse)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let table = doc_table(state.docs.clone(), state.blobs.clone(), doc_id.clone(), query.key_prefix, &resolution, filter.as_ref())
+        .await
+        .map_err(table_error_response)?;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, format.content_type())
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.{}\"", doc_id, format.extension()))
+        .header("x-table-rows", table.len())
+        .header("x-skipped-entries", table.skipped)
+        .body(Body::from(table.write(format)))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// Server-Sent Events
+// Entry events carry an `EventPosition` as their id, so a reconnecting client's `Last-Event-ID`
+// header replays every entry written after it before live events resume. Live events are only
+// dropped when they repeat a replayed entry, since entries synced from peers can carry timestamps
+// older than the ones already sent.
+
+// Handler for streaming document changes via `GET /docs/:id/events`
+pub async fn doc_events_sse_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(doc_id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let last_event_id = match headers.get("last-event-id") {
+        Some(value) => Some(
+            value
+                .to_str()
+                .ok()
+                .and_then(EventPosition::parse)
+                .ok_or((StatusCode::BAD_REQUEST, "Last-Event-ID must be the id of an event of this stream".to_string()))?,
+        ),
+        None => None,
+    };
+
+    // Subscribe before replaying so no entry written in between is missed
+    let live = match subscribe_doc(state.docs.clone(), doc_id.clone()).await {
+        Ok(live) => live,
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    };
+
+    let missed = match last_event_id {
+        Some(since) => match get_entries_since(state.docs.clone(), doc_id, since.timestamp).await {
+            Ok(entries) => entries,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        },
+        None => Vec::new(),
+    };
+    let mut replayed: HashSet<(String, String, String)> = missed.iter().map(entry_identity).collect();
+
+    let replay = futures::stream::iter(missed.into_iter().map(DocStreamItem::Entry));
+    let live = live.filter_map(move |event| {
+        let item = match event {
+            // an entry written between subscribing and replaying arrives twice
+            Ok(DocEvent::InsertLocal { entry }) | Ok(DocEvent::InsertRemote { entry, .. }) => {
+                (replayed.is_empty() || !replayed.remove(&entry_identity(&entry))).then_some(DocStreamItem::Entry(entry))
+            }
+            Ok(event @ DocEvent::SyncFinished { .. }) => Some(DocStreamItem::SyncFinished(event)),
+            _ => None,
+        };
+        futures::future::ready(item)
+    });
+
+    let events = replay.chain(live).scan(last_event_id.unwrap_or_default(), |position, item| {
+        let event = match item {
+            DocStreamItem::Entry(entry) => entry_event(&entry, position.advance(&entry)),
+            DocStreamItem::SyncFinished(event) => Event::default().event("sync_finished").json_data(&event),
+        };
+        futures::future::ready(Some(event))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+// What the events stream of a document sends
+enum DocStreamItem {
+    Entry(EntryDetails),
+    SyncFinished(DocEvent),
+}
+
+// Where a client is in the events stream of a document, sent as the id `<timestamp>-<sequence>` of
+// entry events. The sequence counts the entry events sent, carried over when the client
+// reconnects, and the timestamp is the latest entry timestamp sent, which replays resume after.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct EventPosition {
+    timestamp: u64,
+    sequence: u64,
+}
+
+impl EventPosition {
+    // An event id, or an entry timestamp as sent by earlier versions
+    fn parse(id: &str) -> Option<Self> {
+        match id.split_once('-') {
+            Some((timestamp, sequence)) => Some(Self { timestamp: timestamp.parse().ok()?, sequence: sequence.parse().ok()? }),
+            None => Some(Self { timestamp: id.parse().ok()?, sequence: 0 }),
+        }
+    }
+
+    // Moves past `entry` and returns the id of its event
+    fn advance(&mut self, entry: &EntryDetails) -> String {
+        self.timestamp = self.timestamp.max(entry.record.timestamp);
+        self.sequence += 1;
+        format!("{}-{}", self.timestamp, self.sequence)
+    }
+}
+
+// The author, key and content hash of an entry, which tell apart every write to a document
+fn entry_identity(entry: &EntryDetails) -> (String, String, String) {
+    (entry.namespace.author.clone(), entry.namespace.key.clone(), entry.record.hash.clone())
+}
+
+// Builds the SSE event for an inserted or deleted entry
+fn entry_event(entry: &EntryDetails, id: String) -> Result<Event, axum::Error> {
+    let name = if entry.record.len == 0 { "entry_deleted" } else { "entry_inserted" };
+
+    Event::default()
+        .event(name)
+        .id(id)
+        .json_data(entry)
+}
+
+// Handler for anchoring the digest of a document to the CORD chain
+pub async fn anchor_doc_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<AnchorDocRequest>,
+) -> Result<Json<DocAnchor>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Anchors are chain transactions paid by the node's account, so only the default author can
+    // submit them
+    let default_author = core::authors::get_default_author(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if caller_author_id != default_author {
+        return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
+    }
+
+    match anchor_doc(
+        state.docs.clone(),
+        payload.doc_id,
+        caller_author_id,
+        payload.keys,
+        payload.digest_kind,
+        state.anchor.clone(),
+    )
+    .await
+    {
+        Ok(anchor) => Ok(Json(anchor)),
+        Err(DocError::EntryNotFound) => Err((StatusCode::BAD_REQUEST, DocError::EntryNotFound.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for listing the anchors of a document and whether they still match it
+pub async fn list_doc_anchors_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ListDocAnchorsQuery>,
+) -> Result<Json<ListDocAnchorsResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let anchors = list_doc_anchors(state.docs.clone(), state.blobs.clone(), query.doc_id.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut statuses = Vec::with_capacity(anchors.len());
+    for anchor in anchors {
+        // an anchored key that was deleted since no longer matches
+        let matches_current = match compute_anchor_digest(state.docs.clone(), query.doc_id.clone(), anchor.keys.as_deref(), anchor.digest_kind).await {
+            Ok((digest, _)) => anchor.digest == format!("0x{}", hex::encode(digest)),
+            Err(DocError::EntryNotFound) => false,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+        statuses.push(DocAnchorStatus { anchor, matches_current });
+    }
+
+    Ok(Json(ListDocAnchorsResponse { doc_id: query.doc_id, anchors: statuses }))
+}
+
+// Handler for issuing a Verifiable Credential for an entry of a document with a schema
+pub async fn issue_credential_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<IssueCredentialRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Credentials are issued in the node's name, so only the default author can request them
+    let default_author = core::authors::get_default_author(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if caller_author_id != default_author {
+        return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
+    }
+
+    let key_type = state.key_types.get(&payload.key_type).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let keystore = StarterkitKeystore::from_inner(state.cord_signer.keystore.clone());
+    let public = match &payload.public {
+        Some(public) => decode_public(public).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
+        None => keystore
+            .get_public_key(&key_type)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+    };
+
+    let issued = issue_entry_credential(
+        state.docs.clone(),
+        state.blobs.clone(),
+        payload.doc_id,
+        payload.author_id,
+        payload.key,
+        payload.holder,
+        &keystore,
+        &key_type,
+        &public,
+    )
+    .await;
+    // only a successful credential or a failed signature involved the key
+    if matches!(issued, Ok(_) | Err(DocError::FailedToIssueCredential(_))) {
+        record_signature(&state, &headers, &key_type, KeyPurpose::DocSigning, &public, issued.is_ok())?;
+    }
+
+    match issued {
+        Ok(credential) => Ok(Json(credential)),
+        Err(
+            e @ (DocError::DocumentHasNoSchema
+            | DocError::EntryNotFound
+            | DocError::EntryNotJsonObject
+            | DocError::ValueDoesNotMatchSchema
+            | DocError::FailedToIssueCredential(_)),
+        ) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for mirroring CORD space, schema and registry definitions into a document
+pub async fn sync_cord_definitions_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<SyncCordDefinitionsRequest>,
+) -> Result<Json<SyncCordDefinitionsResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Mirrors are written by the default author, so peers can tell them from user entries
+    let default_author = core::authors::get_default_author(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if caller_author_id != default_author {
+        return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
+    }
+
+    let definitions = match payload.definitions {
+        Some(definitions) => Some(
+            definitions
+                .into_iter()
+                .map(|definition| ChainDefinitionKind::parse(&definition.kind).map(|kind| (kind, definition.id)))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| (StatusCode::BAD_REQUEST, e))?,
+        ),
+        None => None,
+    };
+
+    match sync_chain_definitions(
+        state.docs.clone(),
+        state.blobs.clone(),
+        payload.doc_id.clone(),
+        caller_author_id,
+        definitions,
+        state.cord_client.clone(),
+    )
+    .await
+    {
+        Ok(definitions) => Ok(Json(SyncCordDefinitionsResponse { doc_id: payload.doc_id, definitions })),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for registering a document's schema on CORD and mirroring the new schema definition
+pub async fn push_schema_to_cord_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<PushSchemaToCordRequest>,
+) -> Result<Json<DefinitionSync>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Schemas are created by the node's account, so only the default author can push them
+    let default_author = core::authors::get_default_author(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if caller_author_id != default_author {
+        return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
+    }
+
+    match push_doc_schema(
+        state.docs.clone(),
+        state.blobs.clone(),
+        payload.doc_id,
+        caller_author_id,
+        payload.authorization,
+        state.chain.clone(),
+    )
+    .await
+    {
+        Ok(synced) => Ok(Json(synced)),
+        Err(e @ (DocError::DocumentHasNoSchema | DocError::SchemaNotCordCompatible)) => {
+            Err((StatusCode::BAD_REQUEST, e.to_string()))
+        },
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for revoking an anchor or credential of a document, locally and on chain
+pub async fn revoke_statement_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<RevokeStatementRequest>,
+) -> Result<Json<StatementRevocation>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Revocations are chain transactions paid by the node's account, so only the default author
+    // can submit them
+    let default_author = core::authors::get_default_author(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if caller_author_id != default_author {
+        return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
+    }
+
+    match revoke_statement(
+        state.docs.clone(),
+        state.blobs.clone(),
+        payload.doc_id,
+        caller_author_id,
+        payload.statement,
+        payload.reason,
+        state.anchor.clone(),
+    )
+    .await
+    {
+        Ok(revocation) => Ok(Json(revocation)),
+        Err(e @ DocError::AnchorNotFound) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e @ DocError::StatementAlreadyRevoked) => Err((StatusCode::CONFLICT, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for checking whether an anchor or credential of a document is revoked
+pub async fn statement_status_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<StatementStatusQuery>,
+) -> Result<Json<StatementStatusResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let revocation = get_statement_revocation(state.docs.clone(), state.blobs.clone(), query.doc_id, &query.statement_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let revoked_on_chain = match &revocation {
+        Some(revocation) => {
+            let remark = state
+                .anchor
+                .remark_at(&revocation.block_hash, revocation.extrinsic_index)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            Some(remark.is_some_and(|remark| format!("0x{}", hex::encode(remark)) == revocation.digest))
+        },
+        None => None,
+    };
+
+    Ok(Json(StatementStatusResponse {
+        statement_id: query.statement_id,
+        status: if revocation.is_some() { "revoked" } else { "active" }.to_string(),
+        revocation,
+        revoked_on_chain,
+    }))
+}
+
+// Handler for adding a CORD schema, given as JSON or by its identifier on chain, as a document
+// schema
+pub async fn import_cord_schema_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<ImportCordSchemaRequest>,
+) -> Result<Json<ImportCordSchemaResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Check if the calling author is in the list of authors
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((StatusCode::FORBIDDEN, "Only a registered author can perform this action".to_string()));
+    }
+
+    let cord_schema = match (payload.schema, payload.schema_id) {
+        (Some(schema), None) => schema,
+        (None, Some(schema_id)) => fetch_cord_schema(state.cord_client.clone(), &schema_id)
+            .await
+            .map_err(|e| match e {
+                DocError::CordSchemaNotFound => (StatusCode::NOT_FOUND, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            })?,
+        _ => return Err((StatusCode::UNPROCESSABLE_ENTITY, "Exactly one of schema and schema_id must be given".to_string())),
+    };
+
+    match import_cord_schema(state.docs.clone(), state.blobs.clone(), payload.author_id, payload.doc_id, cord_schema).await {
+        Ok((updated_hash, schema)) => Ok(Json(ImportCordSchemaResponse { updated_hash, schema })),
+        Err(
+            e @ (DocError::InvalidCordSchema
+            | DocError::FailedToValidateSchema(_)
+            | DocError::DocumentNotEmpty),
+        ) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for converting a document's schema to a CORD schema
+pub async fn export_cord_schema_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ExportCordSchemaQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    match export_cord_schema(state.docs.clone(), state.blobs.clone(), query.doc_id, query.author_id, query.title).await {
+        Ok(schema) => Ok(Json(schema)),
+        Err(e @ (DocError::DocumentHasNoSchema | DocError::SchemaNotCordCompatible)) => {
+            Err((StatusCode::BAD_REQUEST, e.to_string()))
+        },
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for restricting the content a document downloads to entries under key prefixes
+pub async fn set_sync_prefixes_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<SetSyncPrefixesRequest>,
+) -> Result<Json<SyncPrefixesResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Check if the calling author is in the list of authors
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((StatusCode::FORBIDDEN, "Only a registered author can perform this action".to_string()));
+    }
+
+    match set_sync_prefixes(state.docs.clone(), payload.doc_id.clone(), payload.prefixes.clone()).await {
+        Ok(()) => Ok(Json(SyncPrefixesResponse { doc_id: payload.doc_id, prefixes: Some(payload.prefixes) })),
+        Err(e @ (DocError::InvalidSyncPrefix | DocError::InvalidDocumentIdFormat)) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e @ DocError::DocumentNotFound) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for getting the key prefixes a document downloads content for
+pub async fn get_sync_prefixes_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SyncPrefixesQuery>,
+) -> Result<Json<SyncPrefixesResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    match get_sync_prefixes(state.docs.clone(), query.doc_id.clone()).await {
+        Ok(prefixes) => Ok(Json(SyncPrefixesResponse { doc_id: query.doc_id, prefixes })),
+        Err(e @ DocError::InvalidDocumentIdFormat) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e @ DocError::DocumentNotFound) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Maps a content policy error to the response status
+fn pinning_error_response(e: PinningError) -> (StatusCode, String) {
+    let status = match &e {
+        PinningError::InvalidDocumentIdFormat
+        | PinningError::InvalidAuthorIdFormat
+        | PinningError::InvalidNodeId
+        | PinningError::InvalidSyncPrefix => StatusCode::BAD_REQUEST,
+        PinningError::DocumentNotFound => StatusCode::NOT_FOUND,
+        PinningError::ContentPolicyNotDownloaded => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+// Handler for setting which nodes download the content of a document. The policy is applied to
+// this node right away, and its missing content fetched in the background.
+pub async fn set_content_policy_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<SetContentPolicyRequest>,
+) -> Result<Json<ContentPolicyResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Check if the calling author is in the list of authors
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((StatusCode::FORBIDDEN, "Only a registered author can perform this action".to_string()));
+    }
+
+    set_content_policy(state.docs.clone(), payload.doc_id.clone(), caller_author_id, &payload.policy)
+        .await
+        .map_err(pinning_error_response)?;
+
+    let node_id = state.blobs.endpoint().node_id().to_string();
+    let outcome = apply_content_policy(state.docs.clone(), state.blobs.clone(), &node_id, &payload.doc_id, false)
+        .await
+        .map_err(pinning_error_response)?;
+    let (docs, blobs, fetch_node_id, doc_id) = (state.docs.clone(), state.blobs.clone(), node_id.clone(), payload.doc_id.clone());
+    tokio::spawn(async move {
+        let _ = apply_content_policy(docs, blobs, &fetch_node_id, &doc_id, true).await;
+    });
+
+    Ok(Json(ContentPolicyResponse {
+        doc_id: payload.doc_id,
+        node_id,
+        policy: Some(payload.policy),
+        mode: outcome.map(|outcome| outcome.mode),
+    }))
+}
+
+// Handler for getting the content policy of a document and the mode it sets for this node
+pub async fn get_content_policy_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ContentPolicyQuery>,
+) -> Result<Json<ContentPolicyResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let policy = get_content_policy(state.docs.clone(), state.blobs.clone(), &query.doc_id)
+        .await
+        .map_err(pinning_error_response)?;
+    let node_id = state.blobs.endpoint().node_id().to_string();
+    let mode = policy.as_ref().map(|policy| policy.mode_for(&node_id).clone());
+
+    Ok(Json(ContentPolicyResponse { doc_id: query.doc_id, node_id, policy, mode }))
+}
+
+// Checks that the caller may change the sync controls of `doc_id` and that the document exists
+async fn check_sync_controls_access(state: &AppState, headers: &HeaderMap, doc_id: &str) -> Result<(), (StatusCode, String)> {
+    let caller_author_id = get_author_id_from_headers(headers)?;
+
+    // Check if the calling author is in the list of authors
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((StatusCode::FORBIDDEN, "Only a registered author can perform this action".to_string()));
+    }
+
+    let namespace_id = decode_doc_id(doc_id)
+        .map_err(|_| (StatusCode::BAD_REQUEST, DocError::InvalidDocumentIdFormat.to_string()))?;
+    match get_document(state.docs.clone(), NamespaceId::from(namespace_id)).await {
+        Ok(_) => Ok(()),
+        Err(e @ DocError::DocumentNotFound) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for stopping a document's sync until it is resumed
+pub async fn pause_sync_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<PauseSyncRequest>,
+) -> Result<Json<SyncControlStatus>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_sync_controls_access(&state, &headers, &payload.doc_id).await?;
+
+    let mut control = state.sync_controls.get(&payload.doc_id);
+    control.paused = true;
+    state.sync_controls
+        .set(&payload.doc_id, control)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(state.sync_controls.status(&payload.doc_id)))
+}
+
+// Handler for resuming a paused document's sync, within its windows and bandwidth limit
+pub async fn resume_sync_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<ResumeSyncRequest>,
+) -> Result<Json<SyncControlStatus>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_sync_controls_access(&state, &headers, &payload.doc_id).await?;
+
+    let mut control = state.sync_controls.get(&payload.doc_id);
+    control.paused = false;
+    state.sync_controls
+        .set(&payload.doc_id, control)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(state.sync_controls.status(&payload.doc_id)))
+}
+
+// Handler for setting the time windows and bandwidth limit of a document's sync
+pub async fn set_sync_controls_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<SetSyncControlsRequest>,
+) -> Result<Json<SyncControlStatus>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_sync_controls_access(&state, &headers, &payload.doc_id).await?;
+
+    let mut control = state.sync_controls.get(&payload.doc_id);
+    control.windows = payload.windows;
+    control.max_bytes_per_minute = payload.max_bytes_per_minute;
+    state.sync_controls
+        .set(&payload.doc_id, control)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    Ok(Json(state.sync_controls.status(&payload.doc_id)))
+}
+
+// Handler for getting a document's sync controls and whether they let it sync right now
+pub async fn get_sync_controls_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SyncControlsQuery>,
+) -> Result<Json<SyncControlStatus>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    if decode_doc_id(&query.doc_id).is_err() {
+        return Err((StatusCode::BAD_REQUEST, DocError::InvalidDocumentIdFormat.to_string()));
+    }
+
+    Ok(Json(state.sync_controls.status(&query.doc_id)))
+}
+
+// Flattens entry details into the API's entry shape
+fn entry_response(details: EntryDetails) -> GetEntryResponse {
+    GetEntryResponse {
+        doc: details.namespace.doc,
+        key: details.namespace.key,
+        author: details.namespace.author,
+        hash: details.record.hash,
+        len: details.record.len,
+        timestamp: details.record.timestamp,
+        anchor: details.record.anchor,
+    }
+}
+
+// Handler for listing the keys of a document that several authors wrote
+pub async fn list_conflicts_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ListConflictsQuery>,
+) -> Result<Json<ListConflictsResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    match list_conflicts(state.docs.clone(), query.doc_id.clone(), query.key_prefix).await {
+        Ok(conflicts) => Ok(Json(ListConflictsResponse {
+            doc_id: query.doc_id,
+            conflicts: conflicts
+                .into_iter()
+                .map(|conflict| KeyConflictResponse {
+                    key: conflict.key,
+                    versions: conflict.versions.into_iter().map(entry_response).collect(),
+                })
+                .collect(),
+        })),
+        Err(e @ DocError::InvalidDocumentIdFormat) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e @ DocError::DocumentNotFound) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for reading a key regardless of its author, resolving conflicts between authors
+pub async fn get_resolved_entry_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<GetResolvedEntryRequest>,
+) -> Result<Json<GetResolvedEntryResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let resolution = ConflictResolution::from_name(&payload.resolution, payload.author_priority)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    match get_resolved_entry(state.docs.clone(), payload.doc_id, payload.key, &resolution).await {
+        Ok(Some((entry, versions))) => Ok(Json(GetResolvedEntryResponse { entry: entry_response(entry), versions })),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Entry not found".to_string())),
+        Err(e @ (DocError::InvalidDocumentIdFormat | DocError::FailedToValidateKey(_))) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e @ DocError::DocumentNotFound) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Maps an archive error to the response status
+fn archive_error_response(e: ArchiveError) -> (StatusCode, String) {
+    let status = match &e {
+        ArchiveError::InvalidDocumentIdFormat
+        | ArchiveError::InvalidArchive
+        | ArchiveError::UnsupportedArchiveFormat
+        | ArchiveError::BlockHashMismatch
+        | ArchiveError::MissingEntryBlock => StatusCode::BAD_REQUEST,
+        ArchiveError::DocumentNotFound => StatusCode::NOT_FOUND,
+        ArchiveError::DocumentNotEmpty => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+// Handler for streaming a document and its content as a CAR archive
+pub async fn export_doc_archive_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(doc_id): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    // Fail before the response starts if the document cannot be exported
+    let namespace_id = decode_doc_id(&doc_id)
+        .map_err(|_| archive_error_response(ArchiveError::InvalidDocumentIdFormat))?;
+    get_document(state.docs.clone(), NamespaceId::from(namespace_id))
+        .await
+        .map_err(|_| archive_error_response(ArchiveError::DocumentNotFound))?;
+
+    let (mut writer, reader) = tokio::io::duplex(64 * 1024);
+    let filename = format!("{}.car", doc_id);
+    tokio::spawn(async move {
+        // An error ends the stream early, which the client sees as a truncated archive
+        let _ = export_doc_archive(state.docs.clone(), state.blobs.clone(), doc_id, &mut writer).await;
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/vnd.ipld.car")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename))
+        .body(Body::from_stream(ReaderStream::new(reader)))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// Handler for importing a document archive into a new or empty document
+pub async fn import_doc_archive_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ImportDocArchiveQuery>,
+    body: Body,
+) -> Result<Json<DocArchiveImport>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Check if the calling author is in the list of authors
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only a registered author can perform this action".to_string(),
+        ));
+    }
+
+    let mut reader = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+    let imported = import_doc_archive(state.docs.clone(), state.blobs.clone(), &mut reader, query.doc_id, caller_author_id)
+        .await
+        .map_err(archive_error_response)?;
+
+    Ok(Json(imported))
+}
+
+// Handler for sharing several documents as one bundle ticket, signed by the calling author
+pub async fn share_doc_bundle_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<ShareDocBundleRequest>,
+) -> Result<Json<ShareDocBundleResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+    let mode = parse_share_mode(&payload.mode)?;
+    let addr_options = parse_addr_options(&payload.addr_options)?;
+
+    match share_doc_bundle(state.docs.clone(), payload.doc_ids, mode, addr_options, caller_author_id, payload.label).await {
+        Ok(ticket) => Ok(Json(ShareDocBundleResponse { ticket })),
+        Err(e @ (DocError::InvalidDocumentIdFormat | DocError::EmptyDocBundle)) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+        Err(e @ (DocError::InvalidAuthorIdFormat | DocError::AuthorNotFound)) => Err((StatusCode::FORBIDDEN, e.to_string())),
+        Err(e @ DocError::DocumentNotFound) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for joining every document of a bundle ticket
+pub async fn join_doc_bundle_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<JoinDocBundleRequest>,
+) -> Result<Json<JoinDocBundleResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    match join_doc_bundle(state.docs.clone(), payload.ticket, payload.issuer, payload.sync_prefixes).await {
+        Ok(bundle) => {
+            let doc_ids: Vec<String> = bundle.docs.into_iter().map(|doc| doc.doc_id).collect();
+            for doc_id in &doc_ids {
+                state.events.publish(NodeEvent::DocJoined { doc_id: doc_id.clone() });
+            }
+            Ok(Json(JoinDocBundleResponse { issuer: bundle.issuer, label: bundle.label, doc_ids }))
+        },
+        Err(e @ (DocError::InvalidDocBundleFormat | DocError::InvalidSyncPrefix | DocError::InvalidAuthorIdFormat)) => {
+            Err((StatusCode::BAD_REQUEST, e.to_string()))
+        },
+        Err(e @ (DocError::InvalidDocBundleSignature | DocError::UnexpectedDocBundleIssuer)) => {
+            Err((StatusCode::FORBIDDEN, e.to_string()))
+        },
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for comparing the replica of a document on a peer with the local one
+pub async fn verify_replica_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<VerifyReplicaRequest>,
+) -> Result<Json<ReplicaReport>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((StatusCode::FORBIDDEN, "Only a registered author can perform this action".to_string()));
+    }
+
+    match verify_replica(&state.docs, &state.blobs, payload.doc_id, payload.peer_node_id).await {
+        Ok(report) => Ok(Json(report)),
+        Err(e @ (VerificationError::InvalidDocumentIdFormat | VerificationError::InvalidNodeIdFormat)) => {
+            Err((StatusCode::BAD_REQUEST, e.to_string()))
+        },
+        Err(e @ VerificationError::DocumentNotFound) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e @ (VerificationError::PeerUnreachable | VerificationError::PeerRefused | VerificationError::InvalidPeerResponse)) => {
+            Err((StatusCode::BAD_GATEWAY, e.to_string()))
+        },
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(timestamp: u64) -> EntryDetails {
+        EntryDetails {
+            namespace: EntryIdDetails { doc: "doc".to_string(), key: "key".to_string(), author: "author".to_string() },
+            record: RecordDetails { hash: "hash".to_string(), len: 1, timestamp, anchor: None },
+        }
+    }
+
+    #[test]
+    fn test_event_ids_are_monotonic_per_stream() {
+        let mut position = EventPosition::default();
+        assert_eq!(position.advance(&entry(20)), "20-1");
+        // an entry synced from a peer with an older timestamp does not move the position back
+        assert_eq!(position.advance(&entry(10)), "20-2");
+        assert_eq!(position.advance(&entry(20)), "20-3");
+        assert_eq!(position.advance(&entry(30)), "30-4");
+
+        // a reconnecting client carries on from its last event
+        let mut resumed = EventPosition::parse("30-4").unwrap();
+        assert_eq!(resumed, EventPosition { timestamp: 30, sequence: 4 });
+        assert_eq!(resumed.advance(&entry(40)), "40-5");
+
+        assert_eq!(EventPosition::parse("30"), Some(EventPosition { timestamp: 30, sequence: 0 }));
+        assert_eq!(EventPosition::parse("30-x"), None);
+        assert_eq!(EventPosition::parse("latest"), None);
+    }
+}
@@ -0,0 +1,152 @@
+use helpers::{federation::FederationMember, state::AppState, utils::get_author_id_from_headers};
+use gateway::access_control::check_node_id_and_domain_header;
+use core::federation::{add_to_catalog, remove_from_catalog, CatalogEntry, FederationError};
+use crate::docs_handler::{parse_addr_options, parse_share_mode};
+use crate::{request_schema, validation::{self, ValidatedJson}};
+
+use serde::{Deserialize, Serialize};
+use axum::{extract::State, Json};
+use axum::http::{StatusCode, HeaderMap};
+
+// Request bodies
+// 1. federation status
+// No request body
+
+// 2. add to catalog
+#[derive(Deserialize)]
+pub struct AddToCatalogRequest {
+    pub doc_id: String,
+    pub mode: String,
+    pub addr_options: String,
+    /// Member nodes only download the content of entries under these key prefixes.
+    #[serde(default)]
+    pub sync_prefixes: Vec<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+request_schema!(AddToCatalogRequest, {
+    "doc_id": validation::doc_id(),
+    "mode": validation::one_of(&["read", "write"]),
+    "addr_options": validation::non_empty(),
+});
+
+// 3. remove from catalog
+#[derive(Deserialize)]
+pub struct RemoveFromCatalogRequest {
+    pub doc_id: String,
+}
+request_schema!(RemoveFromCatalogRequest, {
+    "doc_id": validation::doc_id(),
+});
+
+// Response bodies
+// 1. federation status
+#[derive(Serialize)]
+pub struct FederationStatusResponse {
+    /// Document ID of the catalog, `null` until it is open.
+    pub catalog_doc_id: Option<String>,
+    pub trusted_authors: Vec<String>,
+    /// Seconds between checks of the catalog.
+    pub interval: u64,
+    /// Documents of the catalog, as this node replicates them.
+    pub members: Vec<FederationMember>,
+}
+
+// 2. add to catalog
+// CatalogEntry
+
+// 3. remove from catalog
+#[derive(Serialize)]
+pub struct RemoveFromCatalogResponse {
+    pub message: String,
+}
+
+// Maps a federation error to the response status
+fn federation_error_response(e: FederationError) -> (StatusCode, String) {
+    let status = match &e {
+        FederationError::NotConfigured | FederationError::DocumentNotFound | FederationError::EntryNotFound => StatusCode::NOT_FOUND,
+        FederationError::AuthorNotTrusted => StatusCode::FORBIDDEN,
+        FederationError::CatalogNotReady => StatusCode::SERVICE_UNAVAILABLE,
+        FederationError::InvalidAuthorIdFormat | FederationError::InvalidDocumentIdFormat => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+// Catalog entries are signed by an author of this node, which must also be trusted
+async fn check_registered_author(state: &AppState, headers: &HeaderMap) -> Result<String, (StatusCode, String)> {
+    let caller_author_id = get_author_id_from_headers(headers)?;
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((StatusCode::FORBIDDEN, "Only a registered author can perform this action".to_string()));
+    }
+    Ok(caller_author_id)
+}
+
+// Handler for the catalog this node follows and the documents it replicates through it
+pub async fn federation_status_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<FederationStatusResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    if !state.federation.is_enabled() {
+        return Err(federation_error_response(FederationError::NotConfigured));
+    }
+    let settings = state.federation.settings();
+
+    Ok(Json(FederationStatusResponse {
+        catalog_doc_id: state.federation.catalog_doc_id(),
+        trusted_authors: settings.trusted_authors.clone(),
+        interval: settings.interval.as_secs(),
+        members: state.federation.members().into_values().collect(),
+    }))
+}
+
+// Handler for listing a document in the catalog, so that member nodes join it
+pub async fn add_to_catalog_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<AddToCatalogRequest>,
+) -> Result<Json<CatalogEntry>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    let caller_author_id = check_registered_author(&state, &headers).await?;
+
+    let mode = parse_share_mode(&payload.mode)?;
+    let addr_options = parse_addr_options(&payload.addr_options)?;
+
+    let entry = add_to_catalog(
+        state.docs.clone(),
+        &state.federation,
+        caller_author_id,
+        payload.doc_id,
+        mode,
+        addr_options,
+        payload.sync_prefixes,
+        payload.label,
+    )
+    .await
+    .map_err(federation_error_response)?;
+
+    Ok(Json(entry))
+}
+
+// Handler for deleting the caller's catalog entry for a document
+pub async fn remove_from_catalog_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<RemoveFromCatalogRequest>,
+) -> Result<Json<RemoveFromCatalogResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    let caller_author_id = check_registered_author(&state, &headers).await?;
+
+    remove_from_catalog(state.docs.clone(), &state.federation, caller_author_id, payload.doc_id)
+        .await
+        .map_err(federation_error_response)?;
+
+    Ok(Json(RemoveFromCatalogResponse {
+        message: "Catalog entry removed".to_string(),
+    }))
+}
@@ -6,6 +6,7 @@ use gateway::access_control::{
     add_domain,
     remove_domain
 };
+use crate::{request_schema, validation::{self, ValidatedJson}};
 use helpers::{
     state::AppState,
     utils::normalize_domain,
@@ -24,36 +25,54 @@ use regex::Regex;
 pub struct IsNodeIdAllowedRequest {
     pub node_id: String,
 }
+request_schema!(IsNodeIdAllowedRequest, {
+    "node_id": validation::node_id(),
+});
 
 // 2. is_domain_allowed
 #[derive(Deserialize)]
 pub struct IsDomainAllowedRequest {
     pub domain: String,
 }
+request_schema!(IsDomainAllowedRequest, {
+    "domain": validation::non_empty(),
+});
 
 // 3. add_node_id
 #[derive(Deserialize)]
 pub struct AddNodeIdRequest {
     pub node_id: String,
 }
+request_schema!(AddNodeIdRequest, {
+    "node_id": validation::node_id(),
+});
 
 // 4. remove_node_id
 #[derive(Deserialize)]
 pub struct RemoveNodeIdRequest {
     pub node_id: String,
 }
+request_schema!(RemoveNodeIdRequest, {
+    "node_id": validation::node_id(),
+});
 
 // 5. add_domain
 #[derive(Deserialize)]
 pub struct AddDomainRequest {
     pub domain: String,
 }
+request_schema!(AddDomainRequest, {
+    "domain": validation::non_empty(),
+});
 
 // 6. remove_domain
 #[derive(Deserialize)]
 pub struct RemoveDomainRequest {
     pub domain: String,
 }
+request_schema!(RemoveDomainRequest, {
+    "domain": validation::non_empty(),
+});
 
 // Response bodies
 // 1. is_node_id_allowed
@@ -94,7 +113,7 @@ pub struct RemoveDomainResponse {
 
 // Handler for checking if a node ID is allowed
 pub async fn is_node_id_allowed_handler(
-    Json(req): Json<IsNodeIdAllowedRequest>
+    ValidatedJson(req): ValidatedJson<IsNodeIdAllowedRequest>
 ) -> Result<Json<IsNodeIdAllowedResponse>, (StatusCode, String)> {
     if req.node_id.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "nodeId cannot be empty".to_string()));
@@ -110,7 +129,7 @@ pub async fn is_node_id_allowed_handler(
 
 // Handler for checking if a domain is allowed
 pub async fn is_domain_allowed_handler(
-    Json(req): Json<IsDomainAllowedRequest>
+    ValidatedJson(req): ValidatedJson<IsDomainAllowedRequest>
 ) -> Result<Json<IsDomainAllowedResponse>, (StatusCode, String)> {
     if req.domain.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "domain cannot be empty".to_string()));
@@ -131,7 +150,7 @@ pub async fn is_domain_allowed_handler(
 // Handler for adding a node ID
 #[debug_handler]
 pub async fn add_node_id_handler(
-    Json(req): Json<AddNodeIdRequest>
+    ValidatedJson(req): ValidatedJson<AddNodeIdRequest>
 ) -> Result<Json<AddNodeIdResponse>, (StatusCode, String)> {
     if req.node_id.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "nodeId cannot be empty".to_string()));
@@ -146,7 +165,7 @@ pub async fn add_node_id_handler(
 
 // Handler for removing a node ID
 pub async fn remove_node_id_handler(
-    Json(req): Json<RemoveNodeIdRequest>
+    ValidatedJson(req): ValidatedJson<RemoveNodeIdRequest>
 ) -> Result<Json<RemoveNodeIdResponse>, (StatusCode, String)> {
     if req.node_id.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "nodeId cannot be empty".to_string()));
@@ -161,7 +180,7 @@ pub async fn remove_node_id_handler(
 
 // Handler for adding a domain
 pub async fn add_domain_handler(
-    Json(req): Json<AddDomainRequest>
+    ValidatedJson(req): ValidatedJson<AddDomainRequest>
 ) -> Result<Json<AddDomainResponse>, (StatusCode, String)> {
     if req.domain.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "domain cannot be empty".to_string()));
@@ -181,7 +200,7 @@ pub async fn add_domain_handler(
 
 // Handler for removing a domain
 pub async fn remove_domain_handler(
-    Json(req): Json<RemoveDomainRequest>
+    ValidatedJson(req): ValidatedJson<RemoveDomainRequest>
 ) -> Result<Json<RemoveDomainResponse>, (StatusCode, String)> {
     if req.domain.is_empty() {
         return Err((StatusCode::BAD_REQUEST, "domain cannot be empty".to_string()));
@@ -0,0 +1,90 @@
+use helpers::{jobs::{JobError, JobKind, JobStatus, JobTrigger}, state::AppState};
+use gateway::access_control::check_node_id_and_domain_header;
+use crate::backups_handler::check_default_author;
+
+use serde::Serialize;
+use axum::{extract::{Path, State}, Json};
+use axum::http::{StatusCode, HeaderMap};
+
+// Request bodies
+// 1. list jobs
+// No request body
+
+// 2. get job
+// No request body
+
+// 3. run job
+// No request body
+
+// Response bodies
+// 1. list jobs
+#[derive(Serialize)]
+pub struct ListJobsResponse {
+    pub jobs: Vec<JobStatus>,
+}
+
+// 2. get job
+// JobStatus
+
+// 3. run job
+#[derive(Serialize)]
+pub struct RunJobResponse {
+    pub name: JobKind,
+    /// When the run started, in seconds since the Unix epoch.
+    pub started_at: u64,
+}
+
+// Maps a job error to the response status
+fn job_error_response(e: JobError) -> (StatusCode, String) {
+    match e {
+        JobError::NotFound => (StatusCode::NOT_FOUND, "Job not found".to_string()),
+        JobError::AlreadyRunning => (StatusCode::CONFLICT, "The job is already running".to_string()),
+    }
+}
+
+// The job called `name`, a 404 for an unknown name
+fn parse_job_name(name: &str) -> Result<JobKind, (StatusCode, String)> {
+    name.parse().map_err(|_| job_error_response(JobError::NotFound))
+}
+
+// Handler for listing the maintenance jobs with their schedules and last runs. Jobs take
+// snapshots and delete entries for good, so like backups only the default author manages them.
+pub async fn list_jobs_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ListJobsResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    Ok(Json(ListJobsResponse { jobs: state.jobs.list() }))
+}
+
+// Handler for getting one job, to follow a run started on request
+pub async fn get_job_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<Json<JobStatus>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let kind = parse_job_name(&name)?;
+    let job = state.jobs.get(kind).ok_or_else(|| job_error_response(JobError::NotFound))?;
+
+    Ok(Json(job))
+}
+
+// Handler for running a job now. The run continues in the background.
+pub async fn run_job_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<(StatusCode, Json<RunJobResponse>), (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let kind = parse_job_name(&name)?;
+    let started_at = state.jobs.start(kind, JobTrigger::Manual).map_err(job_error_response)?;
+
+    Ok((StatusCode::ACCEPTED, Json(RunJobResponse { name: kind, started_at })))
+}
@@ -0,0 +1,517 @@
+use helpers::{state::AppState, tenants::{OperatorConnection, Tenant}, utils::{get_author_id_from_headers, get_caller_from_headers}, pagination::{Paginated, PaginationParams}};
+use gateway::access_control::check_node_id_and_domain_header;
+use keystore::audit::{AuditEvent, AuditFilter, AuditOperation, AuditRecord};
+use keystore::keystore::{decode_public, encode_public, KeyScheme, MessageFormat, StarterkitKeystore, KEY_SCHEME_NAMES};
+use keystore::registry::{KeyPurpose, KeyTypeInfo, KEY_PURPOSE_NAMES};
+use crate::{request_schema, validation::{self, ValidatedJson}};
+
+use core::authors::get_default_author;
+use axum::{extract::{Query, State}, Extension, Json, http::{HeaderMap, StatusCode}};
+use serde::{Deserialize, Serialize};
+
+// Request bodies
+// 1. sign payload
+#[derive(Deserialize)]
+pub struct SignPayloadRequest {
+    /// Name of a key type in the registry, e.g. `sign` or `ecdsa`.
+    pub key_type: String,
+    /// SS58 or 0x-prefixed hex public key. Omit to use the node's key of `key_type`.
+    pub public: Option<String>,
+    pub data: String,
+    /// `raw` (default) signs `data` as is, `bytes` signs it wrapped in `<Bytes>...</Bytes>` like
+    /// polkadot.js and Nova wallets do.
+    pub format: Option<String>,
+}
+request_schema!(SignPayloadRequest, {
+    "key_type": validation::non_empty(),
+    "data": validation::non_empty(),
+});
+
+// 2. verify payload
+#[derive(Deserialize)]
+pub struct VerifyPayloadRequest {
+    pub key_type: String,
+    pub public: String,
+    pub data: String,
+    /// 0x-prefixed hex signature.
+    pub signature: String,
+    /// Set to the `timestamp` returned by `/keystore/sign` to check one of its signatures.
+    pub timestamp: Option<u64>,
+    /// How `data` is encoded: `utf8` (default) or `hex`.
+    pub encoding: Option<String>,
+    /// `raw` or `bytes`. Omit to accept a signature in either format.
+    pub format: Option<String>,
+}
+request_schema!(VerifyPayloadRequest, {
+    "key_type": validation::non_empty(),
+    "public": validation::non_empty(),
+    "data": validation::non_empty(),
+    "signature": validation::non_empty(),
+});
+
+// 3. list keys
+#[derive(Deserialize)]
+pub struct ListKeysQuery {
+    pub key_type: String,
+}
+
+// 4. generate key
+#[derive(Deserialize)]
+pub struct GenerateKeyRequest {
+    pub key_type: String,
+    /// Derivation path such as `//payments//1`, applied to the freshly generated phrase.
+    pub derivation_path: Option<String>,
+}
+request_schema!(GenerateKeyRequest, {
+    "key_type": validation::non_empty(),
+});
+
+// 5. has key
+#[derive(Deserialize)]
+pub struct HasKeyRequest {
+    pub key_type: String,
+    pub public: String,
+}
+request_schema!(HasKeyRequest, {
+    "key_type": validation::non_empty(),
+    "public": validation::non_empty(),
+});
+
+// 6. register key type
+#[derive(Deserialize)]
+pub struct RegisterKeyTypeRequest {
+    /// API name of the key type, e.g. `payments`.
+    pub name: String,
+    /// The 4-character KeyTypeId the keys are stored under, e.g. `paym`.
+    pub id: String,
+    pub scheme: String,
+    pub purposes: Vec<String>,
+}
+request_schema!(RegisterKeyTypeRequest, {
+    "name": validation::non_empty(),
+    "id": validation::non_empty(),
+    "scheme": validation::one_of(&KEY_SCHEME_NAMES),
+    "purposes": validation::non_empty_array(validation::one_of(&KEY_PURPOSE_NAMES)),
+});
+
+// 7. list key types
+// No request body
+
+// 8. sign
+#[derive(Deserialize)]
+pub struct SignRequest {
+    pub key_type: String,
+    /// SS58 or 0x-prefixed hex public key. Omit to use the node's key of `key_type`.
+    pub public: Option<String>,
+    pub data: String,
+    /// How `data` is encoded: `utf8` (default) or `hex`.
+    pub encoding: Option<String>,
+}
+request_schema!(SignRequest, {
+    "key_type": validation::non_empty(),
+    "data": validation::non_empty(),
+});
+
+// 9. audit log
+#[derive(Deserialize)]
+pub struct AuditLogQuery {
+    /// `sign` or `decrypt`.
+    pub operation: Option<String>,
+    pub key_type: Option<String>,
+    /// SS58 public key, or `<doc_id>/v<version>` for data encryption keys.
+    pub key: Option<String>,
+    /// e.g. `author:<id>`, `node:<id>` or `node`.
+    pub caller: Option<String>,
+    /// Unix time in seconds.
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+}
+
+// Response bodies
+// 1. sign payload
+#[derive(Serialize)]
+pub struct SignPayloadResponse {
+    pub key_type: String,
+    /// SS58-encoded public key that made the signature.
+    pub public: String,
+    pub signature: String,
+    /// The format `data` was signed in.
+    pub format: MessageFormat,
+}
+
+// 2. verify payload
+#[derive(Serialize)]
+pub struct VerifyPayloadResponse {
+    pub is_valid: bool,
+    /// The format the signature is valid in. Absent if it is not valid.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<MessageFormat>,
+}
+
+// 3. list keys
+#[derive(Serialize)]
+pub struct ListKeysResponse {
+    pub key_type: String,
+    /// SS58-encoded public keys.
+    pub keys: Vec<String>,
+}
+
+// 4. generate key
+#[derive(Serialize)]
+pub struct GenerateKeyResponse {
+    pub key_type: String,
+    pub public: String,
+}
+
+// 5. has key
+#[derive(Serialize)]
+pub struct HasKeyResponse {
+    pub has_key: bool,
+}
+
+// 6. register key type
+// Responds with the registered `KeyTypeInfo`
+
+// 7. list key types
+#[derive(Serialize)]
+pub struct ListKeyTypesResponse {
+    pub key_types: Vec<KeyTypeInfo>,
+}
+
+// 8. sign
+#[derive(Serialize)]
+pub struct SignResponse {
+    pub key_type: String,
+    pub public: String,
+    /// 0x-prefixed blake2-256 hash of the payload.
+    pub hash: String,
+    /// Unix time in seconds at which the payload was signed.
+    pub timestamp: u64,
+    /// Signature over the hash followed by the timestamp as 8 big-endian bytes.
+    pub signature: String,
+}
+
+// 9. audit log
+// Responds with a `Paginated<AuditRecord>`, oldest first
+
+// Encodings accepted for `data`
+const DATA_ENCODINGS: [&str; 2] = ["utf8", "hex"];
+
+fn decode_data(data: &str, encoding: Option<&str>) -> Result<Vec<u8>, (StatusCode, String)> {
+    match encoding.unwrap_or("utf8") {
+        "utf8" => Ok(data.as_bytes().to_vec()),
+        "hex" => hex::decode(data.trim_start_matches("0x"))
+            .map_err(|_| (StatusCode::BAD_REQUEST, "data must be hex-encoded".to_string())),
+        other => Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown encoding '{}'. Expected one of: {}.", other, DATA_ENCODINGS.join(", ")),
+        )),
+    }
+}
+
+fn parse_format(format: Option<&str>) -> Result<Option<MessageFormat>, (StatusCode, String)> {
+    format
+        .map(MessageFormat::parse)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+// Records a signature by `public` in the keystore audit log. Fails if the record cannot be
+// written, so no signature leaves the node unrecorded.
+pub(crate) fn record_signature(
+    state: &AppState,
+    headers: &HeaderMap,
+    key_type: &KeyTypeInfo,
+    purpose: KeyPurpose,
+    public: &[u8],
+    success: bool,
+) -> Result<(), (StatusCode, String)> {
+    state
+        .audit
+        .record(AuditEvent {
+            operation: AuditOperation::Sign,
+            key_type: key_type.name.clone(),
+            key: encode_public(public),
+            purpose: Some(purpose.as_str().to_string()),
+            caller: get_caller_from_headers(headers),
+            success,
+        })
+        .map(|_| ())
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// Fails unless the request came from the node's operator: over a `--listen-uds` socket, without
+// a tenant's API key. Headers such as `author-id` are set by the caller, so they prove nothing.
+fn check_operator(
+    operator: Option<Extension<OperatorConnection>>,
+    tenant: Option<Extension<Tenant>>,
+) -> Result<(), (StatusCode, String)> {
+    if operator.is_none() || tenant.is_some() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only the node operator can perform this action, over a --listen-uds socket".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// handler for signing data with a keystore key
+pub async fn sign_payload_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    operator: Option<Extension<OperatorConnection>>,
+    tenant: Option<Extension<Tenant>>,
+    ValidatedJson(payload): ValidatedJson<SignPayloadRequest>,
+) -> Result<Json<SignPayloadResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    // Signatures speak for the node, so only its operator can request them
+    check_operator(operator, tenant)?;
+
+    let key_type = state.key_types.get(&payload.key_type).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    key_type.ensure_api_signable().map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    // Callers only ever get document signatures, never chain transactions or API credentials
+    let purpose = KeyPurpose::DocSigning;
+    let format = parse_format(payload.format.as_deref())?.unwrap_or(MessageFormat::Raw);
+    let keystore = StarterkitKeystore::from_inner(state.cord_signer.keystore.clone());
+    let public = match &payload.public {
+        Some(public) => decode_public(public).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
+        None => keystore
+            .get_public_key(&key_type)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+    };
+
+    let signed = keystore.sign_payload_as(&key_type, purpose, &public, payload.data.as_bytes(), format);
+    record_signature(&state, &headers, &key_type, purpose, &public, signed.is_ok())?;
+
+    match signed {
+        Ok(signature) => Ok(Json(SignPayloadResponse {
+            key_type: payload.key_type,
+            public: encode_public(&public),
+            signature: format!("0x{}", hex::encode(signature)),
+            format,
+        })),
+        Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+    }
+}
+
+// handler for checking a signature against a public key, made by the node or by a wallet
+pub async fn verify_payload_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<VerifyPayloadRequest>,
+) -> Result<Json<VerifyPayloadResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let key_type = state.key_types.get(&payload.key_type).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let public = decode_public(&payload.public).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let signature = hex::decode(payload.signature.trim_start_matches("0x"))
+        .map_err(|_| (StatusCode::BAD_REQUEST, "signature must be hex-encoded".to_string()))?;
+    let data = decode_data(&payload.data, payload.encoding.as_deref())?;
+    let format = parse_format(payload.format.as_deref())?;
+
+    let verified = match (payload.timestamp, format) {
+        // Timestamped signatures are only made by the node, over the raw hash and timestamp
+        (Some(_), Some(MessageFormat::Bytes)) => {
+            return Err((StatusCode::BAD_REQUEST, "Timestamped signatures are always in the raw format".to_string()));
+        },
+        (Some(timestamp), _) => StarterkitKeystore::verify_timestamped(&key_type, &public, &data, timestamp, &signature)
+            .map(|is_valid| is_valid.then_some(MessageFormat::Raw)),
+        (None, Some(format)) => StarterkitKeystore::verify_payload_formats(&key_type, &public, &data, &signature, &[format]),
+        (None, None) => StarterkitKeystore::verify_payload_formats(
+            &key_type,
+            &public,
+            &data,
+            &signature,
+            &[MessageFormat::Raw, MessageFormat::Bytes],
+        ),
+    };
+    match verified {
+        Ok(format) => Ok(Json(VerifyPayloadResponse { is_valid: format.is_some(), format })),
+        Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+    }
+}
+
+// handler for listing the public keys of a key type
+pub async fn list_keys_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ListKeysQuery>,
+) -> Result<Json<ListKeysResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let key_type = state.key_types.get(&query.key_type).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let keystore = StarterkitKeystore::from_inner(state.cord_signer.keystore.clone());
+
+    Ok(Json(ListKeysResponse {
+        key_type: query.key_type,
+        keys: keystore.list_public_keys(&key_type).iter().map(|public| encode_public(public)).collect(),
+    }))
+}
+
+// handler for generating a new signing key
+pub async fn generate_key_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<GenerateKeyRequest>,
+) -> Result<Json<GenerateKeyResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Only default author can add keys to the keystore
+    let default_author = get_default_author(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if caller_author_id != default_author {
+        return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
+    }
+
+    let key_type = state.key_types.get(&payload.key_type).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let keystore = StarterkitKeystore::from_inner(state.cord_signer.keystore.clone());
+
+    match keystore.generate_key(&key_type, payload.derivation_path.as_deref()) {
+        Ok(public) => Ok(Json(GenerateKeyResponse {
+            key_type: payload.key_type,
+            public: encode_public(&public),
+        })),
+        Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+    }
+}
+
+// handler for checking whether the keystore holds a key
+pub async fn has_key_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<HasKeyRequest>,
+) -> Result<Json<HasKeyResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let key_type = state.key_types.get(&payload.key_type).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let public = decode_public(&payload.public).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let keystore = StarterkitKeystore::from_inner(state.cord_signer.keystore.clone());
+
+    Ok(Json(HasKeyResponse { has_key: keystore.has_key(&key_type, &public) }))
+}
+
+// handler for registering a new key type with its purposes
+pub async fn register_key_type_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<RegisterKeyTypeRequest>,
+) -> Result<Json<KeyTypeInfo>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Only default author can change what the keystore's keys may be used for
+    let default_author = get_default_author(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if caller_author_id != default_author {
+        return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
+    }
+
+    let scheme = KeyScheme::parse(&payload.scheme).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let purposes = payload
+        .purposes
+        .iter()
+        .map(|purpose| KeyPurpose::parse(purpose))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let info = KeyTypeInfo { name: payload.name, id: payload.id, scheme, purposes, builtin: false };
+    match state.key_types.register(info) {
+        Ok(info) => Ok(Json(info)),
+        Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+    }
+}
+
+// handler for listing the key types known to the node
+pub async fn list_key_types_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ListKeyTypesResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    Ok(Json(ListKeyTypesResponse { key_types: state.key_types.list() }))
+}
+
+// handler for signing the hash of a payload together with a timestamp. Any caller the gateway
+// admits may use it, so the node can serve as a signing service; purposes still apply.
+pub async fn sign_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<SignRequest>,
+) -> Result<Json<SignResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let key_type = state.key_types.get(&payload.key_type).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    key_type.ensure_api_signable().map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    // Callers only ever get document signatures, never chain transactions or API credentials
+    let purpose = KeyPurpose::DocSigning;
+    let data = decode_data(&payload.data, payload.encoding.as_deref())?;
+    let keystore = StarterkitKeystore::from_inner(state.cord_signer.keystore.clone());
+    let public = match &payload.public {
+        Some(public) => decode_public(public).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?,
+        None => keystore
+            .get_public_key(&key_type)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+    };
+
+    let signed = keystore.sign_timestamped(&key_type, purpose, &public, &data);
+    record_signature(&state, &headers, &key_type, purpose, &public, signed.is_ok())?;
+
+    match signed {
+        Ok((hash, timestamp, signature)) => Ok(Json(SignResponse {
+            key_type: payload.key_type,
+            public: encode_public(&public),
+            hash: format!("0x{}", hex::encode(hash)),
+            timestamp,
+            signature: format!("0x{}", hex::encode(signature)),
+        })),
+        Err(e) => Err((StatusCode::BAD_REQUEST, e.to_string())),
+    }
+}
+
+// handler for querying the keystore audit log
+pub async fn audit_log_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AuditLogQuery>,
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<AuditRecord>>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Only default author can review how the node's keys were used
+    let default_author = get_default_author(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if caller_author_id != default_author {
+        return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
+    }
+
+    let operation = match query.operation.as_deref() {
+        None => None,
+        Some("sign") => Some(AuditOperation::Sign),
+        Some("decrypt") => Some(AuditOperation::Decrypt),
+        Some(other) => {
+            return Err((StatusCode::BAD_REQUEST, format!("Unknown operation '{}'. Expected one of: sign, decrypt.", other)))
+        }
+    };
+    let filter = AuditFilter {
+        operation,
+        key_type: query.key_type,
+        key: query.key,
+        caller: query.caller,
+        since: query.since,
+        until: query.until,
+    };
+
+    match state.audit.query(&filter) {
+        Ok(records) => Ok(Json(Paginated::from_items(records, &pagination)?)),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
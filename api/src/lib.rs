@@ -1,4 +1,28 @@
+pub mod access_log_handler;
+pub mod approvals_handler;
 pub mod authors_handler;
+pub mod backups_handler;
+pub mod batch_handler;
 pub mod blobs_handler;
+pub mod debug_handler;
+pub mod did_handler;
 pub mod docs_handler;
-pub mod gateway_handler;
\ No newline at end of file
+pub mod federation_handler;
+pub mod gateway_handler;
+pub mod jobs_handler;
+pub mod keystore_handler;
+pub mod node_handler;
+pub mod notifications_handler;
+pub mod proofs_handler;
+pub mod public_handler;
+pub mod replication_handler;
+pub mod s3_handler;
+pub mod schemas_handler;
+pub mod sharding_handler;
+pub mod storage_handler;
+pub mod tenants_handler;
+pub mod validation;
+pub mod webdav_handler;
+pub mod webhooks_handler;
+pub mod write_queue_handler;
+pub mod ws_handler;
\ No newline at end of file
@@ -0,0 +1,99 @@
+use helpers::{limits::{OperationCounts, OperationKind}, replication::SyncMetrics, state::AppState};
+use gateway::access_control::check_node_id_and_domain_header;
+
+use serde::Serialize;
+use axum::{extract::State, Json};
+use axum::http::{header, HeaderMap, StatusCode};
+use std::fmt::Write;
+
+// Reads one counter of a document
+type MetricValue = fn(&SyncMetrics) -> u64;
+
+// Per-document counters exposed by `/metrics`: name, help and value
+const DOC_METRICS: &[(&str, &str, MetricValue)] = &[
+    ("starter_kit_doc_sync_rounds_total", "Sync rounds with peers, failed ones included.", |m| m.sync_rounds),
+    ("starter_kit_doc_sync_failures_total", "Sync rounds with peers that failed.", |m| m.sync_failures),
+    ("starter_kit_doc_entries_received_total", "Entries inserted by peers.", |m| m.entries_received),
+    ("starter_kit_doc_entries_sent_total", "Entries sent to peers in sync rounds.", |m| m.entries_sent),
+    ("starter_kit_doc_content_received_total", "Content blobs of entries from peers that finished downloading.", |m| m.content_received),
+    ("starter_kit_doc_content_received_bytes_total", "Bytes of content of entries from peers that finished downloading.", |m| m.content_bytes_received),
+];
+
+// Reads one count of a kind of operation
+type OperationValue = fn(&OperationCounts) -> usize;
+
+// Gauges of the operations limited by `OperationLimits`, labelled by kind: name, help and value
+const OPERATION_METRICS: &[(&str, &str, OperationValue)] = &[
+    ("starter_kit_operations_in_flight", "Expensive operations running.", |c| c.in_flight),
+    ("starter_kit_operations_waiting", "Expensive operations waiting for a slot.", |c| c.waiting),
+    ("starter_kit_operations_limit", "Expensive operations allowed to run at once.", |c| c.limit),
+];
+
+// Response bodies
+// 1. version
+#[derive(Serialize)]
+pub struct VersionResponse {
+    pub version: String,
+    pub git_commit: String,
+    pub build_timestamp: u64,
+    pub iroh: String,
+    pub iroh_docs: String,
+    pub iroh_blobs: String,
+}
+
+// Handler for getting the build and dependency versions of the running node
+pub async fn version_handler() -> Json<VersionResponse> {
+    Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: env!("STARTER_KIT_GIT_COMMIT").to_string(),
+        build_timestamp: env!("STARTER_KIT_BUILD_TIMESTAMP").parse().unwrap_or(0),
+        iroh: env!("STARTER_KIT_IROH_VERSION").to_string(),
+        iroh_docs: env!("STARTER_KIT_IROH_DOCS_VERSION").to_string(),
+        iroh_blobs: env!("STARTER_KIT_IROH_BLOBS_VERSION").to_string(),
+    })
+}
+
+// 2. metrics
+// Prometheus text exposition format
+
+// Handler for the node's metrics, in the Prometheus text format
+pub async fn metrics_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<([(header::HeaderName, &'static str); 1], String), (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let mut metrics: Vec<(String, SyncMetrics)> = state.replication.all_metrics().into_iter().collect();
+    metrics.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut body = String::new();
+    let _ = writeln!(body, "# HELP starter_kit_start_time_seconds Unix time the node started, when the counters were reset.");
+    let _ = writeln!(body, "# TYPE starter_kit_start_time_seconds gauge");
+    let _ = writeln!(body, "starter_kit_start_time_seconds {}", state.replication.started_at());
+    for (name, help, value) in OPERATION_METRICS {
+        let _ = writeln!(body, "# HELP {} {}", name, help);
+        let _ = writeln!(body, "# TYPE {} gauge", name);
+        for kind in OperationKind::ALL {
+            let _ = writeln!(body, "{}{{kind=\"{}\"}} {}", name, kind.as_str(), value(&state.limits.counts(kind)));
+        }
+    }
+    let cache = state.blobs.cache.stats();
+    for (name, help, value) in [
+        ("starter_kit_blob_cache_blobs", "Blobs kept in the blob cache.", cache.blobs),
+        ("starter_kit_blob_cache_bytes", "Bytes of blob content kept in the blob cache.", cache.bytes),
+        ("starter_kit_blob_cache_capacity_bytes", "Bytes the blob cache may hold, see --blob-cache-size.", cache.capacity),
+    ] {
+        let _ = writeln!(body, "# HELP {} {}", name, help);
+        let _ = writeln!(body, "# TYPE {} gauge", name);
+        let _ = writeln!(body, "{} {}", name, value);
+    }
+    for (name, help, value) in DOC_METRICS {
+        let _ = writeln!(body, "# HELP {} {}", name, help);
+        let _ = writeln!(body, "# TYPE {} counter", name);
+        for (doc_id, doc_metrics) in &metrics {
+            let _ = writeln!(body, "{}{{doc_id=\"{}\"}} {}", name, doc_id, value(doc_metrics));
+        }
+    }
+
+    Ok(([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body))
+}
@@ -0,0 +1,244 @@
+use helpers::{
+    notifications::{NotificationChannel, Watch, DEFAULT_BATCH_WINDOW, DEFAULT_MAX_PER_HOUR, NOTIFICATION_EVENTS},
+    state::AppState,
+    utils::{get_author_id_from_headers, SS58AuthorId},
+};
+use gateway::access_control::check_node_id_and_domain_header;
+use crate::{request_schema, validation::{self, ValidatedJson}};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use axum::{extract::State, Json};
+use axum::http::{StatusCode, HeaderMap};
+
+// Longest batch window a watch can ask for, in seconds
+const MAX_BATCH_WINDOW: u64 = 86_400;
+
+// Request bodies
+// 1. register watch
+#[derive(Deserialize)]
+pub struct RegisterWatchRequest {
+    /// Watch a single document. Omit to watch every document.
+    pub doc_id: Option<String>,
+    /// Only entries whose key starts with this prefix.
+    pub key_prefix: Option<String>,
+    /// Only entries written by this author.
+    pub author: Option<String>,
+    /// Event types to be notified of. Omit or leave empty for all of them.
+    #[serde(default)]
+    pub events: Vec<String>,
+    pub channel: NotificationChannel,
+    /// Seconds events are collected for before notifying.
+    pub batch_window: Option<u64>,
+    /// Notifications sent per hour at most.
+    pub max_per_hour: Option<u32>,
+}
+request_schema!(RegisterWatchRequest, {
+    "channel": json!({
+        "type": "object",
+        "oneOf": [
+            {
+                "properties": {
+                    "type": { "const": "webhook" },
+                    "url": validation::url(),
+                    "secret": validation::non_empty(),
+                },
+                "required": ["type", "url", "secret"],
+            },
+            {
+                "properties": {
+                    "type": { "const": "email" },
+                    "to": { "type": "string", "pattern": "^[^\\s<>@]+@[^\\s<>@]+$" },
+                },
+                "required": ["type", "to"],
+            },
+            {
+                "properties": {
+                    "type": { "const": "matrix" },
+                    "homeserver": validation::url(),
+                    "room_id": { "type": "string", "pattern": "^!\\S+:\\S+$" },
+                    "access_token": validation::non_empty(),
+                },
+                "required": ["type", "homeserver", "room_id", "access_token"],
+            },
+        ],
+    }),
+});
+
+// 2. list watches
+// No request body
+
+// 3. delete watch
+#[derive(Deserialize)]
+pub struct DeleteWatchRequest {
+    pub id: String,
+}
+request_schema!(DeleteWatchRequest, {
+    "id": validation::non_empty(),
+});
+
+// Response bodies
+// 1. register watch
+#[derive(Serialize)]
+pub struct RegisterWatchResponse {
+    pub id: String,
+}
+
+// 2. list watches
+#[derive(Serialize)]
+pub struct WatchInfo {
+    pub id: String,
+    pub doc_id: Option<String>,
+    pub key_prefix: Option<String>,
+    pub author: Option<String>,
+    pub events: Vec<String>,
+    /// `webhook`, `email` or `matrix`.
+    pub channel: String,
+    /// The URL, address or room notifications are sent to.
+    pub destination: String,
+    pub batch_window: u64,
+    pub max_per_hour: u32,
+}
+
+#[derive(Serialize)]
+pub struct ListWatchesResponse {
+    pub watches: Vec<WatchInfo>,
+}
+
+// 3. delete watch
+#[derive(Serialize)]
+pub struct DeleteWatchResponse {
+    pub message: String,
+}
+
+// Checks that the caller is a registered author
+async fn check_registered_author(state: &AppState, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let caller_author_id = get_author_id_from_headers(headers)?;
+
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only a registered author can perform this action".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+// Handler for registering a watch
+pub async fn register_watch_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<RegisterWatchRequest>,
+) -> Result<Json<RegisterWatchResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_registered_author(&state, &headers).await?;
+
+    // request body checks
+    if let Some(event) = payload.events.iter().find(|event| !NOTIFICATION_EVENTS.contains(&event.as_str())) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown event '{}', expected one of: {}", event, NOTIFICATION_EVENTS.join(", ")),
+        ));
+    }
+    if payload.author.as_deref().is_some_and(|author| SS58AuthorId::decode(author).is_err()) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid author ID format".to_string()));
+    }
+    let batch_window = payload.batch_window.unwrap_or(DEFAULT_BATCH_WINDOW);
+    if batch_window > MAX_BATCH_WINDOW {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("batch_window cannot be more than {} seconds", MAX_BATCH_WINDOW),
+        ));
+    }
+    let max_per_hour = payload.max_per_hour.unwrap_or(DEFAULT_MAX_PER_HOUR);
+    if max_per_hour == 0 {
+        return Err((StatusCode::BAD_REQUEST, "max_per_hour must be at least 1".to_string()));
+    }
+    if matches!(payload.channel, NotificationChannel::Email { .. }) && state.notifications.email().is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Email notifications are not enabled on this node, see --smtp-relay".to_string(),
+        ));
+    }
+    if let Some(doc_id) = &payload.doc_id {
+        let docs = core::docs::list_docs(state.docs.clone())
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if !docs.iter().any(|(id, _)| id == doc_id) {
+            return Err((StatusCode::NOT_FOUND, "Document not found".to_string()));
+        }
+    }
+
+    let watch = Watch {
+        id: uuid::Uuid::new_v4().to_string(),
+        doc_id: payload.doc_id,
+        key_prefix: payload.key_prefix.filter(|prefix| !prefix.is_empty()),
+        author: payload.author,
+        events: payload.events,
+        channel: payload.channel,
+        batch_window,
+        max_per_hour,
+    };
+    let id = watch.id.clone();
+
+    match state.notifications.register(watch).await {
+        Ok(()) => Ok(Json(RegisterWatchResponse { id })),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for listing watches. Secrets and access tokens are never returned.
+pub async fn list_watches_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ListWatchesResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_registered_author(&state, &headers).await?;
+
+    let watches = state
+        .notifications
+        .list()
+        .into_iter()
+        .map(|watch| {
+            let channel = watch.channel.kind().to_string();
+            let destination = match watch.channel {
+                NotificationChannel::Webhook { url, .. } => url,
+                NotificationChannel::Email { to } => to,
+                NotificationChannel::Matrix { homeserver, room_id, .. } => format!("{} {}", homeserver, room_id),
+            };
+            WatchInfo {
+                id: watch.id,
+                doc_id: watch.doc_id,
+                key_prefix: watch.key_prefix,
+                author: watch.author,
+                events: watch.events,
+                channel,
+                destination,
+                batch_window: watch.batch_window,
+                max_per_hour: watch.max_per_hour,
+            }
+        })
+        .collect();
+
+    Ok(Json(ListWatchesResponse { watches }))
+}
+
+// Handler for deleting a watch
+pub async fn delete_watch_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<DeleteWatchRequest>,
+) -> Result<Json<DeleteWatchResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_registered_author(&state, &headers).await?;
+
+    match state.notifications.remove(&payload.id).await {
+        Ok(true) => Ok(Json(DeleteWatchResponse { message: "Watch deleted successfully".to_string() })),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Watch not found".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
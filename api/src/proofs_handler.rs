@@ -0,0 +1,133 @@
+use helpers::{proofs::{PendingProof, ProofReceipt, ProofSubject}, state::AppState, utils::get_author_id_from_headers};
+use gateway::access_control::check_node_id_and_domain_header;
+use core::docs::DocError;
+use core::proofs::{create_proofs, get_proof, queue_proofs, verify_proof, ProofError, ProofStatus, ProofVerification};
+use crate::{request_schema, validation::{self, ValidatedJson}};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use axum::{extract::{Path, State}, Json};
+use axum::http::{StatusCode, HeaderMap};
+
+// Request bodies
+// 1. create proofs
+#[derive(Deserialize)]
+pub struct CreateProofsRequest {
+    /// Blobs (`{"type": "blob", "hash"}`) and document entries (`{"type": "entry", "doc_id", "key"}`)
+    /// to prove. They are anchored together in one extrinsic.
+    pub subjects: Vec<ProofSubject>,
+    /// Queue the subjects for the next batch instead of anchoring them now.
+    #[serde(default)]
+    pub batch: bool,
+}
+request_schema!(CreateProofsRequest, {
+    "subjects": validation::non_empty_array(json!({ "type": "object" })),
+});
+
+// 2. get proof
+// No request body
+
+// 3. verify proof
+// No request body
+
+// Response bodies
+// 1. create proofs
+#[derive(Serialize)]
+pub struct CreateProofsResponse {
+    /// The receipts of subjects anchored now. Empty with `batch`.
+    pub receipts: Vec<ProofReceipt>,
+    /// The subjects queued for the next batch. Empty without `batch`.
+    pub queued: Vec<PendingProof>,
+}
+
+// 2. get proof
+// ProofStatus
+
+// 3. verify proof
+// ProofVerification
+
+// Maps a proof error to the response status
+fn proof_error_response(e: ProofError) -> (StatusCode, String) {
+    let status = match &e {
+        ProofError::NoSubjects
+        | ProofError::InvalidBlobHashFormat
+        | ProofError::BlobNotFound
+        | ProofError::Doc(DocError::EntryNotFound) => StatusCode::BAD_REQUEST,
+        ProofError::ReceiptNotFound => StatusCode::NOT_FOUND,
+        ProofError::ProofPending => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+// Handler for anchoring blobs and entries, or queuing them for the next batch, and issuing a
+// receipt for each of them
+pub async fn create_proofs_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<CreateProofsRequest>,
+) -> Result<Json<CreateProofsResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Proofs are chain transactions paid by the node's account, so only the default author can
+    // request them
+    let default_author = core::authors::get_default_author(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if caller_author_id != default_author {
+        return Err((StatusCode::FORBIDDEN, "Only the default author can perform this action".to_string()));
+    }
+
+    if payload.batch {
+        let queued = queue_proofs(state.docs.clone(), state.blobs.clone(), payload.subjects, &state.proofs)
+            .await
+            .map_err(proof_error_response)?;
+        return Ok(Json(CreateProofsResponse { receipts: vec![], queued }));
+    }
+
+    let receipts = create_proofs(
+        state.docs.clone(),
+        state.blobs.clone(),
+        payload.subjects,
+        state.anchor.clone(),
+        &state.proofs,
+    )
+    .await
+    .map_err(proof_error_response)?;
+
+    Ok(Json(CreateProofsResponse { receipts, queued: vec![] }))
+}
+
+// Handler for getting a receipt, or a subject still queued, with its inclusion proof
+pub async fn get_proof_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<ProofStatus>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    get_proof(&state.proofs, &id).map(Json).map_err(proof_error_response)
+}
+
+// Handler for re-checking a receipt against the current subject and the chain
+pub async fn verify_proof_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<ProofVerification>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let verification = verify_proof(
+        state.docs.clone(),
+        state.blobs.clone(),
+        &id,
+        state.anchor.clone(),
+        &state.proofs,
+    )
+    .await
+    .map_err(proof_error_response)?;
+
+    Ok(Json(verification))
+}
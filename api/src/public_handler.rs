@@ -0,0 +1,192 @@
+use core::{
+    blobs::{complete_blob_size, BlobError},
+    docs::{get_entries, get_resolved_entry, ConflictResolution, DocError, EntryDetails},
+};
+use helpers::{
+    pagination::{Paginated, PaginationParams},
+    state::AppState,
+    utils::{is_history_key, is_trash_key},
+};
+use crate::{
+    blobs_handler::{blob_range_response, content_type_of_name},
+    s3_handler::http_time,
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+    Json,
+};
+use iroh_blobs::Hash;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, str::FromStr};
+
+// These handlers are only routed on the `--public-listen` address. They answer anyone, so they
+// only serve the documents of `--public-doc` and the blobs of `--public-blob`, and answer 404 for
+// anything else, whether it is on the node or not
+
+const DOC_NOT_FOUND: &str = "Document not found";
+const ENTRY_NOT_FOUND: &str = "Entry not found";
+const BLOB_NOT_FOUND: &str = "Blob not found";
+
+// Query parameters
+// 1. list public docs
+// No query parameters
+
+// 2. list public entries, along with `PaginationParams`
+#[derive(Deserialize, Default)]
+pub struct PublicEntriesQuery {
+    /// Only keys starting with this.
+    #[serde(default)]
+    pub prefix: String,
+}
+
+// 3. get public entry
+// No query parameters
+
+// 4. get public blob
+// No query parameters
+
+// Response bodies
+// 1. list public docs
+#[derive(Serialize)]
+pub struct PublicDocsResponse {
+    pub docs: Vec<String>,
+}
+
+// 2. list public entries
+// Paginated<PublicEntry>
+#[derive(Serialize)]
+pub struct PublicEntry {
+    pub key: String,
+    pub author: String,
+    pub hash: String,
+    pub len: u64,
+    pub timestamp: u64,
+}
+
+// 3. get public entry
+// The content of the entry
+
+// 4. get public blob
+// The content of the blob
+
+fn not_found(message: &str) -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, message.to_string())
+}
+
+// Keys of deleted entries and of replaced versions are never published
+fn is_published_key(key: &str) -> bool {
+    !is_trash_key(key) && !is_history_key(key)
+}
+
+// Handler for listing the published documents
+pub async fn list_public_docs_handler(State(state): State<AppState>) -> Json<PublicDocsResponse> {
+    Json(PublicDocsResponse { docs: state.public.docs.iter().cloned().collect() })
+}
+
+// Handler for listing the latest version of the published keys of a document, in key order
+pub async fn list_public_entries_handler(
+    State(state): State<AppState>,
+    Path(doc_id): Path<String>,
+    Query(query): Query<PublicEntriesQuery>,
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<Paginated<PublicEntry>>, (StatusCode, String)> {
+    if !state.public.is_public_doc(&doc_id) {
+        return Err(not_found(DOC_NOT_FOUND));
+    }
+
+    let entries = get_entries(state.docs.clone(), doc_id, serde_json::json!({ "key_prefix": query.prefix }))
+        .await
+        .map_err(|e| match e {
+            DocError::InvalidDocumentIdFormat | DocError::DocumentNotFound => not_found(DOC_NOT_FOUND),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+    let mut versions: BTreeMap<String, Vec<EntryDetails>> = BTreeMap::new();
+    for entry in entries.into_iter().filter(|entry| is_published_key(&entry.namespace.key)) {
+        versions.entry(entry.namespace.key.clone()).or_default().push(entry);
+    }
+
+    let latest = versions
+        .iter()
+        .filter_map(|(key, versions)| ConflictResolution::LastWriterWins.resolve(key, versions))
+        .map(|entry| PublicEntry {
+            key: entry.namespace.key,
+            author: entry.namespace.author,
+            hash: entry.record.hash,
+            len: entry.record.len,
+            timestamp: entry.record.timestamp,
+        })
+        .collect();
+    Ok(Json(Paginated::from_items(latest, &pagination)?))
+}
+
+// Handler for the content of the latest version of a published key, typed by the key's extension
+pub async fn get_public_entry_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((doc_id, key)): Path<(String, String)>,
+) -> Result<Response, (StatusCode, String)> {
+    if !state.public.is_public_doc(&doc_id) {
+        return Err(not_found(DOC_NOT_FOUND));
+    }
+    if !is_published_key(&key) {
+        return Err(not_found(ENTRY_NOT_FOUND));
+    }
+
+    let (entry, _) = match get_resolved_entry(state.docs.clone(), doc_id, key.clone(), &ConflictResolution::LastWriterWins).await {
+        Ok(Some(resolved)) => resolved,
+        Ok(None) | Err(DocError::FailedToValidateKey(_)) => return Err(not_found(ENTRY_NOT_FOUND)),
+        Err(DocError::InvalidDocumentIdFormat | DocError::DocumentNotFound) => return Err(not_found(DOC_NOT_FOUND)),
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    };
+    let hash = Hash::from_str(&entry.record.hash).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let size = match complete_blob_size(state.blobs.clone(), hash.to_hex()).await {
+        Ok((_, size)) => size,
+        Err(BlobError::BlobNotComplete) => {
+            return Err(not_found("The content of the entry is not on this node yet"))
+        }
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    };
+
+    let content_type = content_type_of_name(&key).unwrap_or("application/octet-stream");
+    let mut response = blob_range_response(&state, &headers, hash, size, content_type).await?;
+    // a key is rewritten in place, unlike a blob
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    if let Ok(modified) = HeaderValue::from_str(&http_time(entry.record.timestamp)) {
+        response_headers.insert(header::LAST_MODIFIED, modified);
+    }
+    Ok(sandboxed(response))
+}
+
+// Handler for the content of a published blob
+pub async fn get_public_blob_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(hash): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let hash = Hash::from_str(&hash).map_err(|_| not_found(BLOB_NOT_FOUND))?;
+    if !state.public.is_public_blob(&hash) {
+        return Err(not_found(BLOB_NOT_FOUND));
+    }
+
+    let size = match complete_blob_size(state.blobs.clone(), hash.to_hex()).await {
+        Ok((_, size)) => size,
+        Err(BlobError::BlobNotComplete) => return Err(not_found(BLOB_NOT_FOUND)),
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    };
+
+    let response = blob_range_response(&state, &headers, hash, size, "application/octet-stream").await?;
+    Ok(sandboxed(response))
+}
+
+// Published content is written by authors, not by the operator, so HTML and SVG of it must not
+// run scripts in the browser, as on `/gw`
+fn sandboxed(mut response: Response) -> Response {
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CONTENT_SECURITY_POLICY, HeaderValue::from_static("sandbox"));
+    response_headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    response
+}
@@ -0,0 +1,57 @@
+use helpers::state::AppState;
+use gateway::access_control::check_node_id_and_domain_header;
+use core::replication::{replication_report, DocReplication, ReplicationError, ReplicationSummary};
+use crate::backups_handler::check_default_author;
+
+use serde::{Deserialize, Serialize};
+use axum::{extract::{Query, State}, Json};
+use axum::http::{StatusCode, HeaderMap};
+
+// Request bodies
+// 1. replication report
+#[derive(Deserialize)]
+pub struct ReplicationQuery {
+    /// Only report this document.
+    #[serde(default)]
+    pub doc_id: Option<String>,
+}
+
+// Response bodies
+// 1. replication report
+#[derive(Serialize)]
+pub struct ReplicationResponse {
+    pub summary: ReplicationSummary,
+    pub docs: Vec<DocReplication>,
+}
+
+// Maps a replication error to the response status
+fn replication_error_response(e: ReplicationError) -> (StatusCode, String) {
+    let status = match &e {
+        ReplicationError::InvalidDocumentIdFormat => StatusCode::BAD_REQUEST,
+        ReplicationError::DocumentNotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+// Handler for the replication health of every document, or of one
+pub async fn replication_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ReplicationQuery>,
+) -> Result<Json<ReplicationResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let (summary, docs) = replication_report(
+        state.docs.clone(),
+        state.blobs.clone(),
+        &state.replication,
+        &state.sync_controls,
+        query.doc_id,
+    )
+    .await
+    .map_err(replication_error_response)?;
+
+    Ok(Json(ReplicationResponse { summary, docs }))
+}
@@ -0,0 +1,391 @@
+use core::{
+    blobs::{complete_blob_size, list_blobs_after, read_blob_range, BlobError},
+    docs::{get_entries, get_resolved_entry, list_docs, ConflictResolution, DocError, EntryDetails},
+};
+use helpers::state::AppState;
+use crate::blobs_handler::{is_not_modified, parse_range};
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use iroh_blobs::Hash;
+use serde::Deserialize;
+use std::{collections::BTreeMap, fmt::Write, ops::Bound, str::FromStr};
+
+// These handlers are only routed on the `--s3-listen` address. They answer S3 clients without
+// checking their signatures, so every blob and document of the node is readable there
+
+// The bucket of the blob store, keyed by hex hash. Every other bucket is a document, keyed by entry key
+const BLOBS_BUCKET: &str = "blobs";
+// Largest page of a listing, as in S3
+const MAX_KEYS: usize = 1000;
+const XMLNS: &str = "http://s3.amazonaws.com/doc/2006-03-01/";
+
+// Query parameters
+// 1. list_objects, for both ListObjects and ListObjectsV2
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct ListObjectsQuery {
+    pub list_type: Option<u8>,
+    #[serde(default)]
+    pub prefix: String,
+    pub delimiter: Option<String>,
+    pub max_keys: Option<usize>,
+    pub continuation_token: Option<String>,
+    pub start_after: Option<String>,
+    pub marker: Option<String>,
+    pub encoding_type: Option<String>,
+}
+
+// An object of a listing
+struct Object {
+    key: String,
+    size: u64,
+    hash: Hash,
+    // microseconds since the epoch, 0 for blobs, which have no time
+    modified: u64,
+}
+
+#[derive(Default)]
+struct Listing {
+    objects: Vec<Object>,
+    common_prefixes: Vec<String>,
+    truncated: bool,
+    // key or common prefix to continue after
+    next: Option<String>,
+}
+
+// Handler for ListBuckets: the blob store and every document
+pub async fn list_buckets_handler(State(state): State<AppState>) -> Response {
+    let docs = match list_docs(state.docs.clone()).await {
+        Ok(docs) => docs,
+        Err(e) => return s3_error(StatusCode::INTERNAL_SERVER_ERROR, "InternalError", &e.to_string()),
+    };
+
+    let mut body = format!(r#"<?xml version="1.0" encoding="UTF-8"?><ListAllMyBucketsResult xmlns="{XMLNS}"><Buckets>"#);
+    for name in std::iter::once(BLOBS_BUCKET.to_string()).chain(docs.into_iter().map(|(doc_id, _)| doc_id)) {
+        let _ = write!(body, "<Bucket><Name>{}</Name><CreationDate>{}</CreationDate></Bucket>", xml_escape(&name), iso_time(0));
+    }
+    body.push_str("</Buckets></ListAllMyBucketsResult>");
+    xml_response(body)
+}
+
+// Handler for ListObjects and ListObjectsV2 of a bucket, which also answers HeadBucket
+pub async fn list_objects_handler(
+    State(state): State<AppState>,
+    Path(bucket): Path<String>,
+    Query(query): Query<ListObjectsQuery>,
+) -> Response {
+    let v2 = query.list_type == Some(2);
+    let after = if v2 {
+        query.continuation_token.clone().or_else(|| query.start_after.clone())
+    } else {
+        query.marker.clone()
+    };
+    let max_keys = query.max_keys.unwrap_or(MAX_KEYS).min(MAX_KEYS);
+    let delimiter = query.delimiter.as_deref().filter(|delimiter| !delimiter.is_empty());
+
+    let listing = if bucket == BLOBS_BUCKET {
+        list_blob_objects(&state, &query.prefix, after.as_deref(), max_keys).await
+    } else {
+        list_doc_objects(&state, &bucket, &query.prefix, delimiter, after.as_deref(), max_keys).await
+    };
+    let listing = match listing {
+        Ok(listing) => listing,
+        Err(response) => return response,
+    };
+
+    let url_encoded = query.encoding_type.as_deref() == Some("url");
+    let encode = |value: &str| if url_encoded { url_encode(value) } else { xml_escape(value) };
+
+    let mut body = format!(r#"<?xml version="1.0" encoding="UTF-8"?><ListBucketResult xmlns="{XMLNS}">"#);
+    let _ = write!(body, "<Name>{}</Name><Prefix>{}</Prefix><MaxKeys>{}</MaxKeys>", xml_escape(&bucket), encode(&query.prefix), max_keys);
+    if let Some(delimiter) = delimiter {
+        let _ = write!(body, "<Delimiter>{}</Delimiter>", encode(delimiter));
+    }
+    if url_encoded {
+        body.push_str("<EncodingType>url</EncodingType>");
+    }
+    let _ = write!(body, "<IsTruncated>{}</IsTruncated>", listing.truncated);
+    if v2 {
+        let _ = write!(body, "<KeyCount>{}</KeyCount>", listing.objects.len() + listing.common_prefixes.len());
+        if let Some(token) = &query.continuation_token {
+            let _ = write!(body, "<ContinuationToken>{}</ContinuationToken>", xml_escape(token));
+        }
+        if let Some(start_after) = &query.start_after {
+            let _ = write!(body, "<StartAfter>{}</StartAfter>", encode(start_after));
+        }
+        if let Some(next) = listing.next.as_ref().filter(|_| listing.truncated) {
+            let _ = write!(body, "<NextContinuationToken>{}</NextContinuationToken>", xml_escape(next));
+        }
+    } else {
+        let _ = write!(body, "<Marker>{}</Marker>", encode(query.marker.as_deref().unwrap_or_default()));
+        if let Some(next) = listing.next.as_ref().filter(|_| listing.truncated) {
+            let _ = write!(body, "<NextMarker>{}</NextMarker>", encode(next));
+        }
+    }
+    for object in &listing.objects {
+        let _ = write!(
+            body,
+            "<Contents><Key>{}</Key><LastModified>{}</LastModified><ETag>&quot;{}&quot;</ETag><Size>{}</Size><StorageClass>STANDARD</StorageClass></Contents>",
+            encode(&object.key),
+            iso_time(object.modified),
+            object.hash.to_hex(),
+            object.size,
+        );
+    }
+    for prefix in &listing.common_prefixes {
+        let _ = write!(body, "<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>", encode(prefix));
+    }
+    body.push_str("</ListBucketResult>");
+    xml_response(body)
+}
+
+// Handler for GetObject and HeadObject: a blob by hash, or the latest version of a document's key
+pub async fn get_object_handler(
+    State(state): State<AppState>,
+    method: Method,
+    headers: HeaderMap,
+    Path((bucket, key)): Path<(String, String)>,
+) -> Response {
+    let in_blobs = bucket == BLOBS_BUCKET;
+    let (hash, modified) = if in_blobs {
+        match Hash::from_str(&key) {
+            Ok(hash) => (hash, 0),
+            Err(_) => return s3_error(StatusCode::NOT_FOUND, "NoSuchKey", "The specified key does not exist."),
+        }
+    } else {
+        match get_resolved_entry(state.docs.clone(), bucket, key, &ConflictResolution::LastWriterWins).await {
+            Ok(Some((entry, _))) => match Hash::from_str(&entry.record.hash) {
+                Ok(hash) => (hash, entry.record.timestamp),
+                Err(e) => return s3_error(StatusCode::INTERNAL_SERVER_ERROR, "InternalError", &e.to_string()),
+            },
+            Ok(None) | Err(DocError::FailedToValidateKey(_)) => {
+                return s3_error(StatusCode::NOT_FOUND, "NoSuchKey", "The specified key does not exist.")
+            }
+            Err(e) => return doc_error(e),
+        }
+    };
+
+    let size = match complete_blob_size(state.blobs.clone(), hash.to_hex()).await {
+        Ok((_, size)) => size,
+        Err(BlobError::BlobNotComplete) => {
+            let message = if in_blobs {
+                "The specified key does not exist."
+            } else {
+                "The content of the key is not on this node yet."
+            };
+            return s3_error(StatusCode::NOT_FOUND, "NoSuchKey", message);
+        }
+        Err(e) => return s3_error(StatusCode::INTERNAL_SERVER_ERROR, "InternalError", &e.to_string()),
+    };
+
+    let etag = format!("\"{}\"", hash.to_hex());
+    if is_not_modified(&headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| parse_range(value, size));
+    let (status, offset, len) = match range {
+        None | Some(Ok(None)) => (StatusCode::OK, 0, size),
+        Some(Ok(Some((start, end)))) => (StatusCode::PARTIAL_CONTENT, start, end - start + 1),
+        Some(Err(())) => return s3_error(StatusCode::RANGE_NOT_SATISFIABLE, "InvalidRange", "The requested range is not satisfiable."),
+    };
+
+    let body = if len == 0 || method == Method::HEAD {
+        Body::empty()
+    } else {
+        match read_blob_range(state.blobs.clone(), hash, offset, len).await {
+            Ok(reader) => Body::from_stream(reader),
+            Err(e) => return s3_error(StatusCode::INTERNAL_SERVER_ERROR, "InternalError", &e.to_string()),
+        }
+    };
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(header::CONTENT_LENGTH, len)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, &etag)
+        .header(header::LAST_MODIFIED, http_time(modified));
+    if status == StatusCode::PARTIAL_CONTENT {
+        response = response.header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", offset, offset + len - 1, size));
+    }
+    response
+        .body(body)
+        .unwrap_or_else(|e| s3_error(StatusCode::INTERNAL_SERVER_ERROR, "InternalError", &e.to_string()))
+}
+
+// The blobs whose hex hash starts with `prefix`, after `after`. Hashes list in the order of their
+// hex, so the page starts at the prefix and costs the same however deep it is
+async fn list_blob_objects(
+    state: &AppState,
+    prefix: &str,
+    after: Option<&str>,
+    max_keys: usize,
+) -> Result<Listing, Response> {
+    if prefix.len() > 64 || !prefix.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)) {
+        return Ok(Listing::default());
+    }
+    let start = match after {
+        Some(after) if after.len() == 64 => Hash::from_str(after).ok(),
+        Some(after) => hash_before(after),
+        None => None,
+    };
+    let start = start.max(hash_before(prefix));
+
+    let page = list_blobs_after(state.blobs.clone(), start, max_keys)
+        .await
+        .map_err(|e| s3_error(StatusCode::INTERNAL_SERVER_ERROR, "InternalError", &e.to_string()))?;
+    let listed = page.blobs.len();
+    let objects: Vec<Object> = page
+        .blobs
+        .into_iter()
+        .map(|blob| Object { key: blob.hash.to_hex(), size: blob.size, hash: blob.hash, modified: 0 })
+        .take_while(|object| object.key.starts_with(prefix))
+        .collect();
+
+    let truncated = objects.len() == listed && page.next.is_some();
+    let next = objects.last().map(|object| object.key.clone());
+    Ok(Listing { objects, common_prefixes: Vec::new(), truncated, next })
+}
+
+// The hash right before the first one whose hex sorts at or after `hex`, a hash or a part of one,
+// to list blobs after. `None` to list from the first blob
+fn hash_before(hex: &str) -> Option<Hash> {
+    if hex.is_empty() || hex.len() > 64 {
+        return None;
+    }
+    let mut bytes = [0u8; 32];
+    hex::decode_to_slice(format!("{:0<64}", hex), &mut bytes).ok()?;
+    for byte in bytes.iter_mut().rev() {
+        if *byte > 0 {
+            *byte -= 1;
+            return Some(Hash::from_bytes(bytes));
+        }
+        *byte = 0xff;
+    }
+    None
+}
+
+// The latest version of the keys of a document starting with `prefix`, after `after`, keys
+// sharing a part up to `delimiter` rolled up into a common prefix
+async fn list_doc_objects(
+    state: &AppState,
+    doc_id: &str,
+    prefix: &str,
+    delimiter: Option<&str>,
+    after: Option<&str>,
+    max_keys: usize,
+) -> Result<Listing, Response> {
+    let entries = get_entries(state.docs.clone(), doc_id.to_string(), serde_json::json!({ "key_prefix": prefix }))
+        .await
+        .map_err(|e| match e {
+            DocError::InvalidDocumentIdFormat | DocError::DocumentNotFound => {
+                s3_error(StatusCode::NOT_FOUND, "NoSuchBucket", "The specified bucket does not exist.")
+            }
+            e => doc_error(e),
+        })?;
+    let mut versions: BTreeMap<String, Vec<EntryDetails>> = BTreeMap::new();
+    for entry in entries {
+        versions.entry(entry.namespace.key.clone()).or_default().push(entry);
+    }
+
+    let mut listing = Listing::default();
+    let from = after.map_or(Bound::Unbounded, Bound::Excluded);
+    for (key, versions) in versions.range::<str, _>((from, Bound::Unbounded)) {
+        let Some(rest) = key.strip_prefix(prefix) else { continue };
+        // the keys of a common prefix returned by the previous page
+        if let (Some(after), Some(delimiter)) = (after, delimiter) {
+            if after.ends_with(delimiter) && key.starts_with(after) {
+                continue;
+            }
+        }
+        let common_prefix = delimiter
+            .and_then(|delimiter| rest.find(delimiter).map(|at| key[..prefix.len() + at + delimiter.len()].to_string()));
+        if common_prefix.is_some() && listing.common_prefixes.last() == common_prefix.as_ref() {
+            continue;
+        }
+        let Some(entry) = ConflictResolution::LastWriterWins.resolve(key, versions) else { continue };
+
+        if listing.objects.len() + listing.common_prefixes.len() == max_keys {
+            listing.truncated = true;
+            break;
+        }
+        match common_prefix {
+            Some(common_prefix) => {
+                listing.next = Some(common_prefix.clone());
+                listing.common_prefixes.push(common_prefix);
+            }
+            None => {
+                let hash = Hash::from_str(&entry.record.hash)
+                    .map_err(|e| s3_error(StatusCode::INTERNAL_SERVER_ERROR, "InternalError", &e.to_string()))?;
+                listing.next = Some(key.clone());
+                listing.objects.push(Object { key: key.clone(), size: entry.record.len, hash, modified: entry.record.timestamp });
+            }
+        }
+    }
+    Ok(listing)
+}
+
+fn doc_error(e: DocError) -> Response {
+    match e {
+        DocError::InvalidDocumentIdFormat | DocError::DocumentNotFound => {
+            s3_error(StatusCode::NOT_FOUND, "NoSuchBucket", "The specified bucket does not exist.")
+        }
+        e => s3_error(StatusCode::INTERNAL_SERVER_ERROR, "InternalError", &e.to_string()),
+    }
+}
+
+fn s3_error(status: StatusCode, code: &str, message: &str) -> Response {
+    let body = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><Error><Code>{}</Code><Message>{}</Message></Error>"#,
+        code,
+        xml_escape(message),
+    );
+    (status, [(header::CONTENT_TYPE, "application/xml")], body).into_response()
+}
+
+fn xml_response(body: String) -> Response {
+    ([(header::CONTENT_TYPE, "application/xml")], body).into_response()
+}
+
+pub(crate) fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// Percent-encodes all but unreserved characters and `/`, for `encoding-type=url`
+pub(crate) fn url_encode(value: &str) -> String {
+    value.bytes().fold(String::new(), |mut encoded, byte| {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{:02X}", byte);
+        }
+        encoded
+    })
+}
+
+fn datetime(micros: u64) -> DateTime<Utc> {
+    DateTime::from_timestamp_micros(micros as i64).unwrap_or_default()
+}
+
+fn iso_time(micros: u64) -> String {
+    datetime(micros).format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+}
+
+pub(crate) fn http_time(micros: u64) -> String {
+    datetime(micros).format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
@@ -0,0 +1,139 @@
+use helpers::{state::AppState, utils::get_author_id_from_headers};
+use gateway::access_control::check_node_id_and_domain_header;
+use core::docs::DocError;
+use core::schemas::{get_registry_schema, list_registry_schemas, publish_schema, RegistrySchema};
+use crate::{request_schema, validation::{self, ValidatedJson}};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use axum::{extract::{Path, State}, Json};
+use axum::http::{StatusCode, HeaderMap};
+
+// Request bodies
+// 1. publish schema
+#[derive(Deserialize)]
+pub struct PublishSchemaRequest {
+    pub author_id: String,
+    pub name: String,
+    pub version: String,
+    /// The JSON schema, as a string like the `schema` of `add-doc-schema`.
+    pub schema: String,
+}
+request_schema!(PublishSchemaRequest, {
+    "author_id": validation::ss58(),
+    "name": validation::non_empty(),
+    "version": validation::non_empty(),
+    "schema": validation::non_empty(),
+});
+
+// 2. list schemas
+// No request body
+
+// 3. get schema
+// No request body
+
+// Response bodies
+// 1. publish schema
+#[derive(Serialize)]
+pub struct PublishSchemaResponse {
+    /// What documents put in `add-doc-schema` to use the schema.
+    pub reference: String,
+    pub hash: String,
+}
+
+// 2. list schemas
+#[derive(Serialize)]
+pub struct ListSchemasResponse {
+    pub schemas: Vec<RegistrySchema>,
+}
+
+// 3. get schema
+// The schema itself
+
+// Maps a schema registry error to the response status
+fn registry_error_response(e: DocError) -> (StatusCode, String) {
+    let status = match &e {
+        DocError::InvalidSchemaName
+        | DocError::FailedToSerializeSchema(_)
+        | DocError::FailedToValidateSchema(_)
+        | DocError::FailedToResolveSchemaRef(_)
+        | DocError::SchemaRefNotAllowed => StatusCode::BAD_REQUEST,
+        DocError::SchemaNotFound => StatusCode::NOT_FOUND,
+        DocError::SchemaVersionExists => StatusCode::CONFLICT,
+        DocError::SchemaRegistryNotSet => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+// Handler for publishing a new version of a schema to the schema registry document
+pub async fn publish_schema_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<PublishSchemaRequest>,
+) -> Result<Json<PublishSchemaResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+
+    // Check if the calling author is in the list of authors
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((StatusCode::FORBIDDEN, "Only a registered author can perform this action".to_string()));
+    }
+
+    let reference = format!("{}:{}@{}", core::schemas::REGISTRY_SCHEME, payload.name, payload.version);
+    let hash = publish_schema(
+        state.docs.clone(),
+        state.blobs.clone(),
+        payload.author_id,
+        payload.name,
+        payload.version,
+        payload.schema,
+    )
+    .await
+    .map_err(registry_error_response)?;
+
+    Ok(Json(PublishSchemaResponse { reference, hash }))
+}
+
+// Handler for listing the schemas of the schema registry document with their versions
+pub async fn list_schemas_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ListSchemasResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let schemas = list_registry_schemas(state.docs.clone()).await.map_err(registry_error_response)?;
+    Ok(Json(ListSchemasResponse { schemas }))
+}
+
+// Handler for getting the highest version of a schema
+pub async fn get_latest_schema_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    get_registry_schema(state.docs.clone(), state.blobs.clone(), &name, None)
+        .await
+        .map(Json)
+        .map_err(registry_error_response)
+}
+
+// Handler for getting one version of a schema
+pub async fn get_schema_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((name, version)): Path<(String, String)>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    get_registry_schema(state.docs.clone(), state.blobs.clone(), &name, Some(&version))
+        .await
+        .map(Json)
+        .map_err(registry_error_response)
+}
@@ -0,0 +1,367 @@
+use helpers::{events::NodeEvent, pagination::{Paginated, PaginationParams}, state::AppState, utils::get_author_id_from_headers};
+use gateway::access_control::check_node_id_and_domain_header;
+use core::docs::{DocError, EntryDetails};
+use core::sharding::{
+    create_sharded_doc, delete_sharded_entry, drop_sharded_doc, get_sharded_doc, get_sharded_entries,
+    get_sharded_entry, set_sharded_entry, share_sharded_doc, ShardedDoc, ShardingError,
+};
+use crate::docs_handler::{check_entry_authorization, parse_addr_options, parse_share_mode, GetEntryResponse};
+use crate::{request_schema, validation::{self, ValidatedJson}};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use axum::{extract::{Query, State}, Json};
+use axum::http::{StatusCode, HeaderMap};
+
+// Request bodies
+// 1. create sharded document
+#[derive(Deserialize)]
+pub struct CreateShardedDocRequest {
+    /// The author of the shard manifest.
+    pub author_id: String,
+    /// Number of shards, from 1 to `MAX_SHARDS`. It cannot be changed later.
+    pub shards: usize,
+}
+request_schema!(CreateShardedDocRequest, {
+    "author_id": validation::ss58(),
+    "shards": json!({ "type": "integer", "minimum": 1, "maximum": core::sharding::MAX_SHARDS }),
+});
+
+// 2. get sharded document
+#[derive(Deserialize)]
+pub struct ShardedDocQuery {
+    pub doc_id: String,
+}
+
+// 3. set sharded entry
+#[derive(Deserialize)]
+pub struct SetShardedEntryRequest {
+    pub doc_id: String,
+    pub author_id: String,
+    pub key: String,
+    pub value: String,
+}
+request_schema!(SetShardedEntryRequest, {
+    "doc_id": validation::doc_id(),
+    "author_id": validation::ss58(),
+    "key": validation::non_empty(),
+    "value": validation::non_empty(),
+});
+
+// 4. get sharded entry
+#[derive(Deserialize)]
+pub struct GetShardedEntryRequest {
+    pub doc_id: String,
+    pub author_id: String,
+    pub key: String,
+    #[serde(default)]
+    pub include_empty: bool,
+}
+request_schema!(GetShardedEntryRequest, {
+    "doc_id": validation::doc_id(),
+    "author_id": validation::ss58(),
+    "key": validation::non_empty(),
+});
+
+// 5. get sharded entries
+#[derive(Deserialize)]
+pub struct GetShardedEntriesRequest {
+    pub doc_id: String,
+    pub query_params: String, // JSON string from user
+}
+request_schema!(GetShardedEntriesRequest, {
+    "doc_id": validation::doc_id(),
+    "query_params": validation::non_empty(),
+});
+
+// 6. delete sharded entry
+#[derive(Deserialize)]
+pub struct DeleteShardedEntryRequest {
+    pub doc_id: String,
+    pub author_id: String,
+    pub key: String,
+}
+request_schema!(DeleteShardedEntryRequest, {
+    "doc_id": validation::doc_id(),
+    "author_id": validation::ss58(),
+    "key": validation::non_empty(),
+});
+
+// 7. share sharded document
+#[derive(Deserialize)]
+pub struct ShareShardedDocRequest {
+    pub doc_id: String,
+    pub mode: String,
+    pub addr_options: String,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+request_schema!(ShareShardedDocRequest, {
+    "doc_id": validation::doc_id(),
+    "mode": validation::non_empty(),
+    "addr_options": validation::non_empty(),
+});
+
+// 8. drop sharded document
+#[derive(Deserialize)]
+pub struct DropShardedDocRequest {
+    pub doc_id: String,
+}
+request_schema!(DropShardedDocRequest, {
+    "doc_id": validation::doc_id(),
+});
+
+// Response bodies
+// 1. create sharded document, 2. get sharded document, 8. drop sharded document
+// `ShardedDoc`
+
+// 3. set sharded entry
+#[derive(Serialize)]
+pub struct SetShardedEntryResponse {
+    /// The shard the entry was written to.
+    pub shard: String,
+    pub hash: String,
+}
+
+// 4. get sharded entry
+#[derive(Serialize)]
+pub struct GetShardedEntryResponse {
+    /// The shard the entry was read from.
+    pub shard: String,
+    #[serde(flatten)]
+    pub entry: GetEntryResponse,
+}
+
+// 6. delete sharded entry
+#[derive(Serialize)]
+pub struct DeleteShardedEntryResponse {
+    pub shard: String,
+    pub deleted_count: usize,
+}
+
+// 7. share sharded document
+#[derive(Serialize)]
+pub struct ShareShardedDocResponse {
+    /// A bundle ticket listing the root document and every shard, joined with
+    /// `/docs/join-bundle`.
+    pub ticket: String,
+}
+
+// Maps a sharding error to the response status
+fn sharding_error_response(e: ShardingError) -> (StatusCode, String) {
+    let status = match &e {
+        ShardingError::InvalidShardCount
+        | ShardingError::InvalidDocumentIdFormat
+        | ShardingError::InvalidAuthorIdFormat
+        | ShardingError::NotShardedDocument
+        | ShardingError::Doc(
+            DocError::FailedToValidateKey(_)
+            | DocError::ValueDoesNotMatchSchema
+            | DocError::FailedToConvertValueJson(_)
+            | DocError::InvalidAuthorIdFormat
+            | DocError::InvalidSortByValue
+            | DocError::InvalidSortDirectionValue,
+        ) => StatusCode::BAD_REQUEST,
+        ShardingError::Doc(DocError::AuthorNotFound) => StatusCode::FORBIDDEN,
+        ShardingError::DocumentNotFound
+        | ShardingError::Doc(DocError::DocumentNotFound | DocError::EntryNotFound) => StatusCode::NOT_FOUND,
+        ShardingError::ManifestNotDownloaded => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+async fn check_registered_author(state: &AppState, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let caller_author_id = get_author_id_from_headers(headers)?;
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((StatusCode::FORBIDDEN, "Only a registered author can perform this action".to_string()));
+    }
+    Ok(())
+}
+
+fn entry_response(entry: EntryDetails) -> GetEntryResponse {
+    GetEntryResponse {
+        doc: entry.namespace.doc,
+        key: entry.namespace.key,
+        author: entry.namespace.author,
+        hash: entry.record.hash,
+        len: entry.record.len,
+        timestamp: entry.record.timestamp,
+        anchor: entry.record.anchor,
+    }
+}
+
+// Handler for creating a document whose entries are spread across several shards
+pub async fn create_sharded_doc_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<CreateShardedDocRequest>,
+) -> Result<Json<ShardedDoc>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_registered_author(&state, &headers).await?;
+
+    let sharded = create_sharded_doc(state.docs.clone(), payload.author_id, payload.shards)
+        .await
+        .map_err(sharding_error_response)?;
+
+    for doc_id in std::iter::once(&sharded.doc_id).chain(&sharded.shards) {
+        state.events.publish(NodeEvent::DocCreated { doc_id: doc_id.clone() });
+    }
+    Ok(Json(sharded))
+}
+
+// Handler for reading the shards of a sharded document
+pub async fn get_sharded_doc_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ShardedDocQuery>,
+) -> Result<Json<ShardedDoc>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let sharded = get_sharded_doc(state.docs.clone(), state.blobs.clone(), &query.doc_id)
+        .await
+        .map_err(sharding_error_response)?;
+
+    Ok(Json(sharded))
+}
+
+// Handler for setting an entry in the shard of its key
+pub async fn set_sharded_entry_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<SetShardedEntryRequest>,
+) -> Result<Json<SetShardedEntryResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_registered_author(&state, &headers).await?;
+    check_entry_authorization(&state, &payload.doc_id, &payload.author_id).await?;
+
+    let (shard, hash) = set_sharded_entry(
+        state.docs.clone(),
+        state.blobs.clone(),
+        &payload.doc_id,
+        payload.author_id,
+        payload.key,
+        payload.value,
+    )
+    .await
+    .map_err(sharding_error_response)?;
+
+    Ok(Json(SetShardedEntryResponse { shard, hash }))
+}
+
+// Handler for getting an entry from the shard of its key
+pub async fn get_sharded_entry_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<GetShardedEntryRequest>,
+) -> Result<Json<GetShardedEntryResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    match get_sharded_entry(
+        state.docs.clone(),
+        state.blobs.clone(),
+        &payload.doc_id,
+        payload.author_id,
+        payload.key,
+        payload.include_empty,
+    )
+    .await
+    {
+        Ok((shard, Some(entry))) => Ok(Json(GetShardedEntryResponse { shard, entry: entry_response(entry) })),
+        Ok((_, None)) => Err((StatusCode::NOT_FOUND, "Entry not found".to_string())),
+        Err(e) => Err(sharding_error_response(e)),
+    }
+}
+
+// Handler for querying the entries of every shard, merged in key order
+pub async fn get_sharded_entries_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(pagination): Query<PaginationParams>,
+    ValidatedJson(payload): ValidatedJson<GetShardedEntriesRequest>,
+) -> Result<Json<Paginated<GetEntryResponse>>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let query_params: serde_json::Value = serde_json::from_str(&payload.query_params)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid query_params: {}", e)))?;
+
+    let entries = get_sharded_entries(state.docs.clone(), state.blobs.clone(), &payload.doc_id, query_params)
+        .await
+        .map_err(sharding_error_response)?;
+
+    Ok(Json(Paginated::from_items(entries, &pagination)?.map(entry_response)))
+}
+
+// Handler for deleting an entry from the shard of its key
+pub async fn delete_sharded_entry_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<DeleteShardedEntryRequest>,
+) -> Result<Json<DeleteShardedEntryResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_registered_author(&state, &headers).await?;
+
+    let (shard, deleted_count) = delete_sharded_entry(
+        state.docs.clone(),
+        state.blobs.clone(),
+        &payload.doc_id,
+        payload.author_id,
+        payload.key,
+    )
+    .await
+    .map_err(sharding_error_response)?;
+
+    Ok(Json(DeleteShardedEntryResponse { shard, deleted_count }))
+}
+
+// Handler for sharing a sharded document as a bundle ticket, signed by the caller
+pub async fn share_sharded_doc_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<ShareShardedDocRequest>,
+) -> Result<Json<ShareShardedDocResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller_author_id = get_author_id_from_headers(&headers)?;
+    let mode = parse_share_mode(&payload.mode)?;
+    let addr_options = parse_addr_options(&payload.addr_options)?;
+
+    let ticket = share_sharded_doc(
+        state.docs.clone(),
+        state.blobs.clone(),
+        &payload.doc_id,
+        mode,
+        addr_options,
+        caller_author_id,
+        payload.label,
+    )
+    .await
+    .map_err(sharding_error_response)?;
+
+    Ok(Json(ShareShardedDocResponse { ticket }))
+}
+
+// Handler for dropping a sharded document and every shard
+pub async fn drop_sharded_doc_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<DropShardedDocRequest>,
+) -> Result<Json<ShardedDoc>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_registered_author(&state, &headers).await?;
+
+    // With an approval policy, sharded documents are only dropped through the approvals API
+    state.approvals.check_direct().map_err(|e| (StatusCode::FORBIDDEN, e))?;
+
+    let sharded = drop_sharded_doc(state.docs.clone(), state.blobs.clone(), &payload.doc_id)
+        .await
+        .map_err(sharding_error_response)?;
+
+    for doc_id in std::iter::once(&sharded.doc_id).chain(&sharded.shards) {
+        state.events.publish(NodeEvent::DocDropped { doc_id: doc_id.clone() });
+    }
+    Ok(Json(sharded))
+}
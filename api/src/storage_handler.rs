@@ -0,0 +1,52 @@
+use helpers::state::AppState;
+use gateway::access_control::check_node_id_and_domain_header;
+use core::storage::{storage_report, StorageError, StorageReport};
+use crate::backups_handler::check_default_author;
+
+use serde::Deserialize;
+use axum::{extract::{Query, State}, Json};
+use axum::http::{StatusCode, HeaderMap};
+
+// Most blobs `top` may ask for
+const MAX_TOP_BLOBS: usize = 1000;
+
+// Query parameters
+// 1. storage report
+#[derive(Deserialize)]
+pub struct StorageReportQuery {
+    /// Number of the most referenced blobs to list.
+    #[serde(default = "default_top")]
+    pub top: usize,
+}
+
+fn default_top() -> usize {
+    20
+}
+
+// Response bodies
+// 1. storage report
+// StorageReport
+
+// Maps a storage error to the response status
+fn storage_error_response(e: StorageError) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+// Handler for how the documents use the blob store, for capacity planning
+pub async fn storage_report_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<StorageReportQuery>,
+) -> Result<Json<StorageReport>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    if query.top > MAX_TOP_BLOBS {
+        return Err((StatusCode::BAD_REQUEST, format!("top must be at most {}", MAX_TOP_BLOBS)));
+    }
+
+    let report = storage_report(state.docs.clone(), state.blobs.clone(), query.top)
+        .await
+        .map_err(storage_error_response)?;
+    Ok(Json(report))
+}
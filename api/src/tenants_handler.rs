@@ -0,0 +1,350 @@
+use core::{authors::{create_author, list_authors}, docs::list_docs};
+use helpers::{
+    events::NodeEvent,
+    state::AppState,
+    tenants::{Tenant, TenantError, TenantQuota, API_KEY_PREFIX},
+};
+use gateway::access_control::check_node_id_and_domain_header;
+use crate::{backups_handler::check_default_author, request_schema, validation::{self, ValidatedJson}};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use axum::{extract::{Path, State}, Json};
+use axum::http::{StatusCode, HeaderMap};
+
+// Request bodies
+// 1. list tenants
+// No request body
+
+// 2. create tenant
+#[derive(Deserialize)]
+pub struct CreateTenantRequest {
+    /// 1 to 64 lowercase letters, digits and dashes.
+    pub id: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub quota: TenantQuota,
+}
+request_schema!(CreateTenantRequest, {
+    "id": json!({ "type": "string", "pattern": "^[a-z0-9-]{1,64}$" }),
+});
+
+// 3. get tenant
+// No request body
+
+// 4. delete tenant
+// No request body
+
+// 5. set tenant quota
+// TenantQuota, every limit optional
+
+// 6. assign document
+#[derive(Deserialize)]
+pub struct AssignDocRequest {
+    pub doc_id: String,
+}
+request_schema!(AssignDocRequest, {
+    "doc_id": validation::doc_id(),
+});
+
+// 7. unassign document
+// No request body
+
+// 8. add author
+#[derive(Deserialize, Default)]
+pub struct AddAuthorRequest {
+    /// An author of the node to give to the tenant. Omit to create a new one.
+    pub author_id: Option<String>,
+}
+request_schema!(AddAuthorRequest, {});
+
+// 9. remove author
+// No request body
+
+// 10. create API key
+#[derive(Deserialize, Default)]
+pub struct CreateApiKeyRequest {
+    pub label: Option<String>,
+}
+request_schema!(CreateApiKeyRequest, {});
+
+// 11. revoke API key
+// No request body
+
+// Response bodies
+// 1. list tenants
+#[derive(Serialize)]
+pub struct ListTenantsResponse {
+    pub tenants: Vec<TenantResponse>,
+}
+
+// 2. create tenant, 3. get tenant, 5. set tenant quota, 6. assign document, 7. unassign document,
+// 9. remove author, 11. revoke API key
+#[derive(Serialize)]
+pub struct TenantResponse {
+    pub id: String,
+    pub name: Option<String>,
+    pub created_at: u64,
+    pub docs: Vec<String>,
+    pub authors: Vec<String>,
+    pub quota: TenantQuota,
+    pub usage: TenantUsage,
+    pub api_keys: Vec<ApiKeyInfo>,
+}
+
+#[derive(Serialize)]
+pub struct TenantUsage {
+    pub docs: usize,
+    pub authors: usize,
+    /// Bytes of entry content in the tenant's documents, as far as they are indexed.
+    pub bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct ApiKeyInfo {
+    pub id: String,
+    pub label: Option<String>,
+    pub created_at: u64,
+}
+
+// 4. delete tenant
+#[derive(Serialize)]
+pub struct DeleteTenantResponse {
+    pub message: String,
+}
+
+// 8. add author
+#[derive(Serialize)]
+pub struct AddAuthorResponse {
+    pub author_id: String,
+    pub tenant: TenantResponse,
+}
+
+// 10. create API key
+#[derive(Serialize)]
+pub struct CreateApiKeyResponse {
+    pub id: String,
+    /// The key, shown this once. Send it as the `X-API-Key` header.
+    pub key: String,
+    pub label: Option<String>,
+    pub created_at: u64,
+}
+
+// Maps a tenant error to the response status
+fn tenant_error_response(e: TenantError) -> (StatusCode, String) {
+    let status = match &e {
+        TenantError::NotFound | TenantError::ApiKeyNotFound => StatusCode::NOT_FOUND,
+        TenantError::AlreadyExists | TenantError::DocAssigned(_) | TenantError::AuthorAssigned(_) => StatusCode::CONFLICT,
+        TenantError::InvalidId => StatusCode::BAD_REQUEST,
+        TenantError::QuotaExceeded(_) => StatusCode::FORBIDDEN,
+        TenantError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+fn tenant_response(state: &AppState, tenant: Tenant) -> TenantResponse {
+    TenantResponse {
+        usage: TenantUsage {
+            docs: tenant.docs.len(),
+            authors: tenant.authors.len(),
+            bytes: tenant.stored_bytes(&state.entry_index),
+        },
+        id: tenant.id,
+        name: tenant.name,
+        created_at: tenant.created_at,
+        docs: tenant.docs.into_iter().collect(),
+        authors: tenant.authors.into_iter().collect(),
+        quota: tenant.quota,
+        api_keys: tenant
+            .api_keys
+            .into_iter()
+            .map(|api_key| ApiKeyInfo { id: api_key.id, label: api_key.label, created_at: api_key.created_at })
+            .collect(),
+    }
+}
+
+// Handler for listing the tenants with their usage. Tenants are managed by the node's operator,
+// so like backups only the default author manages them.
+pub async fn list_tenants_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ListTenantsResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let tenants = state.tenants.list().into_iter().map(|tenant| tenant_response(&state, tenant)).collect();
+    Ok(Json(ListTenantsResponse { tenants }))
+}
+
+// Handler for creating a tenant, without documents, authors or API keys yet
+pub async fn create_tenant_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<CreateTenantRequest>,
+) -> Result<Json<TenantResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let tenant = state.tenants.create(payload.id, payload.name, payload.quota).await.map_err(tenant_error_response)?;
+    Ok(Json(tenant_response(&state, tenant)))
+}
+
+// Handler for getting one tenant with its usage
+pub async fn get_tenant_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<TenantResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let tenant = state.tenants.get(&id).ok_or_else(|| tenant_error_response(TenantError::NotFound))?;
+    Ok(Json(tenant_response(&state, tenant)))
+}
+
+// Handler for deleting a tenant and its API keys. Its documents and authors stay on the node.
+pub async fn delete_tenant_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<DeleteTenantResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    state.tenants.delete(&id).await.map_err(tenant_error_response)?;
+    Ok(Json(DeleteTenantResponse { message: "Tenant deleted successfully".to_string() }))
+}
+
+// Handler for replacing the quota of a tenant. Lowering it below the usage only stops the tenant
+// from adding more.
+pub async fn set_tenant_quota_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(quota): Json<TenantQuota>,
+) -> Result<Json<TenantResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let tenant = state.tenants.set_quota(&id, quota).await.map_err(tenant_error_response)?;
+    Ok(Json(tenant_response(&state, tenant)))
+}
+
+// Handler for giving a document of the node to a tenant
+pub async fn assign_tenant_doc_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    ValidatedJson(payload): ValidatedJson<AssignDocRequest>,
+) -> Result<Json<TenantResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let docs = list_docs(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !docs.iter().any(|(doc_id, _)| *doc_id == payload.doc_id) {
+        return Err((StatusCode::NOT_FOUND, "Document not found".to_string()));
+    }
+
+    let tenant = state.tenants.assign_doc(&id, &payload.doc_id).await.map_err(tenant_error_response)?;
+    Ok(Json(tenant_response(&state, tenant)))
+}
+
+// Handler for taking a document from a tenant. The document stays on the node.
+pub async fn unassign_tenant_doc_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((id, doc_id)): Path<(String, String)>,
+) -> Result<Json<TenantResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let tenant = state.tenants.unassign_doc(&id, &doc_id).await.map_err(tenant_error_response)?;
+    Ok(Json(tenant_response(&state, tenant)))
+}
+
+// Handler for giving an author to a tenant, an existing one or a new one
+pub async fn add_tenant_author_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    ValidatedJson(payload): ValidatedJson<AddAuthorRequest>,
+) -> Result<Json<AddAuthorResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let tenant = state.tenants.get(&id).ok_or_else(|| tenant_error_response(TenantError::NotFound))?;
+    let author_id = match payload.author_id {
+        Some(author_id) => {
+            let authors = list_authors(state.docs.clone())
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            if !authors.contains(&author_id) {
+                return Err((StatusCode::NOT_FOUND, "Author not found".to_string()));
+            }
+            author_id
+        }
+        None => {
+            // Don't leave an author behind that the tenant has no room for
+            if tenant.quota.max_authors.is_some_and(|max| tenant.authors.len() >= max) {
+                return Err(tenant_error_response(TenantError::QuotaExceeded("authors")));
+            }
+            let author_id = create_author(state.docs.clone(), state.chain.clone())
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            state.events.publish(NodeEvent::AuthorCreated { author_id: author_id.clone() });
+            author_id
+        }
+    };
+
+    let tenant = state.tenants.assign_author(&id, &author_id).await.map_err(tenant_error_response)?;
+    Ok(Json(AddAuthorResponse { author_id, tenant: tenant_response(&state, tenant) }))
+}
+
+// Handler for taking an author from a tenant. The author stays on the node.
+pub async fn remove_tenant_author_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((id, author_id)): Path<(String, String)>,
+) -> Result<Json<TenantResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let tenant = state.tenants.unassign_author(&id, &author_id).await.map_err(tenant_error_response)?;
+    Ok(Json(tenant_response(&state, tenant)))
+}
+
+// Handler for creating an API key of a tenant. Only its hash is kept, so the key is returned
+// this once.
+pub async fn create_api_key_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    ValidatedJson(payload): ValidatedJson<CreateApiKeyRequest>,
+) -> Result<Json<CreateApiKeyResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    // 244 random bits
+    let key = format!("{}{}{}", API_KEY_PREFIX, uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+    let api_key = state.tenants
+        .add_api_key(&id, uuid::Uuid::new_v4().to_string(), payload.label, &key)
+        .await
+        .map_err(tenant_error_response)?;
+
+    Ok(Json(CreateApiKeyResponse { id: api_key.id, key, label: api_key.label, created_at: api_key.created_at }))
+}
+
+// Handler for revoking an API key of a tenant, which is rejected from then on
+pub async fn revoke_api_key_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((id, key_id)): Path<(String, String)>,
+) -> Result<Json<TenantResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_default_author(&state, &headers).await?;
+
+    let tenant = state.tenants.revoke_api_key(&id, &key_id).await.map_err(tenant_error_response)?;
+    Ok(Json(tenant_response(&state, tenant)))
+}
@@ -0,0 +1,192 @@
+use std::{future::Future, pin::Pin};
+
+use axum::{
+    extract::{FromRequest, Request},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+pub use jsonschema::Validator;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Map, Value};
+
+/// A request body type with a JSON Schema that payloads are checked against before deserializing.
+///
+/// Implement it with the `request_schema!` macro next to the request struct.
+pub trait RequestSchema {
+    fn validator() -> &'static Validator;
+}
+
+/// Implements `RequestSchema` for a request body. Every listed field is required.
+///
+/// ```ignore
+/// request_schema!(GetDocumentRequest, {
+///     "doc_id": validation::doc_id(),
+/// });
+/// ```
+#[macro_export]
+macro_rules! request_schema {
+    ($ty:ty, { $($field:literal : $schema:expr),* $(,)? }) => {
+        impl $crate::validation::RequestSchema for $ty {
+            fn validator() -> &'static $crate::validation::Validator {
+                static VALIDATOR: std::sync::OnceLock<$crate::validation::Validator> = std::sync::OnceLock::new();
+                VALIDATOR.get_or_init(|| $crate::validation::object_validator(vec![$(($field, $schema)),*]))
+            }
+        }
+    };
+}
+
+// Field schemas
+/// A document ID: "d" followed by 64 lowercase hex characters.
+pub fn doc_id() -> Value {
+    json!({ "type": "string", "pattern": "^d[0-9a-f]{64}$" })
+}
+
+/// An SS58-encoded author ID.
+pub fn ss58() -> Value {
+    json!({ "type": "string", "pattern": "^[1-9A-HJ-NP-Za-km-z]{46,48}$" })
+}
+
+/// A blake3 hash, as 64 hex characters or 52 base32 characters.
+pub fn hash() -> Value {
+    json!({ "type": "string", "pattern": "^([0-9a-fA-F]{64}|[a-zA-Z2-7]{52})$" })
+}
+
+/// A node ID (ed25519 public key), as 64 hex characters or 52 base32 characters.
+pub fn node_id() -> Value {
+    hash()
+}
+
+/// An entry key: a non-empty string without whitespace.
+pub fn key() -> Value {
+    json!({ "type": "string", "pattern": "^\\S+$" })
+}
+
+/// Any non-empty string.
+pub fn non_empty() -> Value {
+    json!({ "type": "string", "minLength": 1 })
+}
+
+/// One of a fixed set of strings.
+pub fn one_of(values: &[&str]) -> Value {
+    json!({ "type": "string", "enum": values })
+}
+
+/// A non-empty array whose items match `items`.
+pub fn non_empty_array(items: Value) -> Value {
+    json!({ "type": "array", "items": items, "minItems": 1 })
+}
+
+/// An absolute http(s) URL.
+pub fn url() -> Value {
+    json!({ "type": "string", "pattern": "^https?://\\S+$" })
+}
+
+pub fn boolean() -> Value {
+    json!({ "type": "boolean" })
+}
+
+// Builds the validator for an object whose listed fields are all required
+pub fn object_validator(fields: Vec<(&str, Value)>) -> Validator {
+    let required: Vec<&str> = fields.iter().map(|(name, _)| *name).collect();
+    let properties: Map<String, Value> = fields
+        .into_iter()
+        .map(|(name, schema)| (name.to_string(), schema))
+        .collect();
+
+    let schema = json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    });
+
+    jsonschema::validator_for(&schema).expect("request schemas are static and must compile")
+}
+
+// Response bodies
+#[derive(Serialize)]
+pub struct FieldError {
+    /// JSON pointer to the invalid field, e.g. "/doc_id". Empty for errors on the whole body.
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct ValidationErrorResponse {
+    pub message: String,
+    pub errors: Vec<FieldError>,
+}
+
+/// Rejection returned when a body is not JSON or does not match its schema.
+pub enum ValidationRejection {
+    /// The body is missing, not JSON, or sent without a JSON content type.
+    InvalidJson(StatusCode, String),
+    /// The body is JSON but fails the schema.
+    InvalidFields(Vec<FieldError>),
+}
+
+impl IntoResponse for ValidationRejection {
+    fn into_response(self) -> Response {
+        match self {
+            ValidationRejection::InvalidJson(status, message) => (status, message).into_response(),
+            ValidationRejection::InvalidFields(errors) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ValidationErrorResponse {
+                    message: "Request body failed validation".to_string(),
+                    errors,
+                }),
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// JSON extractor that validates the body against `T`'s schema before deserializing it.
+///
+/// Responds with 422 and field-level errors when validation fails.
+pub struct ValidatedJson<T>(pub T);
+
+// `#[async_trait]` expands to `::core::...` paths, which resolve to the workspace `core`
+// crate here, so the boxed future it would generate is written out by hand.
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: RequestSchema + DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ValidationRejection;
+
+    fn from_request<'state, 'future>(
+        req: Request,
+        state: &'state S,
+    ) -> Pin<Box<dyn Future<Output = Result<Self, Self::Rejection>> + Send + 'future>>
+    where
+        'state: 'future,
+        Self: 'future,
+    {
+        Box::pin(async move {
+            let Json(value) = Json::<Value>::from_request(req, state)
+                .await
+                .map_err(|rejection| ValidationRejection::InvalidJson(rejection.status(), rejection.body_text()))?;
+
+            let errors: Vec<FieldError> = T::validator()
+                .iter_errors(&value)
+                .map(|error| FieldError {
+                    field: error.instance_path.to_string(),
+                    message: error.to_string(),
+                })
+                .collect();
+            if !errors.is_empty() {
+                return Err(ValidationRejection::InvalidFields(errors));
+            }
+
+            serde_json::from_value(value)
+                .map(ValidatedJson)
+                .map_err(|e| {
+                    ValidationRejection::InvalidFields(vec![FieldError {
+                        field: String::new(),
+                        message: e.to_string(),
+                    }])
+                })
+        })
+    }
+}
@@ -0,0 +1,255 @@
+use core::{
+    blobs::{complete_blob_size, BlobError},
+    docs::{get_entries, list_docs, ConflictResolution, DocError, EntryDetails},
+};
+use helpers::state::AppState;
+use crate::{
+    blobs_handler::{blob_range_response, content_type_of_name},
+    s3_handler::{http_time, url_encode, xml_escape},
+};
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+use iroh_blobs::Hash;
+use std::{collections::BTreeMap, fmt::Write, str::FromStr};
+
+// These handlers are only routed on the `--webdav-listen` address. File managers cannot send the
+// node ID header, so every document of the node is readable there
+
+const ALLOW: &str = "OPTIONS, GET, HEAD, PROPFIND";
+
+// A file of a folder: the latest version of a key
+struct File {
+    name: String,
+    hash: Hash,
+    size: u64,
+    // microseconds since the epoch
+    modified: u64,
+}
+
+// What a path of the mount names
+enum Resource {
+    // the documents of the node
+    Root(Vec<String>),
+    // the files and sub-folders of a document, or of a folder of one, keys being split at `/`
+    Folder { files: Vec<File>, folders: Vec<(String, u64)>, modified: u64 },
+    File(File),
+}
+
+// Handler for every request of the mount, as WebDAV adds its own methods
+pub async fn webdav_handler(
+    State(state): State<AppState>,
+    method: Method,
+    headers: HeaderMap,
+    path: Option<Path<String>>,
+) -> Response {
+    let path = path.map(|Path(path)| path).unwrap_or_default();
+
+    match method.as_str() {
+        "OPTIONS" => (
+            StatusCode::OK,
+            [(header::ALLOW, ALLOW), (header::HeaderName::from_static("dav"), "1")],
+        )
+            .into_response(),
+        "PROPFIND" | "GET" | "HEAD" => {
+            let resource = match resolve(&state, &path).await {
+                Ok(Some(resource)) => resource,
+                Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+                Err(response) => return response,
+            };
+            if method == "PROPFIND" {
+                propfind(&path, &headers, resource)
+            } else {
+                get(&state, &method, &headers, &path, resource).await
+            }
+        }
+        // Writes come later, the mount is read-only for now
+        "PUT" | "DELETE" | "MKCOL" | "COPY" | "MOVE" | "PROPPATCH" | "LOCK" | "UNLOCK" => {
+            (StatusCode::FORBIDDEN, "The mount is read-only").into_response()
+        }
+        _ => (StatusCode::METHOD_NOT_ALLOWED, [(header::ALLOW, ALLOW)]).into_response(),
+    }
+}
+
+// What `path`, `<doc_id>/<key>` or a folder of a document, names. `None` if nothing
+async fn resolve(state: &AppState, path: &str) -> Result<Option<Resource>, Response> {
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        let docs = list_docs(state.docs.clone()).await.map_err(internal_error)?;
+        return Ok(Some(Resource::Root(docs.into_iter().map(|(doc_id, _)| doc_id).collect())));
+    }
+
+    let (doc_id, key) = path.split_once('/').unwrap_or((path, ""));
+    let entries = match get_entries(state.docs.clone(), doc_id.to_string(), serde_json::json!({ "key_prefix": key })).await {
+        Ok(entries) => entries,
+        Err(DocError::InvalidDocumentIdFormat | DocError::DocumentNotFound) => return Ok(None),
+        Err(e) => return Err(internal_error(e)),
+    };
+    let mut versions: BTreeMap<String, Vec<EntryDetails>> = BTreeMap::new();
+    for entry in entries {
+        versions.entry(entry.namespace.key.clone()).or_default().push(entry);
+    }
+    let latest = |key: &str, versions: &[EntryDetails]| {
+        let entry = ConflictResolution::LastWriterWins.resolve(key, versions)?;
+        let hash = Hash::from_str(&entry.record.hash).ok()?;
+        Some((hash, entry.record.len, entry.record.timestamp))
+    };
+
+    if !key.is_empty() {
+        if let Some((hash, size, modified)) = versions.get(key).and_then(|versions| latest(key, versions)) {
+            let name = key.rsplit('/').next().unwrap_or(key).to_string();
+            return Ok(Some(Resource::File(File { name, hash, size, modified })));
+        }
+    }
+
+    let dir = if key.is_empty() { String::new() } else { format!("{}/", key) };
+    let mut files = Vec::new();
+    let mut folders: BTreeMap<String, u64> = BTreeMap::new();
+    for (entry_key, versions) in &versions {
+        let Some(rest) = entry_key.strip_prefix(&dir) else { continue };
+        let Some((hash, size, modified)) = latest(entry_key, versions) else { continue };
+        match rest.split_once('/') {
+            Some((folder, _)) => {
+                let folder_modified = folders.entry(folder.to_string()).or_default();
+                *folder_modified = (*folder_modified).max(modified);
+            }
+            None => files.push(File { name: rest.to_string(), hash, size, modified }),
+        }
+    }
+    // a key that is also a folder shows as the folder
+    files.retain(|file| !folders.contains_key(&file.name) && !file.name.is_empty());
+
+    if files.is_empty() && folders.is_empty() && !key.is_empty() {
+        return Ok(None);
+    }
+    let modified = files.iter().map(|file| file.modified).chain(folders.values().copied()).max().unwrap_or(0);
+    Ok(Some(Resource::Folder { files, folders: folders.into_iter().collect(), modified }))
+}
+
+// A multistatus of the resource, and of its children unless `Depth: 0`
+fn propfind(path: &str, headers: &HeaderMap, resource: Resource) -> Response {
+    let depth_zero = headers.get("depth").and_then(|value| value.to_str().ok()) == Some("0");
+    let base = format!("/{}", path.trim_matches('/'));
+    let base = base.trim_end_matches('/');
+    let name = base.rsplit('/').next().unwrap_or_default();
+
+    let mut body = String::from(r#"<?xml version="1.0" encoding="utf-8"?><D:multistatus xmlns:D="DAV:">"#);
+    match resource {
+        Resource::File(file) => write_file(&mut body, base, &file),
+        Resource::Root(docs) => {
+            write_folder(&mut body, &format!("{}/", base), name, 0);
+            if !depth_zero {
+                for doc_id in docs {
+                    write_folder(&mut body, &format!("{}/{}/", base, doc_id), &doc_id, 0);
+                }
+            }
+        }
+        Resource::Folder { files, folders, modified } => {
+            write_folder(&mut body, &format!("{}/", base), name, modified);
+            if !depth_zero {
+                for (folder, modified) in folders {
+                    write_folder(&mut body, &format!("{}/{}/", base, folder), &folder, modified);
+                }
+                for file in files {
+                    write_file(&mut body, &format!("{}/{}", base, file.name), &file);
+                }
+            }
+        }
+    }
+    body.push_str("</D:multistatus>");
+
+    (
+        StatusCode::MULTI_STATUS,
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        body,
+    )
+        .into_response()
+}
+
+fn write_folder(body: &mut String, href: &str, name: &str, modified: u64) {
+    let _ = write!(
+        body,
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop><D:displayname>{}</D:displayname><D:resourcetype><D:collection/></D:resourcetype><D:getlastmodified>{}</D:getlastmodified></D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+        xml_escape(&url_encode(href)),
+        xml_escape(name),
+        http_time(modified),
+    );
+}
+
+fn write_file(body: &mut String, href: &str, file: &File) {
+    let _ = write!(
+        body,
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop><D:displayname>{}</D:displayname><D:resourcetype/><D:getcontentlength>{}</D:getcontentlength><D:getcontenttype>{}</D:getcontenttype><D:getetag>&quot;{}&quot;</D:getetag><D:getlastmodified>{}</D:getlastmodified></D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+        xml_escape(&url_encode(href)),
+        xml_escape(&file.name),
+        file.size,
+        content_type_of_name(&file.name).unwrap_or("application/octet-stream"),
+        file.hash.to_hex(),
+        http_time(file.modified),
+    );
+}
+
+// The content of a file, or a page listing a folder for browsers
+async fn get(state: &AppState, method: &Method, headers: &HeaderMap, path: &str, resource: Resource) -> Response {
+    let file = match resource {
+        Resource::File(file) => file,
+        Resource::Root(docs) => {
+            let names = docs.into_iter().map(|doc_id| format!("{}/", doc_id)).collect();
+            return folder_page(path, names);
+        }
+        Resource::Folder { files, folders, .. } => {
+            let names = folders
+                .into_iter()
+                .map(|(folder, _)| format!("{}/", folder))
+                .chain(files.into_iter().map(|file| file.name))
+                .collect();
+            return folder_page(path, names);
+        }
+    };
+
+    let size = match complete_blob_size(state.blobs.clone(), file.hash.to_hex()).await {
+        Ok((_, size)) => size,
+        Err(BlobError::BlobNotComplete) => {
+            return (StatusCode::NOT_FOUND, "The content of the key is not on this node yet").into_response()
+        }
+        Err(e) => return internal_error(e),
+    };
+    let content_type = content_type_of_name(&file.name).unwrap_or("application/octet-stream");
+    let mut response = match blob_range_response(state, headers, file.hash, size, content_type).await {
+        Ok(response) => response,
+        Err(error) => return error.into_response(),
+    };
+    // a key is rewritten in place, unlike a blob
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    if let Ok(modified) = HeaderValue::from_str(&http_time(file.modified)) {
+        response_headers.insert(header::LAST_MODIFIED, modified);
+    }
+    if method == Method::HEAD {
+        *response.body_mut() = Body::empty();
+    }
+    response
+}
+
+fn folder_page(path: &str, names: Vec<String>) -> Response {
+    let base = format!("/{}/", path.trim_matches('/')).replace("//", "/");
+    let items: String = names
+        .iter()
+        .map(|name| format!("<li><a href=\"{}\">{}</a></li>", xml_escape(&url_encode(&format!("{}{}", base, name))), xml_escape(name)))
+        .collect();
+    let page = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title></head><body><h1>{}</h1><ul>{}</ul></body></html>",
+        xml_escape(&base),
+        xml_escape(&base),
+        items,
+    );
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], page).into_response()
+}
+
+fn internal_error(e: impl std::fmt::Display) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+}
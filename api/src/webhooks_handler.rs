@@ -0,0 +1,159 @@
+use helpers::{state::AppState, utils::get_author_id_from_headers, webhooks::{Webhook, WEBHOOK_EVENTS}};
+use gateway::access_control::check_node_id_and_domain_header;
+use crate::{request_schema, validation::{self, ValidatedJson}};
+
+use serde::{Deserialize, Serialize};
+use axum::{extract::State, Json};
+use axum::http::{StatusCode, HeaderMap};
+
+// Request bodies
+// 1. register webhook
+#[derive(Deserialize)]
+pub struct RegisterWebhookRequest {
+    pub url: String,
+    pub secret: String,
+    /// Watch a single document. Omit to receive events for every document.
+    pub doc_id: Option<String>,
+    /// Event types to receive. Omit or leave empty for all of them.
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+request_schema!(RegisterWebhookRequest, {
+    "url": validation::url(),
+    "secret": validation::non_empty(),
+});
+
+// 2. list webhooks
+// No request body
+
+// 3. delete webhook
+#[derive(Deserialize)]
+pub struct DeleteWebhookRequest {
+    pub id: String,
+}
+request_schema!(DeleteWebhookRequest, {
+    "id": validation::non_empty(),
+});
+
+// Response bodies
+// 1. register webhook
+#[derive(Serialize)]
+pub struct RegisterWebhookResponse {
+    pub id: String,
+}
+
+// 2. list webhooks
+#[derive(Serialize)]
+pub struct WebhookInfo {
+    pub id: String,
+    pub url: String,
+    pub doc_id: Option<String>,
+    pub events: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ListWebhooksResponse {
+    pub webhooks: Vec<WebhookInfo>,
+}
+
+// 3. delete webhook
+#[derive(Serialize)]
+pub struct DeleteWebhookResponse {
+    pub message: String,
+}
+
+// Checks that the caller is a registered author
+async fn check_registered_author(state: &AppState, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let caller_author_id = get_author_id_from_headers(headers)?;
+
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Only a registered author can perform this action".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+// Handler for registering a webhook
+pub async fn register_webhook_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<RegisterWebhookRequest>,
+) -> Result<Json<RegisterWebhookResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_registered_author(&state, &headers).await?;
+
+    // request body checks
+    if let Some(event) = payload.events.iter().find(|event| !WEBHOOK_EVENTS.contains(&event.as_str())) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Unknown event '{}', expected one of: {}", event, WEBHOOK_EVENTS.join(", ")),
+        ));
+    }
+    if let Some(doc_id) = &payload.doc_id {
+        let docs = core::docs::list_docs(state.docs.clone())
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if !docs.iter().any(|(id, _)| id == doc_id) {
+            return Err((StatusCode::NOT_FOUND, "Document not found".to_string()));
+        }
+    }
+
+    let webhook = Webhook {
+        id: uuid::Uuid::new_v4().to_string(),
+        url: payload.url,
+        secret: payload.secret,
+        doc_id: payload.doc_id,
+        events: payload.events,
+    };
+    let id = webhook.id.clone();
+
+    match state.webhooks.register(webhook).await {
+        Ok(()) => Ok(Json(RegisterWebhookResponse { id })),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+// Handler for listing webhooks. Secrets are never returned.
+pub async fn list_webhooks_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ListWebhooksResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_registered_author(&state, &headers).await?;
+
+    let webhooks = state
+        .webhooks
+        .list()
+        .into_iter()
+        .map(|hook| WebhookInfo {
+            id: hook.id,
+            url: hook.url,
+            doc_id: hook.doc_id,
+            events: hook.events,
+        })
+        .collect();
+
+    Ok(Json(ListWebhooksResponse { webhooks }))
+}
+
+// Handler for deleting a webhook
+pub async fn delete_webhook_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<DeleteWebhookRequest>,
+) -> Result<Json<DeleteWebhookResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_registered_author(&state, &headers).await?;
+
+    match state.webhooks.remove(&payload.id).await {
+        Ok(true) => Ok(Json(DeleteWebhookResponse { message: "Webhook deleted successfully".to_string() })),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Webhook not found".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
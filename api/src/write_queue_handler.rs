@@ -0,0 +1,116 @@
+use helpers::{state::AppState, utils::get_author_id_from_headers};
+use gateway::access_control::check_node_id_and_domain_header;
+use core::write_queue::{flush_write_queue, write_queue_report, DocWriteQueue, FlushOutcome, WriteQueueError};
+use crate::{request_schema, validation::ValidatedJson};
+
+use serde::{Deserialize, Serialize};
+use axum::{extract::{Query, State}, Json};
+use axum::http::{StatusCode, HeaderMap};
+
+// Request bodies
+// 1. write queue
+#[derive(Deserialize)]
+pub struct WriteQueueQuery {
+    /// Only list the writes of this document.
+    #[serde(default)]
+    pub doc_id: Option<String>,
+}
+
+// 2. flush write queue
+#[derive(Deserialize)]
+pub struct FlushWriteQueueRequest {
+    /// Only flush this document, even if it has no pending writes. Omit to flush every document
+    /// with pending writes.
+    #[serde(default)]
+    pub doc_id: Option<String>,
+}
+request_schema!(FlushWriteQueueRequest, {});
+
+// Response bodies
+// 1. write queue
+#[derive(Serialize)]
+pub struct WriteQueueResponse {
+    /// Whether the node is connected to its home relay, through which peers are reached when
+    /// they cannot be dialed directly.
+    pub relay_connected: bool,
+    /// Pending writes over all listed documents.
+    pub pending: usize,
+    pub docs: Vec<DocWriteQueue>,
+}
+
+// 2. flush write queue
+#[derive(Serialize)]
+pub struct FlushedDoc {
+    pub doc_id: String,
+    #[serde(flatten)]
+    pub outcome: FlushOutcome,
+}
+
+#[derive(Serialize)]
+pub struct FlushWriteQueueResponse {
+    pub relay_connected: bool,
+    pub docs: Vec<FlushedDoc>,
+}
+
+// Maps a write queue error to the response status
+fn write_queue_error_response(e: WriteQueueError) -> (StatusCode, String) {
+    let status = match &e {
+        WriteQueueError::InvalidDocumentIdFormat => StatusCode::BAD_REQUEST,
+        WriteQueueError::DocumentNotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+async fn check_registered_author(state: &AppState, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let caller_author_id = get_author_id_from_headers(headers)?;
+    let authors = core::authors::list_authors(state.docs.clone())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err((StatusCode::FORBIDDEN, "Only a registered author can perform this action".to_string()));
+    }
+    Ok(())
+}
+
+fn relay_connected(state: &AppState) -> bool {
+    state.blobs.endpoint().home_relay().get().ok().flatten().is_some()
+}
+
+// Handler for the local writes not propagated to any peer yet
+pub async fn write_queue_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<WriteQueueQuery>,
+) -> Result<Json<WriteQueueResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_registered_author(&state, &headers).await?;
+
+    let docs = write_queue_report(&state.write_queue, &state.sync_controls, query.doc_id)
+        .map_err(write_queue_error_response)?;
+
+    Ok(Json(WriteQueueResponse {
+        relay_connected: relay_connected(&state),
+        pending: docs.iter().map(|doc| doc.pending).sum(),
+        docs,
+    }))
+}
+
+// Handler for syncing documents with pending writes with their peers now
+pub async fn flush_write_queue_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<FlushWriteQueueRequest>,
+) -> Result<Json<FlushWriteQueueResponse>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+    check_registered_author(&state, &headers).await?;
+
+    let outcomes = flush_write_queue(state.docs.clone(), &state.write_queue, &state.sync_controls, payload.doc_id)
+        .await
+        .map_err(write_queue_error_response)?;
+
+    Ok(Json(FlushWriteQueueResponse {
+        relay_connected: relay_connected(&state),
+        docs: outcomes.into_iter().map(|(doc_id, outcome)| FlushedDoc { doc_id, outcome }).collect(),
+    }))
+}
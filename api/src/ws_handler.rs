@@ -0,0 +1,243 @@
+use core::{docs::subscribe_doc, blobs::download_blob_progress};
+use helpers::state::AppState;
+use gateway::access_control::check_node_id_and_domain_header;
+
+use axum::{
+    extract::{State, ws::{Message, WebSocket, WebSocketUpgrade}},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use futures::{SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+
+// The server stops forwarding events once this many have been sent without an ack.
+const MAX_UNACKED_EVENTS: u64 = 256;
+// Capacity of the channel between subscription tasks and the socket writer.
+const OUTGOING_BUFFER: usize = 64;
+
+// Client messages
+// 1. subscribe
+/* example messages:
+{ "op": "subscribe", "id": "my-doc", "topic": "doc", "doc_id": "d..." }
+{ "op": "subscribe", "id": "my-download", "topic": "download", "hash": "...", "node_id": "..." }
+{ "op": "subscribe", "id": "node", "topic": "node" }
+*/
+// 2. unsubscribe
+// { "op": "unsubscribe", "id": "my-doc" }
+// 3. ack
+// { "op": "ack", "seq": 42 }
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum WsClientMessage {
+    Subscribe {
+        id: String,
+        #[serde(flatten)]
+        topic: WsTopic,
+    },
+    Unsubscribe {
+        id: String,
+    },
+    Ack {
+        seq: u64,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "topic", rename_all = "snake_case")]
+pub enum WsTopic {
+    /// Live events of a single document.
+    Doc { doc_id: String },
+    /// Progress of a blob download from a peer.
+    Download { hash: String, node_id: String },
+    /// Node-level events (documents created/dropped, authors, blobs).
+    Node,
+}
+
+// Server messages
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsServerMessage {
+    Subscribed { id: String },
+    Unsubscribed { id: String },
+    Event { id: String, seq: u64, event: Value },
+    /// The subscription's source ended (document closed, download finished).
+    Closed { id: String },
+    Error { id: Option<String>, message: String },
+}
+
+// Messages from subscription tasks to the socket writer
+enum Outgoing {
+    Event { id: String, event: Value },
+    /// `generation` tells a finished subscription apart from a later one reusing its id.
+    Closed { id: String, generation: u64 },
+    Error { id: String, message: String },
+}
+
+// Handler for upgrading a request to the `/ws` WebSocket API
+pub async fn ws_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Result<Response, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, state)))
+}
+
+async fn handle_socket(socket: WebSocket, state: AppState) {
+    let (mut sink, mut stream) = socket.split();
+    let (tx, mut rx) = mpsc::channel::<Outgoing>(OUTGOING_BUFFER);
+    let mut subscriptions: HashMap<String, (u64, JoinHandle<()>)> = HashMap::new();
+    let mut generation: u64 = 0;
+    let mut seq: u64 = 0;
+    let mut acked: u64 = 0;
+
+    loop {
+        let can_send = seq - acked < MAX_UNACKED_EVENTS;
+
+        let reply = tokio::select! {
+            incoming = stream.next() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<WsClientMessage>(&text) {
+                        Ok(WsClientMessage::Subscribe { id, topic }) => {
+                            generation += 1;
+                            subscribe(&state, &tx, &mut subscriptions, generation, id, topic).await
+                        }
+                        Ok(WsClientMessage::Unsubscribe { id }) => match subscriptions.remove(&id) {
+                            Some((_, handle)) => {
+                                handle.abort();
+                                WsServerMessage::Unsubscribed { id }
+                            }
+                            None => WsServerMessage::Error {
+                                id: Some(id),
+                                message: "Unknown subscription id".to_string(),
+                            },
+                        },
+                        Ok(WsClientMessage::Ack { seq: ack }) => {
+                            acked = acked.max(ack.min(seq));
+                            continue;
+                        }
+                        Err(e) => WsServerMessage::Error {
+                            id: None,
+                            message: format!("Invalid message: {}", e),
+                        },
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            Some(outgoing) = rx.recv(), if can_send => match outgoing {
+                Outgoing::Event { id, event } => {
+                    seq += 1;
+                    WsServerMessage::Event { id, seq, event }
+                }
+                Outgoing::Closed { id, generation } => {
+                    // a subscription that was unsubscribed, and maybe replaced, is already answered for
+                    if !matches!(subscriptions.get(&id), Some((current, _)) if *current == generation) {
+                        continue;
+                    }
+                    subscriptions.remove(&id);
+                    WsServerMessage::Closed { id }
+                }
+                Outgoing::Error { id, message } => WsServerMessage::Error { id: Some(id), message },
+            },
+        };
+
+        let text = match serde_json::to_string(&reply) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        if sink.send(Message::Text(text)).await.is_err() {
+            break;
+        }
+    }
+
+    for (_, (_, handle)) in subscriptions.drain() {
+        handle.abort();
+    }
+}
+
+// Starts a subscription task for the given topic and returns the reply for the client.
+async fn subscribe(
+    state: &AppState,
+    tx: &mpsc::Sender<Outgoing>,
+    subscriptions: &mut HashMap<String, (u64, JoinHandle<()>)>,
+    generation: u64,
+    id: String,
+    topic: WsTopic,
+) -> WsServerMessage {
+    if subscriptions.contains_key(&id) {
+        return WsServerMessage::Error {
+            id: Some(id),
+            message: "Subscription id is already in use".to_string(),
+        };
+    }
+
+    let handle = match topic {
+        WsTopic::Doc { doc_id } => match subscribe_doc(state.docs.clone(), doc_id).await {
+            Ok(events) => tokio::spawn(forward_stream(id.clone(), generation, events, tx.clone())),
+            Err(e) => return WsServerMessage::Error { id: Some(id), message: e.to_string() },
+        },
+        WsTopic::Download { hash, node_id } => {
+            match download_blob_progress(state.blobs.clone(), hash, node_id).await {
+                Ok(progress) => tokio::spawn(forward_stream(id.clone(), generation, progress, tx.clone())),
+                Err(e) => return WsServerMessage::Error { id: Some(id), message: e.to_string() },
+            }
+        }
+        WsTopic::Node => tokio::spawn(forward_node_events(id.clone(), generation, state.events.subscribe(), tx.clone())),
+    };
+
+    subscriptions.insert(id.clone(), (generation, handle));
+    WsServerMessage::Subscribed { id }
+}
+
+// Forwards every item of a stream to the socket writer until the stream ends.
+async fn forward_stream<S, T, E>(id: String, generation: u64, stream: S, tx: mpsc::Sender<Outgoing>)
+where
+    S: Stream<Item = Result<T, E>>,
+    T: Serialize,
+    E: std::fmt::Display,
+{
+    futures::pin_mut!(stream);
+    while let Some(item) = stream.next().await {
+        let outgoing = match item.map(|event| serde_json::to_value(event)) {
+            Ok(Ok(event)) => Outgoing::Event { id: id.clone(), event },
+            Ok(Err(e)) => Outgoing::Error { id: id.clone(), message: e.to_string() },
+            Err(e) => Outgoing::Error { id: id.clone(), message: e.to_string() },
+        };
+        if tx.send(outgoing).await.is_err() {
+            return;
+        }
+    }
+    let _ = tx.send(Outgoing::Closed { id, generation }).await;
+}
+
+// Forwards node events to the socket writer, reporting events missed by a slow client.
+async fn forward_node_events(
+    id: String,
+    generation: u64,
+    mut events: broadcast::Receiver<helpers::events::NodeEvent>,
+    tx: mpsc::Sender<Outgoing>,
+) {
+    loop {
+        let outgoing = match events.recv().await {
+            Ok(event) => match serde_json::to_value(event) {
+                Ok(event) => Outgoing::Event { id: id.clone(), event },
+                Err(e) => Outgoing::Error { id: id.clone(), message: e.to_string() },
+            },
+            Err(broadcast::error::RecvError::Lagged(missed)) => Outgoing::Error {
+                id: id.clone(),
+                message: format!("Missed {} node events", missed),
+            },
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if tx.send(outgoing).await.is_err() {
+            return;
+        }
+    }
+    let _ = tx.send(Outgoing::Closed { id, generation }).await;
+}
@@ -0,0 +1,152 @@
+use crate::commands::{client::Page, target::Target};
+use helpers::{cli::{AuthorArgs, AuthorCommand}, pagination::MAX_PAGE_LIMIT};
+use starter_kit_core::authors;
+
+use serde_json::{json, Value};
+use std::error::Error;
+use std::io::Write;
+use std::path::PathBuf;
+
+pub async fn run(args: AuthorArgs) -> Result<(), Box<dyn Error>> {
+    let target = Target::connect(&args.target).await?;
+    let result = execute(&target, args.command).await;
+    target.close().await?;
+    result
+}
+
+/// Runs one `author` command on `target`, also used by `console`.
+pub async fn execute(target: &Target, command: AuthorCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        AuthorCommand::Create => create(target).await,
+        AuthorCommand::List => list(target).await,
+        AuthorCommand::SetDefault { author_id } => set_default(target, author_id).await,
+        AuthorCommand::Delete { author_id } => delete(target, author_id).await,
+        AuthorCommand::Export { author_id, output } => export(target, author_id, output).await,
+    }
+}
+
+async fn create(target: &Target) -> Result<(), Box<dyn Error>> {
+    let author_id = match target {
+        // Registering the CORD profile needs the chain, which only a running node is connected to
+        Target::Local { store, .. } => {
+            let author_id = authors::create_local_author(store.docs.clone()).await?;
+            eprintln!("ℹ️  No CORD profile was registered. Use --url to create authors with a profile.");
+            author_id
+        }
+        Target::Remote(client) => {
+            let response: Value = client.post("/authors/create-author", &json!({})).await?;
+            response["author_id"]
+                .as_str()
+                .ok_or("❌ Unexpected response from /authors/create-author")?
+                .to_string()
+        }
+    };
+
+    eprintln!("✅ Created author");
+    println!("{}", author_id);
+    Ok(())
+}
+
+async fn list(target: &Target) -> Result<(), Box<dyn Error>> {
+    let (author_ids, default_author) = match target {
+        Target::Local { store, .. } => (
+            authors::list_authors(store.docs.clone()).await?,
+            authors::get_default_author(store.docs.clone()).await?,
+        ),
+        Target::Remote(client) => {
+            let mut author_ids = Vec::new();
+            let mut cursor: Option<String> = None;
+            loop {
+                let mut path = format!("/authors/list-authors?limit={}", MAX_PAGE_LIMIT);
+                if let Some(cursor) = &cursor {
+                    path.push_str(&format!("&cursor={}", cursor));
+                }
+                let page: Page<String> = client.get(&path).await?;
+                author_ids.extend(page.items);
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+            let response: Value = client.get("/authors/get-default-author").await?;
+            let default_author = response["default_author"]
+                .as_str()
+                .ok_or("❌ Unexpected response from /authors/get-default-author")?
+                .to_string();
+            (author_ids, default_author)
+        }
+    };
+
+    for author_id in author_ids {
+        if author_id == default_author {
+            println!("{}  (default)", author_id);
+        } else {
+            println!("{}", author_id);
+        }
+    }
+    Ok(())
+}
+
+async fn set_default(target: &Target, author_id: String) -> Result<(), Box<dyn Error>> {
+    match target {
+        Target::Local { store, .. } => authors::set_default_author(store.docs.clone(), author_id.clone()).await?,
+        Target::Remote(client) => {
+            let _: Value = client
+                .post("/authors/set-default-author", &json!({ "author_id": author_id }))
+                .await?;
+        }
+    }
+
+    eprintln!("✅ {} is now the default author", author_id);
+    Ok(())
+}
+
+async fn delete(target: &Target, author_id: String) -> Result<(), Box<dyn Error>> {
+    match target {
+        Target::Local { store, .. } => {
+            // Deleting the default author leaves the node without one until it is set again
+            if authors::get_default_author(store.docs.clone()).await? == author_id {
+                return Err("❌ Cannot delete the default author. Set another author as the default first.".into());
+            }
+            authors::delete_author(store.docs.clone(), author_id.clone()).await?
+        }
+        Target::Remote(client) => {
+            let _: Value = client
+                .post("/authors/delete-author", &json!({ "author_id": author_id }))
+                .await?;
+        }
+    }
+
+    eprintln!("✅ Deleted author {}", author_id);
+    Ok(())
+}
+
+async fn export(target: &Target, author_id: String, output: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    // Secret keys are never sent over the API, so exporting needs the stopped node's store
+    let Target::Local { store, .. } = target else {
+        return Err("❌ Authors can only be exported with --path, from a stopped node.".into());
+    };
+    let secret = authors::export_author(store.docs.clone(), author_id.clone()).await?;
+
+    match output {
+        Some(output) => {
+            write_private(&output, secret.as_bytes())?;
+            eprintln!("✅ Wrote the secret key of {} to {:?}", author_id, output);
+        }
+        None => println!("{}", secret),
+    }
+    eprintln!("⚠️  Anyone with this key can write as {}. Store it somewhere safe.", author_id);
+    Ok(())
+}
+
+// Creates the file readable by its owner only, since it holds a secret key
+fn write_private(path: &PathBuf, content: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(content)
+}
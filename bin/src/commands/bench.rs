@@ -0,0 +1,333 @@
+use helpers::cli::{BenchArgs, BenchWorkload};
+
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::sync::{atomic::{AtomicUsize, Ordering}, Arc, RwLock};
+use std::time::{Duration, Instant};
+
+// Key prefix of the entries written by the `entries` workload
+const KEY_PREFIX: &str = "bench/";
+
+/// What the writers share with the readers: the keys or blob hashes written so far.
+struct Shared {
+    client: reqwest::Client,
+    base_url: String,
+    author_id: String,
+    doc_id: Option<String>,
+    payload: String,
+    keys: usize,
+    /// Entries: writes started, which go through the keys in order.
+    next_write: AtomicUsize,
+    /// Entries: number of keys written at least once, readers only read those.
+    written: AtomicUsize,
+    /// Blobs: hashes of the blobs written, up to `keys` of them.
+    hashes: RwLock<Vec<String>>,
+    measure_from: Instant,
+    deadline: Instant,
+}
+
+/// Latencies and failures of one worker, or of all workers of a kind once merged.
+#[derive(Default)]
+struct Samples {
+    latencies_us: Vec<u64>,
+    errors: u64,
+    bytes: u64,
+}
+
+/// The measured results of one kind of request.
+#[derive(Serialize)]
+struct OperationReport {
+    requests: u64,
+    errors: u64,
+    requests_per_sec: f64,
+    mb_per_sec: f64,
+    p50_ms: f64,
+    p90_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+}
+
+#[derive(Serialize)]
+struct BenchReport {
+    workload: String,
+    writers: usize,
+    readers: usize,
+    payload_size: usize,
+    duration_secs: u64,
+    writes: OperationReport,
+    reads: OperationReport,
+}
+
+// Drives a running node with concurrent writers and readers and prints what it sustained
+pub async fn run(args: BenchArgs) -> Result<(), Box<dyn Error>> {
+    if args.writers == 0 {
+        return Err("❌ --writers must be greater than 0, readers only read what writers wrote".into());
+    }
+    if args.payload_size == 0 || args.keys == 0 || args.duration == 0 {
+        return Err("❌ --payload-size, --keys and --duration must be greater than 0".into());
+    }
+
+    let base_url = args.url.trim_end_matches('/').to_string();
+    let mut headers = HeaderMap::new();
+    headers.insert("nodeId", HeaderValue::from_str(&args.node_id)?);
+    let client = reqwest::Client::builder().default_headers(headers.clone()).build()?;
+
+    let author_id = match args.author_id.clone() {
+        Some(author_id) => author_id,
+        None => {
+            let response: Value = send(client.get(format!("{}/authors/get-default-author", base_url))).await.map_err(|e| e.to_string())?.json().await?;
+            response["default_author"]
+                .as_str()
+                .ok_or("❌ The node did not return a default author, pass --author-id")?
+                .to_string()
+        }
+    };
+    headers.insert("author-id", HeaderValue::from_str(&author_id)?);
+    let client = reqwest::Client::builder().default_headers(headers).pool_max_idle_per_host(args.writers + args.readers).build()?;
+
+    let doc_id = match (args.workload, args.doc_id.clone()) {
+        (BenchWorkload::Entries, Some(doc_id)) => Some(doc_id),
+        (BenchWorkload::Entries, None) => {
+            let response: Value = send(client.post(format!("{}/docs/create-document", base_url))).await.map_err(|e| e.to_string())?.json().await?;
+            let doc_id = response["doc_id"].as_str().ok_or("❌ The node did not return the created document")?.to_string();
+            eprintln!("📄 Writing to new document {}", doc_id);
+            Some(doc_id)
+        }
+        (BenchWorkload::Blobs, _) => None,
+    };
+
+    let started = Instant::now();
+    let measure_from = started + Duration::from_secs(args.warmup);
+    let shared = Arc::new(Shared {
+        client,
+        base_url,
+        author_id,
+        doc_id,
+        payload: "x".repeat(args.payload_size),
+        keys: args.keys,
+        next_write: AtomicUsize::new(0),
+        written: AtomicUsize::new(0),
+        hashes: RwLock::new(Vec::new()),
+        measure_from,
+        deadline: measure_from + Duration::from_secs(args.duration),
+    });
+
+    eprintln!(
+        "🏋️  Running {:?} with {} writer(s) and {} reader(s) for {}s{}...",
+        args.workload,
+        args.writers,
+        args.readers,
+        args.duration,
+        if args.warmup > 0 { format!(" after {}s of warmup", args.warmup) } else { String::new() },
+    );
+
+    let writers: Vec<_> = (0..args.writers)
+        .map(|worker| tokio::spawn(run_worker(shared.clone(), args.workload, true, worker)))
+        .collect();
+    let readers: Vec<_> = (0..args.readers)
+        .map(|worker| tokio::spawn(run_worker(shared.clone(), args.workload, false, args.writers + worker)))
+        .collect();
+
+    let mut writes = Samples::default();
+    for writer in writers {
+        writes.merge(writer.await?);
+    }
+    let mut reads = Samples::default();
+    for reader in readers {
+        reads.merge(reader.await?);
+    }
+
+    let elapsed = shared.deadline.saturating_duration_since(shared.measure_from).as_secs_f64();
+    let report = BenchReport {
+        workload: format!("{:?}", args.workload).to_lowercase(),
+        writers: args.writers,
+        readers: args.readers,
+        payload_size: args.payload_size,
+        duration_secs: args.duration,
+        writes: writes.report(elapsed),
+        reads: reads.report(elapsed),
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+// Sends requests of one kind until the deadline, recording those sent after the warmup
+async fn run_worker(shared: Arc<Shared>, workload: BenchWorkload, writer: bool, worker: usize) -> Samples {
+    let mut samples = Samples::default();
+    // xorshift state, so each worker walks the keys in its own order
+    let mut state = (worker as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let mut next_index = move |bound: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % bound as u64) as usize
+    };
+
+    loop {
+        let now = Instant::now();
+        if now >= shared.deadline {
+            break;
+        }
+
+        let result = match (workload, writer) {
+            (BenchWorkload::Entries, true) => Some(write_entry(&shared, shared.next_write.fetch_add(1, Ordering::Relaxed) % shared.keys).await),
+            (BenchWorkload::Entries, false) => match shared.written.load(Ordering::Relaxed) {
+                0 => None,
+                written => Some(read_entry(&shared, next_index(written)).await),
+            },
+            (BenchWorkload::Blobs, true) => Some(write_blob(&shared).await),
+            (BenchWorkload::Blobs, false) => {
+                let hash = {
+                    let hashes = shared.hashes.read().unwrap();
+                    (!hashes.is_empty()).then(|| hashes[next_index(hashes.len())].clone())
+                };
+                match hash {
+                    Some(hash) => Some(read_blob(&shared, &hash).await),
+                    None => None,
+                }
+            }
+        };
+
+        // Readers wait for the writers' first writes
+        let Some(result) = result else {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            continue;
+        };
+        if now < shared.measure_from || Instant::now() > shared.deadline {
+            continue;
+        }
+        match result {
+            Ok(bytes) => {
+                samples.latencies_us.push(now.elapsed().as_micros() as u64);
+                samples.bytes += bytes;
+            }
+            Err(_) => samples.errors += 1,
+        }
+    }
+    samples
+}
+
+// Each request returns the payload bytes it moved
+type RequestResult = Result<u64, Box<dyn Error + Send + Sync>>;
+
+async fn write_entry(shared: &Shared, index: usize) -> RequestResult {
+    let body = json!({
+        "doc_id": shared.doc_id,
+        "author_id": shared.author_id,
+        "key": format!("{}{}", KEY_PREFIX, index),
+        "value": shared.payload,
+    });
+    let result = send(shared.client.post(format!("{}/docs/set-entry", shared.base_url)).json(&body)).await;
+    if result.is_ok() {
+        // Writes of lower keys may still be in flight, so a reader may rarely miss one at the start
+        shared.written.fetch_max(index + 1, Ordering::Relaxed);
+    }
+    result.map(|_| shared.payload.len() as u64)
+}
+
+async fn read_entry(shared: &Shared, index: usize) -> RequestResult {
+    let body = json!({
+        "doc_id": shared.doc_id,
+        "author_id": shared.author_id,
+        "key": format!("{}{}", KEY_PREFIX, index),
+        "include_empty": false,
+    });
+    let response = send(shared.client.post(format!("{}/docs/get-entry", shared.base_url)).json(&body)).await?;
+    Ok(response.bytes().await?.len() as u64)
+}
+
+async fn write_blob(shared: &Shared) -> RequestResult {
+    // Blobs are content-addressed, so each one gets a distinct prefix to be stored anew
+    let content = format!("{:x}-{}", unique_prefix(), shared.payload);
+    let body = json!({ "content": content });
+    let response: Value = send(shared.client.post(format!("{}/blobs/add-blob-bytes", shared.base_url)).json(&body))
+        .await?
+        .json()
+        .await?;
+    let hash = response["hash"].as_str().ok_or("no hash in response")?.to_string();
+    let mut hashes = shared.hashes.write().unwrap();
+    if hashes.len() < shared.keys {
+        hashes.push(hash);
+    }
+    Ok(content.len() as u64)
+}
+
+async fn read_blob(shared: &Shared, hash: &str) -> RequestResult {
+    let response = send(shared.client.get(format!("{}/blobs/{}/raw", shared.base_url, hash))).await?;
+    Ok(response.bytes().await?.len() as u64)
+}
+
+// A value unlikely to repeat across writes of one run
+fn unique_prefix() -> u128 {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    nanos ^ ((COUNTER.fetch_add(1, Ordering::Relaxed) as u128) << 64)
+}
+
+// Sends a request, turning a non-2xx response into an error
+async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response, Box<dyn Error + Send + Sync>> {
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("❌ Request failed with {}: {}", status, body).into());
+    }
+    Ok(response)
+}
+
+impl Samples {
+    fn merge(&mut self, other: Samples) {
+        self.latencies_us.extend(other.latencies_us);
+        self.errors += other.errors;
+        self.bytes += other.bytes;
+    }
+
+    fn report(mut self, elapsed_secs: f64) -> OperationReport {
+        self.latencies_us.sort_unstable();
+        let percentile = |p: f64| -> f64 {
+            if self.latencies_us.is_empty() {
+                return 0.0;
+            }
+            let rank = ((p / 100.0) * self.latencies_us.len() as f64).ceil() as usize;
+            self.latencies_us[rank.clamp(1, self.latencies_us.len()) - 1] as f64 / 1000.0
+        };
+        let requests = self.latencies_us.len() as u64;
+        OperationReport {
+            requests,
+            errors: self.errors,
+            requests_per_sec: requests as f64 / elapsed_secs,
+            mb_per_sec: self.bytes as f64 / elapsed_secs / (1024.0 * 1024.0),
+            p50_ms: percentile(50.0),
+            p90_ms: percentile(90.0),
+            p99_ms: percentile(99.0),
+            max_ms: percentile(100.0),
+        }
+    }
+}
+
+fn print_report(report: &BenchReport) {
+    println!(
+        "\n📊 {} workload, {} writer(s), {} reader(s), {} byte payloads, {}s\n",
+        report.workload, report.writers, report.readers, report.payload_size, report.duration_secs
+    );
+    println!(
+        "   {:<7} {:>9} {:>7} {:>10} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        "", "requests", "errors", "req/s", "MB/s", "p50 ms", "p90 ms", "p99 ms", "max ms"
+    );
+    for (name, op) in [("writes", &report.writes), ("reads", &report.reads)] {
+        println!(
+            "   {:<7} {:>9} {:>7} {:>10.1} {:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>9.2}",
+            name, op.requests, op.errors, op.requests_per_sec, op.mb_per_sec, op.p50_ms, op.p90_ms, op.p99_ms, op.max_ms
+        );
+    }
+}
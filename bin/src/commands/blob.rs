@@ -0,0 +1,153 @@
+use crate::commands::{client::Page, target::Target};
+use helpers::{cli::{BlobArgs, BlobCommand}, pagination::MAX_PAGE_LIMIT};
+
+use iroh_blobs::Hash;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{error::Error, path::{Path, PathBuf}, str::FromStr};
+use tokio::io::AsyncWriteExt;
+
+/// A blob as listed by `blob ls`.
+#[derive(Debug, Deserialize)]
+struct BlobRow {
+    hash: String,
+    size: u64,
+    path: String,
+}
+
+pub async fn run(args: BlobArgs) -> Result<(), Box<dyn Error>> {
+    let target = Target::connect(&args.target).await?;
+    let result = execute(&target, args.command).await;
+    target.close().await?;
+    result
+}
+
+/// Runs one `blob` command on `target`, also used by `console`.
+pub async fn execute(target: &Target, command: BlobCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        BlobCommand::Add { path, name } => add(target, &path, name).await,
+        BlobCommand::Get { hash, output } => get(target, &hash, output).await,
+        BlobCommand::Ls => ls(target).await,
+    }
+}
+
+async fn add(target: &Target, path: &Path, name: Option<String>) -> Result<(), Box<dyn Error>> {
+    let (hash, size) = match target {
+        Target::Local { store, .. } => {
+            let outcome = match name {
+                Some(name) => {
+                    let bytes = tokio::fs::read(path).await?;
+                    starter_kit_core::blobs::add_blob_named(store.blobs.clone(), bytes, name).await?
+                }
+                None => starter_kit_core::blobs::add_blob_from_path(store.blobs.clone(), path).await?,
+            };
+            (outcome.hash.to_string(), outcome.size)
+        }
+        Target::Remote(client) => {
+            // The API takes blob content as a JSON string, so only text files can be sent
+            let content = tokio::fs::read_to_string(path).await.map_err(|e| {
+                format!("❌ Failed to read {:?} as UTF-8 text: {}. Binary files can only be added with --path.", path, e)
+            })?;
+            let response: Value = match name {
+                Some(name) => {
+                    client
+                        .post("/blobs/add-blob-named", &json!({ "content": content, "name": name }))
+                        .await?
+                }
+                None => client.post("/blobs/add-blob-bytes", &json!({ "content": content })).await?,
+            };
+            let hash = response["hash"]
+                .as_str()
+                .ok_or("❌ Unexpected response from the blobs API")?
+                .to_string();
+            (hash, response["size"].as_u64().unwrap_or_default())
+        }
+    };
+
+    eprintln!("✅ Added {:?} ({} bytes)", path, size);
+    println!("{}", hash);
+    Ok(())
+}
+
+async fn get(target: &Target, hash: &str, output: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let expected = Hash::from_str(hash).map_err(|_| format!("❌ {} is not a valid blob hash", hash))?;
+
+    let bytes = match target {
+        // The local store verifies content against the hash as it reads
+        Target::Local { store, .. } => {
+            if let Some(output) = &output {
+                let destination = std::path::absolute(output)?;
+                starter_kit_core::blobs::export_blob_to_file(store.blobs.clone(), hash.to_string(), destination).await?;
+                eprintln!("✅ Wrote {} to {:?}", hash, output);
+                return Ok(());
+            }
+            starter_kit_core::blobs::read_blob_bytes(store.blobs.clone(), hash.to_string())
+                .await?
+                .to_vec()
+        }
+        Target::Remote(client) => {
+            let bytes = client.get_bytes(&format!("/blobs/{}/download", hash)).await?;
+            let actual = Hash::new(&bytes);
+            if actual != expected {
+                return Err(format!("❌ Content does not match {}: received {}", hash, actual).into());
+            }
+            bytes
+        }
+    };
+
+    match output {
+        Some(output) => {
+            tokio::fs::write(&output, &bytes).await?;
+            eprintln!("✅ Wrote {} to {:?}", hash, output);
+        }
+        None => {
+            let mut stdout = tokio::io::stdout();
+            stdout.write_all(&bytes).await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+async fn ls(target: &Target) -> Result<(), Box<dyn Error>> {
+    let mut blobs = Vec::new();
+
+    match target {
+        Target::Local { store, .. } => {
+            let mut after = None;
+            loop {
+                let page = starter_kit_core::blobs::list_blobs_after(store.blobs.clone(), after, MAX_PAGE_LIMIT).await?;
+                blobs.extend(page.blobs.into_iter().map(|blob| BlobRow {
+                    hash: blob.hash.to_string(),
+                    size: blob.size,
+                    path: blob.path,
+                }));
+                match page.next {
+                    Some(next) => after = Some(next),
+                    None => break,
+                }
+            }
+        }
+        Target::Remote(client) => {
+            let mut cursor: Option<String> = None;
+            loop {
+                let mut path = format!("/blobs/list-blobs?limit={}", MAX_PAGE_LIMIT);
+                if let Some(cursor) = &cursor {
+                    path.push_str(&format!("&cursor={}", cursor));
+                }
+                let page: Page<BlobRow> = client.get(&path).await?;
+                blobs.extend(page.items);
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    for blob in &blobs {
+        println!("{}  {:>12}  {}", blob.hash, blob.size, blob.path);
+    }
+    eprintln!("{} blob(s)", blobs.len());
+    Ok(())
+}
@@ -0,0 +1,485 @@
+use node::iroh_wrapper::{open_node, pkcs11_config, relay_mode};
+use helpers::{approvals::ApprovalPolicy, backups::BackupSettings, federation::FederationSettings, authorizations::AuthorizationRegistry, cli::{ChainBackend, CliArgs}, sync_controls::SyncControlRegistry, utils::{decode_doc_id, normalize_domain}, webhooks::WebhookRegistry, notifications::WatchRegistry, tenants::TenantRegistry, access_log::{AccessLog, AccessLogSettings}, jobs::{JobKind, JobSpec}, public::PublicSettings};
+use cord::{backend::LocalAnchor, events::ChainEventRoute};
+use keystore::{
+    audit::AuditLog,
+    keystore::{derive_keys, Pkcs11Config, StarterkitKeystore},
+    registry::KeyTypeRegistry,
+};
+use router::serve::DEFAULT_LISTEN_ADDR;
+
+use iroh::RelayMode;
+use iroh_base::PublicKey;
+use std::collections::HashSet;
+use std::error::Error;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+// Collects the outcome of each check so every problem is reported in one run
+#[derive(Default)]
+struct Report {
+    problems: usize,
+}
+
+impl Report {
+    fn ok(&self, message: impl AsRef<str>) {
+        println!("✅ {}", message.as_ref());
+    }
+
+    fn note(&self, message: impl AsRef<str>) {
+        println!("ℹ️  {}", message.as_ref());
+    }
+
+    fn fail(&mut self, message: impl AsRef<str>) {
+        println!("❌ {}", message.as_ref());
+        self.problems += 1;
+    }
+}
+
+// Validates everything `serve` would need with the same arguments, without starting anything.
+// Exits non-zero if any check fails.
+pub async fn run(args: CliArgs) -> Result<(), Box<dyn Error>> {
+    let mut report = Report::default();
+    let path = PathBuf::from(args.path.as_deref().unwrap_or("data"));
+
+    if let Some(config) = &args.config {
+        report.ok(format!("Config file {:?} parsed", config));
+    }
+    if let Some(profile) = &args.profile {
+        report.ok(format!("Using profile '{}' (data directory {:?})", profile, path));
+    }
+
+    check_settings(&args, &mut report);
+
+    if args.bootstrap {
+        check_bootstrap(&args, &path, &mut report);
+    } else if check_data_dir(&path, &mut report) {
+        check_keystore(&args, &path, &mut report);
+        check_acl(&path, &mut report);
+        match WebhookRegistry::load(&path.to_string_lossy()).await {
+            Ok(registry) => report.ok(format!("webhooks.json is valid ({} webhooks)", registry.list().len())),
+            Err(e) => report.fail(format!("webhooks.json is invalid: {}. Fix or remove the file.", e)),
+        }
+        match WatchRegistry::load(&path.to_string_lossy(), None).await {
+            Ok(registry) => report.ok(format!("notifications.json is valid ({} watches)", registry.list().len())),
+            Err(e) => report.fail(format!("notifications.json is invalid: {}. Fix or remove the file.", e)),
+        }
+        match TenantRegistry::load(&path.to_string_lossy()).await {
+            Ok(registry) => report.ok(format!("tenants.json is valid ({} tenants)", registry.list().len())),
+            Err(e) => report.fail(format!("tenants.json is invalid: {}. Fix or remove the file.", e)),
+        }
+        match AuthorizationRegistry::load(&path.to_string_lossy(), args.require_chain_authorization, Duration::from_secs(args.authorization_cache_ttl)).await {
+            Ok(registry) => report.ok(format!("author_dids.json is valid ({} linked authors)", registry.list().len())),
+            Err(e) => report.fail(format!("author_dids.json is invalid: {}. Fix or remove the file.", e)),
+        }
+        match SyncControlRegistry::load(&path.to_string_lossy()).await {
+            Ok(registry) => report.ok(format!("sync_controls.json is valid ({} controlled documents)", registry.list().len())),
+            Err(e) => report.fail(format!("sync_controls.json is invalid: {}. Fix or remove the file.", e)),
+        }
+        if args.chain_backend == ChainBackend::Local {
+            match LocalAnchor::load(&path.to_string_lossy(), String::new()).await {
+                Ok(ledger) => report.ok(format!("local-chain.json is valid ({} blocks)", ledger.height().await)),
+                Err(e) => report.fail(format!("local-chain.json is invalid: {}. Fix or remove the file.", e)),
+            }
+        }
+        match KeyTypeRegistry::load(&path.join("keystore")) {
+            Ok(registry) => report.ok(format!("keystore/key_types.json is valid ({} key types)", registry.list().len())),
+            Err(e) => report.fail(format!("keystore/key_types.json is invalid: {}. Fix or remove the file.", e)),
+        }
+        match AccessLog::open(&path, AccessLogSettings::default()).and_then(|access_log| access_log.verify()) {
+            Ok(0) => {}
+            Ok(records) => report.ok(format!("access.log is intact ({} records)", records)),
+            Err(e) => report.fail(format!("{}. Restore the log from a backup or move it aside.", e.to_string().trim_start_matches("❌ "))),
+        }
+        match AuditLog::open(&path.join("keystore")).and_then(|audit| audit.verify()) {
+            Ok(records) => report.ok(format!("keystore/audit.log is intact ({} records)", records)),
+            Err(e) => report.fail(format!("{}. Restore the log from a backup.", e.to_string().trim_start_matches("❌ "))),
+        }
+    }
+
+    println!();
+    if report.problems > 0 {
+        return Err(format!("❌ {} problem(s) found. The node would not start cleanly.", report.problems).into());
+    }
+    println!("🎉 Configuration is valid.");
+    Ok(())
+}
+
+// Listener addresses and timeouts
+fn check_settings(args: &CliArgs, report: &mut Report) {
+    for (name, value) in [
+        ("--read-timeout", args.read_timeout),
+        ("--write-timeout", args.write_timeout),
+        ("--download-timeout", args.download_timeout),
+        ("--download-connections", args.download_connections as u64),
+        ("--max-concurrent-imports", args.max_concurrent_imports as u64),
+        ("--max-concurrent-exports", args.max_concurrent_exports as u64),
+        ("--max-concurrent-downloads", args.max_concurrent_downloads as u64),
+        ("--max-concurrent-scans", args.max_concurrent_scans as u64),
+        ("--proof-batch-interval", args.proof_batch_interval),
+        ("--authorization-cache-ttl", args.authorization_cache_ttl),
+    ] {
+        if value == 0 {
+            report.fail(format!("{} must be greater than 0", name));
+        }
+    }
+
+    let listen = if args.listen.is_empty() && args.listen_uds.is_empty() {
+        vec![DEFAULT_LISTEN_ADDR.to_string()]
+    } else {
+        args.listen.clone()
+    };
+    for addr in &listen {
+        let resolved = match addr.to_socket_addrs().map(|mut addrs| addrs.next()) {
+            Ok(Some(resolved)) => resolved,
+            _ => {
+                report.fail(format!("--listen {} is not a valid host:port address", addr));
+                continue;
+            }
+        };
+        // Binding and dropping straight away tells whether `serve` could bind it
+        match TcpListener::bind(resolved) {
+            Ok(_) => report.ok(format!("Can listen on {}", addr)),
+            Err(e) => report.fail(format!("Cannot listen on {}: {}. Is the node already running?", addr, e)),
+        }
+    }
+
+    if let Some(addr) = &args.admin_listen {
+        match addr.to_socket_addrs().map(|mut addrs| addrs.next()) {
+            Ok(Some(resolved)) => match TcpListener::bind(resolved) {
+                Ok(_) if resolved.ip().is_loopback() => report.ok(format!("Can serve diagnostics on {}", addr)),
+                Ok(_) => report.ok(format!("Can serve diagnostics on {}, which is not a loopback address, keep it private", addr)),
+                Err(e) => report.fail(format!("Cannot listen on {}: {}. Is the node already running?", addr, e)),
+            },
+            _ => report.fail(format!("--admin-listen {} is not a valid host:port address", addr)),
+        }
+    }
+
+    if let Some(addr) = &args.s3_listen {
+        match addr.to_socket_addrs().map(|mut addrs| addrs.next()) {
+            Ok(Some(resolved)) => match TcpListener::bind(resolved) {
+                Ok(_) if resolved.ip().is_loopback() => report.ok(format!("Can serve the S3 API on {}", addr)),
+                Ok(_) => report.ok(format!("Can serve the S3 API on {}, which is not a loopback address, keep it private", addr)),
+                Err(e) => report.fail(format!("Cannot listen on {}: {}. Is the node already running?", addr, e)),
+            },
+            _ => report.fail(format!("--s3-listen {} is not a valid host:port address", addr)),
+        }
+    }
+
+    if let Some(addr) = &args.webdav_listen {
+        match addr.to_socket_addrs().map(|mut addrs| addrs.next()) {
+            Ok(Some(resolved)) => match TcpListener::bind(resolved) {
+                Ok(_) if resolved.ip().is_loopback() => report.ok(format!("Can serve the WebDAV share on {}", addr)),
+                Ok(_) => report.ok(format!("Can serve the WebDAV share on {}, which is not a loopback address, keep it private", addr)),
+                Err(e) => report.fail(format!("Cannot listen on {}: {}. Is the node already running?", addr, e)),
+            },
+            _ => report.fail(format!("--webdav-listen {} is not a valid host:port address", addr)),
+        }
+    }
+
+    if let Some(addr) = &args.public_listen {
+        match addr.to_socket_addrs().map(|mut addrs| addrs.next()) {
+            Ok(Some(resolved)) => match TcpListener::bind(resolved) {
+                Ok(_) => report.ok(format!("Can serve the public gateway on {}", addr)),
+                Err(e) => report.fail(format!("Cannot listen on {}: {}. Is the node already running?", addr, e)),
+            },
+            _ => report.fail(format!("--public-listen {} is not a valid host:port address", addr)),
+        }
+    }
+
+    for socket_path in &args.listen_uds {
+        let parent = Path::new(socket_path).parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        match check_writable(parent) {
+            Ok(()) => report.ok(format!("Can create unix socket {}", socket_path)),
+            Err(e) => report.fail(format!("Cannot create unix socket {}: {}", socket_path, e)),
+        }
+    }
+
+    match relay_mode(&args.relay) {
+        Ok(RelayMode::Custom(map)) => {
+            let urls: Vec<_> = map.urls().map(ToString::to_string).collect();
+            report.ok(format!("Relays: {}", urls.join(", ")))
+        }
+        Ok(mode) => report.ok(format!("Relays: {:?}", mode)),
+        Err(e) => report.fail(e.to_string().trim_start_matches("❌ ")),
+    }
+
+    if let Some(relay) = &args.smtp_relay {
+        match relay.to_socket_addrs().map(|mut addrs| addrs.next()) {
+            Ok(Some(_)) => report.ok(format!("Email notifications go through {} as {}", relay, args.smtp_from)),
+            _ => report.fail(format!("--smtp-relay {} is not a valid host:port address", relay)),
+        }
+    }
+
+    if let Some(grpc_listen) = &args.grpc_listen {
+        match grpc_listen.parse::<SocketAddr>() {
+            Ok(_) => report.ok(format!("gRPC address {} is valid", grpc_listen)),
+            Err(_) => report.fail(format!("--grpc-listen {} must be an ip:port address", grpc_listen)),
+        }
+    }
+
+    for route in &args.chain_event {
+        match ChainEventRoute::parse(route) {
+            Ok(route) if decode_doc_id(&route.doc_id).is_ok() => report.ok(format!("Chain events {} go to document {}", route_events(&route), route.doc_id)),
+            Ok(route) => report.fail(format!("--chain-event {}: {} is not a valid document ID", route_events(&route), route.doc_id)),
+            Err(e) => report.fail(e),
+        }
+    }
+
+    let mut scheduled = Vec::new();
+    for spec in &args.job {
+        match JobSpec::parse(spec) {
+            Ok(spec) if scheduled.contains(&spec.kind) => report.fail(format!("--job {} is given more than once", spec.kind)),
+            Ok(spec) if spec.kind == JobKind::Backup && args.backup_dir.is_none() => report.fail("--job backup needs --backup-dir"),
+            Ok(spec) if spec.kind.writes() && args.read_only => report.fail(format!("--job {} deletes entries, which a --read-only replica does not do", spec.kind)),
+            Ok(spec) => {
+                report.ok(format!("Job {} runs on '{}' (UTC)", spec.kind, spec.schedule));
+                scheduled.push(spec.kind);
+            }
+            Err(e) => report.fail(e.to_string().trim_start_matches("❌ ")),
+        }
+    }
+
+    if let Some(registry_doc) = &args.schema_registry_doc {
+        match decode_doc_id(registry_doc) {
+            Ok(_) => report.ok(format!("Schema refs registry:<key> are read from document {}", registry_doc)),
+            Err(_) => report.fail(format!("--schema-registry-doc {} is not a valid document ID", registry_doc)),
+        }
+    }
+    for prefix in &args.schema_ref_url {
+        if prefix.starts_with("https://") || prefix.starts_with("http://") {
+            report.ok(format!("Schema refs may be fetched from {}", prefix));
+        } else {
+            report.fail(format!("--schema-ref-url {} must be an http:// or https:// URL", prefix));
+        }
+    }
+
+    if args.read_only {
+        report.ok("Read-only replica: local writes are rejected");
+        if !args.chain_event.is_empty() {
+            report.note("--chain-event is ignored on a read-only replica");
+        }
+    }
+
+    match BackupSettings::from_args(args) {
+        Ok(BackupSettings { dir: Some(dir), s3, .. }) => {
+            // serve creates the directory with the first snapshot
+            let existing = dir.ancestors().find(|p| p.as_os_str().is_empty() || p.exists()).unwrap_or(Path::new("."));
+            let existing = if existing.as_os_str().is_empty() { Path::new(".") } else { existing };
+            match check_writable(existing) {
+                Ok(()) => report.ok(format!("Backup directory {:?} is writable", dir)),
+                Err(e) => report.fail(format!("Cannot write snapshots to {:?}: {}", dir, e)),
+            }
+            match args.backup_interval {
+                Some(interval) => report.ok(format!(
+                    "A snapshot is taken every {}s, keeping {}, with a full one every {}",
+                    interval, args.backup_keep, args.backup_full_every
+                )),
+                None => report.note("No --backup-interval: snapshots are only taken through POST /admin/backups"),
+            }
+            if let Some(s3) = s3 {
+                report.ok(format!("Snapshots are copied to {}", s3.url));
+            }
+        }
+        Ok(_) => {}
+        Err(e) => report.fail(e.trim_start_matches("❌ ")),
+    }
+
+    match FederationSettings::from_args(args) {
+        Ok(FederationSettings { catalog: Some(catalog), trusted_authors, interval }) => {
+            // the catalog may be a ticket, so only a document ID can be checked offline
+            if catalog.starts_with('d') && decode_doc_id(&catalog).is_ok() {
+                report.ok(format!("Following the federation catalog {}", catalog));
+            } else {
+                report.ok("Following the federation catalog of a ticket");
+            }
+            report.ok(format!(
+                "Catalog entries of {} trusted author(s) are applied, checked every {}s",
+                trusted_authors.len(),
+                interval.as_secs()
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => report.fail(e.trim_start_matches("❌ ")),
+    }
+
+    match PublicSettings::from_args(args) {
+        Ok(public) if args.public_listen.is_some() => {
+            if public.docs.is_empty() && public.blobs.is_empty() {
+                report.note("--public-listen publishes nothing without --public-doc or --public-blob");
+            } else {
+                report.ok(format!(
+                    "{} document(s) and {} blob(s) are published, {} requests per minute per client",
+                    public.docs.len(),
+                    public.blobs.len(),
+                    public.rate_limit
+                ));
+            }
+        }
+        Ok(_) => {}
+        Err(e) => report.fail(e.trim_start_matches("❌ ")),
+    }
+
+    match AccessLogSettings::from_args(args) {
+        Ok(settings) if settings.is_enabled() => report.ok(format!(
+            "Reads are recorded in access.log: {}{} document(s) in full",
+            if settings.all { format!("all documents at a sample rate of {}, ", settings.sample_rate) } else { String::new() },
+            settings.docs.len()
+        )),
+        Ok(_) => {}
+        Err(e) => report.fail(e.trim_start_matches("❌ ")),
+    }
+
+    if args.require_api_key {
+        let unchecked = args.listeners_without_api_keys();
+        if args.listen_uds.is_empty() {
+            report.fail("--require-api-key needs --listen-uds, for the operator to manage the node and its tenants");
+        } else if !unchecked.is_empty() {
+            report.fail(format!("--require-api-key cannot be combined with {}, which do not check API keys", unchecked.join(", ")));
+        } else {
+            report.ok("Requests on the --listen addresses need a tenant API key");
+        }
+    }
+
+    match ApprovalPolicy::from_settings(&args.approval_admin, args.approval_threshold) {
+        Ok(Some(policy)) => report.ok(format!(
+            "Destructive operations need {} of {} admin approvals",
+            policy.threshold(),
+            policy.admin_count()
+        )),
+        Ok(None) => {}
+        Err(e) => report.fail(e.to_string()),
+    }
+}
+
+fn route_events(route: &ChainEventRoute) -> String {
+    match &route.variant {
+        Some(variant) => format!("{}.{}", route.pallet, variant),
+        None => route.pallet.clone(),
+    }
+}
+
+// With --bootstrap the data directory must not exist yet and the SURI must be usable
+fn check_bootstrap(args: &CliArgs, path: &Path, report: &mut Report) {
+    if path.exists() {
+        report.fail(format!(
+            "A node is already configured at {:?}. Drop --bootstrap to start it, or choose another --path.",
+            path
+        ));
+    } else {
+        let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        match check_writable(parent) {
+            Ok(()) => report.ok(format!("Data directory {:?} can be created", path)),
+            Err(e) => report.fail(format!("Cannot create the data directory {:?}: {}", path, e)),
+        }
+    }
+
+    match args.suri.as_deref() {
+        None => report.fail("--bootstrap requires --suri. Run `keygen` to create one."),
+        Some(suri) => match derive_keys(suri) {
+            Ok(keys) => report.ok(format!("SURI is valid (NodeId {})", keys.secret_key.public())),
+            Err(e) => report.fail(format!("The SURI cannot be used: {}", e)),
+        },
+    }
+}
+
+// Returns whether the directory exists, so later checks that read from it can be skipped
+fn check_data_dir(path: &Path, report: &mut Report) -> bool {
+    if !path.is_dir() {
+        report.fail(format!(
+            "Data directory {:?} does not exist. Run `init` first, or pass --path to an existing node.",
+            path
+        ));
+        return false;
+    }
+    match check_writable(path) {
+        Ok(()) => report.ok(format!("Data directory {:?} is writable", path)),
+        Err(e) => report.fail(format!("Data directory {:?} is not writable: {}. Check its owner and permissions.", path, e)),
+    }
+    true
+}
+
+// Opens the keystore exactly as `serve` does, which also checks the password
+fn check_keystore(args: &CliArgs, path: &Path, report: &mut Report) {
+    let hsm = pkcs11_config(args);
+    match open_node(&path.to_path_buf(), &args.password, args.secret.clone(), hsm.as_ref()) {
+        Ok((secret_key, cord_signer)) => {
+            report.ok(format!("Keystore opened (NodeId {})", secret_key.public()));
+            report.ok(format!("CORD account {}", cord_signer.public));
+        }
+        Err(e) => {
+            report.fail(e.to_string().trim_start_matches("❌ "));
+            return;
+        }
+    }
+    if let Some(config) = hsm {
+        check_hsm(args, path, &config, report);
+    }
+}
+
+// `serve` falls back to the local keystore when the token cannot be opened, so check it on its own
+fn check_hsm(args: &CliArgs, path: &Path, config: &Pkcs11Config, report: &mut Report) {
+    let attached = StarterkitKeystore::keystore_access(args.secret.clone())
+        .and_then(|secret| StarterkitKeystore::open(&path.join("keystore"), secret))
+        .and_then(|mut keystore| keystore.attach_hsm(config));
+    match attached {
+        Ok(()) => report.ok(format!("PKCS#11 token opened with module {:?}", config.module)),
+        Err(e) => report.fail(format!(
+            "{} (`serve` falls back to the local keystore)",
+            e.to_string().trim_start_matches("❌ ")
+        )),
+    }
+}
+
+// The gateway ACL files. A file that fails to parse is treated as empty by `serve` and
+// overwritten on first start, so report it here instead.
+fn check_acl(path: &Path, report: &mut Report) {
+    match read_set(&path.join("allowed_node_ids.json")) {
+        Ok(None) => report.note("allowed_node_ids.json does not exist yet. It is created with this node's own NodeId on first start."),
+        Ok(Some(node_ids)) => {
+            let invalid: Vec<_> = node_ids.iter().filter(|id| PublicKey::from_str(id).is_err()).collect();
+            if invalid.is_empty() {
+                report.ok(format!("allowed_node_ids.json is valid ({} node IDs)", node_ids.len()));
+            } else {
+                report.fail(format!("allowed_node_ids.json contains invalid node IDs: {:?}", invalid));
+            }
+        }
+        Err(e) => report.fail(format!("allowed_node_ids.json is invalid: {}. It must be a JSON array of node IDs.", e)),
+    }
+
+    match read_set(&path.join("allowed_domains.json")) {
+        Ok(None) => report.note("allowed_domains.json does not exist. No browser origins are allowed."),
+        Ok(Some(domains)) => {
+            let invalid: Vec<_> = domains
+                .iter()
+                .filter(|domain| normalize_domain(domain).as_deref() != Some(domain.as_str()))
+                .collect();
+            if invalid.is_empty() {
+                report.ok(format!("allowed_domains.json is valid ({} domains)", domains.len()));
+            } else {
+                report.fail(format!(
+                    "allowed_domains.json contains domains that never match an Origin: {:?}. Store them as bare host names.",
+                    invalid
+                ));
+            }
+        }
+        Err(e) => report.fail(format!("allowed_domains.json is invalid: {}. It must be a JSON array of domains.", e)),
+    }
+}
+
+fn read_set(file: &Path) -> Result<Option<HashSet<String>>, Box<dyn Error>> {
+    if !file.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(file)?;
+    Ok(Some(serde_json::from_str(&content)?))
+}
+
+// Creates and removes a probe file, since permission bits alone miss read-only mounts
+fn check_writable(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(".starter-kit-write-check");
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(&probe)
+}
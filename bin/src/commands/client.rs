@@ -0,0 +1,81 @@
+use helpers::cli::TargetArgs;
+
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use std::error::Error;
+
+/// One page of a paginated list response.
+#[derive(Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// A small client for a running node's HTTP API, sending the `nodeId` and `author-id` headers.
+pub struct ApiClient {
+    base_url: String,
+    http: reqwest::Client,
+    pub author_id: Option<String>,
+}
+
+impl ApiClient {
+    pub fn new(url: &str, args: &TargetArgs) -> Result<Self, Box<dyn Error>> {
+        let Some(node_id) = &args.node_id else {
+            return Err("❌ --node-id is required with --url. Use a NodeId on the node's allowed list.".into());
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert("nodeId", HeaderValue::from_str(node_id)?);
+        if let Some(author_id) = &args.author_id {
+            headers.insert("author-id", HeaderValue::from_str(author_id)?);
+        }
+
+        Ok(Self {
+            base_url: url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::builder().default_headers(headers).build()?,
+            author_id: args.author_id.clone(),
+        })
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Box<dyn Error>> {
+        let response = self.http.get(self.url(path)).send().await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    pub async fn post<T: DeserializeOwned>(&self, path: &str, body: &Value) -> Result<T, Box<dyn Error>> {
+        let response = self.http.post(self.url(path)).json(body).send().await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    /// Sends a POST request with a raw, possibly streamed, body.
+    pub async fn post_body<T: DeserializeOwned>(&self, path: &str, body: reqwest::Body) -> Result<T, Box<dyn Error>> {
+        let response = self.http.post(self.url(path)).body(body).send().await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    /// Sends a GET request and returns the response unread, for streaming bodies.
+    pub async fn get_response(&self, path: &str) -> Result<reqwest::Response, Box<dyn Error>> {
+        let response = self.http.get(self.url(path)).send().await?;
+        check(response).await
+    }
+
+    pub async fn get_bytes(&self, path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let response = self.http.get(self.url(path)).send().await?;
+        Ok(check(response).await?.bytes().await?.to_vec())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+}
+
+// Turns a non-2xx response into an error carrying the status and body
+async fn check(response: reqwest::Response) -> Result<reqwest::Response, Box<dyn Error>> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("❌ Request failed with {}: {}", status, body).into())
+}
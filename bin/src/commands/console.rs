@@ -0,0 +1,220 @@
+use crate::commands::{author, blob, client::{ApiClient, Page}, docs, target::Target};
+use helpers::{
+    cli::{ConsoleArgs, ConsoleCommand, ConsoleLine, EntryCommand, TargetArgs},
+    pagination::MAX_PAGE_LIMIT,
+};
+
+use clap::{CommandFactory, Parser};
+use dialoguer::{BasicHistory, Completion, Input};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::io::{BufRead, IsTerminal};
+
+// Lines kept for the arrow keys
+const HISTORY_SIZE: usize = 500;
+
+/// Completes command names, then document and author IDs of the node.
+struct ConsoleCompletion {
+    /// Each command with its subcommands.
+    commands: Vec<(String, Vec<String>)>,
+    /// Documents and authors the node listed when last refreshed.
+    ids: Vec<String>,
+}
+
+impl ConsoleCompletion {
+    fn new() -> Self {
+        let mut commands: Vec<(String, Vec<String>)> = ConsoleLine::command()
+            .get_subcommands()
+            .map(|command| {
+                let subcommands = command.get_subcommands().map(|sub| sub.get_name().to_string()).collect();
+                (command.get_name().to_string(), subcommands)
+            })
+            .collect();
+        commands.push(("help".to_string(), commands.iter().map(|(name, _)| name.clone()).collect()));
+        Self { commands, ids: Vec::new() }
+    }
+
+    // Lists the first page of documents and authors. IDs only help typing, so a failure leaves
+    // the previous ones
+    async fn refresh(&mut self, client: &ApiClient) {
+        #[derive(Deserialize)]
+        struct Doc {
+            doc_id: String,
+        }
+        let docs: Result<Page<Doc>, _> = client.get(&format!("/docs/list-docs?limit={}", MAX_PAGE_LIMIT)).await;
+        let authors: Result<Page<String>, _> = client.get(&format!("/authors/list-authors?limit={}", MAX_PAGE_LIMIT)).await;
+        if let (Ok(docs), Ok(authors)) = (docs, authors) {
+            self.ids = docs.items.into_iter().map(|doc| doc.doc_id).chain(authors.items).collect();
+        }
+    }
+}
+
+impl Completion for ConsoleCompletion {
+    fn get(&self, input: &str) -> Option<String> {
+        let (head, word) = match input.rfind(' ') {
+            Some(i) => input.split_at(i + 1),
+            None => ("", input),
+        };
+        let previous: Vec<&str> = head.split_whitespace().collect();
+        let candidates: Vec<&str> = match previous.as_slice() {
+            [] => self.commands.iter().map(|(name, _)| name.as_str()).collect(),
+            [command] => self
+                .commands
+                .iter()
+                .find(|(name, _)| name == command)
+                .map(|(_, subcommands)| subcommands.iter().map(String::as_str).collect())
+                .unwrap_or_default(),
+            _ => self.ids.iter().map(String::as_str).collect(),
+        };
+
+        let matches: Vec<&str> = candidates.into_iter().filter(|candidate| candidate.starts_with(word)).collect();
+        let first = matches.first()?;
+        if matches.len() == 1 {
+            return Some(format!("{}{} ", head, first));
+        }
+        // Several match, so complete as far as they agree
+        let common = matches.iter().fold(first.len(), |len, candidate| {
+            first.bytes().zip(candidate.bytes()).take(len).take_while(|(a, b)| a == b).count()
+        });
+        (common > word.len()).then(|| format!("{}{}", head, &first[..common]))
+    }
+}
+
+pub async fn run(args: ConsoleArgs) -> Result<(), Box<dyn Error>> {
+    let target_args = TargetArgs {
+        path: None,
+        url: Some(args.url.clone()),
+        node_id: Some(args.node_id.clone()),
+        author_id: args.author_id.clone(),
+    };
+    let client = ApiClient::new(&args.url, &target_args)?;
+    let version: Value = client
+        .get("/version")
+        .await
+        .map_err(|e| format!("❌ Node at {} is not reachable: {}", args.url, e))?;
+    let target = Target::Remote(client);
+
+    let mut completion = ConsoleCompletion::new();
+    let interactive = std::io::stdin().is_terminal();
+    if interactive {
+        println!(
+            "✅ Connected to {} (version {})",
+            args.url,
+            version["version"].as_str().unwrap_or("unknown")
+        );
+        println!("Type `help` for the commands, Tab to complete and `exit` to leave.");
+        if let Target::Remote(client) = &target {
+            completion.refresh(client).await;
+        }
+    }
+
+    let mut history = BasicHistory::new().max_entries(HISTORY_SIZE).no_duplicates(true);
+    let mut piped = std::io::stdin().lock().lines();
+    loop {
+        // Without a terminal, such as `console < commands.txt`, lines are read as they come
+        let line = if interactive {
+            let prompt = tokio::task::block_in_place(|| {
+                Input::<String>::new()
+                    .with_prompt("starter-kit")
+                    .allow_empty(true)
+                    .history_with(&mut history)
+                    .completion_with(&completion)
+                    .interact_text()
+            });
+            match prompt {
+                Ok(line) => line,
+                Err(_) => break,
+            }
+        } else {
+            match piped.next() {
+                Some(line) => line?,
+                None => break,
+            }
+        };
+
+        let words = match shell_words::split(&line) {
+            Ok(words) if words.is_empty() => continue,
+            Ok(words) => words,
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                continue;
+            }
+        };
+        let command = match ConsoleLine::try_parse_from(words) {
+            Ok(line) => line.command,
+            // Also how `help` and `--help` are printed
+            Err(e) => {
+                let _ = e.print();
+                continue;
+            }
+        };
+
+        let refresh = matches!(command, ConsoleCommand::Docs { .. } | ConsoleCommand::Author { .. });
+        let result = match command {
+            ConsoleCommand::Docs { command } => docs::execute(&target, command).await,
+            ConsoleCommand::Entry { command } => entry(&target, command).await,
+            ConsoleCommand::Blob { command } => blob::execute(&target, command).await,
+            ConsoleCommand::Author { command } => author::execute(&target, command).await,
+            ConsoleCommand::Exit => break,
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+        if refresh && interactive {
+            if let Target::Remote(client) = &target {
+                completion.refresh(client).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn entry(target: &Target, command: EntryCommand) -> Result<(), Box<dyn Error>> {
+    let Target::Remote(client) = target else {
+        return Err("❌ The console only works with a running node.".into());
+    };
+
+    match command {
+        EntryCommand::Get { doc_id, key } => {
+            let entry: Value = client
+                .post(
+                    "/docs/get-resolved-entry",
+                    &json!({ "doc_id": doc_id, "key": key, "resolution": "last_writer_wins" }),
+                )
+                .await?;
+            let hash = entry["hash"].as_str().ok_or("❌ Unexpected response from /docs/get-resolved-entry")?;
+            let value = client.get_bytes(&format!("/blobs/{}/download", hash)).await?;
+            match String::from_utf8(value) {
+                Ok(text) => println!("{}", text),
+                Err(e) => println!("({} bytes of binary content)", e.as_bytes().len()),
+            }
+            eprintln!(
+                "author={}  hash={}  timestamp={}",
+                entry["author"].as_str().unwrap_or_default(),
+                hash,
+                entry["timestamp"]
+            );
+        }
+        EntryCommand::Set { doc_id, key, value } => {
+            let author_id = target.author_id().await?;
+            let response: Value = client
+                .post(
+                    "/docs/set-entry",
+                    &json!({ "doc_id": doc_id, "author_id": author_id, "key": key, "value": value }),
+                )
+                .await?;
+            println!("{}", response["hash"].as_str().unwrap_or_default());
+        }
+        EntryCommand::Delete { doc_id, key } => {
+            let author_id = target.author_id().await?;
+            let response: Value = client
+                .post("/docs/delete-entry", &json!({ "doc_id": doc_id, "author_id": author_id, "key": key }))
+                .await?;
+            let deleted = response["deleted_count"].as_u64().unwrap_or_default();
+            println!("Deleted {} entr{}", deleted, if deleted == 1 { "y" } else { "ies" });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,444 @@
+use crate::commands::{client::Page, target::Target};
+use helpers::{cli::{DocsArgs, DocsCommand}, pagination::MAX_PAGE_LIMIT};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use starter_kit_core::archives::{DocArchiveImport, DocArchiveSummary};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+
+// Key the document schema is stored under
+const SCHEMA_KEY: &str = "schema";
+// Largest number of operations the API accepts in one `/batch` request
+const BATCH_SIZE: usize = 100;
+
+/// An entry as listed by `docs entries`.
+#[derive(Debug, Deserialize)]
+struct EntryRow {
+    key: String,
+    author: String,
+    hash: String,
+    len: u64,
+    timestamp: u64,
+}
+
+/// The file format written by `docs export` and read by `docs import`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocExport {
+    pub doc_id: String,
+    /// The document's JSON schema, if it has one.
+    pub schema: Option<String>,
+    pub entries: Vec<ExportedEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedEntry {
+    pub key: String,
+    pub author: String,
+    pub timestamp: u64,
+    pub value: String,
+    /// `utf8`, or `base64` for values that are not valid UTF-8.
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+}
+
+fn default_encoding() -> String {
+    "utf8".to_string()
+}
+
+pub async fn run(args: DocsArgs) -> Result<(), Box<dyn Error>> {
+    // Verifying an archive needs no node
+    if let DocsCommand::VerifyArchive { file } = &args.command {
+        return verify_archive(file).await;
+    }
+
+    let target = Target::connect(&args.target).await?;
+    let result = execute(&target, args.command).await;
+    target.close().await?;
+    result
+}
+
+/// Runs one `docs` command on `target`, also used by `console`.
+pub async fn execute(target: &Target, command: DocsCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        DocsCommand::List => list(target).await,
+        DocsCommand::Create => create(target).await,
+        DocsCommand::Entries { doc_id, include_deleted } => entries(target, &doc_id, include_deleted).await,
+        DocsCommand::Export { doc_id, output } => export(target, &doc_id, output).await,
+        DocsCommand::Import { file, doc_id } => import(target, &file, doc_id).await,
+        DocsCommand::ExportArchive { doc_id, output } => export_archive(target, &doc_id, output).await,
+        DocsCommand::ImportArchive { file, doc_id } => import_archive(target, &file, doc_id).await,
+        DocsCommand::VerifyArchive { file } => verify_archive(&file).await,
+    }
+}
+
+async fn list(target: &Target) -> Result<(), Box<dyn Error>> {
+    let docs: Vec<(String, String)> = match target {
+        Target::Local { store, .. } => starter_kit_core::docs::list_docs(store.docs.clone())
+            .await?
+            .into_iter()
+            .map(|(doc_id, capability)| (doc_id, format!("{:?}", capability)))
+            .collect(),
+        Target::Remote(client) => {
+            #[derive(Deserialize)]
+            struct Doc {
+                doc_id: String,
+                capability: String,
+            }
+            let mut docs = Vec::new();
+            let mut cursor: Option<String> = None;
+            loop {
+                let mut path = format!("/docs/list-docs?limit={}", MAX_PAGE_LIMIT);
+                if let Some(cursor) = &cursor {
+                    path.push_str(&format!("&cursor={}", cursor));
+                }
+                let page: Page<Doc> = client.get(&path).await?;
+                docs.extend(page.items.into_iter().map(|doc| (doc.doc_id, doc.capability)));
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+            docs
+        }
+    };
+
+    for (doc_id, capability) in &docs {
+        println!("{}  {}", doc_id, capability);
+    }
+    eprintln!("{} document(s)", docs.len());
+    Ok(())
+}
+
+async fn create(target: &Target) -> Result<(), Box<dyn Error>> {
+    let doc_id = create_doc(target).await?;
+    println!("{}", doc_id);
+    Ok(())
+}
+
+async fn entries(target: &Target, doc_id: &str, include_deleted: bool) -> Result<(), Box<dyn Error>> {
+    let entries = fetch_entries(target, doc_id, include_deleted).await?;
+
+    for entry in &entries {
+        let status = if entry.len == 0 { "  (deleted)" } else { "" };
+        println!(
+            "{}  author={}  hash={}  len={}  timestamp={}{}",
+            entry.key, entry.author, entry.hash, entry.len, entry.timestamp, status
+        );
+    }
+    eprintln!("{} entr{}", entries.len(), if entries.len() == 1 { "y" } else { "ies" });
+    Ok(())
+}
+
+async fn export(target: &Target, doc_id: &str, output: Option<String>) -> Result<(), Box<dyn Error>> {
+    let mut export = DocExport {
+        doc_id: doc_id.to_string(),
+        schema: None,
+        entries: Vec::new(),
+    };
+
+    for entry in fetch_entries(target, doc_id, false).await? {
+        let bytes = read_value(target, &entry.hash).await?;
+        let (value, encoding) = match String::from_utf8(bytes) {
+            Ok(value) => (value, "utf8"),
+            Err(e) => (STANDARD.encode(e.into_bytes()), "base64"),
+        };
+
+        if entry.key == SCHEMA_KEY && encoding == "utf8" {
+            export.schema = Some(value);
+            continue;
+        }
+        export.entries.push(ExportedEntry {
+            key: entry.key,
+            author: entry.author,
+            timestamp: entry.timestamp,
+            value,
+            encoding: encoding.to_string(),
+        });
+    }
+
+    let json = serde_json::to_string_pretty(&export)?;
+    match output {
+        Some(file) => {
+            tokio::fs::write(&file, json).await?;
+            eprintln!("✅ Exported {} entries from {} to {}", export.entries.len(), doc_id, file);
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+async fn import(target: &Target, file: &str, doc_id: Option<String>) -> Result<(), Box<dyn Error>> {
+    let content = tokio::fs::read_to_string(file).await?;
+    let export: DocExport = serde_json::from_str(&content)
+        .map_err(|e| format!("❌ {} is not a document export: {}", file, e))?;
+
+    let author_id = target.author_id().await?;
+    let doc_id = match doc_id {
+        Some(doc_id) => doc_id,
+        None => {
+            let doc_id = create_doc(target).await?;
+            eprintln!("📄 Created document {}", doc_id);
+            doc_id
+        }
+    };
+
+    // The schema has to be set while the document is still empty
+    if let Some(schema) = &export.schema {
+        add_schema(target, &doc_id, &author_id, schema).await?;
+    }
+
+    // Entries are written as `author_id`, since the original authors' keys may not be on this node
+    let (entries, skipped): (Vec<_>, Vec<_>) = export
+        .entries
+        .into_iter()
+        .partition(|entry| entry.encoding == "utf8");
+    for entry in &skipped {
+        eprintln!("⚠️  Skipping {}: binary values can only be imported as files", entry.key);
+    }
+
+    let imported = entries.len();
+    set_entries(target, &doc_id, &author_id, entries).await?;
+
+    eprintln!("✅ Imported {} entries into {}", imported, doc_id);
+    println!("{}", doc_id);
+    Ok(())
+}
+
+async fn export_archive(target: &Target, doc_id: &str, output: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let Some(output) = output else {
+        if let Some(summary) = write_archive(target, doc_id, &mut tokio::io::stdout()).await? {
+            print_archive_summary(&summary);
+        }
+        return Ok(());
+    };
+
+    // Written under a temporary name so a failed export never leaves a truncated archive behind
+    let partial = output.with_extension("partial");
+    let mut file = tokio::fs::File::create(&partial).await?;
+    let written = write_archive(target, doc_id, &mut file).await;
+    let summary = match written {
+        Ok(Some(summary)) => Ok(summary),
+        // A remote archive is streamed, and only known to be complete once read back
+        Ok(None) => {
+            let mut reader = tokio::io::BufReader::new(tokio::fs::File::open(&partial).await?);
+            starter_kit_core::archives::verify_doc_archive(&mut reader)
+                .await
+                .map_err(|e| format!("❌ The archive received from the node is incomplete: {}", e).into())
+        }
+        Err(e) => Err(e),
+    };
+    let summary = match summary {
+        Ok(summary) => summary,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+    };
+    file.sync_all().await?;
+    tokio::fs::rename(&partial, &output).await?;
+
+    print_archive_summary(&summary);
+    eprintln!("✅ Exported {} to {}", doc_id, output.display());
+    Ok(())
+}
+
+// Writes the archive, returning what it holds when written from a local store
+async fn write_archive(
+    target: &Target,
+    doc_id: &str,
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> Result<Option<DocArchiveSummary>, Box<dyn Error>> {
+    match target {
+        Target::Local { store, .. } => Ok(Some(
+            starter_kit_core::archives::export_doc_archive(store.docs.clone(), store.blobs.clone(), doc_id.to_string(), writer)
+                .await?,
+        )),
+        Target::Remote(client) => {
+            let response = client.get_response(&format!("/docs/{}/archive", doc_id)).await?;
+            let mut body = response.bytes_stream();
+            while let Some(chunk) = body.next().await {
+                writer.write_all(&chunk?).await?;
+            }
+            writer.flush().await?;
+            Ok(None)
+        }
+    }
+}
+
+async fn import_archive(target: &Target, file: &Path, doc_id: Option<String>) -> Result<(), Box<dyn Error>> {
+    let imported: DocArchiveImport = match target {
+        Target::Local { store, .. } => {
+            let author_id = target.author_id().await?;
+            let mut reader = tokio::io::BufReader::new(tokio::fs::File::open(file).await?);
+            starter_kit_core::archives::import_doc_archive(store.docs.clone(), store.blobs.clone(), &mut reader, doc_id, author_id)
+                .await?
+        }
+        Target::Remote(client) => {
+            let path = match &doc_id {
+                Some(doc_id) => format!("/docs/import-archive?doc_id={}", doc_id),
+                None => "/docs/import-archive".to_string(),
+            };
+            let body = reqwest::Body::wrap_stream(ReaderStream::new(tokio::fs::File::open(file).await?));
+            client.post_body(&path, body).await?
+        }
+    };
+
+    print_archive_summary(&imported.archive);
+    eprintln!(
+        "✅ Imported {} entries into {}, {} as their original author",
+        imported.archive.entries, imported.doc_id, imported.original_authors
+    );
+    println!("{}", imported.doc_id);
+    Ok(())
+}
+
+async fn verify_archive(file: &Path) -> Result<(), Box<dyn Error>> {
+    let mut reader = tokio::io::BufReader::new(tokio::fs::File::open(file).await?);
+    let summary = starter_kit_core::archives::verify_doc_archive(&mut reader)
+        .await
+        .map_err(|e| format!("❌ {} failed verification: {}", file.display(), e))?;
+
+    print_archive_summary(&summary);
+    eprintln!("✅ Every block of {} matches its hash", file.display());
+    Ok(())
+}
+
+fn print_archive_summary(summary: &DocArchiveSummary) {
+    eprintln!("📦 Document {}, root {}", summary.doc_id, summary.root);
+    eprintln!("   {} entries, {} blobs", summary.entries, summary.blobs);
+    if summary.missing_content > 0 {
+        eprintln!("⚠️  {} entries have no content in the archive", summary.missing_content);
+    }
+}
+
+async fn create_doc(target: &Target) -> Result<String, Box<dyn Error>> {
+    match target {
+        Target::Local { store, .. } => Ok(starter_kit_core::docs::create_doc(store.docs.clone()).await?),
+        Target::Remote(client) => {
+            let response: Value = client.post("/docs/create-document", &json!({})).await?;
+            response["doc_id"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| "❌ Unexpected response from /docs/create-document".into())
+        }
+    }
+}
+
+async fn fetch_entries(target: &Target, doc_id: &str, include_deleted: bool) -> Result<Vec<EntryRow>, Box<dyn Error>> {
+    let query_params = json!({ "include_empty": include_deleted });
+
+    match target {
+        Target::Local { store, .. } => Ok(starter_kit_core::docs::get_entries(store.docs.clone(), doc_id.to_string(), query_params)
+            .await?
+            .into_iter()
+            .map(|entry| EntryRow {
+                key: entry.namespace.key,
+                author: entry.namespace.author,
+                hash: entry.record.hash,
+                len: entry.record.len,
+                timestamp: entry.record.timestamp,
+            })
+            .collect()),
+        Target::Remote(client) => {
+            let body = json!({ "doc_id": doc_id, "query_params": query_params.to_string() });
+            let mut entries = Vec::new();
+            let mut cursor: Option<String> = None;
+            loop {
+                let mut path = format!("/docs/get-entries?limit={}", MAX_PAGE_LIMIT);
+                if let Some(cursor) = &cursor {
+                    path.push_str(&format!("&cursor={}", cursor));
+                }
+                let page: Page<EntryRow> = client.post(&path, &body).await?;
+                entries.extend(page.items);
+                match page.next_cursor {
+                    Some(next) => cursor = Some(next),
+                    None => break,
+                }
+            }
+            Ok(entries)
+        }
+    }
+}
+
+async fn read_value(target: &Target, hash: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    match target {
+        Target::Local { store, .. } => Ok(starter_kit_core::blobs::read_blob_bytes(store.blobs.clone(), hash.to_string())
+            .await?
+            .to_vec()),
+        Target::Remote(client) => client.get_bytes(&format!("/blobs/{}/download", hash)).await,
+    }
+}
+
+async fn add_schema(target: &Target, doc_id: &str, author_id: &str, schema: &str) -> Result<(), Box<dyn Error>> {
+    match target {
+        Target::Local { store, .. } => {
+            starter_kit_core::docs::add_doc_schema(store.docs.clone(), store.blobs.clone(), author_id.to_string(), doc_id.to_string(), schema.to_string())
+                .await?;
+        }
+        Target::Remote(client) => {
+            let _: Value = client
+                .post(
+                    "/docs/add-doc-schema",
+                    &json!({ "author_id": author_id, "doc_id": doc_id, "schema": schema }),
+                )
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn set_entries(
+    target: &Target,
+    doc_id: &str,
+    author_id: &str,
+    entries: Vec<ExportedEntry>,
+) -> Result<(), Box<dyn Error>> {
+    match target {
+        Target::Local { store, .. } => {
+            for entry in entries {
+                starter_kit_core::docs::set_entry(
+                    store.docs.clone(),
+                    store.blobs.clone(),
+                    doc_id.to_string(),
+                    author_id.to_string(),
+                    entry.key.clone(),
+                    entry.value,
+                )
+                .await
+                .map_err(|e| format!("❌ Failed to set {}: {}", entry.key, e))?;
+            }
+        }
+        Target::Remote(client) => {
+            for chunk in entries.chunks(BATCH_SIZE) {
+                let operations: Vec<Value> = chunk
+                    .iter()
+                    .map(|entry| json!({
+                        "op": "set_entry",
+                        "doc_id": doc_id,
+                        "author_id": author_id,
+                        "key": entry.key,
+                        "value": entry.value,
+                    }))
+                    .collect();
+                let response: Value = client.post("/batch", &json!({ "operations": operations })).await?;
+
+                let failed = response["results"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|result| result["status"] == "error");
+                if let Some(result) = failed {
+                    let index = result["index"].as_u64().unwrap_or_default() as usize;
+                    let key = chunk.get(index).map(|entry| entry.key.as_str()).unwrap_or_default();
+                    return Err(format!("❌ Failed to set {}: {}", key, result["error"]).into());
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use helpers::cli::ExportArgs;
+use node::iroh_wrapper::open_local_store;
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// Version of the archive layout, checked by `import`.
+pub const ARCHIVE_FORMAT: u32 = 1;
+/// Name of the manifest, the first entry of every archive.
+pub const MANIFEST_NAME: &str = "manifest.json";
+/// Directory inside the archive that holds the node's data directory.
+pub const DATA_DIR: &str = "data";
+
+/// Lists every file of the archive with its BLAKE3 hash, so `import` can verify the restore.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+    pub created_at: u64,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// Path relative to the data directory, with `/` separators.
+    pub path: String,
+    pub size: u64,
+    pub blake3: String,
+}
+
+// Packs the whole data directory (docs, blobs, authors, keystore, ACL and webhook files) into
+// a zstd-compressed tar archive
+pub async fn run(args: ExportArgs) -> Result<(), Box<dyn Error>> {
+    let path = PathBuf::from(args.path.as_deref().unwrap_or("data"));
+    if !path.is_dir() {
+        return Err(format!("❌ Data directory {:?} does not exist.", path).into());
+    }
+    if std::path::absolute(&args.out)?.starts_with(std::path::absolute(&path)?) {
+        return Err("❌ The archive cannot be written inside the data directory it exports.".into());
+    }
+
+    // Opening the stores fails while a node holds them, and shutting them down flushes to disk
+    let store = open_local_store(&path, false).await?;
+    store.router.shutdown().await?;
+
+    let out = args.out.clone();
+    let manifest = tokio::task::spawn_blocking(move || write_archive(&path, &out).map_err(|e| e.to_string()))
+        .await??;
+
+    let total: u64 = manifest.files.iter().map(|file| file.size).sum();
+    eprintln!(
+        "✅ Exported {} files ({} bytes) to {:?} ({} bytes compressed)",
+        manifest.files.len(),
+        total,
+        args.out,
+        std::fs::metadata(&args.out)?.len()
+    );
+    Ok(())
+}
+
+fn write_archive(path: &Path, out: &Path) -> Result<Manifest, Box<dyn Error + Send + Sync>> {
+    let mut directories = Vec::new();
+    let mut files = Vec::new();
+    for entry in WalkDir::new(path).min_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(path)?.to_path_buf();
+        if entry.file_type().is_dir() {
+            directories.push(relative);
+        } else {
+            files.push(relative);
+        }
+    }
+
+    // The manifest goes first, so every file is hashed once before anything is written
+    let mut manifest = Manifest {
+        format: ARCHIVE_FORMAT,
+        created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        files: Vec::with_capacity(files.len()),
+    };
+    for relative in &files {
+        let mut hasher = blake3::Hasher::new();
+        let size = std::io::copy(&mut File::open(path.join(relative))?, &mut hasher)?;
+        manifest.files.push(ManifestFile {
+            path: archive_path(relative)?,
+            size,
+            blake3: hasher.finalize().to_hex().to_string(),
+        });
+    }
+
+    // Written under a temporary name, so a failed export never leaves a truncated archive behind
+    let mut partial = out.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let result = (|| -> Result<(), Box<dyn Error + Send + Sync>> {
+        let encoder = zstd::Encoder::new(File::create(&partial)?, 0)?;
+        let mut archive = tar::Builder::new(encoder);
+
+        let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest_json.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(manifest.created_at);
+        header.set_cksum();
+        archive.append_data(&mut header, MANIFEST_NAME, manifest_json.as_slice())?;
+
+        for relative in directories.iter().chain(&files) {
+            archive.append_path_with_name(path.join(relative), Path::new(DATA_DIR).join(relative))?;
+        }
+
+        archive.into_inner()?.finish()?.sync_all()?;
+        std::fs::rename(&partial, out)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result.map(|_| manifest)
+}
+
+/// The manifest path of a file: relative to the data directory and `/`-separated on every platform.
+pub fn archive_path(relative: &Path) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let parts: Option<Vec<&str>> = relative.components().map(|part| part.as_os_str().to_str()).collect();
+    Ok(parts.ok_or_else(|| format!("❌ {:?} is not a valid UTF-8 path", relative))?.join("/"))
+}
@@ -0,0 +1,117 @@
+use crate::commands::export::{archive_path, Manifest, ARCHIVE_FORMAT, DATA_DIR, MANIFEST_NAME};
+use helpers::cli::ImportArgs;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+// Restores a data directory from an `export` archive, verifying every file against the manifest
+// before the directory appears under its final name
+pub async fn run(args: ImportArgs) -> Result<(), Box<dyn Error>> {
+    let path = PathBuf::from(args.path.as_deref().unwrap_or("data"));
+    if path.exists() {
+        return Err(format!("❌ {:?} already exists. Choose a new --path to import into.", path).into());
+    }
+    let name = path.file_name().ok_or("❌ --path must name a directory")?.to_string_lossy();
+    let staging = path.with_file_name(format!(".{}.importing", name));
+    if staging.exists() {
+        return Err(format!("❌ {:?} is left over from an interrupted import. Remove it and try again.", staging).into());
+    }
+
+    let (archive, staging_dir, target) = (args.archive.clone(), staging.clone(), path.clone());
+    let result = tokio::task::spawn_blocking(move || {
+        restore(&archive, &staging_dir, &target).map_err(|e| e.to_string())
+    })
+    .await?;
+    let manifest = match result {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e.into());
+        }
+    };
+
+    eprintln!("✅ Restored {} files into {:?}, all matching the manifest", manifest.files.len(), path);
+    eprintln!("⚠️  The restored node has the same NodeId and keys as the exported one. Do not run both at the same time.");
+    eprintln!("▶️  Start it with: serve --path {} --password <PASSWORD>", path.display());
+    Ok(())
+}
+
+fn restore(archive: &Path, staging: &Path, path: &Path) -> Result<Manifest, Box<dyn Error + Send + Sync>> {
+    let decoder = zstd::Decoder::new(
+        File::open(archive).map_err(|e| format!("❌ Failed to open {:?}: {}", archive, e))?,
+    )?;
+    let mut tar = tar::Archive::new(decoder);
+    let mut entries = tar.entries()?;
+
+    let manifest: Manifest = match entries.next() {
+        Some(entry) if entry.as_ref().is_ok_and(|entry| entry.path().is_ok_and(|p| p == Path::new(MANIFEST_NAME))) => {
+            let mut content = String::new();
+            entry?.read_to_string(&mut content)?;
+            serde_json::from_str(&content).map_err(|e| format!("❌ The archive manifest is invalid: {}", e))?
+        }
+        _ => return Err("❌ Not a starter kit archive: it does not start with a manifest.".into()),
+    };
+    if manifest.format != ARCHIVE_FORMAT {
+        return Err(format!(
+            "❌ Unsupported archive format {}. This version reads format {}.",
+            manifest.format, ARCHIVE_FORMAT
+        )
+        .into());
+    }
+
+    std::fs::create_dir_all(staging)?;
+    for entry in entries {
+        let mut entry = entry?;
+        if !entry.path()?.starts_with(DATA_DIR) {
+            return Err(format!("❌ Unexpected entry {:?} in the archive", entry.path()?).into());
+        }
+        // `unpack_in` refuses entries that would land outside the staging directory
+        if !entry.unpack_in(staging)? {
+            return Err(format!("❌ The archive entry {:?} points outside the data directory", entry.path()?).into());
+        }
+    }
+
+    let restored = staging.join(DATA_DIR);
+    verify(&restored, &manifest)?;
+    std::fs::rename(&restored, path)?;
+    std::fs::remove_dir(staging)?;
+    Ok(manifest)
+}
+
+// Every file of the manifest must be restored with its size and hash, and nothing else
+fn verify(restored: &Path, manifest: &Manifest) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut expected: HashMap<&str, _> = manifest.files.iter().map(|file| (file.path.as_str(), file)).collect();
+
+    if restored.is_dir() {
+        for entry in WalkDir::new(restored).min_depth(1) {
+            let entry = entry?;
+            if entry.file_type().is_dir() {
+                continue;
+            }
+            let relative = archive_path(entry.path().strip_prefix(restored)?)?;
+            let file = expected
+                .remove(relative.as_str())
+                .ok_or_else(|| format!("❌ {} is in the archive but not in its manifest", relative))?;
+
+            let mut hasher = blake3::Hasher::new();
+            let size = std::io::copy(&mut File::open(entry.path())?, &mut hasher)?;
+            if size != file.size || hasher.finalize().to_hex().as_str() != file.blake3 {
+                return Err(format!("❌ {} does not match the manifest. The archive is corrupted.", relative).into());
+            }
+        }
+    }
+
+    if let Some(missing) = expected.keys().next() {
+        return Err(format!(
+            "❌ {} and {} other file(s) listed in the manifest are missing from the archive",
+            missing,
+            expected.len() - 1
+        )
+        .into());
+    }
+    Ok(())
+}
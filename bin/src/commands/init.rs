@@ -0,0 +1,112 @@
+use node::iroh_wrapper::{bootstrap_node, open_local_store};
+use helpers::cli::InitArgs;
+use keystore::keystore::{derive_keys, generate_suri};
+use gateway::{storage::init_access_control, access_control::ensure_self_node_id_allowed};
+
+use dialoguer::{Confirm, Input, Password};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// Creates the node directory and keystore, then prints the node's identity
+pub async fn run(args: InitArgs) -> Result<(), Box<dyn Error>> {
+    if args.interactive {
+        return run_interactive(args).await;
+    }
+
+    let path = PathBuf::from(args.path.as_deref().unwrap_or("data"));
+    // clap requires both flags unless `--interactive` is set
+    let password = args.password.unwrap_or_default();
+    let suri = args.suri.unwrap_or_default();
+
+    let (secret_key, cord_signer) = bootstrap_node(&path, &password, &suri, args.secret)?;
+
+    println!("🔗 NodeId: {}", secret_key.public());
+    println!("🔑 CORD account: {}\n", cord_signer.public);
+    print_next_step(&path);
+
+    Ok(())
+}
+
+// Walks through the same setup with prompts, then also creates the default author and seeds
+// the gateway ACL, so the node is ready to use on its first `serve`
+async fn run_interactive(args: InitArgs) -> Result<(), Box<dyn Error>> {
+    println!("👋 Welcome! Let's set up a new starter kit node.\n");
+
+    // 1. data path
+    let path: String = Input::new()
+        .with_prompt("Data directory")
+        .default(args.path.unwrap_or_else(|| "data".to_string()))
+        .validate_with(|path: &String| {
+            if Path::new(path).exists() {
+                Err("A node is already configured at this path")
+            } else {
+                Ok(())
+            }
+        })
+        .interact_text()?;
+    let path = PathBuf::from(path);
+
+    // 2. secret key
+    let suri = match args.suri {
+        Some(suri) => suri,
+        None if Confirm::new().with_prompt("Generate a new SURI for the node's keys?").default(true).interact()? => {
+            let suri = generate_suri();
+            println!("\n🌱 SURI: {}", suri);
+            println!("⚠️  Store this phrase somewhere safe. Anyone with it can recreate these keys.\n");
+            while !Confirm::new().with_prompt("Have you saved the SURI?").default(false).interact()? {}
+            suri
+        }
+        None => Password::new().with_prompt("Existing SURI").interact()?,
+    };
+    derive_keys(&suri)?;
+
+    // 3. passwords
+    let password = match args.password {
+        Some(password) => password,
+        None => Password::new()
+            .with_prompt("Node password (required by `serve`)")
+            .with_confirmation("Confirm node password", "Passwords do not match")
+            .interact()?,
+    };
+    let secret = match args.secret {
+        Some(secret) => Some(secret),
+        None if Confirm::new().with_prompt("Encrypt the keystore with a password?").default(true).interact()? => {
+            Some(
+                Password::new()
+                    .with_prompt("Keystore password")
+                    .with_confirmation("Confirm keystore password", "Passwords do not match")
+                    .interact()?,
+            )
+        }
+        None => None,
+    };
+    println!();
+
+    let (secret_key, cord_signer) = bootstrap_node(&path, &password, &suri, secret)?;
+    let node_id = secret_key.public().to_string();
+
+    // 4. default author
+    let store = open_local_store(&path, false).await?;
+    let author = starter_kit_core::authors::get_default_author(store.docs.clone()).await;
+    store.router.shutdown().await?;
+    let author = author?;
+
+    // 5. gateway ACL
+    let path_str = path.to_str().ok_or("❌ The data directory path is not valid UTF-8")?;
+    let (mut allowed_node_ids, _) = init_access_control(path_str).await?;
+    ensure_self_node_id_allowed(path_str, node_id.clone(), &mut allowed_node_ids).await?;
+
+    println!("🔗 NodeId: {}", node_id);
+    println!("🔑 CORD account: {}", cord_signer.public);
+    println!("✍️  Default author: {}\n", author);
+    print_next_step(&path);
+
+    Ok(())
+}
+
+fn print_next_step(path: &Path) {
+    println!(
+        "▶️  Start the node with: serve --path {} --password <PASSWORD>",
+        path.display()
+    );
+}
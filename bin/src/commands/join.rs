@@ -0,0 +1,181 @@
+use crate::commands::target::Target;
+use helpers::cli::JoinArgs;
+use starter_kit_core::docs::{join_doc, join_doc_bundle, subscribe_doc, DocEvent, DOC_BUNDLE_PREFIX};
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::error::Error;
+use tokio::time::{Duration, Instant};
+
+// Joins a shared document, or each document of a bundle ticket, and reports sync progress,
+// printing the doc IDs on stdout when done
+pub async fn run(args: JoinArgs) -> Result<(), Box<dyn Error>> {
+    let target = Target::connect_networked(&args.target).await?;
+    let result = join_and_follow(&target, args).await;
+
+    target.close().await?;
+    for doc_id in result? {
+        println!("{}", doc_id);
+    }
+    Ok(())
+}
+
+async fn join_and_follow(target: &Target, args: JoinArgs) -> Result<Vec<String>, Box<dyn Error>> {
+    // One deadline for the whole bundle rather than one per document
+    let deadline = Instant::now() + Duration::from_secs(args.timeout);
+    let is_bundle = args.ticket.trim().starts_with(DOC_BUNDLE_PREFIX);
+    if args.issuer.is_some() && !is_bundle {
+        return Err("❌ --issuer only applies to bundle tickets".into());
+    }
+
+    match target {
+        Target::Local { store, .. } => {
+            let doc_ids = if is_bundle {
+                let bundle = join_doc_bundle(store.docs.clone(), args.ticket, args.issuer, args.sync_prefixes).await?;
+                print_bundle(&bundle.issuer, bundle.label.as_deref(), bundle.docs.len());
+                bundle.docs.into_iter().map(|doc| doc.doc_id).collect()
+            } else {
+                vec![join_doc(store.docs.clone(), args.ticket, args.sync_prefixes).await?]
+            };
+
+            for doc_id in &doc_ids {
+                eprintln!("📄 Joined document {}", doc_id);
+                // Closing the store stops syncing, so wait until the content is downloaded too
+                let events = subscribe_doc(store.docs.clone(), doc_id.clone()).await?;
+                if tokio::time::timeout_at(deadline, follow_local(events)).await.is_err() {
+                    eprintln!(
+                        "⏱️  Not fully synced after {}s. Run `join` again, or start the node, to continue syncing.",
+                        args.timeout
+                    );
+                    break;
+                }
+            }
+            Ok(doc_ids)
+        }
+        Target::Remote(client) => {
+            let doc_ids = if is_bundle {
+                let response: Value = client
+                    .post(
+                        "/docs/join-bundle",
+                        &json!({ "ticket": args.ticket, "issuer": args.issuer, "sync_prefixes": args.sync_prefixes }),
+                    )
+                    .await?;
+                let doc_ids: Vec<String> = serde_json::from_value(response["doc_ids"].clone())
+                    .map_err(|_| "❌ Unexpected response from /docs/join-bundle")?;
+                print_bundle(response["issuer"].as_str().unwrap_or_default(), response["label"].as_str(), doc_ids.len());
+                doc_ids
+            } else {
+                let response: Value = client.post("/docs/join-doc", &json!({ "ticket": args.ticket, "sync_prefixes": args.sync_prefixes })).await?;
+                let doc_id = response["doc_id"]
+                    .as_str()
+                    .ok_or("❌ Unexpected response from /docs/join-doc")?
+                    .to_string();
+                vec![doc_id]
+            };
+
+            for doc_id in &doc_ids {
+                eprintln!("📄 Joined document {}", doc_id);
+                // The node keeps syncing on its own, so only wait for the first sync to report on
+                let events = client.get_response(&format!("/docs/{}/events", doc_id)).await?;
+                if tokio::time::timeout_at(deadline, follow_remote(events)).await.is_err() {
+                    eprintln!("⏱️  No sync finished within {}s. The node keeps syncing in the background.", args.timeout);
+                    break;
+                }
+            }
+            Ok(doc_ids)
+        }
+    }
+}
+
+fn print_bundle(issuer: &str, label: Option<&str>, docs: usize) {
+    match label {
+        Some(label) => eprintln!("📦 Bundle \"{}\" signed by {}, {} document(s)", label, issuer, docs),
+        None => eprintln!("📦 Bundle signed by {}, {} document(s)", issuer, docs),
+    }
+}
+
+// Prints live events until a sync has finished and all of its content is downloaded
+async fn follow_local(
+    events: impl futures::Stream<Item = Result<DocEvent, starter_kit_core::docs::DocError>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut events = Box::pin(events);
+    let mut synced = false;
+    let mut downloaded = 0usize;
+
+    while let Some(event) = events.next().await {
+        match event? {
+            DocEvent::NeighborUp { peer } => eprintln!("🤝 Connected to {}", peer),
+            DocEvent::ContentReady { .. } => downloaded += 1,
+            DocEvent::SyncFinished { peer, error: Some(error), .. } => {
+                eprintln!("⚠️  Sync with {} failed: {}", peer, error);
+            }
+            DocEvent::SyncFinished { peer, entries_received, entries_sent, error: None } => {
+                eprintln!("🔄 Synced with {}: {} entries received, {} sent", peer, entries_received, entries_sent);
+                synced = true;
+            }
+            DocEvent::PendingContentReady if synced => {
+                eprintln!("✅ Sync complete, {} blobs downloaded", downloaded);
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+
+    Err("❌ The document event stream ended before the sync finished".into())
+}
+
+// Reads the server-sent events of `/docs/:id/events` until the first successful sync
+async fn follow_remote(response: reqwest::Response) -> Result<(), Box<dyn Error>> {
+    let mut body = response.bytes_stream();
+    let mut buffer = String::new();
+    let mut received = 0usize;
+
+    while let Some(chunk) = body.next().await {
+        buffer.push_str(&String::from_utf8_lossy(&chunk?));
+
+        // Events are separated by a blank line
+        while let Some(end) = buffer.find("\n\n") {
+            let block: String = buffer.drain(..end + 2).collect();
+            let Some((event, data)) = parse_sse_event(&block) else { continue };
+
+            match event.as_str() {
+                "entry_inserted" => received += 1,
+                "sync_finished" => {
+                    let sync: Value = serde_json::from_str(&data)?;
+                    match sync["error"].as_str() {
+                        Some(error) => eprintln!("⚠️  Sync with {} failed: {}", sync["peer"], error),
+                        None => {
+                            eprintln!(
+                                "🔄 Synced with {}: {} entries received, {} sent",
+                                sync["peer"].as_str().unwrap_or_default(),
+                                sync["entries_received"],
+                                sync["entries_sent"]
+                            );
+                            eprintln!("✅ {} entries so far. The node keeps syncing in the background.", received);
+                            return Ok(());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Err("❌ The document event stream ended before the sync finished".into())
+}
+
+// Returns the event name and data of one SSE block, skipping comments such as keep-alives
+fn parse_sse_event(block: &str) -> Option<(String, String)> {
+    let mut event = None;
+    let mut data = Vec::new();
+
+    for line in block.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            event = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+
+    event.map(|event| (event, data.join("\n")))
+}
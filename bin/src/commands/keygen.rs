@@ -0,0 +1,22 @@
+use keystore::keystore::{derive_keys, generate_suri};
+use helpers::cli::KeygenArgs;
+
+use std::error::Error;
+
+// Prints the SURI and the keys a node bootstrapped from it would use
+pub fn run(args: KeygenArgs) -> Result<(), Box<dyn Error>> {
+    let generated = args.suri.is_none();
+    let suri = args.suri.unwrap_or_else(generate_suri);
+
+    let keys = derive_keys(&suri)?;
+
+    if generated {
+        println!("🌱 SURI: {}", suri);
+        println!("⚠️  Store this phrase somewhere safe. Anyone with it can recreate these keys.\n");
+    }
+    println!("🔑 CORD account (sr25519): {}", keys.cord_public);
+    println!("🔑 Starter kit key (ed25519): {}", keys.starterkit_public);
+    println!("🔗 NodeId: {}", keys.secret_key.public());
+
+    Ok(())
+}
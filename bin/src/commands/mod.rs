@@ -0,0 +1,16 @@
+pub mod author;
+pub mod bench;
+pub mod blob;
+pub mod check_config;
+pub mod client;
+pub mod console;
+pub mod docs;
+pub mod export;
+pub mod import;
+pub mod init;
+pub mod join;
+pub mod keygen;
+pub mod restore_snapshot;
+pub mod serve;
+pub mod status;
+pub mod target;
@@ -0,0 +1,33 @@
+use helpers::cli::RestoreSnapshotArgs;
+use node::iroh_wrapper::open_local_store;
+use starter_kit_core::backups::restore_snapshot;
+
+use std::error::Error;
+use std::path::PathBuf;
+
+// Writes the authors, documents and blobs of a snapshot into a stopped node's stores. Unlike
+// `import`, this restores into an existing node, keeping its NodeId and keys.
+pub async fn run(args: RestoreSnapshotArgs) -> Result<(), Box<dyn Error>> {
+    let path = PathBuf::from(args.path.as_deref().unwrap_or("data"));
+    if !args.archive.is_file() {
+        return Err(format!("❌ Snapshot {:?} does not exist.", args.archive).into());
+    }
+
+    let store = open_local_store(&path, false).await?;
+    let result = restore_snapshot(store.docs.clone(), store.blobs.clone(), &args.archive).await;
+    // Shutting the stores down flushes the restored data to disk
+    store.router.shutdown().await?;
+    let report = result.map_err(|e| format!("❌ Failed to restore {:?}: {}", args.archive, e))?;
+
+    eprintln!(
+        "✅ Restored {} authors, {} documents ({} entries) and {} blobs ({} tags) into {:?}",
+        report.authors, report.docs, report.entries_restored, report.blobs, report.tags, path
+    );
+    if report.entries_skipped > 0 {
+        eprintln!(
+            "ℹ️  {} entries of read-only documents or other authors were skipped. They sync back from peers.",
+            report.entries_skipped
+        );
+    }
+    Ok(())
+}
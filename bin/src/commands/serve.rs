@@ -0,0 +1,17 @@
+use helpers::cli::CliArgs;
+use starter_kit::StarterKitBuilder;
+
+use tokio::signal;
+use std::error::Error;
+
+pub async fn run(args: CliArgs) -> Result<(), Box<dyn Error>> {
+    // The node is started the same way when embedded, see `StarterKit::builder`
+    let node = StarterKitBuilder::from_args(args).start().await?;
+
+    tracing::info!("🛑 Press Ctrl+C to shut down the server...");
+
+    signal::ctrl_c().await.expect("failed to listen for event");
+    tracing::info!("👋 Shutdown signal received. Exiting gracefully...");
+
+    node.shutdown().await
+}
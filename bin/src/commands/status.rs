@@ -0,0 +1,28 @@
+use helpers::cli::StatusArgs;
+
+use serde_json::Value;
+use std::error::Error;
+
+// Queries a running node and prints what it reports about itself
+pub async fn run(args: StatusArgs) -> Result<(), Box<dyn Error>> {
+    let base_url = args.url.trim_end_matches('/');
+    let version_url = format!("{}/version", base_url);
+
+    let response = reqwest::get(&version_url)
+        .await
+        .map_err(|e| format!("❌ Node at {} is not reachable: {}", base_url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("❌ Node at {} responded with {}", base_url, response.status()).into());
+    }
+    let version: Value = response.json().await?;
+
+    println!("✅ Node at {} is running\n", base_url);
+    for field in ["version", "git_commit", "build_timestamp", "iroh", "iroh_docs", "iroh_blobs"] {
+        if let Some(value) = version.get(field) {
+            let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+            println!("   {:<16} {}", field, value);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use crate::commands::client::ApiClient;
+use node::iroh_wrapper::{open_local_store, LocalStore};
+use helpers::cli::TargetArgs;
+
+use std::error::Error;
+use std::path::PathBuf;
+
+/// The node a maintenance command works on.
+pub enum Target {
+    /// A stopped node's stores, opened directly.
+    Local {
+        store: LocalStore,
+        author_id: Option<String>,
+    },
+    /// A running node, reached through its HTTP API.
+    Remote(ApiClient),
+}
+
+impl Target {
+    pub async fn connect(args: &TargetArgs) -> Result<Self, Box<dyn Error>> {
+        Self::open(args, false).await
+    }
+
+    /// Like `connect`, but a local store can also reach peers.
+    pub async fn connect_networked(args: &TargetArgs) -> Result<Self, Box<dyn Error>> {
+        Self::open(args, true).await
+    }
+
+    async fn open(args: &TargetArgs, networked: bool) -> Result<Self, Box<dyn Error>> {
+        match &args.url {
+            Some(url) => Ok(Target::Remote(ApiClient::new(url, args)?)),
+            None => {
+                let path = PathBuf::from(args.path.as_deref().unwrap_or("data"));
+                Ok(Target::Local {
+                    store: open_local_store(&path, networked).await?,
+                    author_id: args.author_id.clone(),
+                })
+            }
+        }
+    }
+
+    /// The author to write as: `--author-id`, or offline, the node's default author.
+    pub async fn author_id(&self) -> Result<String, Box<dyn Error>> {
+        match self {
+            Target::Local { author_id: Some(author_id), .. } => Ok(author_id.clone()),
+            Target::Local { store, author_id: None } => {
+                Ok(starter_kit_core::authors::get_default_author(store.docs.clone()).await?)
+            }
+            Target::Remote(client) => client
+                .author_id
+                .clone()
+                .ok_or_else(|| "❌ --author-id is required for this command with --url.".into()),
+        }
+    }
+
+    /// Shuts down the local stores so everything is flushed to disk.
+    pub async fn close(self) -> Result<(), Box<dyn Error>> {
+        if let Target::Local { store, .. } = self {
+            store.router.shutdown().await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use helpers::{
+    cli::CliArgs,
+    jobs::{JobKind, JobRegistry, JobRunner, JobSpec},
+    state::AppState,
+};
+use starter_kit_core::{
+    backups::{create_snapshot, prune_snapshots},
+    blobs::collect_garbage,
+    history::purge_expired_versions,
+    proofs::anchor_pending_proofs,
+    replication::replication_report,
+    trash::purge_expired_trash,
+};
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+// Longest the scheduler sleeps before checking the schedules again, so runs stay on time when
+// the system clock is changed.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// Registers every maintenance job with `state.jobs`, on the schedules of `specs`. Jobs without
+/// a schedule only run on request. A read-only replica has no jobs that write to documents.
+pub(crate) fn register_jobs(state: &AppState, args: &CliArgs, specs: Vec<JobSpec>) {
+    for kind in JobKind::ALL.into_iter().filter(|kind| !(state.read_only && kind.writes())) {
+        let schedule = specs.iter().rev().find(|spec| spec.kind == kind).map(|spec| spec.schedule.clone());
+        state.jobs.register(kind, schedule, job_runner(kind, state.clone(), args));
+    }
+}
+
+/// Starts the scheduled runs of `jobs` when they are due.
+pub(crate) fn spawn_job_scheduler(jobs: JobRegistry) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let sleep = jobs.until_next_run().map_or(MAX_SLEEP, |until| until.min(MAX_SLEEP));
+            tokio::time::sleep(sleep).await;
+            jobs.run_due();
+        }
+    })
+}
+
+// What the job `kind` does, with the settings it runs with
+fn job_runner(kind: JobKind, state: AppState, args: &CliArgs) -> JobRunner {
+    match kind {
+        JobKind::Backup => runner(move || {
+            let state = state.clone();
+            async move {
+                let snapshot = create_snapshot(state.docs.clone(), state.blobs.clone(), &state.backups, false)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let removed = prune_snapshots(&state.backups, state.backups.keep)
+                    .await
+                    .map_err(|e| format!("wrote {} but failed to prune snapshots: {}", snapshot.name, e))?;
+                Ok(format!("Wrote {} ({} bytes) and pruned {} snapshots", snapshot.name, snapshot.size, removed.len()))
+            }
+        }),
+        JobKind::AnchorProofs => runner(move || {
+            let state = state.clone();
+            async move {
+                let anchored = anchor_pending_proofs(state.anchor.as_ref(), &state.proofs)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(format!("Anchored {} proofs", anchored))
+            }
+        }),
+        JobKind::ReplicationCheck => runner(move || {
+            let state = state.clone();
+            async move {
+                let (summary, reports) = replication_report(
+                    state.docs.clone(),
+                    state.blobs.clone(),
+                    &state.replication,
+                    &state.sync_controls,
+                    None,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+                for report in reports.iter().filter(|report| report.peers.is_empty() && report.activity.neighbors.is_empty()) {
+                    tracing::warn!(doc_id = %report.doc_id, "document has no peers to sync with");
+                }
+                Ok(format!(
+                    "{} documents: {} never synced, {} without peers, {} with content to download",
+                    summary.docs, summary.never_synced, summary.without_peers, summary.with_pending_content
+                ))
+            }
+        }),
+        JobKind::TrashPurge => {
+            let retention = args.trash_retention;
+            runner(move || {
+                let state = state.clone();
+                async move {
+                    // 0 keeps deleted entries until the trash is emptied
+                    if retention == 0 {
+                        return Ok("Nothing to do, --trash-retention is 0".to_string());
+                    }
+                    let purged = purge_expired_trash(state.docs.clone(), Duration::from_secs(retention))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    Ok(format!("Deleted {} entries from the trash", purged))
+                }
+            })
+        }
+        JobKind::HistoryPurge => {
+            let retention = args.version_retention;
+            runner(move || {
+                let state = state.clone();
+                async move {
+                    // 0 keeps versions for good
+                    if retention == 0 {
+                        return Ok("Nothing to do, --version-retention is 0".to_string());
+                    }
+                    let purged = purge_expired_versions(state.docs.clone(), Duration::from_secs(retention))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    Ok(format!("Deleted {} versions of entries", purged))
+                }
+            })
+        }
+        JobKind::BlobGc => runner(move || {
+            let state = state.clone();
+            async move {
+                let deleted = collect_garbage(state.docs.clone(), state.blobs.clone())
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(format!("Deleted {} blobs", deleted))
+            }
+        }),
+    }
+}
+
+fn runner<F, Fut>(run: F) -> JobRunner
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<String, String>> + Send + 'static,
+{
+    Arc::new(move || Box::pin(run()))
+}
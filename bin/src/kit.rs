@@ -0,0 +1,582 @@
+use node::iroh_wrapper::{setup_iroh_node, IrohNode};
+use node::sync_scheduler::spawn_sync_scheduler;
+use node::replication_tracker::spawn_replication_tracker;
+use node::entry_indexer::spawn_entry_indexer;
+use node::search_indexer::spawn_search_indexer;
+use node::tenant_tracker::spawn_tenant_tracker;
+use router::{
+    middleware::with_required_api_key,
+    router::{create_admin_router, create_public_router, create_router, create_s3_router, create_webdav_router, RouteTimeouts, RouterConfig},
+    serve::{bind_uds, serve_tcp, serve_tcp_with_client_addr, serve_uds, DEFAULT_LISTEN_ADDR},
+};
+use helpers::{
+    cli::{ChainBackend, CliArgs},
+    state::AppState,
+    events::EventBus,
+    webhooks::WebhookRegistry,
+    notifications::{EmailSettings, WatchRegistry},
+    approvals::{ApprovalPolicy, ApprovalRegistry},
+    proofs::ProofRegistry,
+    authorizations::AuthorizationRegistry,
+    sync_controls::SyncControlRegistry,
+    backups::BackupSettings,
+    federation::{FederationRegistry, FederationSettings},
+    replication::ReplicationTracker,
+    entry_index::EntryIndex,
+    search::SearchIndex,
+    write_queue::WriteQueue,
+    errors::set_debug_errors,
+    downloads::DownloadManager,
+    limits::OperationLimits,
+    jobs::{JobKind, JobRegistry, JobSpec},
+    public::PublicSettings,
+    tenants::TenantRegistry,
+    access_log::{AccessLog, AccessLogSettings},
+};
+use gateway::{
+    storage::init_access_control,
+    access_control::{add_domain, add_node_id, set_storage_path, ensure_self_node_id_allowed},
+};
+use cord::{backend::{ChainAnchor, CordAnchor, LocalAnchor}, client::ChainClient, cord::connect_to_chain};
+use cord::events::{spawn_event_listener, ChainEventRoute};
+use starter_kit_core::{authors::get_default_author, backups::spawn_backup_scheduler, docs::{record_chain_event, spawn_doc_handle_eviction}, federation::spawn_federation, history::spawn_history_purger, pinning::spawn_pinning, proofs::spawn_proof_batcher, trash::spawn_trash_purger};
+use webhooks::{dispatcher::spawn_dispatcher, notifications::Notifier};
+use crate::jobs::{register_jobs, spawn_job_scheduler};
+use keystore::registry::KeyTypeRegistry;
+use keystore::audit::AuditLog;
+
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use axum::Router;
+use clap::{Args, FromArgMatches};
+use iroh::PublicKey;
+
+
+// Builds extra routes once the state of the node exists
+type RouteFactory = Box<dyn FnOnce(AppState) -> Router + Send>;
+
+/// Entry point of the library, see `StarterKit::builder`.
+pub struct StarterKit;
+
+impl StarterKit {
+    /// Configures a node, starting from the defaults of `serve`.
+    pub fn builder() -> StarterKitBuilder {
+        let command = CliArgs::augment_args(clap::Command::new("starter-kit"));
+        let matches = command
+            .try_get_matches_from(["starter-kit", "--password", ""])
+            .expect("the serve arguments have defaults for everything but the password");
+        let args = CliArgs::from_arg_matches(&matches).expect("the serve arguments parse from their own matches");
+        StarterKitBuilder::from_args(args)
+    }
+}
+
+/// Settings of a node to start, see `StarterKit::builder`.
+///
+/// The common settings have their own methods. Every other `serve` flag is set with `configure`,
+/// on the `CliArgs` the flags are parsed into.
+///
+/// Nodes started from several builders keep their own caches, but share the gateway's allowed
+/// node IDs and domains.
+pub struct StarterKitBuilder {
+    args: CliArgs,
+    routes: Vec<RouteFactory>,
+    allowed_node_ids: Vec<String>,
+    allowed_domains: Vec<String>,
+}
+
+impl StarterKitBuilder {
+    /// Starts from `serve` arguments parsed elsewhere, such as the command line.
+    pub fn from_args(args: CliArgs) -> Self {
+        Self { args, routes: Vec::new(), allowed_node_ids: Vec::new(), allowed_domains: Vec::new() }
+    }
+
+    /// The data directory of the blobs, documents and keystore, `data` by default.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.args.path = Some(path.into());
+        self
+    }
+
+    /// The password of the keystore, required.
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.args.password = password.into();
+        self
+    }
+
+    /// Creates the data directory and keystore from `suri` before starting, as `init` does. Only
+    /// for a new data directory.
+    pub fn bootstrap(mut self, suri: impl Into<String>) -> Self {
+        self.args.bootstrap = true;
+        self.args.suri = Some(suri.into());
+        self
+    }
+
+    /// Encrypts the keypairs of the keystore with `secret`, which must then be given on every start.
+    pub fn secret(mut self, secret: impl Into<String>) -> Self {
+        self.args.secret = Some(secret.into());
+        self
+    }
+
+    /// Serves the HTTP API on `addr`, as `host:port`. Can be called several times. Defaults to
+    /// `127.0.0.1:4001`, and port 0 picks a free port, see `RunningNode::http_addrs`.
+    pub fn listen(mut self, addr: impl Into<String>) -> Self {
+        self.args.listen.push(addr.into());
+        self
+    }
+
+    /// Lets `node_id` through the gateway, as `POST /gateway/add-node-id` does.
+    pub fn allow_node_id(mut self, node_id: impl Into<String>) -> Self {
+        self.allowed_node_ids.push(node_id.into());
+        self
+    }
+
+    /// Lets requests with an `Origin` of `domain` through the gateway, as `POST /gateway/add-domain` does.
+    pub fn allow_domain(mut self, domain: impl Into<String>) -> Self {
+        self.allowed_domains.push(domain.into());
+        self
+    }
+
+    /// Serves the routes built by `routes` from the node's state on the HTTP API's addresses,
+    /// next to its routes. They skip the API's middleware, such as the node ID check and the
+    /// tenant checks of API keys.
+    pub fn routes(mut self, routes: impl FnOnce(AppState) -> Router + Send + 'static) -> Self {
+        self.routes.push(Box::new(routes));
+        self
+    }
+
+    /// Sets any other `serve` setting, such as `args.read_only` for `--read-only`.
+    pub fn configure(mut self, configure: impl FnOnce(&mut CliArgs)) -> Self {
+        configure(&mut self.args);
+        self
+    }
+
+    /// Starts the node, its background tasks and its servers, and returns once they are
+    /// listening. The node runs until `RunningNode::shutdown`.
+    pub async fn start(self) -> Result<RunningNode, Box<dyn Error>> {
+        if self.args.password.is_empty() {
+            return Err("❌ A password is required".into());
+        }
+        let node = start(self.args, self.routes).await?;
+        for node_id in self.allowed_node_ids {
+            add_node_id(node_id).await;
+        }
+        for domain in self.allowed_domains {
+            add_domain(domain).await;
+        }
+        Ok(node)
+    }
+}
+
+/// A started node, see `StarterKitBuilder::start`.
+pub struct RunningNode {
+    node_id: PublicKey,
+    state: AppState,
+    http_addrs: Vec<SocketAddr>,
+    iroh: iroh::protocol::Router,
+    shutdown_tx: watch::Sender<()>,
+    servers: Vec<JoinHandle<std::io::Result<()>>>,
+}
+
+impl RunningNode {
+    pub fn node_id(&self) -> PublicKey {
+        self.node_id
+    }
+
+    /// The state the API is served from, to call the `core` functions on the node's stores.
+    pub fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    /// The TCP addresses the HTTP API is served on, with the ports picked for port 0.
+    pub fn http_addrs(&self) -> &[SocketAddr] {
+        &self.http_addrs
+    }
+
+    /// Stops the servers once their requests are answered, then the node.
+    pub async fn shutdown(self) -> Result<(), Box<dyn Error>> {
+        let _ = self.shutdown_tx.send(());
+        for server in self.servers {
+            server.await??;
+        }
+        self.iroh.shutdown().await?;
+        Ok(())
+    }
+}
+
+// Runs the node and serves the API until the returned node is shut down
+async fn start(args: CliArgs, extra_routes: Vec<RouteFactory>) -> Result<RunningNode, Box<dyn Error>> {
+    let cord_client = connect_to_chain().await?;
+    let cord_client = Arc::new(cord_client);
+
+    // Initialize the Iroh node
+    let iroh_node: IrohNode = setup_iroh_node(args.clone()).await?;
+
+    // Initialize gateway
+    let path_str = args.path.clone().unwrap_or_else(|| "data".to_string());
+    let (mut allowed_node_ids, allowed_domains) = init_access_control(&path_str.clone()).await?;
+
+    // Ensure self NodeId is added on first run
+    ensure_self_node_id_allowed(
+        &path_str.to_string().clone(), 
+        iroh_node.node_id.to_string().clone(), 
+        &mut allowed_node_ids
+    ).await?;
+
+    set_storage_path(
+        path_str.to_string(), 
+        allowed_node_ids, 
+        allowed_domains
+    );
+
+    // Start frontend
+    // start_frontend();
+
+    tracing::info!(node_id = %iroh_node.node_id, "✅ Iroh node started successfully!");
+
+    let approval_policy = ApprovalPolicy::from_settings(&args.approval_admin, args.approval_threshold)
+        .map_err(|e| format!("❌ {e}"))?;
+    if let Some(policy) = &approval_policy {
+        tracing::info!(threshold = policy.threshold(), admins = policy.admin_count(), "🔏 Destructive operations need admin approval");
+    }
+
+    if args.proof_batch_interval == 0 {
+        return Err("❌ --proof-batch-interval must be greater than 0".into());
+    }
+    if args.authorization_cache_ttl == 0 {
+        return Err("❌ --authorization-cache-ttl must be greater than 0".into());
+    }
+    if args.download_connections == 0 {
+        return Err("❌ --download-connections must be greater than 0".into());
+    }
+    for (name, value) in [
+        ("--max-concurrent-imports", args.max_concurrent_imports),
+        ("--max-concurrent-exports", args.max_concurrent_exports),
+        ("--max-concurrent-downloads", args.max_concurrent_downloads),
+        ("--max-concurrent-scans", args.max_concurrent_scans),
+    ] {
+        if value == 0 {
+            return Err(format!("❌ {} must be greater than 0", name).into());
+        }
+    }
+    let limits = OperationLimits::new(
+        args.max_concurrent_imports,
+        args.max_concurrent_exports,
+        args.max_concurrent_downloads,
+        args.max_concurrent_scans,
+    );
+    if args.require_chain_authorization {
+        tracing::info!(cache_ttl = args.authorization_cache_ttl, "🔏 Entries need an authorization on the document's CORD registry");
+    }
+
+    let backups = BackupSettings::from_args(&args)?;
+    let federation = FederationSettings::from_args(&args)?;
+    let public = PublicSettings::from_args(&args)?;
+    if args.require_api_key && args.listen_uds.is_empty() {
+        return Err("❌ --require-api-key needs --listen-uds, for the operator to manage the node and its tenants".into());
+    }
+    let unchecked = args.listeners_without_api_keys();
+    if args.require_api_key && !unchecked.is_empty() {
+        return Err(format!("❌ --require-api-key cannot be combined with {}, which do not check API keys", unchecked.join(", ")).into());
+    }
+    let access_log = AccessLog::open(Path::new(&path_str), AccessLogSettings::from_args(&args)?)?;
+    if access_log.settings().is_enabled() {
+        tracing::info!(
+            all = access_log.settings().all,
+            docs = access_log.settings().docs.len(),
+            sample_rate = access_log.settings().sample_rate,
+            "👁️ Recording entry and blob reads in access.log"
+        );
+    }
+
+    let mut job_specs: Vec<JobSpec> = Vec::new();
+    for spec in &args.job {
+        let spec = JobSpec::parse(spec).map_err(|e| e.to_string())?;
+        if job_specs.iter().any(|scheduled| scheduled.kind == spec.kind) {
+            return Err(format!("❌ --job {} is given more than once", spec.kind).into());
+        }
+        if spec.kind == JobKind::Backup && backups.dir.is_none() {
+            return Err("❌ --job backup needs --backup-dir".into());
+        }
+        if spec.kind.writes() && args.read_only {
+            return Err(format!("❌ --job {} deletes entries, which a --read-only replica does not do", spec.kind).into());
+        }
+        job_specs.push(spec);
+    }
+
+    let keystore_dir = PathBuf::from(&path_str).join("keystore");
+    let audit = AuditLog::open(&keystore_dir)?;
+
+    let cord_signer = iroh_node.cord_signer.clone().with_audit(audit.clone());
+    let chain = ChainClient::new(cord_client.clone(), cord_signer.clone());
+    let anchor: Arc<dyn ChainAnchor> = match args.chain_backend {
+        ChainBackend::Cord => Arc::new(CordAnchor::new(chain.clone())),
+        ChainBackend::Local => {
+            let local = LocalAnchor::load(&path_str, chain.account())
+                .await
+                .map_err(|e| format!("❌ Failed to load local-chain.json: {e}"))?;
+            tracing::warn!(blocks = local.height().await, "⚠️ Anchoring to a local ledger. Its anchors and proofs are not verifiable by third parties.");
+            Arc::new(local)
+        },
+    };
+    let email = args.smtp_relay.clone().map(|relay| EmailSettings { relay, from: args.smtp_from.clone() });
+    let state = AppState {
+        blobs: iroh_node.blobs.clone(),
+        docs: iroh_node.docs.clone(),
+        cord_client: cord_client.clone(),
+        chain,
+        anchor,
+        cord_signer,
+        events: EventBus::new(),
+        webhooks: WebhookRegistry::load(&path_str).await?,
+        notifications: WatchRegistry::load(&path_str, email).await?,
+        key_types: KeyTypeRegistry::load(&keystore_dir)?,
+        approvals: ApprovalRegistry::load(&path_str, approval_policy).await?,
+        audit,
+        proofs: ProofRegistry::load(&path_str).await?,
+        authorizations: AuthorizationRegistry::load(
+            &path_str,
+            args.require_chain_authorization,
+            Duration::from_secs(args.authorization_cache_ttl),
+        ).await?,
+        sync_controls: SyncControlRegistry::load(&path_str).await?,
+        backups,
+        federation: FederationRegistry::load(&path_str, federation).await?,
+        replication: ReplicationTracker::new(),
+        write_queue: WriteQueue::load(&path_str).await?,
+        downloads: DownloadManager::new(args.download_connections, limits.clone()),
+        entry_index: EntryIndex::new(args.index_field.clone()),
+        search: SearchIndex::new(args.search)?,
+        limits,
+        jobs: JobRegistry::default(),
+        tenants: TenantRegistry::load(&path_str).await?,
+        access_log,
+        read_only: args.read_only,
+        public_gw: args.public_gw,
+        public,
+    };
+    if state.read_only {
+        tracing::info!("📖 Running as a read-only replica. Local writes are rejected.");
+    }
+
+    // Append the causes of errors to API error messages
+    set_debug_errors(args.debug_errors);
+    if args.debug_errors {
+        tracing::warn!("⚠️  --debug-errors is set: API error messages include their underlying causes.");
+    }
+
+    // Close the handles of documents that went cold
+    spawn_doc_handle_eviction(state.docs.handles.clone());
+
+    // Deliver document events to registered webhooks, and notify the watches they match
+    spawn_dispatcher(
+        state.docs.clone(),
+        state.events.clone(),
+        state.webhooks.clone(),
+        Notifier::new(state.notifications.clone()),
+    );
+
+    // Record the syncs and unpropagated local writes of every document
+    spawn_replication_tracker(state.docs.clone(), state.events.clone(), state.replication.clone(), state.write_queue.clone());
+
+    // Keep the stats and field indexes of every document up to date
+    spawn_entry_indexer(state.docs.clone(), state.blobs.clone(), state.events.clone(), state.entry_index.clone());
+
+    // Take dropped documents and deleted authors out of their tenants
+    spawn_tenant_tracker(state.events.clone(), state.tenants.clone());
+
+    // Keep the full-text index of schema-validated entries up to date
+    if state.search.is_enabled() {
+        spawn_search_indexer(state.docs.clone(), state.blobs.clone(), state.events.clone(), state.search.clone());
+    }
+
+    // Mirror the configured chain events into their documents
+    if !args.chain_event.is_empty() && state.read_only {
+        tracing::warn!("⚠️  --chain-event is ignored: a read-only replica does not write chain events into documents.");
+    } else if !args.chain_event.is_empty() {
+        let routes = args.chain_event
+            .iter()
+            .map(|route| ChainEventRoute::parse(route))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("❌ {e}"))?;
+        let author_id = get_default_author(state.docs.clone()).await?;
+        let docs = state.docs.clone();
+        spawn_event_listener(state.cord_client.clone(), routes, move |route, event| {
+            let docs = docs.clone();
+            let author_id = author_id.clone();
+            async move {
+                if let Err(e) = record_chain_event(docs, route.doc_id.clone(), author_id, &event).await {
+                    tracing::warn!(doc_id = %route.doc_id, block_number = event.block_number, error = %e, "failed to mirror chain event");
+                }
+            }
+        });
+        tracing::info!(routes = args.chain_event.len(), "⛓ Mirroring chain events into documents");
+    }
+
+    // Run the maintenance jobs on their schedules. A scheduled job replaces the fixed interval
+    // its task otherwise runs on below.
+    register_jobs(&state, &args, job_specs);
+    spawn_job_scheduler(state.jobs.clone());
+    for job in state.jobs.list().iter().filter(|job| job.schedule.is_some()) {
+        tracing::info!(job = %job.name, schedule = job.schedule.as_deref().unwrap_or_default(), "⏰ Scheduled maintenance job");
+    }
+
+    // Anchor the proofs queued with `"batch": true` together
+    if !state.jobs.is_scheduled(JobKind::AnchorProofs) {
+        spawn_proof_batcher(state.anchor.clone(), state.proofs.clone(), Duration::from_secs(args.proof_batch_interval));
+    }
+
+    // Pause, window and throttle the sync of documents with sync controls
+    spawn_sync_scheduler(state.docs.clone(), state.sync_controls.clone());
+
+    // Snapshot docs, blobs and authors into the backup directory
+    if let (Some(dir), Some(interval), false) = (&state.backups.dir, args.backup_interval, state.jobs.is_scheduled(JobKind::Backup)) {
+        spawn_backup_scheduler(state.docs.clone(), state.blobs.clone(), state.backups.clone(), Duration::from_secs(interval));
+        tracing::info!(dir = ?dir, interval, keep = state.backups.keep, s3 = state.backups.s3.is_some(), "💾 Writing scheduled snapshots");
+    }
+
+    // Apply the content policies of documents, fetching the content they pin to this node
+    spawn_pinning(state.docs.clone(), state.blobs.clone());
+
+    // Delete for good the entries kept in the trash past their retention
+    if args.trash_retention > 0 && !state.read_only && !state.jobs.is_scheduled(JobKind::TrashPurge) {
+        spawn_trash_purger(state.docs.clone(), Duration::from_secs(args.trash_retention));
+    }
+
+    // Delete for good the past versions of entries kept past their retention
+    if args.version_retention > 0 && !state.read_only && !state.jobs.is_scheduled(JobKind::HistoryPurge) {
+        spawn_history_purger(state.docs.clone(), Duration::from_secs(args.version_retention));
+    }
+
+    // Join and leave the documents listed in the federation catalog
+    if state.federation.is_enabled() {
+        spawn_federation(state.docs.clone(), state.blobs.clone(), state.federation.clone(), state.sync_controls.clone());
+        tracing::info!(trusted_authors = state.federation.settings().trusted_authors.len(), "🌐 Following a federation catalog");
+    }
+
+    // Stops the servers once `RunningNode::shutdown` is called
+    let (shutdown_tx, shutdown_rx) = watch::channel(());
+    let shutdown_for = |mut rx: watch::Receiver<()>| async move {
+        let _ = rx.changed().await;
+    };
+
+    // Start the gRPC server, if enabled
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_listen) = args.grpc_listen.clone() {
+        let grpc_addr: std::net::SocketAddr = grpc_listen.parse()?;
+        let grpc_state = state.clone();
+        let shutdown = shutdown_for(shutdown_rx.clone());
+        tokio::spawn(async move {
+            if let Err(e) = grpc::service::serve(grpc_state, grpc_addr, shutdown).await {
+                tracing::error!(error = %e, "❌ gRPC server failed");
+            }
+        });
+        tracing::info!(address = %grpc_addr, "📡 gRPC server is live");
+    }
+    #[cfg(not(feature = "grpc"))]
+    if args.grpc_listen.is_some() {
+        tracing::warn!("⚠️  --grpc-listen is ignored: this binary was built without the `grpc` feature.");
+    }
+
+    let router_config = RouterConfig {
+        compression_min_size: args.compression_min_size,
+        timeouts: RouteTimeouts {
+            read: Duration::from_secs(args.read_timeout),
+            write: Duration::from_secs(args.write_timeout),
+            download: Duration::from_secs(args.download_timeout),
+        },
+    };
+    let admin_app = args.admin_listen.as_ref().map(|_| create_admin_router(state.clone()));
+    let s3_app = args.s3_listen.as_ref().map(|_| create_s3_router(state.clone(), router_config.clone()));
+    let webdav_app = args.webdav_listen.as_ref().map(|_| create_webdav_router(state.clone(), router_config.clone()));
+    let public_app = args.public_listen.as_ref().map(|_| create_public_router(state.clone(), router_config.clone()));
+    let mut app = create_router(state.clone(), router_config);
+    for routes in extra_routes {
+        app = app.merge(routes(state.clone()));
+    }
+
+    // Bind every listener up front so a bad address fails startup
+    let listen_addrs = if args.listen.is_empty() && args.listen_uds.is_empty() {
+        vec![DEFAULT_LISTEN_ADDR.to_string()]
+    } else {
+        args.listen.clone()
+    };
+
+    let mut tcp_listeners = Vec::new();
+    for addr in &listen_addrs {
+        tcp_listeners.push(tokio::net::TcpListener::bind(addr).await?);
+    }
+    let admin_listener = match &args.admin_listen {
+        Some(addr) => Some(tokio::net::TcpListener::bind(addr).await?),
+        None => None,
+    };
+    let s3_listener = match &args.s3_listen {
+        Some(addr) => Some(tokio::net::TcpListener::bind(addr).await?),
+        None => None,
+    };
+    let webdav_listener = match &args.webdav_listen {
+        Some(addr) => Some(tokio::net::TcpListener::bind(addr).await?),
+        None => None,
+    };
+    let public_listener = match &args.public_listen {
+        Some(addr) => Some(tokio::net::TcpListener::bind(addr).await?),
+        None => None,
+    };
+    let mut uds_listeners = Vec::new();
+    for socket_path in &args.listen_uds {
+        let socket_path = PathBuf::from(socket_path);
+        uds_listeners.push((bind_uds(&socket_path)?, socket_path));
+    }
+
+    let mut servers = Vec::new();
+    let mut http_addrs = Vec::new();
+    // Without a key, only the unix sockets are served, see `--require-api-key`
+    let tcp_app = if args.require_api_key { with_required_api_key(app.clone()) } else { app.clone() };
+    for listener in tcp_listeners {
+        http_addrs.push(listener.local_addr()?);
+        tracing::info!(address = %format!("http://{}", listener.local_addr()?), "🚀 Server is live");
+        servers.push(tokio::spawn(serve_tcp(listener, tcp_app.clone(), shutdown_for(shutdown_rx.clone()))));
+    }
+    for (listener, socket_path) in uds_listeners {
+        tracing::info!(address = %format!("unix:{}", socket_path.display()), "🚀 Server is live");
+        let app = app.clone();
+        let shutdown = shutdown_for(shutdown_rx.clone());
+        servers.push(tokio::spawn(async move {
+            serve_uds(listener, &socket_path, app, shutdown).await
+        }));
+    }
+
+    if let (Some(listener), Some(admin_app)) = (admin_listener, admin_app) {
+        tracing::info!(address = %format!("http://{}/debug", listener.local_addr()?), "🩺 Diagnostics are live");
+        servers.push(tokio::spawn(serve_tcp(listener, admin_app, shutdown_for(shutdown_rx.clone()))));
+    }
+    if let (Some(listener), Some(s3_app)) = (s3_listener, s3_app) {
+        tracing::info!(address = %format!("http://{}", listener.local_addr()?), "🪣 S3 API is live");
+        servers.push(tokio::spawn(serve_tcp(listener, s3_app, shutdown_for(shutdown_rx.clone()))));
+    }
+    if let (Some(listener), Some(webdav_app)) = (webdav_listener, webdav_app) {
+        tracing::info!(address = %format!("http://{}", listener.local_addr()?), "📂 WebDAV share is live");
+        servers.push(tokio::spawn(serve_tcp(listener, webdav_app, shutdown_for(shutdown_rx.clone()))));
+    }
+    if let (Some(listener), Some(public_app)) = (public_listener, public_app) {
+        tracing::info!(
+            address = %format!("http://{}", listener.local_addr()?),
+            docs = state.public.docs.len(),
+            blobs = state.public.blobs.len(),
+            "🌍 Public gateway is live"
+        );
+        servers.push(tokio::spawn(serve_tcp_with_client_addr(listener, public_app, shutdown_for(shutdown_rx.clone()))));
+    }
+
+    Ok(RunningNode {
+        node_id: iroh_node.node_id,
+        state,
+        http_addrs,
+        iroh: iroh_node.router,
+        shutdown_tx,
+        servers,
+    })
+}
@@ -0,0 +1,32 @@
+//! Runs a starter-kit node inside another program, with the same stores, gateway and API as
+//! `serve`.
+//!
+//! ```no_run
+//! use axum::{extract::State, routing::get, Router};
+//! use starter_kit::{AppState, StarterKit};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let node = StarterKit::builder()
+//!     .path("data")
+//!     .password("secret password")
+//!     .listen("127.0.0.1:0")
+//!     .allow_domain("app.example.com")
+//!     .routes(|state: AppState| {
+//!         Router::new()
+//!             .route("/app/read-only", get(|State(state): State<AppState>| async move { state.read_only.to_string() }))
+//!             .with_state(state)
+//!     })
+//!     .start()
+//!     .await?;
+//!
+//! println!("{} serves on {:?}", node.node_id(), node.http_addrs());
+//! node.shutdown().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod jobs;
+mod kit;
+
+pub use kit::{RunningNode, StarterKit, StarterKitBuilder};
+pub use helpers::{cli::CliArgs, state::AppState};
@@ -0,0 +1,63 @@
+use helpers::cli::{LogArgs, LogFormat, LogRotation};
+
+use std::error::Error;
+use tracing_appender::{non_blocking::WorkerGuard, rolling};
+use tracing_subscriber::{fmt::{self, MakeWriter}, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer, Registry};
+
+// Log files are named `starter-kit.<date>.log`
+const LOG_FILE_PREFIX: &str = "starter-kit";
+const LOG_FILE_SUFFIX: &str = "log";
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Installs the global tracing subscriber.
+///
+/// Logs always go to stderr, so command output on stdout can be piped. With `--log-dir` they are
+/// also written to rolling files; keep the returned guard alive until exit so buffered lines are flushed.
+pub fn init(args: &LogArgs) -> Result<Option<WorkerGuard>, Box<dyn Error>> {
+    let filter = match &args.log_level {
+        Some(level) => EnvFilter::try_new(level).map_err(|e| format!("❌ Invalid --log-level '{}': {}", level, e))?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+
+    let mut layers = vec![layer(args.log_format, std::io::stderr, true)];
+
+    let guard = match &args.log_dir {
+        Some(dir) => {
+            let rotation = match args.log_rotation {
+                LogRotation::Minutely => rolling::Rotation::MINUTELY,
+                LogRotation::Hourly => rolling::Rotation::HOURLY,
+                LogRotation::Daily => rolling::Rotation::DAILY,
+                LogRotation::Never => rolling::Rotation::NEVER,
+            };
+            let appender = rolling::Builder::new()
+                .rotation(rotation)
+                .filename_prefix(LOG_FILE_PREFIX)
+                .filename_suffix(LOG_FILE_SUFFIX)
+                .build(dir)
+                .map_err(|e| format!("❌ Failed to open log directory {:?}: {}", dir, e))?;
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            layers.push(layer(args.log_format, writer, false));
+            Some(guard)
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .try_init()?;
+
+    Ok(guard)
+}
+
+fn layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let layer = fmt::layer().with_writer(writer).with_ansi(ansi);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
@@ -1,80 +1,45 @@
-use node::iroh_wrapper::{setup_iroh_node, IrohNode};
-use router::router::create_router;
-use helpers::{
-    cli::CliArgs,
-    frontend::start_frontend,
-    state::AppState,
-};
-use gateway::{
-    storage::init_access_control,
-    access_control::{set_storage_path, ensure_self_node_id_allowed},
-};
-use cord::cord::connect_to_chain;
+mod commands;
+mod logging;
+
+use helpers::{cli::{Cli, Command}, config::resolve_serve_args};
 
-use tokio::signal;
 use std::error::Error;
-use clap::Parser;
-use std::sync::Arc;
+use clap::{CommandFactory, FromArgMatches};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let cord_client = connect_to_chain().await?;
-    let cord_client = Arc::new(cord_client);
-
-    // Parse CLI arguments
-    let args = CliArgs::parse();
-
-    // Initialize the Iroh node
-    let iroh_node: IrohNode = setup_iroh_node(args.clone()).await?;
-
-    // Initialize gateway
-    let path = args.path.unwrap();
-    let path_str = path.to_string();
-    let (mut allowed_node_ids, allowed_domains) = init_access_control(&path_str.clone()).await?;
-
-    // Ensure self NodeId is added on first run
-    ensure_self_node_id_allowed(
-        &path_str.to_string().clone(), 
-        iroh_node.node_id.to_string().clone(), 
-        &mut allowed_node_ids
-    ).await?;
-
-    set_storage_path(
-        path_str.to_string(), 
-        allowed_node_ids, 
-        allowed_domains
-    );
-
-    // Start frontend
-    // start_frontend();
-
-    println!(
-        "✅ Iroh node started successfully!\n🔗 Your NodeId: {}\n",
-        iroh_node.node_id
-    );
-
-    let state = AppState {
-        blobs: iroh_node.blobs.clone(),
-        docs: iroh_node.docs.clone(),
-        cord_client: cord_client.clone(),
-        cord_signer: iroh_node.cord_signer.clone(),
-    };
-
-    let app = create_router(state);
-
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:4001").await?;
-    println!("🚀 Server is live at: http://localhost:4001\n");
-
-    println!("🛑 Press Ctrl+C to shut down the server...\n");
-
-    let shutdown_signal = async {
-        signal::ctrl_c().await.expect("failed to listen for event");
-        println!("\n👋 Shutdown signal received. Exiting gracefully...\n");
-    };
-
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal)
-        .await?;
-
-    Ok(())
-}
\ No newline at end of file
+    // Parse CLI arguments. The raw matches tell which flags were given explicitly, so they
+    // can take precedence over the config file.
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+
+    // Logs go to stderr, and to rolling files with --log-dir
+    let _log_guard = logging::init(&cli.log)?;
+
+    match cli.command {
+        Command::Init(args) => commands::init::run(args).await,
+        Command::Keygen(args) => commands::keygen::run(args),
+        Command::Serve(args) => {
+            let args = resolve_serve_args(args, subcommand_matches(&matches))?;
+            commands::serve::run(args).await
+        }
+        Command::CheckConfig(args) => {
+            let args = resolve_serve_args(args, subcommand_matches(&matches))?;
+            commands::check_config::run(args).await
+        }
+        Command::Status(args) => commands::status::run(args).await,
+        Command::Console(args) => commands::console::run(args).await,
+        Command::Docs(args) => commands::docs::run(args).await,
+        Command::Blob(args) => commands::blob::run(args).await,
+        Command::Join(args) => commands::join::run(args).await,
+        Command::Author(args) => commands::author::run(args).await,
+        Command::Export(args) => commands::export::run(args).await,
+        Command::Import(args) => commands::import::run(args).await,
+        Command::RestoreSnapshot(args) => commands::restore_snapshot::run(args).await,
+        Command::Bench(args) => commands::bench::run(args).await,
+    }
+}
+
+fn subcommand_matches(matches: &clap::ArgMatches) -> &clap::ArgMatches {
+    matches.subcommand().map(|(_, matches)| matches).unwrap_or(matches)
+}
@@ -0,0 +1,82 @@
+use subxt::ext::scale_value::{At, Value};
+use subxt::utils::H256;
+use subxt::config::PolkadotConfig;
+use subxt::client::OnlineClient;
+use std::sync::Arc;
+use crate::client::ChainClient;
+use crate::profile::try_extract_bytes;
+
+/// Where a digest was anchored on chain.
+#[derive(Debug, Clone)]
+pub struct AnchorReceipt {
+    /// 0x-prefixed hash of the finalized block that includes the extrinsic.
+    pub block_hash: String,
+    pub block_number: u64,
+    /// 0x-prefixed hash of the extrinsic.
+    pub extrinsic_hash: String,
+    /// Position of the extrinsic in its block; `<block_number>-<extrinsic_index>` identifies it
+    /// in block explorers.
+    pub extrinsic_index: u32,
+    /// SS58 account that signed the extrinsic.
+    pub account: String,
+}
+
+// Submits `digest` as the remark of a `System::remark_with_event` extrinsic, which every CORD
+// runtime has. The chain emits a `Remarked` event with the remark's hash, and anyone can read the
+// digest back from the extrinsic.
+pub async fn anchor_digest(
+    chain: &ChainClient,
+    digest: [u8; 32],
+) -> Result<AnchorReceipt, Box<dyn std::error::Error>> {
+    let payload = subxt::tx::dynamic("System", "remark_with_event", vec![Value::from_bytes(digest)]);
+
+    let tx = chain
+        .submit(&payload)
+        .await
+        .map_err(|e| format!("Transaction was not finalized successfully: {e}"))?;
+
+    let mut remarked = false;
+    for ev in tx.events.iter() {
+        let ev = ev?;
+        if ev.pallet_name() == "System" && ev.variant_name() == "Remarked" {
+            remarked = true;
+        }
+    }
+    if !remarked {
+        return Err("Remarked event not found".into());
+    }
+
+    Ok(AnchorReceipt {
+        block_hash: format!("{:?}", tx.block_hash),
+        block_number: tx.block_number,
+        extrinsic_hash: format!("{:?}", tx.events.extrinsic_hash()),
+        extrinsic_index: tx.events.extrinsic_index(),
+        account: chain.account(),
+    })
+}
+
+// Returns the remark of the `System::remark_with_event` extrinsic at `extrinsic_index` of block
+// `block_hash`, or `None` if that extrinsic is something else or does not exist. Used to check an
+// `AnchorReceipt` against the chain.
+pub async fn remark_at(
+    cord_client: Arc<OnlineClient<PolkadotConfig>>,
+    block_hash: &str,
+    extrinsic_index: u32,
+) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    let client: &OnlineClient<PolkadotConfig> = &cord_client;
+
+    let hash = hex::decode(block_hash.trim_start_matches("0x"))
+        .ok()
+        .filter(|bytes| bytes.len() == 32)
+        .ok_or_else(|| format!("Invalid block hash {block_hash}"))?;
+    let block = client.blocks().at(H256::from_slice(&hash)).await?;
+
+    let extrinsics = block.extrinsics().await?;
+    let Some(extrinsic) = extrinsics.iter().find(|extrinsic| extrinsic.index() == extrinsic_index) else {
+        return Ok(None);
+    };
+    if extrinsic.pallet_name()? != "System" || extrinsic.variant_name()? != "remark_with_event" {
+        return Ok(None);
+    }
+    Ok(extrinsic.field_values()?.at("remark").and_then(try_extract_bytes))
+}
@@ -0,0 +1,180 @@
+use crate::anchor::{anchor_digest, remark_at, AnchorReceipt};
+use crate::client::ChainClient;
+
+use serde::{Deserialize, Serialize};
+use sp_core::blake2_256;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Error of a chain backend.
+pub type AnchorError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Future returned by `ChainAnchor`, boxed so backends can be used as `dyn ChainAnchor`.
+pub type AnchorFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, AnchorError>> + Send + 'a>>;
+
+/// A chain that digests are anchored to and read back from.
+///
+/// Document anchors, revocations and proof receipts only go through this trait, so the node can
+/// anchor to CORD or, for development and tests, to a local ledger. A receipt can only be checked
+/// with the backend that made it.
+pub trait ChainAnchor: Send + Sync {
+    /// Name of the backend, e.g. `cord`.
+    fn name(&self) -> &'static str;
+
+    /// Anchors `digest` and waits until it is final.
+    fn anchor(&self, digest: [u8; 32]) -> AnchorFuture<'_, AnchorReceipt>;
+
+    /// The digest anchored by the extrinsic at `extrinsic_index` of block `block_hash`, or `None`
+    /// if that extrinsic is not an anchor or does not exist.
+    fn remark_at<'a>(&'a self, block_hash: &'a str, extrinsic_index: u32) -> AnchorFuture<'a, Option<Vec<u8>>>;
+}
+
+/// Anchors digests as `System::remark_with_event` extrinsics on the CORD chain, see
+/// `anchor_digest`.
+#[derive(Clone)]
+pub struct CordAnchor {
+    chain: ChainClient,
+}
+
+impl CordAnchor {
+    pub fn new(chain: ChainClient) -> Self {
+        Self { chain }
+    }
+}
+
+impl ChainAnchor for CordAnchor {
+    fn name(&self) -> &'static str {
+        "cord"
+    }
+
+    fn anchor(&self, digest: [u8; 32]) -> AnchorFuture<'_, AnchorReceipt> {
+        Box::pin(async move { anchor_digest(&self.chain, digest).await.map_err(|e| e.to_string().into()) })
+    }
+
+    fn remark_at<'a>(&'a self, block_hash: &'a str, extrinsic_index: u32) -> AnchorFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            remark_at(self.chain.online(), block_hash, extrinsic_index)
+                .await
+                .map_err(|e| e.to_string().into())
+        })
+    }
+}
+
+// File the local ledger is persisted to, inside the node's data directory.
+const LOCAL_CHAIN_FILE: &str = "local-chain.json";
+
+// A block of the local ledger. Each block holds one extrinsic, at index 0, whose remark is the
+// anchored digest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LocalBlock {
+    block_hash: String,
+    block_number: u64,
+    extrinsic_hash: String,
+    remark: String,
+}
+
+/// Anchors digests to a ledger kept by the node itself, for development and tests without a
+/// chain. Receipts look like CORD ones and verify against this ledger, but prove nothing to
+/// anyone who has to trust the node.
+///
+/// Each anchor makes a block whose hash chains the previous block's hash, its number and the
+/// digest. Clones share the ledger.
+#[derive(Clone)]
+pub struct LocalAnchor {
+    account: String,
+    blocks: Arc<Mutex<Vec<LocalBlock>>>,
+    storage_path: Option<PathBuf>,
+}
+
+impl LocalAnchor {
+    /// An empty ledger kept in memory, whose receipts name `account` as the signer.
+    pub fn new(account: String) -> Self {
+        Self { account, blocks: Arc::default(), storage_path: None }
+    }
+
+    /// Loads the ledger from the data directory at `path`, starting empty if nothing was saved yet.
+    pub async fn load(path: &str, account: String) -> Result<Self, AnchorError> {
+        let storage_path = PathBuf::from(path).join(LOCAL_CHAIN_FILE);
+
+        let blocks = if tokio::fs::try_exists(&storage_path).await? {
+            let content = tokio::fs::read_to_string(&storage_path).await?;
+            serde_json::from_str(&content)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            account,
+            blocks: Arc::new(Mutex::new(blocks)),
+            storage_path: Some(storage_path),
+        })
+    }
+
+    /// Number of blocks in the ledger.
+    pub async fn height(&self) -> u64 {
+        self.blocks.lock().await.len() as u64
+    }
+}
+
+impl ChainAnchor for LocalAnchor {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn anchor(&self, digest: [u8; 32]) -> AnchorFuture<'_, AnchorReceipt> {
+        Box::pin(async move {
+            // held until the ledger is saved, so blocks are written in order
+            let mut blocks = self.blocks.lock().await;
+            let parent_hash = blocks.last().map(|block| block.block_hash.clone()).unwrap_or_default();
+            let block_number = blocks.len() as u64 + 1;
+
+            let mut header = parent_hash.into_bytes();
+            header.extend_from_slice(&block_number.to_be_bytes());
+            header.extend_from_slice(&digest);
+            let block_hash = format!("0x{}", hex::encode(blake2_256(&header)));
+            let extrinsic_hash = format!("0x{}", hex::encode(blake2_256(&[block_hash.as_bytes(), &digest].concat())));
+
+            blocks.push(LocalBlock {
+                block_hash: block_hash.clone(),
+                block_number,
+                extrinsic_hash: extrinsic_hash.clone(),
+                remark: format!("0x{}", hex::encode(digest)),
+            });
+            if let Some(storage_path) = &self.storage_path {
+                let saved = match serde_json::to_string_pretty(&*blocks) {
+                    Ok(json) => tokio::fs::write(storage_path, json).await.map_err(AnchorError::from),
+                    Err(e) => Err(e.into()),
+                };
+                // a block that was not saved was never final
+                if let Err(e) = saved {
+                    blocks.pop();
+                    return Err(e);
+                }
+            }
+
+            Ok(AnchorReceipt {
+                block_hash,
+                block_number,
+                extrinsic_hash,
+                extrinsic_index: 0,
+                account: self.account.clone(),
+            })
+        })
+    }
+
+    fn remark_at<'a>(&'a self, block_hash: &'a str, extrinsic_index: u32) -> AnchorFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            if extrinsic_index != 0 {
+                return Ok(None);
+            }
+            let blocks = self.blocks.lock().await;
+            Ok(blocks
+                .iter()
+                .find(|block| block.block_hash == block_hash)
+                .and_then(|block| hex::decode(block.remark.trim_start_matches("0x")).ok()))
+        })
+    }
+}
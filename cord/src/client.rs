@@ -0,0 +1,146 @@
+use keystore::keystore::CordKeystoreSigner;
+
+use subxt::blocks::ExtrinsicEvents;
+use subxt::config::{DefaultExtrinsicParamsBuilder, PolkadotConfig};
+use subxt::client::OnlineClient;
+use subxt::error::{Error, TransactionError};
+use subxt::tx::{Payload, Signer};
+use subxt::utils::H256;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use crate::profile::format_account_id_as_ss58;
+
+/// How a transaction the pool rejected is resubmitted.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Submissions in total, including the first.
+    pub attempts: u32,
+    /// Wait before the first resubmission, doubled after each one.
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { attempts: 4, delay: Duration::from_secs(3) }
+    }
+}
+
+/// A transaction that was finalized and dispatched successfully.
+#[derive(Debug)]
+pub struct FinalizedTx {
+    pub block_hash: H256,
+    pub block_number: u64,
+    /// The events the transaction emitted; also gives its hash and index in the block.
+    pub events: ExtrinsicEvents<PolkadotConfig>,
+}
+
+/// Submits CORD extrinsics signed with the node's CORD key, which signs through the keystore, so
+/// the key can live in a local keystore or behind a remote one.
+///
+/// Clones share the account nonce, so transactions submitted concurrently from anywhere in the
+/// node get consecutive nonces instead of colliding in the pool. The nonce is read from the chain
+/// on first use and again after any failed submission.
+#[derive(Clone)]
+pub struct ChainClient {
+    client: Arc<OnlineClient<PolkadotConfig>>,
+    signer: CordKeystoreSigner,
+    next_nonce: Arc<Mutex<Option<u64>>>,
+    retry: RetryPolicy,
+}
+
+impl ChainClient {
+    pub fn new(client: Arc<OnlineClient<PolkadotConfig>>, signer: CordKeystoreSigner) -> Self {
+        Self {
+            client,
+            signer,
+            next_nonce: Arc::new(Mutex::new(None)),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// The client, for reading chain state.
+    pub fn online(&self) -> Arc<OnlineClient<PolkadotConfig>> {
+        self.client.clone()
+    }
+
+    /// SS58 address of the account that signs the transactions.
+    pub fn account(&self) -> String {
+        format_account_id_as_ss58(&self.signer.public.0)
+    }
+
+    /// Signs and submits `call`, and waits until it is finalized.
+    ///
+    /// Transactions the pool rejects or reports as invalid or dropped are resubmitted with a fresh
+    /// nonce according to the retry policy. A transaction that made it into a block is never
+    /// resubmitted, so a failed dispatch or a lost subscription is returned as is.
+    pub async fn submit<Call: Payload>(&self, call: &Call) -> Result<FinalizedTx, Error> {
+        let mut delay = self.retry.delay;
+        let mut attempt = 1;
+        loop {
+            match self.submit_once(call).await {
+                Err(Attempt::Retryable(e)) if attempt < self.retry.attempts => {
+                    tracing::warn!(error = %e, attempt, "transaction was rejected, resubmitting");
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                },
+                Err(Attempt::Retryable(e)) | Err(Attempt::Final(e)) => return Err(e),
+                Ok(tx) => return Ok(tx),
+            }
+        }
+    }
+
+    async fn submit_once<Call: Payload>(&self, call: &Call) -> Result<FinalizedTx, Attempt> {
+        let progress = {
+            // held until the pool accepted the transaction, so the next one gets the next nonce
+            let mut next_nonce = self.next_nonce.lock().await;
+            let nonce = match *next_nonce {
+                Some(nonce) => nonce,
+                None => self
+                    .client
+                    .tx()
+                    .account_nonce(&self.signer.account_id())
+                    .await
+                    .map_err(Attempt::Retryable)?,
+            };
+            let params = DefaultExtrinsicParamsBuilder::<PolkadotConfig>::new().nonce(nonce).build();
+            match self.client.tx().sign_and_submit_then_watch(call, &self.signer, params).await {
+                Ok(progress) => {
+                    *next_nonce = Some(nonce + 1);
+                    progress
+                },
+                Err(e) => {
+                    *next_nonce = None;
+                    return Err(Attempt::Retryable(e));
+                },
+            }
+        };
+
+        let in_block = match progress.wait_for_finalized().await {
+            Ok(in_block) => in_block,
+            Err(e @ Error::Transaction(TransactionError::Invalid(_) | TransactionError::Dropped(_))) => {
+                // the nonce was not used, so later transactions would wait for it forever
+                *self.next_nonce.lock().await = None;
+                return Err(Attempt::Retryable(e));
+            },
+            Err(e) => return Err(Attempt::Final(e)),
+        };
+        let block_hash = in_block.block_hash();
+        let events = in_block.wait_for_success().await.map_err(Attempt::Final)?;
+        let block = self.client.blocks().at(block_hash).await.map_err(Attempt::Final)?;
+
+        Ok(FinalizedTx { block_hash, block_number: block.number().into(), events })
+    }
+}
+
+// Whether a failed submission may be tried again
+enum Attempt {
+    Retryable(Error),
+    Final(Error),
+}
@@ -0,0 +1,140 @@
+use subxt::config::PolkadotConfig;
+use subxt::client::OnlineClient;
+use subxt::events::Phase;
+use subxt::ext::scale_value::{Value, ValueDef};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use crate::registry::value_to_json;
+
+// How long to wait before subscribing again after the block subscription ends or fails
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Sends the chain events of a pallet, or of one of its events, to a document. Parsed from
+/// `<Pallet>[.<Event>]=<doc_id>`, e.g. `Statement.Registered=d...` or `Schema=d...`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainEventRoute {
+    pub pallet: String,
+    pub variant: Option<String>,
+    pub doc_id: String,
+}
+
+impl ChainEventRoute {
+    pub fn parse(route: &str) -> Result<Self, String> {
+        let (event, doc_id) = route
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid chain event route '{}'. Expected <Pallet>[.<Event>]=<doc_id>.", route))?;
+        let (pallet, variant) = match event.split_once('.') {
+            Some((pallet, variant)) => (pallet, Some(variant.to_string())),
+            None => (event, None),
+        };
+        if pallet.is_empty() || variant.as_deref() == Some("") || doc_id.is_empty() {
+            return Err(format!("Invalid chain event route '{}'. Expected <Pallet>[.<Event>]=<doc_id>.", route));
+        }
+        Ok(Self { pallet: pallet.to_string(), variant, doc_id: doc_id.to_string() })
+    }
+}
+
+/// An event of a finalized block. The block hash and event index locate it on chain, so anyone
+/// can check the record against the chain.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainEventRecord {
+    pub pallet: String,
+    pub variant: String,
+    pub block_hash: String,
+    pub block_number: u64,
+    /// Position of the event among the block's events.
+    pub event_index: u32,
+    /// The extrinsic that emitted the event, or `None` for block initialization and finalization.
+    pub extrinsic_index: Option<u32>,
+    /// The decoded event fields, see `registry::ChainDefinition`.
+    pub fields: serde_json::Value,
+}
+
+/// Follows finalized blocks and calls `deliver` with every event that matches a route, once per
+/// matching route, in block order. The subscription is renewed when it drops; events finalized
+/// while it was down are not replayed.
+pub fn spawn_event_listener<F, Fut>(
+    cord_client: Arc<OnlineClient<PolkadotConfig>>,
+    routes: Vec<ChainEventRoute>,
+    mut deliver: F,
+) -> JoinHandle<()>
+where
+    F: FnMut(ChainEventRoute, ChainEventRecord) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    tokio::spawn(async move {
+        loop {
+            let mut blocks = match cord_client.blocks().subscribe_finalized().await {
+                Ok(blocks) => blocks,
+                Err(e) => {
+                    tracing::warn!(error = %e, "failed to subscribe to finalized blocks");
+                    tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+                    continue;
+                }
+            };
+
+            while let Some(block) = blocks.next().await {
+                let block = match block {
+                    Ok(block) => block,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "finalized block subscription failed");
+                        break;
+                    }
+                };
+                let block_number: u64 = block.number().into();
+                let events = match block.events().await {
+                    Ok(events) => events,
+                    Err(e) => {
+                        tracing::warn!(block_number, error = %e, "failed to read block events");
+                        continue;
+                    }
+                };
+
+                for event in events.iter() {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(e) => {
+                            tracing::warn!(block_number, error = %e, "failed to decode block event");
+                            continue;
+                        }
+                    };
+                    let wanted: Vec<&ChainEventRoute> = routes
+                        .iter()
+                        .filter(|route| route.pallet == event.pallet_name() && route.variant.as_ref().is_none_or(|variant| variant == event.variant_name()))
+                        .collect();
+                    if wanted.is_empty() {
+                        continue;
+                    }
+
+                    let fields = match event.field_values() {
+                        Ok(fields) => value_to_json(&Value { value: ValueDef::Composite(fields), context: 0 }),
+                        Err(e) => {
+                            tracing::warn!(block_number, error = %e, "failed to decode event fields");
+                            continue;
+                        }
+                    };
+                    let record = ChainEventRecord {
+                        pallet: event.pallet_name().to_string(),
+                        variant: event.variant_name().to_string(),
+                        block_hash: format!("{:?}", block.hash()),
+                        block_number,
+                        event_index: event.index(),
+                        extrinsic_index: match event.phase() {
+                            Phase::ApplyExtrinsic(index) => Some(index),
+                            _ => None,
+                        },
+                        fields,
+                    };
+                    for route in wanted {
+                        deliver(route.clone(), record.clone()).await;
+                    }
+                }
+            }
+
+            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+        }
+    })
+}
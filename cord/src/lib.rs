@@ -1,2 +1,8 @@
 pub mod cord;
-pub mod profile;
\ No newline at end of file
+pub mod client;
+pub mod profile;
+pub mod anchor;
+pub mod backend;
+pub mod registry;
+pub mod schema;
+pub mod events;
\ No newline at end of file
@@ -1,35 +1,25 @@
-use keystore::keystore::CordKeystoreSigner;
+use crate::client::ChainClient;
 
 use subxt_rpcs::RpcClient;
 // use subxt::ext::scale_value::Value;
 use subxt::ext::scale_value::{value, Value, ValueDef};
 // use subxt::{dynamic::Value, ext::scale_value::ValueDef};
-use subxt::config::PolkadotConfig; // or your chain's config
-use subxt::client::OnlineClient;
 use subxt::ext::scale_value::At;
-use std::sync::Arc;
 use sp_core::crypto::Ss58AddressFormat;
 use sp_core::ed25519;
 use sp_core::crypto::Ss58Codec;
 
 pub async fn create_profile(
-    cord_client: Arc<OnlineClient<PolkadotConfig>>, 
-    signer: CordKeystoreSigner
+    chain: &ChainClient,
 ) -> Result<(String, String), Box<dyn std::error::Error>> {
-    // get the OnlineClient<PolkadotConfig>
-    let client: &OnlineClient<PolkadotConfig> = &*cord_client;
-
     let data: Value = value! { () };
     let payload = subxt::tx::dynamic("Profile", "set_profile", vec![data]);
 
-    let events = client
-        .tx()
-        .sign_and_submit_then_watch_default(&payload, &signer)
-        .await
-        .map_err(|e| format!("Failed to sign and submit transaction: {e}"))?
-        .wait_for_finalized_success()
+    let events = chain
+        .submit(&payload)
         .await
-        .map_err(|e| format!("Transaction was not finalized successfully: {e}"))?;
+        .map_err(|e| format!("Transaction was not finalized successfully: {e}"))?
+        .events;
     
     for ev in events.iter() {
         let ev = ev?;
@@ -0,0 +1,220 @@
+use subxt::ext::scale_value::{At, Composite, Primitive, Value, ValueDef};
+use subxt::config::PolkadotConfig;
+use subxt::client::OnlineClient;
+use std::sync::Arc;
+use serde_json::json;
+use crate::client::ChainClient;
+use crate::profile::try_extract_bytes;
+
+/// The definitions on CORD that can be mirrored into documents, each read from the storage map
+/// of its pallet by identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainDefinitionKind {
+    /// `ChainSpace::Spaces`: a chain space, which scopes schemas, registries and statements.
+    Space,
+    /// `Schema::Schemas`: a JSON schema registered on chain.
+    Schema,
+    /// `Registries::RegistryInfo`: a registry and its governance.
+    Registry,
+}
+
+/// The kind names used by the API.
+pub const CHAIN_DEFINITION_KIND_NAMES: [&str; 3] = ["space", "schema", "registry"];
+
+impl ChainDefinitionKind {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "space" => Ok(Self::Space),
+            "schema" => Ok(Self::Schema),
+            "registry" => Ok(Self::Registry),
+            _ => Err(format!("Unknown chain definition kind '{}'. Expected one of: {}.", name, CHAIN_DEFINITION_KIND_NAMES.join(", "))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Space => "space",
+            Self::Schema => "schema",
+            Self::Registry => "registry",
+        }
+    }
+
+    fn storage(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Space => ("ChainSpace", "Spaces"),
+            Self::Schema => ("Schema", "Schemas"),
+            Self::Registry => ("Registries", "RegistryInfo"),
+        }
+    }
+}
+
+/// A definition as read from the latest finalized block.
+#[derive(Debug, Clone)]
+pub struct ChainDefinition {
+    pub block_hash: String,
+    pub block_number: u64,
+    /// The decoded storage value. Byte strings are UTF-8 strings when they can be, 0x-prefixed
+    /// hex otherwise.
+    pub definition: serde_json::Value,
+}
+
+// Reads the definition `id` from the latest finalized block. CORD identifiers are SS58 strings
+// stored as their bytes, which is also the storage key. Returns `None` if there is no such
+// definition, and an error if the runtime has no such pallet.
+pub async fn fetch_definition(
+    cord_client: Arc<OnlineClient<PolkadotConfig>>,
+    kind: ChainDefinitionKind,
+    id: &str,
+) -> Result<Option<ChainDefinition>, Box<dyn std::error::Error>> {
+    let client: &OnlineClient<PolkadotConfig> = &cord_client;
+
+    let block = client.blocks().at_latest().await?;
+    let (pallet, entry) = kind.storage();
+    let address = subxt::dynamic::storage(pallet, entry, vec![Value::from_bytes(id.as_bytes())]);
+
+    let Some(thunk) = block.storage().fetch(&address).await? else {
+        return Ok(None);
+    };
+
+    Ok(Some(ChainDefinition {
+        block_hash: format!("{:?}", block.hash()),
+        block_number: block.number().into(),
+        definition: value_to_json(&thunk.to_value()?),
+    }))
+}
+
+/// A delegation on a registry, as read from `Registries::Authorizations`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryAuthorization {
+    pub registry_id: String,
+    /// Account the authorization was granted to.
+    pub delegate: [u8; 32],
+}
+
+// Reads the registry authorization `id` from the latest finalized block. Returns `None` if there
+// is no such authorization, e.g. because it was removed.
+pub async fn fetch_registry_authorization(
+    cord_client: Arc<OnlineClient<PolkadotConfig>>,
+    id: &str,
+) -> Result<Option<RegistryAuthorization>, Box<dyn std::error::Error>> {
+    let client: &OnlineClient<PolkadotConfig> = &cord_client;
+
+    let block = client.blocks().at_latest().await?;
+    let address = subxt::dynamic::storage("Registries", "Authorizations", vec![Value::from_bytes(id.as_bytes())]);
+
+    let Some(thunk) = block.storage().fetch(&address).await? else {
+        return Ok(None);
+    };
+    let value = thunk.to_value()?;
+
+    let registry_id = value
+        .at("registry_id")
+        .and_then(try_extract_bytes)
+        .ok_or("Authorization has no registry_id")?;
+    let delegate = value
+        .at("delegate")
+        .and_then(try_extract_bytes)
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or("Authorization has no delegate account")?;
+
+    Ok(Some(RegistryAuthorization {
+        registry_id: String::from_utf8(registry_id.clone())
+            .unwrap_or_else(|_| format!("0x{}", hex::encode(registry_id))),
+        delegate,
+    }))
+}
+
+// Registers `schema` on chain under the space authorization `authorization` with
+// `Schema::create`, signed by the node's CORD key. Returns the new schema identifier.
+pub async fn create_schema(
+    chain: &ChainClient,
+    schema: &str,
+    authorization: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let payload = subxt::tx::dynamic(
+        "Schema",
+        "create",
+        vec![Value::from_bytes(schema.as_bytes()), Value::from_bytes(authorization.as_bytes())],
+    );
+
+    let events = chain
+        .submit(&payload)
+        .await
+        .map_err(|e| format!("Transaction was not finalized successfully: {e}"))?
+        .events;
+
+    for ev in events.iter() {
+        let ev = ev?;
+        if ev.pallet_name() == "Schema" && ev.variant_name() == "Created" {
+            let fields = ev.field_values()?;
+            if let Some(identifier) = fields.at("identifier").and_then(try_extract_bytes) {
+                return Ok(String::from_utf8(identifier.clone())
+                    .unwrap_or_else(|_| format!("0x{}", hex::encode(identifier))));
+            }
+        }
+    }
+
+    Err("Schema Created event not found".into())
+}
+
+// Converts a decoded storage value to JSON: named composites become objects, `None`/`Some` become
+// null or their value, other variants `{ "<Variant>": ... }` or just their name.
+pub(crate) fn value_to_json(value: &Value<u32>) -> serde_json::Value {
+    match &value.value {
+        ValueDef::Composite(composite) => {
+            let values: Vec<&Value<u32>> = composite.values().collect();
+            if let (Composite::Unnamed(_), Some(bytes)) = (composite, as_bytes(&values)) {
+                return bytes_to_json(bytes);
+            }
+            match composite {
+                Composite::Named(fields) if !fields.is_empty() => serde_json::Value::Object(
+                    fields.iter().map(|(name, value)| (name.clone(), value_to_json(value))).collect(),
+                ),
+                _ => match values.as_slice() {
+                    [single] => value_to_json(single),
+                    _ => serde_json::Value::Array(values.into_iter().map(value_to_json).collect()),
+                },
+            }
+        },
+        ValueDef::Variant(variant) => {
+            let values: Vec<&Value<u32>> = variant.values.values().collect();
+            match (variant.name.as_str(), values.as_slice()) {
+                ("None", []) => serde_json::Value::Null,
+                ("Some", [inner]) => value_to_json(inner),
+                (name, []) => serde_json::Value::String(name.to_string()),
+                (name, _) => json!({ name: value_to_json(&Value { value: ValueDef::Composite(variant.values.clone()), context: value.context }) }),
+            }
+        },
+        ValueDef::Primitive(primitive) => match primitive {
+            Primitive::Bool(b) => json!(b),
+            Primitive::Char(c) => json!(c.to_string()),
+            Primitive::String(s) => json!(s),
+            Primitive::U128(n) => u64::try_from(*n).map(|n| json!(n)).unwrap_or_else(|_| json!(n.to_string())),
+            Primitive::I128(n) => i64::try_from(*n).map(|n| json!(n)).unwrap_or_else(|_| json!(n.to_string())),
+            Primitive::U256(n) | Primitive::I256(n) => json!(format!("0x{}", hex::encode(n))),
+        },
+        ValueDef::BitSequence(bits) => serde_json::Value::Array(bits.iter().map(|bit| json!(bit)).collect()),
+    }
+}
+
+// A non-empty sequence of u8 values, such as a BoundedVec<u8> or an AccountId32
+fn as_bytes(values: &[&Value<u32>]) -> Option<Vec<u8>> {
+    if values.is_empty() {
+        return None;
+    }
+    values
+        .iter()
+        .map(|value| match &value.value {
+            ValueDef::Primitive(Primitive::U128(n)) => u8::try_from(*n).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+fn bytes_to_json(bytes: Vec<u8>) -> serde_json::Value {
+    match String::from_utf8(bytes) {
+        Ok(text) if !text.chars().any(char::is_control) => serde_json::Value::String(text),
+        Ok(text) => serde_json::Value::String(format!("0x{}", hex::encode(text.as_bytes()))),
+        Err(e) => serde_json::Value::String(format!("0x{}", hex::encode(e.as_bytes()))),
+    }
+}
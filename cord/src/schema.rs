@@ -0,0 +1,82 @@
+use serde_json::{Map, Value};
+
+/// `$schema` of schemas authored for CORD, e.g. with the CORD SDK or Cyra.
+pub const CORD_SCHEMA_DRAFT: &str = "http://cord.network/draft-01/schema#";
+
+// The CORD draft is JSON Schema draft-07 with a restricted set of keywords
+const JSON_SCHEMA_DRAFT: &str = "http://json-schema.org/draft-07/schema#";
+
+// Converts a CORD schema to a JSON Schema document schemas accept. Schema details, which wrap the
+// schema with its chain metadata, are unwrapped. The `$id` is dropped, since it names the schema on
+// chain and not the copy. References to other schemas cannot be resolved locally and are refused.
+pub fn cord_schema_to_json_schema(cord_schema: &Value) -> Result<Value, String> {
+    let mut schema = match cord_schema {
+        Value::Object(object) if !object.contains_key("properties") && object.get("schema").is_some_and(Value::is_object) => {
+            object["schema"].as_object().cloned().unwrap_or_default()
+        },
+        Value::Object(object) => object.clone(),
+        Value::String(text) => return cord_schema_to_json_schema(
+            &serde_json::from_str(text).map_err(|_| "The CORD schema is not valid JSON".to_string())?,
+        ),
+        _ => return Err("A CORD schema must be a JSON object".to_string()),
+    };
+
+    match schema.get("$schema").and_then(Value::as_str) {
+        None | Some(CORD_SCHEMA_DRAFT) => {
+            schema.insert("$schema".to_string(), Value::String(JSON_SCHEMA_DRAFT.to_string()));
+        },
+        Some(_) => {},
+    }
+    schema.remove("$id");
+    check_object_schema(&schema)?;
+    schema.entry("type").or_insert_with(|| Value::String("object".to_string()));
+    if let Some(reference) = external_ref(&Value::Object(schema.clone())) {
+        return Err(format!("The CORD schema references another schema ({}), which cannot be resolved", reference));
+    }
+
+    Ok(Value::Object(schema))
+}
+
+// Converts a document schema to a CORD schema titled `title`, unless it has a title already. CORD
+// schemas describe closed objects, so `additionalProperties` is `false` unless set. The chain
+// assigns the `$id`.
+pub fn json_schema_to_cord_schema(schema: &Value, title: &str) -> Result<Value, String> {
+    let mut schema: Map<String, Value> = schema
+        .as_object()
+        .cloned()
+        .ok_or_else(|| "The document schema is not a JSON object".to_string())?;
+    check_object_schema(&schema)?;
+
+    schema.insert("$schema".to_string(), Value::String(CORD_SCHEMA_DRAFT.to_string()));
+    schema.remove("$id");
+    schema.entry("type").or_insert_with(|| Value::String("object".to_string()));
+    schema.entry("title").or_insert_with(|| Value::String(title.to_string()));
+    schema.entry("additionalProperties").or_insert(Value::Bool(false));
+
+    Ok(Value::Object(schema))
+}
+
+// Both formats describe an object by its properties
+fn check_object_schema(schema: &Map<String, Value>) -> Result<(), String> {
+    match schema.get("type") {
+        None => {},
+        Some(Value::String(kind)) if kind == "object" => {},
+        Some(_) => return Err("The schema must describe an object (\"type\": \"object\")".to_string()),
+    }
+    if !schema.get("properties").is_some_and(Value::is_object) {
+        return Err("The schema must have \"properties\"".to_string());
+    }
+    Ok(())
+}
+
+// The first `$ref` that does not point into the schema itself
+fn external_ref(value: &Value) -> Option<String> {
+    match value {
+        Value::Object(object) => object.iter().find_map(|(key, value)| match (key.as_str(), value) {
+            ("$ref", Value::String(reference)) if !reference.starts_with('#') => Some(reference.clone()),
+            _ => external_ref(value),
+        }),
+        Value::Array(values) => values.iter().find_map(external_ref),
+        _ => None,
+    }
+}
@@ -0,0 +1,527 @@
+use crate::docs::get_document;
+use helpers::utils::{decode_doc_id, encode_doc_id, SS58AuthorId};
+use helpers::stores::{NodeBlobs, NodeDocs};
+
+use ciborium::Value;
+use data_encoding::BASE32_NOPAD;
+use futures::TryStreamExt;
+use iroh_blobs::Hash;
+use iroh_docs::store::Query;
+use iroh_docs::{AuthorId, NamespaceId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// `kind` of the root block of a document archive.
+pub const DOC_ARCHIVE_KIND: &str = "starter-kit/document";
+/// Version of the document archive layout, checked on import.
+pub const DOC_ARCHIVE_FORMAT: u64 = 1;
+
+// Multicodec codes of the CIDs in an archive
+const CID_V1: u64 = 0x01;
+const DAG_CBOR: u64 = 0x71;
+const RAW: u64 = 0x55;
+const BLAKE3: u64 = 0x1e;
+// CBOR tag of an IPLD link
+const CID_TAG: u64 = 42;
+// Sections larger than this are refused rather than read into memory
+const MAX_SECTION_LEN: u64 = 1 << 30;
+
+#[derive(Debug, PartialEq)]
+pub enum ArchiveError {
+    /// Failed to decode the document ID.
+    InvalidDocumentIdFormat,
+    /// Failed to decode the author ID.
+    InvalidAuthorIdFormat,
+    /// The specified document was not found.
+    DocumentNotFound,
+    /// The document to import into already has entries.
+    DocumentNotEmpty,
+    /// Failed to read the entries of the document.
+    FailedToReadDocument,
+    /// Failed to write the archive.
+    FailedToWriteArchive,
+    /// Failed to read the archive, or it is not a document archive.
+    InvalidArchive,
+    /// The archive was written by an unsupported version.
+    UnsupportedArchiveFormat,
+    /// A block does not match the hash in its CID.
+    BlockHashMismatch,
+    /// An entry of the root block is not in the archive.
+    MissingEntryBlock,
+    /// Failed to create the document to import into.
+    FailedToCreateDocument,
+    /// Failed to add a blob of the archive to the store.
+    FailedToAddBlob,
+    /// Failed to write an entry of the archive.
+    FailedToSetEntry,
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+/// What a document archive holds, as written by `export_doc_archive` or checked by
+/// `verify_doc_archive`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocArchiveSummary {
+    /// The document the archive was exported from.
+    pub doc_id: String,
+    /// CID of the root block, base32-encoded.
+    pub root: String,
+    pub entries: usize,
+    pub blobs: usize,
+    /// Entries whose content is neither in the archive nor, on import, in the store.
+    pub missing_content: usize,
+}
+
+/// The result of `import_doc_archive`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocArchiveImport {
+    /// The document the entries were written into.
+    pub doc_id: String,
+    #[serde(flatten)]
+    pub archive: DocArchiveSummary,
+    /// Entries written as their original author, whose key is on this node. The others are
+    /// written as the importing author.
+    pub original_authors: usize,
+}
+
+// An entry as stored in its block
+struct ArchivedEntry {
+    key: Vec<u8>,
+    len: u64,
+    author: String,
+    content: Hash,
+}
+
+// The blocks of an archive, read and checked against their CIDs
+struct ParsedArchive {
+    root: Vec<u8>,
+    doc_id: String,
+    entries: Vec<ArchivedEntry>,
+    blobs: BTreeSet<Hash>,
+}
+
+/// Writes a document's entries, and the blobs they point to, as a CARv1 archive to `writer`.
+///
+/// Every block is content-addressed with a BLAKE3 CID, so the archive can be checked without
+/// the node and moved out-of-band. The root is a DAG-CBOR block naming the document and
+/// linking one DAG-CBOR block per entry, each of which links its content as a raw block, whose
+/// CID holds the blob's hash. Content that is not stored locally is left out.
+pub async fn export_doc_archive(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> Result<DocArchiveSummary, ArchiveError> {
+    let namespace_id = decode_doc_id(&doc_id).map_err(|_| ArchiveError::InvalidDocumentIdFormat)?;
+    let doc = get_document(docs, NamespaceId::from(namespace_id))
+        .await
+        .map_err(|_| ArchiveError::DocumentNotFound)?;
+
+    let mut entries: Vec<iroh_docs::Entry> = doc
+        .get_many(Query::all())
+        .await
+        .map_err(|_| ArchiveError::FailedToReadDocument)?
+        .try_collect()
+        .await
+        .map_err(|_| ArchiveError::FailedToReadDocument)?;
+    entries.sort_by(|a, b| (a.key(), a.author()).cmp(&(b.key(), b.author())));
+
+    let mut entry_blocks = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        let author = SS58AuthorId::from_author_id(&entry.author()).map_err(|_| ArchiveError::InvalidAuthorIdFormat)?;
+        // keys in canonical DAG-CBOR order: by length, then bytewise
+        let block = encode_block(&Value::Map(vec![
+            (text("key"), Value::Bytes(entry.key().to_vec())),
+            (text("len"), Value::Integer(entry.content_len().into())),
+            (text("author"), text(author.as_ss58())),
+            (text("content"), link(&cid(RAW, entry.content_hash().as_bytes()))),
+            (text("timestamp"), Value::Integer(entry.timestamp().into())),
+        ]))?;
+        entry_blocks.push((block_cid(DAG_CBOR, &block), block));
+    }
+
+    let exported_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let root_block = encode_block(&Value::Map(vec![
+        (text("kind"), text(DOC_ARCHIVE_KIND)),
+        (text("doc_id"), text(&doc_id)),
+        (text("format"), Value::Integer(DOC_ARCHIVE_FORMAT.into())),
+        (text("entries"), Value::Array(entry_blocks.iter().map(|(cid, _)| link(cid)).collect())),
+        (text("exported_at"), Value::Integer(exported_at.into())),
+    ]))?;
+    let root = block_cid(DAG_CBOR, &root_block);
+
+    let header = encode_block(&Value::Map(vec![
+        (text("roots"), Value::Array(vec![link(&root)])),
+        (text("version"), Value::Integer(1.into())),
+    ]))?;
+    write_section(writer, &[&header]).await?;
+    write_section(writer, &[&root, &root_block]).await?;
+    for (cid, block) in &entry_blocks {
+        write_section(writer, &[cid, block]).await?;
+    }
+
+    let hashes: BTreeSet<Hash> = entries.iter().map(|entry| entry.content_hash()).collect();
+    let mut written = BTreeSet::new();
+    for hash in &hashes {
+        // a partial replica may not have the content of every entry
+        let Ok(content) = blobs.client().read_to_bytes(*hash).await else {
+            continue;
+        };
+        write_section(writer, &[&cid(RAW, hash.as_bytes()), &content]).await?;
+        written.insert(*hash);
+    }
+    writer.flush().await.map_err(|_| ArchiveError::FailedToWriteArchive)?;
+
+    Ok(DocArchiveSummary {
+        doc_id,
+        root: cid_string(&root),
+        entries: entries.len(),
+        blobs: written.len(),
+        missing_content: entries.iter().filter(|entry| !written.contains(&entry.content_hash())).count(),
+    })
+}
+
+/// Reads a document archive and checks every block against its CID, without a node.
+pub async fn verify_doc_archive(reader: &mut (impl AsyncRead + Unpin)) -> Result<DocArchiveSummary, ArchiveError> {
+    let archive = read_archive(reader, None).await?;
+    Ok(summary(&archive, |hash| archive.blobs.contains(hash)))
+}
+
+/// Writes the entries of a document archive into `doc_id`, or a new document, after checking
+/// every block against its CID. The document to import into must be empty.
+///
+/// Entries keep their key and content, but get a new timestamp. They are written as their
+/// original author if its key is on this node, and as `author_id` otherwise.
+pub async fn import_doc_archive(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    reader: &mut (impl AsyncRead + Unpin),
+    doc_id: Option<String>,
+    author_id: String,
+) -> Result<DocArchiveImport, ArchiveError> {
+    let fallback_author = SS58AuthorId::decode(&author_id).map_err(|_| ArchiveError::InvalidAuthorIdFormat)?;
+
+    let doc = match &doc_id {
+        Some(doc_id) => {
+            let namespace_id = decode_doc_id(doc_id).map_err(|_| ArchiveError::InvalidDocumentIdFormat)?;
+            let doc = get_document(docs.clone(), NamespaceId::from(namespace_id))
+                .await
+                .map_err(|_| ArchiveError::DocumentNotFound)?;
+            let existing = doc
+                .get_many(Query::all())
+                .await
+                .map_err(|_| ArchiveError::FailedToReadDocument)?
+                .try_next()
+                .await
+                .map_err(|_| ArchiveError::FailedToReadDocument)?;
+            if existing.is_some() {
+                return Err(ArchiveError::DocumentNotEmpty);
+            }
+            doc
+        }
+        None => docs.client().create().await.map_err(|_| ArchiveError::FailedToCreateDocument)?,
+    };
+
+    // Blobs are added as they are read; they are content-addressed, so an archive that turns out
+    // to be invalid leaves nothing wrong behind
+    let archive = read_archive(reader, Some(&blobs)).await?;
+
+    let mut local_authors = HashMap::new();
+    let mut original_authors = 0;
+    let mut has_content = HashMap::new();
+    for entry in &archive.entries {
+        let author = SS58AuthorId::decode(&entry.author).map_err(|_| ArchiveError::InvalidArchive)?;
+        let is_local = match local_authors.get(&author) {
+            Some(is_local) => *is_local,
+            None => {
+                let is_local = docs.client().authors().export(author).await.is_ok_and(|author| author.is_some());
+                local_authors.insert(author, is_local);
+                is_local
+            }
+        };
+        let writer: AuthorId = if is_local { author } else { fallback_author };
+        original_authors += is_local as usize;
+
+        if !archive.blobs.contains(&entry.content) && !has_content.contains_key(&entry.content) {
+            let stored = blobs.client().has(entry.content).await.unwrap_or(false);
+            has_content.insert(entry.content, stored);
+        }
+        doc.set_hash(writer, entry.key.clone(), entry.content, entry.len)
+            .await
+            .map_err(|_| ArchiveError::FailedToSetEntry)?;
+    }
+
+    Ok(DocArchiveImport {
+        doc_id: encode_doc_id(doc.id().as_bytes()),
+        archive: summary(&archive, |hash| archive.blobs.contains(hash) || has_content.get(hash).copied().unwrap_or(false)),
+        original_authors,
+    })
+}
+
+fn summary(archive: &ParsedArchive, has_content: impl Fn(&Hash) -> bool) -> DocArchiveSummary {
+    DocArchiveSummary {
+        doc_id: archive.doc_id.clone(),
+        root: cid_string(&archive.root),
+        entries: archive.entries.len(),
+        blobs: archive.blobs.len(),
+        missing_content: archive.entries.iter().filter(|entry| !has_content(&entry.content)).count(),
+    }
+}
+
+// Reads every section, checks each block against its CID, and adds raw blocks to `blobs`
+async fn read_archive(
+    reader: &mut (impl AsyncRead + Unpin),
+    blobs: Option<&Arc<NodeBlobs>>,
+) -> Result<ParsedArchive, ArchiveError> {
+    let header = read_section(reader).await?.ok_or(ArchiveError::InvalidArchive)?;
+    let header = decode_block(&header)?;
+    if map_get(&header, "version").and_then(as_u64) != Some(1) {
+        return Err(ArchiveError::UnsupportedArchiveFormat);
+    }
+    let root = match map_get(&header, "roots") {
+        Some(Value::Array(roots)) if roots.len() == 1 => as_link(&roots[0])?,
+        _ => return Err(ArchiveError::InvalidArchive),
+    };
+
+    let mut dag_blocks = HashMap::new();
+    let mut stored = BTreeSet::new();
+    while let Some(section) = read_section(reader).await? {
+        let (codec, digest, cid_len) = parse_cid(&section)?;
+        let block = &section[cid_len..];
+        if blake3::hash(block).as_bytes() != &digest {
+            return Err(ArchiveError::BlockHashMismatch);
+        }
+        match codec {
+            DAG_CBOR => {
+                dag_blocks.insert(section[..cid_len].to_vec(), block.to_vec());
+            }
+            RAW => {
+                if let Some(blobs) = blobs {
+                    blobs.client().add_bytes(block.to_vec()).await.map_err(|_| ArchiveError::FailedToAddBlob)?;
+                }
+                stored.insert(Hash::from_bytes(digest));
+            }
+            _ => return Err(ArchiveError::InvalidArchive),
+        }
+    }
+
+    let root_block = decode_block(dag_blocks.get(&root).ok_or(ArchiveError::InvalidArchive)?)?;
+    if map_get(&root_block, "kind").and_then(Value::as_text) != Some(DOC_ARCHIVE_KIND) {
+        return Err(ArchiveError::InvalidArchive);
+    }
+    if map_get(&root_block, "format").and_then(as_u64) != Some(DOC_ARCHIVE_FORMAT) {
+        return Err(ArchiveError::UnsupportedArchiveFormat);
+    }
+    let doc_id = map_get(&root_block, "doc_id").and_then(Value::as_text).ok_or(ArchiveError::InvalidArchive)?.to_string();
+    let links = map_get(&root_block, "entries").and_then(Value::as_array).ok_or(ArchiveError::InvalidArchive)?;
+
+    let mut entries = Vec::with_capacity(links.len());
+    for link in links {
+        let block = dag_blocks.get(&as_link(link)?).ok_or(ArchiveError::MissingEntryBlock)?;
+        let block = decode_block(block)?;
+        let content = map_get(&block, "content").ok_or(ArchiveError::InvalidArchive).and_then(as_link)?;
+        let (codec, digest, _) = parse_cid(&content)?;
+        if codec != RAW {
+            return Err(ArchiveError::InvalidArchive);
+        }
+        entries.push(ArchivedEntry {
+            key: map_get(&block, "key").and_then(Value::as_bytes).ok_or(ArchiveError::InvalidArchive)?.clone(),
+            len: map_get(&block, "len").and_then(as_u64).ok_or(ArchiveError::InvalidArchive)?,
+            author: map_get(&block, "author").and_then(Value::as_text).ok_or(ArchiveError::InvalidArchive)?.to_string(),
+            content: Hash::from_bytes(digest),
+        });
+    }
+
+    Ok(ParsedArchive { root, doc_id, entries, blobs: stored })
+}
+
+// Writes one CAR section: its length as a varint, then `parts`
+async fn write_section(writer: &mut (impl AsyncWrite + Unpin), parts: &[&[u8]]) -> Result<(), ArchiveError> {
+    let len: usize = parts.iter().map(|part| part.len()).sum();
+    let mut prefix = Vec::new();
+    write_varint(&mut prefix, len as u64);
+    writer.write_all(&prefix).await.map_err(|_| ArchiveError::FailedToWriteArchive)?;
+    for part in parts {
+        writer.write_all(part).await.map_err(|_| ArchiveError::FailedToWriteArchive)?;
+    }
+    Ok(())
+}
+
+// Reads one CAR section, `None` at the end of the archive
+async fn read_section(reader: &mut (impl AsyncRead + Unpin)) -> Result<Option<Vec<u8>>, ArchiveError> {
+    let mut len = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = match reader.read_u8().await {
+            Ok(byte) => byte,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof && shift == 0 => return Ok(None),
+            Err(_) => return Err(ArchiveError::InvalidArchive),
+        };
+        len |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            if len == 0 || len > MAX_SECTION_LEN {
+                return Err(ArchiveError::InvalidArchive);
+            }
+            let mut section = vec![0; len as usize];
+            reader.read_exact(&mut section).await.map_err(|_| ArchiveError::InvalidArchive)?;
+            return Ok(Some(section));
+        }
+    }
+    Err(ArchiveError::InvalidArchive)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], offset: &mut usize) -> Result<u64, ArchiveError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*offset).ok_or(ArchiveError::InvalidArchive)?;
+        *offset += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(ArchiveError::InvalidArchive)
+}
+
+// A CIDv1 with a BLAKE3 multihash
+fn cid(codec: u64, digest: &[u8; 32]) -> Vec<u8> {
+    let mut cid = Vec::with_capacity(36);
+    for code in [CID_V1, codec, BLAKE3, 32] {
+        write_varint(&mut cid, code);
+    }
+    cid.extend_from_slice(digest);
+    cid
+}
+
+fn block_cid(codec: u64, block: &[u8]) -> Vec<u8> {
+    cid(codec, blake3::hash(block).as_bytes())
+}
+
+// Returns the codec, the digest and the length of the CID at the start of `bytes`
+fn parse_cid(bytes: &[u8]) -> Result<(u64, [u8; 32], usize), ArchiveError> {
+    let mut offset = 0;
+    let version = read_varint(bytes, &mut offset)?;
+    let codec = read_varint(bytes, &mut offset)?;
+    let hash = read_varint(bytes, &mut offset)?;
+    let len = read_varint(bytes, &mut offset)?;
+    if version != CID_V1 || hash != BLAKE3 || len != 32 {
+        return Err(ArchiveError::InvalidArchive);
+    }
+    let digest = bytes.get(offset..offset + 32).ok_or(ArchiveError::InvalidArchive)?;
+    Ok((codec, digest.try_into().map_err(|_| ArchiveError::InvalidArchive)?, offset + 32))
+}
+
+// The multibase base32 form CIDs are usually shown in
+fn cid_string(cid: &[u8]) -> String {
+    format!("b{}", BASE32_NOPAD.encode(cid).to_ascii_lowercase())
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+// A DAG-CBOR link: the CID behind a multibase identity prefix, under tag 42
+fn link(cid: &[u8]) -> Value {
+    let mut bytes = Vec::with_capacity(cid.len() + 1);
+    bytes.push(0);
+    bytes.extend_from_slice(cid);
+    Value::Tag(CID_TAG, Box::new(Value::Bytes(bytes)))
+}
+
+fn as_link(value: &Value) -> Result<Vec<u8>, ArchiveError> {
+    match value {
+        Value::Tag(CID_TAG, inner) => match inner.as_ref() {
+            Value::Bytes(bytes) if bytes.first() == Some(&0) => Ok(bytes[1..].to_vec()),
+            _ => Err(ArchiveError::InvalidArchive),
+        },
+        _ => Err(ArchiveError::InvalidArchive),
+    }
+}
+
+fn as_u64(value: &Value) -> Option<u64> {
+    value.as_integer().and_then(|integer| u64::try_from(integer).ok())
+}
+
+fn map_get<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    value
+        .as_map()?
+        .iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn encode_block(value: &Value) -> Result<Vec<u8>, ArchiveError> {
+    let mut block = Vec::new();
+    ciborium::into_writer(value, &mut block).map_err(|_| ArchiveError::FailedToWriteArchive)?;
+    Ok(block)
+}
+
+fn decode_block(block: &[u8]) -> Result<Value, ArchiveError> {
+    ciborium::from_reader(block).map_err(|_| ArchiveError::InvalidArchive)
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    pub async fn test_archive_blocks_are_verified() -> anyhow::Result<()> {
+        let content = b"entry value".to_vec();
+        let content_hash = blake3::hash(&content);
+        let entry = super::encode_block(&ciborium::Value::Map(vec![
+            (super::text("key"), ciborium::Value::Bytes(b"greeting\0".to_vec())),
+            (super::text("len"), ciborium::Value::Integer((content.len() as u64).into())),
+            (super::text("author"), super::text("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY")),
+            (super::text("content"), super::link(&super::cid(super::RAW, content_hash.as_bytes()))),
+            (super::text("timestamp"), ciborium::Value::Integer(1u64.into())),
+        ]))?;
+        let entry_cid = super::block_cid(super::DAG_CBOR, &entry);
+        let root = super::encode_block(&ciborium::Value::Map(vec![
+            (super::text("kind"), super::text(super::DOC_ARCHIVE_KIND)),
+            (super::text("doc_id"), super::text("d00")),
+            (super::text("format"), ciborium::Value::Integer(super::DOC_ARCHIVE_FORMAT.into())),
+            (super::text("entries"), ciborium::Value::Array(vec![super::link(&entry_cid)])),
+            (super::text("exported_at"), ciborium::Value::Integer(1u64.into())),
+        ]))?;
+        let root_cid = super::block_cid(super::DAG_CBOR, &root);
+        let header = super::encode_block(&ciborium::Value::Map(vec![
+            (super::text("roots"), ciborium::Value::Array(vec![super::link(&root_cid)])),
+            (super::text("version"), ciborium::Value::Integer(1.into())),
+        ]))?;
+
+        let mut archive = Vec::new();
+        super::write_section(&mut archive, &[&header]).await?;
+        super::write_section(&mut archive, &[&root_cid, &root]).await?;
+        super::write_section(&mut archive, &[&entry_cid, &entry]).await?;
+        let mut without_content = archive.clone();
+        super::write_section(&mut archive, &[&super::cid(super::RAW, content_hash.as_bytes()), &content]).await?;
+
+        let summary = super::verify_doc_archive(&mut archive.as_slice()).await?;
+        assert_eq!((summary.doc_id.as_str(), summary.entries, summary.blobs, summary.missing_content), ("d00", 1, 1, 0));
+        assert!(summary.root.starts_with("b"));
+
+        // content can be left out, but not altered
+        assert_eq!(super::verify_doc_archive(&mut without_content.as_slice()).await?.missing_content, 1);
+        super::write_section(&mut without_content, &[&super::cid(super::RAW, content_hash.as_bytes()), b"another value"]).await?;
+        assert_eq!(
+            super::verify_doc_archive(&mut without_content.as_slice()).await.unwrap_err(),
+            super::ArchiveError::BlockHashMismatch
+        );
+        Ok(())
+    }
+}
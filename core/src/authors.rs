@@ -1,18 +1,19 @@
 use helpers::utils::SS58AuthorId;
-use keystore::keystore::CordKeystoreSigner;
+use helpers::stores::NodeDocs;
+use helpers::errors::{fmt_error, Cause};
+use keystore::keystore::StarterkitKeystore;
+use keystore::registry::KeyTypeInfo;
+use cord::client::ChainClient;
 use cord::profile::create_profile;
 
 use anyhow::{Result, Context};
 use std::{collections::HashSet, sync::Arc, fmt};
-use iroh_docs::{protocol::Docs, AuthorId};
-use iroh_blobs::store::fs::Store;
-use futures::TryStreamExt;
+use iroh_docs::{Author, AuthorId};
+use futures::{Stream, StreamExt, TryStreamExt};
 use subxt_rpcs::RpcClient;
-use subxt::config::PolkadotConfig;
-use subxt::client::OnlineClient;
 
 // Errors
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
 pub enum AuthorError {
     /// The specified author was not found in the system.
     AuthorNotFound,
@@ -21,27 +22,30 @@ pub enum AuthorError {
     /// The provided author ID format is invalid or cannot be decoded.
     InvalidAuthorIdFormat,
     /// Failed to retrieve the list of authors from the backend.
-    FailedToListAuthors,
+    FailedToListAuthors(#[source] Cause),
     /// Failed to create a new author.
-    FailedToCreateAuthor,
+    FailedToCreateAuthor(#[source] Cause),
     /// Failed to delete the specified author.
-    FailedToDeleteAuthor,
+    FailedToDeleteAuthor(#[source] Cause),
     /// Failed to set the specified author as the default.
-    FailedToSetDefaultAuthor,
+    FailedToSetDefaultAuthor(#[source] Cause),
     /// An error occurred while streaming the list of authors.
-    StreamingError,
+    StreamingError(#[source] Cause),
     /// Failed to collect the authors from the stream.
-    FailedToCollectAuthors,
+    FailedToCollectAuthors(#[source] Cause),
+    /// Failed to export the secret key of the specified author.
+    FailedToExportAuthor(#[source] Cause),
+    /// The keystore key cannot back an author: it is missing, not allowed to sign documents, or
+    /// its signatures are not deterministic.
+    FailedToDeriveAuthor(#[source] Cause),
 }
 
 impl fmt::Display for AuthorError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        fmt_error(self, f)
     }
 }
 
-impl std::error::Error for AuthorError {}
-
 /// Lists all authors registered in the current context.
 ///
 /// # Arguments
@@ -50,21 +54,21 @@ impl std::error::Error for AuthorError {}
 /// # Returns
 /// * `Vec<String>` - A list of SS58-encoded author IDs.
 pub async fn list_authors(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
 ) -> Result<Vec<String>, AuthorError> {
     let authors_client = docs.client().authors();
 
     let mut author_stream = authors_client
         .list()
         .await
-        .map_err(|_| AuthorError::FailedToListAuthors)?;
+        .map_err(|e| AuthorError::FailedToListAuthors(Cause::new(e)))?;
 
     let mut authors = Vec::new();
 
     while let Some(author) = author_stream
         .try_next()
         .await
-        .map_err(|_| AuthorError::StreamingError)?
+        .map_err(|e| AuthorError::StreamingError(Cause::new(e)))?
     {
         let encode_author = SS58AuthorId::from_author_id(&author)
             .map_err(|_| AuthorError::InvalidAuthorIdFormat)?;
@@ -74,6 +78,31 @@ pub async fn list_authors(
     Ok(authors)
 }
 
+/// Streams all authors as they are read, for listings too large to collect at once.
+///
+/// # Arguments
+/// * `docs` - The Arc-wrapped Docs client.
+///
+/// # Returns
+/// * `Stream` - SS58-encoded author IDs, each item failing if the authors could not be read.
+pub async fn stream_authors(
+    docs: Arc<NodeDocs>,
+) -> Result<impl Stream<Item = Result<String, AuthorError>> + Send + 'static, AuthorError> {
+    let author_stream = docs
+        .client()
+        .authors()
+        .list()
+        .await
+        .map_err(|e| AuthorError::FailedToListAuthors(Cause::new(e)))?;
+
+    Ok(author_stream.map(|author| {
+        let author = author.map_err(|e| AuthorError::StreamingError(Cause::new(e)))?;
+        let encode_author = SS58AuthorId::from_author_id(&author)
+            .map_err(|_| AuthorError::InvalidAuthorIdFormat)?;
+        Ok(encode_author.as_ss58().to_string())
+    }))
+}
+
 /// Retrieves the default author for the current Docs client.
 ///
 /// # Arguments
@@ -82,7 +111,7 @@ pub async fn list_authors(
 /// # Returns
 /// * `String` - The SS58-encoded ID of the default author.
 pub async fn get_default_author(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
 ) -> Result<String, AuthorError> {
     let authors_client = docs.client().authors();
 
@@ -106,7 +135,7 @@ pub async fn get_default_author(
 /// # Returns
 /// * `()` - Returns unit on success.
 pub async fn set_default_author(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
     author_id: String
 ) -> Result<(), AuthorError> {
     let authors_client = docs.client().authors();
@@ -117,7 +146,7 @@ pub async fn set_default_author(
     authors_client
         .set_default(author)
         .await
-        .map_err(|_| AuthorError::FailedToSetDefaultAuthor)?;
+        .map_err(|e| AuthorError::FailedToSetDefaultAuthor(Cause::new(e)))?;
 
     Ok(())
 }
@@ -130,29 +159,40 @@ pub async fn set_default_author(
 /// # Returns
 /// * `String` - The SS58-encoded ID of the newly created author.
 pub async fn create_author(
-    docs: Arc<Docs<Store>>,
-    // cord_client: Arc<RpcClient>,
-    cord_client: Arc<OnlineClient<PolkadotConfig>>,
-    cord_signer: CordKeystoreSigner,
+    docs: Arc<NodeDocs>,
+    chain: ChainClient,
 ) -> Result<String, AuthorError> {
     // add a cord call to create a profile
-    match create_profile(cord_client, cord_signer).await {
+    match create_profile(&chain).await {
         Ok((who, identifier)) => {
-            println!("Created profile: {}, {}", who, identifier);
+            tracing::info!(%who, %identifier, "created CORD profile");
         }
         Err(e) => {
-            println!("Failed to create profile: {e}");
+            tracing::warn!(error = %e, "failed to create CORD profile");
             // You can choose to return an error or continue, e.g.:
             // return Err(AuthorError::FailedToCreateAuthor);
         }
     }
 
+    create_local_author(docs).await
+}
+
+/// Creates a new author in the local store only, without registering a CORD profile.
+///
+/// # Arguments
+/// * `docs` - The Arc-wrapped Docs client.
+///
+/// # Returns
+/// * `String` - The SS58-encoded ID of the newly created author.
+pub async fn create_local_author(
+    docs: Arc<NodeDocs>,
+) -> Result<String, AuthorError> {
     let authors_client = docs.client().authors();
 
     let author_id = authors_client
         .create()
         .await
-        .map_err(|_| AuthorError::FailedToCreateAuthor)?;
+        .map_err(|e| AuthorError::FailedToCreateAuthor(Cause::new(e)))?;
 
     let encode_author = SS58AuthorId::from_author_id(&author_id)
         .map_err(|_| AuthorError::InvalidAuthorIdFormat)?;
@@ -160,6 +200,71 @@ pub async fn create_author(
     Ok(encode_author.as_ss58().to_string())
 }
 
+/// Creates an author whose secret key is derived from a key in the node's keystore.
+///
+/// The same keystore key always gives the same author, so the author can be recreated from the
+/// keystore (and the keystore from its SURI) even if the docs store is lost. Creating it again is
+/// a no-op.
+///
+/// # Arguments
+/// * `docs` - The Arc-wrapped Docs client.
+/// * `keystore` - The node's keystore.
+/// * `key_type` - The key type of the key, which must be ed25519 or ecdsa and allowed to sign documents.
+/// * `public` - The public key of the keystore key.
+///
+/// # Returns
+/// * `String` - The SS58-encoded ID of the author.
+pub async fn create_author_from_keystore(
+    docs: Arc<NodeDocs>,
+    keystore: &StarterkitKeystore,
+    key_type: &KeyTypeInfo,
+    public: &[u8],
+) -> Result<String, AuthorError> {
+    let seed = keystore.derive_author_seed(key_type, public).map_err(|e| {
+        tracing::warn!(error = %e, key_type = %key_type.name, "failed to derive author from keystore key");
+        AuthorError::FailedToDeriveAuthor(Cause::new(e))
+    })?;
+    let author = Author::from_bytes(&seed);
+    let author_id = author.id();
+
+    docs.client()
+        .authors()
+        .import(author)
+        .await
+        .map_err(|e| AuthorError::FailedToCreateAuthor(Cause::new(e)))?;
+
+    let encode_author = SS58AuthorId::from_author_id(&author_id)
+        .map_err(|_| AuthorError::InvalidAuthorIdFormat)?;
+
+    Ok(encode_author.as_ss58().to_string())
+}
+
+/// Exports the secret key of an author, so it can be backed up or imported on another node.
+///
+/// # Arguments
+/// * `docs` - The Arc-wrapped Docs client.
+/// * `author_id` - The SS58-encoded ID of the author to export.
+///
+/// # Returns
+/// * `String` - The hex-encoded secret key of the author. Anyone holding it can write as this author.
+pub async fn export_author(
+    docs: Arc<NodeDocs>,
+    author_id: String
+) -> Result<String, AuthorError> {
+    let authors_client = docs.client().authors();
+
+    let author = SS58AuthorId::decode(&author_id)
+        .map_err(|_| AuthorError::InvalidAuthorIdFormat)?;
+
+    let secret = authors_client
+        .export(author)
+        .await
+        .map_err(|e| AuthorError::FailedToExportAuthor(Cause::new(e)))?
+        .ok_or(AuthorError::AuthorNotFound)?;
+
+    Ok(secret.to_string())
+}
+
 /// Deletes an author based on its ID.
 ///
 /// # Arguments
@@ -169,7 +274,7 @@ pub async fn create_author(
 /// # Returns
 /// * `()` - Returns unit on successful deletion.
 pub async fn delete_author(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
     author_id: String
 ) -> Result<(), AuthorError> {
     let authors_client = docs.client().authors();
@@ -182,10 +287,10 @@ pub async fn delete_author(
         authors_client
             .list()
             .await
-            .map_err(|_| AuthorError::FailedToListAuthors)?
+            .map_err(|e| AuthorError::FailedToListAuthors(Cause::new(e)))?
             .try_collect::<HashSet<_>>()
             .await
-            .map_err(|_| AuthorError::FailedToCollectAuthors)?;
+            .map_err(|e| AuthorError::FailedToCollectAuthors(Cause::new(e)))?;
 
     if !authors_set.contains(&author) {
         return Err(AuthorError::AuthorNotFound);
@@ -194,7 +299,7 @@ pub async fn delete_author(
     authors_client
         .delete(author)
         .await
-        .map_err(|_| AuthorError::FailedToDeleteAuthor)?;
+        .map_err(|e| AuthorError::FailedToDeleteAuthor(Cause::new(e)))?;
 
     Ok(())
 }
@@ -208,7 +313,7 @@ pub async fn delete_author(
 /// # Returns
 /// * `bool` - True if the author exists, false otherwise.
 pub async fn verify_author(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
     author_id: String
 ) -> Result<bool, AuthorError> {
     let authors_client = docs.client().authors();
@@ -220,14 +325,15 @@ pub async fn verify_author(
         authors_client
             .list()
             .await
-            .map_err(|_| AuthorError::FailedToListAuthors)?
+            .map_err(|e| AuthorError::FailedToListAuthors(Cause::new(e)))?
             .try_collect::<HashSet<_>>()
             .await
-            .map_err(|_| AuthorError::FailedToCollectAuthors)?;
+            .map_err(|e| AuthorError::FailedToCollectAuthors(Cause::new(e)))?;
 
     Ok(authors_set.contains(&author))
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use node::iroh_wrapper::{
@@ -239,7 +345,6 @@ mod tests {
     use std::default;
     use std::path::PathBuf;
     use tokio::fs;
-    use tokio::time::{sleep, Duration};
     use tokio::process::Command;
     use std::process::Stdio;
 
@@ -258,8 +363,6 @@ mod tests {
             fs::remove_dir_all("Test").await?;
         }
 
-        sleep(Duration::from_secs(2)).await;
-
         fs::create_dir_all("Test").await?;
 
         let args = CliArgs {
@@ -268,16 +371,80 @@ mod tests {
             bootstrap: true,
             suri: Some("0xe5be9a5092b81bca64be81d212e7f2f9eba183bb7a90954f7b76361f6edb5c0a".to_string()), // don't use this suri in production, it is a preloaded suri for testing(for //Alice)
             secret: Some("test-secret".to_string()), // remove this secret key
+            pkcs11_module: None,
+            pkcs11_slot: None,
+            pkcs11_pin: None,
+            approval_admin: vec![],
+            approval_threshold: None,
+            grpc_listen: None,
+            compression_min_size: 1024,
+            listen: vec![],
+            listen_uds: vec![],
+            admin_listen: None,
+            s3_listen: None,
+            webdav_listen: None,
+            read_timeout: 10,
+            write_timeout: 30,
+            download_timeout: 600,
+            download_connections: 4,
+            max_concurrent_imports: 4,
+            max_concurrent_exports: 16,
+            max_concurrent_downloads: 8,
+            max_concurrent_scans: 32,
+            relay: vec![],
+            chain_event: vec![],
+            proof_batch_interval: 600,
+            require_chain_authorization: false,
+            authorization_cache_ttl: 300,
+            chain_backend: helpers::cli::ChainBackend::Cord,
+            read_only: false,
+            public_gw: false,
+            require_api_key: false,
+            access_log: false,
+            access_log_doc: Vec::new(),
+            access_log_sample_rate: 1.0,
+            debug_errors: false,
+            doc_cache_size: 256,
+            doc_cache_idle: 300,
+            blob_cache_size: 64 * 1024 * 1024,
+            blob_cache_max_blob: 1024 * 1024,
+            index_field: vec![],
+            search: false,
+            schema_registry_doc: None,
+            schema_ref_url: vec![],
+            trash_retention: 2_592_000,
+            version_retention: 7_776_000,
+            smtp_relay: None,
+            smtp_from: "starter-kit@localhost".to_string(),
+            job: Vec::new(),
+            public_listen: None,
+            public_doc: Vec::new(),
+            public_blob: Vec::new(),
+            public_rate_limit: 120,
+            backup_dir: None,
+            backup_interval: None,
+            backup_keep: 7,
+            backup_full_every: 7,
+            backup_s3_url: None,
+            backup_s3_region: "us-east-1".to_string(),
+            backup_s3_access_key: None,
+            backup_s3_secret_key: None,
+            federation_catalog: None,
+            federation_trusted_author: vec![],
+            federation_interval: 60,
+            config: None,
+            profile: None,
         };
         let iroh_node: IrohNode = setup_iroh_node(args).await.or_else(|e| {
             Err(anyhow!("Failed to set up Iroh node. Error: {}", e))
         })?;
+        iroh_node.wait_ready().await?;
         println!("Iroh node started!");
         println!("Your NodeId: {}", iroh_node.node_id);
         Ok(iroh_node)
     }
 
-    pub async fn delete_all_authors(docs: Arc<Docs<Store>>) -> Result<()> {
+    pub async fn delete_all_authors(docs: Arc<NodeDocs>) -> Result<()> {
         let authors = list_authors(docs.clone()).await?;
         let default_author = get_default_author(docs.clone()).await?;
 
@@ -297,7 +464,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author_id = create_author(docs.clone()).await?;
+        let author_id = create_local_author(docs.clone()).await?;
         
         let authors = list_authors(docs.clone()).await?;
         assert!(authors.contains(&author_id));
@@ -311,15 +478,48 @@ mod tests {
         Ok(())
     }
 
+    // create_author_from_keystore
+    #[tokio::test]
+    pub async fn test_create_author_from_keystore() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+
+        let keystore = StarterkitKeystore::from_inner(iroh_node.cord_signer.keystore.clone());
+        let skit = keystore::registry::builtin_key_type(keystore::keystore::STARTERKIT_KEY_TYPE).unwrap();
+        let public = keystore.get_public_key(&skit)?;
+
+        // the same key always gives the same author
+        let author_id = create_author_from_keystore(docs.clone(), &keystore, &skit, &public).await?;
+        let again = create_author_from_keystore(docs.clone(), &keystore, &skit, &public).await?;
+        assert_eq!(author_id, again);
+
+        let authors = list_authors(docs.clone()).await?;
+        assert!(authors.contains(&author_id));
+
+        // sr25519 signatures are randomized, so CORD keys cannot back an author
+        let cord = keystore::registry::builtin_key_type(keystore::keystore::CORD_KEY_TYPE).unwrap();
+        let cord_public = keystore.get_public_key(&cord)?;
+        let result = create_author_from_keystore(docs.clone(), &keystore, &cord, &cord_public).await;
+        assert!(matches!(result, Err(AuthorError::FailedToDeriveAuthor(_))));
+
+        delete_all_authors(docs).await?;
+
+        fs::remove_dir_all("Test/test_blobs").await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
+        Ok(())
+    }
+
     // list_authors
     #[tokio::test]
     pub async fn test_list_authors() -> Result<()> {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author_1 = create_author(docs.clone()).await?;
-        let author_2 = create_author(docs.clone()).await?;
-        let author_3 = create_author(docs.clone()).await?;
+        let author_1 = create_local_author(docs.clone()).await?;
+        let author_2 = create_local_author(docs.clone()).await?;
+        let author_3 = create_local_author(docs.clone()).await?;
         
         let authors = list_authors(docs.clone()).await?;
         assert_eq!(authors.len(), 4); // 3 authors + default author
@@ -348,7 +548,7 @@ mod tests {
         let result = list_authors(docs.clone()).await;
 
         assert!(
-            matches!(result, Err(AuthorError::StreamingError)),
+            matches!(result, Err(AuthorError::StreamingError(_))),
             "Expected streaming error, got: {:?}",
             result
         );
@@ -386,22 +586,17 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author_1 = create_author(docs.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
+        let author_1 = create_local_author(docs.clone()).await?;
         
         let authors = list_authors(docs.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
         assert_eq!(authors.len(), 2); // 1 author + default author
         assert!(authors.contains(&author_1));
 
         let default_author = get_default_author(docs.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
 
         set_default_author(docs.clone(), author_1.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
 
         let new_default_author = get_default_author(docs.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
         assert_eq!(new_default_author, author_1);
         assert_ne!(default_author, new_default_author);
 
@@ -441,19 +636,15 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author_id = create_author(docs.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
+        let author_id = create_local_author(docs.clone()).await?;
 
         let authors = list_authors(docs.clone()).await?;
         assert!(authors.contains(&author_id));
-        sleep(Duration::from_secs(1)).await;
 
         delete_author(docs.clone(), author_id.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
 
         let authors = list_authors(docs.clone()).await?;
         assert!(!authors.contains(&author_id));
-        sleep(Duration::from_secs(1)).await;
 
         fs::remove_dir_all("Test/test_blobs").await?;
         fs::remove_dir_all("Test").await?;
@@ -468,19 +659,15 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author_id = create_author(docs.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
+        let author_id = create_local_author(docs.clone()).await?;
 
         let verified = verify_author(docs.clone(), author_id.clone()).await?;
         assert!(verified);
-        sleep(Duration::from_secs(1)).await;
 
         delete_author(docs.clone(), author_id.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
 
         let verified = verify_author(docs.clone(), author_id.clone()).await?;
         assert!(!verified);
-        sleep(Duration::from_secs(1)).await;
 
         fs::remove_dir_all("Test/test_blobs").await?;
         fs::remove_dir_all("Test").await?;
@@ -496,27 +683,21 @@ mod tests {
         let docs = iroh_node.docs.clone();
 
         let default_author = get_default_author(docs.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
 
-        let author_1 = create_author(docs.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
-        let author_2 = create_author(docs.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
+        let author_1 = create_local_author(docs.clone()).await?;
+        let author_2 = create_local_author(docs.clone()).await?;
 
         let authors = list_authors(docs.clone()).await?;
         assert!(authors.contains(&author_1));
         assert!(authors.contains(&author_2));
         assert!(authors.contains(&default_author));
-        sleep(Duration::from_secs(1)).await;
 
         delete_all_authors(docs.clone()).await?;
-        sleep(Duration::from_secs(1)).await;
 
         let authors = list_authors(docs.clone()).await?;
         assert!(!authors.contains(&author_1));
         assert!(!authors.contains(&author_2));
         assert!(authors.contains(&default_author));
-        sleep(Duration::from_secs(1)).await;
 
         fs::remove_dir_all("Test/test_blobs").await?;
         fs::remove_dir_all("Test").await?;
@@ -0,0 +1,980 @@
+use helpers::backups::{BackupSettings, S3Target};
+use helpers::stores::{NodeBlobs, NodeDocs};
+use helpers::utils::encode_doc_id;
+
+use iroh_blobs::rpc::client::blobs::WrapOption;
+use iroh_blobs::util::{SetTagOption, Tag};
+use iroh_blobs::{BlobFormat, Hash};
+use iroh_docs::rpc::client::docs::ShareMode;
+use iroh_docs::rpc::AddrInfoOptions;
+use iroh_docs::store::{DownloadPolicy, Query};
+use iroh_docs::{Author, AuthorId, Capability, CapabilityKind, NamespaceId, NamespaceSecret};
+use futures::TryStreamExt;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Version of the snapshot layout, checked by `restore_snapshot`.
+pub const SNAPSHOT_FORMAT: u32 = 1;
+/// Name of the manifest, the first entry of every snapshot.
+pub const SNAPSHOT_MANIFEST: &str = "manifest.json";
+
+const SNAPSHOT_PREFIX: &str = "snapshot-";
+const SNAPSHOT_SUFFIX: &str = ".tar.zst";
+const AUTHORS_FILE: &str = "authors.json";
+const TAGS_FILE: &str = "tags.json";
+const DOCS_DIR: &str = "docs";
+const BLOBS_DIR: &str = "blobs";
+const STATE_FILE: &str = ".backup-state.json";
+
+#[derive(Debug, PartialEq)]
+pub enum BackupError {
+    /// No backup directory is configured.
+    NotConfigured,
+    /// Failed to read authors, documents or blobs from the node's stores.
+    FailedToReadStore,
+    /// Failed to write the snapshot archive.
+    FailedToWriteSnapshot,
+    /// Failed to copy the snapshot to the S3 bucket.
+    FailedToUploadSnapshot,
+    /// Failed to list the snapshots in the backup directory.
+    FailedToListSnapshots,
+    /// Failed to delete a snapshot.
+    FailedToDeleteSnapshot,
+    /// The archive is not a snapshot, or was written by an unsupported version.
+    InvalidSnapshot,
+    /// A file of the snapshot does not match its manifest.
+    CorruptedSnapshot,
+    /// An incremental snapshot builds on a snapshot that is not next to it.
+    MissingBaseSnapshot,
+    /// Failed to write authors, documents or blobs of the snapshot into the node's stores.
+    FailedToRestore,
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+/// Lists every file of a snapshot with its BLAKE3 hash, so a restore can verify it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub format: u32,
+    pub created_at: u64,
+    /// The snapshot this one only holds the changes since, `None` for a full snapshot.
+    #[serde(default)]
+    pub base: Option<String>,
+    pub authors: usize,
+    pub docs: usize,
+    pub entries: usize,
+    pub blobs: usize,
+    pub files: Vec<SnapshotFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    /// Path inside the snapshot, with `/` separators.
+    pub path: String,
+    pub size: u64,
+    pub blake3: String,
+}
+
+/// A snapshot in the backup directory, as listed by `list_snapshots`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotInfo {
+    pub name: String,
+    /// Size of the archive in bytes.
+    pub size: u64,
+    pub created_at: u64,
+    /// The snapshot this one only holds the changes since, `None` for a full snapshot.
+    pub base: Option<String>,
+    pub authors: usize,
+    pub docs: usize,
+    pub entries: usize,
+    pub blobs: usize,
+}
+
+/// What `restore_snapshot` wrote into the node's stores.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RestoreReport {
+    pub authors: usize,
+    pub docs: usize,
+    /// Entries written again, because the store did not have them or had another version.
+    pub entries_restored: usize,
+    /// Entries of read-only documents, or by authors the snapshot has no secret for. They come
+    /// back when the document syncs with its peers.
+    pub entries_skipped: usize,
+    pub blobs: usize,
+    pub tags: usize,
+}
+
+// authors.json
+#[derive(Serialize, Deserialize)]
+struct AuthorsSnapshot {
+    authors: Vec<String>,
+}
+
+// docs/<doc_id>.json
+#[derive(Serialize, Deserialize)]
+struct DocSnapshot {
+    doc_id: String,
+    /// The namespace secret of a writable document, `None` for a read-only one.
+    secret: Option<String>,
+    namespace: String,
+    download_policy: DownloadPolicy,
+    entries: Vec<EntrySnapshot>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EntrySnapshot {
+    /// The raw key, hex-encoded.
+    key: String,
+    author: String,
+    hash: String,
+    len: u64,
+    timestamp: u64,
+}
+
+// .backup-state.json: what the latest snapshot covers, so the next one can leave it out
+#[derive(Default, Serialize, Deserialize)]
+struct BackupState {
+    /// Name of the latest snapshot.
+    last: String,
+    /// Incremental snapshots taken since the last full one.
+    increments: usize,
+    /// Newest entry timestamp backed up, per document and author.
+    docs: BTreeMap<String, BTreeMap<String, u64>>,
+    /// Hashes of the blobs backed up.
+    blobs: BTreeSet<String>,
+}
+
+// tags.json
+#[derive(Serialize, Deserialize)]
+struct TagSnapshot {
+    /// The raw tag name, hex-encoded.
+    name: String,
+    hash: String,
+}
+
+/// Writes the node's authors, documents and blobs into a new `snapshot-<millis>.tar.zst` in the
+/// backup directory, and copies it to the S3 bucket if one is configured.
+///
+/// This is a logical snapshot, taken while the node runs. Documents keep the latest entry of
+/// every author and key, without deleted entries; blobs are the complete ones.
+///
+/// Unless `full` is set or `settings.full_every` is reached, the snapshot is incremental: it only
+/// holds the entries newer than the latest snapshot's for their document and author, and the
+/// blobs added since. Authors, document secrets, download policies and tags are always complete.
+pub async fn create_snapshot(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    settings: &BackupSettings,
+    full: bool,
+) -> Result<SnapshotInfo, BackupError> {
+    let dir = settings.dir.clone().ok_or(BackupError::NotConfigured)?;
+    let _running = settings.lock().await;
+
+    tokio::fs::create_dir_all(&dir).await.map_err(|e| {
+        tracing::error!(error = %e, dir = ?dir, "❌ Failed to create backup directory");
+        BackupError::FailedToWriteSnapshot
+    })?;
+    let staging = tempfile::Builder::new()
+        .prefix(".snapshot-staging")
+        .tempdir_in(&dir)
+        .map_err(|_| BackupError::FailedToWriteSnapshot)?;
+
+    // A snapshot can only build on one that is still there
+    let previous = match full {
+        true => None,
+        false => load_state(&dir)
+            .await
+            .filter(|state| state.increments + 1 < settings.full_every && dir.join(&state.last).is_file()),
+    };
+    let mut observed = BackupState::default();
+
+    let created_at_millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+    let name = format!("{}{}{}", SNAPSHOT_PREFIX, created_at_millis, SNAPSHOT_SUFFIX);
+    let mut manifest = SnapshotManifest {
+        format: SNAPSHOT_FORMAT,
+        created_at: (created_at_millis / 1000) as u64,
+        base: previous.as_ref().map(|state| state.last.clone()),
+        authors: 0,
+        docs: 0,
+        entries: 0,
+        blobs: 0,
+        files: Vec::new(),
+    };
+    let mut files = Vec::new();
+
+    stage_authors(&docs, staging.path(), &mut manifest, &mut files).await?;
+    stage_docs(&docs, staging.path(), previous.as_ref(), &mut observed, &mut manifest, &mut files).await?;
+    stage_blobs(&blobs, staging.path(), previous.as_ref(), &mut observed, &mut manifest, &mut files).await?;
+
+    let (staging_path, out) = (staging.path().to_path_buf(), dir.join(&name));
+    let manifest = tokio::task::spawn_blocking(move || write_archive(&staging_path, &files, manifest, &out))
+        .await
+        .map_err(|_| BackupError::FailedToWriteSnapshot)?
+        .map_err(|e| {
+            tracing::error!(error = %e, "❌ Failed to write snapshot archive");
+            BackupError::FailedToWriteSnapshot
+        })?;
+
+    // Without the state, the next snapshot is simply full
+    observed.last = name.clone();
+    observed.increments = previous.map_or(0, |state| state.increments + 1);
+    if let Err(e) = save_state(&dir, &observed).await {
+        tracing::warn!(error = %e, "failed to save backup state, the next snapshot will be full");
+    }
+
+    let path = dir.join(&name);
+    if let Some(target) = &settings.s3 {
+        upload_snapshot(target, &name, &path).await?;
+    }
+
+    let size = tokio::fs::metadata(&path).await.map(|m| m.len()).unwrap_or_default();
+    Ok(snapshot_info(name, size, &manifest))
+}
+
+/// Lists the snapshots in the backup directory, newest first.
+pub async fn list_snapshots(settings: &BackupSettings) -> Result<Vec<SnapshotInfo>, BackupError> {
+    let dir = settings.dir.clone().ok_or(BackupError::NotConfigured)?;
+    tokio::task::spawn_blocking(move || read_snapshots(&dir))
+        .await
+        .map_err(|_| BackupError::FailedToListSnapshots)?
+}
+
+/// Deletes all but the `keep` newest snapshots, from the backup directory and from the S3 bucket.
+/// The snapshots the kept ones build on are kept too, so each of them can still be restored.
+///
+/// # Returns
+/// * `Vec<String>` - The names of the deleted snapshots.
+pub async fn prune_snapshots(settings: &BackupSettings, keep: usize) -> Result<Vec<String>, BackupError> {
+    let dir = settings.dir.clone().ok_or(BackupError::NotConfigured)?;
+    let _running = settings.lock().await;
+
+    let snapshots = list_snapshots(settings).await?;
+    let bases: HashMap<String, Option<String>> = snapshots.iter().map(|s| (s.name.clone(), s.base.clone())).collect();
+    let mut kept = HashSet::new();
+    for snapshot in snapshots.iter().take(keep) {
+        let mut name = Some(snapshot.name.clone());
+        while let Some(current) = name.filter(|current| kept.insert(current.clone())) {
+            name = bases.get(&current).cloned().flatten();
+        }
+    }
+
+    let mut removed = Vec::new();
+    for snapshot in snapshots.into_iter().filter(|snapshot| !kept.contains(&snapshot.name)) {
+        tokio::fs::remove_file(dir.join(&snapshot.name)).await.map_err(|e| {
+            tracing::error!(error = %e, name = %snapshot.name, "❌ Failed to delete snapshot");
+            BackupError::FailedToDeleteSnapshot
+        })?;
+        if let Some(target) = &settings.s3 {
+            delete_uploaded_snapshot(target, &snapshot.name).await?;
+        }
+        removed.push(snapshot.name);
+    }
+    Ok(removed)
+}
+
+/// Takes a snapshot every `interval` and then prunes to `settings.keep` snapshots. A failed run
+/// is logged and tried again at the next tick.
+pub fn spawn_backup_scheduler(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    settings: BackupSettings,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        // the first tick completes immediately
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            match create_snapshot(docs.clone(), blobs.clone(), &settings, false).await {
+                Ok(snapshot) => tracing::info!(name = %snapshot.name, size = snapshot.size, incremental = snapshot.base.is_some(), "💾 Wrote scheduled snapshot"),
+                Err(e) => {
+                    tracing::warn!(error = %e, "failed to write scheduled snapshot");
+                    continue;
+                }
+            }
+            match prune_snapshots(&settings, settings.keep).await {
+                Ok(removed) if !removed.is_empty() => tracing::info!(removed = removed.len(), "🧹 Pruned old snapshots"),
+                Ok(_) => {},
+                Err(e) => tracing::warn!(error = %e, "failed to prune snapshots"),
+            }
+        }
+    })
+}
+
+/// Restores a snapshot into the node's stores, after verifying every file against its manifest.
+/// An incremental snapshot is restored together with the snapshots it builds on, which must be
+/// in the same directory.
+///
+/// Authors, document capabilities, download policies, blobs and tags are imported. Entries of
+/// writable documents by restored authors are written again where the store lacks them, with a
+/// new timestamp; the others come back from peers when the documents sync.
+pub async fn restore_snapshot(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    archive: &Path,
+) -> Result<RestoreReport, BackupError> {
+    let unpacked = tempfile::tempdir().map_err(|_| BackupError::FailedToRestore)?;
+    let (archive_path, unpacked_path) = (archive.to_path_buf(), unpacked.path().to_path_buf());
+    // oldest first, ending with `archive`
+    let chain = tokio::task::spawn_blocking(move || unpack_chain(&archive_path, &unpacked_path))
+        .await
+        .map_err(|_| BackupError::FailedToRestore)??;
+    let newest = chain.last().ok_or(BackupError::InvalidSnapshot)?;
+    let mut report = RestoreReport::default();
+
+    let authors: AuthorsSnapshot = read_json(&newest.join(AUTHORS_FILE)).await?;
+    let mut known_authors = HashSet::new();
+    for secret in authors.authors {
+        let author = Author::from_str(&secret).map_err(|_| BackupError::CorruptedSnapshot)?;
+        known_authors.insert(author.id());
+        docs.client().authors().import(author).await.map_err(|e| {
+            tracing::error!(error = %e, "❌ Failed to import author");
+            BackupError::FailedToRestore
+        })?;
+        report.authors += 1;
+    }
+
+    // Blobs first, so restored entries find their content. The newest snapshot has every tag,
+    // each blob is in the snapshot that first saw it.
+    let tags: Vec<TagSnapshot> = read_json(&newest.join(TAGS_FILE)).await?;
+    let mut tags_by_hash: HashMap<String, Vec<Tag>> = HashMap::new();
+    for tag in tags {
+        let name = hex::decode(&tag.name).map_err(|_| BackupError::CorruptedSnapshot)?;
+        tags_by_hash.entry(tag.hash).or_default().push(Tag(name.into()));
+    }
+    for root in &chain {
+        let mut blob_files = tokio::fs::read_dir(root.join(BLOBS_DIR)).await.map_err(|_| BackupError::CorruptedSnapshot)?;
+        while let Some(file) = blob_files.next_entry().await.map_err(|_| BackupError::CorruptedSnapshot)? {
+            let hash = file.file_name().to_string_lossy().to_string();
+            report.tags += restore_blob(&blobs, &file.path(), &hash, tags_by_hash.remove(&hash).unwrap_or_default()).await?;
+            report.blobs += 1;
+        }
+    }
+
+    let mut restored_docs = HashSet::new();
+    for root in &chain {
+        let mut doc_files = tokio::fs::read_dir(root.join(DOCS_DIR)).await.map_err(|_| BackupError::CorruptedSnapshot)?;
+        while let Some(file) = doc_files.next_entry().await.map_err(|_| BackupError::CorruptedSnapshot)? {
+            let snapshot: DocSnapshot = read_json(&file.path()).await?;
+            restored_docs.insert(snapshot.doc_id.clone());
+            restore_doc(&docs, snapshot, &known_authors, &mut report).await?;
+        }
+    }
+    report.docs = restored_docs.len();
+
+    Ok(report)
+}
+
+// Adds a blob under each of its tags, or an automatic tag if it has none
+//
+// Returns the number of named tags set
+async fn restore_blob(
+    blobs: &Arc<NodeBlobs>,
+    path: &Path,
+    hash: &str,
+    tags: Vec<Tag>,
+) -> Result<usize, BackupError> {
+    let tag_options = if tags.is_empty() {
+        vec![SetTagOption::Auto]
+    } else {
+        tags.into_iter().map(SetTagOption::Named).collect()
+    };
+    let mut named = 0;
+    for tag in tag_options {
+        named += matches!(tag, SetTagOption::Named(_)) as usize;
+        let outcome = blobs
+            .client()
+            .add_from_path(path.to_path_buf(), false, tag, WrapOption::NoWrap)
+            .await
+            .map_err(|_| BackupError::FailedToRestore)?
+            .finish()
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, hash = %hash, "❌ Failed to import blob");
+                BackupError::FailedToRestore
+            })?;
+        if outcome.hash.to_string() != hash {
+            return Err(BackupError::CorruptedSnapshot);
+        }
+    }
+    Ok(named)
+}
+
+async fn restore_doc(
+    docs: &Arc<NodeDocs>,
+    snapshot: DocSnapshot,
+    known_authors: &HashSet<AuthorId>,
+    report: &mut RestoreReport,
+) -> Result<(), BackupError> {
+    let capability = match &snapshot.secret {
+        Some(secret) => Capability::Write(NamespaceSecret::from_str(secret).map_err(|_| BackupError::CorruptedSnapshot)?),
+        None => Capability::Read(NamespaceId::from_str(&snapshot.namespace).map_err(|_| BackupError::CorruptedSnapshot)?),
+    };
+    let writable = matches!(capability, Capability::Write(_));
+
+    // Importing the namespace merges the capability into an existing document, without syncing
+    let doc = docs.client().import_namespace(capability).await.map_err(|e| {
+        tracing::error!(error = %e, doc_id = %snapshot.doc_id, "❌ Failed to import document");
+        BackupError::FailedToRestore
+    })?;
+    doc.set_download_policy(snapshot.download_policy).await.map_err(|_| BackupError::FailedToRestore)?;
+
+    for entry in snapshot.entries {
+        let author = AuthorId::from_str(&entry.author).map_err(|_| BackupError::CorruptedSnapshot)?;
+        if !writable || !known_authors.contains(&author) {
+            report.entries_skipped += 1;
+            continue;
+        }
+        let key = hex::decode(&entry.key).map_err(|_| BackupError::CorruptedSnapshot)?;
+        let hash = Hash::from_str(&entry.hash).map_err(|_| BackupError::CorruptedSnapshot)?;
+
+        let current = doc.get_exact(author, &key, false).await.map_err(|_| BackupError::FailedToRestore)?;
+        if current.is_some_and(|current| current.content_hash() == hash) {
+            continue;
+        }
+        doc.set_hash(author, key, hash, entry.len).await.map_err(|e| {
+            tracing::error!(error = %e, doc_id = %snapshot.doc_id, "❌ Failed to restore entry");
+            BackupError::FailedToRestore
+        })?;
+        report.entries_restored += 1;
+    }
+    Ok(())
+}
+
+async fn stage_authors(
+    docs: &Arc<NodeDocs>,
+    staging: &Path,
+    manifest: &mut SnapshotManifest,
+    files: &mut Vec<String>,
+) -> Result<(), BackupError> {
+    let authors_client = docs.client().authors();
+    let ids: Vec<AuthorId> = authors_client
+        .list()
+        .await
+        .map_err(|_| BackupError::FailedToReadStore)?
+        .try_collect()
+        .await
+        .map_err(|_| BackupError::FailedToReadStore)?;
+
+    let mut authors = Vec::with_capacity(ids.len());
+    for id in ids {
+        if let Some(author) = authors_client.export(id).await.map_err(|_| BackupError::FailedToReadStore)? {
+            authors.push(author.to_string());
+        }
+    }
+    manifest.authors = authors.len();
+    write_json(staging, AUTHORS_FILE, &AuthorsSnapshot { authors }, files).await
+}
+
+// Only the entries newer than `previous` has for their document and author are staged, all of
+// them without `previous`
+async fn stage_docs(
+    docs: &Arc<NodeDocs>,
+    staging: &Path,
+    previous: Option<&BackupState>,
+    observed: &mut BackupState,
+    manifest: &mut SnapshotManifest,
+    files: &mut Vec<String>,
+) -> Result<(), BackupError> {
+    let doc_client = docs.client();
+    let namespaces: Vec<(NamespaceId, CapabilityKind)> = doc_client
+        .list()
+        .await
+        .map_err(|_| BackupError::FailedToReadStore)?
+        .try_collect()
+        .await
+        .map_err(|_| BackupError::FailedToReadStore)?;
+
+    tokio::fs::create_dir_all(staging.join(DOCS_DIR)).await.map_err(|_| BackupError::FailedToWriteSnapshot)?;
+    for (namespace, capability) in namespaces {
+        let doc_id = encode_doc_id(namespace.as_bytes());
+        let doc = doc_client
+            .open(namespace)
+            .await
+            .map_err(|_| BackupError::FailedToReadStore)?
+            .ok_or(BackupError::FailedToReadStore)?;
+
+        let secret = match capability {
+            CapabilityKind::Write => {
+                // Sharing starts sync, so a document that was not syncing leaves again
+                let was_syncing = doc.status().await.map_err(|_| BackupError::FailedToReadStore)?.sync;
+                let ticket = doc
+                    .share(ShareMode::Write, AddrInfoOptions::Id)
+                    .await
+                    .map_err(|_| BackupError::FailedToReadStore)?;
+                if !was_syncing {
+                    doc.leave().await.map_err(|_| BackupError::FailedToReadStore)?;
+                }
+                match ticket.capability {
+                    Capability::Write(secret) => Some(secret.to_string()),
+                    Capability::Read(_) => None,
+                }
+            }
+            CapabilityKind::Read => None,
+        };
+
+        let backed_up = previous.and_then(|state| state.docs.get(&doc_id));
+        let newest = observed.docs.entry(doc_id.clone()).or_default();
+        let mut entries = Vec::new();
+        let mut stream = doc.get_many(Query::all()).await.map_err(|_| BackupError::FailedToReadStore)?;
+        while let Some(entry) = stream.try_next().await.map_err(|_| BackupError::FailedToReadStore)? {
+            let author = entry.author().to_string();
+            let timestamp = entry.timestamp();
+            let newest_timestamp = newest.entry(author.clone()).or_default();
+            *newest_timestamp = (*newest_timestamp).max(timestamp);
+            if backed_up.and_then(|authors| authors.get(&author)).is_some_and(|&last| timestamp <= last) {
+                continue;
+            }
+            entries.push(EntrySnapshot {
+                key: hex::encode(entry.key()),
+                author,
+                hash: entry.content_hash().to_string(),
+                len: entry.content_len(),
+                timestamp,
+            });
+        }
+        let download_policy = doc.get_download_policy().await.map_err(|_| BackupError::FailedToReadStore)?;
+
+        manifest.docs += 1;
+        manifest.entries += entries.len();
+        let snapshot = DocSnapshot { doc_id: doc_id.clone(), secret, namespace: namespace.to_string(), download_policy, entries };
+        write_json(staging, &format!("{}/{}.json", DOCS_DIR, doc_id), &snapshot, files).await?;
+    }
+    Ok(())
+}
+
+// Only the blobs `previous` does not have are staged, all of them without `previous`
+async fn stage_blobs(
+    blobs: &Arc<NodeBlobs>,
+    staging: &Path,
+    previous: Option<&BackupState>,
+    observed: &mut BackupState,
+    manifest: &mut SnapshotManifest,
+    files: &mut Vec<String>,
+) -> Result<(), BackupError> {
+    let blobs_client = blobs.client();
+    tokio::fs::create_dir_all(staging.join(BLOBS_DIR)).await.map_err(|_| BackupError::FailedToWriteSnapshot)?;
+
+    let mut list = blobs_client.list().await.map_err(|_| BackupError::FailedToReadStore)?;
+    while let Some(blob) = list.try_next().await.map_err(|_| BackupError::FailedToReadStore)? {
+        let hash = blob.hash.to_string();
+        let backed_up = previous.is_some_and(|state| state.blobs.contains(&hash));
+        observed.blobs.insert(hash);
+        if backed_up {
+            continue;
+        }
+        let relative = format!("{}/{}", BLOBS_DIR, blob.hash);
+        let mut reader = blobs_client.read(blob.hash).await.map_err(|_| BackupError::FailedToReadStore)?;
+        let mut file = tokio::fs::File::create(staging.join(&relative)).await.map_err(|_| BackupError::FailedToWriteSnapshot)?;
+        tokio::io::copy(&mut reader, &mut file).await.map_err(|e| {
+            tracing::error!(error = %e, hash = %blob.hash, "❌ Failed to copy blob into snapshot");
+            BackupError::FailedToWriteSnapshot
+        })?;
+        manifest.blobs += 1;
+        files.push(relative);
+    }
+
+    // Collections are not snapshotted, so their tags are left out
+    let tags: Vec<TagSnapshot> = blobs_client
+        .tags()
+        .list()
+        .await
+        .map_err(|_| BackupError::FailedToReadStore)?
+        .try_filter(|tag| futures::future::ready(tag.format == BlobFormat::Raw))
+        .map_ok(|tag| TagSnapshot { name: hex::encode(tag.name.0), hash: tag.hash.to_string() })
+        .try_collect()
+        .await
+        .map_err(|_| BackupError::FailedToReadStore)?;
+    write_json(staging, TAGS_FILE, &tags, files).await
+}
+
+async fn load_state(dir: &Path) -> Option<BackupState> {
+    let content = tokio::fs::read(dir.join(STATE_FILE)).await.ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+async fn save_state(dir: &Path, state: &BackupState) -> std::io::Result<()> {
+    let json = serde_json::to_vec(state)?;
+    let partial = dir.join(format!("{}.partial", STATE_FILE));
+    tokio::fs::write(&partial, json).await?;
+    tokio::fs::rename(&partial, dir.join(STATE_FILE)).await
+}
+
+async fn write_json<T: Serialize>(
+    staging: &Path,
+    relative: &str,
+    value: &T,
+    files: &mut Vec<String>,
+) -> Result<(), BackupError> {
+    let json = serde_json::to_vec_pretty(value).map_err(|_| BackupError::FailedToWriteSnapshot)?;
+    tokio::fs::write(staging.join(relative), json).await.map_err(|_| BackupError::FailedToWriteSnapshot)?;
+    files.push(relative.to_string());
+    Ok(())
+}
+
+async fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, BackupError> {
+    let content = tokio::fs::read(path).await.map_err(|_| BackupError::CorruptedSnapshot)?;
+    serde_json::from_slice(&content).map_err(|_| BackupError::CorruptedSnapshot)
+}
+
+fn snapshot_info(name: String, size: u64, manifest: &SnapshotManifest) -> SnapshotInfo {
+    SnapshotInfo {
+        name,
+        size,
+        created_at: manifest.created_at,
+        base: manifest.base.clone(),
+        authors: manifest.authors,
+        docs: manifest.docs,
+        entries: manifest.entries,
+        blobs: manifest.blobs,
+    }
+}
+
+// Hashes the staged files into the manifest and packs them behind it, under a temporary name so
+// a failed run never leaves a truncated snapshot behind
+fn write_archive(
+    staging: &Path,
+    files: &[String],
+    mut manifest: SnapshotManifest,
+    out: &Path,
+) -> Result<SnapshotManifest, Box<dyn std::error::Error + Send + Sync>> {
+    for relative in files {
+        let mut hasher = blake3::Hasher::new();
+        let size = std::io::copy(&mut File::open(staging.join(relative))?, &mut hasher)?;
+        manifest.files.push(SnapshotFile { path: relative.clone(), size, blake3: hasher.finalize().to_hex().to_string() });
+    }
+
+    let mut partial = out.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let result = (|| -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let encoder = zstd::Encoder::new(File::create(&partial)?, 0)?;
+        let mut archive = tar::Builder::new(encoder);
+
+        let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest_json.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(manifest.created_at);
+        header.set_cksum();
+        archive.append_data(&mut header, SNAPSHOT_MANIFEST, manifest_json.as_slice())?;
+
+        for relative in files {
+            archive.append_path_with_name(staging.join(relative), relative)?;
+        }
+
+        archive.into_inner()?.finish()?.sync_all()?;
+        std::fs::rename(&partial, out)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result.map(|_| manifest)
+}
+
+// Reads only the manifest, the first entry of the archive
+fn read_manifest(archive: &Path) -> Result<SnapshotManifest, BackupError> {
+    let decoder = zstd::Decoder::new(File::open(archive).map_err(|_| BackupError::InvalidSnapshot)?)
+        .map_err(|_| BackupError::InvalidSnapshot)?;
+    let mut tar = tar::Archive::new(decoder);
+    let mut entries = tar.entries().map_err(|_| BackupError::InvalidSnapshot)?;
+    read_manifest_entry(entries.next())
+}
+
+fn read_manifest_entry<R: Read>(
+    entry: Option<std::io::Result<tar::Entry<'_, R>>>,
+) -> Result<SnapshotManifest, BackupError> {
+    let mut entry = match entry {
+        Some(Ok(entry)) if entry.path().is_ok_and(|path| path == Path::new(SNAPSHOT_MANIFEST)) => entry,
+        _ => return Err(BackupError::InvalidSnapshot),
+    };
+    let mut content = String::new();
+    entry.read_to_string(&mut content).map_err(|_| BackupError::InvalidSnapshot)?;
+    let manifest: SnapshotManifest = serde_json::from_str(&content).map_err(|_| BackupError::InvalidSnapshot)?;
+    if manifest.format != SNAPSHOT_FORMAT {
+        tracing::error!(format = manifest.format, supported = SNAPSHOT_FORMAT, "❌ Unsupported snapshot format");
+        return Err(BackupError::InvalidSnapshot);
+    }
+    Ok(manifest)
+}
+
+// Unpacks the archive and the snapshots it builds on into numbered directories under `dir`
+//
+// Returns the directories, oldest snapshot first
+fn unpack_chain(archive: &Path, dir: &Path) -> Result<Vec<PathBuf>, BackupError> {
+    let mut chain = Vec::new();
+    let mut next = Some(archive.to_path_buf());
+    while let Some(archive) = next {
+        let unpacked = dir.join(chain.len().to_string());
+        std::fs::create_dir_all(&unpacked).map_err(|_| BackupError::FailedToRestore)?;
+        let manifest = unpack_archive(&archive, &unpacked)?;
+        chain.push(unpacked);
+
+        next = match manifest.base {
+            Some(base) => {
+                let name = archive.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                // a base is always older, which also rules out cycles
+                if snapshot_millis(&base) >= snapshot_millis(&name) || base.contains(['/', '\\']) {
+                    return Err(BackupError::InvalidSnapshot);
+                }
+                let base_path = archive.with_file_name(&base);
+                if !base_path.is_file() {
+                    tracing::error!(base = %base, "❌ The snapshot this one builds on is missing");
+                    return Err(BackupError::MissingBaseSnapshot);
+                }
+                Some(base_path)
+            }
+            None => None,
+        };
+    }
+    chain.reverse();
+    Ok(chain)
+}
+
+// Unpacks the archive into `dir` and checks that it holds exactly the files of its manifest
+fn unpack_archive(archive: &Path, dir: &Path) -> Result<SnapshotManifest, BackupError> {
+    let decoder = zstd::Decoder::new(File::open(archive).map_err(|e| {
+        tracing::error!(error = %e, archive = ?archive, "❌ Failed to open snapshot");
+        BackupError::InvalidSnapshot
+    })?)
+    .map_err(|_| BackupError::InvalidSnapshot)?;
+    let mut tar = tar::Archive::new(decoder);
+    let mut entries = tar.entries().map_err(|_| BackupError::InvalidSnapshot)?;
+    let manifest = read_manifest_entry(entries.next())?;
+
+    let mut expected: HashMap<&str, &SnapshotFile> = manifest.files.iter().map(|file| (file.path.as_str(), file)).collect();
+    for entry in entries {
+        let mut entry = entry.map_err(|_| BackupError::CorruptedSnapshot)?;
+        let path = entry.path().map_err(|_| BackupError::CorruptedSnapshot)?.to_string_lossy().to_string();
+        let file = expected.remove(path.as_str()).ok_or_else(|| {
+            tracing::error!(path = %path, "❌ Snapshot entry is not in its manifest");
+            BackupError::CorruptedSnapshot
+        })?;
+        // `unpack_in` refuses entries that would land outside `dir`
+        if !entry.unpack_in(dir).map_err(|_| BackupError::CorruptedSnapshot)? {
+            return Err(BackupError::CorruptedSnapshot);
+        }
+
+        let mut hasher = blake3::Hasher::new();
+        let size = std::io::copy(&mut File::open(dir.join(&path)).map_err(|_| BackupError::CorruptedSnapshot)?, &mut hasher)
+            .map_err(|_| BackupError::CorruptedSnapshot)?;
+        if size != file.size || hasher.finalize().to_hex().as_str() != file.blake3 {
+            tracing::error!(path = %path, "❌ Snapshot file does not match its manifest");
+            return Err(BackupError::CorruptedSnapshot);
+        }
+    }
+    if let Some(missing) = expected.keys().next() {
+        tracing::error!(path = %missing, missing = expected.len(), "❌ Files of the snapshot manifest are missing");
+        return Err(BackupError::CorruptedSnapshot);
+    }
+    Ok(manifest)
+}
+
+fn read_snapshots(dir: &Path) -> Result<Vec<SnapshotInfo>, BackupError> {
+    let read_dir = match std::fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        // nothing was written yet
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(_) => return Err(BackupError::FailedToListSnapshots),
+    };
+
+    let mut snapshots = Vec::new();
+    for file in read_dir {
+        let file = file.map_err(|_| BackupError::FailedToListSnapshots)?;
+        let name = file.file_name().to_string_lossy().to_string();
+        if !(name.starts_with(SNAPSHOT_PREFIX) && name.ends_with(SNAPSHOT_SUFFIX)) {
+            continue;
+        }
+        match read_manifest(&file.path()) {
+            Ok(manifest) => {
+                let size = file.metadata().map(|m| m.len()).unwrap_or_default();
+                snapshots.push(snapshot_info(name, size, &manifest));
+            }
+            Err(e) => tracing::warn!(error = %e, name = %name, "skipping unreadable snapshot"),
+        }
+    }
+    // the names hold the creation time in milliseconds
+    snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot_millis(&snapshot.name)));
+    Ok(snapshots)
+}
+
+fn snapshot_millis(name: &str) -> u128 {
+    name.trim_start_matches(SNAPSHOT_PREFIX)
+        .trim_end_matches(SNAPSHOT_SUFFIX)
+        .parse()
+        .unwrap_or_default()
+}
+
+async fn upload_snapshot(target: &S3Target, name: &str, path: &Path) -> Result<(), BackupError> {
+    let file = tokio::fs::File::open(path).await.map_err(|_| BackupError::FailedToUploadSnapshot)?;
+    let len = file.metadata().await.map_err(|_| BackupError::FailedToUploadSnapshot)?.len();
+    let body = reqwest::Body::wrap_stream(tokio_util::io::ReaderStream::new(file));
+
+    let request = s3_request(target, reqwest::Method::PUT, name)?
+        .header(reqwest::header::CONTENT_LENGTH, len)
+        .body(body);
+    send_s3_request(request, name).await.map_err(|_| BackupError::FailedToUploadSnapshot)
+}
+
+async fn delete_uploaded_snapshot(target: &S3Target, name: &str) -> Result<(), BackupError> {
+    let request = s3_request(target, reqwest::Method::DELETE, name)?;
+    send_s3_request(request, name).await.map_err(|_| BackupError::FailedToDeleteSnapshot)
+}
+
+async fn send_s3_request(request: reqwest::RequestBuilder, name: &str) -> Result<(), ()> {
+    match request.send().await {
+        Ok(response) if response.status().is_success() => Ok(()),
+        Ok(response) => {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            tracing::error!(status = %status, body = %body, name = %name, "❌ S3 request for snapshot failed");
+            Err(())
+        }
+        Err(e) => {
+            tracing::error!(error = %e, name = %name, "❌ S3 request for snapshot failed");
+            Err(())
+        }
+    }
+}
+
+// Builds a path-style request for the object `name`, signed with AWS Signature Version 4. The
+// payload is left unsigned, so large snapshots are streamed without hashing them first.
+fn s3_request(target: &S3Target, method: reqwest::Method, name: &str) -> Result<reqwest::RequestBuilder, BackupError> {
+    let url = reqwest::Url::parse(&format!("{}/{}", target.url, name)).map_err(|_| BackupError::FailedToUploadSnapshot)?;
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err(BackupError::FailedToUploadSnapshot),
+    };
+
+    let now = chrono::Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let authorization = s3_authorization(target, method.as_str(), &host, url.path(), &amz_date);
+
+    Ok(reqwest::Client::new()
+        .request(method, url)
+        .header("x-amz-date", amz_date)
+        .header("x-amz-content-sha256", "UNSIGNED-PAYLOAD")
+        .header(reqwest::header::AUTHORIZATION, authorization))
+}
+
+fn s3_authorization(target: &S3Target, method: &str, host: &str, path: &str, amz_date: &str) -> String {
+    const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/s3/aws4_request", date, target.region);
+
+    let canonical_request = format!(
+        "{}\n{}\n\nhost:{}\nx-amz-content-sha256:UNSIGNED-PAYLOAD\nx-amz-date:{}\n\n{}\nUNSIGNED-PAYLOAD",
+        method, path, host, amz_date, SIGNED_HEADERS
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let mut key = format!("AWS4{}", target.secret_key).into_bytes();
+    for part in [date, target.region.as_str(), "s3", "aws4_request"] {
+        key = hmac_sha256(&key, part.as_bytes());
+    }
+    let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        target.access_key, scope, SIGNED_HEADERS, signature
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    pub async fn test_snapshot_archive_is_verified() -> anyhow::Result<()> {
+        let staging = tempfile::tempdir()?;
+        let out = tempfile::tempdir()?;
+        std::fs::create_dir_all(staging.path().join(super::BLOBS_DIR))?;
+        std::fs::write(staging.path().join(super::AUTHORS_FILE), br#"{"authors":[]}"#)?;
+        std::fs::write(staging.path().join("blobs/one"), b"first blob")?;
+        let files = vec![super::AUTHORS_FILE.to_string(), "blobs/one".to_string()];
+
+        let manifest = super::SnapshotManifest {
+            format: super::SNAPSHOT_FORMAT,
+            created_at: 1,
+            base: None,
+            authors: 0,
+            docs: 0,
+            entries: 0,
+            blobs: 1,
+            files: Vec::new(),
+        };
+        let archive = out.path().join("snapshot-1000.tar.zst");
+        let manifest = super::write_archive(staging.path(), &files, manifest, &archive)
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(super::read_manifest(&archive)?.blobs, 1);
+
+        let unpacked = tempfile::tempdir()?;
+        super::unpack_archive(&archive, unpacked.path())?;
+        assert_eq!(std::fs::read(unpacked.path().join("blobs/one"))?, b"first blob");
+
+        // an archive missing files of its manifest is refused
+        let tampered = manifest.clone();
+        let tampered_archive = out.path().join("snapshot-2000.tar.zst");
+        super::write_archive(staging.path(), &[], tampered, &tampered_archive)
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        assert_eq!(
+            super::unpack_archive(&tampered_archive, tempfile::tempdir()?.path()).unwrap_err(),
+            super::BackupError::CorruptedSnapshot
+        );
+
+        // an incremental snapshot is unpacked with the one it builds on
+        let mut incremental = manifest.clone();
+        incremental.base = Some("snapshot-1000.tar.zst".to_string());
+        incremental.files.clear();
+        let incremental_archive = out.path().join("snapshot-3000.tar.zst");
+        super::write_archive(staging.path(), &files, incremental, &incremental_archive)
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        assert_eq!(super::unpack_chain(&incremental_archive, tempfile::tempdir()?.path())?.len(), 2);
+
+        // listing reads the manifests, newest first
+        let listed = super::read_snapshots(out.path())?;
+        assert_eq!(
+            listed.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+            ["snapshot-3000.tar.zst", "snapshot-2000.tar.zst", "snapshot-1000.tar.zst"]
+        );
+
+        // pruning keeps the snapshots the kept ones build on
+        let settings = helpers::backups::BackupSettings::new(Some(out.path().to_path_buf()), 1, 7, None);
+        assert_eq!(super::prune_snapshots(&settings, 1).await?, ["snapshot-2000.tar.zst"]);
+        std::fs::remove_file(out.path().join("snapshot-1000.tar.zst"))?;
+        assert_eq!(
+            super::unpack_chain(&incremental_archive, tempfile::tempdir()?.path()).unwrap_err(),
+            super::BackupError::MissingBaseSnapshot
+        );
+        Ok(())
+    }
+}
@@ -1,84 +1,98 @@
+use helpers::{blob_listing::BlobSnapshot, downloads::DownloadManager, limits::OperationKind, errors::{fmt_error, Cause}};
+use helpers::stores::{NodeBlobs, NodeDocs};
 use iroh::{NodeAddr, NodeId};
 use iroh_blobs::{
-    net_protocol::Blobs,
-    rpc::client::blobs::{WrapOption, AddOutcome, BlobInfo, BlobStatus, DownloadOutcome, DownloadOptions},
+    get::db::DownloadProgress as BytesDownloadProgress,
+    hashseq::HashSeq,
+    rpc::client::blobs::{WrapOption, AddOutcome, BlobInfo, BlobStatus, DownloadMode, DownloadOutcome, DownloadOptions, DownloadProgress, ReadAtLen, Reader},
     rpc::client::tags::TagInfo,
-    store::fs::Store,
     util::{SetTagOption, Tag},
-    store::{ExportFormat, ExportMode},
-    Hash,
+    store::{ExportFormat, ExportMode, GcConfig, Store as _},
+    BlobFormat, Hash,
 };
-use std::{path::{Path, PathBuf}, sync::Arc, fmt};
+use std::{collections::{BTreeSet, HashMap}, path::{Path, PathBuf}, sync::Arc, fmt, time::Duration};
+use tokio::sync::{Notify, Semaphore};
 use anyhow::{Result, Context};
 use bytes::Bytes;
-use futures::{StreamExt, TryStreamExt};
+use futures::{Stream, StreamExt, TryStreamExt};
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
 use std::str::FromStr;
 
+// Prefix of the tags keeping the blobs of a running download until it is tagged as a whole
+const DOWNLOAD_TAG_PREFIX: &str = "download:";
+
 // Errors
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
 pub enum BlobError {
     // /// The specified blob was not found in the store.
     // BlobNotFound,
     /// The provided blob hash format is invalid or cannot be decoded.
     InvalidBlobHashFormat,
     /// Failed to add bytes as a blob.
-    FailedToAddBlobBytes,
+    FailedToAddBlobBytes(#[source] Cause),
     /// Failed to add named bytes as a blob.
-    FailedToAddNamedBlob,
+    FailedToAddNamedBlob(#[source] Cause),
     /// Failed to add a blob from the specified file path.
-    FailedToAddBlobFromPath,
+    FailedToAddBlobFromPath(#[source] Cause),
     /// Failed to canonicalize the provided file path.
-    FailedToCanonicalizePath,
+    FailedToCanonicalizePath(#[source] Cause),
     /// Failed to finish the blob add operation.
-    FailedToFinishBlobAdd,
+    FailedToFinishBlobAdd(#[source] Cause),
     /// Failed to list blobs from the store.
-    FailedToListBlobs,
+    FailedToListBlobs(#[source] Cause),
     /// Failed to collect blobs from the stream.
-    FailedToCollectBlobs,
+    FailedToCollectBlobs(#[source] Cause),
     /// Failed to read the blob content.
-    FailedToReadBlob,
+    FailedToReadBlob(#[source] Cause),
     /// Failed to get the status of the blob.
-    FailedToGetBlobStatus,
+    FailedToGetBlobStatus(#[source] Cause),
     /// Failed to check if the blob exists.
-    FailedToCheckBlobExistence,
+    FailedToCheckBlobExistence(#[source] Cause),
     /// Failed to initiate blob download.
-    FailedToInitiateDownload,
+    FailedToInitiateDownload(#[source] Cause),
     /// Failed to finish blob download.
-    FailedToFinishDownload,
+    FailedToFinishDownload(#[source] Cause),
     /// Failed to parse the node ID.
     InvalidNodeIdFormat,
     /// Failed to initiate hash sequence download.
-    FailedToInitiateHashSequenceDownload,
+    FailedToInitiateHashSequenceDownload(#[source] Cause),
     /// Failed to finish hash sequence download.
-    FailedToFinishHashSequenceDownload,
+    FailedToFinishHashSequenceDownload(#[source] Cause),
     /// Failed to initiate download with options.
-    FailedToInitiateDownloadWithOptions,
+    FailedToInitiateDownloadWithOptions(#[source] Cause),
     /// Failed to finish download with options.
-    FailedToFinishDownloadWithOptions,
+    FailedToFinishDownloadWithOptions(#[source] Cause),
     /// Failed to list tags.
-    FailedToListTags,
+    FailedToListTags(#[source] Cause),
     /// Failed to collect tags from the stream.
-    FailedToCollectTags,
+    FailedToCollectTags(#[source] Cause),
     /// Failed to delete the specified tag.
-    FailedToDeleteTag,
+    FailedToDeleteTag(#[source] Cause),
     /// Failed to export the blob to a file.
-    FailedToExportBlob,
+    FailedToExportBlob(#[source] Cause),
     /// Failed to finish the blob export operation.
-    FailedToFinishExportBlob,
+    FailedToFinishExportBlob(#[source] Cause),
+    /// The garbage collector did not finish its round.
+    FailedToCollectGarbage(#[source] Cause),
+    /// The blob is missing or only partially stored.
+    BlobNotComplete,
+    /// A download was started without any peer to fetch from.
+    NoDownloadPeers,
+    /// Failed to read the hash sequence of a collection being downloaded.
+    FailedToReadHashSequence(#[source] Cause),
+    /// A gateway path names no member of the collection.
+    PathNotInCollection,
     // /// The export destination path is invalid or cannot be canonicalized.
     // InvalidExportDestination,
 }
 
 impl fmt::Display for BlobError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        fmt_error(self, f)
     }
 }
 
-impl std::error::Error for BlobError {}
-
 
 /// Adds raw bytes as a blob.
 /// 
@@ -89,7 +103,7 @@ impl std::error::Error for BlobError {}
 /// # Returns
 /// * `AddOutcome` - Metadata about the added blob.
 pub async fn add_blob_bytes(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     bytes: impl Into<Bytes>,
 ) -> Result<AddOutcome, BlobError> {
     let blobs_client = blobs.client();
@@ -97,7 +111,7 @@ pub async fn add_blob_bytes(
     let outcome = blobs_client
         .add_bytes(bytes)
         .await
-        .map_err(|_| BlobError::FailedToAddBlobBytes)?;
+        .map_err(|e| BlobError::FailedToAddBlobBytes(Cause::new(e)))?;
 
     Ok(outcome)
 }
@@ -112,7 +126,7 @@ pub async fn add_blob_bytes(
 /// # Returns
 /// * `AddOutcome` - Metadata about the added blob.
 pub async fn add_blob_named(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     bytes: impl Into<Bytes>,
     name: impl Into<Tag>,
 ) -> Result<AddOutcome, BlobError> {
@@ -121,7 +135,7 @@ pub async fn add_blob_named(
     let outcome = blobs_client
         .add_bytes_named(bytes, name)
         .await
-        .map_err(|_| BlobError::FailedToAddNamedBlob)?;
+        .map_err(|e| BlobError::FailedToAddNamedBlob(Cause::new(e)))?;
 
     Ok(outcome)
 }
@@ -135,23 +149,24 @@ pub async fn add_blob_named(
 /// # Returns
 /// * `AddOutcome` - Metadata about the added blob.
 pub async fn add_blob_from_path(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     file_path: &Path
 ) -> Result<AddOutcome, BlobError> {
     let blobs_client = blobs.client();
     
-    let abs_path = std::fs::canonicalize(file_path)
-        .map_err(|_| BlobError::FailedToCanonicalizePath)?;
+    let abs_path = tokio::fs::canonicalize(file_path)
+        .await
+        .map_err(|e| BlobError::FailedToCanonicalizePath(Cause::new(e)))?;
     
     let add_progress = blobs_client
         .add_from_path(abs_path.clone(), false, SetTagOption::Auto, WrapOption::NoWrap)
         .await
-        .map_err(|_| BlobError::FailedToAddBlobFromPath)?;
+        .map_err(|e| BlobError::FailedToAddBlobFromPath(Cause::new(e)))?;
     
     let outcome = add_progress
         .finish()
         .await
-        .map_err(|_| BlobError::FailedToFinishBlobAdd)?;
+        .map_err(|e| BlobError::FailedToFinishBlobAdd(Cause::new(e)))?;
 
     Ok(outcome)
 }
@@ -160,33 +175,128 @@ pub async fn add_blob_from_path(
 ///
 /// # Arguments
 ///
-/// * `blobs` - An `Arc` pointing to the shared `Blobs<Store>` client.
+/// * `blobs` - An `Arc` pointing to the shared `NodeBlobs` client.
 /// * `page` - The page number to retrieve (zero-based).
 /// * `page_size` - The number of blobs to retrieve per page.
 ///
 /// # Returns
-/// * `Result<Vec<BlobInfo>>` - A vector of `BlobInfo` objects representing the blobs on the specified page.
+/// * `Result<Vec<BlobInfo>>` - A vector of `BlobInfo` objects representing the blobs on the specified page, sorted by hash.
 /// * `anyhow::Result` - An error if the operation fails.
 pub async fn list_blobs(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     page: usize,
     page_size: usize,
 ) -> Result<Vec<BlobInfo>, BlobError> {
-    let blobs_client = blobs.client();
-    
-    let stream = blobs_client
+    let (blobs, _) = list_blobs_with_total(blobs, page.saturating_mul(page_size), page_size).await?;
+    Ok(blobs)
+}
+
+/// Streams every blob in the store as it is read, for listings too large to collect at once.
+///
+/// # Arguments
+///
+/// * `blobs` - An `Arc` pointing to the shared `NodeBlobs` client.
+///
+/// # Returns
+///
+/// * `Stream` - The blobs of the store, each item failing if the store could not be read.
+pub async fn stream_blobs(
+    blobs: Arc<NodeBlobs>,
+) -> Result<impl Stream<Item = Result<BlobInfo, BlobError>> + Send + 'static, BlobError> {
+    let stream = blobs
+        .client()
         .list()
         .await
-        .map_err(|_| BlobError::FailedToListBlobs)?;
+        .map_err(|e| BlobError::FailedToListBlobs(Cause::new(e)))?;
 
-    let blobs: Vec<BlobInfo> = stream
-        .skip(page * page_size)
-        .take(page_size)
-        .try_collect::<Vec<_>>()
-        .await
-        .map_err(|_| BlobError::FailedToCollectBlobs)?;
+    Ok(stream.map_err(|e| BlobError::FailedToCollectBlobs(Cause::new(e))))
+}
 
-    Ok(blobs)
+/// List a window of blobs together with the total number of blobs in the store.
+///
+/// Blobs are sorted by hash. The first window is read from the store, the following ones from
+/// the node's `BlobListing`, so their cost does not grow with `offset`.
+///
+/// # Arguments
+///
+/// * `blobs` - An `Arc` pointing to the shared `NodeBlobs` client.
+/// * `offset` - The number of blobs to skip.
+/// * `limit` - The maximum number of blobs to return.
+///
+/// # Returns
+/// * `Result<(Vec<BlobInfo>, usize)>` - The blobs in the window and the total blob count.
+pub async fn list_blobs_with_total(
+    blobs: Arc<NodeBlobs>,
+    offset: usize,
+    limit: usize,
+) -> Result<(Vec<BlobInfo>, usize), BlobError> {
+    let snapshot = blob_snapshot(&blobs, offset == 0).await?;
+    let page = snapshot.page_at(offset, limit).iter().map(|(hash, size)| blob_info(*hash, *size)).collect();
+    Ok((page, snapshot.len()))
+}
+
+/// A page of blobs listed with `list_blobs_after`.
+#[derive(Debug)]
+pub struct BlobPage {
+    pub blobs: Vec<BlobInfo>,
+    /// The number of blobs in the store.
+    pub total: usize,
+    /// The hash to list the next page after, `None` on the last page.
+    pub next: Option<Hash>,
+}
+
+/// List up to `limit` blobs whose hash sorts after `after`, or from the first blob if `after` is
+/// `None`.
+///
+/// Pages are served from the node's `BlobListing`, so a page costs the
+/// same however deep it is. As the cursor is a hash and not a position, a listing skips or
+/// repeats no blob when blobs are added or deleted during it.
+///
+/// # Arguments
+///
+/// * `blobs` - An `Arc` pointing to the shared `NodeBlobs` client.
+/// * `after` - The `next` hash of the previous page.
+/// * `limit` - The maximum number of blobs to return.
+///
+/// # Returns
+/// * `Result<BlobPage>` - The blobs of the page, the total blob count and the cursor of the next page.
+pub async fn list_blobs_after(
+    blobs: Arc<NodeBlobs>,
+    after: Option<Hash>,
+    limit: usize,
+) -> Result<BlobPage, BlobError> {
+    let snapshot = blob_snapshot(&blobs, after.is_none()).await?;
+    let (page, more) = snapshot.page_after(after.as_ref(), limit);
+    let next = if more { page.last().map(|(hash, _)| *hash) } else { None };
+
+    Ok(BlobPage {
+        blobs: page.iter().map(|(hash, size)| blob_info(*hash, *size)).collect(),
+        total: snapshot.len(),
+        next,
+    })
+}
+
+// The blobs of the store through the node's `BlobListing`, read again if `fresh` is set
+async fn blob_snapshot(blobs: &NodeBlobs, fresh: bool) -> Result<BlobSnapshot, BlobError> {
+    let read = || async {
+        let stream = blobs
+            .client()
+            .list()
+            .await
+            .map_err(|e| BlobError::FailedToListBlobs(Cause::new(e)))?;
+        stream
+            .map_ok(|blob| (blob.hash, blob.size))
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(|e| BlobError::FailedToCollectBlobs(Cause::new(e)))
+    };
+
+    blobs.listing.snapshot(fresh, read).await
+}
+
+// The store lists blobs without a path
+fn blob_info(hash: Hash, size: u64) -> BlobInfo {
+    BlobInfo { path: String::new(), hash, size }
 }
 
 /// Reads a blob's content by hash and returns it as a UTF-8 string or base64-encoded string if binary.
@@ -198,18 +308,15 @@ pub async fn list_blobs(
 /// # Returns
 /// * `String` - UTF-8 content or base64-encoded blob data.
 pub async fn get_blob(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     hash: String,
 ) -> Result<String, BlobError> {
-    let blobs_client = blobs.client();
-
     let hash = Hash::from_str(&hash)
         .map_err(|_| BlobError::InvalidBlobHashFormat)?;
     
-    let blob_content = blobs_client
-        .read_to_bytes(hash)
+    let blob_content = read_blob_cached(&blobs, hash)
         .await
-        .map_err(|_| BlobError::FailedToReadBlob)?;
+        .map_err(|e| BlobError::FailedToReadBlob(Cause::new(e)))?;
 
     match String::from_utf8(blob_content.to_vec()) {
         Ok(utf8_string) => Ok(utf8_string),
@@ -220,6 +327,47 @@ pub async fn get_blob(
     }
 }
 
+/// Reads a blob's content through the node's `BlobCache`, keeping it there for the next read.
+///
+/// # Arguments
+/// * `blobs` - The Blobs client.
+/// * `hash` - The hash identifying the blob.
+///
+/// # Returns
+/// * `Bytes` - The blob content.
+pub async fn read_blob_cached(blobs: &NodeBlobs, hash: Hash) -> Result<Bytes> {
+    if let Some(content) = blobs.cache.get(&hash) {
+        return Ok(content);
+    }
+
+    let content = blobs.client().read_to_bytes(hash).await?;
+    blobs.cache.insert(hash, content.clone());
+    Ok(content)
+}
+
+/// Reads a blob's raw content by hash.
+/// 
+/// # Arguments
+/// * `blobs` - The Arc-wrapped Blobs client.
+/// * `hash` - The hash identifying the blob.
+/// 
+/// # Returns
+/// * `Bytes` - The blob content.
+pub async fn read_blob_bytes(
+    blobs: Arc<NodeBlobs>,
+    hash: String,
+) -> Result<Bytes, BlobError> {
+    let blobs_client = blobs.client();
+
+    let hash = Hash::from_str(&hash)
+        .map_err(|_| BlobError::InvalidBlobHashFormat)?;
+
+    blobs_client
+        .read_to_bytes(hash)
+        .await
+        .map_err(|e| BlobError::FailedToReadBlob(Cause::new(e)))
+}
+
 /// Gets the current status of a blob by its hash (e.g., NotFound, Partial, Complete).
 /// 
 /// # Arguments
@@ -229,7 +377,7 @@ pub async fn get_blob(
 /// # Returns
 /// * `String` - Blob status as a string.
 pub async fn status_blob(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     hash: String,
 ) -> Result<String, BlobError> {
     let blobs_client = blobs.client();
@@ -240,7 +388,7 @@ pub async fn status_blob(
     let blob_status = blobs_client
         .status(hash)
         .await
-        .map_err(|_| BlobError::FailedToGetBlobStatus)?;
+        .map_err(|e| BlobError::FailedToGetBlobStatus(Cause::new(e)))?;
 
     let status_string = match blob_status {
         BlobStatus::NotFound => "NotFound".to_string(),
@@ -260,7 +408,7 @@ pub async fn status_blob(
 /// # Returns
 /// * `bool` - True if blob exists, false otherwise.
 pub async fn has_blob(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     hash: String,
 ) -> Result<bool, BlobError> {
     let blobs_client = blobs.client();
@@ -271,7 +419,7 @@ pub async fn has_blob(
     let is_present = blobs_client
         .has(hash)
         .await
-        .map_err(|_| BlobError::FailedToCheckBlobExistence)?;
+        .map_err(|e| BlobError::FailedToCheckBlobExistence(Cause::new(e)))?;
 
     Ok(is_present)
 }
@@ -285,8 +433,12 @@ pub async fn has_blob(
 /// 
 /// # Returns
 /// * `DownloadOutcome` - Result of the download operation.
+///
+/// # Cancellation
+/// Dropping the returned future (for example when the HTTP request times out) only stops waiting
+/// for the transfer. Data already received stays in the store, so a later call resumes the download.
 pub async fn download_blob(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     hash: String,
     node_id: String,
 ) -> Result<DownloadOutcome, BlobError> {
@@ -303,17 +455,50 @@ pub async fn download_blob(
     let download_progress = blobs_client
         .download(hash, node_addr)
         .await
-        .map_err(|_| BlobError::FailedToInitiateDownload)?;
+        .map_err(|e| BlobError::FailedToInitiateDownload(Cause::new(e)))?;
 
     let download_outcome = download_progress
         .finish()
         .await
-        .map_err(|_| BlobError::FailedToFinishDownload)?;
+        .map_err(|e| BlobError::FailedToFinishDownload(Cause::new(e)))?;
 
     Ok(download_outcome)
 }
 
 
+/// Starts downloading a blob from a specified node and returns the progress stream.
+///
+/// Unlike `download_blob`, this does not wait for the download to finish, so callers can
+/// report intermediate progress to clients.
+///
+/// # Arguments
+/// * `blobs` - The Arc-wrapped Blobs client.
+/// * `hash` - The hash of the blob to download.
+/// * `node_id` - The node ID to download the blob from.
+///
+/// # Returns
+/// * `DownloadProgress` - A stream of progress events for the download.
+pub async fn download_blob_progress(
+    blobs: Arc<NodeBlobs>,
+    hash: String,
+    node_id: String,
+) -> Result<DownloadProgress, BlobError> {
+    let blobs_client = blobs.client();
+
+    let hash = Hash::from_str(&hash)
+        .map_err(|_| BlobError::InvalidBlobHashFormat)?;
+
+    let node_id = NodeId::from_str(&node_id)
+        .map_err(|_| BlobError::InvalidNodeIdFormat)?;
+
+    let download_progress = blobs_client
+        .download(hash, NodeAddr::from(node_id))
+        .await
+        .map_err(|e| BlobError::FailedToInitiateDownload(Cause::new(e)))?;
+
+    Ok(download_progress)
+}
+
 /// Downloads a sequence of hashes from a specified node.
 /// 
 /// # Arguments
@@ -323,8 +508,11 @@ pub async fn download_blob(
 /// 
 /// # Returns
 /// * `DownloadOutcome` - Result of the download operation.
+///
+/// # Cancellation
+/// Same as `download_blob`: dropping the future stops waiting, and received data is kept.
 pub async fn download_hash_sequence(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     hash: String,
     node_id: String,
 ) -> Result<DownloadOutcome, BlobError> {
@@ -341,12 +529,12 @@ pub async fn download_hash_sequence(
     let download_progress = blobs_client
         .download_hash_seq(hash, node_addr)
         .await
-        .map_err(|_| BlobError::FailedToInitiateHashSequenceDownload)?;
+        .map_err(|e| BlobError::FailedToInitiateHashSequenceDownload(Cause::new(e)))?;
 
     let download_outcome = download_progress
         .finish()
         .await
-        .map_err(|_| BlobError::FailedToFinishHashSequenceDownload)?;
+        .map_err(|e| BlobError::FailedToFinishHashSequenceDownload(Cause::new(e)))?;
 
     Ok(download_outcome)
 }
@@ -360,8 +548,11 @@ pub async fn download_hash_sequence(
 /// 
 /// # Returns
 /// * `DownloadOutcome` - Result of the download operation.
+///
+/// # Cancellation
+/// Same as `download_blob`: dropping the future stops waiting, and received data is kept.
 pub async fn download_with_options(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     hash: String,
     options: DownloadOptions,
 ) -> Result<DownloadOutcome, BlobError> {
@@ -373,16 +564,234 @@ pub async fn download_with_options(
     let download_progress = blobs_client
         .download_with_opts(hash, options)
         .await
-        .map_err(|_| BlobError::FailedToInitiateDownloadWithOptions)?;
+        .map_err(|e| BlobError::FailedToInitiateDownloadWithOptions(Cause::new(e)))?;
 
     let download_outcome = download_progress
         .finish()
         .await
-        .map_err(|_| BlobError::FailedToFinishDownloadWithOptions)?;
+        .map_err(|e| BlobError::FailedToFinishDownloadWithOptions(Cause::new(e)))?;
 
     Ok(download_outcome)
 }
 
+/// Starts downloading `hash` from `nodes` in the background and returns the ID its progress is
+/// recorded under in `downloads`.
+///
+/// The children of a collection (`HashSeq`) are spread across the nodes, each node serving at
+/// most `downloads.connections_per_peer()` of them at once, and a child a node fails to serve is
+/// asked of the next one. A raw blob is fetched whole from the first node that serves it, as iroh
+/// does not split a single blob across peers.
+///
+/// # Arguments
+/// * `blobs` - The Arc-wrapped Blobs client.
+/// * `downloads` - The manager recording the progress of the download.
+/// * `hash` - The hash of the blob or collection to download.
+/// * `format` - Whether `hash` is a raw blob or a collection.
+/// * `nodes` - The node IDs to download from.
+/// * `tag` - The tag to keep the downloaded content with.
+///
+/// # Returns
+/// * `String` - The ID of the download.
+pub fn start_download(
+    blobs: Arc<NodeBlobs>,
+    downloads: DownloadManager,
+    hash: String,
+    format: BlobFormat,
+    nodes: Vec<String>,
+    tag: SetTagOption,
+) -> Result<String, BlobError> {
+    let hash = Hash::from_str(&hash)
+        .map_err(|_| BlobError::InvalidBlobHashFormat)?;
+
+    let nodes = nodes
+        .iter()
+        .map(|node_id| NodeId::from_str(node_id.trim()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| BlobError::InvalidNodeIdFormat)?;
+    if nodes.is_empty() {
+        return Err(BlobError::NoDownloadPeers);
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let peers: Vec<String> = nodes.iter().map(|node_id| node_id.to_string()).collect();
+    downloads.start(id.clone(), hash.to_string(), format!("{:?}", format), &peers);
+
+    let download_id = id.clone();
+    tokio::spawn(async move {
+        let permit = downloads.limits().acquire(OperationKind::Download).await;
+        let result = run_download(&blobs, &downloads, &download_id, hash, format, &nodes, tag).await;
+        drop(permit);
+        if let Err(e) = delete_download_tags(&blobs, &download_id).await {
+            tracing::warn!(download_id, error = %e, "failed to delete the temporary tags of a download");
+        }
+        match &result {
+            Ok(()) => tracing::info!(download_id, %hash, "download finished"),
+            Err(e) => tracing::warn!(download_id, %hash, error = %e, "download failed"),
+        }
+        downloads.finish(&download_id, result.err().map(|e| e.to_string()));
+    });
+
+    Ok(id)
+}
+
+fn download_tag(download_id: &str, name: &str) -> SetTagOption {
+    SetTagOption::Named(Tag::from(format!("{}{}:{}", DOWNLOAD_TAG_PREFIX, download_id, name)))
+}
+
+async fn run_download(
+    blobs: &Arc<NodeBlobs>,
+    downloads: &DownloadManager,
+    download_id: &str,
+    hash: Hash,
+    format: BlobFormat,
+    nodes: &[NodeId],
+    tag: SetTagOption,
+) -> Result<(), BlobError> {
+    let limits: Vec<Arc<Semaphore>> = nodes
+        .iter()
+        .map(|_| Arc::new(Semaphore::new(downloads.connections_per_peer())))
+        .collect();
+
+    if format == BlobFormat::Raw {
+        return fetch_from_any(blobs, downloads, download_id, hash, nodes, &limits, 0, tag).await;
+    }
+
+    // The hash sequence first, to know the children to spread across the nodes
+    fetch_from_any(blobs, downloads, download_id, hash, nodes, &limits, 0, download_tag(download_id, "root")).await?;
+    let hash_seq = blobs
+        .client()
+        .read_to_bytes(hash)
+        .await
+        .map_err(|e| BlobError::FailedToReadBlob(Cause::new(e)))?;
+    let children: Vec<Hash> = HashSeq::try_from(hash_seq)
+        .map_err(|e| BlobError::FailedToReadHashSequence(Cause::new(e)))?
+        .into_iter()
+        .collect();
+    downloads.set_blobs_total(download_id, children.len() as u64 + 1);
+
+    futures::stream::iter(children.into_iter().enumerate())
+        .map(|(index, child)| {
+            let tag = download_tag(download_id, &index.to_string());
+            fetch_from_any(blobs, downloads, download_id, child, nodes, &limits, index, tag)
+        })
+        .buffer_unordered(nodes.len() * downloads.connections_per_peer())
+        .try_collect::<()>()
+        .await?;
+
+    // Every blob is in the store by now, so this only tags the collection as a whole
+    let options = DownloadOptions {
+        format: BlobFormat::HashSeq,
+        nodes: nodes.iter().copied().map(NodeAddr::from).collect(),
+        tag,
+        mode: DownloadMode::Direct,
+    };
+    blobs
+        .client()
+        .download_with_opts(hash, options)
+        .await
+        .map_err(|e| BlobError::FailedToInitiateDownload(Cause::new(e)))?
+        .finish()
+        .await
+        .map_err(|e| BlobError::FailedToFinishDownload(Cause::new(e)))?;
+    Ok(())
+}
+
+// Fetches `hash` from the nodes in turn, starting with the one at `start`, within the limit of
+// blobs fetched at once from each node
+#[allow(clippy::too_many_arguments)]
+async fn fetch_from_any(
+    blobs: &Arc<NodeBlobs>,
+    downloads: &DownloadManager,
+    download_id: &str,
+    hash: Hash,
+    nodes: &[NodeId],
+    limits: &[Arc<Semaphore>],
+    start: usize,
+    tag: SetTagOption,
+) -> Result<(), BlobError> {
+    let mut last_error = BlobError::NoDownloadPeers;
+    for attempt in 0..nodes.len() {
+        let index = (start + attempt) % nodes.len();
+        let peer = nodes[index].to_string();
+        let _permit = limits[index]
+            .acquire()
+            .await
+            .map_err(|e| BlobError::FailedToInitiateDownload(Cause::new(e)))?;
+        let options = DownloadOptions {
+            format: BlobFormat::Raw,
+            nodes: vec![NodeAddr::from(nodes[index])],
+            tag: tag.clone(),
+            mode: DownloadMode::Direct,
+        };
+        match fetch(blobs, downloads, download_id, &peer, hash, options).await {
+            Ok(received) => {
+                downloads.record_blob_done(download_id, (received > 0).then_some(peer.as_str()));
+                return Ok(());
+            }
+            Err(e) => {
+                tracing::debug!(download_id, %hash, peer, error = %e, "peer failed to serve a blob");
+                downloads.record_peer_failure(download_id, &peer);
+                last_error = e;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+// Downloads `hash` with `options`, recording its progress. The number of bytes received.
+async fn fetch(
+    blobs: &Arc<NodeBlobs>,
+    downloads: &DownloadManager,
+    download_id: &str,
+    peer: &str,
+    hash: Hash,
+    options: DownloadOptions,
+) -> Result<u64, BlobError> {
+    let mut progress = blobs
+        .client()
+        .download_with_opts(hash, options)
+        .await
+        .map_err(|e| BlobError::FailedToInitiateDownload(Cause::new(e)))?;
+
+    // offsets reached by each blob of the transfer, to count the bytes between two events
+    let mut offsets: HashMap<u64, u64> = HashMap::new();
+    let mut received = 0;
+    while let Some(event) = progress.next().await {
+        match event.map_err(|e| BlobError::FailedToFinishDownload(Cause::new(e)))? {
+            BytesDownloadProgress::FoundLocal { size, .. } => downloads.record_found_local(download_id, size.value()),
+            BytesDownloadProgress::Found { id, size, .. } => {
+                downloads.record_found(download_id, size);
+                offsets.insert(id, 0);
+            }
+            BytesDownloadProgress::Progress { id, offset } => {
+                let bytes = offset.saturating_sub(offsets.insert(id, offset).unwrap_or(0));
+                downloads.record_bytes(download_id, peer, bytes);
+                received += bytes;
+            }
+            BytesDownloadProgress::AllDone(_) => return Ok(received),
+            BytesDownloadProgress::Abort(e) => return Err(BlobError::FailedToFinishDownload(Cause::new(e))),
+            _ => {}
+        }
+    }
+    Err(BlobError::FailedToFinishDownload(Cause::new("the download ended before it finished")))
+}
+
+// Deletes the tags that kept the blobs of a download while it ran
+async fn delete_download_tags(blobs: &Arc<NodeBlobs>, download_id: &str) -> Result<(), BlobError> {
+    let prefix = format!("{}{}:", DOWNLOAD_TAG_PREFIX, download_id);
+    for tag in list_tags(blobs.clone()).await? {
+        if tag.name.0.starts_with(prefix.as_bytes()) {
+            blobs
+                .client()
+                .tags()
+                .delete(tag.name)
+                .await
+                .map_err(|e| BlobError::FailedToDeleteTag(Cause::new(e)))?;
+        }
+    }
+    Ok(())
+}
+
 /// Lists all available tags.
 /// 
 /// # Arguments
@@ -391,7 +800,7 @@ pub async fn download_with_options(
 /// # Returns
 /// * `Vec<TagInfo>` - A list of tag metadata.
 pub async fn list_tags(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
 ) -> Result<Vec<TagInfo>, BlobError> {
     let blobs_client = blobs.client();
 
@@ -400,12 +809,12 @@ pub async fn list_tags(
     let stream = tag_client
         .list()
         .await
-        .map_err(|_| BlobError::FailedToListTags)?;
+        .map_err(|e| BlobError::FailedToListTags(Cause::new(e)))?;
 
     let tags: Vec<TagInfo> = stream
         .try_collect::<Vec<_>>()
         .await
-        .map_err(|_| BlobError::FailedToCollectTags)?;
+        .map_err(|e| BlobError::FailedToCollectTags(Cause::new(e)))?;
 
     Ok(tags)
 }
@@ -419,7 +828,7 @@ pub async fn list_tags(
 /// # Returns
 /// * `()` - Empty result on success.
 pub async fn delete_tag(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     tag_name: impl AsRef<[u8]>,
 ) -> Result<(), BlobError> {
     let blobs_client = blobs.client();
@@ -428,14 +837,84 @@ pub async fn delete_tag(
 
     let tag = Tag(Bytes::copy_from_slice(tag_name.as_ref()));
 
+    // The store may garbage collect the blob once untagged, so it leaves the cache too
+    let tagged = match blobs.cache.is_enabled() {
+        true => list_tags(blobs.clone()).await?.into_iter().find(|info| info.name == tag),
+        false => None,
+    };
+
     tag_client
         .delete(tag.clone())
         .await
-        .map_err(|_| BlobError::FailedToDeleteTag)?;
+        .map_err(|e| BlobError::FailedToDeleteTag(Cause::new(e)))?;
+
+    if let Some(info) = tagged {
+        let cache = &blobs.cache;
+        cache.remove(&info.hash);
+        if info.format == BlobFormat::HashSeq {
+            let hash_seq = blobs_client.read_to_bytes(info.hash).await.ok();
+            for child in hash_seq.and_then(|hash_seq| HashSeq::try_from(hash_seq).ok()).into_iter().flatten() {
+                cache.remove(&child);
+            }
+        }
+    }
 
     Ok(())
 }
 
+/// Deletes the blobs that no tag, document entry or running operation holds, as one round of the
+/// iroh-blobs garbage collector.
+///
+/// # Arguments
+/// * `docs` - The Arc-wrapped Docs client, whose entries keep their content.
+/// * `blobs` - The Arc-wrapped Blobs client.
+///
+/// # Returns
+/// * `usize` - The number of blobs deleted.
+pub async fn collect_garbage(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+) -> Result<usize, BlobError> {
+    let before = blob_snapshot(&blobs, true).await?;
+
+    // the collector runs rounds until it is dropped, so it is dropped once the first one is done
+    let protect = docs.protect_cb();
+    let store = blobs.store().clone();
+    let done = Arc::new(Notify::new());
+    let round_done = done.clone();
+    blobs
+        .rt()
+        .spawn(move || async move {
+            let config = GcConfig {
+                period: Duration::ZERO,
+                done_callback: Some(Box::new(move || round_done.notify_one())),
+            };
+            let protected = || async {
+                let mut live = BTreeSet::new();
+                protect(&mut live).await;
+                live
+            };
+            tokio::select! {
+                _ = store.gc_run(config, protected) => {},
+                _ = done.notified() => {},
+            }
+        })
+        .await
+        .map_err(|e| BlobError::FailedToCollectGarbage(Cause::new(e)))?;
+
+    // deleted blobs can no longer be served from the cache
+    let after = blob_snapshot(&blobs, true).await?;
+    let kept: BTreeSet<Hash> = after.page_at(0, after.len()).iter().map(|(hash, _)| *hash).collect();
+    let mut deleted = 0;
+    for (hash, _) in before.page_at(0, before.len()) {
+        if !kept.contains(hash) {
+            blobs.cache.remove(hash);
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
 /// Exports a blob to a file on disk.
 /// 
 /// # Arguments
@@ -446,7 +925,7 @@ pub async fn delete_tag(
 /// # Returns
 /// * `()` - Empty result on success.
 pub async fn export_blob_to_file(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     hash: String,
     destination: PathBuf,
 ) -> Result<(), BlobError> {
@@ -458,14 +937,162 @@ pub async fn export_blob_to_file(
     blobs_client
         .export(hash, destination.clone() , ExportFormat::Blob, ExportMode::Copy)
         .await
-        .map_err(|_| BlobError::FailedToExportBlob)?
+        .map_err(|e| BlobError::FailedToExportBlob(Cause::new(e)))?
         .finish()
         .await
-        .map_err(|_| BlobError::FailedToFinishExportBlob)?;
+        .map_err(|e| BlobError::FailedToFinishExportBlob(Cause::new(e)))?;
 
     Ok(())
 }
 
+/// Opens a streaming reader over a complete blob, so it can be sent without buffering it in memory.
+/// 
+/// # Arguments
+/// * `blobs` - The Arc-wrapped Blobs client.
+/// * `hash` - The hash of the blob to read.
+/// 
+/// # Returns
+/// * `(Hash, Reader)` - The parsed hash and a reader that yields the blob's bytes.
+pub async fn read_blob_stream(
+    blobs: Arc<NodeBlobs>,
+    hash: String,
+) -> Result<(Hash, Reader), BlobError> {
+    let blobs_client = blobs.client();
+
+    let hash = Hash::from_str(&hash)
+        .map_err(|_| BlobError::InvalidBlobHashFormat)?;
+
+    let status = blobs_client
+        .status(hash)
+        .await
+        .map_err(|e| BlobError::FailedToGetBlobStatus(Cause::new(e)))?;
+    if !matches!(status, BlobStatus::Complete { .. }) {
+        return Err(BlobError::BlobNotComplete);
+    }
+
+    let reader = blobs_client
+        .read(hash)
+        .await
+        .map_err(|e| BlobError::FailedToReadBlob(Cause::new(e)))?;
+
+    Ok((hash, reader))
+}
+
+/// Returns the size of a complete blob.
+///
+/// # Arguments
+/// * `blobs` - The Arc-wrapped Blobs client.
+/// * `hash` - The hash of the blob.
+///
+/// # Returns
+/// * `(Hash, u64)` - The parsed hash and the size of the blob in bytes.
+pub async fn complete_blob_size(
+    blobs: Arc<NodeBlobs>,
+    hash: String,
+) -> Result<(Hash, u64), BlobError> {
+    let hash = Hash::from_str(&hash)
+        .map_err(|_| BlobError::InvalidBlobHashFormat)?;
+
+    match blobs
+        .client()
+        .status(hash)
+        .await
+        .map_err(|e| BlobError::FailedToGetBlobStatus(Cause::new(e)))?
+    {
+        BlobStatus::Complete { size } => Ok((hash, size)),
+        _ => Err(BlobError::BlobNotComplete),
+    }
+}
+
+/// Opens a streaming reader over `len` bytes of a blob from `offset`, so a range of it can be sent
+/// without reading the rest of the blob.
+///
+/// # Arguments
+/// * `blobs` - The Arc-wrapped Blobs client.
+/// * `hash` - The hash of the blob to read.
+/// * `offset` - The first byte to read.
+/// * `len` - The number of bytes to read.
+///
+/// # Returns
+/// * `Reader` - A reader that yields the bytes of the range.
+pub async fn read_blob_range(
+    blobs: Arc<NodeBlobs>,
+    hash: Hash,
+    offset: u64,
+    len: u64,
+) -> Result<Reader, BlobError> {
+    blobs
+        .client()
+        .read_at(hash, offset, ReadAtLen::Exact(len))
+        .await
+        .map_err(|e| BlobError::FailedToReadBlob(Cause::new(e)))
+}
+
+// Largest root blob read as a collection, 32 bytes per member, so larger blobs are served as they are
+const MAX_COLLECTION_ROOT: u64 = 32 * 65_536;
+
+/// What a gateway path `/<hash>/<path>` points to, see `resolve_gateway_path`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GatewayTarget {
+    /// A complete blob, with its name in the collection it was reached through, if any.
+    Blob { hash: Hash, size: u64, name: Option<String> },
+    /// The members of a collection whose names start with the path, named relative to it.
+    Directory(Vec<(String, Hash)>),
+}
+
+/// Resolves a path under a hash the way content-addressed gateways do: without a path, the blob
+/// itself, or for a collection its `index.html` or the list of its members. With a path, the
+/// member of the collection named by it, its `index.html`, or the members under it.
+///
+/// # Arguments
+/// * `blobs` - The Arc-wrapped Blobs client.
+/// * `hash` - The hash of the blob or collection.
+/// * `path` - The `/`-separated name of a member, empty for the root.
+///
+/// # Returns
+/// * `GatewayTarget` - The blob or directory to serve.
+pub async fn resolve_gateway_path(
+    blobs: Arc<NodeBlobs>,
+    hash: String,
+    path: &str,
+) -> Result<GatewayTarget, BlobError> {
+    let (hash, size) = complete_blob_size(blobs.clone(), hash).await?;
+    let path = path.trim_matches('/');
+
+    // a hash sequence whose first member is the collection's names
+    let collection = if size > 0 && size <= MAX_COLLECTION_ROOT && size % 32 == 0 {
+        blobs.client().get_collection(hash).await.ok()
+    } else {
+        None
+    };
+    let Some(collection) = collection else {
+        return match path.is_empty() {
+            true => Ok(GatewayTarget::Blob { hash, size, name: None }),
+            false => Err(BlobError::PathNotInCollection),
+        };
+    };
+
+    let dir = if path.is_empty() { String::new() } else { format!("{}/", path) };
+    let index = format!("{}index.html", dir);
+    let member = collection
+        .iter()
+        .find(|(name, _)| *name == path)
+        .or_else(|| collection.iter().find(|(name, _)| *name == index));
+    if let Some((name, member)) = member {
+        let (member, size) = complete_blob_size(blobs, member.to_hex()).await?;
+        return Ok(GatewayTarget::Blob { hash: member, size, name: Some(name.clone()) });
+    }
+
+    let members: Vec<(String, Hash)> = collection
+        .iter()
+        .filter_map(|(name, member)| name.strip_prefix(&dir).map(|name| (name.to_string(), *member)))
+        .collect();
+    if members.is_empty() {
+        return Err(BlobError::PathNotInCollection);
+    }
+    Ok(GatewayTarget::Directory(members))
+}
+
 // delete_blob
 // do we need this?
 
@@ -502,8 +1129,6 @@ mod tests {
             fs::remove_dir_all("Test").await?;
         }
 
-        sleep(Duration::from_secs(2)).await;
-
         fs::create_dir_all("Test").await?;
 
         let args = CliArgs {
@@ -512,10 +1137,74 @@ mod tests {
             bootstrap: true,
             suri: Some("0xe5be9a5092b81bca64be81d212e7f2f9eba183bb7a90954f7b76361f6edb5c0a".to_string()), // don't use this suri in production, it is a preloaded suri for testing(for //Alice)
             secret: Some("test-secret".to_string()), // remove this secret key
+            pkcs11_module: None,
+            pkcs11_slot: None,
+            pkcs11_pin: None,
+            approval_admin: vec![],
+            approval_threshold: None,
+            grpc_listen: None,
+            compression_min_size: 1024,
+            listen: vec![],
+            listen_uds: vec![],
+            admin_listen: None,
+            s3_listen: None,
+            webdav_listen: None,
+            read_timeout: 10,
+            write_timeout: 30,
+            download_timeout: 600,
+            download_connections: 4,
+            max_concurrent_imports: 4,
+            max_concurrent_exports: 16,
+            max_concurrent_downloads: 8,
+            max_concurrent_scans: 32,
+            relay: vec![],
+            chain_event: vec![],
+            proof_batch_interval: 600,
+            require_chain_authorization: false,
+            authorization_cache_ttl: 300,
+            chain_backend: helpers::cli::ChainBackend::Cord,
+            read_only: false,
+            public_gw: false,
+            require_api_key: false,
+            access_log: false,
+            access_log_doc: Vec::new(),
+            access_log_sample_rate: 1.0,
+            debug_errors: false,
+            doc_cache_size: 256,
+            doc_cache_idle: 300,
+            blob_cache_size: 64 * 1024 * 1024,
+            blob_cache_max_blob: 1024 * 1024,
+            index_field: vec![],
+            search: false,
+            schema_registry_doc: None,
+            schema_ref_url: vec![],
+            trash_retention: 2_592_000,
+            version_retention: 7_776_000,
+            smtp_relay: None,
+            smtp_from: "starter-kit@localhost".to_string(),
+            job: Vec::new(),
+            public_listen: None,
+            public_doc: Vec::new(),
+            public_blob: Vec::new(),
+            public_rate_limit: 120,
+            backup_dir: None,
+            backup_interval: None,
+            backup_keep: 7,
+            backup_full_every: 7,
+            backup_s3_url: None,
+            backup_s3_region: "us-east-1".to_string(),
+            backup_s3_access_key: None,
+            backup_s3_secret_key: None,
+            federation_catalog: None,
+            federation_trusted_author: vec![],
+            federation_interval: 60,
+            config: None,
+            profile: None,
         };
         let iroh_node: IrohNode = setup_iroh_node(args).await.or_else(|_| {
             Err(anyhow!("Failed to set up Iroh node"))
         })?;
+        iroh_node.wait_ready().await?;
         println!("Iroh node started!");
         println!("Your NodeId: {}", iroh_node.node_id);
         Ok(iroh_node)
@@ -592,7 +1281,7 @@ mod tests {
 
         let result = add_blob_from_path(blobs, invalid_path).await;
 
-        assert!(matches!(result, Err(BlobError::FailedToCanonicalizePath)));
+        assert!(matches!(result, Err(BlobError::FailedToCanonicalizePath(_))));
 
         fs::remove_dir_all("Test/test_blobs").await?;
         fs::remove_dir_all("Test").await?;
@@ -618,9 +1307,16 @@ mod tests {
 
         // List blobs
         let blobs_list = list_blobs(blobs.clone(), page, page_size).await?;
-        assert_eq!(blobs_list.len(), 2);
-        assert_eq!(blobs_list[0].hash, outcome_1.hash);
-        assert_eq!(blobs_list[1].hash, outcome_2.hash);
+        let mut expected = vec![outcome_1.hash, outcome_2.hash];
+        expected.sort();
+        assert_eq!(blobs_list.iter().map(|blob| blob.hash).collect::<Vec<_>>(), expected);
+
+        // Page after the first blob
+        let page = list_blobs_after(blobs.clone(), Some(expected[0]), page_size).await?;
+        assert_eq!(page.total, 2);
+        assert_eq!(page.blobs.len(), 1);
+        assert_eq!(page.blobs[0].hash, expected[1]);
+        assert_eq!(page.next, None);
 
         fs::remove_dir_all("Test/test_blobs").await?;
         fs::remove_dir_all("Test").await?;
@@ -642,7 +1338,7 @@ mod tests {
 
         let result = list_blobs(blobs.clone(), 0, 10).await;
 
-        assert!(matches!(result, Err(BlobError::FailedToCollectBlobs)));
+        assert!(matches!(result, Err(BlobError::FailedToCollectBlobs(_))));
 
         // Clean up
         fs::remove_dir_all("Test/test_blobs").await?;
@@ -735,10 +1431,74 @@ mod tests {
             bootstrap: true,
             suri: Some("0xe5be9a5092b81bca64be81d212e7f2f9eba183bb7a90954f7b76361f6edb5c0a".to_string()),
             secret: Some("test-secret-2".to_string()), // remove this secret key
+            pkcs11_module: None,
+            pkcs11_slot: None,
+            pkcs11_pin: None,
+            approval_admin: vec![],
+            approval_threshold: None,
+            grpc_listen: None,
+            compression_min_size: 1024,
+            listen: vec![],
+            listen_uds: vec![],
+            admin_listen: None,
+            s3_listen: None,
+            webdav_listen: None,
+            read_timeout: 10,
+            write_timeout: 30,
+            download_timeout: 600,
+            download_connections: 4,
+            max_concurrent_imports: 4,
+            max_concurrent_exports: 16,
+            max_concurrent_downloads: 8,
+            max_concurrent_scans: 32,
+            relay: vec![],
+            chain_event: vec![],
+            proof_batch_interval: 600,
+            require_chain_authorization: false,
+            authorization_cache_ttl: 300,
+            chain_backend: helpers::cli::ChainBackend::Cord,
+            read_only: false,
+            public_gw: false,
+            require_api_key: false,
+            access_log: false,
+            access_log_doc: Vec::new(),
+            access_log_sample_rate: 1.0,
+            debug_errors: false,
+            doc_cache_size: 256,
+            doc_cache_idle: 300,
+            blob_cache_size: 64 * 1024 * 1024,
+            blob_cache_max_blob: 1024 * 1024,
+            index_field: vec![],
+            search: false,
+            schema_registry_doc: None,
+            schema_ref_url: vec![],
+            trash_retention: 2_592_000,
+            version_retention: 7_776_000,
+            smtp_relay: None,
+            smtp_from: "starter-kit@localhost".to_string(),
+            job: Vec::new(),
+            public_listen: None,
+            public_doc: Vec::new(),
+            public_blob: Vec::new(),
+            public_rate_limit: 120,
+            backup_dir: None,
+            backup_interval: None,
+            backup_keep: 7,
+            backup_full_every: 7,
+            backup_s3_url: None,
+            backup_s3_region: "us-east-1".to_string(),
+            backup_s3_access_key: None,
+            backup_s3_secret_key: None,
+            federation_catalog: None,
+            federation_trusted_author: vec![],
+            federation_interval: 60,
+            config: None,
+            profile: None,
         };
         let iroh_node_2: IrohNode = setup_iroh_node(args).await.or_else(|_| {
             Err(anyhow!("Failed to set up Iroh node"))
         })?;
+        iroh_node_2.wait_ready().await?;
 
         let dir = tempdir()?;
         let file_path = dir.path().join("large_file.txt");
@@ -854,10 +1614,74 @@ mod tests {
             bootstrap: true,
             suri: Some("//Alice".to_string()),
             secret: secret_key_2.clone(), // remove this secret key
+            pkcs11_module: None,
+            pkcs11_slot: None,
+            pkcs11_pin: None,
+            approval_admin: vec![],
+            approval_threshold: None,
+            grpc_listen: None,
+            compression_min_size: 1024,
+            listen: vec![],
+            listen_uds: vec![],
+            admin_listen: None,
+            s3_listen: None,
+            webdav_listen: None,
+            read_timeout: 10,
+            write_timeout: 30,
+            download_timeout: 600,
+            download_connections: 4,
+            max_concurrent_imports: 4,
+            max_concurrent_exports: 16,
+            max_concurrent_downloads: 8,
+            max_concurrent_scans: 32,
+            relay: vec![],
+            chain_event: vec![],
+            proof_batch_interval: 600,
+            require_chain_authorization: false,
+            authorization_cache_ttl: 300,
+            chain_backend: helpers::cli::ChainBackend::Cord,
+            read_only: false,
+            public_gw: false,
+            require_api_key: false,
+            access_log: false,
+            access_log_doc: Vec::new(),
+            access_log_sample_rate: 1.0,
+            debug_errors: false,
+            doc_cache_size: 256,
+            doc_cache_idle: 300,
+            blob_cache_size: 64 * 1024 * 1024,
+            blob_cache_max_blob: 1024 * 1024,
+            index_field: vec![],
+            search: false,
+            schema_registry_doc: None,
+            schema_ref_url: vec![],
+            trash_retention: 2_592_000,
+            version_retention: 7_776_000,
+            smtp_relay: None,
+            smtp_from: "starter-kit@localhost".to_string(),
+            job: Vec::new(),
+            public_listen: None,
+            public_doc: Vec::new(),
+            public_blob: Vec::new(),
+            public_rate_limit: 120,
+            backup_dir: None,
+            backup_interval: None,
+            backup_keep: 7,
+            backup_full_every: 7,
+            backup_s3_url: None,
+            backup_s3_region: "us-east-1".to_string(),
+            backup_s3_access_key: None,
+            backup_s3_secret_key: None,
+            federation_catalog: None,
+            federation_trusted_author: vec![],
+            federation_interval: 60,
+            config: None,
+            profile: None,
         };
         let iroh_node_2: IrohNode = setup_iroh_node(args).await.or_else(|_| {
             Err(anyhow!("Failed to set up Iroh node"))
         })?;
+        iroh_node_2.wait_ready().await?;
         
         let _ = download_blob(iroh_node_2.blobs.clone(), outcome.hash.to_string(), node_id).await?;
         let get_blob = get_blob(iroh_node_2.blobs.clone(), outcome.hash.to_string()).await?;
@@ -1006,6 +1830,69 @@ mod tests {
             bootstrap: true,
             suri: Some("//Alice".to_string()),
             secret: secret_key_2.clone(), // remove this secret key
+            pkcs11_module: None,
+            pkcs11_slot: None,
+            pkcs11_pin: None,
+            approval_admin: vec![],
+            approval_threshold: None,
+            grpc_listen: None,
+            compression_min_size: 1024,
+            listen: vec![],
+            listen_uds: vec![],
+            admin_listen: None,
+            s3_listen: None,
+            webdav_listen: None,
+            read_timeout: 10,
+            write_timeout: 30,
+            download_timeout: 600,
+            download_connections: 4,
+            max_concurrent_imports: 4,
+            max_concurrent_exports: 16,
+            max_concurrent_downloads: 8,
+            max_concurrent_scans: 32,
+            relay: vec![],
+            chain_event: vec![],
+            proof_batch_interval: 600,
+            require_chain_authorization: false,
+            authorization_cache_ttl: 300,
+            chain_backend: helpers::cli::ChainBackend::Cord,
+            read_only: false,
+            public_gw: false,
+            require_api_key: false,
+            access_log: false,
+            access_log_doc: Vec::new(),
+            access_log_sample_rate: 1.0,
+            debug_errors: false,
+            doc_cache_size: 256,
+            doc_cache_idle: 300,
+            blob_cache_size: 64 * 1024 * 1024,
+            blob_cache_max_blob: 1024 * 1024,
+            index_field: vec![],
+            search: false,
+            schema_registry_doc: None,
+            schema_ref_url: vec![],
+            trash_retention: 2_592_000,
+            version_retention: 7_776_000,
+            smtp_relay: None,
+            smtp_from: "starter-kit@localhost".to_string(),
+            job: Vec::new(),
+            public_listen: None,
+            public_doc: Vec::new(),
+            public_blob: Vec::new(),
+            public_rate_limit: 120,
+            backup_dir: None,
+            backup_interval: None,
+            backup_keep: 7,
+            backup_full_every: 7,
+            backup_s3_url: None,
+            backup_s3_region: "us-east-1".to_string(),
+            backup_s3_access_key: None,
+            backup_s3_secret_key: None,
+            federation_catalog: None,
+            federation_trusted_author: vec![],
+            federation_interval: 60,
+            config: None,
+            profile: None,
         };
         let iroh_node_2: IrohNode = setup_iroh_node(args_2).await.or_else(|_| {
             Err(anyhow!("Failed to set up Iroh node 2"))
@@ -1024,6 +1911,69 @@ mod tests {
             bootstrap: true,
             suri: Some("//Alice".to_string()),
             secret: secret_key_2.clone(), // remove this secret key
+            pkcs11_module: None,
+            pkcs11_slot: None,
+            pkcs11_pin: None,
+            approval_admin: vec![],
+            approval_threshold: None,
+            grpc_listen: None,
+            compression_min_size: 1024,
+            listen: vec![],
+            listen_uds: vec![],
+            admin_listen: None,
+            s3_listen: None,
+            webdav_listen: None,
+            read_timeout: 10,
+            write_timeout: 30,
+            download_timeout: 600,
+            download_connections: 4,
+            max_concurrent_imports: 4,
+            max_concurrent_exports: 16,
+            max_concurrent_downloads: 8,
+            max_concurrent_scans: 32,
+            relay: vec![],
+            chain_event: vec![],
+            proof_batch_interval: 600,
+            require_chain_authorization: false,
+            authorization_cache_ttl: 300,
+            chain_backend: helpers::cli::ChainBackend::Cord,
+            read_only: false,
+            public_gw: false,
+            require_api_key: false,
+            access_log: false,
+            access_log_doc: Vec::new(),
+            access_log_sample_rate: 1.0,
+            debug_errors: false,
+            doc_cache_size: 256,
+            doc_cache_idle: 300,
+            blob_cache_size: 64 * 1024 * 1024,
+            blob_cache_max_blob: 1024 * 1024,
+            index_field: vec![],
+            search: false,
+            schema_registry_doc: None,
+            schema_ref_url: vec![],
+            trash_retention: 2_592_000,
+            version_retention: 7_776_000,
+            smtp_relay: None,
+            smtp_from: "starter-kit@localhost".to_string(),
+            job: Vec::new(),
+            public_listen: None,
+            public_doc: Vec::new(),
+            public_blob: Vec::new(),
+            public_rate_limit: 120,
+            backup_dir: None,
+            backup_interval: None,
+            backup_keep: 7,
+            backup_full_every: 7,
+            backup_s3_url: None,
+            backup_s3_region: "us-east-1".to_string(),
+            backup_s3_access_key: None,
+            backup_s3_secret_key: None,
+            federation_catalog: None,
+            federation_trusted_author: vec![],
+            federation_interval: 60,
+            config: None,
+            profile: None,
         };
         let iroh_node_3: IrohNode = setup_iroh_node(args_3).await.or_else(|_| {
             Err(anyhow!("Failed to set up Iroh node 3"))
@@ -1159,6 +2109,38 @@ mod tests {
         Ok(())
     }    
 
+    // collect_garbage
+    #[tokio::test]
+    pub async fn test_collect_garbage() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let blobs = iroh_node.blobs.clone();
+        let docs = iroh_node.docs.clone();
+
+        let kept = add_blob_named(blobs.clone(), Bytes::from("Tagged"), "Tag 1").await?;
+        let dropped = add_blob_named(blobs.clone(), Bytes::from("Untagged"), "Tag 2").await?;
+        assert_eq!(get_blob(blobs.clone(), dropped.hash.to_string()).await?, "Untagged");
+        delete_tag(blobs.clone(), "Tag 2").await?;
+
+        // the content of document entries is held by the entries, not by tags
+        let author = docs.client().authors().default().await?;
+        let doc = docs.client().create().await?;
+        let entry = doc.set_bytes(author, "key", "Entry content").await?;
+
+        assert_eq!(collect_garbage(docs.clone(), blobs.clone()).await?, 1);
+        assert!(has_blob(blobs.clone(), kept.hash.to_string()).await?);
+        assert!(has_blob(blobs.clone(), entry.to_string()).await?);
+        assert!(!has_blob(blobs.clone(), dropped.hash.to_string()).await?);
+        // nor is it served from the cache
+        assert!(get_blob(blobs.clone(), dropped.hash.to_string()).await.is_err());
+
+        // Clean up
+        fs::remove_dir_all("Test/test_blobs").await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
+        Ok(())
+    }
+
     // export_blob_to_file
     #[tokio::test]
     pub async fn test_export_blob_to_file() -> Result<()> {
@@ -1204,6 +2186,65 @@ mod tests {
         fs::remove_dir_all("Test").await?;
         iroh_node.router.shutdown().await?;
 
+        Ok(())
+    }
+    // read_blob_range
+    #[tokio::test]
+    pub async fn test_read_blob_range() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let blobs = iroh_node.blobs.clone();
+        let outcome = add_blob_bytes(blobs.clone(), Bytes::from("0123456789")).await?;
+
+        let (hash, size) = complete_blob_size(blobs.clone(), outcome.hash.to_string()).await?;
+        assert_eq!(size, 10);
+        let range = read_blob_range(blobs.clone(), hash, 2, 5).await?.read_to_bytes().await?;
+        assert_eq!(range, Bytes::from("23456"));
+
+        // Clean up
+        fs::remove_dir_all("Test/test_blobs").await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
+        Ok(())
+    }
+
+    // Benchmark of serving a 128 MiB blob through the JSON path of `get_blob` against the raw
+    // stream of `read_blob_range`. Run it with
+    // ```cargo test --release -p core test_bench_raw_blob_serving -- --ignored --nocapture```.
+    #[tokio::test]
+    #[ignore]
+    pub async fn test_bench_raw_blob_serving() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let blobs = iroh_node.blobs.clone();
+        let content: Vec<u8> = (0..128 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+        let outcome = add_blob_bytes(blobs.clone(), Bytes::from(content)).await?;
+
+        let started = std::time::Instant::now();
+        let encoded = get_blob(blobs.clone(), outcome.hash.to_string()).await?;
+        let json = serde_json::to_vec(&serde_json::json!({ "content": encoded }))?;
+        let json_time = started.elapsed();
+
+        let started = std::time::Instant::now();
+        let (hash, size) = complete_blob_size(blobs.clone(), outcome.hash.to_string()).await?;
+        let mut stream = read_blob_range(blobs.clone(), hash, 0, size).await?;
+        let mut streamed = 0;
+        let mut largest_chunk = 0;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            streamed += chunk.len();
+            largest_chunk = largest_chunk.max(chunk.len());
+        }
+        let raw_time = started.elapsed();
+
+        println!("json:   {:>8.1?} for {} bytes, buffered whole", json_time, json.len());
+        println!("raw:    {:>8.1?} for {} bytes, largest chunk {} bytes", raw_time, streamed, largest_chunk);
+        assert_eq!(streamed as u64, size);
+
+        // Clean up
+        fs::remove_dir_all("Test/test_blobs").await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
         Ok(())
     }
 }
\ No newline at end of file
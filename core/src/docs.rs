@@ -1,13 +1,25 @@
-use helpers::utils::{encode_doc_id, decode_doc_id, encode_key, decode_key, SS58AuthorId, ApiDownloadPolicy, validate_key};
+use helpers::utils::{is_attachment_key, is_history_key, HISTORY_KEY_PREFIX, encode_doc_id, decode_doc_id, encode_key, decode_key, SS58AuthorId, ApiDownloadPolicy, validate_key, validate_key_prefix, signature_key, anchor_key, ANCHOR_KEY_PREFIX, revocation_key, REVOCATION_KEY_PREFIX, SIGNATURE_KEY_PREFIX, cord_definition_key, cord_event_key};
+use helpers::stores::{NodeBlobs, NodeDocs};
+use keystore::keystore::{decode_public, encode_public, signed_message, StarterkitKeystore, SIGNING_KEY_TYPE};
+use keystore::registry::{builtin_key_type, KeyPurpose, KeyTypeInfo};
+use keystore::credential::{issue_credential, CredentialClaims};
+use cord::backend::ChainAnchor;
+use cord::client::ChainClient;
+use cord::registry::{create_schema, fetch_definition, fetch_registry_authorization, ChainDefinitionKind};
+use cord::schema::{cord_schema_to_json_schema, json_schema_to_cord_schema};
+use cord::events::ChainEventRecord;
+use helpers::authorizations::{parse_cord_did, AuthorizationRegistry};
+use helpers::errors::{fmt_error, Cause};
+use helpers::doc_handles::DocHandleCache;
+use crate::filter::Filter;
+use crate::schemas::{build_validator, REGISTRY_SCHEME};
+use crate::trash::move_to_trash;
+use crate::history::{retain_current_version, retain_versions};
 
-use iroh_blobs::net_protocol::Blobs;
 use iroh_blobs::Hash;
-use iroh_docs::protocol::Docs;
-use iroh_blobs::store::fs::Store;
 use iroh_docs::rpc::AddrInfoOptions;
 use iroh_docs::{CapabilityKind, DocTicket, NamespaceId};
 use iroh_docs::rpc::client::docs::{Doc, ShareMode};
-use jsonschema::validator_for;
 use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -21,11 +33,19 @@ use futures::TryStreamExt;
 use futures::StreamExt;
 use iroh_docs::store::{Query, SortBy, SortDirection};
 use std::str::FromStr;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use iroh_docs::actor::OpenState;
+use iroh_docs::engine::LiveEvent;
+use iroh_docs::ContentStatus;
+use futures::Stream;
 use iroh_base::PublicKey;
+use subxt::config::PolkadotConfig;
+use subxt::client::OnlineClient;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use data_encoding::BASE32_NOPAD;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, thiserror::Error)]
 pub enum DocError {
     /// The specified document was not found.
     DocumentNotFound,
@@ -34,97 +54,199 @@ pub enum DocError {
     /// Failed to decode the author ID.
     InvalidAuthorIdFormat,
     /// Failed to create a new document.
-    FailedToCreateDocument,
+    FailedToCreateDocument(#[source] Cause),
     /// Failed to open the specified document.
-    FailedToOpenDocument,
+    FailedToOpenDocument(#[source] Cause),
     /// Failed to list documents from the backend.
-    FailedToListDocuments,
+    FailedToListDocuments(#[source] Cause),
     /// Error while streaming the document list.
-    StreamingError,
+    StreamingError(#[source] Cause),
     /// Failed to drop (delete) the specified document.
-    FailedToDropDocument,
+    FailedToDropDocument(#[source] Cause),
     /// Failed to share the document.
-    FailedToShareDocument,
+    FailedToShareDocument(#[source] Cause),
     /// Failed to parse the document share ticket.
     InvalidDocumentTicketFormat,
     /// Failed to join a shared document.
-    FailedToJoinDocument,
+    FailedToJoinDocument(#[source] Cause),
     /// Failed to close the document.
-    FailedToCloseDocument,
+    FailedToCloseDocument(#[source] Cause),
     /// Failed to serialize the schema to JSON.
-    FailedToSerializeSchema,
+    FailedToSerializeSchema(#[source] Cause),
     /// Failed to validate the schema.
-    FailedToValidateSchema,
+    FailedToValidateSchema(#[source] Cause),
     /// Failed to set the schema in the document.
-    FailedToSetSchema,
+    FailedToSetSchema(#[source] Cause),
     /// Document already contains entries; schema can only be added to an empty document.
     DocumentNotEmpty,
     /// Failed to validate the entry key.
-    FailedToValidateKey,
+    FailedToValidateKey(#[source] Cause),
     /// Failed to get the schema entry from the document.
-    FailedToGetSchemaEntry,
+    FailedToGetSchemaEntry(#[source] Cause),
     /// Failed to read the blob.
-    FailedToReadBlob,
+    FailedToReadBlob(#[source] Cause),
     /// Failed to convert schema blob to UTF-8.
-    FailedToConvertBlobUtf8,
+    FailedToConvertBlobUtf8(#[source] Cause),
     /// Failed to parse schema blob as JSON.
-    FailedToParseSchemaJson,
+    FailedToParseSchemaJson(#[source] Cause),
     /// Failed to create JSON schema validator.
-    FailedToCreateSchemaValidator,
+    FailedToCreateSchemaValidator(#[source] Cause),
+    /// A `$ref` of the schema points to a schema that cannot be read, see `SchemaRefs`.
+    FailedToResolveSchemaRef(#[source] Cause),
+    /// A `$ref` of the schema points to a URL outside of the `--schema-ref-url` prefixes.
+    SchemaRefNotAllowed,
+    /// No schema registry document is set, see `--schema-registry-doc`.
+    SchemaRegistryNotSet,
+    /// A registry schema name or version is empty, or contains `@` or whitespace.
+    InvalidSchemaName,
+    /// The schema registry document already holds this version of the schema.
+    SchemaVersionExists,
+    /// The schema registry document holds no such schema or version.
+    SchemaNotFound,
     /// Failed to convert entry value to JSON.
-    FailedToConvertValueJson,
+    FailedToConvertValueJson(#[source] Cause),
     /// Entry value does not match the schema.
     ValueDoesNotMatchSchema,
     /// Failed to set entry bytes in the document.
-    FailedToSetEntryBytes,
+    FailedToSetEntryBytes(#[source] Cause),
     /// File does not exist at the specified path.
     FileDoesNotExist,
-    /// File import not allowed; cannot add a file to a document with a schema.
+    /// File import not allowed; a document with a schema only takes files under `files/`.
     FileImportNotAllowedWithSchema,
+    /// An attachment link of the entry value names a key outside of `files/`.
+    InvalidAttachmentLink,
+    /// An attachment link of the entry value names a key the document has no entry for.
+    AttachmentNotFound,
     /// Failed to import file into the document.
-    FailedToImportFile,
+    FailedToImportFile(#[source] Cause),
     /// Failed to finish file import.
-    FailedToFinishFileImport,
+    FailedToFinishFileImport(#[source] Cause),
     /// Failed to convert entry key to UTF-8.
-    FailedToConvertKeyUtf8,
+    FailedToConvertKeyUtf8(#[source] Cause),
     /// Failed to get entry from the document.
-    FailedToGetEntry,
+    FailedToGetEntry(#[source] Cause),
     /// Failed to decode entry key.
-    FailedToDecodeEntryKey,
+    FailedToDecodeEntryKey(#[source] Cause),
     // /// Failed to encode author ID.
-    FailedToEncodeAuthorId,
+    FailedToEncodeAuthorId(#[source] Cause),
     /// Failed to parse hash.
-    FailedToParseHash,
+    FailedToParseHash(#[source] Cause),
     /// Failed to get entries for the document.
-    FailedToGetEntries,
+    FailedToGetEntries(#[source] Cause),
     /// Invalid sort_by value.
     InvalidSortByValue,
     /// Invalid sort_direction value.
     InvalidSortDirectionValue,
     /// Failed to delete entry from the document.
-    FailedToDeleteEntry,
+    FailedToDeleteEntry(#[source] Cause),
     /// Entry not found for the specified key.
     EntryNotFound,
+    /// The author has no deleted entry at the key in the trash.
+    TrashedEntryNotFound,
+    /// The author has an entry at the key, which restoring the deleted one would overwrite.
+    EntryAlreadyExists,
+    /// Failed to keep the deleted entry in the trash.
+    FailedToTrashEntry(#[source] Cause),
+    /// Failed to put the deleted entry back from the trash.
+    FailedToRestoreEntry(#[source] Cause),
+    /// Failed to keep the replaced version of an entry.
+    FailedToRetainVersion(#[source] Cause),
     /// Failed to leave the document.
-    FailedToLeaveDocument,
+    FailedToLeaveDocument(#[source] Cause),
     /// Failed to get the status of the document.
-    FailedToGetDocumentStatus,
+    FailedToGetDocumentStatus(#[source] Cause),
     /// Failed to get the download policy for the document.
-    FailedToGetDownloadPolicy,
+    FailedToGetDownloadPolicy(#[source] Cause),
     /// Failed to decode the download policy.
-    FailedToDecodeDownloadPolicy,
+    FailedToDecodeDownloadPolicy(#[source] Cause),
     /// Failed to set the download policy for the document.
-    FailedToSetDownloadPolicy,
+    FailedToSetDownloadPolicy(#[source] Cause),
+    /// Failed to subscribe to the document's live events.
+    FailedToSubscribeDocument(#[source] Cause),
+    /// Failed to receive an event from the document subscription.
+    FailedToReceiveEvent(#[source] Cause),
+    /// Failed to sign the entry value with the node's keystore.
+    FailedToSignEntry(#[source] Cause),
+    /// Failed to store the signature of an entry.
+    FailedToSetEntrySignature(#[source] Cause),
+    /// The entry has no detached signature, see `set_entry_signed`.
+    EntrySignatureNotFound,
+    /// Failed to parse the stored signature of an entry.
+    FailedToParseEntrySignature(#[source] Cause),
+    /// Failed to submit the document digest to the chain.
+    FailedToAnchorDocument(#[source] Cause),
+    /// Failed to store the anchor of a document.
+    FailedToSetAnchor(#[source] Cause),
+    /// Failed to parse a stored anchor.
+    FailedToParseAnchor(#[source] Cause),
+    /// The document has no schema, which credentials need.
+    DocumentHasNoSchema,
+    /// The entry value is not a JSON object, so it cannot be a credential subject.
+    EntryNotJsonObject,
+    /// Failed to sign the credential with the keystore key.
+    FailedToIssueCredential(#[source] Cause),
+    /// The document has no anchor made by the given extrinsic.
+    AnchorNotFound,
+    /// The anchor's digest is not a Merkle root, so entries cannot be proven against it.
+    AnchorNotMerkle,
+    /// The anchor does not cover the entry's key.
+    EntryNotAnchored,
+    /// The entries the anchor covers changed since it was made.
+    AnchorNoLongerMatches,
+    /// Failed to read the anchoring extrinsic from the chain.
+    FailedToReadAnchor(#[source] Cause),
+    /// The statement was already revoked.
+    StatementAlreadyRevoked,
+    /// Failed to submit the revocation to the chain.
+    FailedToRevokeStatement(#[source] Cause),
+    /// Failed to store the revocation of a statement.
+    FailedToSetRevocation(#[source] Cause),
+    /// Failed to parse a stored revocation.
+    FailedToParseRevocation(#[source] Cause),
+    /// Failed to read a definition from the CORD chain.
+    FailedToFetchChainDefinition(#[source] Cause),
+    /// Failed to store or remove the mirror of a chain definition.
+    FailedToSetChainDefinition(#[source] Cause),
+    /// Failed to parse a stored chain definition.
+    FailedToParseChainDefinition(#[source] Cause),
+    /// Failed to register the document's schema on the CORD chain.
+    FailedToPushSchema(#[source] Cause),
+    /// Failed to store a chain event in the document.
+    FailedToSetChainEvent(#[source] Cause),
+    /// The CORD schema cannot be used as a document schema.
+    InvalidCordSchema,
+    /// There is no schema with the given identifier on the CORD chain.
+    CordSchemaNotFound,
+    /// The document's schema cannot be expressed as a CORD schema.
+    SchemaNotCordCompatible,
+    /// The author has no linked CORD DID, which writes to the document need.
+    AuthorHasNoLinkedDid,
+    /// The author's DID holds no authorization on the document's CORD registry.
+    AuthorNotAuthorized,
+    /// Failed to read the author's authorization from the CORD chain.
+    FailedToCheckAuthorization(#[source] Cause),
+    /// A sync prefix is empty.
+    InvalidSyncPrefix,
+    /// The conflict resolution is not one of `CONFLICT_RESOLUTION_NAMES`.
+    InvalidConflictResolution,
+    /// A bundle ticket lists no documents.
+    EmptyDocBundle,
+    /// The node does not hold the secret key of the author signing a bundle ticket.
+    AuthorNotFound,
+    /// Failed to decode a bundle ticket, or one of the tickets it lists.
+    InvalidDocBundleFormat,
+    /// The signature of a bundle ticket does not match its manifest and issuer.
+    InvalidDocBundleSignature,
+    /// A bundle ticket was signed by another author than the expected issuer.
+    UnexpectedDocBundleIssuer,
 }
 
 impl fmt::Display for DocError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        fmt_error(self, f)
     }
 }
 
-impl std::error::Error for DocError {}
-
 /// Retrieves a document by its ID.
 /// 
 /// # Arguments
@@ -134,7 +256,22 @@ impl std::error::Error for DocError {}
 /// # Returns
 /// * `Doc` - The opened document client.
 pub async fn get_document(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
+    doc_id: NamespaceId,
+) -> anyhow::Result<Doc<FlumeConnector<Response, Request>>, DocError> {
+    // the node keeps the handles of recently used documents open
+    if let Some(doc) = docs.handles.get(&doc_id) {
+        return Ok(doc);
+    }
+
+    let doc = open_document(docs.clone(), doc_id).await?;
+    docs.handles.insert(doc_id, doc.clone());
+    Ok(doc)
+}
+
+// Opens a document without going through the handle cache
+async fn open_document(
+    docs: Arc<NodeDocs>,
     doc_id: NamespaceId,
 ) -> anyhow::Result<Doc<FlumeConnector<Response, Request>>, DocError> {
     let doc_client = docs.client(); 
@@ -142,12 +279,36 @@ pub async fn get_document(
     let doc = doc_client
         .open(doc_id)
         .await
-        .map_err(|_| DocError::FailedToOpenDocument)?
+        .map_err(|e| DocError::FailedToOpenDocument(Cause::new(e)))?
         .ok_or(DocError::DocumentNotFound)?;
 
     Ok(doc)
 }
 
+// Takes the handle of a document out of the handle cache and closes it
+async fn release_cached_handle(docs: &NodeDocs, doc_id: NamespaceId) -> anyhow::Result<(), DocError> {
+    if let Some(doc) = docs.handles.remove(&doc_id) {
+        doc.close()
+            .await
+            .map_err(|e| DocError::FailedToCloseDocument(Cause::new(e)))?;
+    }
+    Ok(())
+}
+
+/// Closes the cached document handles that went unused for the cache's idle time.
+pub fn spawn_doc_handle_eviction(handles: DocHandleCache) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval((handles.idle() / 2).max(Duration::from_secs(1)));
+        loop {
+            interval.tick().await;
+            let evicted = handles.evict_idle();
+            if evicted > 0 {
+                tracing::debug!(evicted, "closed idle document handles");
+            }
+        }
+    })
+}
+
 /// Reads and decodes a blob entry from storage.
 /// 
 /// # Arguments
@@ -157,18 +318,15 @@ pub async fn get_document(
 /// # Returns
 /// * `String` - The UTF-8 decoded blob content.
 pub async fn get_blob_entry(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     hash: Hash,
 ) -> anyhow::Result<String, DocError> {
-    let blob_client = blobs.client();
-
-    let read_to_bytes = blob_client
-        .read_to_bytes(hash)
+    let read_to_bytes = crate::blobs::read_blob_cached(&blobs, hash)
         .await
-        .map_err(|_| DocError::FailedToReadBlob)?;
+        .map_err(|e| DocError::FailedToReadBlob(Cause::new(e)))?;
 
     let decoded_str = std::str::from_utf8(&read_to_bytes)
-        .map_err(|_| DocError::FailedToConvertBlobUtf8)?;
+        .map_err(|e| DocError::FailedToConvertBlobUtf8(Cause::new(e)))?;
 
     Ok(decoded_str.to_string())
 }
@@ -181,14 +339,14 @@ pub async fn get_blob_entry(
 /// # Returns
 /// * `String` - The base64-encoded document ID.
 pub async fn create_doc(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
 ) -> anyhow::Result<String, DocError> {
     let doc_client = docs.client();
 
     let doc = doc_client
         .create()
         .await
-        .map_err(|_| DocError::FailedToCreateDocument)?;
+        .map_err(|e| DocError::FailedToCreateDocument(Cause::new(e)))?;
 
     let doc_id = encode_doc_id(doc.id().as_bytes());
 
@@ -203,21 +361,21 @@ pub async fn create_doc(
 /// # Returns
 /// * `Vec<(String, CapabilityKind)>` - A list of encoded document IDs and their capabilities.
 pub async fn list_docs(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
 ) -> anyhow::Result<Vec<(String, CapabilityKind)>, DocError> {
     let doc_client = docs.client();
 
     let mut docs_stream = doc_client
         .list()
         .await
-        .map_err(|_| DocError::FailedToListDocuments)?;
+        .map_err(|e| DocError::FailedToListDocuments(Cause::new(e)))?;
 
     let mut doc_list = Vec::new();
 
     while let Some((namespace_id, capability)) = docs_stream
         .try_next()
         .await
-        .map_err(|_| DocError::StreamingError)?
+        .map_err(|e| DocError::StreamingError(Cause::new(e)))?
     {
         let doc_id = encode_doc_id(namespace_id.as_bytes());
         doc_list.push((doc_id, capability));
@@ -226,6 +384,29 @@ pub async fn list_docs(
     Ok(doc_list)
 }
 
+/// Streams all documents as they are read, for listings too large to collect at once.
+///
+/// # Arguments
+/// * `docs` - The Arc-wrapped Docs client.
+///
+/// # Returns
+/// * `Stream` - Tuples of encoded document ID and capability kind, each item failing if the
+///   documents could not be read.
+pub async fn stream_docs(
+    docs: Arc<NodeDocs>,
+) -> anyhow::Result<impl Stream<Item = Result<(String, CapabilityKind), DocError>> + Send + 'static, DocError> {
+    let docs_stream = docs
+        .client()
+        .list()
+        .await
+        .map_err(|e| DocError::FailedToListDocuments(Cause::new(e)))?;
+
+    Ok(docs_stream.map(|doc| {
+        let (namespace_id, capability) = doc.map_err(|e| DocError::StreamingError(Cause::new(e)))?;
+        Ok((encode_doc_id(namespace_id.as_bytes()), capability))
+    }))
+}
+
 /// Deletes a document by its encoded ID.
 /// 
 /// # Arguments
@@ -235,19 +416,22 @@ pub async fn list_docs(
 /// # Returns
 /// * `()` - Indicates successful deletion.
 pub async fn drop_doc(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
     doc_id: String,
 ) -> anyhow::Result<(), DocError> {
     let namespace_id_vec = decode_doc_id(&doc_id)
         .map_err(|_| DocError::InvalidDocumentIdFormat)?;
     let namespace_id = NamespaceId::from(namespace_id_vec);
 
+    // iroh only drops documents that no handle holds open
+    release_cached_handle(&docs, namespace_id).await?;
+
     let doc_client = docs.client();
 
     doc_client
         .drop_doc(namespace_id)
         .await
-        .map_err(|_| DocError::FailedToDropDocument)?;
+        .map_err(|e| DocError::FailedToDropDocument(Cause::new(e)))?;
 
     Ok(())
 }
@@ -263,7 +447,7 @@ pub async fn drop_doc(
 /// # Returns
 /// * `String` - The generated document share ticket.
 pub async fn share_doc(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
     doc_id: String,
     mode: ShareMode,
     addr_options: AddrInfoOptions,
@@ -279,7 +463,7 @@ pub async fn share_doc(
     let doc_ticket = doc
         .share(mode, addr_options)
         .await
-        .map_err(|_| DocError::FailedToShareDocument)?;
+        .map_err(|e| DocError::FailedToShareDocument(Cause::new(e)))?;
 
     Ok(doc_ticket.to_string())
 }
@@ -289,26 +473,221 @@ pub async fn share_doc(
 /// # Arguments
 /// * `docs` - The Arc-wrapped Docs client.
 /// * `ticket` - The share ticket string.
+/// * `sync_prefixes` - Only download the content of entries under these key prefixes, see
+///   `set_sync_prefixes`. The policy is in place before the first sync. Empty to download all.
 /// 
 /// # Returns
 /// * `String` - The namespace ID of the joined document.
 pub async fn join_doc(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
     ticket: String,
+    sync_prefixes: Vec<String>,
 ) -> anyhow::Result<String, DocError> {
     let doc_ticket = DocTicket::from_str(&ticket)
         .map_err(|_| DocError::InvalidDocumentTicketFormat)?;
+    check_sync_prefixes(&sync_prefixes)?;
 
     let doc_client = docs.client();
 
-    let (doc, _) = doc_client
-        .import_and_subscribe(doc_ticket)
+    if sync_prefixes.is_empty() {
+        let (doc, _) = doc_client
+            .import_and_subscribe(doc_ticket)
+            .await
+            .map_err(|e| DocError::FailedToJoinDocument(Cause::new(e)))?;
+        return Ok(doc.id().to_string());
+    }
+
+    let DocTicket { capability, nodes } = doc_ticket;
+    let doc = doc_client
+        .import_namespace(capability)
+        .await
+        .map_err(|e| DocError::FailedToJoinDocument(Cause::new(e)))?;
+    doc.set_download_policy(ApiDownloadPolicy::only_prefixes(&sync_prefixes).0)
         .await
-        .map_err(|_| DocError::FailedToJoinDocument)?;
+        .map_err(|e| DocError::FailedToSetDownloadPolicy(Cause::new(e)))?;
+    doc.start_sync(nodes)
+        .await
+        .map_err(|e| DocError::FailedToJoinDocument(Cause::new(e)))?;
 
     Ok(doc.id().to_string())
 }
 
+/// Prefix of a bundle ticket, followed by its signature and manifest in base32.
+pub const DOC_BUNDLE_PREFIX: &str = "docbundle";
+// Version of the bundle manifest, checked when decoding
+const DOC_BUNDLE_VERSION: u32 = 1;
+// Signed before the manifest, so a bundle signature cannot be mistaken for another author signature
+const DOC_BUNDLE_SIGNING_CONTEXT: &[u8] = b"starter-kit/doc-bundle/v1:";
+
+/// The signed manifest of a bundle ticket, listing a ticket for each document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocBundle {
+    pub version: u32,
+    /// SS58 ID of the author that signed the bundle.
+    pub issuer: String,
+    /// A name for the suite of documents, shown to the joining node.
+    pub label: Option<String>,
+    /// Unix time in seconds.
+    pub created_at: u64,
+    pub docs: Vec<BundledDoc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundledDoc {
+    pub doc_id: String,
+    pub ticket: String,
+}
+
+/// Shares several documents at once as a single bundle ticket, signed by `author_id`.
+///
+/// # Arguments
+/// * `docs` - The Arc-wrapped Docs client.
+/// * `doc_ids` - The documents to share. Duplicates are listed once.
+/// * `mode` - The sharing mode (read/write), the same for every document.
+/// * `addr_options` - Peer address options to include.
+/// * `author_id` - The SS58 author that signs the bundle. Its secret key must be on this node.
+/// * `label` - An optional name for the bundle.
+///
+/// # Returns
+/// * `String` - The bundle ticket.
+pub async fn share_doc_bundle(
+    docs: Arc<NodeDocs>,
+    doc_ids: Vec<String>,
+    mode: ShareMode,
+    addr_options: AddrInfoOptions,
+    author_id: String,
+    label: Option<String>,
+) -> anyhow::Result<String, DocError> {
+    let mut unique_ids: Vec<String> = Vec::with_capacity(doc_ids.len());
+    for doc_id in doc_ids {
+        if !unique_ids.contains(&doc_id) {
+            unique_ids.push(doc_id);
+        }
+    }
+    if unique_ids.is_empty() {
+        return Err(DocError::EmptyDocBundle);
+    }
+
+    let author = SS58AuthorId::decode(&author_id)
+        .map_err(|_| DocError::InvalidAuthorIdFormat)?;
+    let signer = docs
+        .client()
+        .authors()
+        .export(author)
+        .await
+        .ok()
+        .flatten()
+        .ok_or(DocError::AuthorNotFound)?;
+
+    let mut bundled = Vec::with_capacity(unique_ids.len());
+    for doc_id in unique_ids {
+        let ticket = share_doc(docs.clone(), doc_id.clone(), mode.clone(), addr_options).await?;
+        bundled.push(BundledDoc { doc_id, ticket });
+    }
+
+    let bundle = DocBundle {
+        version: DOC_BUNDLE_VERSION,
+        issuer: author_id,
+        label,
+        created_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        docs: bundled,
+    };
+    let manifest = serde_json::to_vec(&bundle)
+        .map_err(|_| DocError::InvalidDocBundleFormat)?;
+    let signature = signer.sign(&[DOC_BUNDLE_SIGNING_CONTEXT, &manifest].concat());
+
+    let mut payload = signature.to_bytes().to_vec();
+    payload.extend_from_slice(&manifest);
+    Ok(format!("{}{}", DOC_BUNDLE_PREFIX, BASE32_NOPAD.encode(&payload).to_ascii_lowercase()))
+}
+
+/// Decodes a bundle ticket and checks its signature against its issuer.
+///
+/// # Arguments
+/// * `ticket` - The bundle ticket string.
+///
+/// # Returns
+/// * `DocBundle` - The verified manifest.
+pub fn decode_doc_bundle(ticket: &str) -> anyhow::Result<DocBundle, DocError> {
+    let encoded = ticket
+        .trim()
+        .strip_prefix(DOC_BUNDLE_PREFIX)
+        .ok_or(DocError::InvalidDocBundleFormat)?;
+    let payload = BASE32_NOPAD
+        .decode(encoded.to_ascii_uppercase().as_bytes())
+        .map_err(|_| DocError::InvalidDocBundleFormat)?;
+    if payload.len() <= ed25519_dalek::SIGNATURE_LENGTH {
+        return Err(DocError::InvalidDocBundleFormat);
+    }
+    let (signature, manifest) = payload.split_at(ed25519_dalek::SIGNATURE_LENGTH);
+
+    let bundle: DocBundle = serde_json::from_slice(manifest)
+        .map_err(|_| DocError::InvalidDocBundleFormat)?;
+    if bundle.version != DOC_BUNDLE_VERSION || bundle.docs.is_empty() {
+        return Err(DocError::InvalidDocBundleFormat);
+    }
+
+    let issuer = SS58AuthorId::decode(&bundle.issuer)
+        .map_err(|_| DocError::InvalidDocBundleFormat)?
+        .into_public_key()
+        .map_err(|_| DocError::InvalidDocBundleFormat)?;
+    let signature = ed25519_dalek::Signature::from_slice(signature)
+        .map_err(|_| DocError::InvalidDocBundleFormat)?;
+    issuer
+        .verify(&[DOC_BUNDLE_SIGNING_CONTEXT, manifest].concat(), &signature)
+        .map_err(|_| DocError::InvalidDocBundleSignature)?;
+
+    // Every ticket must open the document it is listed under
+    for doc in &bundle.docs {
+        let doc_ticket = DocTicket::from_str(&doc.ticket)
+            .map_err(|_| DocError::InvalidDocBundleFormat)?;
+        let namespace_id = decode_doc_id(&doc.doc_id)
+            .map_err(|_| DocError::InvalidDocBundleFormat)?;
+        if doc_ticket.capability.id() != NamespaceId::from(namespace_id) {
+            return Err(DocError::InvalidDocBundleFormat);
+        }
+    }
+
+    Ok(bundle)
+}
+
+/// Joins every document of a bundle ticket, after checking its signature.
+///
+/// All tickets are checked before any document is joined. A document that fails to join leaves
+/// the ones joined before it in place.
+///
+/// # Arguments
+/// * `docs` - The Arc-wrapped Docs client.
+/// * `ticket` - The bundle ticket string.
+/// * `expected_issuer` - If set, the SS58 author the bundle must be signed by.
+/// * `sync_prefixes` - Only download the content of entries under these key prefixes, applied
+///   to every document. Empty to download all.
+///
+/// # Returns
+/// * `DocBundle` - The verified manifest, listing the joined documents.
+pub async fn join_doc_bundle(
+    docs: Arc<NodeDocs>,
+    ticket: String,
+    expected_issuer: Option<String>,
+    sync_prefixes: Vec<String>,
+) -> anyhow::Result<DocBundle, DocError> {
+    let bundle = decode_doc_bundle(&ticket)?;
+    if let Some(expected_issuer) = expected_issuer {
+        let expected = SS58AuthorId::decode(&expected_issuer)
+            .map_err(|_| DocError::InvalidAuthorIdFormat)?;
+        if SS58AuthorId::decode(&bundle.issuer).ok() != Some(expected) {
+            return Err(DocError::UnexpectedDocBundleIssuer);
+        }
+    }
+    check_sync_prefixes(&sync_prefixes)?;
+
+    for doc in &bundle.docs {
+        join_doc(docs.clone(), doc.ticket.clone(), sync_prefixes.clone()).await?;
+    }
+
+    Ok(bundle)
+}
+
 /// Closes an open document.
 /// 
 /// # Arguments
@@ -318,20 +697,21 @@ pub async fn join_doc(
 /// # Returns
 /// * `()` - Indicates successful closure.
 pub async fn close_doc(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
     doc_id: String,
 ) -> anyhow::Result<(), DocError> {
     let namespace_id_vec = decode_doc_id(&doc_id)
         .map_err(|_| DocError::InvalidDocumentIdFormat)?;
     let namespace_id = NamespaceId::from(namespace_id_vec);
 
-    let doc = get_document(docs, namespace_id)
+    release_cached_handle(&docs, namespace_id).await?;
+    let doc = open_document(docs, namespace_id)
         .await
         .map_err(|_| DocError::DocumentNotFound)?;
 
     doc.close()
         .await
-        .map_err(|_| DocError::FailedToCloseDocument)?;
+        .map_err(|e| DocError::FailedToCloseDocument(Cause::new(e)))?;
 
     Ok(())
 }
@@ -354,8 +734,12 @@ pub async fn close_doc(
 ///     "required": ["owner", "name", "number_of_entries", "terms_and_conditions"]
 /// }"#;
 /// ```
+///
+/// A schema of the schema registry document can be used instead, as `registry:name@version`,
+/// or `registry:name` for its highest version. It is stored as a `$ref` to that schema.
 pub async fn add_doc_schema(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
     author_id: String,
     doc_id: String,
     schema: String,
@@ -364,11 +748,22 @@ pub async fn add_doc_schema(
         .map_err(|_| DocError::InvalidDocumentIdFormat)?;
     let namespace_id = NamespaceId::from(namespace_id_vec);
 
+    let schema = match schema.trim() {
+        reference if reference.starts_with(&format!("{}:", REGISTRY_SCHEME)) => {
+            serde_json::json!({ "$ref": reference }).to_string()
+        }
+        _ => schema,
+    };
+
     let schema_json_bytes = serde_json::from_str(&schema)
-        .map_err(|_| DocError::FailedToSerializeSchema)?;
+        .map_err(|e| DocError::FailedToSerializeSchema(Cause::new(e)))?;
 
-    validator_for(&schema_json_bytes)
-        .map_err(|_| DocError::FailedToValidateSchema)?;
+    build_validator(docs.clone(), blobs, &schema_json_bytes)
+        .await
+        .map_err(|e| match e {
+            DocError::FailedToCreateSchemaValidator(cause) => DocError::FailedToValidateSchema(cause),
+            e => e,
+        })?;
 
     let author = SS58AuthorId::decode(&author_id)
         .map_err(|_| DocError::InvalidAuthorIdFormat)?;
@@ -379,7 +774,7 @@ pub async fn add_doc_schema(
 
     let mut entries_stream = doc.get_many(Query::all())
         .await
-        .map_err(|_| DocError::FailedToGetEntries)?;
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?;
     if entries_stream.next().await.is_some() {
         return Err(DocError::DocumentNotEmpty);
     }
@@ -395,7 +790,7 @@ pub async fn add_doc_schema(
             schema.into_bytes(),
         )
         .await
-        .map_err(|_| DocError::FailedToSetSchema)?;
+        .map_err(|e| DocError::FailedToSetSchema(Cause::new(e)))?;
 
     Ok(updated_hash.to_string())
 }
@@ -414,8 +809,8 @@ pub async fn add_doc_schema(
 /// });
 /// ```
 pub async fn set_entry(
-    docs: Arc<Docs<Store>>,
-    blobs: Arc<Blobs<Store>>,
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
     doc_id: String,
     author_id: String,
     key: String,
@@ -431,62 +826,1188 @@ pub async fn set_entry(
     // validate key
     validate_key(&key, true)
         .await
-        .map_err(|_| DocError::FailedToValidateKey)?;
+        .map_err(|e| DocError::FailedToValidateKey(Cause::new(e)))?;
 
     // get doc
-    let doc = get_document(docs, namespace_id)
+    let doc = get_document(docs.clone(), namespace_id)
         .await
         .map_err(|_| DocError::DocumentNotFound)?;
 
+    // validate the value against the author's schema, if any. Attachments are exempt.
+    if !is_attachment_key(&key) {
+        if let Some(validator) = schema_validator(&docs, &doc, author, &blobs).await? {
+            check_value_matches_schema(&validator, &value)?;
+            check_attachment_links(&doc, &value).await?;
+        }
+    }
+
+    // keep the version it replaces, then put the key-value pair in the document
+    retain_current_version(&doc, author, &key).await?;
+    let encoded_key = encode_key(key.as_bytes());
+    let hash = doc
+        .set_bytes(author, encoded_key, value.into_bytes())
+        .await
+        .map_err(|e| DocError::FailedToSetEntryBytes(Cause::new(e)))?;
+
+    Ok(hash.to_string())
+}
+
+// The validator of the schema `author` set on `doc`, if any
+async fn schema_validator(
+    docs: &Arc<NodeDocs>,
+    doc: &Doc<FlumeConnector<Response, Request>>,
+    author: iroh_docs::AuthorId,
+    blobs: &Arc<NodeBlobs>,
+) -> anyhow::Result<Option<jsonschema::Validator>, DocError> {
     // check if there is any value corresponding to the key 'schema' 
     let schema_key = "schema";
     let encoded_schema_key = encode_key(schema_key.as_bytes());
     let blob_client = blobs.client();
 
-    if let Some(schema_entry) = doc
+    let Some(schema_entry) = doc
         .get_exact(author, encoded_schema_key.clone(), true)
         .await
-        .map_err(|_| DocError::FailedToGetSchemaEntry)?
-    {
-        // get the hash of that entry
-        let schema_entry_hash = schema_entry.content_hash();
-
-        // get the data for that blob
-        let schema_to_bytes = blob_client
-            .read_to_bytes(schema_entry_hash)
-            .await
-            .map_err(|_| DocError::FailedToReadBlob)?;
-
-        // convert the blob data to JSON
-        let schema_str = std::str::from_utf8(&schema_to_bytes)
-            .map_err(|_| DocError::FailedToConvertBlobUtf8)?;
-        let schema_json: Value = serde_json::from_str(schema_str)
-            .map_err(|_| DocError::FailedToParseSchemaJson)?;
-
-        let validator = validator_for(&schema_json)
-            .map_err(|_| DocError::FailedToCreateSchemaValidator)?;
-
-        // convert value to JSON
-        let value_json: Value = serde_json::from_str(&value)
-            .map_err(|_| DocError::FailedToConvertValueJson)?;
-
-        // validate the value against the schema
-        if !validator.is_valid(&value_json) {
-            return Err(DocError::ValueDoesNotMatchSchema);
-        }
-    }
+        .map_err(|e| DocError::FailedToGetSchemaEntry(Cause::new(e)))?
+    else {
+        return Ok(None);
+    };
+
+    // get the hash of that entry
+    let schema_entry_hash = schema_entry.content_hash();
+
+    // get the data for that blob
+    let schema_to_bytes = blob_client
+        .read_to_bytes(schema_entry_hash)
+        .await
+        .map_err(|e| DocError::FailedToReadBlob(Cause::new(e)))?;
+
+    // convert the blob data to JSON
+    let schema_str = std::str::from_utf8(&schema_to_bytes)
+        .map_err(|e| DocError::FailedToConvertBlobUtf8(Cause::new(e)))?;
+    let schema_json: Value = serde_json::from_str(schema_str)
+        .map_err(|e| DocError::FailedToParseSchemaJson(Cause::new(e)))?;
+
+    let validator = build_validator(docs.clone(), blobs.clone(), &schema_json).await?;
+    Ok(Some(validator))
+}
+
+// Checks an entry value against a document schema
+fn check_value_matches_schema(validator: &jsonschema::Validator, value: &str) -> anyhow::Result<(), DocError> {
+    // convert value to JSON
+    let value_json: Value = serde_json::from_str(value)
+        .map_err(|e| DocError::FailedToConvertValueJson(Cause::new(e)))?;
+
+    // validate the value against the schema
+    if !validator.is_valid(&value_json) {
+        return Err(DocError::ValueDoesNotMatchSchema);
+    }
+    Ok(())
+}
+
+/// Field of the JSON objects that link an entry value to an attachment, as in
+/// `{"$attachment": "files/contract.pdf"}`, see `ATTACHMENT_KEY_PREFIX`.
+pub const ATTACHMENT_LINK_FIELD: &str = "$attachment";
+
+/// The attachment keys `value` links to, at any depth, in order of appearance.
+pub fn attachment_links(value: &Value) -> Vec<String> {
+    fn collect(value: &Value, links: &mut Vec<String>) {
+        match value {
+            Value::Object(fields) => {
+                if let Some(Value::String(link)) = fields.get(ATTACHMENT_LINK_FIELD) {
+                    if !links.contains(link) {
+                        links.push(link.clone());
+                    }
+                }
+                fields.values().for_each(|field| collect(field, links));
+            }
+            Value::Array(items) => items.iter().for_each(|item| collect(item, links)),
+            _ => {}
+        }
+    }
+    let mut links = Vec::new();
+    collect(value, &mut links);
+    links
+}
+
+// Checks that the attachments an entry value links to are in the document
+async fn check_attachment_links(doc: &Doc<FlumeConnector<Response, Request>>, value: &str) -> anyhow::Result<(), DocError> {
+    let value_json: Value = serde_json::from_str(value)
+        .map_err(|e| DocError::FailedToConvertValueJson(Cause::new(e)))?;
+    for link in attachment_links(&value_json) {
+        check_attachment_exists(doc, &link).await?;
+    }
+    Ok(())
+}
+
+// Checks that some author has a file at the attachment key `link`
+async fn check_attachment_exists(doc: &Doc<FlumeConnector<Response, Request>>, link: &str) -> anyhow::Result<(), DocError> {
+    if !is_attachment_key(link) {
+        return Err(DocError::InvalidAttachmentLink);
+    }
+    let attachment = doc
+        .get_one(Query::key_exact(encode_key(link.as_bytes())))
+        .await
+        .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
+    if attachment.is_none() {
+        return Err(DocError::AttachmentNotFound);
+    }
+    Ok(())
+}
+
+/// Number of writes `set_entries` keeps in flight to the docs actor at once.
+const SET_ENTRIES_IN_FLIGHT: usize = 32;
+
+/// Adds several entries of one author to a document, like calling [`set_entry`] for each.
+///
+/// The document is opened and the author's schema read once, and every value is checked against
+/// the schema before any entry is written, so a value that does not match writes none of them.
+/// The writes are then pipelined to the docs actor instead of waiting for each one in turn.
+///
+/// # Arguments
+/// * `docs` - Shared reference to the `Docs` store.
+/// * `blobs` - Shared reference to the `Blobs` store.
+/// * `doc_id` - The document ID (base64-encoded).
+/// * `author_id` - SS58-encoded author ID of the entries.
+/// * `entries` - The keys and values to write.
+///
+/// # Returns
+/// The content hashes of the entries, in the order of `entries`.
+pub async fn set_entries(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    author_id: String,
+    entries: Vec<(String, String)>,
+) -> anyhow::Result<Vec<String>, DocError> {
+    let namespace_id_vec = decode_doc_id(&doc_id)
+        .map_err(|_| DocError::InvalidDocumentIdFormat)?;
+    let namespace_id = NamespaceId::from(namespace_id_vec);
+
+    let author = SS58AuthorId::decode(&author_id)
+        .map_err(|_| DocError::InvalidAuthorIdFormat)?;
+
+    for (key, _) in &entries {
+        validate_key(key, true)
+            .await
+            .map_err(|e| DocError::FailedToValidateKey(Cause::new(e)))?;
+    }
+
+    let doc = get_document(docs.clone(), namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    if let Some(validator) = schema_validator(&docs, &doc, author, &blobs).await? {
+        for (key, value) in &entries {
+            if is_attachment_key(key) {
+                continue;
+            }
+            check_value_matches_schema(&validator, value)?;
+            // links may also point to attachments written in the same batch
+            for link in attachment_links(&serde_json::from_str(value).unwrap_or_default()) {
+                if is_attachment_key(&link) && entries.iter().any(|(batched, _)| *batched == link) {
+                    continue;
+                }
+                check_attachment_exists(&doc, &link).await?;
+            }
+        }
+    }
+
+    futures::stream::iter(entries)
+        .map(|(key, value)| {
+            let doc = doc.clone();
+            async move {
+                retain_current_version(&doc, author, &key).await?;
+                doc.set_bytes(author, encode_key(key.as_bytes()), value.into_bytes())
+                    .await
+                    .map(|hash| hash.to_string())
+                    .map_err(|e| DocError::FailedToSetEntryBytes(Cause::new(e)))
+            }
+        })
+        .buffered(SET_ENTRIES_IN_FLIGHT)
+        .try_collect()
+        .await
+}
+
+/// The detached signature of an entry value, stored as JSON under `sig:<key>` next to the entry.
+///
+/// The signature covers the raw value bytes, so it can be checked with `public` alone, without iroh.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntrySignature {
+    /// Signature scheme: `sr25519` for the node's document signing key, `ed25519` for the entry's
+    /// author.
+    pub algorithm: String,
+    /// SS58-encoded public key that signed the value: the node's document signing key, or the
+    /// author ID.
+    pub public: String,
+    /// 0x-prefixed hex signature of the value.
+    pub signature: String,
+    /// Content hash of the signed entry.
+    pub hash: String,
+}
+
+/// The key that signs an entry value in `set_entry_signed`.
+#[derive(Clone, Copy)]
+pub enum EntrySigner<'a> {
+    /// The node's document signing key (the first `sign` key), held by the keystore, with an
+    /// sr25519 signature. Never the CORD key, which only signs chain transactions.
+    Keystore(&'a StarterkitKeystore),
+    /// The secret key of the entry's author, with an ed25519 signature. Peers can check it
+    /// against the author ID alone.
+    Author,
+}
+
+/// Adds an entry like [`set_entry`] and attaches a detached signature of its value, made with
+/// the node's document signing key or the author's key.
+///
+/// The signature is written to the entry `sig:<key>` by the same author.
+///
+/// # Returns
+/// - The content hash of the entry and its signature.
+pub async fn set_entry_signed(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    author_id: String,
+    key: String,
+    value: String,
+    signer: EntrySigner<'_>,
+) -> anyhow::Result<(String, EntrySignature), DocError> {
+    // sign first, so a keystore failure never leaves an unsigned entry behind
+    let (algorithm, public, signature) = match signer {
+        EntrySigner::Keystore(keystore) => {
+            let key_type = builtin_key_type(SIGNING_KEY_TYPE).ok_or_else(|| DocError::FailedToSignEntry(Cause::new("the signing key type is not registered")))?;
+            let public = keystore
+                .get_doc_signing_public_key()
+                .map_err(|e| DocError::FailedToSignEntry(Cause::new(e)))?;
+            let signature = keystore
+                .sign_payload(&key_type, KeyPurpose::DocSigning, public.as_ref(), value.as_bytes())
+                .map_err(|e| DocError::FailedToSignEntry(Cause::new(e)))?;
+            ("sr25519", encode_public(&public.0), signature)
+        }
+        EntrySigner::Author => {
+            let author = SS58AuthorId::decode(&author_id)
+                .map_err(|_| DocError::InvalidAuthorIdFormat)?;
+            let secret = docs
+                .client()
+                .authors()
+                .export(author)
+                .await
+                .map_err(|e| DocError::FailedToSignEntry(Cause::new(e)))?
+                .ok_or(DocError::AuthorNotFound)?;
+            // the author key also signs iroh-docs entries, so only a domain-separated message
+            ("ed25519", author_id.clone(), secret.sign(&signed_message(value.as_bytes())).to_bytes().to_vec())
+        }
+    };
+
+    let hash = set_entry(docs.clone(), blobs, doc_id.clone(), author_id.clone(), key.clone(), value).await?;
+
+    let entry_signature = EntrySignature {
+        algorithm: algorithm.to_string(),
+        public,
+        signature: format!("0x{}", hex::encode(signature)),
+        hash: hash.clone(),
+    };
+
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(&doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let author = SS58AuthorId::decode(&author_id)
+        .map_err(|_| DocError::InvalidAuthorIdFormat)?;
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let signature_json = serde_json::to_vec(&entry_signature)
+        .map_err(|e| DocError::FailedToSetEntrySignature(Cause::new(e)))?;
+    doc.set_bytes(author, encode_key(signature_key(&key).as_bytes()), signature_json)
+        .await
+        .map_err(|e| DocError::FailedToSetEntrySignature(Cause::new(e)))?;
+
+    Ok((hash, entry_signature))
+}
+
+/// The outcome of re-checking the detached signature of an entry, see `verify_entry`.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryVerification {
+    pub key: String,
+    pub author: String,
+    /// Content hash of the entry as it is now.
+    pub hash: String,
+    pub signature: EntrySignature,
+    /// Whether the signature was made for the entry's current content.
+    pub hash_matches: bool,
+    /// Whether the signature is valid for `signature.public` over the current value.
+    pub signature_valid: bool,
+    /// Whether both hold, so the value is exactly what the signer signed.
+    pub valid: bool,
+}
+
+/// Re-checks the detached signature of an entry against its current value, see
+/// `set_entry_signed`.
+///
+/// Without `author_id`, the latest version of the key is checked, whichever author wrote it.
+pub async fn verify_entry(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    author_id: Option<String>,
+    key: String,
+) -> anyhow::Result<EntryVerification, DocError> {
+    let entry = match author_id {
+        Some(author_id) => get_entry(docs.clone(), doc_id.clone(), author_id, key.clone(), false).await?,
+        None => {
+            let versions = get_entries(docs.clone(), doc_id.clone(), serde_json::json!({ "key": key })).await?;
+            ConflictResolution::LastWriterWins.resolve(&key, &versions)
+        }
+    }
+    .ok_or(DocError::EntryNotFound)?;
+
+    let signature_entry = get_entry(docs, doc_id, entry.namespace.author.clone(), signature_key(&key), false)
+        .await?
+        .ok_or(DocError::EntrySignatureNotFound)?;
+    let read = |hash: &str| {
+        let blobs = blobs.clone();
+        let hash = Hash::from_str(hash).map_err(|e| DocError::FailedToParseHash(Cause::new(e)));
+        async move {
+            crate::blobs::read_blob_cached(&blobs, hash?)
+                .await
+                .map_err(|e| DocError::FailedToReadBlob(Cause::new(e)))
+        }
+    };
+    let signature: EntrySignature = serde_json::from_slice(&read(&signature_entry.record.hash).await?)
+        .map_err(|e| DocError::FailedToParseEntrySignature(Cause::new(e)))?;
+    let value = read(&entry.record.hash).await?;
+
+    let hash_matches = signature.hash == entry.record.hash;
+    let signature_valid = check_entry_signature(&signature, &value);
+    Ok(EntryVerification {
+        key,
+        author: entry.namespace.author,
+        hash: entry.record.hash,
+        signature,
+        hash_matches,
+        signature_valid,
+        valid: hash_matches && signature_valid,
+    })
+}
+
+// Whether `signature` is valid for its public key over `value`
+fn check_entry_signature(signature: &EntrySignature, value: &[u8]) -> bool {
+    let Some(bytes) = signature.signature.strip_prefix("0x").and_then(|hex| hex::decode(hex).ok()) else {
+        return false;
+    };
+    match signature.algorithm.as_str() {
+        "sr25519" => {
+            let (Some(key_type), Ok(public)) = (builtin_key_type(SIGNING_KEY_TYPE), decode_public(&signature.public)) else {
+                return false;
+            };
+            StarterkitKeystore::verify_payload(&key_type, &public, value, &bytes).unwrap_or(false)
+        }
+        "ed25519" => {
+            let (Ok(public), Ok(bytes)) = (SS58AuthorId::decode(&signature.public), ed25519_dalek::Signature::from_slice(&bytes)) else {
+                return false;
+            };
+            public.into_public_key().is_ok_and(|public| public.verify(&signed_message(value), &bytes).is_ok())
+        }
+        _ => false,
+    }
+}
+
+/// Where a document's digest was anchored on chain, as stored in its `anchor:<extrinsic_hash>`
+/// entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocAnchor {
+    /// 0x-prefixed digest of the anchored entries, see `compute_doc_digest`.
+    pub digest: String,
+    /// The keys the digest covers, or `None` for the whole document.
+    pub keys: Option<Vec<String>>,
+    /// Number of entries the digest covers.
+    pub entries: usize,
+    pub block_hash: String,
+    pub block_number: u64,
+    pub extrinsic_hash: String,
+    pub extrinsic_index: u32,
+    /// SS58 CORD account that signed the extrinsic.
+    pub account: String,
+    /// Unix time in seconds at which the anchor was stored.
+    pub anchored_at: u64,
+    /// How `digest` was computed. Anchors stored before Merkle roots were supported are `hash`.
+    #[serde(default)]
+    pub digest_kind: DocDigestKind,
+}
+
+/// How the digest of an anchor is computed from the entries it covers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocDigestKind {
+    /// A single hash of every entry, see `compute_doc_digest`.
+    #[default]
+    Hash,
+    /// The root of a Merkle tree with a leaf per entry, see `inclusion::compute_doc_merkle_root`,
+    /// so each entry can be proven on its own with `inclusion::entry_inclusion_proof`.
+    Merkle,
+}
+
+// An entry a document digest covers: its key, author, content hash and timestamp
+pub(crate) type DigestEntry = (Vec<u8>, [u8; 32], [u8; 32], u64);
+
+/// Computes the digest of a document's entries, or of the entries at `keys` only.
+///
+/// The entries are sorted by key and author, and the digest is the blake3 hash of the document ID
+/// followed by, for each entry, the key length as 4 big-endian bytes, the key, the author and the
+/// content hash. Anchors, revocations and past versions of entries are left out, so recording
+/// them, or purging old versions, does not change the digest.
+///
+/// # Returns
+/// - The digest and the number of entries it covers.
+pub async fn compute_doc_digest(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    keys: Option<&[String]>,
+) -> anyhow::Result<([u8; 32], usize), DocError> {
+    let (namespace_id, entries) = digest_entries(docs, &doc_id, keys).await?;
+
+    let mut message = namespace_id.as_bytes().to_vec();
+    for (key, author, content_hash, _) in &entries {
+        message.extend_from_slice(&(key.len() as u32).to_be_bytes());
+        message.extend_from_slice(key);
+        message.extend_from_slice(author);
+        message.extend_from_slice(content_hash);
+    }
+
+    Ok((*Hash::new(&message).as_bytes(), entries.len()))
+}
+
+/// Computes the digest of a document, or of the entries at `keys`, the way `kind` says.
+///
+/// # Returns
+/// - The digest and the number of entries it covers.
+pub async fn compute_anchor_digest(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    keys: Option<&[String]>,
+    kind: DocDigestKind,
+) -> anyhow::Result<([u8; 32], usize), DocError> {
+    match kind {
+        DocDigestKind::Hash => compute_doc_digest(docs, doc_id, keys).await,
+        DocDigestKind::Merkle => crate::inclusion::compute_doc_merkle_root(docs, doc_id, keys).await,
+    }
+}
+
+// The entries a digest of the document covers, sorted by key and author, along with the
+// document's namespace
+pub(crate) async fn digest_entries(
+    docs: Arc<NodeDocs>,
+    doc_id: &str,
+    keys: Option<&[String]>,
+) -> anyhow::Result<(NamespaceId, Vec<DigestEntry>), DocError> {
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let mut entries = Vec::new();
+    let mut entries_stream = doc
+        .get_many(Query::all())
+        .await
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?;
+    while let Some(entry) = entries_stream.next().await {
+        let entry = entry.map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
+        let key = decode_key(entry.id().key());
+        if key.starts_with(ANCHOR_KEY_PREFIX.as_bytes())
+            || key.starts_with(REVOCATION_KEY_PREFIX.as_bytes())
+            || key.starts_with(HISTORY_KEY_PREFIX.as_bytes())
+        {
+            continue;
+        }
+        if let Some(keys) = keys {
+            if !keys.iter().any(|wanted| wanted.as_bytes() == key.as_slice()) {
+                continue;
+            }
+        }
+        entries.push((
+            key,
+            *entry.id().author().as_bytes(),
+            *entry.record().content_hash().as_bytes(),
+            entry.record().timestamp(),
+        ));
+    }
+
+    // every requested key must be covered, so a typo cannot anchor less than intended
+    if let Some(keys) = keys {
+        if keys.iter().any(|wanted| !entries.iter().any(|(key, _, _, _)| wanted.as_bytes() == key.as_slice())) {
+            return Err(DocError::EntryNotFound);
+        }
+    }
+
+    entries.sort();
+    Ok((namespace_id, entries))
+}
+
+/// Anchors the digest of a document, or of the entries at `keys`, to the chain, on CORD signed
+/// with the node's CORD key. The anchor is stored in the document under `anchor:<extrinsic_hash>` by
+/// `author_id`, so it syncs to peers along with the entries.
+///
+/// With `DocDigestKind::Merkle`, the anchored digest is a Merkle root, against which each entry
+/// can later be proven with `inclusion::entry_inclusion_proof`.
+///
+/// # Returns
+/// - The stored anchor.
+pub async fn anchor_doc(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    author_id: String,
+    keys: Option<Vec<String>>,
+    digest_kind: DocDigestKind,
+    anchor: Arc<dyn ChainAnchor>,
+) -> anyhow::Result<DocAnchor, DocError> {
+    let author = SS58AuthorId::decode(&author_id)
+        .map_err(|_| DocError::InvalidAuthorIdFormat)?;
+    let (digest, entries) = compute_anchor_digest(docs.clone(), doc_id.clone(), keys.as_deref(), digest_kind).await?;
+
+    let receipt = anchor
+        .anchor(digest)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, doc_id = %doc_id, backend = anchor.name(), "❌ Failed to anchor document");
+            DocError::FailedToAnchorDocument(Cause::new(e))
+        })?;
+
+    let anchor = DocAnchor {
+        digest: format!("0x{}", hex::encode(digest)),
+        keys,
+        entries,
+        block_hash: receipt.block_hash,
+        block_number: receipt.block_number,
+        extrinsic_hash: receipt.extrinsic_hash,
+        extrinsic_index: receipt.extrinsic_index,
+        account: receipt.account,
+        anchored_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| DocError::FailedToSetAnchor(Cause::new(e)))?
+            .as_secs(),
+        digest_kind,
+    };
+
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(&doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+    let anchor_json = serde_json::to_vec(&anchor)
+        .map_err(|e| DocError::FailedToSetAnchor(Cause::new(e)))?;
+    doc.set_bytes(author, encode_key(anchor_key(&anchor.extrinsic_hash).as_bytes()), anchor_json)
+        .await
+        .map_err(|e| DocError::FailedToSetAnchor(Cause::new(e)))?;
+
+    Ok(anchor)
+}
+
+/// Lists the anchors stored in a document, oldest first.
+pub async fn list_doc_anchors(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+) -> anyhow::Result<Vec<DocAnchor>, DocError> {
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(&doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let mut anchors = Vec::new();
+    let mut entries_stream = doc
+        .get_many(Query::key_prefix(ANCHOR_KEY_PREFIX.as_bytes()))
+        .await
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?;
+    while let Some(entry) = entries_stream.next().await {
+        let entry = entry.map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
+        let content = get_blob_entry(blobs.clone(), entry.record().content_hash()).await?;
+        let anchor: DocAnchor = serde_json::from_str(&content)
+            .map_err(|e| DocError::FailedToParseAnchor(Cause::new(e)))?;
+        anchors.push(anchor);
+    }
+
+    anchors.sort_by_key(|anchor| (anchor.block_number, anchor.extrinsic_index));
+    Ok(anchors)
+}
+
+/// Issues a W3C Verifiable Credential for an entry of a document with a schema.
+///
+/// The entry's JSON value, checked against the schema of the same author, becomes the credential
+/// subject, and the credential is signed by the keystore key `public` with a Data Integrity proof.
+/// The issuer is the key's `did:key`.
+///
+/// # Arguments
+/// * `holder` - DID or URI of the subject, set as the subject's `id`.
+/// * `key_type` - The key type of the key, which must be ed25519 or sr25519 and allowed to sign documents.
+///
+/// # Returns
+/// * `Value` - The signed credential.
+#[allow(clippy::too_many_arguments)]
+pub async fn issue_entry_credential(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    author_id: String,
+    key: String,
+    holder: Option<String>,
+    keystore: &StarterkitKeystore,
+    key_type: &KeyTypeInfo,
+    public: &[u8],
+) -> anyhow::Result<Value, DocError> {
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(&doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let author = SS58AuthorId::decode(&author_id)
+        .map_err(|_| DocError::InvalidAuthorIdFormat)?;
+    validate_key(&key, true)
+        .await
+        .map_err(|e| DocError::FailedToValidateKey(Cause::new(e)))?;
+    let doc = get_document(docs.clone(), namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let schema_entry = doc
+        .get_exact(author, encode_key("schema".as_bytes()), false)
+        .await
+        .map_err(|e| DocError::FailedToGetSchemaEntry(Cause::new(e)))?
+        .ok_or(DocError::DocumentHasNoSchema)?;
+    let schema_json: Value = serde_json::from_str(&get_blob_entry(blobs.clone(), schema_entry.content_hash()).await?)
+        .map_err(|e| DocError::FailedToParseSchemaJson(Cause::new(e)))?;
+    let validator = build_validator(docs, blobs.clone(), &schema_json).await?;
+
+    let entry = doc
+        .get_exact(author, encode_key(key.as_bytes()), false)
+        .await
+        .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?
+        .ok_or(DocError::EntryNotFound)?;
+    let mut subject: Value = serde_json::from_str(&get_blob_entry(blobs, entry.content_hash()).await?)
+        .map_err(|e| DocError::FailedToConvertValueJson(Cause::new(e)))?;
+
+    // the schema may have changed since the entry was written
+    if !validator.is_valid(&subject) {
+        return Err(DocError::ValueDoesNotMatchSchema);
+    }
+    let claims = subject.as_object_mut().ok_or(DocError::EntryNotJsonObject)?;
+    if let Some(holder) = holder {
+        claims.insert("id".to_string(), Value::String(holder));
+    }
+
+    let claims = CredentialClaims {
+        id: format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        subject,
+        // the schema is content-addressed, so the ID pins the exact version
+        schema_id: format!("urn:iroh-docs:{}:{}", doc_id, schema_entry.content_hash()),
+    };
+    issue_credential(keystore, key_type, public, claims).map_err(|e| {
+        tracing::warn!(error = %e, key_type = %key_type.name, "failed to sign credential");
+        DocError::FailedToIssueCredential(Cause::new(e))
+    })
+}
+
+/// A statement made by the node that can be revoked: an anchor of a document, or a credential
+/// issued for one of its entries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RevokedStatement {
+    /// The anchor made by `extrinsic_hash`, see `anchor_doc`.
+    Anchor { extrinsic_hash: String },
+    /// The credential `id` (`urn:uuid:...`), see `issue_entry_credential`.
+    Credential { id: String },
+}
+
+impl RevokedStatement {
+    /// The ID the revocation is stored and looked up under.
+    pub fn id(&self) -> &str {
+        match self {
+            RevokedStatement::Anchor { extrinsic_hash } => extrinsic_hash,
+            RevokedStatement::Credential { id } => id,
+        }
+    }
+}
+
+/// The revocation of a statement, as stored in its `revoked:<statement_id>` entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatementRevocation {
+    pub statement: RevokedStatement,
+    /// 0x-prefixed digest anchored on chain for the revocation, see `revocation_digest`.
+    pub digest: String,
+    pub reason: Option<String>,
+    pub block_hash: String,
+    pub block_number: u64,
+    pub extrinsic_hash: String,
+    pub extrinsic_index: u32,
+    /// SS58 CORD account that signed the extrinsic.
+    pub account: String,
+    /// Unix time in seconds at which the revocation was stored.
+    pub revoked_at: u64,
+}
+
+/// The digest anchored to revoke the statement `statement_id`: the blake3 hash of `revoke:`
+/// followed by the ID, so anyone holding the statement can look for its revocation on chain.
+pub fn revocation_digest(statement_id: &str) -> [u8; 32] {
+    *Hash::new(format!("revoke:{}", statement_id).as_bytes()).as_bytes()
+}
+
+/// Revokes an anchor or credential of a document. The revocation digest is anchored to the chain,
+/// on CORD signed with the node's CORD key, and the revocation stored in the document under
+/// `revoked:<statement_id>` by `author_id`, so it syncs to peers along with the entries.
+///
+/// # Returns
+/// - The stored revocation.
+#[allow(clippy::too_many_arguments)]
+pub async fn revoke_statement(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    author_id: String,
+    statement: RevokedStatement,
+    reason: Option<String>,
+    anchor: Arc<dyn ChainAnchor>,
+) -> anyhow::Result<StatementRevocation, DocError> {
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(&doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let author = SS58AuthorId::decode(&author_id)
+        .map_err(|_| DocError::InvalidAuthorIdFormat)?;
+    let doc = get_document(docs.clone(), namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    // credentials are not stored, so only anchors can be checked for existence
+    if let RevokedStatement::Anchor { extrinsic_hash } = &statement {
+        doc.get_one(Query::key_exact(encode_key(anchor_key(extrinsic_hash).as_bytes())))
+            .await
+            .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?
+            .ok_or(DocError::AnchorNotFound)?;
+    }
+    if get_statement_revocation(docs, blobs, doc_id.clone(), statement.id()).await?.is_some() {
+        return Err(DocError::StatementAlreadyRevoked);
+    }
+
+    let digest = revocation_digest(statement.id());
+    let receipt = anchor
+        .anchor(digest)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, doc_id = %doc_id, backend = anchor.name(), statement_id = %statement.id(), "❌ Failed to revoke statement");
+            DocError::FailedToRevokeStatement(Cause::new(e))
+        })?;
+
+    let revocation = StatementRevocation {
+        digest: format!("0x{}", hex::encode(digest)),
+        statement,
+        reason,
+        block_hash: receipt.block_hash,
+        block_number: receipt.block_number,
+        extrinsic_hash: receipt.extrinsic_hash,
+        extrinsic_index: receipt.extrinsic_index,
+        account: receipt.account,
+        revoked_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| DocError::FailedToSetRevocation(Cause::new(e)))?
+            .as_secs(),
+    };
+
+    let revocation_json = serde_json::to_vec(&revocation)
+        .map_err(|e| DocError::FailedToSetRevocation(Cause::new(e)))?;
+    doc.set_bytes(author, encode_key(revocation_key(revocation.statement.id()).as_bytes()), revocation_json)
+        .await
+        .map_err(|e| DocError::FailedToSetRevocation(Cause::new(e)))?;
+
+    Ok(revocation)
+}
+
+/// Returns the revocation of the statement `statement_id` stored in a document by any author, or
+/// `None` if it is active.
+pub async fn get_statement_revocation(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    statement_id: &str,
+) -> anyhow::Result<Option<StatementRevocation>, DocError> {
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(&doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let Some(entry) = doc
+        .get_one(Query::key_exact(encode_key(revocation_key(statement_id).as_bytes())))
+        .await
+        .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?
+    else {
+        return Ok(None);
+    };
+    let content = get_blob_entry(blobs, entry.content_hash()).await?;
+    let revocation = serde_json::from_str(&content)
+        .map_err(|e| DocError::FailedToParseRevocation(Cause::new(e)))?;
+    Ok(Some(revocation))
+}
+
+/// A CORD definition mirrored in a document, as stored in its `cord:<kind>:<id>` entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MirroredDefinition {
+    /// `space`, `schema` or `registry`.
+    pub kind: String,
+    pub id: String,
+    /// The decoded storage value, see `cord::registry::ChainDefinition`.
+    pub definition: Value,
+    /// The finalized block the definition was read at.
+    pub block_hash: String,
+    pub block_number: u64,
+    /// Unix time in seconds at which the definition was read.
+    pub synced_at: u64,
+}
+
+/// What a sync did to the entry of one definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefinitionSyncStatus {
+    /// The definition was not mirrored before.
+    Created,
+    /// The definition changed on chain and its entry was replaced.
+    Updated,
+    /// The entry already holds the current definition and was left as is.
+    Unchanged,
+    /// The definition is gone from the chain, so its entry was deleted.
+    Removed,
+    /// The definition is not on the chain and was never mirrored.
+    NotFound,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DefinitionSync {
+    pub kind: String,
+    pub id: String,
+    /// The entry key, `cord:<kind>:<id>`.
+    pub key: String,
+    pub status: DefinitionSyncStatus,
+}
+
+/// Mirrors space, schema and registry definitions from the latest finalized CORD block into a
+/// document, as `cord:<kind>:<id>` entries by `author_id`. Entries are only written when the
+/// definition changed, and deleted when it is gone from the chain.
+///
+/// # Arguments
+/// * `definitions` - The definitions to mirror, or `None` to refresh every definition the
+///   author already mirrored in the document.
+///
+/// # Returns
+/// * `Vec<DefinitionSync>` - What happened to each definition, in order.
+pub async fn sync_chain_definitions(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    author_id: String,
+    definitions: Option<Vec<(ChainDefinitionKind, String)>>,
+    cord_client: Arc<OnlineClient<PolkadotConfig>>,
+) -> anyhow::Result<Vec<DefinitionSync>, DocError> {
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(&doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let author = SS58AuthorId::decode(&author_id)
+        .map_err(|_| DocError::InvalidAuthorIdFormat)?;
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let mut mirrored = BTreeMap::new();
+    for kind in [ChainDefinitionKind::Space, ChainDefinitionKind::Schema, ChainDefinitionKind::Registry] {
+        let prefix = cord_definition_key(kind.as_str(), "");
+        let mut entries_stream = doc
+            .get_many(Query::author(author).key_prefix(prefix.as_bytes()))
+            .await
+            .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?;
+        while let Some(entry) = entries_stream.next().await {
+            let entry = entry.map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
+            let content = get_blob_entry(blobs.clone(), entry.record().content_hash()).await?;
+            let stored: MirroredDefinition = serde_json::from_str(&content)
+                .map_err(|e| DocError::FailedToParseChainDefinition(Cause::new(e)))?;
+            mirrored.insert(cord_definition_key(&stored.kind, &stored.id), stored);
+        }
+    }
+
+    let definitions = match definitions {
+        Some(definitions) => definitions,
+        None => mirrored
+            .values()
+            .map(|stored| {
+                ChainDefinitionKind::parse(&stored.kind)
+                    .map(|kind| (kind, stored.id.clone()))
+                    .map_err(|e| DocError::FailedToParseChainDefinition(Cause::new(e)))
+            })
+            .collect::<Result<_, _>>()?,
+    };
+
+    let mut synced = Vec::new();
+    for (kind, id) in definitions {
+        let key = cord_definition_key(kind.as_str(), &id);
+        let fetched = fetch_definition(cord_client.clone(), kind, &id)
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, kind = kind.as_str(), id = %id, "❌ Failed to read chain definition");
+                DocError::FailedToFetchChainDefinition(Cause::new(e.to_string()))
+            })?;
+        let stored = mirrored.get(&key);
+
+        let status = match (fetched, stored) {
+            (None, None) => DefinitionSyncStatus::NotFound,
+            (None, Some(_)) => {
+                doc.del(author, encode_key(key.as_bytes()))
+                    .await
+                    .map_err(|e| DocError::FailedToSetChainDefinition(Cause::new(e)))?;
+                DefinitionSyncStatus::Removed
+            },
+            (Some(fetched), Some(stored)) if fetched.definition == stored.definition => DefinitionSyncStatus::Unchanged,
+            (Some(fetched), stored) => {
+                let definition = MirroredDefinition {
+                    kind: kind.as_str().to_string(),
+                    id: id.clone(),
+                    definition: fetched.definition,
+                    block_hash: fetched.block_hash,
+                    block_number: fetched.block_number,
+                    synced_at: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_err(|e| DocError::FailedToSetChainDefinition(Cause::new(e)))?
+                        .as_secs(),
+                };
+                let definition_json = serde_json::to_vec(&definition)
+                    .map_err(|e| DocError::FailedToSetChainDefinition(Cause::new(e)))?;
+                doc.set_bytes(author, encode_key(key.as_bytes()), definition_json)
+                    .await
+                    .map_err(|e| DocError::FailedToSetChainDefinition(Cause::new(e)))?;
+                if stored.is_some() { DefinitionSyncStatus::Updated } else { DefinitionSyncStatus::Created }
+            },
+        };
+
+        synced.push(DefinitionSync { kind: kind.as_str().to_string(), id, key, status });
+    }
+
+    Ok(synced)
+}
+
+/// Registers the schema `author_id` set on a document as a CORD schema under the space
+/// authorization `authorization`, signed with the node's CORD key, and mirrors the new
+/// definition into the document like `sync_chain_definitions`. The schema is registered in the
+/// CORD format of `export_cord_schema`.
+///
+/// # Returns
+/// * `DefinitionSync` - The mirrored schema, whose `id` is the new schema identifier.
+pub async fn push_doc_schema(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    author_id: String,
+    authorization: String,
+    chain: ChainClient,
+) -> anyhow::Result<DefinitionSync, DocError> {
+    let schema = export_cord_schema(docs.clone(), blobs.clone(), doc_id.clone(), author_id.clone(), None).await?;
+    let schema = serde_json::to_string(&schema).map_err(|e| DocError::FailedToSerializeSchema(Cause::new(e)))?;
+
+    let schema_id = create_schema(&chain, &schema, &authorization)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, doc_id = %doc_id, "❌ Failed to register schema on chain");
+            DocError::FailedToPushSchema(Cause::new(e.to_string()))
+        })?;
+
+    let mut synced = sync_chain_definitions(
+        docs,
+        blobs,
+        doc_id,
+        author_id,
+        Some(vec![(ChainDefinitionKind::Schema, schema_id)]),
+        chain.online(),
+    ).await?;
+    synced.pop().ok_or_else(|| DocError::FailedToSetChainDefinition(Cause::new("the pushed schema was not mirrored")))
+}
+
+/// Converts a CORD schema, as authored with the CORD SDK or Cyra, to a JSON schema and adds it
+/// to an empty document like `add_doc_schema`. Schema details, which wrap the schema with its
+/// chain metadata, are accepted too.
+///
+/// # Returns
+/// * `(String, Value)` - The hash of the schema entry and the schema as stored.
+pub async fn import_cord_schema(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    author_id: String,
+    doc_id: String,
+    cord_schema: Value,
+) -> anyhow::Result<(String, Value), DocError> {
+    let schema = cord_schema_to_json_schema(&cord_schema).map_err(|e| {
+        tracing::warn!(error = %e, doc_id = %doc_id, "CORD schema cannot be imported");
+        DocError::InvalidCordSchema
+    })?;
+    let schema_json = serde_json::to_string(&schema).map_err(|e| DocError::FailedToSerializeSchema(Cause::new(e)))?;
+
+    let updated_hash = add_doc_schema(docs, blobs, author_id, doc_id, schema_json).await?;
+
+    Ok((updated_hash, schema))
+}
+
+/// Reads the schema `schema_id` registered on CORD, for `import_cord_schema`.
+///
+/// # Returns
+/// * `Value` - The CORD schema.
+pub async fn fetch_cord_schema(
+    cord_client: Arc<OnlineClient<PolkadotConfig>>,
+    schema_id: &str,
+) -> anyhow::Result<Value, DocError> {
+    let fetched = fetch_definition(cord_client, ChainDefinitionKind::Schema, schema_id)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, schema_id = %schema_id, "❌ Failed to read schema from chain");
+            DocError::FailedToFetchChainDefinition(Cause::new(e.to_string()))
+        })?
+        .ok_or(DocError::CordSchemaNotFound)?;
+
+    // the schema is stored as its JSON text next to its digest and space
+    fetched.definition.get("schema").cloned().ok_or(DocError::InvalidCordSchema)
+}
+
+/// Converts the schema `author_id` set on a document to a CORD schema, titled `title` unless it
+/// has a title already, or else after the document.
+///
+/// # Returns
+/// * `Value` - The CORD schema.
+pub async fn export_cord_schema(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    author_id: String,
+    title: Option<String>,
+) -> anyhow::Result<Value, DocError> {
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(&doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let author = SS58AuthorId::decode(&author_id)
+        .map_err(|_| DocError::InvalidAuthorIdFormat)?;
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let schema_entry = doc
+        .get_exact(author, encode_key("schema".as_bytes()), false)
+        .await
+        .map_err(|e| DocError::FailedToGetSchemaEntry(Cause::new(e)))?
+        .ok_or(DocError::DocumentHasNoSchema)?;
+    let schema = get_blob_entry(blobs, schema_entry.content_hash()).await?;
+    let schema: Value = serde_json::from_str(&schema).map_err(|e| DocError::FailedToSerializeSchema(Cause::new(e)))?;
+
+    json_schema_to_cord_schema(&schema, title.as_deref().unwrap_or(&doc_id)).map_err(|e| {
+        tracing::warn!(error = %e, doc_id = %doc_id, "Document schema cannot be exported to CORD");
+        DocError::SchemaNotCordCompatible
+    })
+}
+
+/// Checks that `author_id` may write to a document when `--require-chain-authorization` is set.
+///
+/// Documents that mirror CORD registries (see `sync_chain_definitions`) only accept entries from
+/// authors whose linked DID holds an authorization on one of them, as read from the chain or from
+/// the registry's cache. Other documents, and every document without the setting, accept any
+/// author.
+pub async fn check_author_authorization(
+    docs: Arc<NodeDocs>,
+    cord_client: Arc<OnlineClient<PolkadotConfig>>,
+    authorizations: &AuthorizationRegistry,
+    doc_id: &str,
+    author_id: &str,
+) -> anyhow::Result<(), DocError> {
+    if !authorizations.required() {
+        return Ok(());
+    }
+
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let registry_prefix = cord_definition_key(ChainDefinitionKind::Registry.as_str(), "");
+    let mut registries = Vec::new();
+    let mut entries_stream = doc
+        .get_many(Query::key_prefix(registry_prefix.as_bytes()))
+        .await
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?;
+    while let Some(entry) = entries_stream.next().await {
+        let entry = entry.map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
+        let key = String::from_utf8(decode_key(entry.id().key())).map_err(|e| DocError::FailedToDecodeEntryKey(Cause::new(e)))?;
+        if let Some(registry_id) = key.strip_prefix(&registry_prefix) {
+            registries.push(registry_id.to_string());
+        }
+    }
+    if registries.is_empty() {
+        return Ok(());
+    }
+
+    let link = authorizations.get(author_id).ok_or(DocError::AuthorHasNoLinkedDid)?;
+    let account = parse_cord_did(&link.did).map_err(|_| DocError::AuthorHasNoLinkedDid)?;
+
+    let authorization = match authorizations.cached(&link.authorization) {
+        Some(authorization) => authorization,
+        None => {
+            let authorization = fetch_registry_authorization(cord_client, &link.authorization)
+                .await
+                .map_err(|e| {
+                    tracing::error!(error = %e, authorization = %link.authorization, "❌ Failed to read authorization from chain");
+                    DocError::FailedToCheckAuthorization(Cause::new(e.to_string()))
+                })?;
+            authorizations.cache(&link.authorization, authorization.clone());
+            authorization
+        },
+    };
+
+    match authorization {
+        Some(authorization)
+            if authorization.delegate == *AsRef::<[u8; 32]>::as_ref(&account)
+                && registries.contains(&authorization.registry_id) => Ok(()),
+        _ => Err(DocError::AuthorNotAuthorized),
+    }
+}
+
+/// Stores a chain event in a document under `cord:event:<block_number>-<event_index>` by
+/// `author_id`. Writing the same event again replaces the entry with identical content.
+///
+/// # Returns
+/// * `String` - The key of the entry.
+pub async fn record_chain_event(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    author_id: String,
+    event: &ChainEventRecord,
+) -> anyhow::Result<String, DocError> {
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(&doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let author = SS58AuthorId::decode(&author_id)
+        .map_err(|_| DocError::InvalidAuthorIdFormat)?;
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
 
-    // put the key-value pair in the document
-    let encoded_key = encode_key(key.as_bytes());
-    let hash = doc
-        .set_bytes(author, encoded_key, value.into_bytes())
+    let key = cord_event_key(event.block_number, event.event_index);
+    let event_json = serde_json::to_vec(event)
+        .map_err(|e| DocError::FailedToSetChainEvent(Cause::new(e)))?;
+    doc.set_bytes(author, encode_key(key.as_bytes()), event_json)
         .await
-        .map_err(|_| DocError::FailedToSetEntryBytes)?;
+        .map_err(|e| DocError::FailedToSetChainEvent(Cause::new(e)))?;
 
-    Ok(hash.to_string())
+    Ok(key)
 }
 
-/// Adds a file as an entry to the document, only if no schema is defined.
+/// Adds a file as an entry to the document.
+///
+/// A document with a schema only takes files as attachments, under keys starting with
+/// `files/`, which its JSON entries can link to, see `attachment_links`.
 ///
 /// # Parameters
 /// - `docs`: Shared reference to the document store.
@@ -508,7 +2029,7 @@ pub struct ImportFileOutcome {
 }
 
 pub async fn set_entry_file (
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
     doc_id: String,
     author_id: String,
     key: String,
@@ -523,10 +2044,10 @@ pub async fn set_entry_file (
 
     validate_key(&key, true)
         .await
-        .map_err(|_| DocError::FailedToValidateKey)?;
+        .map_err(|e| DocError::FailedToValidateKey(Cause::new(e)))?;
 
     let path = PathBuf::from(file_path);
-    if !path.exists() {
+    if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
         return Err(DocError::FileDoesNotExist);
     }
 
@@ -539,28 +2060,29 @@ pub async fn set_entry_file (
     let schema_entry = doc
         .get_exact(author, encoded_schema_key.clone(), true)
         .await
-        .map_err(|_| DocError::FailedToGetSchemaEntry)?;
+        .map_err(|e| DocError::FailedToGetSchemaEntry(Cause::new(e)))?;
 
-    if schema_entry.is_some() {
+    if schema_entry.is_some() && !is_attachment_key(&key) {
         return Err(DocError::FileImportNotAllowedWithSchema);
     }
 
+    retain_current_version(&doc, author, &key).await?;
     let encoded_key = encode_key(key.clone().as_bytes());
     let progress = doc
         .import_file(author, Bytes::from(encoded_key), &path, false)
         .await
-        .map_err(|_| DocError::FailedToImportFile)?;
+        .map_err(|e| DocError::FailedToImportFile(Cause::new(e)))?;
 
     let outcome = progress
         .finish()
         .await
-        .map_err(|_| DocError::FailedToFinishFileImport)?;
+        .map_err(|e| DocError::FailedToFinishFileImport(Cause::new(e)))?;
 
     Ok(ImportFileOutcome {
         hash: outcome.hash.to_string(),
         size: outcome.size,
         key: String::from_utf8(outcome.key.to_vec())
-            .map_err(|_| DocError::FailedToConvertKeyUtf8)?,
+            .map_err(|e| DocError::FailedToConvertKeyUtf8(Cause::new(e)))?,
     })
 }
 
@@ -593,10 +2115,27 @@ pub struct RecordDetails {
     pub hash: String,
     pub len: u64,
     pub timestamp: u64,
+    /// The latest anchor that covers the entry, set by `attach_entry_anchors`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<EntryAnchor>,
+}
+
+/// The chain block an entry was anchored in, which proves the entry existed no later than the
+/// block. Recomputing the anchor's digest, see `compute_anchor_digest`, gives the `digest` remarked
+/// in the extrinsic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryAnchor {
+    pub block_hash: String,
+    pub block_number: u64,
+    pub extrinsic_hash: String,
+    pub extrinsic_index: u32,
+    pub digest: String,
+    /// The keys the digest covers, or `None` for the whole document.
+    pub keys: Option<Vec<String>>,
 }
 
 pub async fn get_entry(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
     doc_id: String,
     author_id: String,
     key: String,
@@ -611,7 +2150,7 @@ pub async fn get_entry(
 
     validate_key(&key, false)
         .await
-        .map_err(|_| DocError::FailedToValidateKey)?;
+        .map_err(|e| DocError::FailedToValidateKey(Cause::new(e)))?;
 
     let doc = get_document(docs, namespace_id)
         .await
@@ -621,17 +2160,17 @@ pub async fn get_entry(
     let entry = doc
         .get_exact(author, encoded_key, include_empty)
         .await
-        .map_err(|_| DocError::FailedToGetEntry)?;
+        .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
 
     if let Some(entry) = entry {
         let decoded_key = decode_key(entry.id().key());
         let encode_author = SS58AuthorId::from_author_id(&entry.id().author())
-            .map_err(|_| DocError::FailedToEncodeAuthorId)?;
+            .map_err(|e| DocError::FailedToEncodeAuthorId(Cause::new(e)))?;
 
         let id_details = EntryIdDetails {
             doc: entry.id().namespace().to_string(),
             key: String::from_utf8(decoded_key)
-                .map_err(|_| DocError::FailedToDecodeEntryKey)?,
+                .map_err(|e| DocError::FailedToDecodeEntryKey(Cause::new(e)))?,
             author: encode_author.as_ss58().to_string(),
         };
 
@@ -639,6 +2178,7 @@ pub async fn get_entry(
             hash: entry.record().content_hash().to_string(),
             len: entry.record().content_len(),
             timestamp: entry.record().timestamp(),
+            anchor: None,
         };
 
         return Ok(Some(EntryDetails {
@@ -650,6 +2190,99 @@ pub async fn get_entry(
     Ok(None)
 }
 
+/// An attachment an entry value links to, see `attachment_links`.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryAttachment {
+    pub key: String,
+    /// The latest file at the key, whichever author wrote it. `None` if it was deleted or has
+    /// not synced yet.
+    pub entry: Option<EntryDetails>,
+}
+
+/// The attachments the value of `author_id`'s entry at `key` links to.
+pub async fn entry_attachments(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    author_id: String,
+    key: String,
+) -> anyhow::Result<Vec<EntryAttachment>, DocError> {
+    let entry = get_entry(docs.clone(), doc_id.clone(), author_id, key, false)
+        .await?
+        .ok_or(DocError::EntryNotFound)?;
+    let hash = Hash::from_str(&entry.record.hash).map_err(|e| DocError::FailedToParseHash(Cause::new(e)))?;
+    let content = crate::blobs::read_blob_cached(&blobs, hash)
+        .await
+        .map_err(|e| DocError::FailedToReadBlob(Cause::new(e)))?;
+    let value: Value = serde_json::from_slice(&content)
+        .map_err(|e| DocError::FailedToConvertValueJson(Cause::new(e)))?;
+
+    let mut attachments = Vec::new();
+    for link in attachment_links(&value).into_iter().filter(|link| is_attachment_key(link)) {
+        let versions = get_entries(docs.clone(), doc_id.clone(), serde_json::json!({ "key": link })).await?;
+        let entry = ConflictResolution::LastWriterWins.resolve(&link, &versions);
+        attachments.push(EntryAttachment { key: link, entry });
+    }
+    Ok(attachments)
+}
+
+/// Sets the `anchor` of each entry to the latest anchor of the document whose digest still
+/// matches the current entries and covers the entry, so the entry provably existed when the
+/// anchor's block was finalized. Entries without such an anchor, empty ones and anchors and
+/// revocations, which digests leave out, are left as is.
+///
+/// # Arguments
+/// * `entries` - Entries of the document `doc_id`, as returned by `get_entry` or `get_entries`.
+pub async fn attach_entry_anchors(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    entries: &mut [EntryDetails],
+) -> anyhow::Result<(), DocError> {
+    let anchors = list_doc_anchors(docs.clone(), blobs, doc_id.clone()).await?;
+
+    // whether each anchor still matches, computed once and newest first
+    let mut matching: Vec<(DocAnchor, bool)> = Vec::new();
+    for anchor in anchors.into_iter().rev() {
+        if !entries.iter().any(|entry| anchor_covers(&anchor, entry)) {
+            continue;
+        }
+        let matches_current = match compute_anchor_digest(docs.clone(), doc_id.clone(), anchor.keys.as_deref(), anchor.digest_kind).await {
+            Ok((digest, _)) => anchor.digest == format!("0x{}", hex::encode(digest)),
+            // an anchored key that was deleted since no longer matches
+            Err(DocError::EntryNotFound) => false,
+            Err(e) => return Err(e),
+        };
+        matching.push((anchor, matches_current));
+    }
+
+    for entry in entries.iter_mut() {
+        let anchor = matching
+            .iter()
+            .find(|(anchor, matches_current)| *matches_current && anchor_covers(anchor, entry));
+        entry.record.anchor = anchor.map(|(anchor, _)| EntryAnchor {
+            block_hash: anchor.block_hash.clone(),
+            block_number: anchor.block_number,
+            extrinsic_hash: anchor.extrinsic_hash.clone(),
+            extrinsic_index: anchor.extrinsic_index,
+            digest: anchor.digest.clone(),
+            keys: anchor.keys.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+// Whether the digest of `anchor` includes `entry`
+fn anchor_covers(anchor: &DocAnchor, entry: &EntryDetails) -> bool {
+    let key = &entry.namespace.key;
+    entry.record.len > 0
+        && !key.starts_with(ANCHOR_KEY_PREFIX)
+        && !key.starts_with(REVOCATION_KEY_PREFIX)
+        && !is_history_key(key)
+        && anchor.keys.as_ref().is_none_or(|keys| keys.contains(key))
+}
+
 /// Retrieves a blob entry's content using its hash.
 /// 
 /// # Arguments
@@ -659,19 +2292,55 @@ pub async fn get_entry(
 /// # Returns
 /// The content of the blob as a `String`.
 pub async fn get_entry_blob(
-    blobs: Arc<Blobs<Store>>,
+    blobs: Arc<NodeBlobs>,
     hash: String,
 ) -> anyhow::Result<String, DocError> {
     let hash = Hash::from_str(&hash)
-        .map_err(|_| DocError::FailedToParseHash)?;
+        .map_err(|e| DocError::FailedToParseHash(Cause::new(e)))?;
 
     let content = get_blob_entry(blobs, hash)
         .await
-        .map_err(|_| DocError::FailedToReadBlob)?;
+        .map_err(|e| DocError::FailedToReadBlob(Cause::new(e)))?;
 
     Ok(content)
 }
 
+/// Whether an entry of a document, deleted or past versions included, has the content `hash`.
+///
+/// # Arguments
+/// * `docs` - Shared reference to the `Docs` store.
+/// * `doc_id` - The document ID as a string.
+/// * `hash` - The hash of the content (as a hex string).
+pub async fn doc_references_hash(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    hash: &str,
+) -> anyhow::Result<bool, DocError> {
+    let hash = Hash::from_str(hash)
+        .map_err(|e| DocError::FailedToParseHash(Cause::new(e)))?;
+    let namespace_id_vec = decode_doc_id(&doc_id)
+        .map_err(|_| DocError::InvalidDocumentIdFormat)?;
+
+    let doc = get_document(docs, NamespaceId::from(namespace_id_vec))
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let mut entries_stream = doc
+        .get_many(Query::all())
+        .await
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?;
+
+    while let Some(entry) = entries_stream.next().await {
+        let entry = entry
+            .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
+        if entry.content_hash() == hash {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
 /// Retrieves entries from a document based on provided query parameters.
 /// 
 /// # Arguments
@@ -689,7 +2358,7 @@ pub async fn get_entry_blob(
 /// # Returns
 /// A list of `EntryDetails` matching the query.
 pub async fn get_entries(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
     doc_id: String,
     query_params: serde_json::Value,
 ) -> anyhow::Result<Vec<EntryDetails>, DocError> {
@@ -708,7 +2377,7 @@ pub async fn get_entries(
     if let Some(key) = query_params.get("key").and_then(|v| v.as_str()) {
         validate_key(key, false)
             .await
-            .map_err(|_| DocError::FailedToValidateKey)?;
+            .map_err(|e| DocError::FailedToValidateKey(Cause::new(e)))?;
         let encoded_key = encode_key(key.as_bytes());
         query = query.key_exact(encoded_key);
     }
@@ -760,20 +2429,20 @@ pub async fn get_entries(
     let mut entries_stream = doc
         .get_many(query)
         .await
-        .map_err(|_| DocError::FailedToGetEntries)?;
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?;
 
     while let Some(entry) = entries_stream.next().await {
         let entry = entry
-            .map_err(|_| DocError::FailedToGetEntry)?;
+            .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
 
         let encode_author = SS58AuthorId::from_author_id(&entry.id().author())
-            .map_err(|_| DocError::FailedToEncodeAuthorId)?;
+            .map_err(|e| DocError::FailedToEncodeAuthorId(Cause::new(e)))?;
         let decoded_key = decode_key(entry.id().key());
 
         let id_details = EntryIdDetails {
             doc: entry.id().namespace().to_string(),
             key: String::from_utf8(decoded_key)
-                .map_err(|_| DocError::FailedToDecodeEntryKey)?,
+                .map_err(|e| DocError::FailedToDecodeEntryKey(Cause::new(e)))?,
             author: encode_author.as_ss58().to_string(),
         };
         
@@ -781,6 +2450,7 @@ pub async fn get_entries(
             hash: entry.record().content_hash().to_string(),
             len: entry.record().content_len(),
             timestamp: entry.record().timestamp(),
+            anchor: None,
         };
 
         entries.push(EntryDetails {
@@ -792,7 +2462,181 @@ pub async fn get_entries(
     Ok(entries)
 }
 
+// Larger values are not decoded for filters, and read as `null`
+const MAX_FILTERED_VALUE: u64 = 1024 * 1024;
+
+/// The entries out of `entries` that match `filter`, in the same order.
+///
+/// Values are only read if the filter uses them. A value that is not JSON, larger than 1 MiB or
+/// not downloaded yet is `null` to the filter.
+pub async fn filter_entries(
+    blobs: Arc<NodeBlobs>,
+    entries: Vec<EntryDetails>,
+    filter: &Filter,
+) -> anyhow::Result<Vec<EntryDetails>, DocError> {
+    let mut matched = Vec::new();
+    for entry in entries {
+        let value = if filter.reads_value() && entry.record.len > 0 && entry.record.len <= MAX_FILTERED_VALUE {
+            let hash = Hash::from_str(&entry.record.hash)
+                .map_err(|e| DocError::FailedToReadBlob(Cause::new(e)))?;
+            match crate::blobs::read_blob_cached(&blobs, hash).await {
+                Ok(content) => serde_json::from_slice(&content).unwrap_or(Value::Null),
+                Err(_) => Value::Null,
+            }
+        } else {
+            Value::Null
+        };
+        if filter.matches(&entry, &value) {
+            matched.push(entry);
+        }
+    }
+    Ok(matched)
+}
+
+/// The versions of a key written by different authors, as listed by `list_conflicts`.
+#[derive(Serialize, Debug, Clone)]
+pub struct KeyConflict {
+    pub key: String,
+    /// The live version of each author, newest first.
+    pub versions: Vec<EntryDetails>,
+}
+
+/// A custom conflict resolution: picks the version of `key` to read out of `versions`, or `None`
+/// to read none of them.
+pub type ConflictResolver = Arc<dyn Fn(&str, &[EntryDetails]) -> Option<EntryDetails> + Send + Sync>;
+
+/// Names of the conflict resolutions the API accepts, see `ConflictResolution::from_name`.
+pub const CONFLICT_RESOLUTION_NAMES: &[&str] = &["last_writer_wins", "author_priority"];
+
+/// How a read picks one version of a key that several authors wrote.
+///
+/// iroh-docs keeps an entry per author and key, so concurrent writes of a key by two authors
+/// both survive sync. Resolution only applies to reads; the versions are left in the document.
+#[derive(Clone)]
+pub enum ConflictResolution {
+    /// The version with the latest timestamp, ties broken by content hash, as iroh-docs does
+    /// for the entries of one author.
+    LastWriterWins,
+    /// The version of the first listed author (SS58) who wrote the key, or the latest version if
+    /// none of them did.
+    AuthorPriority(Vec<String>),
+    /// A callback for library users.
+    Custom(ConflictResolver),
+}
+
+impl ConflictResolution {
+    /// The resolution called `name` in `CONFLICT_RESOLUTION_NAMES`. `author_priority` is the
+    /// author order of `author_priority` and ignored otherwise.
+    pub fn from_name(name: &str, author_priority: Vec<String>) -> anyhow::Result<Self, DocError> {
+        match name {
+            "last_writer_wins" => Ok(ConflictResolution::LastWriterWins),
+            "author_priority" => Ok(ConflictResolution::AuthorPriority(author_priority)),
+            _ => Err(DocError::InvalidConflictResolution),
+        }
+    }
+
+    /// Picks the version of `key` to read out of `versions`, the entries of different authors.
+    pub fn resolve(&self, key: &str, versions: &[EntryDetails]) -> Option<EntryDetails> {
+        let latest = || {
+            versions
+                .iter()
+                .max_by(|a, b| (a.record.timestamp, &a.record.hash).cmp(&(b.record.timestamp, &b.record.hash)))
+                .cloned()
+        };
+        match self {
+            ConflictResolution::LastWriterWins => latest(),
+            ConflictResolution::AuthorPriority(authors) => authors
+                .iter()
+                .find_map(|author| versions.iter().find(|version| &version.namespace.author == author).cloned())
+                .or_else(latest),
+            ConflictResolution::Custom(resolver) => resolver(key, versions),
+        }
+    }
+}
+
+/// Reduces `entries` to one per key, picked by `resolution`. Keys keep the order of their first
+/// entry.
+pub fn resolve_entries(entries: Vec<EntryDetails>, resolution: &ConflictResolution) -> Vec<EntryDetails> {
+    let mut keys: Vec<String> = Vec::new();
+    let mut versions: BTreeMap<String, Vec<EntryDetails>> = BTreeMap::new();
+    for entry in entries {
+        let key = entry.namespace.key.clone();
+        if !versions.contains_key(&key) {
+            keys.push(key.clone());
+        }
+        versions.entry(key).or_default().push(entry);
+    }
+
+    keys.into_iter()
+        .filter_map(|key| resolution.resolve(&key, &versions[&key]))
+        .collect()
+}
+
+/// Lists the keys of a document that more than one author holds a live (non-empty) entry for.
+/// Signature entries (`sig:<key>`) are left out, since they follow the entries they sign.
+///
+/// # Arguments
+/// * `docs` - Shared reference to the `Docs` store.
+/// * `doc_id` - The document ID.
+/// * `key_prefix` - Only list keys starting with this prefix.
+///
+/// # Returns
+/// The conflicting keys in key order.
+pub async fn list_conflicts(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    key_prefix: Option<String>,
+) -> anyhow::Result<Vec<KeyConflict>, DocError> {
+    let query_params = match key_prefix {
+        Some(key_prefix) => serde_json::json!({ "key_prefix": key_prefix }),
+        None => serde_json::json!({}),
+    };
+    let entries = get_entries(docs, doc_id, query_params).await?;
+
+    let mut versions: BTreeMap<String, Vec<EntryDetails>> = BTreeMap::new();
+    for entry in entries {
+        if entry.namespace.key.starts_with(SIGNATURE_KEY_PREFIX) {
+            continue;
+        }
+        versions.entry(entry.namespace.key.clone()).or_default().push(entry);
+    }
+
+    Ok(versions
+        .into_iter()
+        .filter(|(_, versions)| versions.len() > 1)
+        .map(|(key, mut versions)| {
+            versions.sort_by(|a, b| (b.record.timestamp, &b.record.hash).cmp(&(a.record.timestamp, &a.record.hash)));
+            KeyConflict { key, versions }
+        })
+        .collect())
+}
+
+/// Reads a key of a document regardless of its author, picking one version with `resolution`
+/// when several authors wrote it.
+///
+/// # Arguments
+/// * `docs` - Shared reference to the `Docs` store.
+/// * `doc_id` - The document ID.
+/// * `key` - The key to read.
+/// * `resolution` - How to pick a version of the key.
+///
+/// # Returns
+/// The picked entry and the number of live versions of the key, or `None` if it has none or
+/// the resolution picked none.
+pub async fn get_resolved_entry(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    key: String,
+    resolution: &ConflictResolution,
+) -> anyhow::Result<Option<(EntryDetails, usize)>, DocError> {
+    let versions = get_entries(docs, doc_id, serde_json::json!({ "key": key })).await?;
+    Ok(resolution.resolve(&key, &versions).map(|entry| (entry, versions.len())))
+}
+
 /// Deletes an entry from a document using author ID and key.
+///
+/// The entry is moved to the trash first, from which `trash::restore_entry` puts it back until
+/// it is emptied or `--trash-retention` passes.
 /// 
 /// # Arguments
 /// * `docs` - Shared reference to the `Docs` store.
@@ -803,7 +2647,7 @@ pub async fn get_entries(
 /// # Returns
 /// The number of deleted entries (should be 1 if successful).
 pub async fn delete_entry(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
     doc_id: String,
     author_id: String,
     key: String,
@@ -817,29 +2661,80 @@ pub async fn delete_entry(
 
     validate_key(&key, true)
         .await
-        .map_err(|_| DocError::FailedToValidateKey)?;
+        .map_err(|e| DocError::FailedToValidateKey(Cause::new(e)))?;
 
     let doc = get_document(docs.clone(), namespace_id)
         .await
         .map_err(|_| DocError::DocumentNotFound)?;
 
     let encoded_key = encode_key(key.clone().as_bytes());
-    let entry = get_entry(docs, doc_id.clone(), author_id.clone(), key.clone(), false)
+    let entry = doc
+        .get_exact(author, encoded_key.clone(), false)
         .await
-        .map_err(|_| DocError::FailedToGetEntry)?;
+        .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?
+        .ok_or(DocError::EntryNotFound)?;
 
-    if entry.is_none() {
-        return Err(DocError::EntryNotFound);
-    }
+    let entries = [entry];
+    retain_versions(&doc, author, &entries).await?;
+    move_to_trash(&doc, author, &entries).await?;
 
     let delete = doc
         .del(author, encoded_key)
         .await
-        .map_err(|_| DocError::FailedToDeleteEntry)?;
+        .map_err(|e| DocError::FailedToDeleteEntry(Cause::new(e)))?;
 
     Ok(delete)
 }
 
+/// Deletes every entry of an author whose key starts with `prefix`, with a single request to the
+/// docs actor instead of one per key. The entries are moved to the trash first, like with
+/// `delete_entry`.
+///
+/// # Arguments
+/// * `docs` - Shared reference to the `Docs` store.
+/// * `doc_id` - The document ID (base64-encoded).
+/// * `author_id` - SS58-encoded author ID of the entries.
+/// * `prefix` - The key prefix of the entries to delete.
+///
+/// # Returns
+/// The number of deleted entries.
+pub async fn delete_entries_by_prefix(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    author_id: String,
+    prefix: String,
+) -> anyhow::Result<usize, DocError> {
+    let namespace_id_vec = decode_doc_id(&doc_id)
+        .map_err(|_| DocError::InvalidDocumentIdFormat)?;
+    let namespace_id = NamespaceId::from(namespace_id_vec);
+
+    let author = SS58AuthorId::decode(&author_id)
+        .map_err(|_| DocError::InvalidAuthorIdFormat)?;
+
+    validate_key_prefix(&prefix)
+        .await
+        .map_err(|e| DocError::FailedToValidateKey(Cause::new(e)))?;
+
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let entries: Vec<iroh_docs::Entry> = doc
+        .get_many(Query::author(author).key_prefix(prefix.as_bytes()))
+        .await
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?
+        .try_collect()
+        .await
+        .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
+    retain_versions(&doc, author, &entries).await?;
+    move_to_trash(&doc, author, &entries).await?;
+
+    // Keys are stored null-terminated, so the bare prefix matches every key that starts with it
+    doc.del(author, prefix.into_bytes())
+        .await
+        .map_err(|e| DocError::FailedToDeleteEntry(Cause::new(e)))
+}
+
 /// Leaves the current document, releasing resources and closing its state.
 /// 
 /// # Arguments
@@ -849,8 +2744,95 @@ pub async fn delete_entry(
 /// # Returns
 /// An empty result on success.
 pub async fn leave(
-    docs: Arc<Docs<Store>>,
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+) -> anyhow::Result<(), DocError> {
+    let namespace_id_vec = decode_doc_id(&doc_id)
+        .map_err(|_| DocError::InvalidDocumentIdFormat)?;
+    let namespace_id = NamespaceId::from(namespace_id_vec);
+
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    doc.leave()
+        .await
+        .map_err(|e| DocError::FailedToLeaveDocument(Cause::new(e)))?;
+
+    Ok(())
+}
+
+/// Retrieves the current open status of a document.
+/// 
+/// # Arguments
+/// * `docs` - Shared reference to the `Docs` store.
+/// * `doc_id` - The document ID (base64-encoded).
+///
+/// # Returns
+/// The `OpenState` representing whether the document is joined or not.
+pub async fn status (
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+) -> anyhow::Result<OpenState, DocError> {
+    let namespace_id_vec = decode_doc_id(&doc_id)
+        .map_err(|_| DocError::InvalidDocumentIdFormat)?;
+    let namespace_id = NamespaceId::from(namespace_id_vec);
+
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let status = doc
+        .status()
+        .await
+        .map_err(|e| DocError::FailedToGetDocumentStatus(Cause::new(e)))?;
+
+    Ok(status)
+}
+
+/// Fetches the download policy of a document, if any.
+///
+/// # Arguments
+/// * `docs` - Shared reference to the `Docs` store.
+/// * `doc_id` - The document ID (base64-encoded).
+///
+/// # Returns
+/// A JSON representation of the download policy.
+pub async fn get_download_policy(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+) -> anyhow::Result<serde_json::Value, DocError> {
+    let namespace_id_vec = decode_doc_id(&doc_id)
+        .map_err(|_| DocError::InvalidDocumentIdFormat)?;
+    let namespace_id = NamespaceId::from(namespace_id_vec);
+
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let download_policy = doc
+        .get_download_policy()
+        .await
+        .map_err(|e| DocError::FailedToGetDownloadPolicy(Cause::new(e)))?;
+
+    let api_policy = ApiDownloadPolicy(download_policy);
+
+    Ok(api_policy.to_json())
+}
+
+/// Sets or updates the download policy of a document.
+/// 
+/// # Arguments
+/// * `docs` - Shared reference to the `Docs` store.
+/// * `doc_id` - The document ID (base64-encoded).
+/// * `download_policy` - JSON object representing the download policy.
+///
+/// # Returns
+/// An empty result on success.
+pub async fn set_download_policy(
+    docs: Arc<NodeDocs>,
     doc_id: String,
+    download_policy: serde_json::Value,
 ) -> anyhow::Result<(), DocError> {
     let namespace_id_vec = decode_doc_id(&doc_id)
         .map_err(|_| DocError::InvalidDocumentIdFormat)?;
@@ -860,53 +2842,184 @@ pub async fn leave(
         .await
         .map_err(|_| DocError::DocumentNotFound)?;
 
-    doc.leave()
+    let api_policy = ApiDownloadPolicy::from_json(&download_policy)
+        .map_err(|e| DocError::FailedToDecodeDownloadPolicy(Cause::new(e)))?;
+
+    doc.set_download_policy(api_policy.0)
+        .await
+        .map_err(|e| DocError::FailedToSetDownloadPolicy(Cause::new(e)))?;
+
+    Ok(())
+}
+
+/// Only downloads the content of entries whose key starts with one of `prefixes`, and of their
+/// signatures, for partial replicas of large documents. The entries themselves, with their keys
+/// and content hashes, still sync, and content downloaded before is kept. Without prefixes, all
+/// content is downloaded again.
+///
+/// This sets the document's download policy, replacing any other.
+pub async fn set_sync_prefixes(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    prefixes: Vec<String>,
+) -> anyhow::Result<(), DocError> {
+    check_sync_prefixes(&prefixes)?;
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(&doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    doc.set_download_policy(ApiDownloadPolicy::only_prefixes(&prefixes).0)
+        .await
+        .map_err(|e| DocError::FailedToSetDownloadPolicy(Cause::new(e)))?;
+
+    Ok(())
+}
+
+/// The key prefixes a document downloads content for, see `set_sync_prefixes`.
+///
+/// # Returns
+/// * `Option<Vec<String>>` - Empty if all content is downloaded, `None` if the download policy
+///   was set some other way.
+pub async fn get_sync_prefixes(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+) -> anyhow::Result<Option<Vec<String>>, DocError> {
+    let namespace_id = NamespaceId::from(
+        decode_doc_id(&doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?
+    );
+    let doc = get_document(docs, namespace_id)
+        .await
+        .map_err(|_| DocError::DocumentNotFound)?;
+
+    let download_policy = doc
+        .get_download_policy()
         .await
-        .map_err(|_| DocError::FailedToLeaveDocument)?;
+        .map_err(|e| DocError::FailedToGetDownloadPolicy(Cause::new(e)))?;
+
+    Ok(ApiDownloadPolicy(download_policy).prefixes())
+}
 
+// An empty prefix would match every key, which is what no prefixes already mean
+fn check_sync_prefixes(prefixes: &[String]) -> anyhow::Result<(), DocError> {
+    if prefixes.iter().any(String::is_empty) {
+        return Err(DocError::InvalidSyncPrefix);
+    }
     Ok(())
 }
 
-/// Retrieves the current open status of a document.
-/// 
-/// # Arguments
-/// * `docs` - Shared reference to the `Docs` store.
-/// * `doc_id` - The document ID (base64-encoded).
+/// A serializable view of a live document event.
 ///
-/// # Returns
-/// The `OpenState` representing whether the document is joined or not.
-pub async fn status (
-    docs: Arc<Docs<Store>>,
-    doc_id: String,
-) -> anyhow::Result<OpenState, DocError> {
-    let namespace_id_vec = decode_doc_id(&doc_id)
-        .map_err(|_| DocError::InvalidDocumentIdFormat)?;
-    let namespace_id = NamespaceId::from(namespace_id_vec);
+/// This mirrors `iroh_docs::engine::LiveEvent`, with entries rendered as `EntryDetails`
+/// and peers rendered as strings so the events can be sent to API clients as JSON.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DocEvent {
+    /// An entry was inserted by this node.
+    InsertLocal { entry: EntryDetails },
+    /// An entry was received from a peer.
+    InsertRemote {
+        from: String,
+        entry: EntryDetails,
+        content_status: String,
+    },
+    /// The content of an entry was downloaded and is now available locally.
+    ContentReady { hash: String },
+    /// All content queued by the last sync run has been processed.
+    PendingContentReady,
+    /// A new neighbor joined the document swarm.
+    NeighborUp { peer: String },
+    /// A neighbor left the document swarm.
+    NeighborDown { peer: String },
+    /// A set-reconciliation sync with a peer finished.
+    SyncFinished {
+        peer: String,
+        entries_received: usize,
+        entries_sent: usize,
+        error: Option<String>,
+    },
+}
 
-    let doc = get_document(docs, namespace_id)
-        .await
-        .map_err(|_| DocError::DocumentNotFound)?;
+impl DocEvent {
+    /// Converts an `iroh_docs` live event into a `DocEvent`.
+    pub fn from_live_event(event: LiveEvent) -> anyhow::Result<Self, DocError> {
+        let doc_event = match event {
+            LiveEvent::InsertLocal { entry } => DocEvent::InsertLocal {
+                entry: entry_to_details(&entry)?,
+            },
+            LiveEvent::InsertRemote { from, entry, content_status } => DocEvent::InsertRemote {
+                from: from.to_string(),
+                entry: entry_to_details(&entry)?,
+                content_status: match content_status {
+                    ContentStatus::Complete => "Complete".to_string(),
+                    ContentStatus::Incomplete => "Incomplete".to_string(),
+                    ContentStatus::Missing => "Missing".to_string(),
+                },
+            },
+            LiveEvent::ContentReady { hash } => DocEvent::ContentReady {
+                hash: hash.to_string(),
+            },
+            LiveEvent::PendingContentReady => DocEvent::PendingContentReady,
+            LiveEvent::NeighborUp(peer) => DocEvent::NeighborUp {
+                peer: peer.to_string(),
+            },
+            LiveEvent::NeighborDown(peer) => DocEvent::NeighborDown {
+                peer: peer.to_string(),
+            },
+            LiveEvent::SyncFinished(sync_event) => {
+                let (entries_received, entries_sent, error) = match sync_event.result {
+                    Ok(details) => (details.entries_received, details.entries_sent, None),
+                    Err(e) => (0, 0, Some(e)),
+                };
+                DocEvent::SyncFinished {
+                    peer: sync_event.peer.to_string(),
+                    entries_received,
+                    entries_sent,
+                    error,
+                }
+            }
+        };
 
-    let status = doc
-        .status()
-        .await
-        .map_err(|_| DocError::FailedToGetDocumentStatus)?;
+        Ok(doc_event)
+    }
+}
 
-    Ok(status)
+/// Converts a document entry into `EntryDetails`, decoding the key and SS58-encoding the author.
+pub fn entry_to_details(entry: &iroh_docs::Entry) -> anyhow::Result<EntryDetails, DocError> {
+    let encode_author = SS58AuthorId::from_author_id(&entry.id().author())
+        .map_err(|e| DocError::FailedToEncodeAuthorId(Cause::new(e)))?;
+    let decoded_key = decode_key(entry.id().key());
+
+    Ok(EntryDetails {
+        namespace: EntryIdDetails {
+            doc: entry.id().namespace().to_string(),
+            key: String::from_utf8(decoded_key)
+                .map_err(|e| DocError::FailedToDecodeEntryKey(Cause::new(e)))?,
+            author: encode_author.as_ss58().to_string(),
+        },
+        record: RecordDetails {
+            hash: entry.record().content_hash().to_string(),
+            len: entry.record().content_len(),
+            timestamp: entry.record().timestamp(),
+            anchor: None,
+        },
+    })
 }
 
-/// Fetches the download policy of a document, if any.
+/// Subscribes to the live events of a document.
 ///
 /// # Arguments
 /// * `docs` - Shared reference to the `Docs` store.
 /// * `doc_id` - The document ID (base64-encoded).
 ///
 /// # Returns
-/// A JSON representation of the download policy.
-pub async fn get_download_policy(
-    docs: Arc<Docs<Store>>,
+/// A stream of `DocEvent`s that ends when the document is closed or the node shuts down.
+pub async fn subscribe_doc(
+    docs: Arc<NodeDocs>,
     doc_id: String,
-) -> anyhow::Result<serde_json::Value, DocError> {
+) -> anyhow::Result<impl Stream<Item = anyhow::Result<DocEvent, DocError>>, DocError> {
     let namespace_id_vec = decode_doc_id(&doc_id)
         .map_err(|_| DocError::InvalidDocumentIdFormat)?;
     let namespace_id = NamespaceId::from(namespace_id_vec);
@@ -915,30 +3028,32 @@ pub async fn get_download_policy(
         .await
         .map_err(|_| DocError::DocumentNotFound)?;
 
-    let download_policy = doc
-        .get_download_policy()
+    let events = doc
+        .subscribe()
         .await
-        .map_err(|_| DocError::FailedToGetDownloadPolicy)?;
+        .map_err(|e| DocError::FailedToSubscribeDocument(Cause::new(e)))?;
 
-    let api_policy = ApiDownloadPolicy(download_policy);
-
-    Ok(api_policy.to_json())
+    Ok(events.map(|event| {
+        event
+            .map_err(|e| DocError::FailedToReceiveEvent(Cause::new(e)))
+            .and_then(DocEvent::from_live_event)
+    }))
 }
 
-/// Sets or updates the download policy of a document.
-/// 
+/// Retrieves the entries of a document written after a given timestamp, including deletions.
+///
 /// # Arguments
 /// * `docs` - Shared reference to the `Docs` store.
 /// * `doc_id` - The document ID (base64-encoded).
-/// * `download_policy` - JSON object representing the download policy.
+/// * `since` - Entry timestamp in microseconds since the Unix epoch (exclusive).
 ///
 /// # Returns
-/// An empty result on success.
-pub async fn set_download_policy(
-    docs: Arc<Docs<Store>>,
+/// The matching `EntryDetails`, oldest first. Deleted entries have a `len` of 0.
+pub async fn get_entries_since(
+    docs: Arc<NodeDocs>,
     doc_id: String,
-    download_policy: serde_json::Value,
-) -> anyhow::Result<(), DocError> {
+    since: u64,
+) -> anyhow::Result<Vec<EntryDetails>, DocError> {
     let namespace_id_vec = decode_doc_id(&doc_id)
         .map_err(|_| DocError::InvalidDocumentIdFormat)?;
     let namespace_id = NamespaceId::from(namespace_id_vec);
@@ -947,25 +3062,36 @@ pub async fn set_download_policy(
         .await
         .map_err(|_| DocError::DocumentNotFound)?;
 
-    let api_policy = ApiDownloadPolicy::from_json(&download_policy)
-        .map_err(|_| DocError::FailedToDecodeDownloadPolicy)?;
-
-    doc.set_download_policy(api_policy.0)
+    let mut entries = Vec::new();
+    let mut entries_stream = doc
+        .get_many(Query::all().include_empty())
         .await
-        .map_err(|_| DocError::FailedToSetDownloadPolicy)?;
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?;
 
-    Ok(())
+    while let Some(entry) = entries_stream.next().await {
+        let entry = entry
+            .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
+
+        if entry.record().timestamp() > since {
+            entries.push(entry_to_details(&entry)?);
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.record.timestamp);
+
+    Ok(entries)
 }
 
 // update_doc_schema
 // do we need this? 
 
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use node::iroh_wrapper::{IrohNode, setup_iroh_node};
     use helpers::cli::CliArgs;
-    use crate::authors::create_author;
+    use crate::authors::create_local_author;
 
     use anyhow::{Result, anyhow};
     use tokio::fs::{self, File};
@@ -990,8 +3116,6 @@ mod tests {
             fs::remove_dir_all("Test").await?;
         }
 
-        sleep(Duration::from_secs(2)).await;
-
         fs::create_dir_all("Test").await?;
 
         let args = CliArgs {
@@ -1000,17 +3124,81 @@ mod tests {
             bootstrap: true,
             suri: Some("0xe5be9a5092b81bca64be81d212e7f2f9eba183bb7a90954f7b76361f6edb5c0a".to_string()), // don't use this suri in production, it is a preloaded suri for testing(for //Alice)
             secret: Some("test-secret".to_string()), // remove this secret key
+            pkcs11_module: None,
+            pkcs11_slot: None,
+            pkcs11_pin: None,
+            approval_admin: vec![],
+            approval_threshold: None,
+            grpc_listen: None,
+            compression_min_size: 1024,
+            listen: vec![],
+            listen_uds: vec![],
+            admin_listen: None,
+            s3_listen: None,
+            webdav_listen: None,
+            read_timeout: 10,
+            write_timeout: 30,
+            download_timeout: 600,
+            download_connections: 4,
+            max_concurrent_imports: 4,
+            max_concurrent_exports: 16,
+            max_concurrent_downloads: 8,
+            max_concurrent_scans: 32,
+            relay: vec![],
+            chain_event: vec![],
+            proof_batch_interval: 600,
+            require_chain_authorization: false,
+            authorization_cache_ttl: 300,
+            chain_backend: helpers::cli::ChainBackend::Cord,
+            read_only: false,
+            public_gw: false,
+            require_api_key: false,
+            access_log: false,
+            access_log_doc: Vec::new(),
+            access_log_sample_rate: 1.0,
+            debug_errors: false,
+            doc_cache_size: 256,
+            doc_cache_idle: 300,
+            blob_cache_size: 64 * 1024 * 1024,
+            blob_cache_max_blob: 1024 * 1024,
+            index_field: vec![],
+            search: false,
+            schema_registry_doc: None,
+            schema_ref_url: vec![],
+            trash_retention: 2_592_000,
+            version_retention: 7_776_000,
+            smtp_relay: None,
+            smtp_from: "starter-kit@localhost".to_string(),
+            job: Vec::new(),
+            public_listen: None,
+            public_doc: Vec::new(),
+            public_blob: Vec::new(),
+            public_rate_limit: 120,
+            backup_dir: None,
+            backup_interval: None,
+            backup_keep: 7,
+            backup_full_every: 7,
+            backup_s3_url: None,
+            backup_s3_region: "us-east-1".to_string(),
+            backup_s3_access_key: None,
+            backup_s3_secret_key: None,
+            federation_catalog: None,
+            federation_trusted_author: vec![],
+            federation_interval: 60,
+            config: None,
+            profile: None,
         };
         let iroh_node: IrohNode = setup_iroh_node(args).await.or_else(|_| {
             Err(anyhow!("Failed to set up Iroh node"))
         })?;
+        iroh_node.wait_ready().await?;
         println!("Iroh node started!");
         println!("Your NodeId: {}", iroh_node.node_id);
         Ok(iroh_node)
     }
 
     pub async fn delete_all_docs(
-        docs: Arc<Docs<Store>>,
+        docs: Arc<NodeDocs>,
     ) -> Result<()> {
         let docs_list = list_docs(docs.clone()).await?;
         for (doc_id, _) in docs_list {
@@ -1050,7 +3238,6 @@ mod tests {
         let docs = iroh_node.docs.clone();
 
         let doc_1 = create_doc(docs.clone()).await?;
-        sleep(Duration::from_secs(2)).await;
         let doc_2 = create_doc(docs.clone()).await?;
 
         let list = list_docs(docs.clone()).await?;
@@ -1159,12 +3346,79 @@ mod tests {
             bootstrap: true,
             suri: Some("0xe5be9a5092b81bca64be81d212e7f2f9eba183bb7a90954f7b76361f6edb5c0a".to_string()),
             secret: Some("test-secret-1".to_string()), // remove this secret key
+            pkcs11_module: None,
+            pkcs11_slot: None,
+            pkcs11_pin: None,
+            approval_admin: vec![],
+            approval_threshold: None,
+            grpc_listen: None,
+            compression_min_size: 1024,
+            listen: vec![],
+            listen_uds: vec![],
+            admin_listen: None,
+            s3_listen: None,
+            webdav_listen: None,
+            read_timeout: 10,
+            write_timeout: 30,
+            download_timeout: 600,
+            download_connections: 4,
+            max_concurrent_imports: 4,
+            max_concurrent_exports: 16,
+            max_concurrent_downloads: 8,
+            max_concurrent_scans: 32,
+            relay: vec![],
+            chain_event: vec![],
+            proof_batch_interval: 600,
+            require_chain_authorization: false,
+            authorization_cache_ttl: 300,
+            chain_backend: helpers::cli::ChainBackend::Cord,
+            read_only: false,
+            public_gw: false,
+            require_api_key: false,
+            access_log: false,
+            access_log_doc: Vec::new(),
+            access_log_sample_rate: 1.0,
+            debug_errors: false,
+            doc_cache_size: 256,
+            doc_cache_idle: 300,
+            blob_cache_size: 64 * 1024 * 1024,
+            blob_cache_max_blob: 1024 * 1024,
+            index_field: vec![],
+            search: false,
+            schema_registry_doc: None,
+            schema_ref_url: vec![],
+            trash_retention: 2_592_000,
+            version_retention: 7_776_000,
+            smtp_relay: None,
+            smtp_from: "starter-kit@localhost".to_string(),
+            job: Vec::new(),
+            public_listen: None,
+            public_doc: Vec::new(),
+            public_blob: Vec::new(),
+            public_rate_limit: 120,
+            backup_dir: None,
+            backup_interval: None,
+            backup_keep: 7,
+            backup_full_every: 7,
+            backup_s3_url: None,
+            backup_s3_region: "us-east-1".to_string(),
+            backup_s3_access_key: None,
+            backup_s3_secret_key: None,
+            federation_catalog: None,
+            federation_trusted_author: vec![],
+            federation_interval: 60,
+            config: None,
+            profile: None,
         };
         let iroh_node_2: IrohNode = setup_iroh_node(args).await.or_else(|_| {
             Err(anyhow!("Failed to set up Iroh node"))
         })?;
 
-        let _ = join_doc(iroh_node_2.docs.clone(), ticket).await?;
+        iroh_node_2.wait_ready().await?;
+
+        let _ = join_doc(iroh_node_2.docs.clone(), ticket, vec![]).await?;
+        let sync = tokio::time::timeout(Duration::from_secs(30), iroh_node_2.await_sync(&doc_id, iroh_node.node_id)).await??;
+        assert!(sync.result.is_ok());
 
         let list_of_docs_1 = list_docs(docs.clone()).await?;
         let list_of_docs_2 = list_docs(iroh_node_2.docs.clone()).await?;
@@ -1208,6 +3462,54 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    pub async fn test_doc_bundle_is_signed_by_issuer() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+
+        let author = crate::authors::get_default_author(docs.clone()).await?;
+        let doc_1 = create_doc(docs.clone()).await?;
+        let doc_2 = create_doc(docs.clone()).await?;
+
+        let ticket = share_doc_bundle(
+            docs.clone(),
+            vec![doc_1.clone(), doc_2.clone(), doc_1.clone()],
+            ShareMode::Read,
+            AddrInfoOptions::Addresses,
+            author.clone(),
+            Some("registries".to_string()),
+        ).await?;
+
+        let bundle = decode_doc_bundle(&ticket)?;
+        assert_eq!(bundle.issuer, author);
+        assert_eq!(bundle.label.as_deref(), Some("registries"));
+        let doc_ids: Vec<&str> = bundle.docs.iter().map(|doc| doc.doc_id.as_str()).collect();
+        assert_eq!(doc_ids, vec![doc_1.as_str(), doc_2.as_str()]);
+
+        // a manifest changed after signing no longer matches the signature
+        let payload = BASE32_NOPAD.decode(ticket[DOC_BUNDLE_PREFIX.len()..].to_ascii_uppercase().as_bytes())?;
+        let (signature, manifest) = payload.split_at(ed25519_dalek::SIGNATURE_LENGTH);
+        let forged = String::from_utf8(manifest.to_vec())?.replace("registries", "registrieZ");
+        let forged = format!(
+            "{}{}",
+            DOC_BUNDLE_PREFIX,
+            BASE32_NOPAD.encode(&[signature, forged.as_bytes()].concat()).to_ascii_lowercase()
+        );
+        assert!(matches!(decode_doc_bundle(&forged), Err(DocError::InvalidDocBundleSignature)));
+
+        let other_author = docs.client().authors().create().await?;
+        let other_author = SS58AuthorId::from_author_id(&other_author)?.as_ss58().to_string();
+        let result = join_doc_bundle(docs.clone(), ticket, Some(other_author), vec![]).await;
+        assert!(matches!(result, Err(DocError::UnexpectedDocBundleIssuer)));
+
+        // cleanup
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
+        Ok(())
+    }
+
     #[tokio::test]
     pub async fn test_join_doc_fails_on_invalid_ticket() -> Result<()> {
         let iroh_node = setup_node().await?;
@@ -1215,7 +3517,7 @@ mod tests {
 
         let invalid_ticket = "not-a-valid-ticket";
 
-        let result = join_doc(docs.clone(), invalid_ticket.to_string()).await;
+        let result = join_doc(docs.clone(), invalid_ticket.to_string(), vec![]).await;
 
         assert!(matches!(result, Err(DocError::InvalidDocumentTicketFormat)));
 
@@ -1265,10 +3567,11 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let result = add_doc_schema(
             docs.clone(),
+            iroh_node.blobs.clone(),
             author,
             "not-a-valid-doc-id".into(),
             "{}".into(),
@@ -1289,18 +3592,19 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let doc_id = create_doc(docs.clone()).await?;
 
         let result = add_doc_schema(
             docs.clone(),
+            iroh_node.blobs.clone(),
             author,
             doc_id.clone(),
             "this is not valid json".into(),
         ).await;
 
-        assert!(matches!(result, Err(DocError::FailedToSerializeSchema)));
+        assert!(matches!(result, Err(DocError::FailedToSerializeSchema(_))));
 
         // cleanup
         delete_all_docs(docs).await?;
@@ -1315,7 +3619,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let doc_id = create_doc(docs.clone()).await?;
 
@@ -1326,12 +3630,13 @@ mod tests {
 
         let result = add_doc_schema(
             docs.clone(),
+            iroh_node.blobs.clone(),
             author,
             doc_id.clone(),
             invalid_schema.into(),
         ).await;
 
-        assert!(matches!(result, Err(DocError::FailedToValidateSchema)));
+        assert!(matches!(result, Err(DocError::FailedToValidateSchema(_))));
 
         // cleanup
         delete_all_docs(docs).await?;
@@ -1357,6 +3662,7 @@ mod tests {
 
         let result = add_doc_schema(
             docs.clone(),
+            iroh_node.blobs.clone(),
             "not-a-valid-author-id".into(),
             doc_id.clone(),
             valid_schema.into(),
@@ -1378,12 +3684,11 @@ mod tests {
         let docs = iroh_node.docs.clone();
         let blobs = iroh_node.blobs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let doc_id = create_doc(docs.clone()).await?;
 
         let _ = set_entry(docs.clone(), blobs.clone(), doc_id.clone(), author.clone(), "key".to_string(), "value".to_string()).await?;
-        sleep(Duration::from_secs(3)).await;
 
         let valid_schema = r#"{
             "type": "object",
@@ -1394,6 +3699,7 @@ mod tests {
 
         let result = add_doc_schema(
             docs.clone(),
+            iroh_node.blobs.clone(),
             author,
             doc_id.clone(),
             valid_schema.into(),
@@ -1414,7 +3720,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let doc_id = create_doc(docs.clone()).await?;
 
@@ -1431,6 +3737,7 @@ mod tests {
 
         let result = add_doc_schema(
             docs.clone(),
+            iroh_node.blobs.clone(),
             author.clone(),
             doc_id.clone(),
             valid_schema.into(),
@@ -1459,7 +3766,7 @@ mod tests {
         let docs = iroh_node.docs.clone();
         let blobs = iroh_node.blobs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let result = set_entry(
             docs.clone(),
@@ -1513,7 +3820,7 @@ mod tests {
         let docs = iroh_node.docs.clone();
         let blobs = iroh_node.blobs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let doc = create_doc(docs.clone()).await?;
 
@@ -1526,7 +3833,7 @@ mod tests {
             "value".to_string(),
         ).await;
 
-        assert!(matches!(result, Err(DocError::FailedToValidateKey)));
+        assert!(matches!(result, Err(DocError::FailedToValidateKey(_))));
 
         // cleanup
         delete_all_docs(docs).await?;
@@ -1549,7 +3856,7 @@ mod tests {
         let docs = iroh_node.docs.clone();
         let blobs = iroh_node.blobs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let doc = create_doc(docs.clone()).await?;
 
@@ -1568,7 +3875,7 @@ mod tests {
             "required": ["owner", "name", "number_of_entries", "terms_and_conditions"]
         }"#;
 
-        let add_schema_result = add_doc_schema(docs.clone(), author.clone(), doc.clone(), valid_schema.to_string()).await;
+        let add_schema_result = add_doc_schema(docs.clone(), iroh_node.blobs.clone(), author.clone(), doc.clone(), valid_schema.to_string()).await;
         assert!(add_schema_result.is_ok());
 
         let invalid_entry = r#"{
@@ -1585,8 +3892,310 @@ mod tests {
             "entry".to_string(),
             invalid_entry.to_string(),
         ).await;
-        
-        assert!(matches!(set_entry_result, Err(DocError::ValueDoesNotMatchSchema)));
+        
+        assert!(matches!(set_entry_result, Err(DocError::ValueDoesNotMatchSchema)));
+
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_set_entry() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+
+        let author = create_local_author(docs.clone()).await?;
+
+        let doc = create_doc(docs.clone()).await?;
+
+        let namespace_id_vec = decode_doc_id(&doc)
+        .with_context(|| format!("Failed to decode document ID {doc}"))?;
+        let namespace_id = NamespaceId::from(namespace_id_vec);
+
+        let valid_schema = r#"{
+            "type": "object",
+            "properties": {
+                "owner": { "type": "string" },
+                "name": { "type": "string" },
+                "number_of_entries": { "type": "integer" },
+                "terms_and_conditions": { "type": "string" }
+            },
+            "required": ["owner", "name", "number_of_entries", "terms_and_conditions"]
+        }"#;
+
+        let add_schema_result = add_doc_schema(docs.clone(), iroh_node.blobs.clone(), author.clone(), doc.clone(), valid_schema.to_string()).await;
+        assert!(add_schema_result.is_ok());
+
+        let valid_entry = r#"{
+            "owner": "Dhiway",
+            "name": "Cyra",
+            "number_of_entries": 3,
+            "terms_and_conditions": "Agreed"
+        }"#;
+
+        let set_entry_result = set_entry(
+            docs.clone(),
+            blobs.clone(),
+            doc.clone(),
+            author.clone(),
+            "entry".to_string(),
+            valid_entry.to_string(),
+        ).await;
+        assert!(set_entry_result.is_ok());
+
+        if let Some(fetch_entry) = get_entry(docs.clone(), doc.clone(), author.clone(), "entry".to_string(), true).await? {
+            assert_eq!(fetch_entry.namespace.doc, namespace_id.to_string());
+            assert_eq!(fetch_entry.namespace.key, "entry".to_string());
+            assert_eq!(fetch_entry.namespace.author, author.clone());
+            assert_eq!(fetch_entry.record.hash, set_entry_result.unwrap());
+        }
+        
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
+        Ok(())
+    }
+
+    // compute_doc_digest
+    #[tokio::test]
+    pub async fn test_compute_doc_digest() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+
+        let author = crate::authors::get_default_author(docs.clone()).await?;
+        let doc = create_doc(docs.clone()).await?;
+
+        for (key, value) in [("first", "1"), ("second", "2")] {
+            set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), key.to_string(), value.to_string()).await?;
+        }
+
+        let (digest, entries) = compute_doc_digest(docs.clone(), doc.clone(), None).await?;
+        assert_eq!(entries, 2);
+        assert_eq!(compute_doc_digest(docs.clone(), doc.clone(), None).await?.0, digest);
+
+        let first = ["first".to_string()];
+        let (first_digest, entries) = compute_doc_digest(docs.clone(), doc.clone(), Some(&first)).await?;
+        assert_eq!(entries, 1);
+        assert_ne!(first_digest, digest);
+
+        // changing an entry outside the anchored keys leaves their digest alone
+        set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "second".to_string(), "3".to_string()).await?;
+        assert_ne!(compute_doc_digest(docs.clone(), doc.clone(), None).await?.0, digest);
+        assert_eq!(compute_doc_digest(docs.clone(), doc.clone(), Some(&first)).await?.0, first_digest);
+
+        let missing = ["missing".to_string()];
+        let result = compute_doc_digest(docs.clone(), doc.clone(), Some(&missing)).await;
+        assert_eq!(result, Err(DocError::EntryNotFound));
+
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
+        Ok(())
+    }
+
+    // attach_entry_anchors
+    #[tokio::test]
+    pub async fn test_attach_entry_anchors() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+
+        let author = crate::authors::get_default_author(docs.clone()).await?;
+        let doc = create_doc(docs.clone()).await?;
+        set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "a".to_string(), "one".to_string()).await?;
+        set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "b".to_string(), "two".to_string()).await?;
+
+        // store an anchor of key `a` as `anchor_doc` would after the extrinsic was finalized
+        let keys = vec!["a".to_string()];
+        let (digest, entries) = compute_doc_digest(docs.clone(), doc.clone(), Some(&keys)).await?;
+        let anchor = DocAnchor {
+            digest: format!("0x{}", hex::encode(digest)),
+            keys: Some(keys),
+            entries,
+            block_hash: "0xb1".to_string(),
+            block_number: 7,
+            extrinsic_hash: "0xe1".to_string(),
+            extrinsic_index: 1,
+            account: "3x".to_string(),
+            anchored_at: 0,
+            digest_kind: DocDigestKind::Hash,
+        };
+        let namespace_id = NamespaceId::from(decode_doc_id(&doc)?);
+        get_document(docs.clone(), namespace_id)
+            .await?
+            .set_bytes(
+                SS58AuthorId::decode(&author)?,
+                encode_key(anchor_key(&anchor.extrinsic_hash).as_bytes()),
+                serde_json::to_vec(&anchor)?,
+            )
+            .await?;
+
+        let mut entries = get_entries(docs.clone(), doc.clone(), serde_json::json!({})).await?;
+        attach_entry_anchors(docs.clone(), blobs.clone(), doc.clone(), &mut entries).await?;
+        for entry in &entries {
+            let anchor = entry.record.anchor.as_ref().map(|anchor| anchor.block_number);
+            assert_eq!(anchor, if entry.namespace.key == "a" { Some(7) } else { None });
+        }
+
+        // once the entry changes, the anchor no longer proves it
+        set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "a".to_string(), "three".to_string()).await?;
+        let mut entries = get_entries(docs.clone(), doc.clone(), serde_json::json!({ "key": "a" })).await?;
+        attach_entry_anchors(docs.clone(), blobs.clone(), doc.clone(), &mut entries).await?;
+        assert!(entries[0].record.anchor.is_none());
+
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
+        Ok(())
+    }
+
+    // anchor_doc and revoke_statement with the local chain backend
+    #[tokio::test]
+    pub async fn test_anchor_and_revoke_with_local_backend() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+
+        let author = crate::authors::get_default_author(docs.clone()).await?;
+        let doc = create_doc(docs.clone()).await?;
+        set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "a".to_string(), "one".to_string()).await?;
+
+        let ledger: Arc<dyn ChainAnchor> = Arc::new(cord::backend::LocalAnchor::new("3x".to_string()));
+        let anchor = anchor_doc(docs.clone(), doc.clone(), author.clone(), None, DocDigestKind::Hash, ledger.clone()).await?;
+        assert_eq!(anchor.block_number, 1);
+        assert_eq!(anchor.account, "3x");
+        let remark = ledger.remark_at(&anchor.block_hash, anchor.extrinsic_index).await.map_err(|e| anyhow!("{e}"))?;
+        assert_eq!(remark.map(|remark| format!("0x{}", hex::encode(remark))), Some(anchor.digest.clone()));
+        assert_eq!(list_doc_anchors(docs.clone(), blobs.clone(), doc.clone()).await?, vec![anchor.clone()]);
+
+        let statement = RevokedStatement::Anchor { extrinsic_hash: anchor.extrinsic_hash.clone() };
+        let revocation = revoke_statement(docs.clone(), blobs.clone(), doc.clone(), author.clone(), statement, None, ledger.clone()).await?;
+        assert_eq!(revocation.block_number, 2);
+        assert_ne!(revocation.block_hash, anchor.block_hash);
+        let stored = get_statement_revocation(docs.clone(), blobs.clone(), doc.clone(), &anchor.extrinsic_hash).await?;
+        assert_eq!(stored, Some(revocation));
+
+        // the ledger has no extrinsic besides the anchor in each block
+        assert_eq!(ledger.remark_at(&anchor.block_hash, 1).await.map_err(|e| anyhow!("{e}"))?, None);
+
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
+        Ok(())
+    }
+
+    // entry_inclusion_proof against a Merkle anchor
+    #[tokio::test]
+    pub async fn test_entry_inclusion_proof() -> Result<()> {
+        use crate::inclusion::{entry_inclusion_proof, verify_inclusion_proof};
+
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+
+        let author = crate::authors::get_default_author(docs.clone()).await?;
+        let doc = create_doc(docs.clone()).await?;
+        for (key, value) in [("a", "one"), ("b", "two"), ("c", "three")] {
+            set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), key.to_string(), value.to_string()).await?;
+        }
+
+        let ledger: Arc<dyn ChainAnchor> = Arc::new(cord::backend::LocalAnchor::new("3x".to_string()));
+        let anchor = anchor_doc(docs.clone(), doc.clone(), author.clone(), None, DocDigestKind::Merkle, ledger.clone()).await?;
+        assert_eq!(anchor.entries, 3);
+
+        let proof = entry_inclusion_proof(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "b".to_string(), Some(anchor.extrinsic_hash.clone())).await?;
+        assert_eq!(proof.root, anchor.digest);
+        assert_eq!(proof.index, 1);
+        let verification = verify_inclusion_proof(&proof, ledger.clone()).await?;
+        assert_eq!(verification.anchored, Some(true));
+        assert!(verification.valid);
+
+        // a proof does not hold for another value
+        let mut forged = proof.clone();
+        forged.hash = Hash::new(b"forged").to_string();
+        assert!(!verify_inclusion_proof(&forged, ledger.clone()).await?.valid);
+
+        // once an anchored entry changes, the anchored root can no longer be proven against
+        set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "c".to_string(), "four".to_string()).await?;
+        let result = entry_inclusion_proof(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "b".to_string(), Some(anchor.extrinsic_hash.clone())).await;
+        assert!(matches!(result, Err(DocError::AnchorNoLongerMatches)));
+        let current = entry_inclusion_proof(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "b".to_string(), None).await?;
+        assert_ne!(current.root, anchor.digest);
+        assert!(verify_inclusion_proof(&current, ledger.clone()).await?.valid);
+
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
+        Ok(())
+    }
+
+    // get_statement_revocation
+    #[tokio::test]
+    pub async fn test_get_statement_revocation() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+
+        let doc = create_doc(docs.clone()).await?;
+
+        // nothing was revoked yet
+        let revocation = get_statement_revocation(docs.clone(), blobs.clone(), doc.clone(), "urn:uuid:1").await?;
+        assert_eq!(revocation, None);
+
+        // revocations of different statements never share a digest
+        assert_ne!(revocation_digest("urn:uuid:1"), revocation_digest("urn:uuid:2"));
+
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
+        Ok(())
+    }
+
+    // issue_entry_credential
+    #[tokio::test]
+    pub async fn test_issue_entry_credential() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+
+        let author = crate::authors::get_default_author(docs.clone()).await?;
+        let doc = create_doc(docs.clone()).await?;
+
+        let keystore = StarterkitKeystore::from_inner(iroh_node.cord_signer.keystore.clone());
+        let skit = keystore::registry::builtin_key_types()
+            .into_iter()
+            .find(|key_type| key_type.name == "skit")
+            .ok_or_else(|| anyhow!("skit key type missing"))?;
+        let public = keystore.get_public_key(&skit)?;
+
+        set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "entry".to_string(), r#"{"name":"Cyra"}"#.to_string()).await?;
+        let result = issue_entry_credential(
+            docs.clone(), blobs.clone(), doc.clone(), author.clone(), "entry".to_string(), None, &keystore, &skit, &public,
+        ).await;
+        assert_eq!(result, Err(DocError::DocumentHasNoSchema));
+
+        let doc = create_doc(docs.clone()).await?;
+        let schema = r#"{ "type": "object", "properties": { "name": { "type": "string" } }, "required": ["name"] }"#;
+        add_doc_schema(docs.clone(), iroh_node.blobs.clone(), author.clone(), doc.clone(), schema.to_string()).await?;
+        set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "entry".to_string(), r#"{"name":"Cyra"}"#.to_string()).await?;
+
+        let credential = issue_entry_credential(
+            docs.clone(), blobs.clone(), doc.clone(), author.clone(), "entry".to_string(), Some("did:example:holder".to_string()), &keystore, &skit, &public,
+        ).await?;
+        assert_eq!(credential["credentialSubject"]["name"], "Cyra");
+        assert_eq!(credential["credentialSubject"]["id"], "did:example:holder");
+        assert!(keystore::credential::verify_credential(&credential)?);
 
         delete_all_docs(docs).await?;
         fs::remove_dir_all("Test").await?;
@@ -1595,58 +4204,43 @@ mod tests {
         Ok(())
     }
 
+    // import_cord_schema and export_cord_schema
     #[tokio::test]
-    pub async fn test_set_entry() -> Result<()> {
+    pub async fn test_import_and_export_cord_schema() -> Result<()> {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
         let blobs = iroh_node.blobs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = crate::authors::get_default_author(docs.clone()).await?;
 
         let doc = create_doc(docs.clone()).await?;
+        let result = import_cord_schema(docs.clone(), blobs.clone(), author.clone(), doc.clone(), serde_json::json!({ "type": "string" })).await;
+        assert_eq!(result, Err(DocError::InvalidCordSchema));
+
+        let cord_schema = serde_json::json!({
+            "$id": "schema:cord:s3abc",
+            "schema": {
+                "$id": "schema:cord:s3abc",
+                "$schema": "http://cord.network/draft-01/schema#",
+                "title": "Membership",
+                "type": "object",
+                "properties": { "name": { "type": "string" } },
+                "additionalProperties": false
+            }
+        });
+        let (_, schema) = import_cord_schema(docs.clone(), blobs.clone(), author.clone(), doc.clone(), cord_schema).await?;
+        assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+        assert!(schema.get("$id").is_none());
 
-        let namespace_id_vec = decode_doc_id(&doc)
-        .with_context(|| format!("Failed to decode document ID {doc}"))?;
-        let namespace_id = NamespaceId::from(namespace_id_vec);
-
-        let valid_schema = r#"{
-            "type": "object",
-            "properties": {
-                "owner": { "type": "string" },
-                "name": { "type": "string" },
-                "number_of_entries": { "type": "integer" },
-                "terms_and_conditions": { "type": "string" }
-            },
-            "required": ["owner", "name", "number_of_entries", "terms_and_conditions"]
-        }"#;
-
-        let add_schema_result = add_doc_schema(docs.clone(), author.clone(), doc.clone(), valid_schema.to_string()).await;
-        assert!(add_schema_result.is_ok());
-
-        let valid_entry = r#"{
-            "owner": "Dhiway",
-            "name": "Cyra",
-            "number_of_entries": 3,
-            "terms_and_conditions": "Agreed"
-        }"#;
+        // the imported schema validates entries like any other
+        let result = set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "entry".to_string(), r#"{"name":"Cyra","extra":1}"#.to_string()).await;
+        assert_eq!(result, Err(DocError::ValueDoesNotMatchSchema));
 
-        let set_entry_result = set_entry(
-            docs.clone(),
-            blobs.clone(),
-            doc.clone(),
-            author.clone(),
-            "entry".to_string(),
-            valid_entry.to_string(),
-        ).await;
-        assert!(set_entry_result.is_ok());
+        let exported = export_cord_schema(docs.clone(), blobs.clone(), doc.clone(), author.clone(), None).await?;
+        assert_eq!(exported["$schema"], cord::schema::CORD_SCHEMA_DRAFT);
+        assert_eq!(exported["title"], "Membership");
+        assert_eq!(exported["properties"], schema["properties"]);
 
-        if let Some(fetch_entry) = get_entry(docs.clone(), doc.clone(), author.clone(), "entry".to_string(), true).await? {
-            assert_eq!(fetch_entry.namespace.doc, namespace_id.to_string());
-            assert_eq!(fetch_entry.namespace.key, "entry".to_string());
-            assert_eq!(fetch_entry.namespace.author, author.clone());
-            assert_eq!(fetch_entry.record.hash, set_entry_result.unwrap());
-        }
-        
         delete_all_docs(docs).await?;
         fs::remove_dir_all("Test").await?;
         iroh_node.router.shutdown().await?;
@@ -1660,7 +4254,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let result = set_entry_file(
             docs.clone(), 
@@ -1708,7 +4302,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let doc_id = create_doc(docs.clone()).await?;
 
@@ -1720,7 +4314,7 @@ mod tests {
             "path".to_string(),
         ).await;
         
-        assert!(matches!(result, Err(DocError::FailedToValidateKey)));
+        assert!(matches!(result, Err(DocError::FailedToValidateKey(_))));
 
         delete_all_docs(docs).await?;
         fs::remove_dir_all("Test").await?;
@@ -1734,7 +4328,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let doc_id = create_doc(docs.clone()).await?;
 
@@ -1760,7 +4354,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let doc_id = create_doc(docs.clone()).await?;
 
@@ -1781,8 +4375,7 @@ mod tests {
             "required": ["owner", "name", "number_of_entries", "terms_and_conditions"]
         }"#;
 
-        let add_schema_result = add_doc_schema(docs.clone(), author.clone(), doc_id.clone(), valid_schema.to_string()).await;
-        sleep(Duration::from_secs(1)).await;
+        let add_schema_result = add_doc_schema(docs.clone(), iroh_node.blobs.clone(), author.clone(), doc_id.clone(), valid_schema.to_string()).await;
         assert!(add_schema_result.is_ok());
 
         let result = set_entry_file(
@@ -1806,13 +4399,83 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    pub async fn test_set_entry_file_allows_attachments_with_schema() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+
+        let author = crate::authors::create_local_author(docs.clone()).await?;
+
+        let doc_id = create_doc(docs.clone()).await?;
+
+        let dir = tempfile::tempdir()?;
+        let file_path = dir.path().join("contract.pdf");
+        let mut file = File::create(&file_path).await?;
+        file.write_all(b"%PDF-1.7").await?;
+
+        let valid_schema = r#"{
+            "type": "object",
+            "properties": {
+                "owner": { "type": "string" },
+                "contract": {
+                    "type": "object",
+                    "properties": { "$attachment": { "type": "string" } },
+                    "required": ["$attachment"]
+                }
+            },
+            "required": ["owner"]
+        }"#;
+        add_doc_schema(docs.clone(), blobs.clone(), author.clone(), doc_id.clone(), valid_schema.to_string()).await?;
+
+        let result = set_entry_file(
+            docs.clone(),
+            doc_id.clone(),
+            author.clone(),
+            "files/contract.pdf".to_string(),
+            file_path.to_str().unwrap().to_string(),
+        ).await;
+        assert!(result.is_ok());
+
+        // links are checked against the attachments of the document
+        let missing = set_entry(
+            docs.clone(),
+            blobs.clone(),
+            doc_id.clone(),
+            author.clone(),
+            "entry".to_string(),
+            r#"{"owner": "Dhiway", "contract": {"$attachment": "files/missing.pdf"}}"#.to_string(),
+        ).await;
+        assert!(matches!(missing, Err(DocError::AttachmentNotFound)));
+
+        set_entry(
+            docs.clone(),
+            blobs.clone(),
+            doc_id.clone(),
+            author.clone(),
+            "entry".to_string(),
+            r#"{"owner": "Dhiway", "contract": {"$attachment": "files/contract.pdf"}}"#.to_string(),
+        ).await?;
+
+        let attachments = entry_attachments(docs.clone(), blobs.clone(), doc_id.clone(), author.clone(), "entry".to_string()).await?;
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].key, "files/contract.pdf");
+        assert!(attachments[0].entry.is_some());
+
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+
+        Ok(())
+    }
+
     #[tokio::test]
     pub async fn test_set_entry_file() -> Result<()> {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
         let blobs = iroh_node.blobs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let doc_id = create_doc(docs.clone()).await?;
 
@@ -1843,7 +4506,7 @@ mod tests {
     pub async fn test_get_entry_fails_on_incorrect_doc_id() -> Result<()> {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let result = get_entry(
             docs.clone(),
@@ -1866,7 +4529,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
         let doc_id = create_doc(docs.clone()).await?;
 
         // Use a key that will fail validation (e.g., empty string)
@@ -1878,7 +4541,7 @@ mod tests {
             false,
         ).await;
 
-        assert!(matches!(result, Err(DocError::FailedToValidateKey)));
+        assert!(matches!(result, Err(DocError::FailedToValidateKey(_))));
 
         delete_all_docs(docs).await?;
         fs::remove_dir_all("Test").await?;
@@ -1913,7 +4576,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
         let doc_id = create_doc(docs.clone()).await?;
 
         let result = get_entry(
@@ -1938,7 +4601,7 @@ mod tests {
         let docs = iroh_node.docs.clone();
         let blobs = iroh_node.blobs.clone();
 
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
         let doc_id = create_doc(docs.clone()).await?;
         let key = "test_key".to_string();
         let value = "test_value".to_string();
@@ -1972,6 +4635,57 @@ mod tests {
         Ok(())
     }
 
+    // list_conflicts, get_resolved_entry
+    #[tokio::test]
+    pub async fn test_list_and_resolve_conflicts() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+
+        let first = crate::authors::create_local_author(docs.clone()).await?;
+        let second = crate::authors::create_local_author(docs.clone()).await?;
+        let doc_id = create_doc(docs.clone()).await?;
+
+        set_entry(docs.clone(), blobs.clone(), doc_id.clone(), first.clone(), "shared".to_string(), "first".to_string()).await?;
+        sleep(Duration::from_millis(10)).await;
+        set_entry(docs.clone(), blobs.clone(), doc_id.clone(), second.clone(), "shared".to_string(), "second".to_string()).await?;
+        set_entry(docs.clone(), blobs.clone(), doc_id.clone(), first.clone(), "solo".to_string(), "only".to_string()).await?;
+
+        let conflicts = list_conflicts(docs.clone(), doc_id.clone(), None).await?;
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].key, "shared");
+        // newest first
+        assert_eq!(conflicts[0].versions.len(), 2);
+        assert_eq!(conflicts[0].versions[0].namespace.author, second);
+        assert!(list_conflicts(docs.clone(), doc_id.clone(), Some("solo".to_string())).await?.is_empty());
+
+        let (latest, versions) = get_resolved_entry(docs.clone(), doc_id.clone(), "shared".to_string(), &ConflictResolution::LastWriterWins)
+            .await?
+            .ok_or_else(|| anyhow!("no version resolved"))?;
+        assert_eq!(latest.namespace.author, second);
+        assert_eq!(versions, 2);
+
+        let priority = ConflictResolution::from_name("author_priority", vec![first.clone()])?;
+        let (preferred, _) = get_resolved_entry(docs.clone(), doc_id.clone(), "shared".to_string(), &priority)
+            .await?
+            .ok_or_else(|| anyhow!("no version resolved"))?;
+        assert_eq!(preferred.namespace.author, first);
+
+        let none: ConflictResolver = Arc::new(|_, _| None);
+        assert!(get_resolved_entry(docs.clone(), doc_id.clone(), "shared".to_string(), &ConflictResolution::Custom(none)).await?.is_none());
+        assert!(matches!(ConflictResolution::from_name("first_writer_wins", vec![]), Err(DocError::InvalidConflictResolution)));
+
+        let entries = get_entries(docs.clone(), doc_id.clone(), serde_json::json!({})).await?;
+        let resolved = resolve_entries(entries, &priority);
+        assert_eq!(resolved.len(), 2);
+        assert!(resolved.iter().all(|entry| entry.namespace.author == first));
+
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+        Ok(())
+    }
+
     // get_entry_blob
     #[tokio::test]
     pub async fn test_get_entry_blob_fails_on_invalid_hash() -> Result<()> {
@@ -1982,7 +4696,7 @@ mod tests {
 
         let result = get_entry_blob(blobs.clone(), invalid_hash).await;
 
-        assert!(matches!(result, Err(DocError::FailedToParseHash)));
+        assert!(matches!(result, Err(DocError::FailedToParseHash(_))));
 
         fs::remove_dir_all("Test").await?;
         iroh_node.router.shutdown().await?;
@@ -2020,7 +4734,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
         let doc_id = create_doc(docs.clone()).await?;
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let query_params = serde_json::json!({
             "key": "some key",
@@ -2035,7 +4749,7 @@ mod tests {
             query_params
         ).await;
 
-        assert!(matches!(result, Err(DocError::FailedToValidateKey)));
+        assert!(matches!(result, Err(DocError::FailedToValidateKey(_))));
 
         delete_all_docs(docs).await?;
         fs::remove_dir_all("Test").await?;
@@ -2048,7 +4762,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
         let doc_id = create_doc(docs.clone()).await?;
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let query_params = serde_json::json!({
             "key": "Key",
@@ -2076,7 +4790,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
         let doc_id = create_doc(docs.clone()).await?;
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let query_params = serde_json::json!({
             "key": "Key",
@@ -2105,7 +4819,7 @@ mod tests {
         let docs = iroh_node.docs.clone();
         let blobs = iroh_node.blobs.clone();
         let doc_id = create_doc(docs.clone()).await?;
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let entr_1 = set_entry(docs.clone(), blobs.clone(), doc_id.clone(), author.clone(), "organisation_name".to_string(), "Test Org".to_string()).await?;
         let entr_2 = set_entry(docs.clone(), blobs.clone(), doc_id.clone(), author.clone(), "organisation_address".to_string(), "Test Address".to_string()).await?;
@@ -2142,7 +4856,7 @@ mod tests {
     pub async fn test_delete_entry_fails_on_incorrect_document_id() -> Result<()> {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let result = delete_entry(
             docs.clone(),
@@ -2185,7 +4899,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
         let doc_id = create_doc(docs.clone()).await?;
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let result = delete_entry(
             docs.clone(),
@@ -2194,7 +4908,7 @@ mod tests {
             "schema".to_string(), // can use 'some key'
         ).await;
 
-        assert!(matches!(result, Err(DocError::FailedToValidateKey)));
+        assert!(matches!(result, Err(DocError::FailedToValidateKey(_))));
 
         delete_all_docs(docs).await?;
         fs::remove_dir_all("Test").await?;
@@ -2207,7 +4921,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
         let doc_id = create_doc(docs.clone()).await?;
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
 
         let result = delete_entry(
             docs.clone(),
@@ -2229,7 +4943,7 @@ mod tests {
         let iroh_node = setup_node().await?;
         let docs = iroh_node.docs.clone();
         let blobs = iroh_node.blobs.clone();
-        let author = create_author(docs.clone()).await?;
+        let author = create_local_author(docs.clone()).await?;
         let doc = create_doc(docs.clone()).await?;
 
         let entry = set_entry(
@@ -2242,8 +4956,6 @@ mod tests {
         ).await;
         assert!(entry.is_ok());
 
-        sleep(Duration::from_secs(2)).await;
-
         let entry_before_deletion_option = get_entry(
             docs.clone(),
             doc.clone(),
@@ -2251,7 +4963,6 @@ mod tests {
             "Key".to_string(),
             true
         ).await?;
-        sleep(Duration::from_secs(2)).await;
 
         let entry_before_deletion = entry_before_deletion_option.unwrap();
         let hash = entry.unwrap();
@@ -2266,8 +4977,6 @@ mod tests {
         ).await;
         assert!(delete_result.is_ok());
 
-        sleep(Duration::from_secs(2)).await;
-
         let entry_after_deletion_option = get_entry(
             docs.clone(),
             doc.clone(),
@@ -2284,6 +4993,110 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    pub async fn test_restore_entry_from_trash() -> Result<()> {
+        use crate::trash::{empty_trash, list_trash, restore_entry};
+
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+        let author = crate::authors::create_local_author(docs.clone()).await?;
+        let doc = create_doc(docs.clone()).await?;
+
+        let hash = set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "Key".to_string(), "Value".to_string()).await?;
+        delete_entry(docs.clone(), doc.clone(), author.clone(), "Key".to_string()).await?;
+
+        let trash = list_trash(docs.clone(), doc.clone(), Some(author.clone())).await?;
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].key, "Key");
+        assert_eq!(trash[0].hash, hash);
+
+        let restored = restore_entry(docs.clone(), doc.clone(), author.clone(), "Key".to_string()).await?;
+        assert_eq!(restored, hash);
+        let entry = get_entry(docs.clone(), doc.clone(), author.clone(), "Key".to_string(), false).await?;
+        assert_eq!(entry.unwrap().record.hash, hash);
+        assert!(list_trash(docs.clone(), doc.clone(), None).await?.is_empty());
+
+        // an emptied trash has nothing left to restore
+        delete_entry(docs.clone(), doc.clone(), author.clone(), "Key".to_string()).await?;
+        assert_eq!(empty_trash(docs.clone(), doc.clone(), author.clone(), None).await?, 1);
+        let result = restore_entry(docs.clone(), doc.clone(), author.clone(), "Key".to_string()).await;
+        assert!(matches!(result, Err(DocError::TrashedEntryNotFound)));
+
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_verify_entry_signed_by_author() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+        let author = crate::authors::create_local_author(docs.clone()).await?;
+        let doc = create_doc(docs.clone()).await?;
+
+        let (hash, signature) = set_entry_signed(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "Key".to_string(), "Value".to_string(), EntrySigner::Author).await?;
+        assert_eq!(signature.algorithm, "ed25519");
+        assert_eq!(signature.public, author);
+
+        let verification = verify_entry(docs.clone(), blobs.clone(), doc.clone(), None, "Key".to_string()).await?;
+        assert_eq!(verification.hash, hash);
+        assert!(verification.valid);
+
+        // a value written without signing no longer matches the signature
+        set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "Key".to_string(), "Tampered".to_string()).await?;
+        let verification = verify_entry(docs.clone(), blobs.clone(), doc.clone(), Some(author.clone()), "Key".to_string()).await?;
+        assert!(!verification.hash_matches);
+        assert!(!verification.signature_valid);
+        assert!(!verification.valid);
+
+        set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "Unsigned".to_string(), "Value".to_string()).await?;
+        let result = verify_entry(docs.clone(), blobs.clone(), doc.clone(), None, "Unsigned".to_string()).await;
+        assert!(matches!(result, Err(DocError::EntrySignatureNotFound)));
+
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_get_entries_at() -> Result<()> {
+        use crate::history::get_entries_at;
+
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+        let author = crate::authors::create_local_author(docs.clone()).await?;
+        let doc = create_doc(docs.clone()).await?;
+
+        let first = set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "Key".to_string(), "One".to_string()).await?;
+        let first_at = get_entry(docs.clone(), doc.clone(), author.clone(), "Key".to_string(), false).await?.unwrap().record.timestamp;
+        let second = set_entry(docs.clone(), blobs.clone(), doc.clone(), author.clone(), "Key".to_string(), "Two".to_string()).await?;
+        let second_at = get_entry(docs.clone(), doc.clone(), author.clone(), "Key".to_string(), false).await?.unwrap().record.timestamp;
+        delete_entry(docs.clone(), doc.clone(), author.clone(), "Key".to_string()).await?;
+
+        assert!(get_entries_at(docs.clone(), doc.clone(), first_at - 1, None, None).await?.is_empty());
+        let entries = get_entries_at(docs.clone(), doc.clone(), first_at, None, None).await?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].namespace.key, "Key");
+        assert_eq!(entries[0].record.hash, first);
+        let entries = get_entries_at(docs.clone(), doc.clone(), second_at, Some("K".to_string()), Some(author.clone())).await?;
+        assert_eq!(entries[0].record.hash, second);
+        assert_eq!(entries[0].record.timestamp, second_at);
+
+        // the entry is deleted now, which only the trash and history still hold
+        assert!(get_entries_at(docs.clone(), doc.clone(), u64::MAX, None, None).await?.is_empty());
+        assert!(get_entries_at(docs.clone(), doc.clone(), first_at, Some("Other".to_string()), None).await?.is_empty());
+
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+        Ok(())
+    }
+
     // leave
     // not sure how to test
 
@@ -2349,7 +5162,7 @@ mod tests {
             incorrect_download_policy
         ).await;
 
-        assert!(matches!(result, Err(DocError::FailedToDecodeDownloadPolicy)));
+        assert!(matches!(result, Err(DocError::FailedToDecodeDownloadPolicy(_))));
 
         delete_all_docs(docs).await?;
         fs::remove_dir_all("Test").await?;
@@ -2376,8 +5189,6 @@ mod tests {
 
         assert!(result.is_ok());
 
-        sleep(Duration::from_secs(2)).await;
-
         let result = get_download_policy(
             docs.clone(), 
             doc_id.clone()
@@ -2391,4 +5202,222 @@ mod tests {
         iroh_node.router.shutdown().await?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    // set_sync_prefixes and get_sync_prefixes
+    #[tokio::test]
+    pub async fn test_set_and_get_sync_prefixes() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let doc_id = create_doc(docs.clone()).await?;
+
+        // a new document downloads everything
+        assert_eq!(get_sync_prefixes(docs.clone(), doc_id.clone()).await?, Some(vec![]));
+
+        set_sync_prefixes(docs.clone(), doc_id.clone(), vec!["public/".to_string()]).await?;
+        assert_eq!(get_sync_prefixes(docs.clone(), doc_id.clone()).await?, Some(vec!["public/".to_string()]));
+        // signatures of the entries under the prefix are downloaded too
+        let policy = get_download_policy(docs.clone(), doc_id.clone()).await?;
+        assert_eq!(policy["policy"], "nothing_except");
+        assert_eq!(policy["filters"], serde_json::json!(["prefix:utf8:public/", "prefix:utf8:sig:public/"]));
+
+        // policies set some other way have no prefixes
+        let download_policy = serde_json::json!({ "policy": "everything_except", "filters": ["prefix:utf8:private/"] });
+        set_download_policy(docs.clone(), doc_id.clone(), download_policy).await?;
+        assert_eq!(get_sync_prefixes(docs.clone(), doc_id.clone()).await?, None);
+
+        let result = set_sync_prefixes(docs.clone(), doc_id.clone(), vec!["".to_string()]).await;
+        assert_eq!(result, Err(DocError::InvalidSyncPrefix));
+
+        set_sync_prefixes(docs.clone(), doc_id.clone(), vec![]).await?;
+        assert_eq!(get_sync_prefixes(docs.clone(), doc_id.clone()).await?, Some(vec![]));
+
+        delete_all_docs(docs).await?;
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_replication_report() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+
+        let author = crate::authors::get_default_author(docs.clone()).await?;
+        let doc_id = create_doc(docs.clone()).await?;
+        set_entry(docs.clone(), blobs.clone(), doc_id.clone(), author, "greeting".to_string(), "hello".to_string()).await?;
+
+        let tracker = helpers::replication::ReplicationTracker::new();
+        let controls = helpers::sync_controls::SyncControlRegistry::default();
+        let (summary, reports) = crate::replication::replication_report(docs.clone(), blobs.clone(), &tracker, &controls, Some(doc_id.clone())).await?;
+        assert_eq!((summary.docs, summary.never_synced, summary.with_pending_content), (1, 1, 0));
+        assert_eq!((reports[0].capability.as_str(), reports[0].entries, reports[0].pending_content), ("write", 1, 0));
+
+        tracker.record_sync(&doc_id, "peer".to_string(), 2, None);
+        let (summary, reports) = crate::replication::replication_report(docs.clone(), blobs.clone(), &tracker, &controls, Some(doc_id.clone())).await?;
+        assert_eq!(summary.never_synced, 0);
+        assert_eq!(reports[0].activity.last_sync_peer.as_deref(), Some("peer"));
+
+        // content is counted once it finished downloading
+        tracker.record_insert_remote(&doc_id, "hash".to_string(), 5, false);
+        assert_eq!(tracker.metrics(&doc_id).content_bytes_received, 0);
+        tracker.record_content_ready(&doc_id, "hash");
+        let metrics = tracker.metrics(&doc_id);
+        assert_eq!((metrics.sync_rounds, metrics.entries_sent, metrics.entries_received), (1, 2, 1));
+        assert_eq!((metrics.content_received, metrics.content_bytes_received), (1, 5));
+
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_sharded_doc() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+
+        let author = crate::authors::get_default_author(docs.clone()).await?;
+        let sharded = crate::sharding::create_sharded_doc(docs.clone(), author.clone(), 4).await?;
+        assert_eq!(sharded.shards.len(), 4);
+        assert_eq!(crate::sharding::get_sharded_doc(docs.clone(), blobs.clone(), &sharded.doc_id).await?, sharded);
+
+        for i in 0..20 {
+            let key = format!("record/{:02}", i);
+            let (shard, _) = crate::sharding::set_sharded_entry(docs.clone(), blobs.clone(), &sharded.doc_id, author.clone(), key.clone(), format!("value {}", i)).await?;
+            assert_eq!(shard, sharded.shard_for(&key));
+        }
+
+        // every key is read from its own shard
+        let (shard, entry) = crate::sharding::get_sharded_entry(docs.clone(), blobs.clone(), &sharded.doc_id, author.clone(), "record/07".to_string(), false).await?;
+        assert_eq!(shard, sharded.shard_for("record/07"));
+        assert!(entry.is_some());
+
+        // queries over every shard come back in key order, paginated across shards
+        let page = crate::sharding::get_sharded_entries(docs.clone(), blobs.clone(), &sharded.doc_id, serde_json::json!({ "offset": 5, "limit": 3 })).await?;
+        let keys: Vec<_> = page.iter().map(|entry| entry.namespace.key.as_str()).collect();
+        assert_eq!(keys, vec!["record/05", "record/06", "record/07"]);
+
+        let all = crate::sharding::get_sharded_entries(docs.clone(), blobs.clone(), &sharded.doc_id, serde_json::json!({ "sort_direction": "descending" })).await?;
+        assert_eq!(all.len(), 20);
+        assert_eq!(all[0].namespace.key, "record/19");
+
+        // a shard has no manifest of its own
+        assert_eq!(
+            crate::sharding::get_sharded_doc(docs.clone(), blobs.clone(), &sharded.shards[0]).await.unwrap_err(),
+            crate::sharding::ShardingError::NotShardedDocument
+        );
+
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_replica_digests() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+        let blobs = iroh_node.blobs.clone();
+
+        let author = crate::authors::get_default_author(docs.clone()).await?;
+        let doc_id = create_doc(docs.clone()).await?;
+        let namespace_id = NamespaceId::from(decode_doc_id(&doc_id)?);
+        for i in 0..10 {
+            set_entry(docs.clone(), blobs.clone(), doc_id.clone(), author.clone(), format!("key{}", i), format!("value {}", i)).await?;
+        }
+
+        let before = node::replica_digests::bucket_digests(&docs, namespace_id).await?;
+        assert_eq!(before.values().map(|bucket| bucket.keys).sum::<u64>(), 10);
+
+        // a new version of a key only changes the digest of its bucket
+        set_entry(docs.clone(), blobs.clone(), doc_id.clone(), author.clone(), "key3".to_string(), "changed".to_string()).await?;
+        let after = node::replica_digests::bucket_digests(&docs, namespace_id).await?;
+        let bucket = node::replica_digests::bucket_of(b"key3");
+        let changed: Vec<u8> = before.keys().filter(|b| before.get(*b) != after.get(*b)).copied().collect();
+        assert_eq!(changed, vec![bucket]);
+
+        let keys = node::replica_digests::key_digests(&docs, namespace_id, &std::collections::BTreeSet::from([bucket])).await?;
+        assert!(keys.contains_key("key3"));
+        assert!(keys.keys().all(|key| node::replica_digests::bucket_of(key.as_bytes()) == bucket));
+
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_doc_handle_cache() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+
+        let handles = helpers::doc_handles::DocHandleCache::new(2, Duration::from_secs(300));
+        let mut doc_ids = Vec::new();
+        for _ in 0..3 {
+            let doc_id = NamespaceId::from(decode_doc_id(&create_doc(docs.clone()).await?)?);
+            handles.insert(doc_id, open_document(docs.clone(), doc_id).await?);
+            doc_ids.push(doc_id);
+            // the first document is the most recently used when the third one comes in
+            handles.get(&doc_ids[0]);
+        }
+        assert!(handles.contains(&doc_ids[0]));
+        assert!(!handles.contains(&doc_ids[1]));
+        assert!(handles.contains(&doc_ids[2]));
+
+        // a removed handle can be closed, after which the document can be dropped
+        let doc = handles.remove(&doc_ids[0]).ok_or(anyhow!("handle not cached"))?;
+        doc.close().await?;
+        drop_doc(docs.clone(), encode_doc_id(doc_ids[0].as_bytes())).await?;
+
+        let idle = helpers::doc_handles::DocHandleCache::new(2, Duration::from_secs(0));
+        idle.insert(doc_ids[2], open_document(docs.clone(), doc_ids[2]).await?);
+        assert_eq!(idle.evict_idle(), 1);
+
+        // every node keeps its own handles, even with two nodes in one process
+        get_document(docs.clone(), doc_ids[2]).await?;
+        assert!(docs.handles.contains(&doc_ids[2]));
+        let other = Arc::new(NodeDocs::new((**docs).clone()).with_handles(helpers::doc_handles::DocHandleCache::new(2, Duration::from_secs(300))));
+        assert!(!other.handles.contains(&doc_ids[2]));
+        get_document(other.clone(), doc_ids[2]).await?;
+        assert!(other.handles.contains(&doc_ids[2]));
+
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_error_causes() -> Result<()> {
+        let error = DocError::FailedToGetEntry(Cause::new(anyhow!("connection lost")));
+
+        // the cause is kept for debugging, but messages and comparisons only use the variant
+        assert_eq!(std::error::Error::source(&error).map(|cause| cause.to_string()), Some("connection lost".to_string()));
+        assert!(format!("{:?}", error).contains("connection lost"));
+        assert_eq!(error.to_string(), "FailedToGetEntry");
+        assert_eq!(error, DocError::FailedToGetEntry(Cause::new("another cause")));
+        assert_eq!(DocError::DocumentNotFound.to_string(), "DocumentNotFound");
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_stream_docs() -> Result<()> {
+        let iroh_node = setup_node().await?;
+        let docs = iroh_node.docs.clone();
+
+        for _ in 0..3 {
+            create_doc(docs.clone()).await?;
+        }
+
+        // streaming yields the same documents as listing
+        let streamed: Vec<_> = stream_docs(docs.clone()).await?.try_collect().await?;
+        let mut streamed: Vec<String> = streamed.into_iter().map(|(doc_id, _)| doc_id).collect();
+        let mut listed: Vec<String> = list_docs(docs.clone()).await?.into_iter().map(|(doc_id, _)| doc_id).collect();
+        streamed.sort();
+        listed.sort();
+        assert_eq!(streamed, listed);
+        assert!(streamed.len() >= 3);
+
+        fs::remove_dir_all("Test").await?;
+        iroh_node.router.shutdown().await?;
+        Ok(())
+    }
+}
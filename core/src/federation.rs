@@ -0,0 +1,409 @@
+use crate::docs::{get_document, join_doc, share_doc};
+use helpers::federation::{FederationMember, FederationRegistry, MemberState};
+use helpers::stores::{NodeBlobs, NodeDocs};
+use helpers::sync_controls::SyncControlRegistry;
+use helpers::utils::{decode_doc_id, encode_doc_id, encode_key, SS58AuthorId};
+
+use futures::StreamExt;
+use iroh_docs::rpc::client::docs::{Doc, ShareMode};
+use iroh_docs::rpc::proto::{Request, Response};
+use iroh_docs::rpc::AddrInfoOptions;
+use iroh_docs::store::Query;
+use iroh_docs::{AuthorId, DocTicket, NamespaceId};
+use quic_rpc::transport::flume::FlumeConnector;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Prefix of the catalog keys, each followed by the ID of the listed document.
+pub const CATALOG_KEY_PREFIX: &str = "federation/";
+
+#[derive(Debug, PartialEq)]
+pub enum FederationError {
+    /// The node does not follow a catalog.
+    NotConfigured,
+    /// The catalog has not been opened yet.
+    CatalogNotReady,
+    /// The catalog is neither a document on this node nor a valid ticket.
+    InvalidCatalog,
+    /// Failed to join or open the catalog.
+    FailedToOpenCatalog,
+    /// Failed to read the entries of the catalog.
+    FailedToReadCatalog,
+    /// Failed to write to the catalog.
+    FailedToWriteCatalog,
+    /// The author is not a `--federation-trusted-author`.
+    AuthorNotTrusted,
+    /// Failed to decode the author ID.
+    InvalidAuthorIdFormat,
+    /// Failed to decode the document ID.
+    InvalidDocumentIdFormat,
+    /// The document to list was not found.
+    DocumentNotFound,
+    /// Failed to share the document to list.
+    FailedToShareDocument,
+    /// The document is not listed by the author.
+    EntryNotFound,
+}
+
+impl fmt::Display for FederationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for FederationError {}
+
+/// The value of a catalog entry: how member nodes join the document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    pub ticket: String,
+    /// Only download the content of entries under these key prefixes. Empty to download all.
+    #[serde(default)]
+    pub sync_prefixes: Vec<String>,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+/// A document listed in the catalog by a trusted author.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CatalogListing {
+    pub doc_id: String,
+    /// The trusted author of the newest entry for the document.
+    pub listed_by: String,
+    #[serde(flatten)]
+    pub entry: CatalogEntry,
+}
+
+/// Opens the catalog `catalog`, a document ID or a ticket, and starts syncing it. A ticket for a
+/// document the node does not have yet is joined.
+///
+/// # Returns
+/// * `String` - The document ID of the catalog.
+pub async fn open_catalog(docs: Arc<NodeDocs>, catalog: &str) -> Result<String, FederationError> {
+    let (namespace_id, nodes) = match decode_doc_id(catalog) {
+        Ok(namespace_id) => (NamespaceId::from(namespace_id), vec![]),
+        Err(_) => {
+            let ticket = DocTicket::from_str(catalog).map_err(|_| FederationError::InvalidCatalog)?;
+            (ticket.capability.id(), ticket.nodes)
+        }
+    };
+    let doc_id = encode_doc_id(namespace_id.as_bytes());
+
+    match docs.client().open(namespace_id).await.map_err(|_| FederationError::FailedToOpenCatalog)? {
+        Some(doc) => doc.start_sync(nodes).await.map_err(|_| FederationError::FailedToOpenCatalog)?,
+        None if nodes.is_empty() => return Err(FederationError::InvalidCatalog),
+        None => {
+            join_doc(docs.clone(), catalog.to_string(), vec![])
+                .await
+                .map_err(|_| FederationError::FailedToOpenCatalog)?;
+        }
+    }
+
+    Ok(doc_id)
+}
+
+/// Lists the documents of the catalog, as listed by the trusted authors.
+///
+/// A document is listed while any trusted author has an entry for it, with the values of the
+/// newest such entry. Entries whose content has not been downloaded yet, or that do not hold a
+/// ticket for the document under their key, are skipped.
+pub async fn list_catalog(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    catalog_doc_id: &str,
+    trusted_authors: &[String],
+) -> Result<BTreeMap<String, CatalogListing>, FederationError> {
+    let namespace_id = decode_doc_id(catalog_doc_id).map_err(|_| FederationError::InvalidDocumentIdFormat)?;
+    let doc = get_document(docs, NamespaceId::from(namespace_id))
+        .await
+        .map_err(|_| FederationError::FailedToReadCatalog)?;
+
+    let mut newest: BTreeMap<String, (u64, CatalogListing)> = BTreeMap::new();
+    let mut entries = doc
+        .get_many(Query::key_prefix(CATALOG_KEY_PREFIX.as_bytes()))
+        .await
+        .map_err(|_| FederationError::FailedToReadCatalog)?;
+    while let Some(entry) = entries.next().await {
+        let entry = entry.map_err(|_| FederationError::FailedToReadCatalog)?;
+        let Ok(author) = SS58AuthorId::from_author_id(&entry.author()) else {
+            continue;
+        };
+        if !trusted_authors.iter().any(|trusted| trusted == author.as_ss58()) {
+            continue;
+        }
+        let key = String::from_utf8_lossy(entry.key());
+        let Some(doc_id) = key.trim_end_matches('\0').strip_prefix(CATALOG_KEY_PREFIX).map(str::to_string) else {
+            continue;
+        };
+        if newest.get(&doc_id).is_some_and(|(timestamp, _)| *timestamp >= entry.timestamp()) {
+            continue;
+        }
+
+        let Ok(content) = blobs.client().read_to_bytes(entry.content_hash()).await else {
+            continue;
+        };
+        let catalog_entry = match serde_json::from_slice::<CatalogEntry>(&content) {
+            Ok(catalog_entry) if ticket_opens(&catalog_entry.ticket, &doc_id) => catalog_entry,
+            _ => {
+                tracing::warn!(doc_id = %doc_id, author = %author.as_ss58(), "skipping invalid catalog entry");
+                continue;
+            }
+        };
+        let listing = CatalogListing { doc_id: doc_id.clone(), listed_by: author.as_ss58().to_string(), entry: catalog_entry };
+        newest.insert(doc_id, (entry.timestamp(), listing));
+    }
+
+    Ok(newest.into_iter().map(|(doc_id, (_, listing))| (doc_id, listing)).collect())
+}
+
+// Whether `ticket` is a ticket for the document `doc_id`
+fn ticket_opens(ticket: &str, doc_id: &str) -> bool {
+    match (DocTicket::from_str(ticket), decode_doc_id(doc_id)) {
+        (Ok(ticket), Ok(namespace_id)) => ticket.capability.id() == NamespaceId::from(namespace_id),
+        _ => false,
+    }
+}
+
+/// Lists `doc_id` in the catalog as `author_id`, with a new ticket for it, so that member nodes
+/// join it. Listing it again replaces the author's entry.
+#[allow(clippy::too_many_arguments)]
+pub async fn add_to_catalog(
+    docs: Arc<NodeDocs>,
+    registry: &FederationRegistry,
+    author_id: String,
+    doc_id: String,
+    mode: ShareMode,
+    addr_options: AddrInfoOptions,
+    sync_prefixes: Vec<String>,
+    label: Option<String>,
+) -> Result<CatalogEntry, FederationError> {
+    let (catalog, author) = open_for_writing(&docs, registry, &author_id).await?;
+
+    let ticket = share_doc(docs.clone(), doc_id.clone(), mode, addr_options)
+        .await
+        .map_err(|e| match e {
+            crate::docs::DocError::InvalidDocumentIdFormat => FederationError::InvalidDocumentIdFormat,
+            crate::docs::DocError::DocumentNotFound => FederationError::DocumentNotFound,
+            _ => FederationError::FailedToShareDocument,
+        })?;
+    let entry = CatalogEntry { ticket, sync_prefixes, label };
+    let value = serde_json::to_vec(&entry).map_err(|_| FederationError::FailedToWriteCatalog)?;
+
+    catalog
+        .set_bytes(author, encode_key(format!("{}{}", CATALOG_KEY_PREFIX, doc_id).as_bytes()), value)
+        .await
+        .map_err(|_| FederationError::FailedToWriteCatalog)?;
+    registry.notify_changed();
+
+    Ok(entry)
+}
+
+/// Deletes the catalog entry of `author_id` for `doc_id`. The document stays listed while
+/// another trusted author lists it.
+pub async fn remove_from_catalog(
+    docs: Arc<NodeDocs>,
+    registry: &FederationRegistry,
+    author_id: String,
+    doc_id: String,
+) -> Result<(), FederationError> {
+    let (catalog, author) = open_for_writing(&docs, registry, &author_id).await?;
+
+    let removed = catalog
+        .del(author, encode_key(format!("{}{}", CATALOG_KEY_PREFIX, doc_id).as_bytes()))
+        .await
+        .map_err(|_| FederationError::FailedToWriteCatalog)?;
+    if removed == 0 {
+        return Err(FederationError::EntryNotFound);
+    }
+    registry.notify_changed();
+
+    Ok(())
+}
+
+// Opens the catalog to be written by `author_id`, which must be trusted
+async fn open_for_writing(
+    docs: &Arc<NodeDocs>,
+    registry: &FederationRegistry,
+    author_id: &str,
+) -> Result<(Doc<FlumeConnector<Response, Request>>, AuthorId), FederationError> {
+    if !registry.is_enabled() {
+        return Err(FederationError::NotConfigured);
+    }
+    if !registry.is_trusted(author_id) {
+        return Err(FederationError::AuthorNotTrusted);
+    }
+    let author = SS58AuthorId::decode(author_id).map_err(|_| FederationError::InvalidAuthorIdFormat)?;
+    let catalog_doc_id = registry.catalog_doc_id().ok_or(FederationError::CatalogNotReady)?;
+    let namespace_id = decode_doc_id(&catalog_doc_id).map_err(|_| FederationError::InvalidDocumentIdFormat)?;
+    let catalog = get_document(docs.clone(), NamespaceId::from(namespace_id))
+        .await
+        .map_err(|_| FederationError::CatalogNotReady)?;
+    Ok((catalog, author))
+}
+
+/// Keeps the node's documents in line with the catalog of `registry`: joins every listed
+/// document, keeps the ones it joined syncing, and leaves them once they are delisted. Documents
+/// that were on the node before they were listed are never left.
+///
+/// The catalog is checked whenever it changes, and every `--federation-interval`. A catalog that
+/// cannot be opened yet, for example because no peer is reachable, is tried again at each tick.
+pub fn spawn_federation(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    registry: FederationRegistry,
+    controls: SyncControlRegistry,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let Some(catalog) = registry.settings().catalog.clone() else {
+            return;
+        };
+        let changed = registry.changed();
+        let mut ticks = tokio::time::interval(registry.settings().interval);
+
+        let catalog_doc_id = loop {
+            ticks.tick().await;
+            match open_catalog(docs.clone(), &catalog).await {
+                Ok(doc_id) => break doc_id,
+                Err(e) => tracing::warn!(error = %e, "failed to open the federation catalog"),
+            }
+        };
+        registry.set_catalog_doc_id(catalog_doc_id.clone());
+        tracing::info!(catalog = %catalog_doc_id, "🌐 Following the federation catalog");
+
+        let mut events = match decode_doc_id(&catalog_doc_id) {
+            Ok(namespace_id) => match get_document(docs.clone(), NamespaceId::from(namespace_id)).await {
+                Ok(doc) => doc.subscribe().await.ok(),
+                Err(_) => None,
+            },
+            Err(_) => None,
+        };
+
+        loop {
+            if let Err(e) = reconcile(&docs, &blobs, &registry, &controls, &catalog_doc_id).await {
+                tracing::warn!(error = %e, "failed to apply the federation catalog");
+            }
+            tokio::select! {
+                _ = ticks.tick() => {},
+                _ = changed.notified() => {},
+                // any change to the catalog, local or synced; a closed stream falls back to the ticks
+                Some(_) = async { events.as_mut()?.next().await } => {},
+            }
+        }
+    })
+}
+
+// Joins, resumes and leaves documents so that the members match the catalog
+async fn reconcile(
+    docs: &Arc<NodeDocs>,
+    blobs: &Arc<NodeBlobs>,
+    registry: &FederationRegistry,
+    controls: &SyncControlRegistry,
+    catalog_doc_id: &str,
+) -> Result<(), FederationError> {
+    let listed = list_catalog(docs.clone(), blobs.clone(), catalog_doc_id, &registry.settings().trusted_authors).await?;
+    let members = registry.members();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+    for (doc_id, listing) in &listed {
+        if doc_id == catalog_doc_id {
+            continue;
+        }
+        let member = members.get(doc_id);
+        let Ok(ticket) = DocTicket::from_str(&listing.entry.ticket) else {
+            continue;
+        };
+
+        match member.map(|member| member.state) {
+            Some(MemberState::Present) => continue,
+            Some(MemberState::Joined) => {
+                // keeps syncing after a restart, unless sync controls stop it
+                if controls.blocked_by(doc_id).is_some() {
+                    continue;
+                }
+                let Ok(Some(doc)) = docs.client().open(ticket.capability.id()).await else {
+                    continue;
+                };
+                if doc.status().await.is_ok_and(|status| !status.sync) {
+                    if let Err(e) = doc.start_sync(ticket.nodes.clone()).await {
+                        tracing::warn!(doc_id = %doc_id, error = %e, "failed to resume federated document");
+                    }
+                }
+                continue;
+            }
+            Some(MemberState::Failed) | None => {}
+        }
+
+        let present = docs.client().open(ticket.capability.id()).await.is_ok_and(|doc| doc.is_some());
+        let (state, error) = if present && member.is_none() {
+            (MemberState::Present, None)
+        } else {
+            match join_doc(docs.clone(), listing.entry.ticket.clone(), listing.entry.sync_prefixes.clone()).await {
+                Ok(_) => {
+                    tracing::info!(doc_id = %doc_id, listed_by = %listing.listed_by, "🌐 Joined federated document");
+                    (MemberState::Joined, None)
+                }
+                Err(e) => {
+                    tracing::warn!(doc_id = %doc_id, error = %e, "failed to join federated document");
+                    (MemberState::Failed, Some(e.to_string()))
+                }
+            }
+        };
+        let member = FederationMember {
+            doc_id: doc_id.clone(),
+            state,
+            listed_by: listing.listed_by.clone(),
+            label: listing.entry.label.clone(),
+            updated_at: now,
+            error,
+        };
+        registry.set_member(member).await.map_err(|_| FederationError::FailedToWriteCatalog)?;
+    }
+
+    for (doc_id, member) in members {
+        if listed.contains_key(&doc_id) {
+            continue;
+        }
+        if member.state == MemberState::Joined {
+            let Ok(namespace_id) = decode_doc_id(&doc_id) else {
+                continue;
+            };
+            if let Ok(doc) = get_document(docs.clone(), NamespaceId::from(namespace_id)).await {
+                if let Err(e) = doc.leave().await {
+                    tracing::warn!(doc_id = %doc_id, error = %e, "failed to leave delisted document");
+                    continue;
+                }
+            }
+            tracing::info!(doc_id = %doc_id, "🌐 Left delisted federated document");
+        }
+        registry.remove_member(&doc_id).await.map_err(|_| FederationError::FailedToWriteCatalog)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    pub async fn test_catalog_entries_match_their_key() -> anyhow::Result<()> {
+        let namespace_id = iroh_docs::NamespaceSecret::new(&mut rand::thread_rng()).id();
+        // tickets without a node to contact do not parse
+        let node = iroh::NodeAddr::new(iroh::SecretKey::generate(rand::thread_rng()).public());
+        let ticket = iroh_docs::DocTicket { capability: iroh_docs::Capability::Read(namespace_id), nodes: vec![node] }.to_string();
+        let doc_id = helpers::utils::encode_doc_id(namespace_id.as_bytes());
+
+        let entry: super::CatalogEntry = serde_json::from_value(serde_json::json!({ "ticket": ticket }))?;
+        assert!(entry.sync_prefixes.is_empty() && entry.label.is_none());
+        assert!(super::ticket_opens(&entry.ticket, &doc_id));
+
+        // a ticket listed under the key of another document is not followed
+        let other_doc_id = helpers::utils::encode_doc_id(iroh_docs::NamespaceSecret::new(&mut rand::thread_rng()).id().as_bytes());
+        assert!(!super::ticket_opens(&entry.ticket, &other_doc_id));
+        assert!(!super::ticket_opens("not a ticket", &doc_id));
+        Ok(())
+    }
+}
@@ -0,0 +1,443 @@
+//! Filter expressions over entries, such as `value.owner == "Dhiway" && value.count > 2`.
+//!
+//! An expression compares fields of an entry with literals, and combines comparisons with `&&`,
+//! `||`, `!` and parentheses:
+//!
+//! - `value` is the entry's value decoded as JSON, and `value.a.b` or `value.items[0]` a field of
+//!   it. A field that is missing, or a value that is not JSON, is `null`.
+//! - `key`, `author`, `timestamp` and `len` are the entry's key, SS58 author ID, timestamp in
+//!   microseconds and value length.
+//! - Literals are strings in double quotes, numbers, `true`, `false` and `null`.
+//! - `==` and `!=` compare any two values. `<`, `<=`, `>` and `>=` compare two numbers or two
+//!   strings, and are false otherwise. `a contains b` is true if the string `a` holds the string
+//!   `b`, or the array `a` has an element equal to `b`.
+//! - A field alone is true unless it is `null` or `false`.
+
+use crate::docs::EntryDetails;
+
+use serde_json::Value;
+use std::fmt;
+
+/// A filter expression that does not parse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterError {
+    /// Byte offset in the expression where parsing stopped.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid filter at position {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+/// A parsed filter expression, see the module documentation for its syntax.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    expr: Expr,
+    // whether some path reads the value, so entries are only read when needed
+    reads_value: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Operand, Op, Operand),
+    Truthy(Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Literal(Value),
+    Path(Root, Vec<Segment>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Root {
+    Value,
+    Key,
+    Author,
+    Timestamp,
+    Len,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(f64),
+    Dot,
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    Not,
+    And,
+    Or,
+    Op(Op),
+}
+
+impl Filter {
+    /// Parses `expression`.
+    pub fn parse(expression: &str) -> Result<Self, FilterError> {
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser { tokens, next: 0, end: expression.len(), reads_value: false };
+        let expr = parser.or()?;
+        if let Some((position, _)) = parser.tokens.get(parser.next) {
+            return Err(FilterError { position: *position, message: "unexpected token".to_string() });
+        }
+        Ok(Self { expr, reads_value: parser.reads_value })
+    }
+
+    /// Whether the expression reads `value`, so the values of entries must be decoded.
+    pub fn reads_value(&self) -> bool {
+        self.reads_value
+    }
+
+    /// Whether `entry`, whose value decodes to `value`, matches.
+    pub fn matches(&self, entry: &EntryDetails, value: &Value) -> bool {
+        self.expr.eval(entry, value)
+    }
+}
+
+impl Expr {
+    fn eval(&self, entry: &EntryDetails, value: &Value) -> bool {
+        match self {
+            Expr::Or(left, right) => left.eval(entry, value) || right.eval(entry, value),
+            Expr::And(left, right) => left.eval(entry, value) && right.eval(entry, value),
+            Expr::Not(inner) => !inner.eval(entry, value),
+            Expr::Truthy(operand) => !matches!(operand.resolve(entry, value), Value::Null | Value::Bool(false)),
+            Expr::Compare(left, op, right) => compare(&left.resolve(entry, value), *op, &right.resolve(entry, value)),
+        }
+    }
+}
+
+impl Operand {
+    fn resolve(&self, entry: &EntryDetails, value: &Value) -> Value {
+        let (root, segments) = match self {
+            Operand::Literal(literal) => return literal.clone(),
+            Operand::Path(root, segments) => (root, segments),
+        };
+        let root = match root {
+            Root::Value => value,
+            Root::Key => return Value::from(entry.namespace.key.as_str()),
+            Root::Author => return Value::from(entry.namespace.author.as_str()),
+            Root::Timestamp => return Value::from(entry.record.timestamp),
+            Root::Len => return Value::from(entry.record.len),
+        };
+        let mut current = root;
+        for segment in segments {
+            let next = match segment {
+                Segment::Field(field) => current.get(field),
+                Segment::Index(index) => current.get(index),
+            };
+            match next {
+                Some(next) => current = next,
+                None => return Value::Null,
+            }
+        }
+        current.clone()
+    }
+}
+
+fn compare(left: &Value, op: Op, right: &Value) -> bool {
+    use std::cmp::Ordering;
+
+    let ordering = match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64().zip(b.as_f64()).and_then(|(a, b)| a.partial_cmp(&b)),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    match op {
+        Op::Eq => ordering.map_or(left == right, |ordering| ordering == Ordering::Equal),
+        Op::Ne => ordering.map_or(left != right, |ordering| ordering != Ordering::Equal),
+        Op::Lt => ordering == Some(Ordering::Less),
+        Op::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        Op::Gt => ordering == Some(Ordering::Greater),
+        Op::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        Op::Contains => match (left, right) {
+            (Value::String(haystack), Value::String(needle)) => haystack.contains(needle.as_str()),
+            (Value::Array(items), _) => items.iter().any(|item| compare(item, Op::Eq, right)),
+            _ => false,
+        },
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<(usize, Token)>, FilterError> {
+    let error = |position: usize, message: &str| FilterError { position, message: message.to_string() };
+    let bytes = expression.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let two = expression.get(i..i + 2).unwrap_or("");
+        let (token, width) = match bytes[i] {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                i += 1;
+                continue;
+            }
+            _ if two == "&&" => (Token::And, 2),
+            _ if two == "||" => (Token::Or, 2),
+            _ if two == "==" => (Token::Op(Op::Eq), 2),
+            _ if two == "!=" => (Token::Op(Op::Ne), 2),
+            _ if two == "<=" => (Token::Op(Op::Le), 2),
+            _ if two == ">=" => (Token::Op(Op::Ge), 2),
+            b'<' => (Token::Op(Op::Lt), 1),
+            b'>' => (Token::Op(Op::Gt), 1),
+            b'!' => (Token::Not, 1),
+            b'.' => (Token::Dot, 1),
+            b'[' => (Token::LBracket, 1),
+            b']' => (Token::RBracket, 1),
+            b'(' => (Token::LParen, 1),
+            b')' => (Token::RParen, 1),
+            b'"' => {
+                // JSON string rules, so escapes work as in values
+                let mut end = i + 1;
+                while end < bytes.len() && bytes[end] != b'"' {
+                    end += if bytes[end] == b'\\' { 2 } else { 1 };
+                }
+                if end >= bytes.len() {
+                    return Err(error(start, "unterminated string"));
+                }
+                let text: String = serde_json::from_str(&expression[i..=end]).map_err(|_| error(start, "invalid string"))?;
+                i = end + 1;
+                tokens.push((start, Token::Str(text)));
+                continue;
+            }
+            b'-' | b'0'..=b'9' => {
+                let mut end = i + 1;
+                while end < bytes.len() && matches!(bytes[end], b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-') {
+                    end += 1;
+                }
+                let number = expression[i..end].parse().map_err(|_| error(start, "invalid number"))?;
+                i = end;
+                tokens.push((start, Token::Number(number)));
+                continue;
+            }
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+                let mut end = i + 1;
+                while end < bytes.len() && matches!(bytes[end], b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'-') {
+                    end += 1;
+                }
+                let word = &expression[i..end];
+                i = end;
+                tokens.push((start, if word == "contains" { Token::Op(Op::Contains) } else { Token::Ident(word.to_string()) }));
+                continue;
+            }
+            _ => return Err(error(start, "unexpected character")),
+        };
+        i += width;
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    // length of the expression, the position of errors at its end
+    end: usize,
+    reads_value: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.next).map_or(self.end, |(position, _)| *position)
+    }
+
+    fn error(&self, message: &str) -> FilterError {
+        FilterError { position: self.position(), message: message.to_string() }
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.and()?;
+        while self.eat(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.unary()?;
+        while self.eat(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, FilterError> {
+        if self.eat(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat(&Token::LParen) {
+            let expr = self.or()?;
+            if !self.eat(&Token::RParen) {
+                return Err(self.error("expected `)`"));
+            }
+            return Ok(expr);
+        }
+        let left = self.operand()?;
+        match self.peek() {
+            Some(Token::Op(op)) => {
+                let op = *op;
+                self.next += 1;
+                Ok(Expr::Compare(left, op, self.operand()?))
+            }
+            _ => Ok(Expr::Truthy(left)),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, FilterError> {
+        let Some(token) = self.peek().cloned() else {
+            return Err(self.error("expected a field or a literal"));
+        };
+        let position = self.position();
+        self.next += 1;
+        let root = match token {
+            Token::Str(text) => return Ok(Operand::Literal(Value::from(text))),
+            Token::Number(number) => return Ok(Operand::Literal(Value::from(number))),
+            Token::Ident(word) => match word.as_str() {
+                "true" => return Ok(Operand::Literal(Value::Bool(true))),
+                "false" => return Ok(Operand::Literal(Value::Bool(false))),
+                "null" => return Ok(Operand::Literal(Value::Null)),
+                "value" => Root::Value,
+                "key" => Root::Key,
+                "author" => Root::Author,
+                "timestamp" => Root::Timestamp,
+                "len" => Root::Len,
+                _ => {
+                    return Err(FilterError {
+                        position,
+                        message: format!("unknown field `{}`, expected value, key, author, timestamp or len", word),
+                    })
+                }
+            },
+            _ => {
+                self.next -= 1;
+                return Err(self.error("expected a field or a literal"));
+            }
+        };
+
+        let mut segments = Vec::new();
+        loop {
+            if self.eat(&Token::Dot) {
+                match self.peek().cloned() {
+                    Some(Token::Ident(field)) => {
+                        self.next += 1;
+                        segments.push(Segment::Field(field));
+                    }
+                    _ => return Err(self.error("expected a field name after `.`")),
+                }
+            } else if self.eat(&Token::LBracket) {
+                match self.peek().cloned() {
+                    Some(Token::Number(index)) if index >= 0.0 && index.fract() == 0.0 => {
+                        self.next += 1;
+                        segments.push(Segment::Index(index as usize));
+                    }
+                    Some(Token::Str(field)) => {
+                        self.next += 1;
+                        segments.push(Segment::Field(field));
+                    }
+                    _ => return Err(self.error("expected an index or a quoted field name")),
+                }
+                if !self.eat(&Token::RBracket) {
+                    return Err(self.error("expected `]`"));
+                }
+            } else {
+                break;
+            }
+        }
+        if root == Root::Value {
+            self.reads_value = true;
+        } else if !segments.is_empty() {
+            return Err(FilterError { position, message: "only `value` has fields".to_string() });
+        }
+        Ok(Operand::Path(root, segments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docs::{EntryIdDetails, RecordDetails};
+    use serde_json::json;
+
+    fn entry(key: &str) -> EntryDetails {
+        EntryDetails {
+            namespace: EntryIdDetails { doc: "doc".to_string(), key: key.to_string(), author: "author".to_string() },
+            record: RecordDetails { hash: "hash".to_string(), len: 42, timestamp: 1_000, anchor: None },
+        }
+    }
+
+    fn matches(expression: &str, value: Value) -> bool {
+        Filter::parse(expression).unwrap().matches(&entry("users/alice"), &value)
+    }
+
+    #[test]
+    fn compares_fields_of_the_value() {
+        let value = json!({ "owner": "Dhiway", "number_of_entries": 3, "tags": ["a", "b"], "nested": { "ok": true } });
+        assert!(matches(r#"value.owner == "Dhiway" && value.number_of_entries > 2"#, value.clone()));
+        assert!(!matches(r#"value.owner == "Dhiway" && value.number_of_entries > 3"#, value.clone()));
+        assert!(matches(r#"value.owner != "Acme" || value.missing == 1"#, value.clone()));
+        assert!(matches(r#"value.tags contains "b" && value.tags[0] == "a""#, value.clone()));
+        assert!(matches("value.nested.ok && !value.missing", value.clone()));
+        assert!(matches(r#"value["owner"] contains "hi""#, value.clone()));
+        assert!(matches("value.missing == null", value));
+    }
+
+    #[test]
+    fn compares_entry_fields() {
+        assert!(matches(r#"key >= "users/" && key < "users0" && len == 42 && timestamp <= 1000"#, Value::Null));
+        assert!(matches(r#"author == "author""#, Value::Null));
+    }
+
+    #[test]
+    fn mismatched_types_do_not_order() {
+        assert!(!matches(r#"value.n > "1""#, json!({ "n": 2 })));
+        assert!(matches(r#"value.n != "2""#, json!({ "n": 2 })));
+    }
+
+    #[test]
+    fn reports_where_parsing_stopped() {
+        assert_eq!(Filter::parse("value.a ==").unwrap_err().position, 10);
+        assert_eq!(Filter::parse("owner == 1").unwrap_err().position, 0);
+        assert_eq!(Filter::parse("(value.a").unwrap_err().position, 8);
+        assert_eq!(Filter::parse("key.a == 1").unwrap_err().position, 0);
+        assert!(!Filter::parse("key == 1").unwrap().reads_value());
+    }
+}
@@ -0,0 +1,168 @@
+use crate::docs::{DocError, EntryDetails, EntryIdDetails, RecordDetails};
+use crate::trash::{open_doc, purge_expired_entries};
+use helpers::errors::Cause;
+use helpers::stores::NodeDocs;
+use helpers::utils::{decode_key, encode_key, history_key, is_history_key, is_trash_key, parse_history_key, SS58AuthorId, HISTORY_KEY_PREFIX};
+
+use futures::TryStreamExt;
+use iroh_docs::rpc::client::docs::Doc;
+use iroh_docs::rpc::proto::{Request, Response};
+use iroh_docs::store::{FlatQuery, Query, QueryBuilder};
+use iroh_docs::{AuthorId, Entry};
+use quic_rpc::transport::flume::FlumeConnector;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+// How often the versions kept past their retention are removed
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Keeps the versions `entries` of `author` hold before they are replaced or deleted. The content
+/// of each one is referenced again under `__history/<key>/<written_at>`, so it syncs to peers
+/// and is not garbage collected, and the history entry's own timestamp is when it was replaced.
+pub(crate) async fn retain_versions(
+    doc: &Doc<FlumeConnector<Response, Request>>,
+    author: AuthorId,
+    entries: &[Entry],
+) -> anyhow::Result<(), DocError> {
+    for entry in entries.iter().filter(|entry| entry.content_len() > 0) {
+        let key = String::from_utf8(decode_key(entry.key()))
+            .map_err(|e| DocError::FailedToDecodeEntryKey(Cause::new(e)))?;
+        doc.set_hash(author, encode_key(history_key(&key, entry.timestamp()).as_bytes()), entry.content_hash(), entry.content_len())
+            .await
+            .map_err(|e| DocError::FailedToRetainVersion(Cause::new(e)))?;
+    }
+    Ok(())
+}
+
+/// Keeps the version of the entry `author` holds at `key`, if any, before it is replaced.
+pub(crate) async fn retain_current_version(
+    doc: &Doc<FlumeConnector<Response, Request>>,
+    author: AuthorId,
+    key: &str,
+) -> anyhow::Result<(), DocError> {
+    let current = doc
+        .get_exact(author, encode_key(key.as_bytes()), false)
+        .await
+        .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
+    match current {
+        Some(entry) => retain_versions(doc, author, &[entry]).await,
+        None => Ok(()),
+    }
+}
+
+/// Reconstructs the entries a document held at `timestamp`, in microseconds since the Unix epoch
+/// like entry timestamps, from its current entries and the versions kept of replaced and deleted
+/// ones. Only entries at `key_prefix` and of `author_id` are returned, if given.
+///
+/// Versions replaced longer than `--version-retention` ago are no longer kept, so older states
+/// may miss entries.
+///
+/// # Returns
+/// The entries as they were at `timestamp`, sorted by key and author, each with the timestamp it
+/// was written at.
+pub async fn get_entries_at(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    timestamp: u64,
+    key_prefix: Option<String>,
+    author_id: Option<String>,
+) -> anyhow::Result<Vec<EntryDetails>, DocError> {
+    let author = author_id
+        .map(|author_id| SS58AuthorId::decode(&author_id).map_err(|_| DocError::InvalidAuthorIdFormat))
+        .transpose()?;
+    let doc = open_doc(docs, &doc_id).await?;
+    let prefix = key_prefix.unwrap_or_default();
+
+    // the version of each key and author that was live at `timestamp`
+    let mut versions: BTreeMap<(String, AuthorId), RecordDetails> = BTreeMap::new();
+    let mut keep = |key: &str, author: AuthorId, entry: &Entry, written_at: u64| {
+        let newer = versions.get(&(key.to_string(), author)).is_none_or(|kept| kept.timestamp < written_at);
+        if newer {
+            versions.insert((key.to_string(), author), RecordDetails {
+                hash: entry.content_hash().to_string(),
+                len: entry.content_len(),
+                timestamp: written_at,
+                anchor: None,
+            });
+        }
+    };
+
+    for entry in get_many(&doc, Query::key_prefix(prefix.as_bytes()), author).await? {
+        let key = String::from_utf8(decode_key(entry.key()))
+            .map_err(|e| DocError::FailedToDecodeEntryKey(Cause::new(e)))?;
+        if is_history_key(&key) || is_trash_key(&key) || entry.timestamp() > timestamp {
+            continue;
+        }
+        keep(&key, entry.author(), &entry, entry.timestamp());
+    }
+
+    let history_prefix = format!("{}{}", HISTORY_KEY_PREFIX, prefix);
+    for entry in get_many(&doc, Query::key_prefix(history_prefix.as_bytes()), author).await? {
+        let history_key = String::from_utf8(decode_key(entry.key()))
+            .map_err(|e| DocError::FailedToDecodeEntryKey(Cause::new(e)))?;
+        let Some((key, written_at)) = parse_history_key(&history_key) else {
+            continue;
+        };
+        // the history entry was written when the version was replaced
+        if written_at <= timestamp && timestamp < entry.timestamp() {
+            keep(key, entry.author(), &entry, written_at);
+        }
+    }
+
+    let doc = doc.id().to_string();
+    versions
+        .into_iter()
+        .map(|((key, author), record)| {
+            let author = SS58AuthorId::from_author_id(&author)
+                .map_err(|e| DocError::FailedToEncodeAuthorId(Cause::new(e)))?;
+            Ok(EntryDetails {
+                namespace: EntryIdDetails { doc: doc.clone(), key, author: author.as_ss58().to_string() },
+                record,
+            })
+        })
+        .collect()
+}
+
+/// Deletes for good the versions that the authors of this node replaced longer than `retention`
+/// ago, in any document.
+///
+/// # Returns
+/// The number of versions deleted.
+pub async fn purge_expired_versions(docs: Arc<NodeDocs>, retention: Duration) -> anyhow::Result<usize, DocError> {
+    purge_expired_entries(docs, HISTORY_KEY_PREFIX, retention).await
+}
+
+/// Removes the versions kept past `retention` every hour, see `--version-retention`.
+pub fn spawn_history_purger(docs: Arc<NodeDocs>, retention: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            ticks.tick().await;
+            match purge_expired_versions(docs.clone(), retention).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!(purged, "🕰 Deleted expired versions of entries"),
+                Err(e) => tracing::warn!(error = %e, "failed to list documents"),
+            }
+        }
+    })
+}
+
+async fn get_many(
+    doc: &Doc<FlumeConnector<Response, Request>>,
+    query: QueryBuilder<FlatQuery>,
+    author: Option<AuthorId>,
+) -> anyhow::Result<Vec<Entry>, DocError> {
+    let query = match author {
+        Some(author) => query.author(author),
+        None => query,
+    };
+    doc.get_many(query)
+        .await
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?
+        .try_collect()
+        .await
+        .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))
+}
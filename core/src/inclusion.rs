@@ -0,0 +1,203 @@
+use crate::docs::{digest_entries, list_doc_anchors, DigestEntry, DocDigestKind, DocError, EntryAnchor};
+use crate::proofs::{merkle_path, merkle_root, root_from_path};
+use helpers::errors::Cause;
+use helpers::stores::{NodeBlobs, NodeDocs};
+use helpers::proofs::MerkleStep;
+use helpers::utils::{decode_doc_id, validate_key, SS58AuthorId};
+use cord::backend::ChainAnchor;
+
+use iroh_blobs::Hash;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Proof that an entry is one of the leaves of a document's Merkle root, on its own and without
+/// the other entries. Paired with a Merkle anchor, it proves the entry existed when the anchor's
+/// block was finalized.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryInclusionProof {
+    pub doc_id: String,
+    pub key: String,
+    pub author: String,
+    /// Content hash of the entry.
+    pub hash: String,
+    /// Microseconds since the Unix epoch, like entry timestamps.
+    pub timestamp: u64,
+    /// Position of the entry's leaf among the leaves, which are sorted by key and author.
+    pub index: usize,
+    /// Number of leaves in the tree.
+    pub entries: usize,
+    /// Path from the entry's leaf to `root`.
+    pub path: Vec<MerkleStep>,
+    /// 0x-prefixed Merkle root.
+    pub root: String,
+    /// The anchor whose digest is `root`, when the proof was made against one.
+    pub anchor: Option<EntryAnchor>,
+}
+
+/// The result of checking an inclusion proof.
+#[derive(Debug, Clone, Serialize)]
+pub struct InclusionVerification {
+    /// Whether the path leads from the entry's leaf to the root.
+    pub path_valid: bool,
+    /// Whether the anchor's extrinsic is on chain with the root as its remark. `None` for a proof
+    /// made against the current state of the document.
+    pub anchored: Option<bool>,
+    /// Whether the path is valid and, for an anchored proof, the root is anchored.
+    pub valid: bool,
+}
+
+/// The digest of an entry a Merkle leaf is made from: the blake3 hash of the document ID, the key
+/// length as 4 big-endian bytes, the key, the author, the content hash and the timestamp as 8
+/// big-endian bytes.
+pub fn entry_digest(namespace: &[u8; 32], key: &[u8], author: &[u8; 32], content_hash: &[u8; 32], timestamp: u64) -> [u8; 32] {
+    let mut message = namespace.to_vec();
+    message.extend_from_slice(&(key.len() as u32).to_be_bytes());
+    message.extend_from_slice(key);
+    message.extend_from_slice(author);
+    message.extend_from_slice(content_hash);
+    message.extend_from_slice(&timestamp.to_be_bytes());
+    *Hash::new(&message).as_bytes()
+}
+
+/// Computes the Merkle root of a document's entries, or of the entries at `keys` only, with a leaf
+/// per entry made from `entry_digest`. Covers the same entries as `docs::compute_doc_digest`.
+///
+/// # Returns
+/// - The root and the number of entries it covers.
+pub async fn compute_doc_merkle_root(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    keys: Option<&[String]>,
+) -> anyhow::Result<([u8; 32], usize), DocError> {
+    let (namespace_id, entries) = digest_entries(docs, &doc_id, keys).await?;
+    // a tree needs at least one leaf
+    if entries.is_empty() {
+        return Err(DocError::EntryNotFound);
+    }
+
+    Ok((merkle_root(&leaf_digests(namespace_id.as_bytes(), &entries)), entries.len()))
+}
+
+/// Proves that the entry `author_id` wrote at `key` is included in the Merkle root of the
+/// document, or, with `extrinsic_hash`, in the root anchored by that extrinsic.
+///
+/// Fails with `AnchorNoLongerMatches` when the entries the anchor covers changed since, as the
+/// anchored root can then no longer be rebuilt.
+pub async fn entry_inclusion_proof(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    author_id: String,
+    key: String,
+    extrinsic_hash: Option<String>,
+) -> anyhow::Result<EntryInclusionProof, DocError> {
+    let author = SS58AuthorId::decode(&author_id).map_err(|_| DocError::InvalidAuthorIdFormat)?;
+    validate_key(&key, true)
+        .await
+        .map_err(|e| DocError::FailedToValidateKey(Cause::new(e)))?;
+
+    let anchor = match extrinsic_hash {
+        Some(extrinsic_hash) => {
+            let anchor = list_doc_anchors(docs.clone(), blobs, doc_id.clone())
+                .await?
+                .into_iter()
+                .find(|anchor| anchor.extrinsic_hash == extrinsic_hash)
+                .ok_or(DocError::AnchorNotFound)?;
+            if anchor.digest_kind != DocDigestKind::Merkle {
+                return Err(DocError::AnchorNotMerkle);
+            }
+            if anchor.keys.as_ref().is_some_and(|keys| !keys.contains(&key)) {
+                return Err(DocError::EntryNotAnchored);
+            }
+            Some(anchor)
+        }
+        None => None,
+    };
+
+    let keys = anchor.as_ref().and_then(|anchor| anchor.keys.as_deref());
+    let (namespace_id, entries) = match digest_entries(docs, &doc_id, keys).await {
+        // an anchored key that was deleted since
+        Err(DocError::EntryNotFound) if anchor.is_some() => return Err(DocError::AnchorNoLongerMatches),
+        result => result?,
+    };
+    let index = entries
+        .iter()
+        .position(|(entry_key, entry_author, _, _)| entry_key == key.as_bytes() && entry_author == author.as_bytes())
+        .ok_or(DocError::EntryNotFound)?;
+
+    let leaves = leaf_digests(namespace_id.as_bytes(), &entries);
+    let root = to_hex(&merkle_root(&leaves));
+    if anchor.as_ref().is_some_and(|anchor| anchor.digest != root) {
+        return Err(DocError::AnchorNoLongerMatches);
+    }
+
+    let (_, _, content_hash, timestamp) = &entries[index];
+    Ok(EntryInclusionProof {
+        doc_id,
+        key,
+        author: author_id,
+        hash: Hash::from_bytes(*content_hash).to_string(),
+        timestamp: *timestamp,
+        index,
+        entries: entries.len(),
+        path: merkle_path(&leaves, index),
+        root,
+        anchor: anchor.map(|anchor| EntryAnchor {
+            block_hash: anchor.block_hash,
+            block_number: anchor.block_number,
+            extrinsic_hash: anchor.extrinsic_hash,
+            extrinsic_index: anchor.extrinsic_index,
+            digest: anchor.digest,
+            keys: anchor.keys,
+        }),
+    })
+}
+
+/// Checks an inclusion proof without reading the document, so it holds for a node that does not
+/// have it: the entry's leaf must lead to the root along the path and, for an anchored proof, the
+/// root must be the remark of the anchor's extrinsic.
+pub async fn verify_inclusion_proof(
+    proof: &EntryInclusionProof,
+    anchor: Arc<dyn ChainAnchor>,
+) -> anyhow::Result<InclusionVerification, DocError> {
+    let path_valid = proof_digest(proof)
+        .and_then(|digest| root_from_path(digest, &proof.path))
+        .is_some_and(|root| to_hex(&root) == proof.root);
+
+    let anchored = match &proof.anchor {
+        Some(entry_anchor) => {
+            let remark = anchor
+                .remark_at(&entry_anchor.block_hash, entry_anchor.extrinsic_index)
+                .await
+                .map_err(|e| DocError::FailedToReadAnchor(Cause::new(e)))?;
+            Some(entry_anchor.digest == proof.root && remark.is_some_and(|remark| to_hex(&remark) == proof.root))
+        }
+        None => None,
+    };
+
+    Ok(InclusionVerification {
+        valid: path_valid && anchored.unwrap_or(true),
+        path_valid,
+        anchored,
+    })
+}
+
+fn leaf_digests(namespace: &[u8; 32], entries: &[DigestEntry]) -> Vec<[u8; 32]> {
+    entries
+        .iter()
+        .map(|(key, author, content_hash, timestamp)| entry_digest(namespace, key, author, content_hash, *timestamp))
+        .collect()
+}
+
+// The digest of the entry a proof is about, or `None` if one of its fields is malformed
+fn proof_digest(proof: &EntryInclusionProof) -> Option<[u8; 32]> {
+    let namespace = decode_doc_id(&proof.doc_id).ok()?;
+    let author = SS58AuthorId::decode(&proof.author).ok()?;
+    let content_hash = Hash::from_str(&proof.hash).ok()?;
+    Some(entry_digest(&namespace, proof.key.as_bytes(), author.as_bytes(), content_hash.as_bytes(), proof.timestamp))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
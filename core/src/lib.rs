@@ -1,3 +1,19 @@
+pub mod archives;
 pub mod authors;
+pub mod backups;
 pub mod blobs;
 pub mod docs;
+pub mod federation;
+pub mod filter;
+pub mod history;
+pub mod inclusion;
+pub mod pinning;
+pub mod proofs;
+pub mod replication;
+pub mod schemas;
+pub mod sharding;
+pub mod storage;
+pub mod tables;
+pub mod trash;
+pub mod verification;
+pub mod write_queue;
@@ -0,0 +1,380 @@
+use crate::docs::{get_document, list_docs};
+use helpers::utils::{decode_doc_id, encode_key, ApiDownloadPolicy, SS58AuthorId, CONTENT_POLICY_KEY};
+use helpers::stores::{NodeBlobs, NodeDocs};
+
+use futures::TryStreamExt;
+use iroh::{NodeAddr, NodeId};
+use iroh_base::PublicKey;
+use iroh_blobs::rpc::client::blobs::{DownloadMode, DownloadOptions};
+use iroh_blobs::util::{SetTagOption, Tag};
+use iroh_blobs::{BlobFormat, Hash};
+use iroh_docs::rpc::client::docs::Doc;
+use iroh_docs::rpc::proto::{Request, Response};
+use iroh_docs::store::{DownloadPolicy, FilterKind, Query};
+use iroh_docs::NamespaceId;
+use quic_rpc::transport::flume::FlumeConnector;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+// How often every document's content policy is applied again, to pick up policies synced from
+// peers and fetch content that could not be downloaded before.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// Prefix of the tags that keep the content fetched for a `full` or `prefixes` policy
+const PIN_TAG_PREFIX: &str = "pin:";
+
+#[derive(Debug, PartialEq)]
+pub enum PinningError {
+    /// Failed to decode the document ID.
+    InvalidDocumentIdFormat,
+    /// Failed to decode the author ID.
+    InvalidAuthorIdFormat,
+    /// The document was not found.
+    DocumentNotFound,
+    /// A peer of the policy is not a node ID.
+    InvalidNodeId,
+    /// A `prefixes` policy has no prefixes, or an empty one.
+    InvalidSyncPrefix,
+    /// The content of the policy entry is not a content policy.
+    InvalidContentPolicy,
+    /// The policy entry was synced, but its content was not downloaded yet.
+    ContentPolicyNotDownloaded,
+    /// Failed to read the policy or the entries of the document.
+    FailedToReadDocument,
+    /// Failed to write the policy entry.
+    FailedToWriteContentPolicy,
+    /// Failed to set the download policy of the document.
+    FailedToSetDownloadPolicy,
+    /// Failed to tag or untag content.
+    FailedToUpdateTags,
+}
+
+impl fmt::Display for PinningError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for PinningError {}
+
+/// Which content of a document a node downloads.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ContentMode {
+    /// All content, including content that was skipped before.
+    Full,
+    /// Only the entries, not their content.
+    Metadata,
+    /// The content of entries under these key prefixes, and of their signatures.
+    Prefixes { prefixes: Vec<String> },
+}
+
+impl ContentMode {
+    /// The download policy of the mode. The content of the policy entry itself is always
+    /// downloaded, so that changes to the policy reach every node.
+    pub fn download_policy(&self) -> DownloadPolicy {
+        let policy_filter = FilterKind::Prefix(CONTENT_POLICY_KEY.as_bytes().to_vec().into());
+        match self {
+            ContentMode::Full => DownloadPolicy::EverythingExcept(vec![]),
+            ContentMode::Metadata => DownloadPolicy::NothingExcept(vec![policy_filter]),
+            ContentMode::Prefixes { prefixes } => {
+                let DownloadPolicy::NothingExcept(mut filters) = ApiDownloadPolicy::only_prefixes(prefixes).0 else {
+                    return DownloadPolicy::EverythingExcept(vec![]);
+                };
+                filters.push(policy_filter);
+                DownloadPolicy::NothingExcept(filters)
+            }
+        }
+    }
+}
+
+/// Which nodes download the content of a document, stored in the document itself under
+/// `CONTENT_POLICY_KEY` so that it reaches every node that replicates it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentPolicy {
+    /// The mode of nodes without one of their own.
+    pub default: ContentMode,
+    /// Modes of single nodes, by node ID, such as `full` for archive nodes.
+    #[serde(default)]
+    pub peers: BTreeMap<String, ContentMode>,
+}
+
+impl ContentPolicy {
+    /// The mode of the node `node_id`.
+    pub fn mode_for(&self, node_id: &str) -> &ContentMode {
+        self.peers.get(node_id).unwrap_or(&self.default)
+    }
+
+    fn validate(&self) -> Result<(), PinningError> {
+        for node_id in self.peers.keys() {
+            NodeId::from_str(node_id).map_err(|_| PinningError::InvalidNodeId)?;
+        }
+        for mode in self.peers.values().chain([&self.default]) {
+            if let ContentMode::Prefixes { prefixes } = mode {
+                if prefixes.is_empty() || prefixes.iter().any(String::is_empty) {
+                    return Err(PinningError::InvalidSyncPrefix);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What applying a document's content policy did on this node.
+#[derive(Debug, Clone, Serialize)]
+pub struct PinningOutcome {
+    pub doc_id: String,
+    pub mode: ContentMode,
+    /// Missing content that was downloaded.
+    pub fetched: usize,
+    /// Missing content that no peer could provide. It is tried again at the next check.
+    pub failed: usize,
+}
+
+// Opens `doc_id`
+async fn open_doc(docs: &Arc<NodeDocs>, doc_id: &str) -> Result<Doc<FlumeConnector<Response, Request>>, PinningError> {
+    let namespace_id = decode_doc_id(doc_id).map_err(|_| PinningError::InvalidDocumentIdFormat)?;
+    get_document(docs.clone(), NamespaceId::from(namespace_id))
+        .await
+        .map_err(|_| PinningError::DocumentNotFound)
+}
+
+/// Sets the content policy of `doc_id`, written by `author_id`. It replaces any earlier policy
+/// once synced, whoever wrote it.
+pub async fn set_content_policy(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    author_id: String,
+    policy: &ContentPolicy,
+) -> Result<(), PinningError> {
+    policy.validate()?;
+    let author = SS58AuthorId::decode(&author_id).map_err(|_| PinningError::InvalidAuthorIdFormat)?;
+    let doc = open_doc(&docs, &doc_id).await?;
+
+    let value = serde_json::to_vec(policy).map_err(|_| PinningError::FailedToWriteContentPolicy)?;
+    doc.set_bytes(author, encode_key(CONTENT_POLICY_KEY.as_bytes()), value)
+        .await
+        .map_err(|_| PinningError::FailedToWriteContentPolicy)?;
+
+    Ok(())
+}
+
+/// The content policy of `doc_id`, the newest one written by any author, or `None` if it has
+/// none.
+pub async fn get_content_policy(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: &str,
+) -> Result<Option<ContentPolicy>, PinningError> {
+    let doc = open_doc(&docs, doc_id).await?;
+    let Some(hash) = policy_hash(&doc).await? else {
+        return Ok(None);
+    };
+    read_policy(&blobs, hash).await.map(Some)
+}
+
+// Content hash of the newest policy entry of `doc`
+async fn policy_hash(doc: &Doc<FlumeConnector<Response, Request>>) -> Result<Option<Hash>, PinningError> {
+    let entries: Vec<_> = doc
+        .get_many(Query::key_exact(encode_key(CONTENT_POLICY_KEY.as_bytes())))
+        .await
+        .map_err(|_| PinningError::FailedToReadDocument)?
+        .try_collect()
+        .await
+        .map_err(|_| PinningError::FailedToReadDocument)?;
+    Ok(entries.into_iter().max_by_key(|entry| entry.timestamp()).map(|entry| entry.content_hash()))
+}
+
+async fn read_policy(blobs: &Arc<NodeBlobs>, hash: Hash) -> Result<ContentPolicy, PinningError> {
+    let content = blobs
+        .client()
+        .read_to_bytes(hash)
+        .await
+        .map_err(|_| PinningError::ContentPolicyNotDownloaded)?;
+    serde_json::from_slice(&content).map_err(|_| PinningError::InvalidContentPolicy)
+}
+
+/// Applies the content policy of `doc_id` to this node, `node_id`: sets the download policy of
+/// its mode and, unless it is `metadata`, downloads the missing content it covers from the
+/// document's peers. Content fetched this way is tagged, and untagged again once the node is
+/// set to `metadata`.
+///
+/// # Returns
+/// * `None` if the document has no content policy, in which case its download policy is left
+///   as it is.
+pub async fn apply_content_policy(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    node_id: &str,
+    doc_id: &str,
+    fetch: bool,
+) -> Result<Option<PinningOutcome>, PinningError> {
+    let doc = open_doc(&docs, doc_id).await?;
+    let Some(policy_hash) = policy_hash(&doc).await? else {
+        return Ok(None);
+    };
+    let peers = sync_peers(&doc).await?;
+    // a node that skips the policy's content, such as one that joined with sync prefixes, fetches it here
+    if fetch && !blobs.client().has(policy_hash).await.unwrap_or(false) {
+        download(&blobs, doc_id, policy_hash, &peers).await;
+    }
+    let mode = read_policy(&blobs, policy_hash).await?.mode_for(node_id).clone();
+
+    let download_policy = mode.download_policy();
+    let current = doc.get_download_policy().await.map_err(|_| PinningError::FailedToReadDocument)?;
+    let changed = current != download_policy;
+    if changed {
+        doc.set_download_policy(download_policy.clone())
+            .await
+            .map_err(|_| PinningError::FailedToSetDownloadPolicy)?;
+        tracing::info!(doc_id = %doc_id, ?mode, "📌 Applied content policy");
+    }
+
+    let mut outcome = PinningOutcome { doc_id: doc_id.to_string(), mode, fetched: 0, failed: 0 };
+    if outcome.mode == ContentMode::Metadata {
+        if changed {
+            unpin(&blobs, doc_id).await?;
+        }
+        return Ok(Some(outcome));
+    }
+    if !fetch {
+        return Ok(Some(outcome));
+    }
+
+    let mut entries = doc
+        .get_many(Query::all())
+        .await
+        .map_err(|_| PinningError::FailedToReadDocument)?;
+    while let Some(entry) = entries.try_next().await.map_err(|_| PinningError::FailedToReadDocument)? {
+        let hash = entry.content_hash();
+        if !download_policy.matches(&entry) || blobs.client().has(hash).await.unwrap_or(false) {
+            continue;
+        }
+        if download(&blobs, doc_id, hash, &peers).await {
+            outcome.fetched += 1;
+        } else {
+            outcome.failed += 1;
+        }
+    }
+
+    Ok(Some(outcome))
+}
+
+// Peers `doc` syncs with
+async fn sync_peers(doc: &Doc<FlumeConnector<Response, Request>>) -> Result<Vec<NodeAddr>, PinningError> {
+    Ok(doc
+        .get_sync_peers()
+        .await
+        .map_err(|_| PinningError::FailedToReadDocument)?
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|peer| PublicKey::from_bytes(&peer).ok())
+        .map(NodeAddr::from)
+        .collect())
+}
+
+// Downloads `hash` from any of `peers` and tags it for `doc_id`. Whether it was downloaded.
+async fn download(blobs: &Arc<NodeBlobs>, doc_id: &str, hash: Hash, peers: &[NodeAddr]) -> bool {
+    if peers.is_empty() {
+        return false;
+    }
+    let options = DownloadOptions {
+        format: BlobFormat::Raw,
+        nodes: peers.to_vec(),
+        tag: SetTagOption::Named(Tag::from(format!("{}{}:{}", PIN_TAG_PREFIX, doc_id, hash))),
+        mode: DownloadMode::Queued,
+    };
+    match blobs.client().download_with_opts(hash, options).await {
+        Ok(progress) => progress.finish().await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+// Deletes the tags of the content fetched for `doc_id`, leaving it to be garbage collected
+// once no entry refers to it
+async fn unpin(blobs: &Arc<NodeBlobs>, doc_id: &str) -> Result<(), PinningError> {
+    let prefix = format!("{}{}:", PIN_TAG_PREFIX, doc_id);
+    let tags: Vec<_> = blobs
+        .client()
+        .tags()
+        .list()
+        .await
+        .map_err(|_| PinningError::FailedToUpdateTags)?
+        .try_collect()
+        .await
+        .map_err(|_| PinningError::FailedToUpdateTags)?;
+    for tag in tags {
+        if tag.name.0.starts_with(prefix.as_bytes()) {
+            blobs.client().tags().delete(tag.name).await.map_err(|_| PinningError::FailedToUpdateTags)?;
+        }
+    }
+    Ok(())
+}
+
+/// Applies the content policy of every document every minute, so that policies synced from
+/// peers take effect and content that could not be fetched is tried again.
+pub fn spawn_pinning(docs: Arc<NodeDocs>, blobs: Arc<NodeBlobs>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let node_id = blobs.endpoint().node_id().to_string();
+        let mut ticks = tokio::time::interval(CHECK_INTERVAL);
+
+        loop {
+            ticks.tick().await;
+            let doc_list = match list_docs(docs.clone()).await {
+                Ok(doc_list) => doc_list,
+                Err(e) => {
+                    tracing::warn!(error = %e, "failed to list documents");
+                    continue;
+                }
+            };
+            for (doc_id, _) in doc_list {
+                match apply_content_policy(docs.clone(), blobs.clone(), &node_id, &doc_id, true).await {
+                    Ok(Some(outcome)) if outcome.fetched > 0 || outcome.failed > 0 => {
+                        tracing::info!(doc_id = %doc_id, fetched = outcome.fetched, failed = outcome.failed, "📌 Fetched pinned content");
+                    }
+                    Ok(_) => {}
+                    Err(PinningError::ContentPolicyNotDownloaded) => {}
+                    Err(e) => tracing::warn!(doc_id = %doc_id, error = %e, "failed to apply content policy"),
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    pub async fn test_content_policy_modes() -> anyhow::Result<()> {
+        let archive = "ae58ff8833241ac82d6ff7611046ed67b5072d142c588d0063e942d9a75502b6";
+        let policy: super::ContentPolicy = serde_json::from_value(serde_json::json!({
+            "default": { "mode": "metadata" },
+            "peers": { archive: { "mode": "full" } },
+        }))?;
+        policy.validate()?;
+        assert_eq!(policy.mode_for(archive), &super::ContentMode::Full);
+        assert_eq!(policy.mode_for("another node"), &super::ContentMode::Metadata);
+
+        // metadata-only nodes still download the policy, so that they see its changes
+        let iroh_docs::store::DownloadPolicy::NothingExcept(filters) = super::ContentMode::Metadata.download_policy() else {
+            panic!("expected a nothing_except policy");
+        };
+        assert!(filters.iter().all(|filter| filter.matches(b"pinning:policy\0")));
+
+        let prefixes = super::ContentMode::Prefixes { prefixes: vec!["public/".to_string()] };
+        let iroh_docs::store::DownloadPolicy::NothingExcept(filters) = prefixes.download_policy() else {
+            panic!("expected a nothing_except policy");
+        };
+        assert!(filters.iter().any(|filter| filter.matches(b"public/a\0")));
+        assert!(filters.iter().any(|filter| filter.matches(b"pinning:policy\0")));
+        assert!(!filters.iter().any(|filter| filter.matches(b"private/a\0")));
+
+        let empty = super::ContentPolicy { default: super::ContentMode::Prefixes { prefixes: vec![] }, peers: Default::default() };
+        assert_eq!(empty.validate().unwrap_err(), super::PinningError::InvalidSyncPrefix);
+        Ok(())
+    }
+}
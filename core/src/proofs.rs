@@ -0,0 +1,499 @@
+use crate::blobs::has_blob;
+use crate::docs::{compute_doc_digest, DocError};
+use helpers::proofs::{MerkleSide, MerkleStep, PendingProof, ProofReceipt, ProofRegistry, ProofSubject};
+use helpers::stores::{NodeBlobs, NodeDocs};
+use cord::backend::ChainAnchor;
+
+use iroh_blobs::Hash;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, PartialEq)]
+pub enum ProofError {
+    /// No subjects were given.
+    NoSubjects,
+    /// The blob hash could not be parsed.
+    InvalidBlobHashFormat,
+    /// The blob is not stored on this node.
+    BlobNotFound,
+    /// Failed to check whether the blob is stored.
+    FailedToCheckBlob,
+    /// Failed to compute the digest of an entry.
+    Doc(DocError),
+    /// Failed to submit the Merkle root to the chain.
+    FailedToAnchorProof,
+    /// Failed to store queued subjects or receipts.
+    FailedToStoreProofs,
+    /// No receipt or queued subject has the given ID.
+    ReceiptNotFound,
+    /// The subject is queued and not anchored yet.
+    ProofPending,
+    /// Failed to read the anchoring extrinsic from the chain.
+    FailedToReadChain,
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProofError::Doc(e) => write!(f, "{}", e),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl std::error::Error for ProofError {}
+
+/// A proof as looked up by ID: queued for the next batch, or anchored.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ProofStatus {
+    Pending(PendingProof),
+    Anchored(ProofReceipt),
+}
+
+/// The result of re-checking a receipt.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProofVerification {
+    pub receipt: ProofReceipt,
+    /// Whether the subject still has the digest it was proven with. `false` for a blob that is
+    /// no longer stored or an entry that changed or was deleted.
+    pub subject_unchanged: bool,
+    /// Whether the Merkle path leads from the digest to the root.
+    pub path_valid: bool,
+    /// Whether the extrinsic in the receipt is on chain with the root as its remark.
+    pub anchored: bool,
+    /// All of the above.
+    pub valid: bool,
+}
+
+/// Computes the digest a subject is proven by: a blob's hash, or the digest of the entries at a
+/// document key.
+pub async fn subject_digest(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    subject: &ProofSubject,
+) -> Result<[u8; 32], ProofError> {
+    match subject {
+        ProofSubject::Blob { hash } => {
+            let parsed = Hash::from_str(hash).map_err(|_| ProofError::InvalidBlobHashFormat)?;
+            if !has_blob(blobs, hash.clone()).await.map_err(|_| ProofError::FailedToCheckBlob)? {
+                return Err(ProofError::BlobNotFound);
+            }
+            Ok(*parsed.as_bytes())
+        },
+        ProofSubject::Entry { doc_id, key } => {
+            let (digest, _) = compute_doc_digest(docs, doc_id.clone(), Some(std::slice::from_ref(key)))
+                .await
+                .map_err(ProofError::Doc)?;
+            Ok(digest)
+        },
+    }
+}
+
+/// Anchors `subjects` to the chain in a single extrinsic, as the root of a Merkle tree of
+/// their digests, and stores a receipt for each of them in `registry`.
+///
+/// # Returns
+/// * `Vec<ProofReceipt>` - One receipt per subject, in order.
+pub async fn create_proofs(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    subjects: Vec<ProofSubject>,
+    anchor: Arc<dyn ChainAnchor>,
+    registry: &ProofRegistry,
+) -> Result<Vec<ProofReceipt>, ProofError> {
+    let batch = prepare_proofs(docs, blobs, subjects).await?;
+    let receipts = anchor_batch(anchor.as_ref(), &batch).await?;
+
+    // the root is on chain either way, so return the receipts even if they could not be stored
+    if let Err(e) = registry.add(&receipts).await {
+        tracing::error!(error = %e, extrinsic_hash = %receipts[0].extrinsic_hash, "❌ Failed to store proof receipts");
+    }
+    Ok(receipts)
+}
+
+/// Queues `subjects` for the next batch, see `spawn_proof_batcher`. Their digests are taken now.
+///
+/// # Returns
+/// * `Vec<PendingProof>` - One queued proof per subject, in order, with the ID its receipt will have.
+pub async fn queue_proofs(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    subjects: Vec<ProofSubject>,
+    registry: &ProofRegistry,
+) -> Result<Vec<PendingProof>, ProofError> {
+    let batch = prepare_proofs(docs, blobs, subjects).await?;
+    registry.enqueue(&batch).await.map_err(|e| {
+        tracing::error!(error = %e, "❌ Failed to queue proofs");
+        ProofError::FailedToStoreProofs
+    })?;
+    Ok(batch)
+}
+
+/// Anchors every queued subject under one Merkle root and stores their receipts.
+///
+/// # Returns
+/// * `usize` - The number of subjects anchored, 0 if none were queued.
+pub async fn anchor_pending_proofs(
+    anchor: &dyn ChainAnchor,
+    registry: &ProofRegistry,
+) -> Result<usize, ProofError> {
+    let batch = registry.pending();
+    if batch.is_empty() {
+        return Ok(0);
+    }
+    let receipts = anchor_batch(anchor, &batch).await?;
+
+    // the subjects stay queued if this fails, and are anchored again with the next batch
+    registry.complete(&receipts).await.map_err(|e| {
+        tracing::error!(error = %e, extrinsic_hash = %receipts[0].extrinsic_hash, "❌ Failed to store proof receipts");
+        ProofError::FailedToStoreProofs
+    })?;
+    Ok(receipts.len())
+}
+
+/// Anchors the queued subjects every `interval`, so many subjects share one extrinsic. Nothing is
+/// submitted while the queue is empty, and a failed batch is tried again at the next tick.
+pub fn spawn_proof_batcher(
+    anchor: Arc<dyn ChainAnchor>,
+    registry: ProofRegistry,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        // the first tick completes immediately
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            match anchor_pending_proofs(anchor.as_ref(), &registry).await {
+                Ok(0) => {},
+                Ok(anchored) => tracing::info!(anchored, "⚓ Anchored batch of proofs"),
+                Err(e) => tracing::warn!(error = %e, queued = registry.pending().len(), "failed to anchor batch of proofs"),
+            }
+        }
+    })
+}
+
+/// Looks up a receipt, or a subject still queued, by ID.
+pub fn get_proof(registry: &ProofRegistry, id: &str) -> Result<ProofStatus, ProofError> {
+    if let Some(receipt) = registry.get(id) {
+        return Ok(ProofStatus::Anchored(receipt));
+    }
+    registry.get_pending(id).map(ProofStatus::Pending).ok_or(ProofError::ReceiptNotFound)
+}
+
+// Takes the digest of every subject and gives each the ID of its future receipt
+async fn prepare_proofs(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    subjects: Vec<ProofSubject>,
+) -> Result<Vec<PendingProof>, ProofError> {
+    if subjects.is_empty() {
+        return Err(ProofError::NoSubjects);
+    }
+    let queued_at = unix_time();
+    let mut batch = Vec::with_capacity(subjects.len());
+    for subject in subjects {
+        let digest = subject_digest(docs.clone(), blobs.clone(), &subject).await?;
+        batch.push(PendingProof {
+            id: uuid::Uuid::new_v4().to_string(),
+            subject,
+            digest: to_hex(&digest),
+            queued_at,
+        });
+    }
+    Ok(batch)
+}
+
+// Anchors the Merkle root of `batch` and builds a receipt for each of its subjects
+async fn anchor_batch(anchor: &dyn ChainAnchor, batch: &[PendingProof]) -> Result<Vec<ProofReceipt>, ProofError> {
+    let leaves = batch
+        .iter()
+        .map(|pending| from_hex(&pending.digest))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(ProofError::FailedToAnchorProof)?;
+    let root = merkle_root(&leaves);
+
+    let receipt = anchor
+        .anchor(root)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, subjects = batch.len(), "❌ Failed to anchor proof");
+            ProofError::FailedToAnchorProof
+        })?;
+    let anchored_at = unix_time();
+
+    Ok(batch
+        .iter()
+        .enumerate()
+        .map(|(index, pending)| ProofReceipt {
+            id: pending.id.clone(),
+            subject: pending.subject.clone(),
+            digest: pending.digest.clone(),
+            merkle_root: to_hex(&root),
+            merkle_path: merkle_path(&leaves, index),
+            block_hash: receipt.block_hash.clone(),
+            block_number: receipt.block_number,
+            extrinsic_hash: receipt.extrinsic_hash.clone(),
+            extrinsic_index: receipt.extrinsic_index,
+            account: receipt.account.clone(),
+            anchored_at,
+        })
+        .collect())
+}
+
+/// Re-checks the receipt `id`: the subject's current digest, the Merkle path and the extrinsic
+/// on chain.
+pub async fn verify_proof(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    id: &str,
+    anchor: Arc<dyn ChainAnchor>,
+    registry: &ProofRegistry,
+) -> Result<ProofVerification, ProofError> {
+    let receipt = match get_proof(registry, id)? {
+        ProofStatus::Anchored(receipt) => receipt,
+        ProofStatus::Pending(_) => return Err(ProofError::ProofPending),
+    };
+
+    let subject_unchanged = match subject_digest(docs, blobs, &receipt.subject).await {
+        Ok(digest) => to_hex(&digest) == receipt.digest,
+        Err(ProofError::BlobNotFound) | Err(ProofError::Doc(DocError::EntryNotFound)) => false,
+        Err(e) => return Err(e),
+    };
+
+    let path_valid = from_hex(&receipt.digest)
+        .and_then(|digest| root_from_path(digest, &receipt.merkle_path))
+        .is_some_and(|root| to_hex(&root) == receipt.merkle_root);
+
+    let remark = anchor
+        .remark_at(&receipt.block_hash, receipt.extrinsic_index)
+        .await
+        .map_err(|e| {
+            tracing::warn!(error = %e, id = %id, "failed to read anchoring extrinsic");
+            ProofError::FailedToReadChain
+        })?;
+    let anchored = remark.is_some_and(|remark| to_hex(&remark) == receipt.merkle_root);
+
+    Ok(ProofVerification {
+        valid: subject_unchanged && path_valid && anchored,
+        receipt,
+        subject_unchanged,
+        path_valid,
+        anchored,
+    })
+}
+
+// Merkle tree over blake3: leaves are hash(0x00 || digest) and nodes hash(0x01 || left || right),
+// so a leaf can never pass for a node. A node without a sibling moves up a level unchanged.
+
+fn leaf_hash(digest: &[u8; 32]) -> [u8; 32] {
+    let mut message = vec![0u8];
+    message.extend_from_slice(digest);
+    *Hash::new(&message).as_bytes()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut message = vec![1u8];
+    message.extend_from_slice(left);
+    message.extend_from_slice(right);
+    *Hash::new(&message).as_bytes()
+}
+
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            [single] => *single,
+            _ => unreachable!("chunks(2) yields one or two nodes"),
+        })
+        .collect()
+}
+
+/// The root of the Merkle tree with `leaves`, which must not be empty.
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut level: Vec<[u8; 32]> = leaves.iter().map(leaf_hash).collect();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// The siblings from leaf `index` up to the root.
+pub fn merkle_path(leaves: &[[u8; 32]], mut index: usize) -> Vec<MerkleStep> {
+    let mut path = Vec::new();
+    let mut level: Vec<[u8; 32]> = leaves.iter().map(leaf_hash).collect();
+    while level.len() > 1 {
+        let sibling = index ^ 1;
+        if let Some(hash) = level.get(sibling) {
+            let side = if sibling < index { MerkleSide::Left } else { MerkleSide::Right };
+            path.push(MerkleStep { hash: to_hex(hash), side });
+        }
+        level = next_level(&level);
+        index /= 2;
+    }
+    path
+}
+
+/// The root reached from `digest` along `path`, or `None` if a step is not a valid hash.
+pub fn root_from_path(digest: [u8; 32], path: &[MerkleStep]) -> Option<[u8; 32]> {
+    let mut hash = leaf_hash(&digest);
+    for step in path {
+        let sibling = from_hex(&step.hash)?;
+        hash = match step.side {
+            MerkleSide::Left => node_hash(&sibling, &hash),
+            MerkleSide::Right => node_hash(&hash, &sibling),
+        };
+    }
+    Some(hash)
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn from_hex(hex_str: &str) -> Option<[u8; 32]> {
+    hex::decode(hex_str.trim_start_matches("0x")).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cord::backend::LocalAnchor;
+
+    #[tokio::test]
+    pub async fn test_merkle_paths() -> anyhow::Result<()> {
+        let leaves: Vec<[u8; 32]> = (0u8..5).map(|i| [i; 32]).collect();
+        let root = super::merkle_root(&leaves);
+
+        for (index, leaf) in leaves.iter().enumerate() {
+            let path = super::merkle_path(&leaves, index);
+            assert_eq!(super::root_from_path(*leaf, &path), Some(root));
+            // a path does not prove another leaf
+            assert_ne!(super::root_from_path([9; 32], &path), Some(root));
+        }
+
+        // a single subject is its own tree
+        assert!(super::merkle_path(&leaves[..1], 0).is_empty());
+        assert_eq!(super::root_from_path(leaves[0], &[]), Some(super::merkle_root(&leaves[..1])));
+
+        Ok(())
+    }
+
+    // Queues a subject with `digest` for the next batch
+    async fn queue(registry: &ProofRegistry, digest: [u8; 32]) -> String {
+        let pending = PendingProof {
+            id: uuid::Uuid::new_v4().to_string(),
+            subject: ProofSubject::Blob { hash: hex::encode(digest) },
+            digest: to_hex(&digest),
+            queued_at: 0,
+        };
+        registry.enqueue(std::slice::from_ref(&pending)).await.unwrap();
+        pending.id
+    }
+
+    // Whether `receipt` leads from its digest to its root, and the root is what was anchored
+    async fn receipt_verifies(anchor: &LocalAnchor, receipt: &ProofReceipt) -> bool {
+        let path_valid = from_hex(&receipt.digest)
+            .and_then(|digest| root_from_path(digest, &receipt.merkle_path))
+            .is_some_and(|root| to_hex(&root) == receipt.merkle_root);
+        let remark = anchor.remark_at(&receipt.block_hash, receipt.extrinsic_index).await.unwrap();
+        path_valid && remark.is_some_and(|remark| to_hex(&remark) == receipt.merkle_root)
+    }
+
+    #[tokio::test]
+    pub async fn test_batch_with_odd_leaf_count() -> anyhow::Result<()> {
+        let anchor = LocalAnchor::new("account".to_string());
+        let registry = ProofRegistry::default();
+        let mut ids = Vec::new();
+        for i in 1u8..=3 {
+            ids.push(queue(&registry, [i; 32]).await);
+        }
+
+        assert_eq!(anchor_pending_proofs(&anchor, &registry).await, Ok(3));
+        assert!(registry.pending().is_empty());
+        assert_eq!(anchor.height().await, 1);
+        // nothing is anchored for an empty queue
+        assert_eq!(anchor_pending_proofs(&anchor, &registry).await, Ok(0));
+        assert_eq!(anchor.height().await, 1);
+
+        let receipts: Vec<ProofReceipt> = ids.iter().map(|id| registry.get(id).unwrap()).collect();
+        for receipt in &receipts {
+            assert_eq!(receipt.merkle_root, receipts[0].merkle_root);
+            assert!(receipt_verifies(&anchor, receipt).await);
+        }
+        // the last leaf has no sibling and moves up a level unchanged
+        assert_eq!(receipts.iter().map(|receipt| receipt.merkle_path.len()).collect::<Vec<_>>(), vec![2, 2, 1]);
+        assert_eq!(receipts[2].merkle_path[0].side, MerkleSide::Left);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_batch_with_single_leaf() -> anyhow::Result<()> {
+        let anchor = LocalAnchor::new("account".to_string());
+        let registry = ProofRegistry::default();
+        let id = queue(&registry, [7; 32]).await;
+
+        assert_eq!(anchor_pending_proofs(&anchor, &registry).await, Ok(1));
+        let receipt = registry.get(&id).unwrap();
+        assert!(receipt.merkle_path.is_empty());
+        // the root is the leaf hash, not the digest itself
+        assert_eq!(receipt.merkle_root, to_hex(&leaf_hash(&[7; 32])));
+        assert!(receipt_verifies(&anchor, &receipt).await);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    pub async fn test_forged_proofs_are_rejected() -> anyhow::Result<()> {
+        let anchor = LocalAnchor::new("account".to_string());
+        let registry = ProofRegistry::default();
+        let mut ids = Vec::new();
+        for i in 1u8..=4 {
+            ids.push(queue(&registry, [i; 32]).await);
+        }
+        anchor_pending_proofs(&anchor, &registry).await.unwrap();
+        let receipt = registry.get(&ids[0]).unwrap();
+        let other = registry.get(&ids[3]).unwrap();
+        assert!(receipt_verifies(&anchor, &receipt).await);
+
+        let mut forgeries = vec![
+            ProofReceipt { digest: to_hex(&[9; 32]), ..receipt.clone() },
+            ProofReceipt { digest: other.digest.clone(), ..receipt.clone() },
+            ProofReceipt { merkle_path: other.merkle_path.clone(), ..receipt.clone() },
+            ProofReceipt { merkle_path: receipt.merkle_path[..1].to_vec(), ..receipt.clone() },
+            ProofReceipt { merkle_root: to_hex(&[0; 32]), ..receipt.clone() },
+            ProofReceipt { block_hash: "0x00".to_string(), ..receipt.clone() },
+        ];
+        let mut flipped = receipt.clone();
+        flipped.merkle_path[0].side = MerkleSide::Left;
+        forgeries.push(flipped);
+        let mut invalid_step = receipt.clone();
+        invalid_step.merkle_path[0].hash = "0xzz".to_string();
+        forgeries.push(invalid_step);
+
+        // an inner node of the tree does not pass for a leaf with the rest of the path
+        let leaves: Vec<[u8; 32]> = (1u8..=4).map(|i| [i; 32]).collect();
+        let inner = node_hash(&leaf_hash(&leaves[0]), &leaf_hash(&leaves[1]));
+        forgeries.push(ProofReceipt { digest: to_hex(&inner), merkle_path: receipt.merkle_path[1..].to_vec(), ..receipt.clone() });
+
+        for forged in &forgeries {
+            assert!(!receipt_verifies(&anchor, forged).await, "{:?}", forged);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use crate::docs::{get_document, list_docs};
+use helpers::replication::{ReplicationTracker, SyncActivity};
+use helpers::stores::{NodeBlobs, NodeDocs};
+use helpers::sync_controls::{SyncBlock, SyncControlRegistry};
+use helpers::utils::decode_doc_id;
+
+use futures::TryStreamExt;
+use iroh_base::PublicKey;
+use iroh_docs::store::Query;
+use iroh_docs::{CapabilityKind, NamespaceId};
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq)]
+pub enum ReplicationError {
+    /// Failed to decode the document ID.
+    InvalidDocumentIdFormat,
+    /// The document was not found.
+    DocumentNotFound,
+    /// Failed to list the documents of the node.
+    FailedToListDocuments,
+    /// Failed to read the sync state or entries of a document.
+    FailedToReadDocument,
+}
+
+impl fmt::Display for ReplicationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for ReplicationError {}
+
+/// How a document is replicated by this node.
+#[derive(Debug, Clone, Serialize)]
+pub struct DocReplication {
+    pub doc_id: String,
+    /// `read` or `write`.
+    pub capability: String,
+    /// Whether the document is syncing with its peers.
+    pub syncing: bool,
+    /// Why sync controls stop the document from syncing, if they do.
+    pub sync_blocked_by: Option<SyncBlock>,
+    /// Peers the document syncs with, as remembered by iroh-docs.
+    pub peers: Vec<String>,
+    /// Latest entries of every author and key.
+    pub entries: usize,
+    /// Entries whose content has not been downloaded yet but will be, by the download policy.
+    pub pending_content: usize,
+    /// Bytes of that content.
+    pub pending_bytes: u64,
+    /// Entries whose content is missing and that the download policy skips.
+    pub skipped_content: usize,
+    #[serde(flatten)]
+    pub activity: SyncActivity,
+}
+
+/// Totals of the documents of a replication report.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReplicationSummary {
+    pub docs: usize,
+    pub syncing: usize,
+    /// Documents that did not sync successfully with any peer since the node started.
+    pub never_synced: usize,
+    /// Documents with content still to download.
+    pub with_pending_content: usize,
+    /// Documents without any known peer.
+    pub without_peers: usize,
+}
+
+/// Reports how each document of the node, or only `doc_id`, is replicated.
+///
+/// Last sync times and neighbors come from `tracker` and only cover the time since the node
+/// started. Pending content is counted over the latest entries, so reports of large documents
+/// take a while.
+pub async fn replication_report(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    tracker: &ReplicationTracker,
+    controls: &SyncControlRegistry,
+    doc_id: Option<String>,
+) -> Result<(ReplicationSummary, Vec<DocReplication>), ReplicationError> {
+    let doc_list = list_docs(docs.clone())
+        .await
+        .map_err(|_| ReplicationError::FailedToListDocuments)?;
+    let doc_list = match doc_id {
+        Some(doc_id) => {
+            decode_doc_id(&doc_id).map_err(|_| ReplicationError::InvalidDocumentIdFormat)?;
+            let doc = doc_list
+                .into_iter()
+                .find(|(id, _)| *id == doc_id)
+                .ok_or(ReplicationError::DocumentNotFound)?;
+            vec![doc]
+        }
+        None => doc_list,
+    };
+
+    let mut summary = ReplicationSummary::default();
+    let mut reports = Vec::with_capacity(doc_list.len());
+    for (doc_id, capability) in doc_list {
+        let report = doc_replication(&docs, &blobs, tracker, controls, doc_id, capability).await?;
+        summary.docs += 1;
+        summary.syncing += usize::from(report.syncing);
+        summary.never_synced += usize::from(report.activity.last_sync_at.is_none());
+        summary.with_pending_content += usize::from(report.pending_content > 0);
+        summary.without_peers += usize::from(report.peers.is_empty() && report.activity.neighbors.is_empty());
+        reports.push(report);
+    }
+
+    Ok((summary, reports))
+}
+
+async fn doc_replication(
+    docs: &Arc<NodeDocs>,
+    blobs: &Arc<NodeBlobs>,
+    tracker: &ReplicationTracker,
+    controls: &SyncControlRegistry,
+    doc_id: String,
+    capability: CapabilityKind,
+) -> Result<DocReplication, ReplicationError> {
+    let namespace_id = decode_doc_id(&doc_id).map_err(|_| ReplicationError::InvalidDocumentIdFormat)?;
+    let doc = get_document(docs.clone(), NamespaceId::from(namespace_id))
+        .await
+        .map_err(|_| ReplicationError::DocumentNotFound)?;
+
+    let status = doc.status().await.map_err(|_| ReplicationError::FailedToReadDocument)?;
+    let peers = doc
+        .get_sync_peers()
+        .await
+        .map_err(|_| ReplicationError::FailedToReadDocument)?
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|peer| PublicKey::from_bytes(&peer).ok())
+        .map(|peer| peer.to_string())
+        .collect();
+    let policy = doc
+        .get_download_policy()
+        .await
+        .map_err(|_| ReplicationError::FailedToReadDocument)?;
+
+    let (mut entries, mut pending_content, mut pending_bytes, mut skipped_content) = (0, 0, 0, 0);
+    let mut stream = doc
+        .get_many(Query::all())
+        .await
+        .map_err(|_| ReplicationError::FailedToReadDocument)?;
+    while let Some(entry) = stream.try_next().await.map_err(|_| ReplicationError::FailedToReadDocument)? {
+        entries += 1;
+        if blobs.client().has(entry.content_hash()).await.unwrap_or(false) {
+            continue;
+        }
+        if policy.matches(&entry) {
+            pending_content += 1;
+            pending_bytes += entry.content_len();
+        } else {
+            skipped_content += 1;
+        }
+    }
+
+    Ok(DocReplication {
+        capability: match capability {
+            CapabilityKind::Write => "write".to_string(),
+            CapabilityKind::Read => "read".to_string(),
+        },
+        syncing: status.sync,
+        sync_blocked_by: controls.blocked_by(&doc_id),
+        peers,
+        entries,
+        pending_content,
+        pending_bytes,
+        skipped_content,
+        activity: tracker.get(&doc_id),
+        doc_id,
+    })
+}
@@ -0,0 +1,305 @@
+use crate::docs::{get_entries, resolve_entries, set_entry, ConflictResolution, DocError};
+use helpers::errors::Cause;
+use helpers::stores::{NodeBlobs, NodeDocs};
+
+use iroh_blobs::Hash;
+use jsonschema::{Retrieve, Uri, Validator};
+use serde::Serialize;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Scheme of the refs to entries of the schema registry document, as in `registry:address`.
+pub const REGISTRY_SCHEME: &str = "registry";
+
+/// Separates the name of a registry schema from its version, as in `address@1.2.0`.
+pub const VERSION_SEPARATOR: char = '@';
+
+// Caps on the schemas a schema pulls in through its refs, and on each one fetched from a URL
+const MAX_REFERENCED_SCHEMAS: usize = 64;
+const MAX_REMOTE_SCHEMA: usize = 1024 * 1024;
+const REMOTE_SCHEMA_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Builds the validator of `schema`, with the schemas its `$ref`s point to, see `SchemaRefs`.
+///
+/// The referenced schemas are read before the validator is built, and their own refs in turn,
+/// so building never blocks on the network.
+pub async fn build_validator(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    schema: &Value,
+) -> anyhow::Result<Validator, DocError> {
+    let mut resolved: HashMap<String, Value> = HashMap::new();
+    loop {
+        let retriever = Resolved { schemas: Arc::new(resolved.clone()), missing: Arc::default() };
+        let error = match jsonschema::options().with_retriever(retriever.clone()).build(schema) {
+            Ok(validator) => return Ok(validator),
+            Err(e) => e,
+        };
+
+        // the build stops at the first ref it cannot resolve, so they are read one round at a time
+        let missing = std::mem::take(&mut *retriever.missing.lock().unwrap());
+        if missing.is_empty() {
+            return Err(DocError::FailedToCreateSchemaValidator(Cause::new(error.to_string())));
+        }
+        for uri in missing {
+            if resolved.len() >= MAX_REFERENCED_SCHEMAS {
+                return Err(DocError::FailedToResolveSchemaRef(Cause::new(format!(
+                    "a schema cannot reference more than {} schemas",
+                    MAX_REFERENCED_SCHEMAS
+                ))));
+            }
+            let referenced = resolve_ref(docs.clone(), blobs.clone(), &uri).await?;
+            resolved.insert(uri, referenced);
+        }
+    }
+}
+
+// Serves the schemas read so far to the validator, and records the refs it asked for that are not
+#[derive(Clone)]
+struct Resolved {
+    schemas: Arc<HashMap<String, Value>>,
+    missing: Arc<Mutex<Vec<String>>>,
+}
+
+impl Retrieve for Resolved {
+    fn retrieve(&self, uri: &Uri<String>) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        match self.schemas.get(uri.as_str()) {
+            Some(schema) => Ok(schema.clone()),
+            None => {
+                self.missing.lock().unwrap().push(uri.as_str().to_string());
+                Err(format!("{} is not resolved yet", uri.as_str()).into())
+            }
+        }
+    }
+}
+
+// Reads the schema `uri` points to
+pub(crate) async fn resolve_ref(docs: Arc<NodeDocs>, blobs: Arc<NodeBlobs>, uri: &str) -> anyhow::Result<Value, DocError> {
+    let refs = docs.schema_refs.clone();
+    let unresolvable = |reason: String| DocError::FailedToResolveSchemaRef(Cause::new(format!("{}: {}", uri, reason)));
+
+    let (scheme, rest) = uri.split_once(':').unwrap_or(("", uri));
+    match scheme {
+        REGISTRY_SCHEME => {
+            let registry_doc = refs.registry_doc()
+                .ok_or_else(|| unresolvable("no schema registry document is set, see --schema-registry-doc".to_string()))?;
+            registry_schema(docs, blobs, registry_doc, rest).await?
+                .ok_or_else(|| unresolvable("not in the schema registry document".to_string()))
+        }
+        "http" | "https" => {
+            if !refs.is_url_allowed(uri) {
+                return Err(DocError::SchemaRefNotAllowed);
+            }
+            if let Some(schema) = refs.cached(uri) {
+                return Ok(schema);
+            }
+            let schema = fetch_schema(uri).await.map_err(|e| unresolvable(e.to_string()))?;
+            refs.cache(uri, schema.clone());
+            Ok(schema)
+        }
+        _ => Err(unresolvable("only registry:, http and https refs are resolved".to_string())),
+    }
+}
+
+/// The schema stored under `key` in the registry document `registry_doc`, whichever author wrote
+/// it last.
+///
+/// A `key` without a version, as in `address`, names the highest version of the schema if there
+/// is no entry `address` itself.
+pub async fn registry_schema(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    registry_doc: &str,
+    key: &str,
+) -> anyhow::Result<Option<Value>, DocError> {
+    let versions = get_entries(docs.clone(), registry_doc.to_string(), serde_json::json!({ "key": key })).await?;
+    let entry = match ConflictResolution::LastWriterWins.resolve(key, &versions) {
+        Some(entry) => entry,
+        None if !key.contains(VERSION_SEPARATOR) => {
+            let prefix = format!("{}{}", key, VERSION_SEPARATOR);
+            let entries = get_entries(docs, registry_doc.to_string(), serde_json::json!({ "key_prefix": prefix })).await?;
+            let latest = resolve_entries(entries, &ConflictResolution::LastWriterWins)
+                .into_iter()
+                .filter_map(|entry| Some((split_version(&entry.namespace.key)?.1.to_string(), entry)))
+                .max_by(|(a, _), (b, _)| compare_versions(a, b));
+            match latest {
+                Some((_, entry)) => entry,
+                None => return Ok(None),
+            }
+        }
+        None => return Ok(None),
+    };
+    let hash = Hash::from_str(&entry.record.hash).map_err(|e| DocError::FailedToReadBlob(Cause::new(e)))?;
+    let content = crate::blobs::read_blob_cached(&blobs, hash)
+        .await
+        .map_err(|e| DocError::FailedToReadBlob(Cause::new(e)))?;
+    let schema = serde_json::from_slice(&content).map_err(|e| DocError::FailedToParseSchemaJson(Cause::new(e)))?;
+    Ok(Some(schema))
+}
+
+/// A schema of the registry document, with all of its versions.
+#[derive(Debug, Clone, Serialize)]
+pub struct RegistrySchema {
+    pub name: String,
+    /// Lowest first, see `compare_versions`.
+    pub versions: Vec<String>,
+}
+
+/// Stores `schema` as version `version` of `name` in the schema registry document, see
+/// `--schema-registry-doc`.
+///
+/// A version cannot be changed once published, so documents referencing it as
+/// `registry:name@version` keep validating against the same schema. Publish a new version
+/// instead.
+pub async fn publish_schema(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    author_id: String,
+    name: String,
+    version: String,
+    schema: String,
+) -> anyhow::Result<String, DocError> {
+    let registry_doc = schema_registry_doc(&docs)?;
+    check_schema_name(&name)?;
+    check_schema_name(&version)?;
+
+    let schema_json: Value = serde_json::from_str(&schema)
+        .map_err(|e| DocError::FailedToSerializeSchema(Cause::new(e)))?;
+    build_validator(docs.clone(), blobs.clone(), &schema_json)
+        .await
+        .map_err(|e| match e {
+            DocError::FailedToCreateSchemaValidator(cause) => DocError::FailedToValidateSchema(cause),
+            e => e,
+        })?;
+
+    let key = format!("{}{}{}", name, VERSION_SEPARATOR, version);
+    let existing = get_entries(docs.clone(), registry_doc.clone(), serde_json::json!({ "key": key })).await?;
+    if !existing.is_empty() {
+        return Err(DocError::SchemaVersionExists);
+    }
+
+    set_entry(docs, blobs, registry_doc, author_id, key, schema).await
+}
+
+/// The schemas of the schema registry document, by name.
+pub async fn list_registry_schemas(docs: Arc<NodeDocs>) -> anyhow::Result<Vec<RegistrySchema>, DocError> {
+    let registry_doc = schema_registry_doc(&docs)?;
+    let entries = get_entries(docs, registry_doc, serde_json::json!({})).await?;
+
+    let mut schemas: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for entry in resolve_entries(entries, &ConflictResolution::LastWriterWins) {
+        if let Some((name, version)) = split_version(&entry.namespace.key) {
+            schemas.entry(name.to_string()).or_default().push(version.to_string());
+        }
+    }
+    Ok(schemas
+        .into_iter()
+        .map(|(name, mut versions)| {
+            versions.sort_by(|a, b| compare_versions(a, b));
+            RegistrySchema { name, versions }
+        })
+        .collect())
+}
+
+/// Version `version` of the registry schema `name`, or its highest version without one.
+pub async fn get_registry_schema(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    name: &str,
+    version: Option<&str>,
+) -> anyhow::Result<Value, DocError> {
+    let registry_doc = schema_registry_doc(&docs)?;
+    check_schema_name(name)?;
+    let key = match version {
+        Some(version) => {
+            check_schema_name(version)?;
+            format!("{}{}{}", name, VERSION_SEPARATOR, version)
+        }
+        None => name.to_string(),
+    };
+    registry_schema(docs, blobs, &registry_doc, &key).await?.ok_or(DocError::SchemaNotFound)
+}
+
+/// Orders versions by their dot-separated parts, numerically where both parts are numbers, so
+/// `1.10` comes after `1.9`.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        let ordering = match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+// The name and version of a registry key, as in `address@1.2.0`
+fn split_version(key: &str) -> Option<(&str, &str)> {
+    key.split_once(VERSION_SEPARATOR).filter(|(name, version)| !name.is_empty() && !version.is_empty())
+}
+
+fn check_schema_name(name: &str) -> anyhow::Result<(), DocError> {
+    if name.is_empty() || name.contains(VERSION_SEPARATOR) || name.chars().any(char::is_whitespace) {
+        return Err(DocError::InvalidSchemaName);
+    }
+    Ok(())
+}
+
+fn schema_registry_doc(docs: &NodeDocs) -> anyhow::Result<String, DocError> {
+    docs.schema_refs
+        .registry_doc()
+        .map(str::to_string)
+        .ok_or(DocError::SchemaRegistryNotSet)
+}
+
+async fn fetch_schema(url: &str) -> anyhow::Result<Value> {
+    let response = reqwest::Client::new()
+        .get(url)
+        .timeout(REMOTE_SCHEMA_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+    if response.content_length().is_some_and(|len| len > MAX_REMOTE_SCHEMA as u64) {
+        anyhow::bail!("the schema is larger than {} bytes", MAX_REMOTE_SCHEMA);
+    }
+    let body = response.bytes().await?;
+    if body.len() > MAX_REMOTE_SCHEMA {
+        anyhow::bail!("the schema is larger than {} bytes", MAX_REMOTE_SCHEMA);
+    }
+    Ok(serde_json::from_slice(&body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_numerically_by_part() {
+        assert_eq!(compare_versions("1.10.0", "1.9.0"), Ordering::Greater);
+        assert_eq!(compare_versions("2", "2.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0-beta", "1.0-alpha"), Ordering::Greater);
+    }
+
+    #[test]
+    fn registry_keys_split_into_name_and_version() {
+        assert_eq!(split_version("person@1.0.0"), Some(("person", "1.0.0")));
+        assert_eq!(split_version("person"), None);
+        assert_eq!(split_version("@1.0.0"), None);
+        assert!(check_schema_name("person").is_ok());
+        assert!(check_schema_name("per son").is_err());
+        assert!(check_schema_name("person@1").is_err());
+    }
+}
@@ -0,0 +1,347 @@
+use crate::docs::{
+    create_doc, delete_entry, drop_doc, get_document, get_entries, get_entry, set_entry, share_doc_bundle,
+    DocError, EntryDetails,
+};
+use helpers::utils::{decode_doc_id, encode_key, SS58AuthorId, SHARD_MANIFEST_KEY};
+use helpers::stores::{NodeBlobs, NodeDocs};
+
+use futures::future::try_join_all;
+use futures::TryStreamExt;
+use iroh_blobs::Hash;
+use iroh_docs::rpc::client::docs::{Doc, ShareMode};
+use iroh_docs::rpc::proto::{Request, Response};
+use iroh_docs::rpc::AddrInfoOptions;
+use iroh_docs::store::Query;
+use iroh_docs::NamespaceId;
+use quic_rpc::transport::flume::FlumeConnector;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+
+// Version of the shard manifest, checked when reading it
+const SHARD_MANIFEST_VERSION: u32 = 1;
+
+/// Most shards a sharded document may have.
+pub const MAX_SHARDS: usize = 256;
+
+#[derive(Debug, PartialEq)]
+pub enum ShardingError {
+    /// The shard count is zero or above `MAX_SHARDS`.
+    InvalidShardCount,
+    /// Failed to decode the document ID.
+    InvalidDocumentIdFormat,
+    /// Failed to decode the author ID.
+    InvalidAuthorIdFormat,
+    /// The document was not found.
+    DocumentNotFound,
+    /// The document has no shard manifest.
+    NotShardedDocument,
+    /// The content of the shard manifest is not on this node yet.
+    ManifestNotDownloaded,
+    /// The shard manifest could not be parsed.
+    InvalidShardManifest,
+    /// Failed to read the shard manifest.
+    FailedToReadManifest,
+    /// Failed to write the shard manifest.
+    FailedToWriteManifest,
+    /// A document operation on a shard failed.
+    Doc(DocError),
+}
+
+impl fmt::Display for ShardingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShardingError::Doc(e) => write!(f, "{}", e),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl std::error::Error for ShardingError {}
+
+/// The value of the shard manifest of a sharded document: the documents its entries are spread
+/// across. The shard of a key is `shard_index(key, shards.len())`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardManifest {
+    pub version: u32,
+    pub shards: Vec<String>,
+}
+
+/// A sharded document, as read from the manifest in its root document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShardedDoc {
+    /// ID of the root document, which stands for the sharded document in the API.
+    pub doc_id: String,
+    /// SS58 author of the manifest.
+    pub created_by: String,
+    pub shards: Vec<String>,
+}
+
+impl ShardedDoc {
+    /// The shard `key` is stored in.
+    pub fn shard_for(&self, key: &str) -> &str {
+        &self.shards[shard_index(key, self.shards.len())]
+    }
+}
+
+/// Index of the shard, out of `shard_count`, that `key` is stored in: the first 8 bytes of the
+/// blake3 hash of the key, little-endian, modulo the count.
+pub fn shard_index(key: &str, shard_count: usize) -> usize {
+    let hash = Hash::new(key.as_bytes());
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&hash.as_bytes()[..8]);
+    (u64::from_le_bytes(prefix) % shard_count as u64) as usize
+}
+
+/// Creates a sharded document: a root document and `shard_count` shards, with the manifest
+/// listing the shards written to the root by `author_id`.
+///
+/// The root document only holds the manifest, and its ID is used for the sharded document in the
+/// API. The shard count cannot be changed later, since it decides which shard holds each key.
+pub async fn create_sharded_doc(
+    docs: Arc<NodeDocs>,
+    author_id: String,
+    shard_count: usize,
+) -> Result<ShardedDoc, ShardingError> {
+    if shard_count == 0 || shard_count > MAX_SHARDS {
+        return Err(ShardingError::InvalidShardCount);
+    }
+    let author = SS58AuthorId::decode(&author_id).map_err(|_| ShardingError::InvalidAuthorIdFormat)?;
+
+    let doc_id = create_doc(docs.clone()).await.map_err(ShardingError::Doc)?;
+    let mut shards = Vec::with_capacity(shard_count);
+    for _ in 0..shard_count {
+        shards.push(create_doc(docs.clone()).await.map_err(ShardingError::Doc)?);
+    }
+
+    let manifest = ShardManifest { version: SHARD_MANIFEST_VERSION, shards };
+    let value = serde_json::to_vec(&manifest).map_err(|_| ShardingError::FailedToWriteManifest)?;
+    let root = open_doc(&docs, &doc_id).await?;
+    root.set_bytes(author, encode_key(SHARD_MANIFEST_KEY.as_bytes()), value)
+        .await
+        .map_err(|_| ShardingError::FailedToWriteManifest)?;
+
+    Ok(ShardedDoc { doc_id, created_by: author_id, shards: manifest.shards })
+}
+
+/// Reads the shard manifest of the sharded document `doc_id`.
+///
+/// If several authors wrote a manifest, the oldest one is used, so a writer of the root document
+/// cannot move keys to other shards later.
+pub async fn get_sharded_doc(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: &str,
+) -> Result<ShardedDoc, ShardingError> {
+    let root = open_doc(&docs, doc_id).await?;
+    let entries: Vec<_> = root
+        .get_many(Query::key_exact(encode_key(SHARD_MANIFEST_KEY.as_bytes())))
+        .await
+        .map_err(|_| ShardingError::FailedToReadManifest)?
+        .try_collect()
+        .await
+        .map_err(|_| ShardingError::FailedToReadManifest)?;
+    let entry = entries
+        .into_iter()
+        .min_by_key(|entry| (entry.timestamp(), entry.content_hash()))
+        .ok_or(ShardingError::NotShardedDocument)?;
+
+    let content = blobs
+        .client()
+        .read_to_bytes(entry.content_hash())
+        .await
+        .map_err(|_| ShardingError::ManifestNotDownloaded)?;
+    let manifest: ShardManifest = serde_json::from_slice(&content).map_err(|_| ShardingError::InvalidShardManifest)?;
+    if manifest.version != SHARD_MANIFEST_VERSION
+        || manifest.shards.is_empty()
+        || manifest.shards.len() > MAX_SHARDS
+        || manifest.shards.iter().any(|shard| decode_doc_id(shard).is_err())
+    {
+        return Err(ShardingError::InvalidShardManifest);
+    }
+    let created_by = SS58AuthorId::from_author_id(&entry.author())
+        .map_err(|_| ShardingError::InvalidShardManifest)?
+        .as_ss58()
+        .to_string();
+
+    Ok(ShardedDoc { doc_id: doc_id.to_string(), created_by, shards: manifest.shards })
+}
+
+/// Adds an entry to the shard of `key`, like `set_entry`, and validated against the schema of
+/// that shard if it has one.
+///
+/// # Returns
+/// The shard the entry was written to and its content hash.
+pub async fn set_sharded_entry(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: &str,
+    author_id: String,
+    key: String,
+    value: String,
+) -> Result<(String, String), ShardingError> {
+    let sharded = get_sharded_doc(docs.clone(), blobs.clone(), doc_id).await?;
+    let shard = sharded.shard_for(&key).to_string();
+    let hash = set_entry(docs, blobs, shard.clone(), author_id, key, value)
+        .await
+        .map_err(ShardingError::Doc)?;
+    Ok((shard, hash))
+}
+
+/// Reads an entry of `author_id` from the shard of `key`, like `get_entry`.
+///
+/// # Returns
+/// The shard of the key and the entry, if any.
+pub async fn get_sharded_entry(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: &str,
+    author_id: String,
+    key: String,
+    include_empty: bool,
+) -> Result<(String, Option<EntryDetails>), ShardingError> {
+    let sharded = get_sharded_doc(docs.clone(), blobs, doc_id).await?;
+    let shard = sharded.shard_for(&key).to_string();
+    let entry = get_entry(docs, shard.clone(), author_id, key, include_empty)
+        .await
+        .map_err(ShardingError::Doc)?;
+    Ok((shard, entry))
+}
+
+/// Deletes an entry of `author_id` from the shard of `key`, like `delete_entry`.
+///
+/// # Returns
+/// The shard of the key and the number of deleted entries.
+pub async fn delete_sharded_entry(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: &str,
+    author_id: String,
+    key: String,
+) -> Result<(String, usize), ShardingError> {
+    let sharded = get_sharded_doc(docs.clone(), blobs, doc_id).await?;
+    let shard = sharded.shard_for(&key).to_string();
+    let deleted = delete_entry(docs, shard.clone(), author_id, key)
+        .await
+        .map_err(ShardingError::Doc)?;
+    Ok((shard, deleted))
+}
+
+/// Queries the entries of a sharded document with the parameters of `get_entries`.
+///
+/// A query for a `key` only reads its shard. Any other query reads every shard concurrently and
+/// merges the results in key order, then author order within a key, in `sort_direction`;
+/// `sort_by` is ignored. With a `limit`, each shard returns at most `offset + limit` entries, so
+/// deep pages cost more than shallow ones.
+pub async fn get_sharded_entries(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: &str,
+    query_params: Value,
+) -> Result<Vec<EntryDetails>, ShardingError> {
+    let sharded = get_sharded_doc(docs.clone(), blobs, doc_id).await?;
+
+    if let Some(key) = query_params.get("key").and_then(|v| v.as_str()) {
+        let shard = sharded.shard_for(key).to_string();
+        return get_entries(docs, shard, query_params).await.map_err(ShardingError::Doc);
+    }
+
+    let descending = match query_params.get("sort_direction").and_then(|v| v.as_str()) {
+        Some(direction) => match direction.to_lowercase().as_str() {
+            "ascending" => false,
+            "descending" => true,
+            _ => return Err(ShardingError::Doc(DocError::InvalidSortDirectionValue)),
+        },
+        None => false,
+    };
+    let offset = query_params.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+    let limit = query_params.get("limit").and_then(|v| v.as_u64()).map(|limit| limit as usize);
+
+    // Each shard sorted by key then author, as the merge below expects
+    let mut shard_params = query_params.clone();
+    if let Some(params) = shard_params.as_object_mut() {
+        params.remove("offset");
+        params.insert("sort_by".to_string(), Value::from("author"));
+        match limit {
+            Some(limit) => params.insert("limit".to_string(), Value::from((offset + limit) as u64)),
+            None => params.remove("limit"),
+        };
+    }
+
+    let results = try_join_all(
+        sharded.shards.iter().map(|shard| get_entries(docs.clone(), shard.clone(), shard_params.clone())),
+    )
+    .await
+    .map_err(ShardingError::Doc)?;
+
+    // A key only lives in one shard, so a stable sort by key keeps each shard's author order
+    let mut entries: Vec<EntryDetails> = results.into_iter().flatten().collect();
+    entries.sort_by(|a, b| {
+        let order = a.namespace.key.cmp(&b.namespace.key);
+        if descending { order.reverse() } else { order }
+    });
+
+    let entries = entries.into_iter().skip(offset);
+    Ok(match limit {
+        Some(limit) => entries.take(limit).collect(),
+        None => entries.collect(),
+    })
+}
+
+/// Shares a sharded document as a bundle ticket listing its root and every shard, signed by
+/// `author_id`. It is joined with `join_doc_bundle`.
+pub async fn share_sharded_doc(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: &str,
+    mode: ShareMode,
+    addr_options: AddrInfoOptions,
+    author_id: String,
+    label: Option<String>,
+) -> Result<String, ShardingError> {
+    let sharded = get_sharded_doc(docs.clone(), blobs, doc_id).await?;
+    let mut doc_ids = vec![sharded.doc_id];
+    doc_ids.extend(sharded.shards);
+    share_doc_bundle(docs, doc_ids, mode, addr_options, author_id, label)
+        .await
+        .map_err(ShardingError::Doc)
+}
+
+/// Drops every shard of a sharded document, then its root.
+pub async fn drop_sharded_doc(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: &str,
+) -> Result<ShardedDoc, ShardingError> {
+    let sharded = get_sharded_doc(docs.clone(), blobs, doc_id).await?;
+    for shard in &sharded.shards {
+        drop_doc(docs.clone(), shard.clone()).await.map_err(ShardingError::Doc)?;
+    }
+    drop_doc(docs, sharded.doc_id.clone()).await.map_err(ShardingError::Doc)?;
+    Ok(sharded)
+}
+
+// Opens `doc_id`
+async fn open_doc(docs: &Arc<NodeDocs>, doc_id: &str) -> Result<Doc<FlumeConnector<Response, Request>>, ShardingError> {
+    let namespace_id = decode_doc_id(doc_id).map_err(|_| ShardingError::InvalidDocumentIdFormat)?;
+    get_document(docs.clone(), NamespaceId::from(namespace_id))
+        .await
+        .map_err(|_| ShardingError::DocumentNotFound)
+}
+
+mod tests {
+    #[tokio::test]
+    pub async fn test_shard_index() -> anyhow::Result<()> {
+        let mut counts = [0usize; 8];
+        for i in 0..8000 {
+            let index = super::shard_index(&format!("key-{}", i), counts.len());
+            counts[index] += 1;
+        }
+        // keys spread evenly, and a key always routes to the same shard
+        assert!(counts.iter().all(|count| (800..1200).contains(count)));
+        assert_eq!(super::shard_index("key-1", 8), super::shard_index("key-1", 8));
+        assert_eq!(super::shard_index("anything", 1), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use crate::docs::{get_document, list_docs};
+use helpers::utils::decode_doc_id;
+use helpers::stores::{NodeBlobs, NodeDocs};
+
+use futures::TryStreamExt;
+use iroh_blobs::Hash;
+use iroh_docs::store::Query;
+use iroh_docs::NamespaceId;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq)]
+pub enum StorageError {
+    /// Failed to list the documents of the node.
+    FailedToListDocuments,
+    /// Failed to read the entries of a document.
+    FailedToReadDocument,
+    /// Failed to list the blobs of the store.
+    FailedToListBlobs,
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+/// Totals of a storage report.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StorageSummary {
+    pub docs: usize,
+    /// Entries with content, the latest of every author and key, trash and history included.
+    pub entries: usize,
+    /// Bytes the entries point to, counting content once per entry.
+    pub logical_bytes: u64,
+    /// Distinct blobs the entries point to.
+    pub referenced_blobs: usize,
+    /// Bytes of those blobs, counting each once.
+    pub referenced_bytes: u64,
+    /// `logical_bytes` that are not stored twice as entries share content.
+    pub deduplicated_bytes: u64,
+    /// `logical_bytes / referenced_bytes`, 1 when no content is shared.
+    pub deduplication_ratio: f64,
+    /// Complete blobs in the store.
+    pub stored_blobs: usize,
+    /// Bytes of those blobs. The store's outboards and database come on top.
+    pub stored_bytes: u64,
+    /// Blobs entries point to that are not complete in the store, such as content still to sync.
+    pub missing_blobs: usize,
+    pub missing_bytes: u64,
+    /// Complete blobs no entry points to, kept by tags, collections or not collected yet.
+    pub unreferenced_blobs: usize,
+    pub unreferenced_bytes: u64,
+    /// Blobs partly downloaded.
+    pub incomplete_blobs: usize,
+    /// Bytes of those blobs received so far.
+    pub incomplete_bytes: u64,
+}
+
+/// How much of the store one document accounts for.
+#[derive(Debug, Clone, Serialize)]
+pub struct DocStorage {
+    pub doc_id: String,
+    /// Entries with content.
+    pub entries: usize,
+    /// Bytes its entries point to, counting content once per entry.
+    pub logical_bytes: u64,
+    /// Bytes of the distinct blobs its entries point to.
+    pub unique_bytes: u64,
+    /// Of `unique_bytes`, those no other document points to, freed if it is dropped.
+    pub exclusive_bytes: u64,
+    /// Of `unique_bytes`, those other documents point to as well.
+    pub shared_bytes: u64,
+}
+
+/// A blob that entries point to, by how often they do.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlobReferences {
+    pub hash: String,
+    pub size: u64,
+    /// Entries pointing to it.
+    pub references: usize,
+    /// Documents with entries pointing to it.
+    pub docs: usize,
+    /// Whether it is complete in the store.
+    pub stored: bool,
+}
+
+/// The report of `storage_report`.
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageReport {
+    pub summary: StorageSummary,
+    /// The most referenced blobs, most references first.
+    pub top_blobs: Vec<BlobReferences>,
+    /// Every document, most unique bytes first.
+    pub docs: Vec<DocStorage>,
+}
+
+// What the report knows of one referenced blob
+struct BlobUsage {
+    size: u64,
+    references: usize,
+    docs: usize,
+    // index of the last document that pointed to it, the only one when `docs` is 1
+    last_doc: usize,
+}
+
+/// Reports how the blob store is used by the documents of the node: the bytes their entries
+/// point to against those stored, the blobs most shared, and what each document accounts for.
+///
+/// Every entry of every document and every blob is read, so reports of large nodes take a while.
+/// `top` caps the number of blobs in `top_blobs`.
+pub async fn storage_report(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    top: usize,
+) -> Result<StorageReport, StorageError> {
+    let doc_list = list_docs(docs.clone())
+        .await
+        .map_err(|_| StorageError::FailedToListDocuments)?;
+
+    let mut summary = StorageSummary { docs: doc_list.len(), ..Default::default() };
+    let mut usage: HashMap<Hash, BlobUsage> = HashMap::new();
+    let mut doc_reports = Vec::with_capacity(doc_list.len());
+    for (index, (doc_id, _)) in doc_list.into_iter().enumerate() {
+        let namespace_id = decode_doc_id(&doc_id).map_err(|_| StorageError::FailedToReadDocument)?;
+        let doc = get_document(docs.clone(), NamespaceId::from(namespace_id))
+            .await
+            .map_err(|_| StorageError::FailedToReadDocument)?;
+
+        let mut report = DocStorage { doc_id, entries: 0, logical_bytes: 0, unique_bytes: 0, exclusive_bytes: 0, shared_bytes: 0 };
+        let mut seen: HashSet<Hash> = HashSet::new();
+        let mut stream = doc
+            .get_many(Query::all())
+            .await
+            .map_err(|_| StorageError::FailedToReadDocument)?;
+        while let Some(entry) = stream.try_next().await.map_err(|_| StorageError::FailedToReadDocument)? {
+            // deleted entries point to the empty blob
+            if entry.content_len() == 0 {
+                continue;
+            }
+            let (hash, size) = (entry.content_hash(), entry.content_len());
+            report.entries += 1;
+            report.logical_bytes += size;
+            if seen.insert(hash) {
+                report.unique_bytes += size;
+            }
+
+            let blob = usage.entry(hash).or_insert(BlobUsage { size, references: 0, docs: 1, last_doc: index });
+            blob.references += 1;
+            if blob.last_doc != index {
+                blob.docs += 1;
+                blob.last_doc = index;
+            }
+        }
+
+        summary.entries += report.entries;
+        summary.logical_bytes += report.logical_bytes;
+        doc_reports.push(report);
+    }
+
+    let mut stored: HashMap<Hash, u64> = HashMap::new();
+    let mut listing = blobs.client().list().await.map_err(|_| StorageError::FailedToListBlobs)?;
+    while let Some(blob) = listing.try_next().await.map_err(|_| StorageError::FailedToListBlobs)? {
+        stored.insert(blob.hash, blob.size);
+    }
+    let mut incomplete = blobs.client().list_incomplete().await.map_err(|_| StorageError::FailedToListBlobs)?;
+    while let Some(blob) = incomplete.try_next().await.map_err(|_| StorageError::FailedToListBlobs)? {
+        summary.incomplete_blobs += 1;
+        summary.incomplete_bytes += blob.size;
+    }
+
+    summary.stored_blobs = stored.len();
+    summary.stored_bytes = stored.values().sum();
+    for (hash, blob) in &usage {
+        summary.referenced_blobs += 1;
+        summary.referenced_bytes += blob.size;
+        if !stored.contains_key(hash) {
+            summary.missing_blobs += 1;
+            summary.missing_bytes += blob.size;
+        }
+        if blob.docs == 1 {
+            doc_reports[blob.last_doc].exclusive_bytes += blob.size;
+        }
+    }
+    for (hash, size) in &stored {
+        if !usage.contains_key(hash) {
+            summary.unreferenced_blobs += 1;
+            summary.unreferenced_bytes += size;
+        }
+    }
+    summary.deduplicated_bytes = summary.logical_bytes - summary.referenced_bytes;
+    summary.deduplication_ratio = if summary.referenced_bytes == 0 {
+        1.0
+    } else {
+        summary.logical_bytes as f64 / summary.referenced_bytes as f64
+    };
+
+    for report in &mut doc_reports {
+        report.shared_bytes = report.unique_bytes - report.exclusive_bytes;
+    }
+    doc_reports.sort_by(|a, b| b.unique_bytes.cmp(&a.unique_bytes).then_with(|| a.doc_id.cmp(&b.doc_id)));
+
+    let mut top_blobs: Vec<BlobReferences> = usage
+        .into_iter()
+        .map(|(hash, blob)| BlobReferences {
+            hash: hash.to_string(),
+            size: blob.size,
+            references: blob.references,
+            docs: blob.docs,
+            stored: stored.contains_key(&hash),
+        })
+        .collect();
+    top_blobs.sort_by(|a, b| {
+        b.references.cmp(&a.references).then_with(|| b.size.cmp(&a.size)).then_with(|| a.hash.cmp(&b.hash))
+    });
+    top_blobs.truncate(top);
+
+    Ok(StorageReport { summary, top_blobs, docs: doc_reports })
+}
@@ -0,0 +1,701 @@
+use crate::docs::{filter_entries, get_entries, get_resolved_entry, resolve_entries, ConflictResolution, DocError, EntryDetails};
+use crate::filter::Filter;
+use crate::schemas::resolve_ref;
+use helpers::stores::{NodeBlobs, NodeDocs};
+use helpers::utils::{
+    is_attachment_key, is_history_key, is_trash_key, ANCHOR_KEY_PREFIX, CONTENT_POLICY_KEY, CORD_KEY_PREFIX,
+    REVOCATION_KEY_PREFIX, SHARD_MANIFEST_KEY, SIGNATURE_KEY_PREFIX,
+};
+
+use chrono::{DateTime, SecondsFormat};
+use iroh_blobs::Hash;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Columns every table starts with, before the fields of the entries. A field of the same name
+/// is left out.
+pub const ENTRY_COLUMNS: [&str; 4] = ["_key", "_author", "_timestamp", "_hash"];
+
+// The key a document schema is stored under, see `add_doc_schema`
+const SCHEMA_KEY: &str = "schema";
+
+// Parquet physical types, converted types and encodings, see parquet.thrift
+const PARQUET_MAGIC: &[u8] = b"PAR1";
+const BOOLEAN: i32 = 0;
+const INT64: i32 = 2;
+const DOUBLE: i32 = 5;
+const BYTE_ARRAY: i32 = 6;
+const UTF8: i32 = 0;
+const TIMESTAMP_MICROS: i32 = 10;
+const OPTIONAL: i32 = 1;
+const PLAIN: i32 = 0;
+const RLE: i32 = 3;
+const DATA_PAGE: i32 = 0;
+const UNCOMPRESSED: i32 = 0;
+
+#[derive(Debug, PartialEq)]
+pub enum TableError {
+    /// Failed to decode the document ID.
+    InvalidDocumentIdFormat,
+    /// The specified document was not found.
+    DocumentNotFound,
+    /// The format is not one of `csv` or `parquet`.
+    UnsupportedFormat,
+    /// Failed to read the entries of the document.
+    FailedToReadDocument,
+    /// Failed to read the schema of the document, or a schema it references.
+    FailedToReadSchema,
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for TableError {}
+
+impl From<DocError> for TableError {
+    fn from(e: DocError) -> Self {
+        match e {
+            DocError::InvalidDocumentIdFormat => TableError::InvalidDocumentIdFormat,
+            DocError::DocumentNotFound => TableError::DocumentNotFound,
+            _ => TableError::FailedToReadDocument,
+        }
+    }
+}
+
+/// The file formats a table is written in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TableFormat {
+    /// RFC 4180 CSV with a header row.
+    Csv,
+    /// Apache Parquet, one row group, uncompressed.
+    Parquet,
+}
+
+impl TableFormat {
+    pub fn from_name(name: &str) -> Result<Self, TableError> {
+        match name {
+            "csv" => Ok(TableFormat::Csv),
+            "parquet" => Ok(TableFormat::Parquet),
+            _ => Err(TableError::UnsupportedFormat),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TableFormat::Csv => "text/csv; charset=utf-8",
+            TableFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TableFormat::Csv => "csv",
+            TableFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// What the values of a column are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnKind {
+    /// Strings, and any value the column's other kinds do not fit, as JSON.
+    Text,
+    Integer,
+    Number,
+    Boolean,
+    /// Microseconds since the Unix epoch.
+    Timestamp,
+}
+
+impl ColumnKind {
+    // The kind a JSON schema `type` declares, if it is one of a single kind
+    fn declared(property: &Value) -> Option<Self> {
+        let kind = match property.get("type")? {
+            Value::String(kind) => kind.as_str(),
+            Value::Array(kinds) => {
+                let mut kinds = kinds.iter().filter_map(Value::as_str).filter(|kind| *kind != "null");
+                match (kinds.next(), kinds.next()) {
+                    (Some(kind), None) => kind,
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        match kind {
+            "integer" => Some(ColumnKind::Integer),
+            "number" => Some(ColumnKind::Number),
+            "boolean" => Some(ColumnKind::Boolean),
+            _ => Some(ColumnKind::Text),
+        }
+    }
+
+    fn fits(&self, value: &Value) -> bool {
+        match self {
+            ColumnKind::Text => true,
+            ColumnKind::Integer => value.is_i64(),
+            ColumnKind::Number => value.is_number(),
+            ColumnKind::Boolean => value.is_boolean(),
+            ColumnKind::Timestamp => value.is_i64(),
+        }
+    }
+
+    // The value as a CSV field or Parquet string, `None` for null
+    fn text(&self, value: &Value) -> Option<String> {
+        match value {
+            Value::Null => None,
+            Value::String(text) => Some(text.clone()),
+            Value::Number(micros) if *self == ColumnKind::Timestamp => micros
+                .as_i64()
+                .and_then(DateTime::from_timestamp_micros)
+                .map(|time| time.to_rfc3339_opts(SecondsFormat::Micros, true)),
+            value => Some(value.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub kind: ColumnKind,
+}
+
+/// The latest entries of a document as rows, with a column for each top-level field, see
+/// `doc_table`.
+#[derive(Debug, Clone)]
+pub struct DocTable {
+    pub columns: Vec<Column>,
+    /// A value for each column, null where an entry lacks the field.
+    rows: Vec<Vec<Value>>,
+    /// Entries left out as their content is not a JSON object or not stored on this node.
+    pub skipped: usize,
+}
+
+/// Reads the latest entries of a document as a table, for loading into BI tools, see
+/// `DocTable::write`.
+///
+/// Each key is one row, picked out of its authors' versions with `resolution` and sorted by key,
+/// after the entries are narrowed to `key_prefix` and `filter`. Trash, history, attachments, the
+/// schema, signatures, anchors and other bookkeeping keys are left out.
+///
+/// The first columns are `ENTRY_COLUMNS`. The fields of the document schema follow in name
+/// order, then any other top-level field an entry has. A column takes the kind its schema
+/// property declares, or for fields outside the schema the one all its values fit, and is
+/// `Text` if a value does not fit. Nested objects and arrays are written as JSON.
+pub async fn doc_table(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+    key_prefix: Option<String>,
+    resolution: &ConflictResolution,
+    filter: Option<&Filter>,
+) -> Result<DocTable, TableError> {
+    let properties = schema_properties(docs.clone(), blobs.clone(), doc_id.clone()).await?;
+
+    let query = match &key_prefix {
+        Some(prefix) => serde_json::json!({ "key_prefix": prefix }),
+        None => serde_json::json!({}),
+    };
+    let entries = get_entries(docs, doc_id, query).await?;
+    let entries: Vec<EntryDetails> = entries
+        .into_iter()
+        .filter(|entry| is_record_key(&entry.namespace.key))
+        .collect();
+    let mut entries = resolve_entries(entries, resolution);
+    if let Some(filter) = filter {
+        entries = filter_entries(blobs.clone(), entries, filter).await?;
+    }
+    entries.sort_by(|a, b| a.namespace.key.cmp(&b.namespace.key));
+
+    let mut records: Vec<(EntryDetails, Map<String, Value>)> = Vec::with_capacity(entries.len());
+    let mut skipped = 0;
+    for entry in entries {
+        match read_record(&blobs, &entry).await {
+            Some(fields) => records.push((entry, fields)),
+            None => skipped += 1,
+        }
+    }
+
+    let mut names: Vec<String> = properties.keys().cloned().collect();
+    let extra: BTreeSet<&String> = records
+        .iter()
+        .flat_map(|(_, fields)| fields.keys())
+        .filter(|name| !properties.contains_key(*name))
+        .collect();
+    names.extend(extra.into_iter().cloned());
+    names.retain(|name| !ENTRY_COLUMNS.contains(&name.as_str()));
+
+    let mut columns = vec![
+        Column { name: ENTRY_COLUMNS[0].to_string(), kind: ColumnKind::Text },
+        Column { name: ENTRY_COLUMNS[1].to_string(), kind: ColumnKind::Text },
+        Column { name: ENTRY_COLUMNS[2].to_string(), kind: ColumnKind::Timestamp },
+        Column { name: ENTRY_COLUMNS[3].to_string(), kind: ColumnKind::Text },
+    ];
+    for name in names {
+        let declared = properties.get(&name).copied().flatten();
+        let values: Vec<&Value> = records
+            .iter()
+            .filter_map(|(_, fields)| fields.get(&name))
+            .filter(|value| !value.is_null())
+            .collect();
+        let candidates = match declared {
+            Some(kind) => vec![kind],
+            None if values.is_empty() => vec![],
+            None => vec![ColumnKind::Integer, ColumnKind::Number, ColumnKind::Boolean],
+        };
+        let kind = candidates
+            .into_iter()
+            .find(|kind| values.iter().all(|value| kind.fits(value)))
+            .unwrap_or(ColumnKind::Text);
+        columns.push(Column { name, kind });
+    }
+
+    let rows = records
+        .into_iter()
+        .map(|(entry, mut fields)| {
+            let mut row = vec![
+                Value::from(entry.namespace.key),
+                Value::from(entry.namespace.author),
+                Value::from(entry.record.timestamp),
+                Value::from(entry.record.hash),
+            ];
+            row.extend(columns[ENTRY_COLUMNS.len()..].iter().map(|column| fields.remove(&column.name).unwrap_or(Value::Null)));
+            row
+        })
+        .collect();
+
+    Ok(DocTable { columns, rows, skipped })
+}
+
+impl DocTable {
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn write(&self, format: TableFormat) -> Vec<u8> {
+        match format {
+            TableFormat::Csv => self.to_csv(),
+            TableFormat::Parquet => self.to_parquet(),
+        }
+    }
+
+    /// The table as CSV, with a header row and CRLF line breaks. Nulls are empty fields and
+    /// timestamps are RFC 3339 in UTC.
+    pub fn to_csv(&self) -> Vec<u8> {
+        let mut out = String::new();
+        push_csv_line(&mut out, self.columns.iter().map(|column| Some(column.name.clone())));
+        for row in &self.rows {
+            push_csv_line(&mut out, self.columns.iter().zip(row).map(|(column, value)| column.kind.text(value)));
+        }
+        out.into_bytes()
+    }
+
+    /// The table as a Parquet file, every column optional and plainly encoded in one page.
+    /// `Text` columns are UTF-8 strings and `Timestamp` ones microseconds in UTC.
+    pub fn to_parquet(&self) -> Vec<u8> {
+        let mut out = PARQUET_MAGIC.to_vec();
+        let mut chunks = Vec::new();
+        let mut total_size = 0;
+        if !self.rows.is_empty() {
+            for (index, column) in self.columns.iter().enumerate() {
+                let page = data_page(column.kind, self.rows.iter().map(|row| &row[index]));
+                let offset = out.len() as i64;
+                out.extend_from_slice(&page);
+                total_size += page.len() as i64;
+                chunks.push(Thrift::Struct(vec![
+                    (2, Thrift::I64(offset)),
+                    (3, Thrift::Struct(vec![
+                        (1, Thrift::I32(physical_type(column.kind))),
+                        (2, Thrift::List(vec![Thrift::I32(PLAIN), Thrift::I32(RLE)])),
+                        (3, Thrift::List(vec![Thrift::Binary(column.name.clone().into_bytes())])),
+                        (4, Thrift::I32(UNCOMPRESSED)),
+                        (5, Thrift::I64(self.rows.len() as i64)),
+                        (6, Thrift::I64(page.len() as i64)),
+                        (7, Thrift::I64(page.len() as i64)),
+                        (9, Thrift::I64(offset)),
+                    ])),
+                ]));
+            }
+        }
+
+        let mut schema = vec![Thrift::Struct(vec![
+            (4, Thrift::Binary(b"schema".to_vec())),
+            (5, Thrift::I32(self.columns.len() as i32)),
+        ])];
+        for column in &self.columns {
+            let mut element = vec![
+                (1, Thrift::I32(physical_type(column.kind))),
+                (3, Thrift::I32(OPTIONAL)),
+                (4, Thrift::Binary(column.name.clone().into_bytes())),
+            ];
+            match column.kind {
+                ColumnKind::Text => element.push((6, Thrift::I32(UTF8))),
+                ColumnKind::Timestamp => element.push((6, Thrift::I32(TIMESTAMP_MICROS))),
+                _ => {}
+            }
+            schema.push(Thrift::Struct(element));
+        }
+
+        let row_groups = if chunks.is_empty() {
+            vec![]
+        } else {
+            vec![Thrift::Struct(vec![
+                (1, Thrift::List(chunks)),
+                (2, Thrift::I64(total_size)),
+                (3, Thrift::I64(self.rows.len() as i64)),
+            ])]
+        };
+        let metadata = Thrift::Struct(vec![
+            (1, Thrift::I32(1)),
+            (2, Thrift::List(schema)),
+            (3, Thrift::I64(self.rows.len() as i64)),
+            (4, Thrift::List(row_groups)),
+            (6, Thrift::Binary(b"starter-kit".to_vec())),
+        ]);
+
+        let footer_start = out.len();
+        metadata.write(&mut out);
+        let footer_len = (out.len() - footer_start) as u32;
+        out.extend_from_slice(&footer_len.to_le_bytes());
+        out.extend_from_slice(PARQUET_MAGIC);
+        out
+    }
+}
+
+// Whether `key` holds a record rather than bookkeeping of the node
+fn is_record_key(key: &str) -> bool {
+    !is_trash_key(key)
+        && !is_history_key(key)
+        && !is_attachment_key(key)
+        && key != SCHEMA_KEY
+        && key != CONTENT_POLICY_KEY
+        && key != SHARD_MANIFEST_KEY
+        && ![SIGNATURE_KEY_PREFIX, ANCHOR_KEY_PREFIX, REVOCATION_KEY_PREFIX, CORD_KEY_PREFIX]
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+}
+
+// The top-level properties of the latest schema of the document with the kind each declares,
+// following a schema that is only a `$ref`. Empty if the document has no schema.
+async fn schema_properties(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    doc_id: String,
+) -> Result<BTreeMap<String, Option<ColumnKind>>, TableError> {
+    let Some((entry, _)) = get_resolved_entry(docs.clone(), doc_id, SCHEMA_KEY.to_string(), &ConflictResolution::LastWriterWins).await? else {
+        return Ok(BTreeMap::new());
+    };
+    let hash = Hash::from_str(&entry.record.hash).map_err(|_| TableError::FailedToReadSchema)?;
+    let content = crate::blobs::read_blob_cached(&blobs, hash)
+        .await
+        .map_err(|_| TableError::FailedToReadSchema)?;
+    let mut schema: Value = serde_json::from_slice(&content).map_err(|_| TableError::FailedToReadSchema)?;
+    if schema.get("properties").is_none() {
+        if let Some(uri) = schema.get("$ref").and_then(Value::as_str).map(str::to_string) {
+            schema = resolve_ref(docs, blobs, &uri).await.map_err(|_| TableError::FailedToReadSchema)?;
+        }
+    }
+
+    Ok(schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, property)| (name.clone(), ColumnKind::declared(property)))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+// The fields of an entry, or `None` if its content is not a JSON object or not on this node
+async fn read_record(blobs: &NodeBlobs, entry: &EntryDetails) -> Option<Map<String, Value>> {
+    let hash = Hash::from_str(&entry.record.hash).ok()?;
+    let content = crate::blobs::read_blob_cached(blobs, hash).await.ok()?;
+    match serde_json::from_slice(&content).ok()? {
+        Value::Object(fields) => Some(fields),
+        _ => None,
+    }
+}
+
+fn push_csv_line(out: &mut String, fields: impl Iterator<Item = Option<String>>) {
+    for (index, field) in fields.enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+        let field = field.unwrap_or_default();
+        if field.contains([',', '"', '\r', '\n']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(&field);
+        }
+    }
+    out.push_str("\r\n");
+}
+
+fn physical_type(kind: ColumnKind) -> i32 {
+    match kind {
+        ColumnKind::Text => BYTE_ARRAY,
+        ColumnKind::Integer | ColumnKind::Timestamp => INT64,
+        ColumnKind::Number => DOUBLE,
+        ColumnKind::Boolean => BOOLEAN,
+    }
+}
+
+// A v1 data page of one column: its header, the definition levels of the values and the
+// non-null values
+fn data_page<'a>(kind: ColumnKind, values: impl ExactSizeIterator<Item = &'a Value>) -> Vec<u8> {
+    let count = values.len();
+    let mut defined = Vec::with_capacity(count);
+    let mut flags = Vec::new();
+    let mut plain = Vec::new();
+    for value in values {
+        let written = match kind {
+            ColumnKind::Text => kind.text(value).map(|text| {
+                plain.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                plain.extend_from_slice(text.as_bytes());
+            }),
+            ColumnKind::Integer | ColumnKind::Timestamp => {
+                value.as_i64().map(|number| plain.extend_from_slice(&number.to_le_bytes()))
+            }
+            ColumnKind::Number => value.as_f64().map(|number| plain.extend_from_slice(&number.to_le_bytes())),
+            ColumnKind::Boolean => value.as_bool().map(|flag| flags.push(flag)),
+        };
+        defined.push(written.is_some());
+    }
+    if kind == ColumnKind::Boolean {
+        plain = pack_bits(&flags);
+    }
+
+    // definition levels are a single bit-packed run of the RLE hybrid encoding, after its length
+    let mut levels = Vec::new();
+    write_varint(&mut levels, ((count.div_ceil(8) as u64) << 1) | 1);
+    levels.extend_from_slice(&pack_bits(&defined));
+    let mut data = (levels.len() as u32).to_le_bytes().to_vec();
+    data.extend_from_slice(&levels);
+    data.extend_from_slice(&plain);
+
+    let mut page = Vec::new();
+    Thrift::Struct(vec![
+        (1, Thrift::I32(DATA_PAGE)),
+        (2, Thrift::I32(data.len() as i32)),
+        (3, Thrift::I32(data.len() as i32)),
+        (5, Thrift::Struct(vec![
+            (1, Thrift::I32(count as i32)),
+            (2, Thrift::I32(PLAIN)),
+            (3, Thrift::I32(RLE)),
+            (4, Thrift::I32(RLE)),
+        ])),
+    ])
+    .write(&mut page);
+    page.extend_from_slice(&data);
+    page
+}
+
+// Bits in least significant first order, padded to whole bytes
+fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+    for (index, bit) in bits.iter().enumerate() {
+        if *bit {
+            bytes[index / 8] |= 1 << (index % 8);
+        }
+    }
+    bytes
+}
+
+// The values of Parquet's metadata, written in the Thrift compact protocol
+enum Thrift {
+    I32(i32),
+    I64(i64),
+    Binary(Vec<u8>),
+    /// Elements all of one type.
+    List(Vec<Thrift>),
+    /// Fields by ascending ID.
+    Struct(Vec<(i16, Thrift)>),
+}
+
+impl Thrift {
+    fn compact_type(&self) -> u8 {
+        match self {
+            Thrift::I32(_) => 5,
+            Thrift::I64(_) => 6,
+            Thrift::Binary(_) => 8,
+            Thrift::List(_) => 9,
+            Thrift::Struct(_) => 12,
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Thrift::I32(value) => write_varint(out, ((value << 1) ^ (value >> 31)) as u32 as u64),
+            Thrift::I64(value) => write_varint(out, ((value << 1) ^ (value >> 63)) as u64),
+            Thrift::Binary(bytes) => {
+                write_varint(out, bytes.len() as u64);
+                out.extend_from_slice(bytes);
+            }
+            Thrift::List(items) => {
+                // the element type of an empty list does not matter
+                let element_type = items.first().map_or(12, Thrift::compact_type);
+                if items.len() < 15 {
+                    out.push(((items.len() as u8) << 4) | element_type);
+                } else {
+                    out.push(0xf0 | element_type);
+                    write_varint(out, items.len() as u64);
+                }
+                for item in items {
+                    item.write(out);
+                }
+            }
+            Thrift::Struct(fields) => {
+                let mut last_id = 0;
+                for (id, value) in fields {
+                    let delta = id - last_id;
+                    if (1..=15).contains(&delta) {
+                        out.push(((delta as u8) << 4) | value.compact_type());
+                    } else {
+                        out.push(value.compact_type());
+                        write_varint(out, ((id << 1) ^ (id >> 15)) as u16 as u64);
+                    }
+                    value.write(out);
+                    last_id = *id;
+                }
+                out.push(0);
+            }
+        }
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    fn table() -> super::DocTable {
+        let column = |name: &str, kind| super::Column { name: name.to_string(), kind };
+        super::DocTable {
+            columns: vec![
+                column("_key", super::ColumnKind::Text),
+                column("_timestamp", super::ColumnKind::Timestamp),
+                column("age", super::ColumnKind::Integer),
+                column("active", super::ColumnKind::Boolean),
+                column("tags", super::ColumnKind::Text),
+            ],
+            rows: vec![
+                vec![serde_json::json!("a,\"b\""), serde_json::json!(0), serde_json::json!(30), serde_json::json!(true), serde_json::json!(["x"])],
+                vec![serde_json::json!("c"), serde_json::json!(1), serde_json::Value::Null, serde_json::json!(false), serde_json::Value::Null],
+            ],
+            skipped: 0,
+        }
+    }
+
+    #[test]
+    pub fn test_csv_quotes_fields_and_writes_nulls_empty() {
+        let csv = String::from_utf8(table().to_csv()).unwrap();
+        assert_eq!(
+            csv,
+            "_key,_timestamp,age,active,tags\r\n\
+             \"a,\"\"b\"\"\",1970-01-01T00:00:00.000000Z,30,true,\"[\"\"x\"\"]\"\r\n\
+             c,1970-01-01T00:00:00.000001Z,,false,\r\n"
+        );
+    }
+
+    #[test]
+    pub fn test_parquet_has_footer_and_pages() {
+        let parquet = table().to_parquet();
+        assert!(parquet.starts_with(super::PARQUET_MAGIC) && parquet.ends_with(super::PARQUET_MAGIC));
+        let footer_len = u32::from_le_bytes(parquet[parquet.len() - 8..parquet.len() - 4].try_into().unwrap()) as usize;
+        let footer = &parquet[parquet.len() - 8 - footer_len..parquet.len() - 8];
+        // version 1, then a schema of the root and the five columns
+        assert_eq!(&footer[..3], &[0x15, 0x02, 0x19]);
+        assert_eq!(footer[3], (6 << 4) | 12);
+
+        // the first page holds both values of `_key`, with both defined
+        let page = super::data_page(super::ColumnKind::Text, table().rows.iter().map(|row| &row[0]));
+        assert_eq!(&parquet[4..4 + page.len()], page.as_slice());
+        assert!(page.ends_with(&[2, 0, 0, 0, 3, 0b11, 5, 0, 0, 0, b'a', b',', b'"', b'b', b'"', 1, 0, 0, 0, b'c']));
+    }
+
+    #[test]
+    pub fn test_parquet_reads_back() -> anyhow::Result<()> {
+        use parquet::{
+            basic::{ConvertedType, Repetition, Type},
+            file::reader::{FileReader, SerializedFileReader},
+            record::Field,
+        };
+
+        let reader = SerializedFileReader::new(bytes::Bytes::from(table().to_parquet()))?;
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 2);
+        let columns: Vec<_> = metadata
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|column| {
+                let info = column.self_type().get_basic_info();
+                (column.name().to_string(), column.physical_type(), info.repetition(), info.converted_type())
+            })
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("_key".to_string(), Type::BYTE_ARRAY, Repetition::OPTIONAL, ConvertedType::UTF8),
+                ("_timestamp".to_string(), Type::INT64, Repetition::OPTIONAL, ConvertedType::TIMESTAMP_MICROS),
+                ("age".to_string(), Type::INT64, Repetition::OPTIONAL, ConvertedType::NONE),
+                ("active".to_string(), Type::BOOLEAN, Repetition::OPTIONAL, ConvertedType::NONE),
+                ("tags".to_string(), Type::BYTE_ARRAY, Repetition::OPTIONAL, ConvertedType::UTF8),
+            ]
+        );
+
+        let rows = reader
+            .get_row_iter(None)?
+            .map(|row| Ok(row?.into_columns().into_iter().map(|(_, field)| field).collect()))
+            .collect::<anyhow::Result<Vec<Vec<Field>>>>()?;
+        assert_eq!(
+            rows,
+            vec![
+                vec![
+                    Field::Str("a,\"b\"".to_string()),
+                    Field::TimestampMicros(0),
+                    Field::Long(30),
+                    Field::Bool(true),
+                    Field::Str("[\"x\"]".to_string()),
+                ],
+                vec![Field::Str("c".to_string()), Field::TimestampMicros(1), Field::Null, Field::Bool(false), Field::Null],
+            ]
+        );
+
+        // a table without rows is still a readable file with its schema
+        let empty = super::DocTable { rows: vec![], ..table() };
+        let reader = SerializedFileReader::new(bytes::Bytes::from(empty.to_parquet()))?;
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 0);
+        assert_eq!(reader.metadata().file_metadata().schema_descr().num_columns(), 5);
+        assert_eq!(reader.num_row_groups(), 0);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_declared_kinds() {
+        let kind = |property| super::ColumnKind::declared(&property);
+        assert_eq!(kind(serde_json::json!({ "type": "integer" })), Some(super::ColumnKind::Integer));
+        assert_eq!(kind(serde_json::json!({ "type": ["number", "null"] })), Some(super::ColumnKind::Number));
+        assert_eq!(kind(serde_json::json!({ "type": "object" })), Some(super::ColumnKind::Text));
+        assert_eq!(kind(serde_json::json!({ "type": ["string", "integer"] })), None);
+        assert_eq!(kind(serde_json::json!({})), None);
+    }
+}
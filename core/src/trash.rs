@@ -0,0 +1,254 @@
+use crate::docs::{get_document, list_docs, DocError};
+use helpers::errors::Cause;
+use helpers::stores::NodeDocs;
+use helpers::utils::{decode_doc_id, decode_key, encode_key, trash_key, validate_key, SS58AuthorId, TRASH_KEY_PREFIX};
+
+use futures::TryStreamExt;
+use iroh_docs::rpc::client::docs::Doc;
+use iroh_docs::rpc::proto::{Request, Response};
+use iroh_docs::store::Query;
+use iroh_docs::{AuthorId, Entry, NamespaceId};
+use quic_rpc::transport::flume::FlumeConnector;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+// How often the entries kept in the trash past their retention are removed
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// A deleted entry kept in the trash, under `__trash/<key>`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashedEntry {
+    /// The key the entry had before it was deleted.
+    pub key: String,
+    pub author: String,
+    pub hash: String,
+    pub len: u64,
+    /// When the entry was deleted, in microseconds since the Unix epoch like entry timestamps.
+    pub deleted_at: u64,
+}
+
+/// Keeps `entries` of `author` restorable before they are deleted. The content of each one is
+/// referenced again under `__trash/<key>`, so it syncs to peers and is not garbage collected.
+/// A later deletion of the same key replaces its earlier one in the trash.
+pub(crate) async fn move_to_trash(
+    doc: &Doc<FlumeConnector<Response, Request>>,
+    author: AuthorId,
+    entries: &[Entry],
+) -> anyhow::Result<(), DocError> {
+    for entry in entries.iter().filter(|entry| entry.content_len() > 0) {
+        let key = String::from_utf8(decode_key(entry.key()))
+            .map_err(|e| DocError::FailedToDecodeEntryKey(Cause::new(e)))?;
+        doc.set_hash(author, encode_key(trash_key(&key).as_bytes()), entry.content_hash(), entry.content_len())
+            .await
+            .map_err(|e| DocError::FailedToTrashEntry(Cause::new(e)))?;
+    }
+    Ok(())
+}
+
+/// Lists the deleted entries kept in the trash of a document, of `author_id` only if given.
+pub async fn list_trash(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    author_id: Option<String>,
+) -> anyhow::Result<Vec<TrashedEntry>, DocError> {
+    let doc = open_doc(docs, &doc_id).await?;
+
+    let mut query = Query::key_prefix(TRASH_KEY_PREFIX.as_bytes());
+    if let Some(author_id) = author_id {
+        let author = SS58AuthorId::decode(&author_id).map_err(|_| DocError::InvalidAuthorIdFormat)?;
+        query = query.author(author);
+    }
+
+    let entries: Vec<Entry> = doc
+        .get_many(query)
+        .await
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?
+        .try_collect()
+        .await
+        .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
+
+    entries
+        .iter()
+        .map(|entry| {
+            let key = String::from_utf8(decode_key(entry.key()))
+                .map_err(|e| DocError::FailedToDecodeEntryKey(Cause::new(e)))?;
+            let author = SS58AuthorId::from_author_id(&entry.author())
+                .map_err(|e| DocError::FailedToEncodeAuthorId(Cause::new(e)))?;
+            Ok(TrashedEntry {
+                key: key.strip_prefix(TRASH_KEY_PREFIX).unwrap_or(&key).to_string(),
+                author: author.as_ss58().to_string(),
+                hash: entry.content_hash().to_string(),
+                len: entry.content_len(),
+                deleted_at: entry.timestamp(),
+            })
+        })
+        .collect()
+}
+
+/// Puts the entry `author_id` deleted at `key` back from the trash, with the content it had.
+///
+/// # Returns
+/// The content hash of the restored entry.
+pub async fn restore_entry(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    author_id: String,
+    key: String,
+) -> anyhow::Result<String, DocError> {
+    let author = SS58AuthorId::decode(&author_id).map_err(|_| DocError::InvalidAuthorIdFormat)?;
+    validate_key(&key, true)
+        .await
+        .map_err(|e| DocError::FailedToValidateKey(Cause::new(e)))?;
+    let doc = open_doc(docs, &doc_id).await?;
+
+    let trashed_key = encode_key(trash_key(&key).as_bytes());
+    let trashed = doc
+        .get_exact(author, trashed_key.clone(), false)
+        .await
+        .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?
+        .ok_or(DocError::TrashedEntryNotFound)?;
+
+    // a value written at the key since it was deleted is not overwritten
+    let current = doc
+        .get_exact(author, encode_key(key.as_bytes()), false)
+        .await
+        .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
+    if current.is_some() {
+        return Err(DocError::EntryAlreadyExists);
+    }
+
+    doc.set_hash(author, encode_key(key.as_bytes()), trashed.content_hash(), trashed.content_len())
+        .await
+        .map_err(|e| DocError::FailedToRestoreEntry(Cause::new(e)))?;
+    doc.del(author, trashed_key)
+        .await
+        .map_err(|e| DocError::FailedToRestoreEntry(Cause::new(e)))?;
+
+    Ok(trashed.content_hash().to_string())
+}
+
+/// Deletes for good the entries `author_id` keeps in the trash of a document, or only the one
+/// deleted at `key`.
+///
+/// # Returns
+/// The number of entries removed from the trash.
+pub async fn empty_trash(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    author_id: String,
+    key: Option<String>,
+) -> anyhow::Result<usize, DocError> {
+    let author = SS58AuthorId::decode(&author_id).map_err(|_| DocError::InvalidAuthorIdFormat)?;
+    let prefix = match key {
+        Some(key) => {
+            validate_key(&key, true)
+                .await
+                .map_err(|e| DocError::FailedToValidateKey(Cause::new(e)))?;
+            encode_key(trash_key(&key).as_bytes())
+        }
+        // Keys are stored null-terminated, so the bare prefix matches every key in the trash
+        None => TRASH_KEY_PREFIX.as_bytes().to_vec(),
+    };
+    let doc = open_doc(docs, &doc_id).await?;
+
+    doc.del(author, prefix)
+        .await
+        .map_err(|e| DocError::FailedToDeleteEntry(Cause::new(e)))
+}
+
+/// Deletes for good the entries the authors of this node keep in the trash of any document for
+/// longer than `retention`. Entries other nodes' authors deleted are left to those nodes.
+///
+/// # Returns
+/// The number of entries removed from the trash.
+pub async fn purge_expired_trash(docs: Arc<NodeDocs>, retention: Duration) -> anyhow::Result<usize, DocError> {
+    purge_expired_entries(docs, TRASH_KEY_PREFIX, retention).await
+}
+
+/// Deletes for good the entries under `prefix` that the authors of this node wrote in any
+/// document longer than `retention` ago. Entries of other nodes' authors are left to those nodes.
+///
+/// # Returns
+/// The number of entries deleted.
+pub(crate) async fn purge_expired_entries(
+    docs: Arc<NodeDocs>,
+    prefix: &str,
+    retention: Duration,
+) -> anyhow::Result<usize, DocError> {
+    let local_authors: HashSet<AuthorId> = docs
+        .client()
+        .authors()
+        .list()
+        .await
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?
+        .try_collect()
+        .await
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let cutoff = now.saturating_sub(retention).as_micros() as u64;
+
+    let mut purged = 0;
+    for (doc_id, _) in list_docs(docs.clone()).await? {
+        match purge_doc(docs.clone(), &doc_id, prefix, &local_authors, cutoff).await {
+            Ok(count) => purged += count,
+            Err(e) => tracing::warn!(doc_id = %doc_id, prefix, error = %e, "failed to delete expired entries"),
+        }
+    }
+    Ok(purged)
+}
+
+// Deletes the entries of `local_authors` under `prefix` in a document that were written before `cutoff`
+async fn purge_doc(
+    docs: Arc<NodeDocs>,
+    doc_id: &str,
+    prefix: &str,
+    local_authors: &HashSet<AuthorId>,
+    cutoff: u64,
+) -> anyhow::Result<usize, DocError> {
+    let doc = open_doc(docs, doc_id).await?;
+    let entries: Vec<Entry> = doc
+        .get_many(Query::key_prefix(prefix.as_bytes()))
+        .await
+        .map_err(|e| DocError::FailedToGetEntries(Cause::new(e)))?
+        .try_collect()
+        .await
+        .map_err(|e| DocError::FailedToGetEntry(Cause::new(e)))?;
+
+    let mut purged = 0;
+    for entry in entries {
+        if entry.timestamp() >= cutoff || !local_authors.contains(&entry.author()) {
+            continue;
+        }
+        purged += doc
+            .del(entry.author(), entry.key().to_vec())
+            .await
+            .map_err(|e| DocError::FailedToDeleteEntry(Cause::new(e)))?;
+    }
+    Ok(purged)
+}
+
+/// Removes the entries kept in the trash past `retention` every hour, see `--trash-retention`.
+pub fn spawn_trash_purger(docs: Arc<NodeDocs>, retention: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            ticks.tick().await;
+            match purge_expired_trash(docs.clone(), retention).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!(purged, "🗑 Emptied expired entries from the trash"),
+                Err(e) => tracing::warn!(error = %e, "failed to list documents"),
+            }
+        }
+    })
+}
+
+pub(crate) async fn open_doc(docs: Arc<NodeDocs>, doc_id: &str) -> anyhow::Result<Doc<FlumeConnector<Response, Request>>, DocError> {
+    let namespace_id = decode_doc_id(doc_id).map_err(|_| DocError::InvalidDocumentIdFormat)?;
+    get_document(docs, NamespaceId::from(namespace_id))
+        .await
+        .map_err(|_| DocError::DocumentNotFound)
+}
@@ -0,0 +1,204 @@
+use helpers::utils::decode_doc_id;
+use helpers::stores::{NodeBlobs, NodeDocs};
+use node::replica_digests::{bucket_digests, key_digests, request_digests, DigestRequest, DigestResponse};
+
+use iroh::NodeAddr;
+use iroh_base::PublicKey;
+use iroh_docs::NamespaceId;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Most divergent keys listed in a report; the rest are only counted.
+pub const MAX_REPORTED_KEYS: usize = 1000;
+
+// Buckets whose key digests are asked for in one request
+const BUCKETS_PER_REQUEST: usize = 16;
+
+#[derive(Debug, PartialEq)]
+pub enum VerificationError {
+    /// Failed to decode the document ID.
+    InvalidDocumentIdFormat,
+    /// Failed to decode the peer node ID.
+    InvalidNodeIdFormat,
+    /// The document was not found.
+    DocumentNotFound,
+    /// Failed to compute the local digests of the document.
+    FailedToReadDocument,
+    /// The peer could not be reached.
+    PeerUnreachable,
+    /// The peer does not hold the document, or does not answer this node for it.
+    PeerRefused,
+    /// The peer answered with something else than the digests asked for.
+    InvalidPeerResponse,
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+/// How the replica of a document on a peer compares to the local one.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplicaReport {
+    pub doc_id: String,
+    pub peer: String,
+    /// Whether both replicas hold exactly the same entries.
+    pub consistent: bool,
+    /// Keys in the local replica.
+    pub keys_local: u64,
+    /// Keys in the peer's replica.
+    pub keys_peer: u64,
+    /// Buckets of keys whose digests differ.
+    pub divergent_buckets: usize,
+    /// Keys only the local replica has.
+    pub missing_on_peer: Vec<String>,
+    /// Keys only the peer's replica has.
+    pub missing_locally: Vec<String>,
+    /// Keys both replicas have, with different entries.
+    pub different: Vec<String>,
+    /// Divergent keys left out of the lists above, past `MAX_REPORTED_KEYS`.
+    pub truncated: usize,
+}
+
+/// Splits divergent keys into those missing on the peer, those missing locally and those with
+/// different entries.
+pub fn diff_keys(
+    local: &BTreeMap<String, String>,
+    peer: &BTreeMap<String, String>,
+) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let missing_on_peer = local.keys().filter(|key| !peer.contains_key(*key)).cloned().collect();
+    let missing_locally = peer.keys().filter(|key| !local.contains_key(*key)).cloned().collect();
+    let different = local
+        .iter()
+        .filter(|(key, digest)| peer.get(*key).is_some_and(|peer_digest| peer_digest != *digest))
+        .map(|(key, _)| key.clone())
+        .collect();
+    (missing_on_peer, missing_locally, different)
+}
+
+/// Compares the replica of `doc_id` on `peer_node_id` to the local one.
+///
+/// Both nodes digest their entries in buckets of keys; only the keys of the buckets whose
+/// digests differ are then compared one by one.
+pub async fn verify_replica(
+    docs: &NodeDocs,
+    blobs: &NodeBlobs,
+    doc_id: String,
+    peer_node_id: String,
+) -> Result<ReplicaReport, VerificationError> {
+    let namespace_id = decode_doc_id(&doc_id)
+        .map(NamespaceId::from)
+        .map_err(|_| VerificationError::InvalidDocumentIdFormat)?;
+    let peer = PublicKey::from_str(&peer_node_id).map_err(|_| VerificationError::InvalidNodeIdFormat)?;
+    docs.client()
+        .open(namespace_id)
+        .await
+        .map_err(|_| VerificationError::FailedToReadDocument)?
+        .ok_or(VerificationError::DocumentNotFound)?;
+
+    let local_buckets = bucket_digests(docs, namespace_id)
+        .await
+        .map_err(|_| VerificationError::FailedToReadDocument)?;
+    let request = DigestRequest::Buckets { doc_id: doc_id.clone() };
+    let peer_buckets = match ask_peer(blobs, peer, &request).await? {
+        DigestResponse::Buckets { buckets } => buckets,
+        _ => return Err(VerificationError::InvalidPeerResponse),
+    };
+
+    let divergent: BTreeSet<u8> = local_buckets
+        .keys()
+        .chain(peer_buckets.keys())
+        .filter(|bucket| local_buckets.get(*bucket) != peer_buckets.get(*bucket))
+        .copied()
+        .collect();
+
+    let mut local_keys = BTreeMap::new();
+    let mut peer_keys = BTreeMap::new();
+    let divergent_list: Vec<u8> = divergent.iter().copied().collect();
+    for chunk in divergent_list.chunks(BUCKETS_PER_REQUEST) {
+        let buckets: BTreeSet<u8> = chunk.iter().copied().collect();
+        local_keys.extend(
+            key_digests(docs, namespace_id, &buckets)
+                .await
+                .map_err(|_| VerificationError::FailedToReadDocument)?,
+        );
+        let request = DigestRequest::Keys { doc_id: doc_id.clone(), buckets };
+        match ask_peer(blobs, peer, &request).await? {
+            DigestResponse::Keys { keys } => peer_keys.extend(keys),
+            _ => return Err(VerificationError::InvalidPeerResponse),
+        }
+    }
+
+    let (mut missing_on_peer, mut missing_locally, mut different) = diff_keys(&local_keys, &peer_keys);
+    let divergent_keys = missing_on_peer.len() + missing_locally.len() + different.len();
+    let mut room = MAX_REPORTED_KEYS;
+    for keys in [&mut missing_on_peer, &mut missing_locally, &mut different] {
+        keys.truncate(room);
+        room -= keys.len();
+    }
+
+    Ok(ReplicaReport {
+        doc_id,
+        peer: peer_node_id,
+        consistent: divergent.is_empty(),
+        keys_local: local_buckets.values().map(|bucket| bucket.keys).sum(),
+        keys_peer: peer_buckets.values().map(|bucket| bucket.keys).sum(),
+        divergent_buckets: divergent.len(),
+        truncated: divergent_keys - (missing_on_peer.len() + missing_locally.len() + different.len()),
+        missing_on_peer,
+        missing_locally,
+        different,
+    })
+}
+
+// Sends `request` to `peer`, turning a refusal into an error
+async fn ask_peer(
+    blobs: &NodeBlobs,
+    peer: PublicKey,
+    request: &DigestRequest,
+) -> Result<DigestResponse, VerificationError> {
+    let response = request_digests(blobs.endpoint(), NodeAddr::new(peer), request)
+        .await
+        .map_err(|e| {
+            tracing::warn!(peer = %peer, error = %e, "failed to request replica digests");
+            VerificationError::PeerUnreachable
+        })?;
+    match response {
+        DigestResponse::Refused { reason } => {
+            tracing::warn!(peer = %peer, reason = %reason, "peer refused to send replica digests");
+            Err(VerificationError::PeerRefused)
+        }
+        response => Ok(response),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    pub async fn test_diff_keys() -> anyhow::Result<()> {
+        let local = std::collections::BTreeMap::from([
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+            ("c".to_string(), "3".to_string()),
+        ]);
+        let peer = std::collections::BTreeMap::from([
+            ("b".to_string(), "2".to_string()),
+            ("c".to_string(), "4".to_string()),
+            ("d".to_string(), "5".to_string()),
+        ]);
+
+        let (missing_on_peer, missing_locally, different) = super::diff_keys(&local, &peer);
+        assert_eq!(missing_on_peer, vec!["a".to_string()]);
+        assert_eq!(missing_locally, vec!["d".to_string()]);
+        assert_eq!(different, vec!["c".to_string()]);
+
+        let (missing_on_peer, missing_locally, different) = super::diff_keys(&local, &local);
+        assert!(missing_on_peer.is_empty() && missing_locally.is_empty() && different.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+use crate::docs::get_document;
+use helpers::sync_controls::{SyncBlock, SyncControlRegistry};
+use helpers::stores::NodeDocs;
+use helpers::utils::decode_doc_id;
+use helpers::write_queue::{QueuedWrite, WriteQueue};
+
+use iroh::NodeAddr;
+use iroh_base::PublicKey;
+use iroh_docs::NamespaceId;
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq)]
+pub enum WriteQueueError {
+    /// Failed to decode the document ID.
+    InvalidDocumentIdFormat,
+    /// The document was not found.
+    DocumentNotFound,
+    /// Failed to read the sync peers of a document.
+    FailedToReadDocument,
+    /// Failed to start syncing a document with its peers.
+    FailedToStartSync,
+}
+
+impl fmt::Display for WriteQueueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for WriteQueueError {}
+
+/// The local writes of a document that no peer is known to have yet.
+#[derive(Debug, Clone, Serialize)]
+pub struct DocWriteQueue {
+    pub doc_id: String,
+    pub pending: usize,
+    /// Timestamp of the oldest pending write, in microseconds since the Unix epoch.
+    pub oldest: Option<u64>,
+    /// Why sync controls hold the writes back, if they do.
+    pub sync_blocked_by: Option<SyncBlock>,
+    pub writes: Vec<QueuedWrite>,
+}
+
+/// What a flush did for a document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum FlushOutcome {
+    /// A sync round was started with these peers.
+    Started { peers: Vec<String> },
+    /// The document has no known peer to sync with.
+    NoPeers,
+    /// Sync controls do not let the document sync right now.
+    Blocked { reason: SyncBlock },
+}
+
+/// Lists the pending writes of each document that has any, or only of `doc_id`.
+pub fn write_queue_report(
+    queue: &WriteQueue,
+    controls: &SyncControlRegistry,
+    doc_id: Option<String>,
+) -> Result<Vec<DocWriteQueue>, WriteQueueError> {
+    let pending = match doc_id {
+        Some(doc_id) => {
+            decode_doc_id(&doc_id).map_err(|_| WriteQueueError::InvalidDocumentIdFormat)?;
+            let writes = queue.get(&doc_id);
+            if writes.is_empty() { Vec::new() } else { vec![(doc_id, writes)] }
+        }
+        None => queue.list().into_iter().collect(),
+    };
+
+    Ok(pending
+        .into_iter()
+        .map(|(doc_id, writes)| DocWriteQueue {
+            pending: writes.len(),
+            oldest: writes.iter().map(|write| write.timestamp).min(),
+            sync_blocked_by: controls.blocked_by(&doc_id),
+            writes,
+            doc_id,
+        })
+        .collect())
+}
+
+/// Starts a sync round with the known peers of each document with pending writes, or of `doc_id`
+/// whether it has any or not, so the writes propagate as soon as a peer is reachable again.
+///
+/// The round runs in the background; writes leave the queue once it succeeds. Documents that
+/// sync controls block are left alone.
+///
+/// # Returns
+/// What the flush did for each document.
+pub async fn flush_write_queue(
+    docs: Arc<NodeDocs>,
+    queue: &WriteQueue,
+    controls: &SyncControlRegistry,
+    doc_id: Option<String>,
+) -> Result<Vec<(String, FlushOutcome)>, WriteQueueError> {
+    let doc_ids = match doc_id {
+        Some(doc_id) => vec![doc_id],
+        None => queue.list().into_keys().collect(),
+    };
+
+    let mut outcomes = Vec::with_capacity(doc_ids.len());
+    for doc_id in doc_ids {
+        let outcome = flush_doc(&docs, controls, &doc_id).await?;
+        outcomes.push((doc_id, outcome));
+    }
+    Ok(outcomes)
+}
+
+async fn flush_doc(
+    docs: &Arc<NodeDocs>,
+    controls: &SyncControlRegistry,
+    doc_id: &str,
+) -> Result<FlushOutcome, WriteQueueError> {
+    let namespace_id = decode_doc_id(doc_id).map_err(|_| WriteQueueError::InvalidDocumentIdFormat)?;
+    let doc = get_document(docs.clone(), NamespaceId::from(namespace_id))
+        .await
+        .map_err(|_| WriteQueueError::DocumentNotFound)?;
+
+    if let Some(reason) = controls.blocked_by(doc_id) {
+        return Ok(FlushOutcome::Blocked { reason });
+    }
+
+    let peers: Vec<PublicKey> = doc
+        .get_sync_peers()
+        .await
+        .map_err(|_| WriteQueueError::FailedToReadDocument)?
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|peer| PublicKey::from_bytes(&peer).ok())
+        .collect();
+    if peers.is_empty() {
+        return Ok(FlushOutcome::NoPeers);
+    }
+
+    doc.start_sync(peers.iter().copied().map(NodeAddr::from).collect())
+        .await
+        .map_err(|_| WriteQueueError::FailedToStartSync)?;
+
+    Ok(FlushOutcome::Started { peers: peers.iter().map(|peer| peer.to_string()).collect() })
+}
+
+#[cfg(test)]
+mod tests {
+    #[tokio::test]
+    pub async fn test_write_queue_confirm() -> anyhow::Result<()> {
+        let write = |key: &str, author: &str, timestamp: u64| super::QueuedWrite {
+            key: key.to_string(),
+            author: author.to_string(),
+            hash: "hash".to_string(),
+            len: 4,
+            timestamp,
+        };
+        let doc_id = "dae58ff8833241ac82d6ff7611046ed67b5072d142c588d0063e942d9a75502b6";
+        let queue = super::WriteQueue::default();
+        let controls = super::SyncControlRegistry::default();
+
+        queue.record(doc_id, write("a", "alice", 10)).await?;
+        queue.record(doc_id, write("b", "alice", 20)).await?;
+        // a later write of the same author and key replaces the earlier one
+        queue.record(doc_id, write("a", "alice", 30)).await?;
+
+        let report = super::write_queue_report(&queue, &controls, Some(doc_id.to_string()))?;
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].pending, 2);
+        assert_eq!(report[0].oldest, Some(20));
+
+        // a sync round that started at 25 only covers the write made at 20
+        assert_eq!(queue.confirm(doc_id, 25).await?, 1);
+        assert_eq!(queue.get(doc_id), vec![write("a", "alice", 30)]);
+
+        assert_eq!(queue.confirm(doc_id, 31).await?, 1);
+        assert!(super::write_queue_report(&queue, &controls, None)?.is_empty());
+
+        assert_eq!(
+            super::write_queue_report(&queue, &controls, Some("not a doc id".to_string())).unwrap_err(),
+            super::WriteQueueError::InvalidDocumentIdFormat
+        );
+        Ok(())
+    }
+}
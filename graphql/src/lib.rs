@@ -0,0 +1 @@
+pub mod schema;
\ No newline at end of file
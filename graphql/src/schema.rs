@@ -0,0 +1,333 @@
+use starter_kit_core::{authors::*, blobs::*, docs::*};
+use helpers::{state::{AppState, READ_ONLY_MESSAGE}, utils::get_author_id_from_headers, events::NodeEvent};
+use gateway::access_control::check_node_id_and_domain_header;
+
+use async_graphql::{
+    ComplexObject, Context, EmptySubscription, Error, InputObject, Object, Result, Schema, SimpleObject,
+    http::GraphiQLSource,
+};
+use axum::{extract::State, Json, http::{HeaderMap, StatusCode}, response::Html};
+use iroh_docs::CapabilityKind;
+use serde_json::json;
+use std::sync::OnceLock;
+
+pub type StarterKitSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+static SCHEMA: OnceLock<StarterKitSchema> = OnceLock::new();
+
+// Returns the GraphQL schema. Per-request data (the node state and caller) is attached by the handler.
+pub fn schema() -> &'static StarterKitSchema {
+    SCHEMA.get_or_init(|| Schema::build(QueryRoot, MutationRoot, EmptySubscription).finish())
+}
+
+// The author ID from the `author-id` header, if the caller sent one
+struct Caller(Option<String>);
+
+// Objects
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Doc {
+    pub doc_id: String,
+    pub capability: String,
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Entry {
+    pub doc: String,
+    pub key: String,
+    pub author: String,
+    pub hash: String,
+    pub len: u64,
+    pub timestamp: u64,
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Blob {
+    pub hash: String,
+    pub status: String,
+}
+
+#[derive(SimpleObject)]
+pub struct AddedBlob {
+    pub hash: String,
+    pub format: String,
+    pub size: u64,
+    pub tag: String,
+}
+
+// Inputs
+/// Filters for entries, matching the `query_params` of `POST /docs/get-entries`.
+#[derive(InputObject, Default)]
+pub struct EntryFilter {
+    pub author_id: Option<String>,
+    pub key: Option<String>,
+    pub key_prefix: Option<String>,
+    pub include_empty: Option<bool>,
+    /// "author" or "key".
+    pub sort_by: Option<String>,
+    /// "ascending" or "descending".
+    pub sort_direction: Option<String>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+impl EntryFilter {
+    fn to_query_params(&self) -> serde_json::Value {
+        let mut params = serde_json::Map::new();
+        let fields = [
+            ("author_id", self.author_id.clone().map(|v| json!(v))),
+            ("key", self.key.clone().map(|v| json!(v))),
+            ("key_prefix", self.key_prefix.clone().map(|v| json!(v))),
+            ("include_empty", self.include_empty.map(|v| json!(v))),
+            ("sort_by", self.sort_by.clone().map(|v| json!(v))),
+            ("sort_direction", self.sort_direction.clone().map(|v| json!(v))),
+            ("limit", self.limit.map(|v| json!(v))),
+            ("offset", self.offset.map(|v| json!(v))),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                params.insert(name.to_string(), value);
+            }
+        }
+        serde_json::Value::Object(params)
+    }
+}
+
+#[ComplexObject]
+impl Doc {
+    /// Entries of this document matching the filter.
+    async fn entries(&self, ctx: &Context<'_>, filter: Option<EntryFilter>) -> Result<Vec<Entry>> {
+        fetch_entries(ctx, self.doc_id.clone(), filter.unwrap_or_default()).await
+    }
+}
+
+#[ComplexObject]
+impl Entry {
+    /// The entry's content, read from the blob store.
+    async fn content(&self, ctx: &Context<'_>) -> Result<String> {
+        let state = ctx.data::<AppState>()?;
+        get_entry_blob(state.blobs.clone(), self.hash.clone())
+            .await
+            .map_err(|e| Error::new(e.to_string()))
+    }
+}
+
+#[ComplexObject]
+impl Blob {
+    /// The blob's content as UTF-8, or base64 if it is binary.
+    async fn content(&self, ctx: &Context<'_>) -> Result<String> {
+        let state = ctx.data::<AppState>()?;
+        get_blob(state.blobs.clone(), self.hash.clone())
+            .await
+            .map_err(|e| Error::new(e.to_string()))
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// All documents on this node.
+    async fn docs(&self, ctx: &Context<'_>) -> Result<Vec<Doc>> {
+        let state = ctx.data::<AppState>()?;
+        let docs = list_docs(state.docs.clone())
+            .await
+            .map_err(|e| Error::new(e.to_string()))?;
+
+        Ok(docs
+            .into_iter()
+            .map(|(doc_id, capability)| Doc {
+                doc_id,
+                capability: capability_to_string(capability),
+            })
+            .collect())
+    }
+
+    /// A single document by ID.
+    async fn doc(&self, ctx: &Context<'_>, doc_id: String) -> Result<Option<Doc>> {
+        let state = ctx.data::<AppState>()?;
+        let docs = list_docs(state.docs.clone())
+            .await
+            .map_err(|e| Error::new(e.to_string()))?;
+
+        Ok(docs
+            .into_iter()
+            .find(|(id, _)| *id == doc_id)
+            .map(|(doc_id, capability)| Doc {
+                doc_id,
+                capability: capability_to_string(capability),
+            }))
+    }
+
+    /// Entries of a document matching the filter.
+    async fn entries(&self, ctx: &Context<'_>, doc_id: String, filter: Option<EntryFilter>) -> Result<Vec<Entry>> {
+        fetch_entries(ctx, doc_id, filter.unwrap_or_default()).await
+    }
+
+    /// Metadata of a blob by hash.
+    async fn blob(&self, ctx: &Context<'_>, hash: String) -> Result<Blob> {
+        let state = ctx.data::<AppState>()?;
+        let status = status_blob(state.blobs.clone(), hash.clone())
+            .await
+            .map_err(|e| Error::new(e.to_string()))?;
+
+        Ok(Blob { hash, status })
+    }
+
+    /// All author IDs on this node.
+    async fn authors(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+        let state = ctx.data::<AppState>()?;
+        list_authors(state.docs.clone())
+            .await
+            .map_err(|e| Error::new(e.to_string()))
+    }
+
+    /// The default author ID.
+    async fn default_author(&self, ctx: &Context<'_>) -> Result<String> {
+        let state = ctx.data::<AppState>()?;
+        get_default_author(state.docs.clone())
+            .await
+            .map_err(|e| Error::new(e.to_string()))
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Creates a new document.
+    async fn create_doc(&self, ctx: &Context<'_>) -> Result<Doc> {
+        let state = ctx.data::<AppState>()?;
+        require_registered_author(ctx).await?;
+
+        let doc_id = create_doc(state.docs.clone())
+            .await
+            .map_err(|e| Error::new(e.to_string()))?;
+        state.events.publish(NodeEvent::DocCreated { doc_id: doc_id.clone() });
+
+        Ok(Doc {
+            doc_id,
+            capability: capability_to_string(CapabilityKind::Write),
+        })
+    }
+
+    /// Sets an entry in a document and returns the hash of its content.
+    async fn set_entry(
+        &self,
+        ctx: &Context<'_>,
+        doc_id: String,
+        author_id: String,
+        key: String,
+        value: String,
+    ) -> Result<String> {
+        let state = ctx.data::<AppState>()?;
+        require_registered_author(ctx).await?;
+
+        if doc_id.is_empty() || author_id.is_empty() || key.is_empty() || value.is_empty() {
+            return Err(Error::new("doc_id, author_id, key and value cannot be empty"));
+        }
+
+        check_author_authorization(state.docs.clone(), state.cord_client.clone(), &state.authorizations, &doc_id, &author_id)
+            .await
+            .map_err(|e| Error::new(e.to_string()))?;
+
+        set_entry(state.docs.clone(), state.blobs.clone(), doc_id, author_id, key, value)
+            .await
+            .map_err(|e| Error::new(e.to_string()))
+    }
+
+    /// Adds a blob from UTF-8 content.
+    async fn add_blob(&self, ctx: &Context<'_>, content: String) -> Result<AddedBlob> {
+        let state = ctx.data::<AppState>()?;
+        require_registered_author(ctx).await?;
+
+        if content.is_empty() {
+            return Err(Error::new("Content cannot be empty"));
+        }
+
+        let outcome = add_blob_bytes(state.blobs.clone(), content.into_bytes())
+            .await
+            .map_err(|e| Error::new(e.to_string()))?;
+        state.events.publish(NodeEvent::BlobAdded {
+            hash: outcome.hash.to_string(),
+            size: outcome.size,
+        });
+
+        Ok(AddedBlob {
+            hash: outcome.hash.to_string(),
+            format: format!("{:?}", outcome.format),
+            size: outcome.size,
+            tag: outcome.tag.to_string(),
+        })
+    }
+}
+
+async fn fetch_entries(ctx: &Context<'_>, doc_id: String, filter: EntryFilter) -> Result<Vec<Entry>> {
+    let state = ctx.data::<AppState>()?;
+    let entries = get_entries(state.docs.clone(), doc_id, filter.to_query_params())
+        .await
+        .map_err(|e| Error::new(e.to_string()))?;
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| Entry {
+            doc: entry.namespace.doc,
+            key: entry.namespace.key,
+            author: entry.namespace.author,
+            hash: entry.record.hash,
+            len: entry.record.len,
+            timestamp: entry.record.timestamp,
+        })
+        .collect())
+}
+
+// Mutations follow the REST handlers: a read-only replica rejects them, and only a registered
+// author may write
+async fn require_registered_author(ctx: &Context<'_>) -> Result<()> {
+    let state = ctx.data::<AppState>()?;
+    if state.read_only {
+        return Err(Error::new(READ_ONLY_MESSAGE));
+    }
+    let caller_author_id = ctx
+        .data::<Caller>()?
+        .0
+        .clone()
+        .ok_or_else(|| Error::new("Missing author-id header"))?;
+
+    let authors = list_authors(state.docs.clone())
+        .await
+        .map_err(|e| Error::new(e.to_string()))?;
+    if !authors.contains(&caller_author_id) {
+        return Err(Error::new("Only a registered author can perform this action"));
+    }
+
+    Ok(())
+}
+
+fn capability_to_string(capability: CapabilityKind) -> String {
+    match capability {
+        CapabilityKind::Write => "Write".to_string(),
+        CapabilityKind::Read => "Read".to_string(),
+    }
+}
+
+// Handler for executing GraphQL queries and mutations at `POST /graphql`
+pub async fn graphql_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<async_graphql::Request>,
+) -> Result<Json<async_graphql::Response>, (StatusCode, String)> {
+    check_node_id_and_domain_header(&headers)?;
+
+    let caller = Caller(get_author_id_from_headers(&headers).ok());
+    let request = request.data(state).data(caller);
+
+    Ok(Json(schema().execute(request).await))
+}
+
+// Handler for serving the GraphiQL explorer at `GET /graphql`
+pub async fn graphiql_handler() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
@@ -0,0 +1,12 @@
+// Compiles the proto definitions with protox, so building does not require a system `protoc`.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let file_descriptors = protox::compile(["proto/starterkit.proto"], ["proto"])?;
+
+    tonic_build::configure()
+        .build_client(true)
+        .build_server(true)
+        .compile_fds(file_descriptors)?;
+
+    println!("cargo:rerun-if-changed=proto/starterkit.proto");
+    Ok(())
+}
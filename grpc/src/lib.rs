@@ -0,0 +1,7 @@
+// `tonic::Status` is large, but it is the error type the generated service traits require
+#![allow(clippy::result_large_err)]
+
+pub mod proto {
+    tonic::include_proto!("starterkit.v1");
+}
+pub mod service;
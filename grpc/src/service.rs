@@ -0,0 +1,655 @@
+use crate::proto::{
+    self,
+    authors_server::{Authors, AuthorsServer},
+    blobs_server::{Blobs, BlobsServer},
+    docs_server::{Docs, DocsServer},
+};
+use starter_kit_core::{authors::*, blobs::*, docs::*};
+use helpers::{state::{AppState, READ_ONLY_MESSAGE}, utils::get_author_id_from_headers, events::NodeEvent};
+use gateway::access_control::check_node_id_and_domain_header;
+
+use axum::http::StatusCode;
+use futures::{Stream, StreamExt};
+use iroh_blobs::{BlobFormat, get::db::DownloadProgress as BytesDownloadProgress, util::Tag};
+use iroh_docs::CapabilityKind;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use tonic::{Request, Response, Status, transport::Server};
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send + 'static>>;
+
+// Default page size for ListBlobs when the request leaves `limit` at 0
+const DEFAULT_LIST_BLOBS_LIMIT: u64 = 100;
+
+/// Implements the gRPC services on top of the same core functions as the HTTP handlers.
+#[derive(Clone)]
+pub struct GrpcService {
+    state: AppState,
+}
+
+impl GrpcService {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    // Applies the gateway access control to the request metadata
+    fn check_access<T>(&self, request: &Request<T>) -> Result<(), Status> {
+        let headers = request.metadata().clone().into_headers();
+        check_node_id_and_domain_header(&headers).map_err(to_status)
+    }
+
+    // Only a registered author can perform write operations, and none on a read-only replica
+    async fn check_registered_author<T>(&self, request: &Request<T>) -> Result<(), Status> {
+        if self.state.read_only {
+            return Err(Status::permission_denied(READ_ONLY_MESSAGE));
+        }
+        let headers = request.metadata().clone().into_headers();
+        let caller_author_id = get_author_id_from_headers(&headers).map_err(to_status)?;
+
+        let authors = list_authors(self.state.docs.clone())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        if !authors.contains(&caller_author_id) {
+            return Err(Status::permission_denied("Only a registered author can perform this action"));
+        }
+
+        Ok(())
+    }
+
+    // Only the default author can manage authors, and not on a read-only replica
+    async fn check_default_author<T>(&self, request: &Request<T>) -> Result<(), Status> {
+        if self.state.read_only {
+            return Err(Status::permission_denied(READ_ONLY_MESSAGE));
+        }
+        let headers = request.metadata().clone().into_headers();
+        let caller_author_id = get_author_id_from_headers(&headers).map_err(to_status)?;
+
+        let default_author = get_default_author(self.state.docs.clone())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        if caller_author_id != default_author {
+            return Err(Status::permission_denied("Only the default author can perform this action"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Serves the Docs, Blobs and Authors gRPC services on `addr` until `shutdown` resolves.
+pub async fn serve(
+    state: AppState,
+    addr: SocketAddr,
+    shutdown: impl std::future::Future<Output = ()>,
+) -> Result<(), tonic::transport::Error> {
+    let service = GrpcService::new(state);
+
+    Server::builder()
+        .add_service(DocsServer::new(service.clone()))
+        .add_service(BlobsServer::new(service.clone()))
+        .add_service(AuthorsServer::new(service))
+        .serve_with_shutdown(addr, shutdown)
+        .await
+}
+
+// Maps the HTTP status used by the shared helpers to the matching gRPC status
+fn to_status((code, message): (StatusCode, String)) -> Status {
+    match code {
+        StatusCode::BAD_REQUEST => Status::invalid_argument(message),
+        StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+        StatusCode::FORBIDDEN => Status::permission_denied(message),
+        StatusCode::NOT_FOUND => Status::not_found(message),
+        _ => Status::internal(message),
+    }
+}
+
+fn require(value: &str, name: &str) -> Result<(), Status> {
+    if value.is_empty() {
+        return Err(Status::invalid_argument(format!("{} cannot be empty", name)));
+    }
+    Ok(())
+}
+
+fn entry_to_proto(entry: EntryDetails) -> proto::Entry {
+    proto::Entry {
+        doc: entry.namespace.doc,
+        key: entry.namespace.key,
+        author: entry.namespace.author,
+        hash: entry.record.hash,
+        len: entry.record.len,
+        timestamp: entry.record.timestamp,
+    }
+}
+
+fn doc_event_to_proto(event: DocEvent) -> proto::DocEvent {
+    use proto::doc_event::Event;
+
+    let event = match event {
+        DocEvent::InsertLocal { entry } => Event::InsertLocal(entry_to_proto(entry)),
+        DocEvent::InsertRemote { from, entry, content_status } => Event::InsertRemote(proto::InsertRemote {
+            from,
+            entry: Some(entry_to_proto(entry)),
+            content_status,
+        }),
+        DocEvent::ContentReady { hash } => Event::ContentReady(hash),
+        DocEvent::PendingContentReady => Event::PendingContentReady(proto::PendingContentReady {}),
+        DocEvent::NeighborUp { peer } => Event::NeighborUp(peer),
+        DocEvent::NeighborDown { peer } => Event::NeighborDown(peer),
+        DocEvent::SyncFinished { peer, entries_received, entries_sent, error } => {
+            Event::SyncFinished(proto::SyncFinished {
+                peer,
+                entries_received: entries_received as u64,
+                entries_sent: entries_sent as u64,
+                error,
+            })
+        }
+    };
+
+    proto::DocEvent { event: Some(event) }
+}
+
+fn download_progress_to_proto(progress: BytesDownloadProgress) -> proto::DownloadProgress {
+    let (kind, offset, size, error) = match progress {
+        BytesDownloadProgress::InitialState(_) => ("initial_state", 0, 0, None),
+        BytesDownloadProgress::FoundLocal { size, .. } => ("found_local", 0, size.value(), None),
+        BytesDownloadProgress::Connected => ("connected", 0, 0, None),
+        BytesDownloadProgress::Found { size, .. } => ("found", 0, size, None),
+        BytesDownloadProgress::FoundHashSeq { .. } => ("found_hash_seq", 0, 0, None),
+        BytesDownloadProgress::Progress { offset, .. } => ("progress", offset, 0, None),
+        BytesDownloadProgress::Done { .. } => ("done", 0, 0, None),
+        BytesDownloadProgress::AllDone(stats) => ("all_done", stats.bytes_read, 0, None),
+        BytesDownloadProgress::Abort(e) => ("abort", 0, 0, Some(e.to_string())),
+    };
+
+    proto::DownloadProgress {
+        kind: kind.to_string(),
+        offset,
+        size,
+        error,
+    }
+}
+
+fn format_to_string(format: BlobFormat) -> String {
+    match format {
+        BlobFormat::Raw => "Raw".to_string(),
+        BlobFormat::HashSeq => "HashSeq".to_string(),
+    }
+}
+
+#[tonic::async_trait]
+impl Docs for GrpcService {
+    type SubscribeStream = ResponseStream<proto::DocEvent>;
+
+    async fn create_doc(
+        &self,
+        request: Request<proto::CreateDocRequest>,
+    ) -> Result<Response<proto::CreateDocResponse>, Status> {
+        self.check_access(&request)?;
+        self.check_registered_author(&request).await?;
+
+        let doc_id = create_doc(self.state.docs.clone())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        self.state.events.publish(NodeEvent::DocCreated { doc_id: doc_id.clone() });
+
+        Ok(Response::new(proto::CreateDocResponse { doc_id }))
+    }
+
+    async fn list_docs(
+        &self,
+        request: Request<proto::ListDocsRequest>,
+    ) -> Result<Response<proto::ListDocsResponse>, Status> {
+        self.check_access(&request)?;
+
+        let docs = list_docs(self.state.docs.clone())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let docs = docs
+            .into_iter()
+            .map(|(doc_id, capability)| proto::DocInfo {
+                doc_id,
+                capability: match capability {
+                    CapabilityKind::Write => "Write".to_string(),
+                    CapabilityKind::Read => "Read".to_string(),
+                },
+            })
+            .collect();
+
+        Ok(Response::new(proto::ListDocsResponse { docs }))
+    }
+
+    async fn drop_doc(
+        &self,
+        request: Request<proto::DropDocRequest>,
+    ) -> Result<Response<proto::DropDocResponse>, Status> {
+        self.check_access(&request)?;
+        self.check_registered_author(&request).await?;
+        self.state.approvals.check_direct().map_err(Status::permission_denied)?;
+
+        let doc_id = request.into_inner().doc_id;
+        require(&doc_id, "doc_id")?;
+
+        drop_doc(self.state.docs.clone(), doc_id.clone())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        self.state.events.publish(NodeEvent::DocDropped { doc_id });
+
+        Ok(Response::new(proto::DropDocResponse {}))
+    }
+
+    async fn join_doc(
+        &self,
+        request: Request<proto::JoinDocRequest>,
+    ) -> Result<Response<proto::JoinDocResponse>, Status> {
+        self.check_access(&request)?;
+
+        let request = request.into_inner();
+        require(&request.ticket, "ticket")?;
+
+        let doc_id = join_doc(self.state.docs.clone(), request.ticket, request.sync_prefixes)
+            .await
+            .map_err(|e| match e {
+                DocError::InvalidSyncPrefix => Status::invalid_argument(e.to_string()),
+                e => Status::internal(e.to_string()),
+            })?;
+        self.state.events.publish(NodeEvent::DocJoined { doc_id: doc_id.clone() });
+
+        Ok(Response::new(proto::JoinDocResponse { doc_id }))
+    }
+
+    async fn set_entry(
+        &self,
+        request: Request<proto::SetEntryRequest>,
+    ) -> Result<Response<proto::SetEntryResponse>, Status> {
+        self.check_access(&request)?;
+        self.check_registered_author(&request).await?;
+
+        let payload = request.into_inner();
+        require(&payload.doc_id, "doc_id")?;
+        require(&payload.author_id, "author_id")?;
+        require(&payload.key, "key")?;
+        require(&payload.value, "value")?;
+
+        check_author_authorization(
+            self.state.docs.clone(),
+            self.state.cord_client.clone(),
+            &self.state.authorizations,
+            &payload.doc_id,
+            &payload.author_id,
+        )
+        .await
+        .map_err(|e| match e {
+            DocError::AuthorHasNoLinkedDid | DocError::AuthorNotAuthorized => Status::permission_denied(e.to_string()),
+            _ => Status::internal(e.to_string()),
+        })?;
+
+        let hash = set_entry(
+            self.state.docs.clone(),
+            self.state.blobs.clone(),
+            payload.doc_id,
+            payload.author_id,
+            payload.key,
+            payload.value,
+        )
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(proto::SetEntryResponse { hash }))
+    }
+
+    async fn get_entry(
+        &self,
+        request: Request<proto::GetEntryRequest>,
+    ) -> Result<Response<proto::GetEntryResponse>, Status> {
+        self.check_access(&request)?;
+
+        let payload = request.into_inner();
+        require(&payload.doc_id, "doc_id")?;
+        require(&payload.author_id, "author_id")?;
+        require(&payload.key, "key")?;
+
+        let entry = get_entry(
+            self.state.docs.clone(),
+            payload.doc_id,
+            payload.author_id,
+            payload.key,
+            payload.include_empty,
+        )
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(proto::GetEntryResponse {
+            entry: entry.map(entry_to_proto),
+        }))
+    }
+
+    async fn get_entries(
+        &self,
+        request: Request<proto::GetEntriesRequest>,
+    ) -> Result<Response<proto::GetEntriesResponse>, Status> {
+        self.check_access(&request)?;
+
+        let payload = request.into_inner();
+        require(&payload.doc_id, "doc_id")?;
+
+        // Same shape as the `query_params` of the HTTP handler
+        let mut query_params = serde_json::Map::new();
+        if let Some(author_id) = payload.author_id {
+            query_params.insert("author_id".to_string(), author_id.into());
+        }
+        if let Some(key) = payload.key {
+            query_params.insert("key".to_string(), key.into());
+        }
+        if let Some(key_prefix) = payload.key_prefix {
+            query_params.insert("key_prefix".to_string(), key_prefix.into());
+        }
+        if let Some(limit) = payload.limit {
+            query_params.insert("limit".to_string(), limit.into());
+        }
+        if let Some(offset) = payload.offset {
+            query_params.insert("offset".to_string(), offset.into());
+        }
+        if payload.include_empty {
+            query_params.insert("include_empty".to_string(), true.into());
+        }
+        if let Some(sort_by) = payload.sort_by {
+            query_params.insert("sort_by".to_string(), sort_by.into());
+        }
+        if let Some(sort_direction) = payload.sort_direction {
+            query_params.insert("sort_direction".to_string(), sort_direction.into());
+        }
+
+        let entries = get_entries(
+            self.state.docs.clone(),
+            payload.doc_id,
+            serde_json::Value::Object(query_params),
+        )
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(proto::GetEntriesResponse {
+            entries: entries.into_iter().map(entry_to_proto).collect(),
+        }))
+    }
+
+    async fn delete_entry(
+        &self,
+        request: Request<proto::DeleteEntryRequest>,
+    ) -> Result<Response<proto::DeleteEntryResponse>, Status> {
+        self.check_access(&request)?;
+        self.check_registered_author(&request).await?;
+
+        let payload = request.into_inner();
+        require(&payload.doc_id, "doc_id")?;
+        require(&payload.author_id, "author_id")?;
+        require(&payload.key, "key")?;
+
+        let deleted = delete_entry(self.state.docs.clone(), payload.doc_id, payload.author_id, payload.key)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(proto::DeleteEntryResponse { deleted: deleted as u64 }))
+    }
+
+    async fn subscribe(
+        &self,
+        request: Request<proto::SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        self.check_access(&request)?;
+
+        let doc_id = request.into_inner().doc_id;
+        require(&doc_id, "doc_id")?;
+
+        let events = subscribe_doc(self.state.docs.clone(), doc_id)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let stream = events.map(|event| {
+            event
+                .map(doc_event_to_proto)
+                .map_err(|e| Status::internal(e.to_string()))
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+#[tonic::async_trait]
+impl Blobs for GrpcService {
+    type DownloadBlobStream = ResponseStream<proto::DownloadProgress>;
+
+    async fn add_blob(
+        &self,
+        request: Request<proto::AddBlobRequest>,
+    ) -> Result<Response<proto::AddBlobResponse>, Status> {
+        self.check_access(&request)?;
+        self.check_registered_author(&request).await?;
+
+        let payload = request.into_inner();
+        if payload.content.is_empty() {
+            return Err(Status::invalid_argument("Content cannot be empty"));
+        }
+
+        let outcome = match payload.name {
+            Some(name) => add_blob_named(self.state.blobs.clone(), payload.content, Tag::from(name)).await,
+            None => add_blob_bytes(self.state.blobs.clone(), payload.content).await,
+        }
+        .map_err(|e| Status::internal(e.to_string()))?;
+
+        self.state.events.publish(NodeEvent::BlobAdded {
+            hash: outcome.hash.to_string(),
+            size: outcome.size,
+        });
+
+        Ok(Response::new(proto::AddBlobResponse {
+            hash: outcome.hash.to_string(),
+            format: format_to_string(outcome.format),
+            size: outcome.size,
+            tag: outcome.tag.to_string(),
+        }))
+    }
+
+    async fn get_blob(
+        &self,
+        request: Request<proto::GetBlobRequest>,
+    ) -> Result<Response<proto::GetBlobResponse>, Status> {
+        self.check_access(&request)?;
+
+        let hash = request.into_inner().hash;
+        require(&hash, "hash")?;
+
+        let content = get_blob(self.state.blobs.clone(), hash)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(proto::GetBlobResponse { content }))
+    }
+
+    async fn status_blob(
+        &self,
+        request: Request<proto::StatusBlobRequest>,
+    ) -> Result<Response<proto::StatusBlobResponse>, Status> {
+        self.check_access(&request)?;
+
+        let hash = request.into_inner().hash;
+        require(&hash, "hash")?;
+
+        let status = status_blob(self.state.blobs.clone(), hash)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(proto::StatusBlobResponse { status }))
+    }
+
+    async fn list_blobs(
+        &self,
+        request: Request<proto::ListBlobsRequest>,
+    ) -> Result<Response<proto::ListBlobsResponse>, Status> {
+        self.check_access(&request)?;
+
+        let payload = request.into_inner();
+        let limit = if payload.limit == 0 { DEFAULT_LIST_BLOBS_LIMIT } else { payload.limit };
+
+        let (blobs, total) = list_blobs_with_total(self.state.blobs.clone(), payload.offset as usize, limit as usize)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(proto::ListBlobsResponse {
+            blobs: blobs
+                .into_iter()
+                .map(|blob| proto::BlobInfo {
+                    path: blob.path,
+                    hash: blob.hash.to_string(),
+                    size: blob.size,
+                })
+                .collect(),
+            total: total as u64,
+        }))
+    }
+
+    async fn list_tags(
+        &self,
+        request: Request<proto::ListTagsRequest>,
+    ) -> Result<Response<proto::ListTagsResponse>, Status> {
+        self.check_access(&request)?;
+
+        let tags = list_tags(self.state.blobs.clone())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(proto::ListTagsResponse {
+            tags: tags
+                .into_iter()
+                .map(|tag_info| proto::TagInfo {
+                    name: tag_info.name.to_string(),
+                    format: format_to_string(tag_info.format),
+                    hash: tag_info.hash.to_string(),
+                })
+                .collect(),
+        }))
+    }
+
+    async fn delete_tag(
+        &self,
+        request: Request<proto::DeleteTagRequest>,
+    ) -> Result<Response<proto::DeleteTagResponse>, Status> {
+        self.check_access(&request)?;
+        self.check_registered_author(&request).await?;
+        self.state.approvals.check_direct().map_err(Status::permission_denied)?;
+
+        let tag = request.into_inner().tag;
+        require(&tag, "tag")?;
+
+        delete_tag(self.state.blobs.clone(), tag.as_bytes())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        self.state.events.publish(NodeEvent::TagDeleted { tag });
+
+        Ok(Response::new(proto::DeleteTagResponse {}))
+    }
+
+    async fn download_blob(
+        &self,
+        request: Request<proto::DownloadBlobRequest>,
+    ) -> Result<Response<Self::DownloadBlobStream>, Status> {
+        self.check_access(&request)?;
+
+        let payload = request.into_inner();
+        require(&payload.hash, "hash")?;
+        require(&payload.node_id, "node_id")?;
+
+        let progress = download_blob_progress(self.state.blobs.clone(), payload.hash, payload.node_id)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let stream = progress.map(|event| {
+            event
+                .map(download_progress_to_proto)
+                .map_err(|e| Status::internal(e.to_string()))
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+#[tonic::async_trait]
+impl Authors for GrpcService {
+    async fn list_authors(
+        &self,
+        request: Request<proto::ListAuthorsRequest>,
+    ) -> Result<Response<proto::ListAuthorsResponse>, Status> {
+        self.check_access(&request)?;
+
+        let authors = list_authors(self.state.docs.clone())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(proto::ListAuthorsResponse { authors }))
+    }
+
+    async fn get_default_author(
+        &self,
+        request: Request<proto::GetDefaultAuthorRequest>,
+    ) -> Result<Response<proto::GetDefaultAuthorResponse>, Status> {
+        self.check_access(&request)?;
+
+        let author_id = get_default_author(self.state.docs.clone())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(proto::GetDefaultAuthorResponse { author_id }))
+    }
+
+    async fn create_author(
+        &self,
+        request: Request<proto::CreateAuthorRequest>,
+    ) -> Result<Response<proto::CreateAuthorResponse>, Status> {
+        self.check_access(&request)?;
+        self.check_default_author(&request).await?;
+
+        let author_id = create_author(
+            self.state.docs.clone(),
+            self.state.chain.clone(),
+        )
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+        self.state.events.publish(NodeEvent::AuthorCreated { author_id: author_id.clone() });
+
+        Ok(Response::new(proto::CreateAuthorResponse { author_id }))
+    }
+
+    async fn delete_author(
+        &self,
+        request: Request<proto::DeleteAuthorRequest>,
+    ) -> Result<Response<proto::DeleteAuthorResponse>, Status> {
+        self.check_access(&request)?;
+        self.check_default_author(&request).await?;
+        self.state.approvals.check_direct().map_err(Status::permission_denied)?;
+
+        let author_id = request.into_inner().author_id;
+        require(&author_id, "author_id")?;
+
+        delete_author(self.state.docs.clone(), author_id.clone())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        self.state.events.publish(NodeEvent::AuthorDeleted { author_id });
+
+        Ok(Response::new(proto::DeleteAuthorResponse {}))
+    }
+
+    async fn verify_author(
+        &self,
+        request: Request<proto::VerifyAuthorRequest>,
+    ) -> Result<Response<proto::VerifyAuthorResponse>, Status> {
+        self.check_access(&request)?;
+
+        let author_id = request.into_inner().author_id;
+        require(&author_id, "author_id")?;
+
+        let is_valid = verify_author(self.state.docs.clone(), author_id)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(proto::VerifyAuthorResponse { is_valid }))
+    }
+}
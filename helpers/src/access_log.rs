@@ -0,0 +1,279 @@
+use crate::{cli::CliArgs, utils::decode_doc_id};
+
+use anyhow::{anyhow, Result};
+use iroh_blobs::Hash;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Reads are appended to this file in the data directory, one JSON record per line. As in the
+// keystore audit log, each record carries the hash of the line before it, so removing or editing
+// a record breaks the chain and `AccessLog::verify` reports where.
+const ACCESS_LOG_FILE: &str = "access.log";
+
+/// The routes whose successful requests are recorded, by route template. They return the
+/// content of entries or blobs.
+pub const ACCESS_LOGGED_ROUTES: &[&str] = &[
+    "/docs/get-entry",
+    "/docs/get-entries",
+    "/docs/get-resolved-entry",
+    "/docs/find-by-field",
+    "/docs/:id/entries/*key",
+    "/docs/:id/entries/*key/attachments",
+    "/docs/:id/entries-at",
+    "/docs/:id/trash",
+    "/docs/:id/search",
+    "/docs/:id/archive",
+    "/docs/:id/export",
+    "/sharded/get-entry",
+    "/sharded/get-entries",
+    "/docs/get-entry-blob",
+    "/blobs/get-blob",
+    "/blobs/:hash",
+    "/blobs/:hash/download",
+    "/blobs/:hash/raw",
+    "/blobs/export-blob-to-file",
+    "/gw/:hash",
+    "/gw/:hash/",
+    "/gw/:hash/*path",
+];
+
+/// Which reads are recorded, see `--access-log`, `--access-log-doc` and
+/// `--access-log-sample-rate`.
+#[derive(Debug, Clone, Default)]
+pub struct AccessLogSettings {
+    /// Record reads of every document and blob, sampled at `sample_rate`.
+    pub all: bool,
+    /// Documents whose every read is recorded.
+    pub docs: BTreeSet<String>,
+    pub sample_rate: f64,
+}
+
+impl AccessLogSettings {
+    /// The settings of the `--access-log*` flags of `serve`.
+    pub fn from_args(args: &CliArgs) -> Result<Self, String> {
+        if !(args.access_log_sample_rate > 0.0 && args.access_log_sample_rate <= 1.0) {
+            return Err("❌ --access-log-sample-rate must be above 0 and at most 1.".to_string());
+        }
+        let mut docs = BTreeSet::new();
+        for doc_id in &args.access_log_doc {
+            decode_doc_id(doc_id).map_err(|_| format!("❌ --access-log-doc {} is not a document ID.", doc_id))?;
+            docs.insert(doc_id.clone());
+        }
+        Ok(Self { all: args.access_log, docs, sample_rate: args.access_log_sample_rate })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.all || !self.docs.is_empty()
+    }
+
+    /// The rate a read of `doc_id`, or of a blob when `None`, is recorded at. `None` if it is
+    /// not recorded at all.
+    pub fn rate_for(&self, doc_id: Option<&str>) -> Option<f64> {
+        if doc_id.is_some_and(|doc_id| self.docs.contains(doc_id)) {
+            Some(1.0)
+        } else if self.all {
+            Some(self.sample_rate)
+        } else {
+            None
+        }
+    }
+}
+
+/// A read, as seen by the middleware that records it.
+#[derive(Debug, Clone)]
+pub struct AccessEvent {
+    /// `author:<id>`, `node:<id>` or `origin:<domain>`, as in the keystore audit log.
+    pub caller: String,
+    /// The tenant of the API key the request carried.
+    pub tenant: Option<String>,
+    /// Route template, such as `/docs/get-entry`.
+    pub route: String,
+    pub doc_id: Option<String>,
+    pub key: Option<String>,
+    pub hash: Option<String>,
+    /// The rate the read was sampled at, 1 when every such read is recorded.
+    pub sample_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessRecord {
+    /// Position in the log, starting at 1.
+    pub seq: u64,
+    /// Unix time in seconds.
+    pub timestamp: u64,
+    pub caller: String,
+    pub tenant: Option<String>,
+    pub route: String,
+    pub doc_id: Option<String>,
+    pub key: Option<String>,
+    pub hash: Option<String>,
+    pub sample_rate: f64,
+    /// Hex BLAKE3 hash of the previous line, or all zeros for the first record.
+    pub prev_hash: String,
+}
+
+/// Narrows `AccessLog::query`. Unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct AccessFilter {
+    pub doc_id: Option<String>,
+    /// Only records of keys starting with this.
+    pub key_prefix: Option<String>,
+    pub hash: Option<String>,
+    pub caller: Option<String>,
+    pub tenant: Option<String>,
+    /// Only records at or after this unix time.
+    pub since: Option<u64>,
+    /// Only records at or before this unix time.
+    pub until: Option<u64>,
+}
+
+impl AccessFilter {
+    fn matches(&self, record: &AccessRecord) -> bool {
+        self.doc_id.as_ref().is_none_or(|doc_id| record.doc_id.as_ref() == Some(doc_id))
+            && self.key_prefix.as_ref().is_none_or(|prefix| record.key.as_ref().is_some_and(|key| key.starts_with(prefix.as_str())))
+            && self.hash.as_ref().is_none_or(|hash| record.hash.as_ref() == Some(hash))
+            && self.caller.as_ref().is_none_or(|caller| *caller == record.caller)
+            && self.tenant.as_ref().is_none_or(|tenant| record.tenant.as_ref() == Some(tenant))
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp <= until)
+    }
+}
+
+struct Tail {
+    file: File,
+    seq: u64,
+    last_hash: String,
+}
+
+/// The append-only log of entry and blob reads, shared through `AppState`.
+///
+/// Lines are handed to the OS as they are written but, unlike the keystore audit log, not synced
+/// to disk one by one, as reads are far more frequent than signatures.
+#[derive(Clone)]
+pub struct AccessLog {
+    settings: AccessLogSettings,
+    path: PathBuf,
+    /// `None` while no reads are recorded. Records of earlier runs can still be queried.
+    tail: Option<Arc<Mutex<Tail>>>,
+}
+
+impl AccessLog {
+    /// Opens the access log in the data directory at `data_dir`, creating it if reads are
+    /// recorded.
+    pub fn open(data_dir: &Path, settings: AccessLogSettings) -> Result<Self> {
+        let path = data_dir.join(ACCESS_LOG_FILE);
+        let tail = if settings.is_enabled() {
+            let (seq, last_hash) = read_chain(&path)?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| anyhow!("❌ Failed to open access log {:?}: {e}", path))?;
+            Some(Arc::new(Mutex::new(Tail { file, seq, last_hash })))
+        } else {
+            None
+        };
+        Ok(Self { settings, path, tail })
+    }
+
+    pub fn settings(&self) -> &AccessLogSettings {
+        &self.settings
+    }
+
+    /// Whether a read on `route` of `doc_id`, or of a blob when `None`, is to be recorded,
+    /// drawing the sample. `Some` holds the rate it was drawn at.
+    pub fn sample(&self, route: &str, doc_id: Option<&str>) -> Option<f64> {
+        self.tail.as_ref()?;
+        if !ACCESS_LOGGED_ROUTES.contains(&route) {
+            return None;
+        }
+        let rate = self.settings.rate_for(doc_id)?;
+        (rate >= 1.0 || rand::random::<f64>() < rate).then_some(rate)
+    }
+
+    /// Appends `event`. Does nothing while no reads are recorded.
+    pub fn record(&self, event: AccessEvent) -> Result<()> {
+        let Some(tail) = &self.tail else {
+            return Ok(());
+        };
+        let mut tail = tail.lock().map_err(|_| anyhow!("❌ Access log lock poisoned"))?;
+        let record = AccessRecord {
+            seq: tail.seq + 1,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            caller: event.caller,
+            tenant: event.tenant,
+            route: event.route,
+            doc_id: event.doc_id,
+            key: event.key,
+            hash: event.hash,
+            sample_rate: event.sample_rate,
+            prev_hash: tail.last_hash.clone(),
+        };
+        let line = serde_json::to_string(&record)?;
+        writeln!(tail.file, "{}", line).map_err(|e| anyhow!("❌ Failed to write access log {:?}: {e}", self.path))?;
+        tail.seq = record.seq;
+        tail.last_hash = Hash::new(line.as_bytes()).to_hex().to_string();
+        Ok(())
+    }
+
+    /// Returns the records matching `filter`, oldest first.
+    pub fn query(&self, filter: &AccessFilter) -> Result<Vec<AccessRecord>> {
+        // hold the lock so a half-written line is never read
+        let _tail = match &self.tail {
+            Some(tail) => Some(tail.lock().map_err(|_| anyhow!("❌ Access log lock poisoned"))?),
+            None => None,
+        };
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut records = Vec::new();
+        for line in lines(&self.path)? {
+            let record: AccessRecord = serde_json::from_str(&line?)?;
+            if filter.matches(&record) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Checks that no record was removed, reordered or edited. Returns the number of records.
+    pub fn verify(&self) -> Result<u64> {
+        let _tail = match &self.tail {
+            Some(tail) => Some(tail.lock().map_err(|_| anyhow!("❌ Access log lock poisoned"))?),
+            None => None,
+        };
+        read_chain(&self.path).map(|(seq, _)| seq)
+    }
+}
+
+fn lines(path: &Path) -> Result<impl Iterator<Item = std::io::Result<String>>> {
+    let file = File::open(path).map_err(|e| anyhow!("❌ Failed to read access log {:?}: {e}", path))?;
+    Ok(BufReader::new(file).lines())
+}
+
+// Walks the whole chain and returns the last sequence number and line hash
+fn read_chain(path: &Path) -> Result<(u64, String)> {
+    let mut seq = 0;
+    let mut last_hash = "0".repeat(64);
+    if !path.exists() {
+        return Ok((seq, last_hash));
+    }
+    for line in lines(path)? {
+        let line = line?;
+        let record: AccessRecord = serde_json::from_str(&line)
+            .map_err(|e| anyhow!("❌ Access log {:?} has an invalid record after #{}: {e}", path, seq))?;
+        if record.seq != seq + 1 || record.prev_hash != last_hash {
+            return Err(anyhow!("❌ Access log {:?} was tampered with: the chain breaks at record #{}", path, record.seq));
+        }
+        seq = record.seq;
+        last_hash = Hash::new(line.as_bytes()).to_hex().to_string();
+    }
+    Ok((seq, last_hash))
+}
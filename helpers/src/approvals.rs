@@ -0,0 +1,452 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use crate::utils::verify_account_signature;
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::{path::PathBuf, sync::{Arc, RwLock}, time::{SystemTime, UNIX_EPOCH}};
+use tokio::fs;
+
+// File the pending operations are persisted to, inside the node's data directory.
+const PENDING_OPERATIONS_FILE: &str = "pending_operations.json";
+
+/// Seconds a proposed operation waits for approval before it expires and has to be proposed again.
+pub const PENDING_OPERATION_TTL: u64 = 7 * 24 * 60 * 60;
+
+/// Operations that need the approval of M of N admins once an approval policy is configured.
+pub const GUARDED_OPERATIONS: [&str; 5] = ["drop_doc", "drop_sharded_doc", "delete_author", "delete_tag", "empty_trash"];
+
+/// A destructive operation waiting for admin approval.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum GuardedOperation {
+    DropDoc { doc_id: String },
+    /// Dropping a sharded document with its manifest and every shard.
+    DropShardedDoc { doc_id: String },
+    DeleteAuthor { author_id: String },
+    /// Deleting a blob tag, after which the blob is purged by garbage collection.
+    DeleteTag { tag_name: String },
+    /// Purging the entries `author_id` deleted in a document, or only the one at `key`.
+    EmptyTrash {
+        doc_id: String,
+        author_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key: Option<String>,
+    },
+}
+
+impl GuardedOperation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::DropDoc { .. } => "drop_doc",
+            Self::DropShardedDoc { .. } => "drop_sharded_doc",
+            Self::DeleteAuthor { .. } => "delete_author",
+            Self::DeleteTag { .. } => "delete_tag",
+            Self::EmptyTrash { .. } => "empty_trash",
+        }
+    }
+
+    fn target(&self) -> String {
+        match self {
+            Self::DropDoc { doc_id } | Self::DropShardedDoc { doc_id } => doc_id.clone(),
+            Self::DeleteAuthor { author_id } => author_id.clone(),
+            Self::DeleteTag { tag_name } => tag_name.clone(),
+            Self::EmptyTrash { doc_id, author_id, key: None } => format!("{}:{}", doc_id, author_id),
+            Self::EmptyTrash { doc_id, author_id, key: Some(key) } => format!("{}:{}:{}", doc_id, author_id, key),
+        }
+    }
+}
+
+/// An admin's signature over `PendingOperation::message`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Approval {
+    /// SS58 public key of the admin.
+    pub admin: String,
+    /// 0x-prefixed hex signature.
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingOperation {
+    pub id: String,
+    #[serde(flatten)]
+    pub operation: GuardedOperation,
+    /// Author that proposed the operation.
+    pub proposed_by: String,
+    pub created_at: u64,
+    pub approvals: Vec<Approval>,
+    /// The operation reached its threshold and is running. Not persisted, so an operation that
+    /// was running when the node stopped can be approved again to retry it.
+    #[serde(skip)]
+    pub executing: bool,
+}
+
+impl PendingOperation {
+    /// The message each admin signs to approve this operation, e.g.
+    /// `starter-kit-approval:<id>:drop_doc:<doc_id>`, or `...:empty_trash:<doc_id>:<author_id>[:<key>]`.
+    /// The ID makes every approval single-use.
+    pub fn message(&self) -> String {
+        format!("starter-kit-approval:{}:{}:{}", self.id, self.operation.name(), self.operation.target())
+    }
+
+    /// Whether the operation waited longer than `PENDING_OPERATION_TTL` at unix time `now`.
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.created_at + PENDING_OPERATION_TTL
+    }
+}
+
+fn now() -> anyhow::Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+/// The admin keys allowed to approve guarded operations, and how many of them must.
+#[derive(Debug, Clone)]
+pub struct ApprovalPolicy {
+    admins: Vec<AccountId32>,
+    threshold: usize,
+}
+
+impl ApprovalPolicy {
+    /// Builds a policy from SS58 admin keys (sr25519 or ed25519) and the number of approvals
+    /// required, which must be between 1 and the number of admins.
+    pub fn new(admins: &[String], threshold: usize) -> anyhow::Result<Self> {
+        let mut keys = Vec::with_capacity(admins.len());
+        for admin in admins {
+            let key = AccountId32::from_ss58check(admin)
+                .map_err(|e| anyhow!("Invalid admin key '{}': {:?}. Use an SS58 public key.", admin, e))?;
+            if keys.contains(&key) {
+                return Err(anyhow!("Admin key '{}' is listed twice", admin));
+            }
+            keys.push(key);
+        }
+        if threshold == 0 || threshold > keys.len() {
+            return Err(anyhow!(
+                "The approval threshold must be between 1 and the number of admin keys ({}), got {}",
+                keys.len(),
+                threshold
+            ));
+        }
+        Ok(Self { admins: keys, threshold })
+    }
+
+    /// The policy set by `--approval-admin` and `--approval-threshold`, if any. The threshold
+    /// defaults to all admins.
+    pub fn from_settings(admins: &[String], threshold: Option<usize>) -> anyhow::Result<Option<Self>> {
+        if admins.is_empty() {
+            return match threshold {
+                Some(_) => Err(anyhow!("--approval-threshold needs at least one --approval-admin")),
+                None => Ok(None),
+            };
+        }
+        Self::new(admins, threshold.unwrap_or(admins.len())).map(Some)
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    pub fn admin_count(&self) -> usize {
+        self.admins.len()
+    }
+}
+
+/// Operations waiting for admin approval, shared through `AppState` and persisted as JSON.
+/// Without a policy (the default) nothing needs approval.
+#[derive(Clone, Default)]
+pub struct ApprovalRegistry {
+    policy: Option<ApprovalPolicy>,
+    pending: Arc<RwLock<Vec<PendingOperation>>>,
+    storage_path: Option<PathBuf>,
+}
+
+impl ApprovalRegistry {
+    /// Loads the pending operations from the data directory at `path`.
+    pub async fn load(path: &str, policy: Option<ApprovalPolicy>) -> anyhow::Result<Self> {
+        let storage_path = PathBuf::from(path).join(PENDING_OPERATIONS_FILE);
+
+        let pending = if fs::try_exists(&storage_path).await? {
+            let content = fs::read_to_string(&storage_path).await?;
+            serde_json::from_str(&content)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            policy,
+            pending: Arc::new(RwLock::new(pending)),
+            storage_path: Some(storage_path),
+        })
+    }
+
+    pub fn policy(&self) -> Option<&ApprovalPolicy> {
+        self.policy.as_ref()
+    }
+
+    /// Fails if guarded operations must go through approval instead of running directly.
+    pub fn check_direct(&self) -> Result<(), String> {
+        match &self.policy {
+            None => Ok(()),
+            Some(policy) => Err(format!(
+                "❌ This operation needs the approval of {} of {} admins. Propose it with POST /approvals/propose-operation.",
+                policy.threshold,
+                policy.admins.len()
+            )),
+        }
+    }
+
+    /// The operations waiting for approval, without the expired ones.
+    pub fn list(&self) -> Vec<PendingOperation> {
+        let now = now().unwrap_or_default();
+        self.pending.read().unwrap().iter().filter(|pending| !pending.is_expired(now)).cloned().collect()
+    }
+
+    /// Adds `operation` to the pending operations under `id`.
+    pub async fn propose(&self, id: String, operation: GuardedOperation, proposed_by: String) -> anyhow::Result<PendingOperation> {
+        if self.policy.is_none() {
+            return Err(anyhow!("❌ No approval policy is configured, so this operation can run directly."));
+        }
+        let created_at = now()?;
+        let pending_operation = {
+            let mut pending = self.pending.write().unwrap();
+            // an expired operation does not block proposing it again
+            pending.retain(|pending| !pending.is_expired(created_at));
+            if let Some(existing) = pending.iter().find(|pending| pending.operation == operation) {
+                return Err(anyhow!("❌ This operation is already pending as {}", existing.id));
+            }
+            let pending_operation = PendingOperation {
+                id,
+                operation,
+                proposed_by,
+                created_at,
+                approvals: Vec::new(),
+                executing: false,
+            };
+            pending.push(pending_operation.clone());
+            pending_operation
+        };
+        self.save().await?;
+        Ok(pending_operation)
+    }
+
+    /// Records `admin`'s approval of operation `id`. Returns the operation and whether it has
+    /// enough approvals to run. A repeated approval by the same admin is not counted again, so
+    /// it can be used to retry an operation that failed to run. An expired operation is removed
+    /// instead.
+    ///
+    /// An operation returned as ready is marked as executing, and fails to be approved until it
+    /// is removed or released with `release`, so only one approver runs it.
+    pub async fn approve(&self, id: &str, admin: &str, signature: &str) -> anyhow::Result<(PendingOperation, bool)> {
+        let Some(policy) = &self.policy else {
+            return Err(anyhow!("❌ No approval policy is configured."));
+        };
+        let admin_key = AccountId32::from_ss58check(admin).map_err(|e| anyhow!("❌ Invalid admin key: {:?}", e))?;
+        if !policy.admins.contains(&admin_key) {
+            return Err(anyhow!("❌ {} is not an admin key of the approval policy", admin));
+        }
+        let signature_bytes = sp_core::bytes::from_hex(signature).map_err(|_| anyhow!("❌ signature must be hex-encoded"))?;
+
+        let now = now()?;
+        let expired = {
+            let mut pending = self.pending.write().unwrap();
+            let before = pending.len();
+            pending.retain(|pending| !(pending.id == id && pending.is_expired(now)));
+            pending.len() != before
+        };
+        if expired {
+            self.save().await?;
+            return Err(anyhow!("❌ Pending operation {} expired. Propose it again.", id));
+        }
+
+        let (pending_operation, changed) = {
+            let mut pending = self.pending.write().unwrap();
+            let pending_operation = pending
+                .iter_mut()
+                .find(|pending| pending.id == id)
+                .ok_or_else(|| anyhow!("❌ No pending operation with ID {}", id))?;
+            if !verify_account_signature(&admin_key, &pending_operation.message(), &signature_bytes) {
+                return Err(anyhow!("❌ The signature is not {}'s signature over the operation's message", admin));
+            }
+            if pending_operation.executing {
+                return Err(anyhow!("❌ Pending operation {} is already running", id));
+            }
+            let approved_before = pending_operation
+                .approvals
+                .iter()
+                .any(|approval| AccountId32::from_ss58check(&approval.admin).is_ok_and(|key| key == admin_key));
+            if !approved_before {
+                pending_operation.approvals.push(Approval { admin: admin.to_string(), signature: signature.to_string() });
+            }
+            // claimed under the lock, so a concurrent approval cannot run it a second time
+            pending_operation.executing = pending_operation.approvals.len() >= policy.threshold;
+            (pending_operation.clone(), !approved_before)
+        };
+        if changed {
+            self.save().await?;
+        }
+
+        let ready = pending_operation.executing;
+        Ok((pending_operation, ready))
+    }
+
+    /// Returns operation `id`, claimed by `approve`, to the pending operations after it failed
+    /// to run, so approving it again retries it.
+    pub fn release(&self, id: &str) {
+        let mut pending = self.pending.write().unwrap();
+        if let Some(pending_operation) = pending.iter_mut().find(|pending| pending.id == id) {
+            pending_operation.executing = false;
+        }
+    }
+
+    /// Removes operation `id`, e.g. once it ran. Returns `false` if it was not pending.
+    pub async fn remove(&self, id: &str) -> anyhow::Result<bool> {
+        let removed = {
+            let mut pending = self.pending.write().unwrap();
+            let before = pending.len();
+            pending.retain(|pending| pending.id != id);
+            pending.len() != before
+        };
+        if removed {
+            self.save().await?;
+        }
+        Ok(removed)
+    }
+
+    async fn save(&self) -> anyhow::Result<()> {
+        let Some(storage_path) = &self.storage_path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&*self.pending.read().unwrap())?;
+        fs::write(storage_path, json).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_core::{ed25519, Pair};
+
+    fn admin(seed: u8) -> (ed25519::Pair, String) {
+        let pair = ed25519::Pair::from_seed(&[seed; 32]);
+        let ss58 = AccountId32::from(pair.public().0).to_ss58check();
+        (pair, ss58)
+    }
+
+    fn sign(pair: &ed25519::Pair, pending: &PendingOperation) -> String {
+        sp_core::bytes::to_hex(&pair.sign(pending.message().as_bytes()).0, false)
+    }
+
+    // A registry with a 2 of 3 policy, kept in memory only
+    fn registry() -> ApprovalRegistry {
+        let admins: Vec<String> = (1..=3).map(|seed| admin(seed).1).collect();
+        ApprovalRegistry {
+            policy: Some(ApprovalPolicy::new(&admins, 2).unwrap()),
+            pending: Arc::default(),
+            storage_path: None,
+        }
+    }
+
+    fn drop_doc() -> GuardedOperation {
+        GuardedOperation::DropDoc { doc_id: "d00".to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_operation_is_ready_once_the_quorum_approved() -> anyhow::Result<()> {
+        let registry = registry();
+        assert!(registry.check_direct().is_err());
+        let pending = registry.propose("op".to_string(), drop_doc(), "author".to_string()).await?;
+
+        let (first, first_ss58) = admin(1);
+        let (approved, ready) = registry.approve("op", &first_ss58, &sign(&first, &pending)).await?;
+        assert_eq!(approved.approvals.len(), 1);
+        assert!(!ready);
+
+        let (second, second_ss58) = admin(2);
+        let (approved, ready) = registry.approve("op", &second_ss58, &sign(&second, &pending)).await?;
+        assert_eq!(approved.approvals.len(), 2);
+        assert!(ready);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_approval_is_counted_once() -> anyhow::Result<()> {
+        let registry = registry();
+        let pending = registry.propose("op".to_string(), drop_doc(), "author".to_string()).await?;
+
+        let (pair, ss58) = admin(1);
+        let signature = sign(&pair, &pending);
+        registry.approve("op", &ss58, &signature).await?;
+        let (approved, ready) = registry.approve("op", &ss58, &signature).await?;
+        assert_eq!(approved.approvals.len(), 1);
+        assert!(!ready);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unlisted_approver_and_wrong_signature_are_rejected() -> anyhow::Result<()> {
+        let registry = registry();
+        let pending = registry.propose("op".to_string(), drop_doc(), "author".to_string()).await?;
+
+        let (outsider, outsider_ss58) = admin(9);
+        assert!(registry.approve("op", &outsider_ss58, &sign(&outsider, &pending)).await.is_err());
+
+        // a listed admin's key with another key's signature
+        let (_, listed_ss58) = admin(1);
+        assert!(registry.approve("op", &listed_ss58, &sign(&outsider, &pending)).await.is_err());
+
+        // a signature over another operation's message cannot be replayed
+        let other = registry.propose("other".to_string(), GuardedOperation::DeleteTag { tag_name: "t".to_string() }, "author".to_string()).await?;
+        let (pair, ss58) = admin(1);
+        assert!(registry.approve("op", &ss58, &sign(&pair, &other)).await.is_err());
+
+        assert!(registry.list().iter().all(|pending| pending.approvals.is_empty()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expired_operation_cannot_be_approved() -> anyhow::Result<()> {
+        let registry = registry();
+        let pending = registry.propose("op".to_string(), drop_doc(), "author".to_string()).await?;
+        registry.pending.write().unwrap()[0].created_at -= PENDING_OPERATION_TTL;
+
+        assert!(registry.list().is_empty());
+        let (pair, ss58) = admin(1);
+        assert!(registry.approve("op", &ss58, &sign(&pair, &pending)).await.is_err());
+        assert!(registry.pending.read().unwrap().is_empty());
+
+        // and can be proposed again
+        registry.propose("again".to_string(), drop_doc(), "author".to_string()).await?;
+        assert!(registry.propose("twice".to_string(), drop_doc(), "author".to_string()).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ready_operation_is_run_by_a_single_approver() -> anyhow::Result<()> {
+        let registry = registry();
+        let pending = registry.propose("op".to_string(), drop_doc(), "author".to_string()).await?;
+        let (first, first_ss58) = admin(1);
+        let (second, second_ss58) = admin(2);
+        let (third, third_ss58) = admin(3);
+        registry.approve("op", &first_ss58, &sign(&first, &pending)).await?;
+        let (_, ready) = registry.approve("op", &second_ss58, &sign(&second, &pending)).await?;
+        assert!(ready);
+
+        // while it runs, neither another admin nor a repeated approval runs it again
+        assert!(registry.approve("op", &third_ss58, &sign(&third, &pending)).await.is_err());
+        assert!(registry.approve("op", &second_ss58, &sign(&second, &pending)).await.is_err());
+
+        // once it failed, approving again retries it
+        registry.release("op");
+        let (_, ready) = registry.approve("op", &second_ss58, &sign(&second, &pending)).await?;
+        assert!(ready);
+        registry.remove("op").await?;
+        assert!(registry.approve("op", &third_ss58, &sign(&third, &pending)).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_without_policy_operations_run_directly() {
+        let registry = ApprovalRegistry::default();
+        assert!(registry.check_direct().is_ok());
+        assert!(ApprovalPolicy::from_settings(&[], None).unwrap().is_none());
+        assert!(ApprovalPolicy::new(&[admin(1).1], 2).is_err());
+    }
+}
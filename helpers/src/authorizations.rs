@@ -0,0 +1,157 @@
+use crate::utils::verify_account_signature;
+use cord::registry::RegistryAuthorization;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::{AccountId32, Ss58Codec};
+use std::{collections::{BTreeMap, HashMap}, path::PathBuf, sync::{Arc, RwLock}, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use tokio::fs;
+
+// File the links between authors and CORD DIDs are persisted to, inside the node's data directory.
+const AUTHOR_DIDS_FILE: &str = "author_dids.json";
+
+/// Prefix of CORD DIDs, followed by the SS58 address of the account.
+pub const CORD_DID_PREFIX: &str = "did:cord:";
+
+/// The CORD DID an author writes for, and the registry authorization that DID holds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorDidLink {
+    pub author_id: String,
+    /// `did:cord:<ss58>`.
+    pub did: String,
+    /// Identifier of the authorization on a registry delegated to the DID's account.
+    pub authorization: String,
+    /// Unix time in seconds at which the link was made.
+    pub linked_at: u64,
+}
+
+impl AuthorDidLink {
+    /// The message the DID's account signs to link it to `author_id`, e.g.
+    /// `starter-kit-link-did:<author_id>:<authorization>`.
+    pub fn message(author_id: &str, authorization: &str) -> String {
+        format!("starter-kit-link-did:{}:{}", author_id, authorization)
+    }
+}
+
+/// The account of a `did:cord:` DID.
+pub fn parse_cord_did(did: &str) -> anyhow::Result<AccountId32> {
+    let address = did
+        .strip_prefix(CORD_DID_PREFIX)
+        .ok_or_else(|| anyhow!("❌ {} is not a CORD DID. Expected {}<ss58>.", did, CORD_DID_PREFIX))?;
+    AccountId32::from_ss58check(address).map_err(|e| anyhow!("❌ Invalid CORD DID {}: {:?}", did, e))
+}
+
+// A registry authorization as read from the chain, `None` if it did not exist, and when it was read
+type CachedAuthorization = (Instant, Option<RegistryAuthorization>);
+
+/// Links between authors and CORD DIDs, shared through `AppState` and persisted as JSON, and the
+/// registry authorizations last read from the chain.
+///
+/// With `--require-chain-authorization`, writes to documents that mirror a CORD registry are
+/// only accepted from authors whose DID holds an authorization on it. Authorizations are cached
+/// for `--authorization-cache-ttl` seconds, so a revoked one is noticed within that time.
+#[derive(Clone)]
+pub struct AuthorizationRegistry {
+    required: bool,
+    ttl: Duration,
+    links: Arc<RwLock<BTreeMap<String, AuthorDidLink>>>,
+    cache: Arc<RwLock<HashMap<String, CachedAuthorization>>>,
+    storage_path: Option<PathBuf>,
+}
+
+impl Default for AuthorizationRegistry {
+    fn default() -> Self {
+        Self {
+            required: false,
+            ttl: Duration::from_secs(300),
+            links: Arc::default(),
+            cache: Arc::default(),
+            storage_path: None,
+        }
+    }
+}
+
+impl AuthorizationRegistry {
+    /// Loads the links from the data directory at `path`, starting empty if nothing was saved yet.
+    pub async fn load(path: &str, required: bool, ttl: Duration) -> anyhow::Result<Self> {
+        let storage_path = PathBuf::from(path).join(AUTHOR_DIDS_FILE);
+
+        let links = if fs::try_exists(&storage_path).await? {
+            let content = fs::read_to_string(&storage_path).await?;
+            serde_json::from_str(&content)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            required,
+            ttl,
+            links: Arc::new(RwLock::new(links)),
+            cache: Arc::default(),
+            storage_path: Some(storage_path),
+        })
+    }
+
+    /// Whether writes must be backed by an authorization on chain.
+    pub fn required(&self) -> bool {
+        self.required
+    }
+
+    pub fn get(&self, author_id: &str) -> Option<AuthorDidLink> {
+        self.links.read().unwrap().get(author_id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<AuthorDidLink> {
+        self.links.read().unwrap().values().cloned().collect()
+    }
+
+    /// Links `author_id` to `did` once `signature` shows the DID's account agrees, replacing any
+    /// earlier link of the author.
+    pub async fn link(&self, author_id: &str, did: &str, authorization: &str, signature: &str) -> anyhow::Result<AuthorDidLink> {
+        let account = parse_cord_did(did)?;
+        let signature_bytes = sp_core::bytes::from_hex(signature).map_err(|_| anyhow!("❌ signature must be hex-encoded"))?;
+        if !verify_account_signature(&account, &AuthorDidLink::message(author_id, authorization), &signature_bytes) {
+            return Err(anyhow!("❌ The signature is not {}'s signature over the link message", did));
+        }
+
+        let link = AuthorDidLink {
+            author_id: author_id.to_string(),
+            did: did.to_string(),
+            authorization: authorization.to_string(),
+            linked_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        };
+        self.links.write().unwrap().insert(author_id.to_string(), link.clone());
+        self.save().await?;
+        Ok(link)
+    }
+
+    /// Removes the link of `author_id`. Returns `false` if it had none.
+    pub async fn unlink(&self, author_id: &str) -> anyhow::Result<bool> {
+        let removed = self.links.write().unwrap().remove(author_id).is_some();
+        if removed {
+            self.save().await?;
+        }
+        Ok(removed)
+    }
+
+    /// The authorization `id` as read from the chain, if it was read less than the TTL ago.
+    pub fn cached(&self, id: &str) -> Option<Option<RegistryAuthorization>> {
+        let cache = self.cache.read().unwrap();
+        let (read_at, authorization) = cache.get(id)?;
+        (read_at.elapsed() < self.ttl).then(|| authorization.clone())
+    }
+
+    /// Remembers the authorization `id` as just read from the chain, `None` if it does not exist.
+    pub fn cache(&self, id: &str, authorization: Option<RegistryAuthorization>) {
+        self.cache.write().unwrap().insert(id.to_string(), (Instant::now(), authorization));
+    }
+
+    async fn save(&self) -> anyhow::Result<()> {
+        let Some(storage_path) = &self.storage_path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&*self.links.read().unwrap())?;
+        fs::write(storage_path, json).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use crate::cli::CliArgs;
+
+use std::{path::PathBuf, sync::Arc};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// An S3-compatible bucket that snapshots are copied to.
+#[derive(Debug, Clone)]
+pub struct S3Target {
+    /// URL of the bucket, path-style and optionally with a key prefix, e.g.
+    /// `https://s3.eu-central-1.amazonaws.com/my-bucket/starter-kit`.
+    pub url: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+/// Where snapshots are written and how many are kept, shared through `AppState`.
+///
+/// Snapshots are made by `core::backups`, on demand or every `--backup-interval` seconds.
+#[derive(Clone, Default)]
+pub struct BackupSettings {
+    /// Directory snapshots are written to, `None` if backups are not configured.
+    pub dir: Option<PathBuf>,
+    /// Number of snapshots left after scheduled pruning.
+    pub keep: usize,
+    /// Every this many snapshots, one is full, see `--backup-full-every`.
+    pub full_every: usize,
+    /// Bucket snapshots are also uploaded to.
+    pub s3: Option<S3Target>,
+    // held while a snapshot is written or pruned, so runs never overlap
+    running: Arc<Mutex<()>>,
+}
+
+impl BackupSettings {
+    pub fn new(dir: Option<PathBuf>, keep: usize, full_every: usize, s3: Option<S3Target>) -> Self {
+        Self { dir, keep, full_every, s3, running: Arc::default() }
+    }
+
+    /// The settings of the `--backup-*` flags of `serve`.
+    pub fn from_args(args: &CliArgs) -> Result<Self, String> {
+        if args.backup_dir.is_none() && (args.backup_interval.is_some() || args.backup_s3_url.is_some()) {
+            return Err("❌ --backup-interval and --backup-s3-url need --backup-dir.".to_string());
+        }
+        if args.backup_interval == Some(0) {
+            return Err("❌ --backup-interval must be greater than 0.".to_string());
+        }
+        if args.backup_keep == 0 {
+            return Err("❌ --backup-keep must be at least 1.".to_string());
+        }
+        if args.backup_full_every == 0 {
+            return Err("❌ --backup-full-every must be at least 1.".to_string());
+        }
+
+        let s3 = match (&args.backup_s3_url, &args.backup_s3_access_key, &args.backup_s3_secret_key) {
+            (Some(url), Some(access_key), Some(secret_key)) => {
+                if !(url.starts_with("https://") || url.starts_with("http://")) {
+                    return Err(format!("❌ --backup-s3-url {} must be an http(s) URL.", url));
+                }
+                Some(S3Target {
+                    url: url.trim_end_matches('/').to_string(),
+                    region: args.backup_s3_region.clone(),
+                    access_key: access_key.clone(),
+                    secret_key: secret_key.clone(),
+                })
+            }
+            (Some(_), _, _) => {
+                return Err("❌ --backup-s3-url needs --backup-s3-access-key and --backup-s3-secret-key.".to_string());
+            }
+            (None, _, _) => None,
+        };
+
+        Ok(Self::new(args.backup_dir.as_ref().map(PathBuf::from), args.backup_keep, args.backup_full_every, s3))
+    }
+
+    /// Waits until no other snapshot or prune is running, and keeps others out until dropped.
+    pub async fn lock(&self) -> MutexGuard<'_, ()> {
+        self.running.lock().await
+    }
+}
@@ -0,0 +1,91 @@
+use bytes::Bytes;
+use iroh_blobs::Hash;
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::Instant};
+
+/// Contents of small blobs read often, such as schemas and config entries, kept in memory so
+/// that `core::blobs::get_blob` and `core::docs::get_entry_blob` skip the store for them.
+///
+/// Holds blobs of up to `--blob-cache-max-blob` bytes, `--blob-cache-size` bytes in total,
+/// dropping the least recently read ones to make room. Blobs are content-addressed, so a cached
+/// blob never goes stale, but it is dropped once its tag is deleted, as the store may then
+/// garbage collect it.
+#[derive(Clone)]
+pub struct BlobCache {
+    capacity: usize,
+    max_blob_size: usize,
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    blobs: HashMap<Hash, (Bytes, Instant)>,
+    size: usize,
+}
+
+/// What the cache holds, reported at `/metrics`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobCacheStats {
+    pub blobs: usize,
+    pub bytes: usize,
+    pub capacity: usize,
+}
+
+impl BlobCache {
+    /// A cache of up to `capacity` bytes of blobs no larger than `max_blob_size`, 0 disabling it.
+    pub fn new(capacity: usize, max_blob_size: usize) -> Self {
+        Self { capacity, max_blob_size: max_blob_size.min(capacity), inner: Arc::default() }
+    }
+
+    /// Whether blobs are kept at all.
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn get(&self, hash: &Hash) -> Option<Bytes> {
+        let mut inner = self.inner.lock().unwrap();
+        let (content, last_read) = inner.blobs.get_mut(hash)?;
+        *last_read = Instant::now();
+        Some(content.clone())
+    }
+
+    /// Keeps `content`, unless it is larger than the largest blob the cache takes.
+    pub fn insert(&self, hash: Hash, content: Bytes) {
+        if content.len() > self.max_blob_size {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if let Some((previous, _)) = inner.blobs.remove(&hash) {
+            inner.size -= previous.len();
+        }
+        while inner.size + content.len() > self.capacity {
+            let least_recent = inner
+                .blobs
+                .iter()
+                .min_by_key(|(_, (_, last_read))| *last_read)
+                .map(|(hash, _)| *hash);
+            let Some(least_recent) = least_recent else { break };
+            if let Some((evicted, _)) = inner.blobs.remove(&least_recent) {
+                inner.size -= evicted.len();
+            }
+        }
+        inner.size += content.len();
+        inner.blobs.insert(hash, (content, Instant::now()));
+    }
+
+    /// Drops a blob, returning whether it was cached.
+    pub fn remove(&self, hash: &Hash) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.blobs.remove(hash) {
+            Some((content, _)) => {
+                inner.size -= content.len();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn stats(&self) -> BlobCacheStats {
+        let inner = self.inner.lock().unwrap();
+        BlobCacheStats { blobs: inner.blobs.len(), bytes: inner.size, capacity: self.capacity }
+    }
+}
@@ -0,0 +1,159 @@
+use iroh_blobs::Hash;
+use std::{future::Future, sync::Arc, time::{Duration, Instant}};
+use tokio::sync::Mutex;
+
+/// How long a snapshot serves the pages of a listing before the store is read again.
+pub const SNAPSHOT_TTL: Duration = Duration::from_secs(30);
+
+/// The hashes and sizes of every blob in the store, sorted by hash, as read at one point in time.
+#[derive(Clone)]
+pub struct BlobSnapshot {
+    blobs: Arc<Vec<(Hash, u64)>>,
+    taken_at: Instant,
+}
+
+impl BlobSnapshot {
+    pub fn new(mut blobs: Vec<(Hash, u64)>) -> Self {
+        blobs.sort_unstable_by_key(|(hash, _)| *hash);
+        Self { blobs: Arc::new(blobs), taken_at: Instant::now() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.blobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blobs.is_empty()
+    }
+
+    /// Up to `limit` blobs from position `offset`.
+    pub fn page_at(&self, offset: usize, limit: usize) -> &[(Hash, u64)] {
+        let start = offset.min(self.blobs.len());
+        let end = start.saturating_add(limit).min(self.blobs.len());
+        &self.blobs[start..end]
+    }
+
+    /// Up to `limit` blobs whose hash sorts after `after`, and whether more follow them. `after`
+    /// need not be in the snapshot, so a cursor stays valid once the snapshot is taken again.
+    pub fn page_after(&self, after: Option<&Hash>, limit: usize) -> (&[(Hash, u64)], bool) {
+        let start = match after {
+            Some(after) => self.blobs.partition_point(|(hash, _)| hash <= after),
+            None => 0,
+        };
+        let end = start.saturating_add(limit).min(self.blobs.len());
+        (&self.blobs[start..end], end < self.blobs.len())
+    }
+}
+
+/// The snapshot that pages of blob listings are served from, so that a page costs a binary
+/// search and a slice instead of walking the store up to it.
+///
+/// A snapshot is taken on the first page of a listing and whenever the last one is older than
+/// `SNAPSHOT_TTL`, so blobs added or deleted during a listing show up at the latest
+/// `SNAPSHOT_TTL` later. Concurrent listings wait for a single read of the store.
+#[derive(Clone, Default)]
+pub struct BlobListing {
+    snapshot: Arc<Mutex<Option<BlobSnapshot>>>,
+}
+
+impl BlobListing {
+    /// The current snapshot, taken with `read` if `fresh` is set or it has expired.
+    pub async fn snapshot<F, Fut, E>(&self, fresh: bool, read: F) -> Result<BlobSnapshot, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<(Hash, u64)>, E>>,
+    {
+        let mut snapshot = self.snapshot.lock().await;
+        match snapshot.as_ref() {
+            Some(current) if !fresh && current.taken_at.elapsed() < SNAPSHOT_TTL => Ok(current.clone()),
+            _ => {
+                let taken = BlobSnapshot::new(read().await?);
+                *snapshot = Some(taken.clone());
+                Ok(taken)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(i: u32) -> Hash {
+        Hash::new(i.to_be_bytes())
+    }
+
+    // Every page of `snapshot`, `limit` blobs at a time, following the cursors
+    fn all_pages(snapshot: &BlobSnapshot, limit: usize) -> Vec<Vec<Hash>> {
+        let mut pages = Vec::new();
+        let mut after = None;
+        loop {
+            let (page, more) = snapshot.page_after(after.as_ref(), limit);
+            pages.push(page.iter().map(|(hash, _)| *hash).collect::<Vec<_>>());
+            if !more {
+                return pages;
+            }
+            after = page.last().map(|(hash, _)| *hash);
+        }
+    }
+
+    #[test]
+    fn test_page_resumes_right_after_the_cursor() {
+        let snapshot = BlobSnapshot::new((0..10).map(|i| (hash(i), i as u64)).collect());
+        let mut sorted: Vec<Hash> = (0..10).map(hash).collect();
+        sorted.sort();
+
+        let pages = all_pages(&snapshot, 3);
+        assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), vec![3, 3, 3, 1]);
+        assert_eq!(pages.concat(), sorted);
+
+        // an exact last page has no more after it
+        let (page, more) = snapshot.page_after(Some(&sorted[4]), 5);
+        assert_eq!(page.iter().map(|(hash, _)| *hash).collect::<Vec<_>>(), sorted[5..]);
+        assert!(!more);
+        let (page, more) = snapshot.page_after(Some(&sorted[9]), 5);
+        assert!(page.is_empty() && !more);
+    }
+
+    #[test]
+    fn test_no_duplicates_or_gaps_under_interleaved_writes() {
+        let mut stored: Vec<(Hash, u64)> = (0..20).map(|i| (hash(i), 0)).collect();
+        let mut kept: Vec<Hash> = (0..20).filter(|i| i % 5 != 0).map(hash).collect();
+        kept.sort();
+
+        let mut listed = Vec::new();
+        let mut after = None;
+        let mut round = 0;
+        loop {
+            // the snapshot is taken again between pages, with blobs added and deleted
+            let snapshot = BlobSnapshot::new(stored.clone());
+            let (page, more) = snapshot.page_after(after.as_ref(), 4);
+            listed.extend(page.iter().map(|(hash, _)| *hash));
+            if !more {
+                break;
+            }
+            after = page.last().map(|(hash, _)| *hash);
+
+            stored.push((hash(100 + round), 0));
+            stored.retain(|(blob, _)| *blob != hash(round * 5));
+            round += 1;
+        }
+
+        let mut deduplicated = listed.clone();
+        deduplicated.dedup();
+        assert_eq!(deduplicated, listed);
+        assert!(listed.windows(2).all(|pair| pair[0] < pair[1]));
+        // every blob stored for the whole listing is listed
+        assert!(kept.iter().all(|blob| listed.contains(blob)));
+    }
+
+    #[test]
+    fn test_cursor_of_a_deleted_blob_stays_valid() {
+        let mut sorted: Vec<Hash> = (0..6).map(hash).collect();
+        sorted.sort();
+        let without_cursor = BlobSnapshot::new(sorted.iter().filter(|blob| **blob != sorted[2]).map(|blob| (*blob, 0)).collect());
+
+        let (page, _) = without_cursor.page_after(Some(&sorted[2]), 10);
+        assert_eq!(page.iter().map(|(hash, _)| *hash).collect::<Vec<_>>(), sorted[3..]);
+    }
+}
@@ -1,20 +1,496 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
 use std::path::PathBuf;
 
-// Command-line arguments for running the starter kit. 
+// Command-line interface of the starter kit.
 // ```
-// Run with default in-memory and random secret
-// cargo run
+// # Create a node directory and keystore
+// cargo run -- init --path ./data --password <password> --suri "<suri>"
 //
-// Run with persistent path and generated secret
-// cargo run -- --path ./iroh_data
-
-// # Run with persistent path and custom secret key
-// cargo run -- --path <path> --secret-key <your_secret_key>
+// # Run the node
+// cargo run -- serve --path ./data --password <password>
+//
+// # Generate a new SURI and print the keys it derives
+// cargo run -- keygen
+//
+// # Check a running node
+// cargo run -- status --url http://127.0.0.1:4001
+//
+// # Work with a running node interactively
+// cargo run -- console --url http://127.0.0.1:4001 --node-id <node_id> --author-id <author_id>
+//
+// # Work with documents and blobs of a stopped node
+// cargo run -- docs --path ./data list
+// cargo run -- blob --path ./data add ./report.pdf
+//
+// # Back up a stopped node and restore it elsewhere
+// cargo run -- export --path ./data --out backup.tar.zst
+// cargo run -- import backup.tar.zst --path ./restored
 // ```
 #[derive(Parser, Debug, Clone)]
 #[command(name = "Starter Kit")]
 #[command(about = "A starter kit for decentralized data providers", long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    #[command(flatten)]
+    pub log: LogArgs,
+}
+
+/// Logging options. Accepted before or after any subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct LogArgs {
+    /// Log filter such as `debug` or `info,iroh=warn`. Takes precedence over `RUST_LOG`.
+    #[arg(long, global = true, value_name = "LEVEL", help = "Log level or filter directives (default: RUST_LOG, then info).")]
+    pub log_level: Option<String>,
+
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text, help = "Log output format.")]
+    pub log_format: LogFormat,
+
+    /// Also write logs to rolling files in this directory.
+    #[arg(long, global = true, value_name = "DIR", help = "Directory to also write rolling log files to.")]
+    pub log_dir: Option<PathBuf>,
+
+    #[arg(long, global = true, value_enum, default_value_t = LogRotation::Daily, help = "How often to start a new log file in --log-dir.")]
+    pub log_rotation: LogRotation,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    Text,
+    /// One JSON object per line, for log shippers such as ELK or Loki.
+    Json,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    Daily,
+    Never,
+}
+
+/// Chain backends documents and proofs can be anchored to, see `cord::backend::ChainAnchor`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChainBackend {
+    /// The CORD chain the node is connected to.
+    Cord,
+    /// A ledger kept by the node, for development and tests.
+    Local,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Bootstrap a new node directory and keystore.
+    Init(InitArgs),
+    /// Generate a SURI, or derive from an existing one, and print the keys it produces.
+    Keygen(KeygenArgs),
+    /// Run the node and serve the API.
+    Serve(CliArgs),
+    /// Check the config, data directory, keystore and ACL files without starting the node.
+    CheckConfig(CliArgs),
+    /// Query a running node's API.
+    Status(StatusArgs),
+    /// Open an interactive shell on a running node's API.
+    Console(ConsoleArgs),
+    /// Inspect and repair documents, offline or through a running node.
+    Docs(DocsArgs),
+    /// Add, fetch and list blobs, offline or through a running node.
+    Blob(BlobArgs),
+    /// Join a shared document from a ticket and wait for it to sync.
+    Join(JoinArgs),
+    /// Create, list and delete authors, offline or through a running node.
+    Author(AuthorArgs),
+    /// Pack a stopped node's data directory into a verifiable `.tar.zst` archive.
+    Export(ExportArgs),
+    /// Restore a node from an archive written by `export`.
+    Import(ImportArgs),
+    /// Restore authors, documents and blobs from a snapshot into a stopped node.
+    RestoreSnapshot(RestoreSnapshotArgs),
+    /// Drive a running node with concurrent writers and readers and report throughput and latency.
+    Bench(BenchArgs),
+}
+
+/// Where a maintenance command runs: on a stopped node's data directory, or through a running node's API.
+#[derive(Args, Debug, Clone)]
+pub struct TargetArgs {
+    /// Data directory of a stopped node. Used when `--url` is not given.
+    #[arg(long, value_name = "PATH", conflicts_with = "url", help = "Data directory of a stopped node (default: data).")]
+    pub path: Option<String>,
+
+    /// Base URL of a running node's HTTP API.
+    #[arg(long, value_name = "URL", help = "Base URL of a running node's HTTP API.")]
+    pub url: Option<String>,
+
+    /// Allowed node ID sent in the `nodeId` header. Required with `--url`.
+    #[arg(long, value_name = "NODE_ID", requires = "url", help = "NodeId to authenticate with when using --url.")]
+    pub node_id: Option<String>,
+
+    /// Author to act as. Defaults to the node's default author when working offline.
+    #[arg(long, value_name = "AUTHOR_ID", help = "Author ID to act as.")]
+    pub author_id: Option<String>,
+}
+
+/// Arguments for `docs`.
+#[derive(Args, Debug, Clone)]
+pub struct DocsArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+
+    #[command(subcommand)]
+    pub command: DocsCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum DocsCommand {
+    /// List documents and their capabilities.
+    List,
+    /// Create a new document.
+    Create,
+    /// List the entries of a document.
+    Entries {
+        doc_id: String,
+        /// Also list deleted entries.
+        #[arg(long)]
+        include_deleted: bool,
+    },
+    /// Write a document's schema and entries to a JSON file.
+    Export {
+        doc_id: String,
+        /// File to write. Prints to stdout if not given.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+    },
+    /// Write the entries from an exported JSON file into a document.
+    Import {
+        file: String,
+        /// Document to import into. A new document is created if not given.
+        #[arg(long, value_name = "DOC_ID")]
+        doc_id: Option<String>,
+    },
+    /// Write a document's entries and their content to a content-addressed CAR archive.
+    ExportArchive {
+        doc_id: String,
+        /// File to write. Writes to stdout if not given.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Check a CAR archive and write its entries and content into an empty document.
+    ImportArchive {
+        file: PathBuf,
+        /// Empty document to import into. A new document is created if not given.
+        #[arg(long, value_name = "DOC_ID")]
+        doc_id: Option<String>,
+    },
+    /// Check every block of a CAR archive against its hash, without a node.
+    VerifyArchive { file: PathBuf },
+}
+
+/// Arguments for `blob`.
+#[derive(Args, Debug, Clone)]
+pub struct BlobArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+
+    #[command(subcommand)]
+    pub command: BlobCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum BlobCommand {
+    /// Add a file as a blob and print its hash.
+    Add {
+        path: PathBuf,
+        /// Tag to keep the blob under. An automatic tag is used if not given.
+        #[arg(long, value_name = "NAME")]
+        name: Option<String>,
+    },
+    /// Fetch a blob by hash, verifying its content against the hash.
+    Get {
+        hash: String,
+        /// File to write. Writes to stdout if not given.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// List the blobs in the store.
+    Ls,
+}
+
+/// Arguments for `author`.
+#[derive(Args, Debug, Clone)]
+pub struct AuthorArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+
+    #[command(subcommand)]
+    pub command: AuthorCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum AuthorCommand {
+    /// Create a new author and print its ID.
+    Create,
+    /// List authors, marking the default one.
+    List,
+    /// Make an author the default for new writes.
+    SetDefault { author_id: String },
+    /// Permanently delete an author.
+    Delete { author_id: String },
+    /// Print an author's secret key, for backups or moving it to another node.
+    Export {
+        author_id: String,
+        /// File to write. Prints to stdout if not given.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Arguments for `join`.
+#[derive(Args, Debug, Clone)]
+pub struct JoinArgs {
+    #[command(flatten)]
+    pub target: TargetArgs,
+
+    /// Document ticket, as returned by `/docs/share-doc`, or a bundle ticket from
+    /// `/docs/share-bundle` to join each of its documents.
+    pub ticket: String,
+
+    /// Only join a bundle ticket signed by this author.
+    #[arg(long, value_name = "AUTHOR_ID", help = "SS58 author a bundle ticket must be signed by.")]
+    pub issuer: Option<String>,
+
+    /// Only download the content of entries under this key prefix, e.g. `public/`. Repeat for
+    /// several prefixes. Entries themselves still sync.
+    #[arg(long = "sync-prefix", value_name = "PREFIX", help = "Key prefix to download content for. Repeatable.")]
+    pub sync_prefixes: Vec<String>,
+
+    /// Seconds to wait for the first sync before giving up on progress output. The document
+    /// stays joined either way.
+    #[arg(long, value_name = "SECS", default_value_t = 120, help = "Seconds to wait for the document to sync.")]
+    pub timeout: u64,
+}
+
+/// Arguments for `init`.
+#[derive(Args, Debug, Clone)]
+pub struct InitArgs {
+    /// Directory to create the node in. Defaults to `data` in the current working directory.
+    #[arg(long, value_name = "PATH", help = "Path to create the node directory in.")]
+    pub path: Option<String>,
+
+    /// Password that `serve` will require to start the node.
+    #[arg(
+        long,
+        value_name = "PASSWORD",
+        required_unless_present = "interactive",
+        help = "A password for your starter-kit."
+    )]
+    pub password: Option<String>,
+
+    /// Seed phrase or SURI to generate keypairs. Use `keygen` to create one.
+    #[arg(
+        long,
+        value_name = "SURI",
+        required_unless_present = "interactive",
+        help = "Seed phrase or SURI(secret URI) to generate keypairs."
+    )]
+    pub suri: Option<String>,
+
+    /// Secret key for encrypting keypairs on disk.
+    #[arg(
+        long,
+        value_name = "SECRET",
+        help = "Added layer of security for your keypairs. If provided, the keypairs will get encrypted."
+    )]
+    pub secret: Option<String>,
+
+    /// Prompt for each setting instead. Values given as flags are used as the defaults.
+    #[arg(long, help = "Walk through the setup with prompts.")]
+    pub interactive: bool,
+}
+
+/// Arguments for `keygen`.
+#[derive(Args, Debug, Clone)]
+pub struct KeygenArgs {
+    /// Derive keys from this SURI instead of generating a new one.
+    #[arg(long, value_name = "SURI", help = "Existing SURI to derive keys from.")]
+    pub suri: Option<String>,
+}
+
+/// Arguments for `export`.
+#[derive(Args, Debug, Clone)]
+pub struct ExportArgs {
+    /// Data directory of the node to export. The node must be stopped.
+    #[arg(long, value_name = "PATH", help = "Data directory of a stopped node (default: data).")]
+    pub path: Option<String>,
+
+    /// Archive to write, conventionally ending in `.tar.zst`.
+    #[arg(long, value_name = "FILE", help = "Archive file to write.")]
+    pub out: PathBuf,
+}
+
+/// Arguments for `import`.
+#[derive(Args, Debug, Clone)]
+pub struct ImportArgs {
+    /// Archive written by `export`.
+    pub archive: PathBuf,
+
+    /// Directory to restore the node into. It must not exist yet.
+    #[arg(long, value_name = "PATH", help = "Data directory to create (default: data).")]
+    pub path: Option<String>,
+}
+
+/// Arguments for `restore-snapshot`.
+#[derive(Args, Debug, Clone)]
+pub struct RestoreSnapshotArgs {
+    /// Snapshot written by the node's scheduled or `/admin/backups` backups.
+    pub archive: PathBuf,
+
+    /// Data directory of the node to restore into. The node must be stopped.
+    #[arg(long, value_name = "PATH", help = "Data directory of a stopped node (default: data).")]
+    pub path: Option<String>,
+}
+
+/// Arguments for `status`.
+#[derive(Args, Debug, Clone)]
+pub struct StatusArgs {
+    /// Base URL of the node's HTTP API.
+    #[arg(
+        long,
+        value_name = "URL",
+        default_value = "http://127.0.0.1:4001",
+        help = "Base URL of the running node's HTTP API."
+    )]
+    pub url: String,
+}
+
+/// Arguments for `console`.
+#[derive(Args, Debug, Clone)]
+pub struct ConsoleArgs {
+    /// Base URL of the node's HTTP API.
+    #[arg(
+        long,
+        value_name = "URL",
+        default_value = "http://127.0.0.1:4001",
+        help = "Base URL of the running node's HTTP API."
+    )]
+    pub url: String,
+
+    /// Allowed node ID sent in the `nodeId` header.
+    #[arg(long, value_name = "NODE_ID", help = "NodeId to authenticate with.")]
+    pub node_id: String,
+
+    /// Author to act as. Needed for commands that write entries or manage the node.
+    #[arg(long, value_name = "AUTHOR_ID", help = "Author ID to act as.")]
+    pub author_id: Option<String>,
+}
+
+/// A line typed into `console`. Takes the same commands as `docs`, `blob` and `author`, plus
+/// `entry` to work with single entries.
+#[derive(Parser, Debug, Clone)]
+#[command(name = "", no_binary_name = true, disable_version_flag = true, about = "Commands run on the node the console is connected to.")]
+pub struct ConsoleLine {
+    #[command(subcommand)]
+    pub command: ConsoleCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConsoleCommand {
+    /// Inspect documents and their entries.
+    Docs {
+        #[command(subcommand)]
+        command: DocsCommand,
+    },
+    /// Read, write and delete single entries.
+    Entry {
+        #[command(subcommand)]
+        command: EntryCommand,
+    },
+    /// Add, fetch and list blobs.
+    Blob {
+        #[command(subcommand)]
+        command: BlobCommand,
+    },
+    /// Create, list and delete authors.
+    Author {
+        #[command(subcommand)]
+        command: AuthorCommand,
+    },
+    /// Leave the console.
+    #[command(alias = "quit")]
+    Exit,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum EntryCommand {
+    /// Print the latest value of a key across authors.
+    Get { doc_id: String, key: String },
+    /// Write a value under a key as `--author-id`.
+    Set { doc_id: String, key: String, value: String },
+    /// Delete a key as `--author-id`.
+    Delete { doc_id: String, key: String },
+}
+
+/// What `bench` writes and reads.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchWorkload {
+    /// Entries of a document, through `/docs/set-entry` and `/docs/get-entry`.
+    Entries,
+    /// Blobs, through `/blobs/add-blob-bytes` and `/blobs/:hash/raw`.
+    Blobs,
+}
+
+/// Arguments for `bench`.
+#[derive(Args, Debug, Clone)]
+pub struct BenchArgs {
+    /// Base URL of the node's HTTP API.
+    #[arg(long, value_name = "URL", default_value = "http://127.0.0.1:4001", help = "Base URL of the running node's HTTP API.")]
+    pub url: String,
+
+    /// Allowed node ID sent in the `nodeId` header.
+    #[arg(long, value_name = "NODE_ID", help = "NodeId to authenticate with.")]
+    pub node_id: String,
+
+    /// Author the writers act as. Defaults to the node's default author.
+    #[arg(long, value_name = "AUTHOR_ID", help = "Author ID to write as.")]
+    pub author_id: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = BenchWorkload::Entries, help = "What to write and read.")]
+    pub workload: BenchWorkload,
+
+    /// Document the `entries` workload writes to. A new document is created if not given.
+    #[arg(long, value_name = "DOC_ID", help = "Document to write entries to (default: a new one).")]
+    pub doc_id: Option<String>,
+
+    #[arg(long, value_name = "COUNT", default_value_t = 4, help = "Number of concurrent writers.")]
+    pub writers: usize,
+
+    #[arg(long, value_name = "COUNT", default_value_t = 4, help = "Number of concurrent readers.")]
+    pub readers: usize,
+
+    #[arg(long, value_name = "BYTES", default_value_t = 256, help = "Size of each written value or blob.")]
+    pub payload_size: usize,
+
+    /// Distinct keys the writers cycle through, and the readers read.
+    #[arg(long, value_name = "COUNT", default_value_t = 1000, help = "Number of distinct keys written and read.")]
+    pub keys: usize,
+
+    #[arg(long, value_name = "SECS", default_value_t = 30, help = "How long to run the load.")]
+    pub duration: u64,
+
+    /// Seconds of load before measuring, so the node's caches are warm.
+    #[arg(long, value_name = "SECS", default_value_t = 0, help = "Seconds of unmeasured load before measuring.")]
+    pub warmup: u64,
+
+    /// Print the report as JSON, to compare runs in scripts.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for `serve`.
+#[derive(Args, Debug, Clone)]
 pub struct CliArgs {
     /// Directory to persistently store blobs and documents.
     ///
@@ -36,9 +512,10 @@ pub struct CliArgs {
     )]
     pub password: String,
 
-    /// Bootstrap a new node (requires --suri).
+    /// Bootstrap a new node before serving it (requires --suri).
     ///
     /// If set, a new data directory will be created and keypairs will be generated from the provided SURI.
+    /// Same as running `init` first.
     #[arg(
         long,
         help = "Bootstraps the node. Requires the --suri parameter."
@@ -65,4 +542,635 @@ pub struct CliArgs {
         help = "Added layer of security for your keypairs. If provided, the keypairs will get encrypted."
     )]
     pub secret: Option<String>,
-}
\ No newline at end of file
+
+    /// Path of a PKCS#11 module (e.g. `/usr/lib/softhsm/libsofthsm2.so`) whose token holds the node's keys.
+    ///
+    /// ed25519 and ecdsa keys on the token sign in hardware; the sr25519 CORD key always stays in the local keystore.
+    /// If the token cannot be opened, the node logs a warning and uses the local keystore only.
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "PKCS#11 module of the HSM holding the node keys. Requires the `pkcs11` feature."
+    )]
+    pub pkcs11_module: Option<String>,
+
+    /// Slot of the PKCS#11 token. Defaults to the first slot with a token.
+    #[arg(
+        long,
+        value_name = "SLOT",
+        help = "Slot of the PKCS#11 token. Defaults to the first slot with a token."
+    )]
+    pub pkcs11_slot: Option<u64>,
+
+    /// User PIN of the PKCS#11 token.
+    #[arg(
+        long,
+        value_name = "PIN",
+        help = "User PIN of the PKCS#11 token."
+    )]
+    pub pkcs11_pin: Option<String>,
+
+    /// SS58 public key (sr25519 or ed25519) of an admin who can approve destructive operations. Can be repeated.
+    ///
+    /// With admin keys set, dropping documents, deleting authors and deleting blob tags only run once
+    /// `--approval-threshold` of these admins have signed off on them through the approvals API.
+    #[arg(
+        long,
+        value_name = "SS58",
+        help = "Admin key that can approve destructive operations. Can be repeated."
+    )]
+    pub approval_admin: Vec<String>,
+
+    /// Number of admin approvals a destructive operation needs (M of N). Defaults to all admins.
+    #[arg(
+        long,
+        value_name = "M",
+        help = "Number of admin approvals a destructive operation needs. Defaults to all admins."
+    )]
+    pub approval_threshold: Option<usize>,
+
+    /// Address for the gRPC server, e.g. `127.0.0.1:50051`.
+    ///
+    /// Only used when the binary is built with the `grpc` feature. The gRPC server is not started if this is not set.
+    #[arg(
+        long,
+        value_name = "ADDR",
+        help = "Address to serve the gRPC API on. Requires the `grpc` feature."
+    )]
+    pub grpc_listen: Option<String>,
+
+    /// Minimum response size in bytes before gzip/brotli compression is applied.
+    ///
+    /// Compression is negotiated with the client's `Accept-Encoding` header. Smaller responses are sent as-is.
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value_t = 1024,
+        help = "Minimum response size in bytes to compress."
+    )]
+    pub compression_min_size: u16,
+
+    /// TCP address(es) to serve the HTTP API on, as `host:port`. Can be repeated.
+    ///
+    /// Defaults to `127.0.0.1:4001` when neither `--listen` nor `--listen-uds` is given.
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        help = "TCP address to serve the HTTP API on. Can be repeated."
+    )]
+    pub listen: Vec<String>,
+
+    /// Unix domain socket path(s) to serve the HTTP API on. Can be repeated.
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Unix socket path to serve the HTTP API on. Can be repeated."
+    )]
+    pub listen_uds: Vec<String>,
+
+    /// TCP address to serve the diagnostics endpoints under `/debug` on, as `host:port`.
+    ///
+    /// They are served on this address only, without the node ID check of the HTTP API, so bind
+    /// it to loopback or a private interface. They are not served if this is not set.
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        help = "TCP address to serve the /debug diagnostics endpoints on. Keep it private."
+    )]
+    pub admin_listen: Option<String>,
+
+    /// TCP address to serve a read-only S3-compatible API on, as `host:port`.
+    ///
+    /// The bucket `blobs` holds every blob keyed by hex hash, and every document is a bucket
+    /// keyed by entry key. Requests are answered unsigned, without the node ID check of the HTTP
+    /// API, so bind it to loopback or a private interface. It is not served if this is not set.
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        help = "TCP address to serve blobs and documents to S3 clients on. Keep it private."
+    )]
+    pub s3_listen: Option<String>,
+
+    /// TCP address to serve the documents as a read-only WebDAV share on, as `host:port`.
+    ///
+    /// Each document is a folder, and its keys are files, split into sub-folders at `/`, so they
+    /// can be browsed with a file manager. Requests are answered without the node ID check of
+    /// the HTTP API, so bind it to loopback or a private interface. It is not served if this is
+    /// not set.
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        help = "TCP address to serve documents as a read-only WebDAV share on. Keep it private."
+    )]
+    pub webdav_listen: Option<String>,
+
+    /// TCP address to publish documents and blobs to anyone on, as `host:port`.
+    ///
+    /// Only the documents of `--public-doc` and the blobs of `--public-blob` are served there,
+    /// on GET routes without the node ID check, and each client IP address is limited to
+    /// `--public-rate-limit` requests per minute. No route that changes the node is mounted on
+    /// it, so it can face the internet while the HTTP API stays private.
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        help = "TCP address to publish the --public-doc documents and --public-blob blobs to anyone on."
+    )]
+    pub public_listen: Option<String>,
+
+    /// Document to publish on `--public-listen`. Can be repeated.
+    ///
+    /// Its entries are listed and their content served, except deleted entries and the versions
+    /// kept by `--version-retention`.
+    #[arg(
+        long,
+        value_name = "DOC_ID",
+        help = "Document to publish on --public-listen. Can be repeated."
+    )]
+    pub public_doc: Vec<String>,
+
+    /// Blob to publish on `--public-listen`, as a hex hash. Can be repeated.
+    #[arg(
+        long,
+        value_name = "HASH",
+        help = "Blob to publish on --public-listen, by hash. Can be repeated."
+    )]
+    pub public_blob: Vec<String>,
+
+    /// Requests each client IP address may make per minute on `--public-listen`.
+    ///
+    /// A client may use them in a burst. Requests over the limit are answered with
+    /// `429 Too Many Requests`. Behind a reverse proxy every request comes from the proxy's
+    /// address, so rate limit there instead.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 120,
+        help = "Requests per minute allowed to each client IP address on --public-listen."
+    )]
+    pub public_rate_limit: u32,
+
+    /// Seconds a read-only request (lookups and listings) may run before it is cancelled.
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 10,
+        help = "Timeout in seconds for read requests."
+    )]
+    pub read_timeout: u64,
+
+    /// Seconds a write request (creating docs, setting entries, adding blobs) may run before it is cancelled.
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 30,
+        help = "Timeout in seconds for write requests."
+    )]
+    pub write_timeout: u64,
+
+    /// Seconds a download from peers, or a file import/export, may run before it is cancelled.
+    ///
+    /// Keep this high enough for the largest blobs you expect to fetch over your slowest link.
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 600,
+        help = "Timeout in seconds for downloads and file imports/exports."
+    )]
+    pub download_timeout: u64,
+
+    /// Blobs fetched at once from each peer by downloads started through `/blobs/downloads`,
+    /// which spread the children of a collection across every peer they are given.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 4,
+        help = "Number of blobs a download fetches at once from each peer."
+    )]
+    pub download_connections: usize,
+
+    /// Imports of local files and archives running at once. Requests over the limit wait for a
+    /// slot, within the timeout of their route.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 4,
+        help = "Number of file and archive imports running at once."
+    )]
+    pub max_concurrent_imports: usize,
+
+    /// Exports of blobs and documents, to files or streamed responses, running at once.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 16,
+        help = "Number of blob and document exports running at once."
+    )]
+    pub max_concurrent_exports: usize,
+
+    /// Blob downloads from peers running at once, those started through `/blobs/downloads` included.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 8,
+        help = "Number of blob downloads from peers running at once."
+    )]
+    pub max_concurrent_downloads: usize,
+
+    /// Listings and queries that walk a whole document or store running at once.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 32,
+        help = "Number of document and store scans running at once."
+    )]
+    pub max_concurrent_scans: usize,
+
+    /// Relay servers the node uses to reach peers behind NATs. Can be repeated.
+    ///
+    /// `default` uses n0's production relays, `staging` n0's staging relays, and `disabled` none.
+    /// Anything else is taken as the URL of a relay server.
+    #[arg(
+        long,
+        value_name = "RELAY",
+        help = "Relay to use: default, staging, disabled, or a relay server URL. Can be repeated."
+    )]
+    pub relay: Vec<String>,
+
+    /// Chain events to mirror into a document, as `<Pallet>[.<Event>]=<doc_id>`. Can be repeated.
+    ///
+    /// Every matching event of a finalized CORD block is stored in the document by the default
+    /// author, under `cord:event:<block_number>-<event_index>`. The document must exist on the node.
+    #[arg(
+        long,
+        value_name = "PALLET[.EVENT]=DOC_ID",
+        help = "Mirror CORD chain events into a document, e.g. Statement.Registered=<doc_id>. Can be repeated."
+    )]
+    pub chain_event: Vec<String>,
+
+    /// Seconds between batches of proofs queued with `POST /proofs` and `"batch": true`.
+    ///
+    /// Each batch anchors the Merkle root of every queued subject in one extrinsic. Nothing is
+    /// submitted while the queue is empty.
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 600,
+        help = "Interval in seconds at which queued proofs are anchored in one batch."
+    )]
+    pub proof_batch_interval: u64,
+
+    /// Only accept entries from authors whose CORD DID holds an authorization on the document's registry.
+    ///
+    /// Applies to documents that mirror a CORD registry (see `POST /docs/sync-cord-definitions`).
+    /// Authors link their DID and authorization with `POST /authors/link-did`.
+    #[arg(
+        long,
+        help = "Check on chain that authors are authorized on a document's CORD registry before accepting entries."
+    )]
+    pub require_chain_authorization: bool,
+
+    /// Seconds a registry authorization read from the chain is trusted before it is read again.
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 300,
+        help = "Time in seconds registry authorizations read from the chain are cached."
+    )]
+    pub authorization_cache_ttl: u64,
+
+    /// Where documents, revocations and proofs are anchored.
+    ///
+    /// `local` keeps a ledger in `local-chain.json` in the data directory, for development and
+    /// tests. Its anchors prove nothing to third parties. Other CORD features still use the chain.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ChainBackend::Cord,
+        help = "Chain that documents and proofs are anchored to."
+    )]
+    pub chain_backend: ChainBackend,
+
+    /// Serve reads only, as a replica of shared documents.
+    ///
+    /// Requests that would write documents, blobs, authors or the chain are rejected with
+    /// `403 Forbidden`. Joining, leaving and syncing documents still work, so the node keeps up
+    /// with writes made elsewhere.
+    #[arg(
+        long,
+        help = "Reject local writes while still joining and syncing documents."
+    )]
+    pub read_only: bool,
+
+    /// Serve `/gw/:hash` without the node ID check.
+    ///
+    /// Browsers load images, scripts and links embedded in web pages without the `nodeId` or
+    /// `Origin` header, so gateway links only work in pages once this is set. Anyone who knows a
+    /// hash can then read the blob, as on public content-addressed gateways.
+    #[arg(
+        long,
+        help = "Serve blobs under /gw/:hash to anyone, so gateway links can be embedded in web pages."
+    )]
+    pub public_gw: bool,
+
+    /// Reject requests on the `--listen` addresses that carry no tenant API key.
+    ///
+    /// Each tenant then only reaches its own documents and authors. Requests over `--listen-uds`
+    /// are still served without a key, so the operator manages tenants and the node there.
+    #[arg(
+        long,
+        help = "Only serve requests with a tenant API key on the --listen addresses. Needs --listen-uds, and no gRPC, admin, S3 or WebDAV listener."
+    )]
+    pub require_api_key: bool,
+
+    /// Record the entry and blob reads of every document in `access.log` in the data
+    /// directory, sampled with `--access-log-sample-rate`.
+    #[arg(
+        long,
+        help = "Record entry and blob reads in access.log, sampled with --access-log-sample-rate."
+    )]
+    pub access_log: bool,
+
+    /// Document whose every entry read is recorded in `access.log`, whatever the sample rate.
+    /// Can be repeated. Works without `--access-log`, to only record reads of these documents.
+    #[arg(
+        long,
+        value_name = "DOC_ID",
+        help = "Document whose every read is recorded in access.log. Can be repeated."
+    )]
+    pub access_log_doc: Vec<String>,
+
+    /// Fraction of the reads recorded with `--access-log`, from above 0 to 1. Reads of
+    /// `--access-log-doc` documents are always recorded.
+    #[arg(
+        long,
+        value_name = "RATE",
+        default_value_t = 1.0,
+        help = "Fraction of reads recorded with --access-log, from above 0 to 1."
+    )]
+    pub access_log_sample_rate: f64,
+
+    /// Include the underlying error in API error messages.
+    ///
+    /// Errors such as `FailedToGetEntry` are followed by the iroh, IO or chain error that caused
+    /// them, for example `FailedToGetEntry: connection lost`. Causes may reveal file paths and
+    /// internals, so this is meant for development and troubleshooting.
+    #[arg(
+        long,
+        help = "Append the underlying cause to API error messages."
+    )]
+    pub debug_errors: bool,
+
+    /// Open document handles kept between requests, so that requests to hot documents do not
+    /// reopen them. The least recently used handle is closed when the cache is full.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 256,
+        help = "Number of open document handles kept between requests, 0 to disable."
+    )]
+    pub doc_cache_size: usize,
+
+    /// Seconds a cached document handle may stay unused before it is closed.
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 300,
+        help = "Time in seconds an unused document handle stays open."
+    )]
+    pub doc_cache_idle: u64,
+
+    /// Bytes of small, often read blobs, such as schemas and config entries, kept in memory so
+    /// that reading them skips the store. The least recently read blob is dropped to make room.
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value_t = 64 * 1024 * 1024,
+        help = "Bytes of blob content kept in memory for repeated reads, 0 to disable."
+    )]
+    pub blob_cache_size: usize,
+
+    /// Largest blob, in bytes, kept in the blob cache. Larger blobs are always read from the store.
+    #[arg(
+        long,
+        value_name = "BYTES",
+        default_value_t = 1024 * 1024,
+        help = "Size in bytes of the largest blob kept in the blob cache."
+    )]
+    pub blob_cache_max_blob: usize,
+
+    /// Top-level JSON field of entry values to index, so entries can be looked up by its value
+    /// without scanning the document. Can be repeated.
+    #[arg(
+        long,
+        value_name = "FIELD",
+        help = "Top-level JSON field of entry values to index for lookups. Can be repeated."
+    )]
+    pub index_field: Vec<String>,
+
+    /// Indexes the string values of schema-validated entries for full-text search with
+    /// `/docs/:id/search`. The index is kept in memory and built when the node starts.
+    #[arg(
+        long,
+        help = "Enables full-text search over the values of schema-validated entries."
+    )]
+    pub search: bool,
+
+    /// Document whose entries `registry:<key>` refs in document schemas point to, so schemas can
+    /// share definitions kept in one replicated document.
+    #[arg(
+        long,
+        value_name = "DOC_ID",
+        help = "Document that `registry:<key>` refs of schemas are read from."
+    )]
+    pub schema_registry_doc: Option<String>,
+
+    /// URL prefix that `$ref`s of document schemas may be fetched from, such as
+    /// `https://schemas.example.com/`. Can be repeated. Other URLs are rejected.
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "URL prefix schema refs may be fetched from. Can be repeated."
+    )]
+    pub schema_ref_url: Vec<String>,
+
+    /// How long deleted entries stay in the trash, restorable, before they are deleted for good.
+    /// `0` keeps them until the trash is emptied.
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 2_592_000,
+        help = "Seconds deleted entries stay restorable in the trash. 0 keeps them until emptied."
+    )]
+    pub trash_retention: u64,
+
+    /// How long the past versions of entries are kept after they are replaced or deleted, for
+    /// point-in-time reads. `0` keeps them for good.
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 7_776_000,
+        help = "Seconds replaced versions of entries are kept for point-in-time reads. 0 keeps them for good."
+    )]
+    pub version_retention: u64,
+
+    /// `host:port` of an SMTP relay that notifications with an email channel are handed to, such
+    /// as a local Postfix. Mail is sent in plain text without authentication, so the relay should
+    /// be on loopback or a private network. Without it, email channels cannot be registered.
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        help = "SMTP relay for email notifications. Enables email channels."
+    )]
+    pub smtp_relay: Option<String>,
+
+    /// Sender address of email notifications.
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        default_value = "starter-kit@localhost",
+        help = "Sender address of email notifications."
+    )]
+    pub smtp_from: String,
+
+    /// Maintenance job to run on a cron schedule, as `<name>=<minute hour day month weekday>` in
+    /// UTC, such as `backup=0 3 * * *`. Can be repeated, once per job.
+    ///
+    /// Jobs are `backup`, `anchor-proofs`, `replication-check`, `trash-purge`, `history-purge`
+    /// and `blob-gc`. A scheduled job replaces the fixed interval its task otherwise runs on,
+    /// and every job can be run on request through `/admin/jobs`.
+    #[arg(
+        long,
+        value_name = "NAME=SCHEDULE",
+        help = "Run a maintenance job on a cron schedule in UTC, e.g. \"backup=0 3 * * *\". Can be repeated."
+    )]
+    pub job: Vec<String>,
+
+    /// Directory to write snapshots of the node's authors, documents and blobs to.
+    ///
+    /// Snapshots are made every `--backup-interval` seconds, or through `/admin/backups`, and
+    /// restored with `restore-snapshot`.
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Directory snapshots are written to. Enables backups."
+    )]
+    pub backup_dir: Option<String>,
+
+    /// Seconds between scheduled snapshots. Without it, snapshots are only made on request.
+    #[arg(
+        long,
+        value_name = "SECS",
+        help = "Interval in seconds between scheduled snapshots."
+    )]
+    pub backup_interval: Option<u64>,
+
+    /// Number of snapshots kept when scheduled snapshots prune older ones.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 7,
+        help = "Number of snapshots kept by scheduled pruning."
+    )]
+    pub backup_keep: usize,
+
+    /// Every this many snapshots, one is full; the others only hold what changed since the
+    /// previous one. 1 makes every snapshot full.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = 7,
+        help = "Take a full snapshot every COUNT snapshots, and incremental ones in between."
+    )]
+    pub backup_full_every: usize,
+
+    /// Path-style URL of an S3-compatible bucket, and optional key prefix, to copy snapshots to.
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "S3 bucket URL snapshots are uploaded to, e.g. https://s3.eu-central-1.amazonaws.com/my-bucket/prefix."
+    )]
+    pub backup_s3_url: Option<String>,
+
+    /// Region of the S3 bucket, used to sign requests.
+    #[arg(
+        long,
+        value_name = "REGION",
+        default_value = "us-east-1",
+        help = "Region of the S3 bucket."
+    )]
+    pub backup_s3_region: String,
+
+    /// Access key ID for the S3 bucket.
+    #[arg(long, value_name = "KEY", help = "Access key ID for the S3 bucket.")]
+    pub backup_s3_access_key: Option<String>,
+
+    /// Secret access key for the S3 bucket.
+    #[arg(long, value_name = "SECRET", help = "Secret access key for the S3 bucket.")]
+    pub backup_s3_secret_key: Option<String>,
+
+    /// Catalog document of a federation, as a ticket or, if the node already has it, a document ID.
+    ///
+    /// The node joins every document the catalog lists under `federation/<doc_id>`, as written by
+    /// a `--federation-trusted-author`, and leaves the ones it joined once they are delisted.
+    #[arg(
+        long,
+        value_name = "TICKET|DOC_ID",
+        help = "Catalog document listing the documents this node replicates. Enables federation."
+    )]
+    pub federation_catalog: Option<String>,
+
+    /// Author whose catalog entries are followed. Can be repeated. Entries of other authors are
+    /// ignored.
+    #[arg(
+        long,
+        value_name = "AUTHOR_ID",
+        help = "SS58 author trusted to list documents in the catalog. Can be repeated."
+    )]
+    pub federation_trusted_author: Vec<String>,
+
+    /// Seconds between checks of the catalog, on top of the checks made when it changes.
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 60,
+        help = "Interval in seconds at which the catalog is checked again."
+    )]
+    pub federation_interval: u64,
+
+    /// TOML file to read settings from. Flags given on the command line take precedence.
+    ///
+    /// See `helpers::config::NodeConfig` for the accepted keys.
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "TOML config file. Flags override its values."
+    )]
+    pub config: Option<PathBuf>,
+
+    /// Profile from the config file to use, e.g. `dev` or `prod`.
+    ///
+    /// Its settings override the top-level ones in the file.
+    #[arg(
+        long,
+        value_name = "NAME",
+        requires = "config",
+        help = "Named profile in the config file to use."
+    )]
+    pub profile: Option<String>,
+}
+impl CliArgs {
+    /// The listener flags given whose servers do not check tenant API keys. With
+    /// `--require-api-key` they would let requests without a key past the tenant checks, so the
+    /// node refuses to start with any of them.
+    pub fn listeners_without_api_keys(&self) -> Vec<&'static str> {
+        [
+            ("--grpc-listen", self.grpc_listen.is_some()),
+            ("--admin-listen", self.admin_listen.is_some()),
+            ("--s3-listen", self.s3_listen.is_some()),
+            ("--webdav-listen", self.webdav_listen.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(flag, given)| given.then_some(flag))
+        .collect()
+    }
+}
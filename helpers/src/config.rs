@@ -0,0 +1,226 @@
+use crate::cli::{ChainBackend, CliArgs};
+
+use anyhow::Context;
+use clap::{parser::ValueSource, ArgMatches};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// Settings for `serve` and `check-config` read from a TOML file.
+// ```toml
+// path = "data"
+// listen = ["0.0.0.0:4001"]
+// read_timeout = 15
+//
+// [profiles.dev]
+// path = "data-dev"
+// listen = ["127.0.0.1:4002"]
+// relay = ["disabled"]
+// ```
+// Every key is optional and mirrors the flag of the same name. With `--profile`, the keys of
+// that profile override the top-level ones, and flags given on the command line override both.
+// Passwords, secrets and PINs are only accepted as flags.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeConfig {
+    pub path: Option<String>,
+    pub grpc_listen: Option<String>,
+    pub pkcs11_module: Option<String>,
+    pub pkcs11_slot: Option<u64>,
+    pub approval_admin: Option<Vec<String>>,
+    pub approval_threshold: Option<usize>,
+    pub compression_min_size: Option<u16>,
+    pub listen: Option<Vec<String>>,
+    pub listen_uds: Option<Vec<String>>,
+    pub admin_listen: Option<String>,
+    pub s3_listen: Option<String>,
+    pub webdav_listen: Option<String>,
+    pub public_listen: Option<String>,
+    pub public_doc: Option<Vec<String>>,
+    pub public_blob: Option<Vec<String>>,
+    pub public_rate_limit: Option<u32>,
+    pub read_timeout: Option<u64>,
+    pub write_timeout: Option<u64>,
+    pub download_timeout: Option<u64>,
+    pub download_connections: Option<usize>,
+    pub max_concurrent_imports: Option<usize>,
+    pub max_concurrent_exports: Option<usize>,
+    pub max_concurrent_downloads: Option<usize>,
+    pub max_concurrent_scans: Option<usize>,
+    pub relay: Option<Vec<String>>,
+    pub chain_event: Option<Vec<String>>,
+    pub proof_batch_interval: Option<u64>,
+    pub require_chain_authorization: Option<bool>,
+    pub authorization_cache_ttl: Option<u64>,
+    pub chain_backend: Option<ChainBackend>,
+    pub read_only: Option<bool>,
+    pub public_gw: Option<bool>,
+    pub require_api_key: Option<bool>,
+    pub access_log: Option<bool>,
+    pub access_log_doc: Option<Vec<String>>,
+    pub access_log_sample_rate: Option<f64>,
+    pub debug_errors: Option<bool>,
+    pub doc_cache_size: Option<usize>,
+    pub doc_cache_idle: Option<u64>,
+    pub blob_cache_size: Option<usize>,
+    pub blob_cache_max_blob: Option<usize>,
+    pub index_field: Option<Vec<String>>,
+    pub search: Option<bool>,
+    pub schema_registry_doc: Option<String>,
+    pub schema_ref_url: Option<Vec<String>>,
+    pub trash_retention: Option<u64>,
+    pub version_retention: Option<u64>,
+    pub smtp_relay: Option<String>,
+    pub smtp_from: Option<String>,
+    pub job: Option<Vec<String>>,
+    pub backup_dir: Option<String>,
+    pub backup_interval: Option<u64>,
+    pub backup_keep: Option<usize>,
+    pub backup_full_every: Option<usize>,
+    pub backup_s3_url: Option<String>,
+    pub backup_s3_region: Option<String>,
+    pub federation_catalog: Option<String>,
+    pub federation_trusted_author: Option<Vec<String>>,
+    pub federation_interval: Option<u64>,
+    /// Named sets of overrides, selected with `--profile`.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, NodeConfig>,
+}
+
+// Runs `$apply!(field)` for every setting, so adding one only needs a change here
+macro_rules! for_each_setting {
+    ($apply:ident) => {
+        $apply!(path);
+        $apply!(grpc_listen);
+        $apply!(pkcs11_module);
+        $apply!(pkcs11_slot);
+        $apply!(approval_admin);
+        $apply!(approval_threshold);
+        $apply!(compression_min_size);
+        $apply!(listen);
+        $apply!(listen_uds);
+        $apply!(admin_listen);
+        $apply!(s3_listen);
+        $apply!(webdav_listen);
+        $apply!(public_listen);
+        $apply!(public_doc);
+        $apply!(public_blob);
+        $apply!(public_rate_limit);
+        $apply!(read_timeout);
+        $apply!(write_timeout);
+        $apply!(download_timeout);
+        $apply!(download_connections);
+        $apply!(max_concurrent_imports);
+        $apply!(max_concurrent_exports);
+        $apply!(max_concurrent_downloads);
+        $apply!(max_concurrent_scans);
+        $apply!(relay);
+        $apply!(chain_event);
+        $apply!(proof_batch_interval);
+        $apply!(require_chain_authorization);
+        $apply!(authorization_cache_ttl);
+        $apply!(chain_backend);
+        $apply!(read_only);
+        $apply!(public_gw);
+        $apply!(require_api_key);
+        $apply!(access_log);
+        $apply!(access_log_doc);
+        $apply!(access_log_sample_rate);
+        $apply!(debug_errors);
+        $apply!(doc_cache_size);
+        $apply!(doc_cache_idle);
+        $apply!(blob_cache_size);
+        $apply!(blob_cache_max_blob);
+        $apply!(index_field);
+        $apply!(search);
+        $apply!(schema_registry_doc);
+        $apply!(schema_ref_url);
+        $apply!(trash_retention);
+        $apply!(version_retention);
+        $apply!(smtp_relay);
+        $apply!(smtp_from);
+        $apply!(job);
+        $apply!(backup_dir);
+        $apply!(backup_interval);
+        $apply!(backup_keep);
+        $apply!(backup_full_every);
+        $apply!(backup_s3_url);
+        $apply!(backup_s3_region);
+        $apply!(federation_catalog);
+        $apply!(federation_trusted_author);
+        $apply!(federation_interval);
+    };
+}
+
+impl NodeConfig {
+    /// Reads and parses a config file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {:?}", path))?;
+        toml::from_str(&content).with_context(|| format!("Failed to parse config file {:?}", path))
+    }
+
+    /// Returns the settings of profile `name` layered over the top-level ones.
+    ///
+    /// A profile that sets no `path` gets `data-<name>`, so profiles never share a data
+    /// directory by accident.
+    pub fn select_profile(mut self, name: &str) -> anyhow::Result<Self> {
+        let Some(mut profile) = self.profiles.remove(name) else {
+            let available: Vec<_> = self.profiles.keys().map(String::as_str).collect();
+            anyhow::bail!(
+                "Profile '{}' is not defined in the config file. Available profiles: {}",
+                name,
+                if available.is_empty() { "none".to_string() } else { available.join(", ") }
+            );
+        };
+        if !profile.profiles.is_empty() {
+            anyhow::bail!("Profile '{}' cannot define its own profiles", name);
+        }
+        let defaults_path = self.path.take();
+
+        macro_rules! inherit {
+            ($field:ident) => {
+                if profile.$field.is_none() {
+                    profile.$field = self.$field.take();
+                }
+            };
+        }
+        for_each_setting!(inherit);
+
+        if profile.path.is_none() {
+            profile.path = Some(format!("{}-{}", defaults_path.as_deref().unwrap_or("data"), name));
+        }
+        Ok(profile)
+    }
+
+    /// Fills in `args` from this config wherever the flag was not given on the command line.
+    ///
+    /// `matches` are the parsed arguments of the subcommand `args` came from.
+    pub fn apply(self, args: &mut CliArgs, matches: &ArgMatches) {
+        let from_command_line = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        macro_rules! apply {
+            ($field:ident) => {
+                if let Some(value) = self.$field {
+                    if !from_command_line(stringify!($field)) {
+                        args.$field = value.into();
+                    }
+                }
+            };
+        }
+        for_each_setting!(apply);
+    }
+}
+
+/// Applies the `--config` file, if one was given, to the arguments of `serve` or `check-config`,
+/// using the settings of `--profile` when one is selected.
+pub fn resolve_serve_args(mut args: CliArgs, matches: &ArgMatches) -> Result<CliArgs, String> {
+    if let Some(config_path) = args.config.clone() {
+        let mut config = NodeConfig::load(&config_path).map_err(|e| format!("❌ {:#}", e))?;
+        if let Some(profile) = &args.profile {
+            config = config.select_profile(profile).map_err(|e| format!("❌ {:#}", e))?;
+        }
+        config.apply(&mut args, matches);
+    }
+    Ok(args)
+}
@@ -0,0 +1,79 @@
+use iroh_docs::rpc::client::docs::Doc;
+use iroh_docs::rpc::proto::{Request, Response};
+use iroh_docs::NamespaceId;
+use quic_rpc::transport::flume::FlumeConnector;
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+/// An open document, as returned by `Docs::client().open`.
+pub type DocHandle = Doc<FlumeConnector<Response, Request>>;
+
+/// Open document handles kept between requests, held by the node's `NodeDocs`, so that requests
+/// to hot documents do not open and close them every time.
+///
+/// Holds up to `--doc-cache-size` handles, replacing the least recently used one when full, and
+/// closes those unused for `--doc-cache-idle` seconds. A handle is closed once it leaves the
+/// cache and no request uses it anymore. iroh refuses to drop a document that is still open, so
+/// a document's handle has to be taken out with `remove` before it is closed or dropped.
+#[derive(Clone)]
+pub struct DocHandleCache {
+    capacity: usize,
+    idle: Duration,
+    handles: Arc<Mutex<HashMap<NamespaceId, (DocHandle, Instant)>>>,
+}
+
+impl DocHandleCache {
+    /// A cache of up to `capacity` handles, 0 disabling it.
+    pub fn new(capacity: usize, idle: Duration) -> Self {
+        Self { capacity, idle, handles: Arc::default() }
+    }
+
+    pub fn idle(&self) -> Duration {
+        self.idle
+    }
+
+    pub fn get(&self, doc_id: &NamespaceId) -> Option<DocHandle> {
+        let mut handles = self.handles.lock().unwrap();
+        let (doc, last_used) = handles.get_mut(doc_id)?;
+        *last_used = Instant::now();
+        Some(doc.clone())
+    }
+
+    pub fn insert(&self, doc_id: NamespaceId, doc: DocHandle) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut handles = self.handles.lock().unwrap();
+        if !handles.contains_key(&doc_id) && handles.len() >= self.capacity {
+            let least_recent = handles
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(doc_id, _)| *doc_id);
+            if let Some(least_recent) = least_recent {
+                handles.remove(&least_recent);
+            }
+        }
+        handles.insert(doc_id, (doc, Instant::now()));
+    }
+
+    /// Takes the handle of a document out of the cache.
+    pub fn remove(&self, doc_id: &NamespaceId) -> Option<DocHandle> {
+        self.handles.lock().unwrap().remove(doc_id).map(|(doc, _)| doc)
+    }
+
+    /// Drops the handles unused for longer than the idle time, returning how many were dropped.
+    pub fn evict_idle(&self) -> usize {
+        let mut handles = self.handles.lock().unwrap();
+        let before = handles.len();
+        handles.retain(|_, (_, last_used)| last_used.elapsed() < self.idle);
+        before - handles.len()
+    }
+
+    /// Number of handles kept open.
+    pub fn open_handles(&self) -> usize {
+        self.handles.lock().unwrap().len()
+    }
+
+    pub fn contains(&self, doc_id: &NamespaceId) -> bool {
+        self.handles.lock().unwrap().contains_key(doc_id)
+    }
+}
@@ -0,0 +1,182 @@
+use crate::limits::OperationLimits;
+use serde::Serialize;
+use std::{collections::{BTreeMap, HashMap}, sync::{Arc, RwLock}, time::{SystemTime, UNIX_EPOCH}};
+
+// Finished downloads kept for their status to be read, the oldest being forgotten first
+const MAX_FINISHED_DOWNLOADS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadState {
+    Running,
+    Done,
+    Failed,
+}
+
+/// What a single peer contributed to a download.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PeerProgress {
+    /// Blobs fetched from the peer.
+    pub blobs: u64,
+    /// Bytes received from the peer.
+    pub bytes: u64,
+    /// Blobs the peer failed to serve, which were then asked of another peer.
+    pub failures: u64,
+}
+
+/// The merged progress of a download from several peers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DownloadJob {
+    pub id: String,
+    pub hash: String,
+    /// `Raw` or `HashSeq`.
+    pub format: String,
+    pub state: DownloadState,
+    /// Blobs to fetch, the collection itself included. Known once the collection was read.
+    pub blobs_total: u64,
+    pub blobs_done: u64,
+    /// Size of the content announced by peers so far.
+    pub bytes_total: u64,
+    pub bytes_downloaded: u64,
+    /// Size of the content that was already in the store.
+    pub bytes_local: u64,
+    /// Progress by peer node ID. A raw blob is fetched from one peer at a time, so its bytes are
+    /// counted under the first peer of the request.
+    pub peers: BTreeMap<String, PeerProgress>,
+    pub error: Option<String>,
+    /// Unix time in seconds.
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+/// The downloads started through `/blobs/downloads`, shared through `AppState`.
+///
+/// Downloads are kept in memory only: the content they fetched stays in the store, but their
+/// status is lost when the node restarts.
+#[derive(Clone)]
+pub struct DownloadManager {
+    jobs: Arc<RwLock<HashMap<String, DownloadJob>>>,
+    connections_per_peer: usize,
+    limits: OperationLimits,
+}
+
+impl DownloadManager {
+    /// A manager fetching at most `connections_per_peer` blobs at once from each peer, each
+    /// download taking a download slot of `limits` while it runs.
+    pub fn new(connections_per_peer: usize, limits: OperationLimits) -> Self {
+        Self { jobs: Arc::default(), connections_per_peer, limits }
+    }
+
+    pub fn limits(&self) -> &OperationLimits {
+        &self.limits
+    }
+
+    pub fn connections_per_peer(&self) -> usize {
+        self.connections_per_peer
+    }
+
+    /// Records a new download of `hash` from `peers` and returns its status.
+    pub fn start(&self, id: String, hash: String, format: String, peers: &[String]) -> DownloadJob {
+        let job = DownloadJob {
+            id: id.clone(),
+            hash,
+            format,
+            state: DownloadState::Running,
+            blobs_total: 1,
+            blobs_done: 0,
+            bytes_total: 0,
+            bytes_downloaded: 0,
+            bytes_local: 0,
+            peers: peers.iter().map(|peer| (peer.clone(), PeerProgress::default())).collect(),
+            error: None,
+            started_at: now(),
+            finished_at: None,
+        };
+        let mut jobs = self.jobs.write().unwrap();
+        forget_oldest_finished(&mut jobs);
+        jobs.insert(id, job.clone());
+        job
+    }
+
+    pub fn get(&self, id: &str) -> Option<DownloadJob> {
+        self.jobs.read().unwrap().get(id).cloned()
+    }
+
+    /// Every download, the most recent first.
+    pub fn list(&self) -> Vec<DownloadJob> {
+        let mut jobs: Vec<DownloadJob> = self.jobs.read().unwrap().values().cloned().collect();
+        jobs.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| a.id.cmp(&b.id)));
+        jobs
+    }
+
+    /// Records the number of blobs of a collection, itself included.
+    pub fn set_blobs_total(&self, id: &str, blobs_total: u64) {
+        self.update(id, |job| job.blobs_total = blobs_total);
+    }
+
+    /// Records the size of a blob announced by a peer.
+    pub fn record_found(&self, id: &str, size: u64) {
+        self.update(id, |job| job.bytes_total += size);
+    }
+
+    /// Records a blob that was already in the store.
+    pub fn record_found_local(&self, id: &str, size: u64) {
+        self.update(id, |job| job.bytes_local += size);
+    }
+
+    /// Records `bytes` received from `peer`.
+    pub fn record_bytes(&self, id: &str, peer: &str, bytes: u64) {
+        self.update(id, |job| {
+            job.bytes_downloaded += bytes;
+            job.peers.entry(peer.to_string()).or_default().bytes += bytes;
+        });
+    }
+
+    /// Records a blob fetched from `peer`, or found locally when `peer` is `None`.
+    pub fn record_blob_done(&self, id: &str, peer: Option<&str>) {
+        self.update(id, |job| {
+            job.blobs_done += 1;
+            if let Some(peer) = peer {
+                job.peers.entry(peer.to_string()).or_default().blobs += 1;
+            }
+        });
+    }
+
+    pub fn record_peer_failure(&self, id: &str, peer: &str) {
+        self.update(id, |job| job.peers.entry(peer.to_string()).or_default().failures += 1);
+    }
+
+    /// Marks the download as done, or failed with `error`.
+    pub fn finish(&self, id: &str, error: Option<String>) {
+        self.update(id, |job| {
+            job.state = if error.is_some() { DownloadState::Failed } else { DownloadState::Done };
+            job.error = error;
+            job.finished_at = Some(now());
+        });
+    }
+
+    fn update(&self, id: &str, apply: impl FnOnce(&mut DownloadJob)) {
+        if let Some(job) = self.jobs.write().unwrap().get_mut(id) {
+            apply(job);
+        }
+    }
+}
+
+// Keeps the finished downloads under `MAX_FINISHED_DOWNLOADS` before one more is added
+fn forget_oldest_finished(jobs: &mut HashMap<String, DownloadJob>) {
+    let mut finished: Vec<(u64, String)> = jobs
+        .values()
+        .filter_map(|job| job.finished_at.map(|finished_at| (finished_at, job.id.clone())))
+        .collect();
+    if finished.len() < MAX_FINISHED_DOWNLOADS {
+        return;
+    }
+    finished.sort();
+    for (_, id) in finished.iter().take(finished.len() + 1 - MAX_FINISHED_DOWNLOADS) {
+        jobs.remove(id);
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
@@ -0,0 +1,275 @@
+use serde::Serialize;
+use std::{collections::{BTreeMap, HashMap, HashSet}, sync::{Arc, RwLock}};
+
+/// Entries and bytes an author holds in a document.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AuthorStats {
+    pub entries: u64,
+    pub bytes: u64,
+    /// Timestamp in microseconds of the author's latest entry.
+    pub last_write: u64,
+}
+
+/// Counts of a document's entries, deletions excluded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DocStats {
+    /// Distinct keys, whichever authors wrote them.
+    pub keys: u64,
+    /// Entries of every author.
+    pub entries: u64,
+    pub bytes: u64,
+    pub authors: BTreeMap<String, AuthorStats>,
+}
+
+/// An entry whose indexed field matched, see `EntryIndex::find_by_field`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct IndexedEntry {
+    pub key: String,
+    pub author: String,
+}
+
+/// An entry given to the index, with its key as stored, before decoding.
+#[derive(Debug, Clone)]
+pub struct IndexUpdate {
+    pub raw_key: Vec<u8>,
+    pub key: String,
+    pub author: String,
+    pub hash: String,
+    pub len: u64,
+    pub timestamp: u64,
+}
+
+// An entry by author and key as stored
+type EntryId = (String, Vec<u8>);
+
+// What the index keeps of one entry, to undo it once the entry is replaced or deleted
+struct Indexed {
+    key: String,
+    hash: String,
+    len: u64,
+    fields: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+struct DocIndex {
+    // false until the first scan of the document is done
+    ready: bool,
+    entries: HashMap<EntryId, Indexed>,
+    authors_per_key: HashMap<Vec<u8>, u64>,
+    stats: DocStats,
+    // field -> value -> entries
+    fields: HashMap<String, HashMap<String, HashSet<EntryId>>>,
+    // content hash -> entries whose fields wait for it
+    pending: HashMap<String, HashSet<EntryId>>,
+}
+
+/// Key counts, author stats and field indexes of every document, shared through `AppState`, so
+/// that stats and field lookups skip scanning the document.
+///
+/// It is kept up to date by `node::entry_indexer` from the documents' live events, and is kept in
+/// memory only, so each document is scanned once whenever the node starts.
+#[derive(Clone, Default)]
+pub struct EntryIndex {
+    docs: Arc<RwLock<HashMap<String, DocIndex>>>,
+    fields: Arc<Vec<String>>,
+}
+
+impl EntryIndex {
+    /// Indexes the values of the top-level JSON `fields` of entries, see `--index-field`.
+    pub fn new(fields: Vec<String>) -> Self {
+        Self { docs: Arc::default(), fields: Arc::new(fields) }
+    }
+
+    /// The top-level JSON fields indexed.
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Drops what is indexed of `doc_id` to rebuild it from a scan of the document.
+    pub fn reset(&self, doc_id: &str) {
+        self.docs.write().unwrap().insert(doc_id.to_string(), DocIndex::default());
+    }
+
+    /// Marks the scan of `doc_id` as done, from then on its stats and lookups are answered.
+    pub fn mark_ready(&self, doc_id: &str) {
+        if let Some(index) = self.docs.write().unwrap().get_mut(doc_id) {
+            index.ready = true;
+        }
+    }
+
+    /// Records an entry inserted in `doc_id`. An empty entry is a deletion of every entry of its
+    /// author whose key starts with its key.
+    ///
+    /// `content` is the entry's value if it is available, otherwise its fields are indexed once
+    /// `record_content` is given the content of its hash.
+    pub fn record_entry(&self, doc_id: &str, update: IndexUpdate, content: Option<&[u8]>) {
+        let mut docs = self.docs.write().unwrap();
+        let index = docs.entry(doc_id.to_string()).or_default();
+
+        if update.len == 0 {
+            let deleted: Vec<_> = index
+                .entries
+                .keys()
+                .filter(|(author, raw_key)| *author == update.author && raw_key.starts_with(&update.raw_key))
+                .cloned()
+                .collect();
+            for id in deleted {
+                index.remove(&id);
+            }
+            return;
+        }
+
+        let id = (update.author.clone(), update.raw_key.clone());
+        index.remove(&id);
+
+        let fields = match content {
+            Some(content) => extract_fields(&self.fields, content),
+            None => {
+                if !self.fields.is_empty() {
+                    index.pending.entry(update.hash.clone()).or_default().insert(id.clone());
+                }
+                Vec::new()
+            }
+        };
+        index.insert(id, update, fields);
+    }
+
+    /// Indexes the fields of the entries of `doc_id` that waited for the content `hash`.
+    pub fn record_content(&self, doc_id: &str, hash: &str, content: &[u8]) {
+        let mut docs = self.docs.write().unwrap();
+        let Some(index) = docs.get_mut(doc_id) else {
+            return;
+        };
+        let Some(waiting) = index.pending.remove(hash) else {
+            return;
+        };
+        let fields = extract_fields(&self.fields, content);
+        for id in waiting {
+            let Some(entry) = index.entries.get_mut(&id) else {
+                continue;
+            };
+            if entry.hash != hash {
+                continue;
+            }
+            entry.fields = fields.clone();
+            for (field, value) in &fields {
+                index.fields.entry(field.clone()).or_default().entry(value.clone()).or_default().insert(id.clone());
+            }
+        }
+    }
+
+    /// Whether some entry of `doc_id` waits for the content `hash` to index its fields.
+    pub fn is_pending(&self, doc_id: &str, hash: &str) -> bool {
+        self.docs.read().unwrap().get(doc_id).is_some_and(|index| index.pending.contains_key(hash))
+    }
+
+    /// The stats of `doc_id`, `None` if it is not indexed yet.
+    pub fn stats(&self, doc_id: &str) -> Option<DocStats> {
+        let docs = self.docs.read().unwrap();
+        docs.get(doc_id).filter(|index| index.ready).map(|index| index.stats.clone())
+    }
+
+    /// The entries of `doc_id` whose `field` has `value`, sorted by key then author. `None` if
+    /// the document is not indexed yet.
+    pub fn find_by_field(&self, doc_id: &str, field: &str, value: &str) -> Option<Vec<IndexedEntry>> {
+        let docs = self.docs.read().unwrap();
+        let index = docs.get(doc_id).filter(|index| index.ready)?;
+        let mut found: Vec<_> = index
+            .fields
+            .get(field)
+            .and_then(|values| values.get(value))
+            .into_iter()
+            .flatten()
+            .filter_map(|id| index.entries.get(id).map(|entry| IndexedEntry { key: entry.key.clone(), author: id.0.clone() }))
+            .collect();
+        found.sort();
+        Some(found)
+    }
+
+    /// Forgets `doc_id`, once it is dropped.
+    pub fn forget(&self, doc_id: &str) {
+        self.docs.write().unwrap().remove(doc_id);
+    }
+}
+
+impl DocIndex {
+    fn insert(&mut self, id: EntryId, update: IndexUpdate, fields: Vec<(String, String)>) {
+        *self.authors_per_key.entry(id.1.clone()).or_default() += 1;
+        if self.authors_per_key[&id.1] == 1 {
+            self.stats.keys += 1;
+        }
+        self.stats.entries += 1;
+        self.stats.bytes += update.len;
+        let author = self.stats.authors.entry(id.0.clone()).or_default();
+        author.entries += 1;
+        author.bytes += update.len;
+        author.last_write = author.last_write.max(update.timestamp);
+
+        for (field, value) in &fields {
+            self.fields.entry(field.clone()).or_default().entry(value.clone()).or_default().insert(id.clone());
+        }
+        self.entries.insert(id, Indexed { key: update.key, hash: update.hash, len: update.len, fields });
+    }
+
+    fn remove(&mut self, id: &EntryId) {
+        let Some(entry) = self.entries.remove(id) else {
+            return;
+        };
+        if let Some(authors) = self.authors_per_key.get_mut(&id.1) {
+            *authors -= 1;
+            if *authors == 0 {
+                self.authors_per_key.remove(&id.1);
+                self.stats.keys -= 1;
+            }
+        }
+        self.stats.entries -= 1;
+        self.stats.bytes -= entry.len;
+        if let Some(author) = self.stats.authors.get_mut(&id.0) {
+            author.entries -= 1;
+            author.bytes -= entry.len;
+            if author.entries == 0 {
+                self.stats.authors.remove(&id.0);
+            }
+        }
+
+        for (field, value) in entry.fields {
+            let Some(values) = self.fields.get_mut(&field) else {
+                continue;
+            };
+            if let Some(ids) = values.get_mut(&value) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    values.remove(&value);
+                }
+            }
+        }
+        if let Some(waiting) = self.pending.get_mut(&entry.hash) {
+            waiting.remove(id);
+            if waiting.is_empty() {
+                self.pending.remove(&entry.hash);
+            }
+        }
+    }
+}
+
+// The values of the indexed fields of a JSON object, scalars only. Strings are indexed as they
+// are, numbers and booleans as their JSON text.
+fn extract_fields(fields: &[String], content: &[u8]) -> Vec<(String, String)> {
+    if fields.is_empty() {
+        return Vec::new();
+    }
+    let Ok(serde_json::Value::Object(object)) = serde_json::from_slice::<serde_json::Value>(content) else {
+        return Vec::new();
+    };
+    fields
+        .iter()
+        .filter_map(|field| {
+            let value = match object.get(field)? {
+                serde_json::Value::String(value) => value.clone(),
+                value @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_)) => value.to_string(),
+                _ => return None,
+            };
+            Some((field.clone(), value))
+        })
+        .collect()
+}
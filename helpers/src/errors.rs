@@ -0,0 +1,71 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// Set once at startup from `--debug-errors`
+static DEBUG_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Makes error messages include their causes, so API error responses carry the underlying
+/// iroh, IO or chain error. They may reveal paths and internals, so this is off by default.
+pub fn set_debug_errors(enabled: bool) {
+    DEBUG_ERRORS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn debug_errors() -> bool {
+    DEBUG_ERRORS.load(Ordering::Relaxed)
+}
+
+/// The error behind a failed operation, carried by the `FailedTo...` variants of the error
+/// enums of `core`.
+///
+/// Causes are left out of comparisons: two errors of the same variant are equal whatever caused
+/// them, so callers keep matching on the variant alone.
+#[derive(Clone)]
+pub struct Cause(Arc<dyn Error + Send + Sync>);
+
+impl Cause {
+    pub fn new(error: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        Self(Arc::from(error.into()))
+    }
+}
+
+impl fmt::Debug for Cause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Cause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl Error for Cause {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl PartialEq for Cause {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// Writes the name of the variant of `error`, which API clients match on, followed with
+/// `--debug-errors` by the chain of its causes.
+pub fn fmt_error<E: Error + fmt::Debug>(error: &E, f: &mut fmt::Formatter) -> fmt::Result {
+    // variants are unit-like or carry only their cause, so the name ends at the first parenthesis
+    let debug = format!("{:?}", error);
+    f.write_str(debug.split('(').next().unwrap_or(&debug))?;
+    if debug_errors() {
+        let mut source = error.source();
+        while let Some(cause) = source {
+            write!(f, ": {}", cause)?;
+            source = cause.source();
+        }
+    }
+    Ok(())
+}
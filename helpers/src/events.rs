@@ -0,0 +1,51 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+// Number of events a slow subscriber may fall behind before it starts missing events.
+const EVENT_BUS_CAPACITY: usize = 1024;
+
+/// Node-level events published by the API handlers.
+///
+/// These complement the per-document events from `iroh_docs` with changes that are not
+/// tied to a single document subscription, such as documents being created or dropped.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeEvent {
+    DocCreated { doc_id: String },
+    DocDropped { doc_id: String },
+    DocJoined { doc_id: String },
+    AuthorCreated { author_id: String },
+    AuthorDeleted { author_id: String },
+    BlobAdded { hash: String, size: u64 },
+    TagDeleted { tag: String },
+}
+
+/// A broadcast bus for `NodeEvent`s, shared through `AppState`.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<NodeEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        Self { sender }
+    }
+
+    /// Publishes an event to all current subscribers. Events published while nobody is
+    /// subscribed are dropped.
+    pub fn publish(&self, event: NodeEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Returns a receiver for all events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<NodeEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
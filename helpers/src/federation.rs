@@ -0,0 +1,169 @@
+use crate::cli::CliArgs;
+use crate::utils::SS58AuthorId;
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf, sync::{Arc, RwLock}, time::Duration};
+use tokio::{fs, sync::Notify};
+
+// File the documents joined through the catalog are persisted to, inside the node's data directory.
+const FEDERATION_FILE: &str = "federation.json";
+
+/// The catalog a node follows and whose entries it trusts, from the `--federation-*` flags.
+#[derive(Debug, Clone, Default)]
+pub struct FederationSettings {
+    /// Ticket or document ID of the catalog, `None` if federation is off.
+    pub catalog: Option<String>,
+    /// SS58 authors whose catalog entries are followed.
+    pub trusted_authors: Vec<String>,
+    /// Time between checks of the catalog when it does not change.
+    pub interval: Duration,
+}
+
+impl FederationSettings {
+    /// The settings of the `--federation-*` flags of `serve`.
+    pub fn from_args(args: &CliArgs) -> Result<Self, String> {
+        if args.federation_catalog.as_deref().is_some_and(|catalog| catalog.trim().is_empty()) {
+            return Err("❌ --federation-catalog must be a ticket or a document ID.".to_string());
+        }
+        if args.federation_catalog.is_none() && !args.federation_trusted_author.is_empty() {
+            return Err("❌ --federation-trusted-author needs --federation-catalog.".to_string());
+        }
+        if args.federation_catalog.is_some() && args.federation_trusted_author.is_empty() {
+            return Err("❌ --federation-catalog needs at least one --federation-trusted-author.".to_string());
+        }
+        if args.federation_interval == 0 {
+            return Err("❌ --federation-interval must be greater than 0.".to_string());
+        }
+        for author in &args.federation_trusted_author {
+            if SS58AuthorId::decode(author).is_err() {
+                return Err(format!("❌ --federation-trusted-author {} is not an SS58 author ID.", author));
+            }
+        }
+
+        Ok(Self {
+            catalog: args.federation_catalog.clone(),
+            trusted_authors: args.federation_trusted_author.clone(),
+            interval: Duration::from_secs(args.federation_interval),
+        })
+    }
+}
+
+/// How a document listed in the catalog is replicated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemberState {
+    /// Joined through the catalog, and left once it is delisted.
+    Joined,
+    /// Already on the node when it was listed, so it is left alone when delisted.
+    Present,
+    /// Joining failed. It is tried again at the next check.
+    Failed,
+}
+
+/// A document of the catalog, as this node replicates it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FederationMember {
+    pub doc_id: String,
+    pub state: MemberState,
+    /// Author of the catalog entry that lists the document.
+    pub listed_by: String,
+    pub label: Option<String>,
+    /// Unix time in seconds the state was last changed.
+    pub updated_at: u64,
+    /// Why joining failed, for `failed` members.
+    pub error: Option<String>,
+}
+
+/// The federation settings and the catalog documents this node replicates, shared through
+/// `AppState` and persisted as JSON.
+///
+/// Members are kept in line with the catalog by `core::federation`, which is woken through
+/// `changed` whenever they change.
+#[derive(Clone, Default)]
+pub struct FederationRegistry {
+    settings: FederationSettings,
+    // document ID of the catalog, once it is open
+    catalog_doc_id: Arc<RwLock<Option<String>>>,
+    members: Arc<RwLock<BTreeMap<String, FederationMember>>>,
+    changed: Arc<Notify>,
+    storage_path: Option<PathBuf>,
+}
+
+impl FederationRegistry {
+    /// Loads the members from the data directory at `path`, starting empty if nothing was saved yet.
+    pub async fn load(path: &str, settings: FederationSettings) -> anyhow::Result<Self> {
+        let storage_path = PathBuf::from(path).join(FEDERATION_FILE);
+
+        let members = if fs::try_exists(&storage_path).await? {
+            let content = fs::read_to_string(&storage_path).await?;
+            serde_json::from_str(&content)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            settings,
+            catalog_doc_id: Arc::default(),
+            members: Arc::new(RwLock::new(members)),
+            changed: Arc::default(),
+            storage_path: Some(storage_path),
+        })
+    }
+
+    pub fn settings(&self) -> &FederationSettings {
+        &self.settings
+    }
+
+    /// Whether the node follows a catalog.
+    pub fn is_enabled(&self) -> bool {
+        self.settings.catalog.is_some()
+    }
+
+    /// Whether catalog entries of `author_id` are followed.
+    pub fn is_trusted(&self, author_id: &str) -> bool {
+        self.settings.trusted_authors.iter().any(|trusted| trusted == author_id)
+    }
+
+    /// Document ID of the catalog, once it is open.
+    pub fn catalog_doc_id(&self) -> Option<String> {
+        self.catalog_doc_id.read().unwrap().clone()
+    }
+
+    pub fn set_catalog_doc_id(&self, doc_id: String) {
+        *self.catalog_doc_id.write().unwrap() = Some(doc_id);
+    }
+
+    pub fn members(&self) -> BTreeMap<String, FederationMember> {
+        self.members.read().unwrap().clone()
+    }
+
+    /// Records how `member` is replicated.
+    pub async fn set_member(&self, member: FederationMember) -> anyhow::Result<()> {
+        self.members.write().unwrap().insert(member.doc_id.clone(), member);
+        self.save().await
+    }
+
+    /// Forgets `doc_id`, once it is no longer listed.
+    pub async fn remove_member(&self, doc_id: &str) -> anyhow::Result<()> {
+        self.members.write().unwrap().remove(doc_id);
+        self.save().await
+    }
+
+    /// Notified when the catalog was changed through the API, to check it right away.
+    pub fn changed(&self) -> Arc<Notify> {
+        self.changed.clone()
+    }
+
+    pub fn notify_changed(&self) {
+        self.changed.notify_one();
+    }
+
+    async fn save(&self) -> anyhow::Result<()> {
+        let Some(storage_path) = &self.storage_path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&*self.members.read().unwrap())?;
+        fs::write(storage_path, json).await?;
+        Ok(())
+    }
+}
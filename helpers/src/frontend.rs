@@ -7,7 +7,7 @@ pub fn start_frontend() {
         .spawn();
 
     match frontend {
-        Ok(_) => println!("✅ Frontend server started on http://localhost:3000"),
-        Err(e) => eprintln!("❌ Failed to start frontend server: {}", e),
+        Ok(_) => tracing::info!(url = "http://localhost:3000", "✅ Frontend server started"),
+        Err(e) => tracing::error!(error = %e, "❌ Failed to start frontend server"),
     }
 }
\ No newline at end of file
@@ -0,0 +1,390 @@
+use anyhow::anyhow;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDate, TimeZone, Timelike, Utc};
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt, future::Future, pin::Pin, str::FromStr, sync::{Arc, Mutex}, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+// Years a schedule is searched ahead for its next run before it is taken to never run.
+const MAX_YEARS_AHEAD: i32 = 5;
+
+/// The maintenance tasks the node can run as jobs, on a schedule or on request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobKind {
+    /// Takes a snapshot into `--backup-dir` and prunes to `--backup-keep` snapshots.
+    Backup,
+    /// Anchors the proofs queued with `"batch": true`.
+    AnchorProofs,
+    /// Reports the documents that never synced, have no peers or have content to download.
+    ReplicationCheck,
+    /// Deletes the entries kept in the trash past `--trash-retention`.
+    TrashPurge,
+    /// Deletes the versions of entries kept past `--version-retention`.
+    HistoryPurge,
+    /// Deletes the blobs no tag, document entry or running operation holds.
+    BlobGc,
+}
+
+impl JobKind {
+    pub const ALL: [JobKind; 6] = [
+        JobKind::Backup,
+        JobKind::AnchorProofs,
+        JobKind::ReplicationCheck,
+        JobKind::TrashPurge,
+        JobKind::HistoryPurge,
+        JobKind::BlobGc,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobKind::Backup => "backup",
+            JobKind::AnchorProofs => "anchor-proofs",
+            JobKind::ReplicationCheck => "replication-check",
+            JobKind::TrashPurge => "trash-purge",
+            JobKind::HistoryPurge => "history-purge",
+            JobKind::BlobGc => "blob-gc",
+        }
+    }
+
+    /// Whether the job writes to documents, which a read-only replica does not do.
+    pub fn writes(&self) -> bool {
+        matches!(self, JobKind::TrashPurge | JobKind::HistoryPurge)
+    }
+}
+
+impl fmt::Display for JobKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for JobKind {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> anyhow::Result<Self> {
+        JobKind::ALL.into_iter().find(|kind| kind.name() == name).ok_or_else(|| {
+            let names: Vec<_> = JobKind::ALL.iter().map(JobKind::name).collect();
+            anyhow!("❌ Unknown job '{}'. Expected one of: {}", name, names.join(", "))
+        })
+    }
+}
+
+/// A cron schedule of five fields, `minute hour day-of-month month day-of-week`, in UTC.
+///
+/// Each field is `*`, a number, a range `a-b`, a step `*/n` or `a-b/n`, or a comma-separated list
+/// of those. Sunday is 0 or 7. As in cron, when both the day of the month and the day of the week
+/// are restricted, a day matching either one runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> anyhow::Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(anyhow!(
+                "❌ Invalid schedule '{}'. Expected 5 fields: minute hour day-of-month month day-of-week.",
+                expression
+            ));
+        };
+
+        let mut weekday_set = parse_field(weekdays, 0, 7, "day-of-week")?;
+        // 7 is another name for Sunday
+        if weekday_set[7] {
+            weekday_set[0] = true;
+        }
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: parse_field(minutes, 0, 59, "minute")?,
+            hours: parse_field(hours, 0, 23, "hour")?,
+            days: parse_field(days, 1, 31, "day-of-month")?,
+            months: parse_field(months, 1, 12, "month")?,
+            weekdays: weekday_set,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// The first minute after `after` the schedule runs at, or `None` if it never does, such as
+    /// on February 30th.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let mut time = start;
+
+        while time.year() <= start.year() + MAX_YEARS_AHEAD {
+            if !self.months[time.month() as usize] {
+                let (year, month) = if time.month() == 12 { (time.year() + 1, 1) } else { (time.year(), time.month() + 1) };
+                time = Utc.from_utc_datetime(&NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?);
+                continue;
+            }
+            if !self.matches_day(time) {
+                time = Utc.from_utc_datetime(&time.date_naive().succ_opt()?.and_hms_opt(0, 0, 0)?);
+                continue;
+            }
+            if !self.hours[time.hour() as usize] {
+                time = time.with_minute(0)? + ChronoDuration::hours(1);
+                continue;
+            }
+            if !self.minutes[time.minute() as usize] {
+                time += ChronoDuration::minutes(1);
+                continue;
+            }
+            return Some(time);
+        }
+        None
+    }
+
+    fn matches_day(&self, time: DateTime<Utc>) -> bool {
+        let day = self.days[time.day() as usize];
+        let weekday = self.weekdays[time.weekday().num_days_from_sunday() as usize];
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+// The values `min..=max` a cron field allows, indexed by value
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> anyhow::Result<Vec<bool>> {
+    let invalid = || anyhow!("❌ Invalid {} '{}'. Expected *, a number from {} to {}, a range or a step.", name, field, min, max);
+    let number = |value: &str| value.parse::<u32>().ok().filter(|n| (min..=max).contains(n)).ok_or_else(invalid);
+
+    let mut allowed = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0).ok_or_else(invalid)?),
+            None => (part, 1),
+        };
+        let (first, last) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((first, last)) => (number(first)?, number(last)?),
+                // `n/step` runs from n to the end of the field
+                None if step > 1 => (number(range)?, max),
+                None => (number(range)?, number(range)?),
+            },
+        };
+        if first > last {
+            return Err(invalid());
+        }
+        for value in (first..=last).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+    Ok(allowed)
+}
+
+/// A job and when it runs, from `--job <name>=<schedule>`.
+#[derive(Debug, Clone)]
+pub struct JobSpec {
+    pub kind: JobKind,
+    pub schedule: CronSchedule,
+}
+
+impl JobSpec {
+    /// Parses `<name>=<schedule>`, such as `backup=0 3 * * *`.
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let (name, schedule) = spec
+            .split_once('=')
+            .ok_or_else(|| anyhow!("❌ Invalid job '{}'. Expected <name>=<schedule>, such as backup=0 3 * * *.", spec))?;
+        Ok(Self {
+            kind: name.trim().parse()?,
+            schedule: CronSchedule::parse(schedule)?,
+        })
+    }
+}
+
+/// Why a job run was started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobTrigger {
+    Schedule,
+    Manual,
+}
+
+/// The outcome of a finished job run.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRun {
+    pub trigger: JobTrigger,
+    /// Seconds since the Unix epoch.
+    pub started_at: u64,
+    /// Seconds since the Unix epoch.
+    pub finished_at: u64,
+    pub success: bool,
+    /// What the job did, or why it failed.
+    pub message: String,
+}
+
+/// A job, its schedule and its last run, as served by `GET /admin/jobs`.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub name: JobKind,
+    /// The cron expression the job runs on, `None` if it only runs on request.
+    pub schedule: Option<String>,
+    /// Seconds since the Unix epoch of the next scheduled run.
+    pub next_run: Option<u64>,
+    pub running: bool,
+    /// Seconds since the Unix epoch the current run started at, while `running`.
+    pub running_since: Option<u64>,
+    pub last_run: Option<JobRun>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum JobError {
+    /// The job is not available on this node.
+    NotFound,
+    /// A run of the job has not finished yet.
+    AlreadyRunning,
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for JobError {}
+
+/// What a job does. Resolves to a summary of what it did, or why it failed.
+pub type JobRunner = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Result<String, String>> + Send>> + Send + Sync>;
+
+struct JobSlot {
+    runner: JobRunner,
+    schedule: Option<CronSchedule>,
+    next_run: Option<DateTime<Utc>>,
+    running_since: Option<u64>,
+    last_run: Option<JobRun>,
+}
+
+/// The jobs of the node, shared through `AppState`. Runs them on request and records the outcome
+/// of their last run. The schedules are kept here and run by the node's job scheduler.
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+    jobs: Arc<Mutex<BTreeMap<JobKind, JobSlot>>>,
+}
+
+impl JobRegistry {
+    /// Adds the job `kind`, run by `runner` on `schedule` if given, and otherwise only on request.
+    pub fn register(&self, kind: JobKind, schedule: Option<CronSchedule>, runner: JobRunner) {
+        let next_run = schedule.as_ref().and_then(|schedule| schedule.next_after(Utc::now()));
+        self.jobs.lock().unwrap().insert(kind, JobSlot { runner, schedule, next_run, running_since: None, last_run: None });
+    }
+
+    pub fn list(&self) -> Vec<JobStatus> {
+        self.jobs.lock().unwrap().iter().map(|(kind, slot)| status(*kind, slot)).collect()
+    }
+
+    pub fn get(&self, kind: JobKind) -> Option<JobStatus> {
+        self.jobs.lock().unwrap().get(&kind).map(|slot| status(kind, slot))
+    }
+
+    /// Whether `kind` runs on a schedule.
+    pub fn is_scheduled(&self, kind: JobKind) -> bool {
+        self.jobs.lock().unwrap().get(&kind).is_some_and(|slot| slot.schedule.is_some())
+    }
+
+    /// How long until the next scheduled run of any job, `None` if no job is scheduled.
+    pub fn until_next_run(&self) -> Option<Duration> {
+        let now = Utc::now();
+        self.jobs
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|slot| slot.next_run)
+            .min()
+            .map(|next_run| (next_run - now).to_std().unwrap_or_default())
+    }
+
+    /// Starts the scheduled runs that are due, and moves their next run to the following one.
+    /// A job still running when its time comes skips that run.
+    pub fn run_due(&self) {
+        let now = Utc::now();
+        let due: Vec<JobKind> = {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.iter_mut()
+                .filter(|(_, slot)| slot.next_run.is_some_and(|next_run| next_run <= now))
+                .map(|(kind, slot)| {
+                    slot.next_run = slot.schedule.as_ref().and_then(|schedule| schedule.next_after(now));
+                    *kind
+                })
+                .collect()
+        };
+        for kind in due {
+            if let Err(JobError::AlreadyRunning) = self.start(kind, JobTrigger::Schedule) {
+                tracing::warn!(job = %kind, "⏭ Skipped scheduled run, the previous run has not finished");
+            }
+        }
+    }
+
+    /// Starts a run of `kind` in the background.
+    ///
+    /// # Returns
+    /// * `u64` - When the run started, in seconds since the Unix epoch.
+    pub fn start(&self, kind: JobKind, trigger: JobTrigger) -> Result<u64, JobError> {
+        let started_at = now_secs();
+        let runner = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let slot = jobs.get_mut(&kind).ok_or(JobError::NotFound)?;
+            if slot.running_since.is_some() {
+                return Err(JobError::AlreadyRunning);
+            }
+            slot.running_since = Some(started_at);
+            slot.runner.clone()
+        };
+
+        let jobs = self.jobs.clone();
+        tokio::spawn(async move {
+            tracing::info!(job = %kind, ?trigger, "⚙️ Job started");
+            let result = runner().await;
+            match &result {
+                Ok(message) => tracing::info!(job = %kind, %message, "⚙️ Job finished"),
+                Err(e) => tracing::warn!(job = %kind, error = %e, "job failed"),
+            }
+
+            let mut jobs = jobs.lock().unwrap();
+            if let Some(slot) = jobs.get_mut(&kind) {
+                slot.running_since = None;
+                slot.last_run = Some(JobRun {
+                    trigger,
+                    started_at,
+                    finished_at: now_secs(),
+                    success: result.is_ok(),
+                    message: result.unwrap_or_else(|e| e),
+                });
+            }
+        });
+        Ok(started_at)
+    }
+}
+
+fn status(kind: JobKind, slot: &JobSlot) -> JobStatus {
+    JobStatus {
+        name: kind,
+        schedule: slot.schedule.as_ref().map(|schedule| schedule.expression().to_string()),
+        next_run: slot.next_run.map(|next_run| next_run.timestamp() as u64),
+        running: slot.running_since.is_some(),
+        running_since: slot.running_since,
+        last_run: slot.last_run.clone(),
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
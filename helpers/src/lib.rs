@@ -1,4 +1,31 @@
+pub mod access_log;
+pub mod approvals;
+pub mod authorizations;
+pub mod backups;
+pub mod blob_cache;
+pub mod blob_listing;
 pub mod cli;
+pub mod config;
+pub mod doc_handles;
+pub mod downloads;
+pub mod entry_index;
+pub mod errors;
+pub mod events;
+pub mod federation;
+pub mod limits;
 pub mod frontend;
+pub mod jobs;
+pub mod notifications;
+pub mod pagination;
+pub mod proofs;
+pub mod public;
+pub mod replication;
+pub mod schema_refs;
+pub mod search;
 pub mod state;
+pub mod stores;
+pub mod sync_controls;
+pub mod tenants;
 pub mod utils;
+pub mod webhooks;
+pub mod write_queue;
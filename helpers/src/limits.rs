@@ -0,0 +1,108 @@
+use serde::Serialize;
+use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// The kinds of expensive operations limited by `OperationLimits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationKind {
+    /// Imports of local files and archives into the store.
+    Import,
+    /// Exports of blobs and documents to files or response bodies.
+    Export,
+    /// Downloads of blobs from peers.
+    Download,
+    /// Listings and queries that walk a whole document or store.
+    Scan,
+}
+
+impl OperationKind {
+    pub const ALL: [OperationKind; 4] = [Self::Import, Self::Export, Self::Download, Self::Scan];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Import => "import",
+            Self::Export => "export",
+            Self::Download => "download",
+            Self::Scan => "scan",
+        }
+    }
+}
+
+// The limit of one kind of operation
+struct Limit {
+    capacity: usize,
+    permits: Arc<Semaphore>,
+    waiting: AtomicUsize,
+}
+
+/// How many operations of a kind run at once, and how many wait for a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct OperationCounts {
+    pub in_flight: usize,
+    pub waiting: usize,
+    pub limit: usize,
+}
+
+/// Caps on how many expensive operations of each kind run at once, shared through `AppState`, so
+/// that a burst of heavy requests queues up instead of exhausting file handles and memory.
+///
+/// Operations over the cap wait for a slot, within the timeout of their route.
+#[derive(Clone)]
+pub struct OperationLimits {
+    limits: Arc<[Limit; 4]>,
+}
+
+/// A slot taken by a running operation, freed when dropped.
+pub struct OperationPermit {
+    _permit: OwnedSemaphorePermit,
+}
+
+impl OperationLimits {
+    /// Limits of `--max-concurrent-imports`, `--max-concurrent-exports`,
+    /// `--max-concurrent-downloads` and `--max-concurrent-scans`.
+    pub fn new(imports: usize, exports: usize, downloads: usize, scans: usize) -> Self {
+        let limit = |capacity: usize| Limit {
+            capacity,
+            permits: Arc::new(Semaphore::new(capacity)),
+            waiting: AtomicUsize::new(0),
+        };
+        Self { limits: Arc::new([limit(imports), limit(exports), limit(downloads), limit(scans)]) }
+    }
+
+    fn limit(&self, kind: OperationKind) -> &Limit {
+        &self.limits[kind as usize]
+    }
+
+    /// Waits for a slot to run an operation of `kind`.
+    pub async fn acquire(&self, kind: OperationKind) -> OperationPermit {
+        let limit = self.limit(kind);
+        let _waiting = Waiting::new(&limit.waiting);
+        let permit = limit.permits.clone().acquire_owned().await;
+        OperationPermit { _permit: permit.expect("operation semaphores are never closed") }
+    }
+
+    pub fn counts(&self, kind: OperationKind) -> OperationCounts {
+        let limit = self.limit(kind);
+        OperationCounts {
+            in_flight: limit.capacity - limit.permits.available_permits(),
+            waiting: limit.waiting.load(Ordering::Relaxed),
+            limit: limit.capacity,
+        }
+    }
+}
+
+// Counts an operation as waiting until dropped, also when the request is cancelled while it waits
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl<'a> Waiting<'a> {
+    fn new(waiting: &'a AtomicUsize) -> Self {
+        waiting.fetch_add(1, Ordering::Relaxed);
+        Self(waiting)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
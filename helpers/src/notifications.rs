@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::{Arc, RwLock}};
+use tokio::fs;
+
+// File the registered watches are persisted to, inside the node's data directory.
+const WATCHES_FILE: &str = "notifications.json";
+
+/// Event types a watch can be notified of.
+pub const NOTIFICATION_EVENTS: &[&str] = &["entry.set", "entry.deleted"];
+
+/// Seconds a watch collects events for before notifying, unless set at registration.
+pub const DEFAULT_BATCH_WINDOW: u64 = 60;
+
+/// Notifications a watch sends per hour at most, unless set at registration.
+pub const DEFAULT_MAX_PER_HOUR: u32 = 12;
+
+/// Where the notifications of a watch are sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotificationChannel {
+    /// POSTs each batch, signed with `secret` like webhook deliveries.
+    Webhook { url: String, secret: String },
+    /// Mails each batch to `to` through the relay set with `--smtp-relay`.
+    Email { to: String },
+    /// Posts each batch as a message to a Matrix room, as the user `access_token` belongs to.
+    Matrix { homeserver: String, room_id: String, access_token: String },
+}
+
+impl NotificationChannel {
+    /// The channel's type, as in its JSON `type` field.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NotificationChannel::Webhook { .. } => "webhook",
+            NotificationChannel::Email { .. } => "email",
+            NotificationChannel::Matrix { .. } => "matrix",
+        }
+    }
+}
+
+/// A watch on the entries of one or every document, by key prefix and author.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Watch {
+    pub id: String,
+    /// Document to watch, or `None` for every document on the node.
+    pub doc_id: Option<String>,
+    /// Only entries whose key starts with this prefix, if given.
+    pub key_prefix: Option<String>,
+    /// Only entries written by this author, if given.
+    pub author: Option<String>,
+    /// Event types to notify of. Empty means all of `NOTIFICATION_EVENTS`.
+    pub events: Vec<String>,
+    pub channel: NotificationChannel,
+    /// Seconds the events following a first one are collected for, to be sent together.
+    pub batch_window: u64,
+    /// Notifications sent per hour at most. Events keep being collected while over it.
+    pub max_per_hour: u32,
+}
+
+impl Watch {
+    /// Whether this watch wants `event` on the entry `author` wrote at `key` in `doc_id`.
+    pub fn matches(&self, doc_id: &str, event: &str, key: &str, author: &str) -> bool {
+        self.doc_id.as_deref().is_none_or(|id| id == doc_id)
+            && self.key_prefix.as_deref().is_none_or(|prefix| key.starts_with(prefix))
+            && self.author.as_deref().is_none_or(|id| id == author)
+            && (self.events.is_empty() || self.events.iter().any(|e| e == event))
+    }
+}
+
+/// How notifications are mailed, see `--smtp-relay`.
+#[derive(Debug, Clone)]
+pub struct EmailSettings {
+    /// `host:port` of the SMTP relay mail is handed to.
+    pub relay: String,
+    /// Sender address of the mails.
+    pub from: String,
+}
+
+/// The set of registered watches, shared through `AppState` and persisted as JSON.
+#[derive(Clone, Default)]
+pub struct WatchRegistry {
+    watches: Arc<RwLock<Vec<Watch>>>,
+    storage_path: Option<PathBuf>,
+    email: Option<EmailSettings>,
+}
+
+impl WatchRegistry {
+    /// Loads the registry from the data directory at `path`, starting empty if nothing was saved yet.
+    /// Email channels can only be registered with `email` settings.
+    pub async fn load(path: &str, email: Option<EmailSettings>) -> anyhow::Result<Self> {
+        let storage_path = PathBuf::from(path).join(WATCHES_FILE);
+
+        let watches = if fs::try_exists(&storage_path).await? {
+            let content = fs::read_to_string(&storage_path).await?;
+            serde_json::from_str(&content)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            watches: Arc::new(RwLock::new(watches)),
+            storage_path: Some(storage_path),
+            email,
+        })
+    }
+
+    pub fn email(&self) -> Option<&EmailSettings> {
+        self.email.as_ref()
+    }
+
+    pub fn list(&self) -> Vec<Watch> {
+        self.watches.read().unwrap().clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Watch> {
+        self.watches.read().unwrap().iter().find(|watch| watch.id == id).cloned()
+    }
+
+    /// Watches that should be notified of `event` on the entry `author` wrote at `key` in `doc_id`.
+    pub fn matching(&self, doc_id: &str, event: &str, key: &str, author: &str) -> Vec<Watch> {
+        self.watches
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|watch| watch.matches(doc_id, event, key, author))
+            .cloned()
+            .collect()
+    }
+
+    pub async fn register(&self, watch: Watch) -> anyhow::Result<()> {
+        self.watches.write().unwrap().push(watch);
+        self.save().await
+    }
+
+    /// Removes a watch by ID. Returns `false` if no watch had that ID.
+    pub async fn remove(&self, id: &str) -> anyhow::Result<bool> {
+        let removed = {
+            let mut watches = self.watches.write().unwrap();
+            let before = watches.len();
+            watches.retain(|watch| watch.id != id);
+            watches.len() != before
+        };
+        if removed {
+            self.save().await?;
+        }
+        Ok(removed)
+    }
+
+    async fn save(&self) -> anyhow::Result<()> {
+        let Some(storage_path) = &self.storage_path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&*self.watches.read().unwrap())?;
+        fs::write(storage_path, json).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use axum::body::Body;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures::{Stream, StreamExt};
+use iroh_blobs::Hash;
+use serde::{Deserialize, Serialize};
+
+/// Number of items returned when the client does not pass a `limit`.
+pub const DEFAULT_PAGE_LIMIT: usize = 100;
+/// Largest `limit` a client may request.
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+/// Query parameters shared by all list endpoints.
+///
+/// `cursor` is the opaque `next_cursor` returned with the previous page; omit it to start from the beginning.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct PaginationParams {
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl PaginationParams {
+    /// Returns the offset encoded in the cursor, or 0 for the first page.
+    pub fn offset(&self) -> Result<usize, (StatusCode, String)> {
+        match &self.cursor {
+            Some(cursor) => cursor
+                .parse::<usize>()
+                .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid cursor".to_string())),
+            None => Ok(0),
+        }
+    }
+
+    /// Returns the hash encoded in the cursor of listings paged by hash, such as blob listings,
+    /// or `None` for the first page.
+    pub fn hash_cursor(&self) -> Result<Option<Hash>, (StatusCode, String)> {
+        match &self.cursor {
+            Some(cursor) => cursor
+                .parse::<Hash>()
+                .map(Some)
+                .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid cursor".to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the requested page size, defaulting to `DEFAULT_PAGE_LIMIT`.
+    pub fn limit(&self) -> Result<usize, (StatusCode, String)> {
+        match self.limit {
+            Some(0) => Err((StatusCode::BAD_REQUEST, "limit must be greater than 0".to_string())),
+            Some(limit) if limit > MAX_PAGE_LIMIT => Err((
+                StatusCode::BAD_REQUEST,
+                format!("limit cannot be greater than {}", MAX_PAGE_LIMIT),
+            )),
+            Some(limit) => Ok(limit),
+            None => Ok(DEFAULT_PAGE_LIMIT),
+        }
+    }
+}
+
+/// A single page of a list response.
+#[derive(Serialize, Debug)]
+pub struct Paginated<T> {
+    /// The items on this page.
+    pub items: Vec<T>,
+    /// Total number of items across all pages.
+    pub total: usize,
+    /// Cursor for the next page, or `None` if this is the last page.
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Paginated<T> {
+    /// Builds a page from an already fetched page of items.
+    pub fn from_page(items: Vec<T>, offset: usize, total: usize) -> Self {
+        let end = offset + items.len();
+        let next_cursor = if end < total { Some(end.to_string()) } else { None };
+
+        Paginated { items, total, next_cursor }
+    }
+
+    /// Builds a page by slicing the full list of items.
+    pub fn from_items(items: Vec<T>, params: &PaginationParams) -> Result<Self, (StatusCode, String)> {
+        let offset = params.offset()?;
+        let limit = params.limit()?;
+        let total = items.len();
+
+        let page = items.into_iter().skip(offset).take(limit).collect();
+
+        Ok(Paginated::from_page(page, offset, total))
+    }
+
+    /// Converts the items of the page, keeping the totals and cursor.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Paginated<U> {
+        Paginated {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+/// Serves a whole list as NDJSON, one JSON item per line, written as the items are read, so
+/// that listings too large for pages are never held in memory.
+///
+/// An error while reading the items aborts the response, which clients see as an incomplete body.
+pub fn ndjson_response<T, E, S>(items: S) -> Response
+where
+    T: Serialize,
+    E: std::error::Error + Send + Sync + 'static,
+    S: Stream<Item = Result<T, E>> + Send + 'static,
+{
+    let lines = items.map(|item| {
+        let item = item.inspect_err(|e| tracing::warn!(error = %e, "listing aborted"))?;
+        let mut line = serde_json::to_vec(&item)?;
+        line.push(b'\n');
+        Ok::<_, axum::BoxError>(line)
+    });
+    ([(header::CONTENT_TYPE, "application/x-ndjson")], Body::from_stream(lines)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(cursor: &str) -> PaginationParams {
+        PaginationParams { cursor: Some(cursor.to_string()), limit: None }
+    }
+
+    #[test]
+    fn test_invalid_cursors_are_rejected() {
+        let hash = Hash::new(b"blob");
+        assert_eq!(params(&hash.to_string()).hash_cursor().unwrap(), Some(hash));
+        assert_eq!(PaginationParams::default().hash_cursor().unwrap(), None);
+        for cursor in ["", "12", "not-a-hash", &hash.to_string()[1..]] {
+            assert_eq!(params(cursor).hash_cursor().unwrap_err().0, StatusCode::BAD_REQUEST, "{:?}", cursor);
+        }
+
+        assert_eq!(params("12").offset().unwrap(), 12);
+        assert_eq!(params("-1").offset().unwrap_err().0, StatusCode::BAD_REQUEST);
+        assert_eq!(params(&hash.to_string()).offset().unwrap_err().0, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf, sync::{Arc, RwLock}};
+use tokio::fs;
+
+// Files the proof receipts and the subjects waiting for the next batch are persisted to, inside
+// the node's data directory.
+const PROOFS_FILE: &str = "proofs.json";
+const PROOF_QUEUE_FILE: &str = "proof-queue.json";
+
+/// What a proof of existence is about.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProofSubject {
+    /// A blob, proven by its hash.
+    Blob { hash: String },
+    /// The entries at `key` of a document, proven by their digest (see `core::docs::compute_doc_digest`).
+    Entry { doc_id: String, key: String },
+}
+
+/// Which side of the running hash a sibling goes on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MerkleSide {
+    Left,
+    Right,
+}
+
+/// One step from a leaf up to the Merkle root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleStep {
+    /// 0x-prefixed hash of the sibling node.
+    pub hash: String,
+    pub side: MerkleSide,
+}
+
+/// Proof that a subject existed when a block was finalized: its digest is a leaf of a Merkle tree
+/// whose root was anchored on chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofReceipt {
+    pub id: String,
+    pub subject: ProofSubject,
+    /// 0x-prefixed digest of the subject when it was proven.
+    pub digest: String,
+    /// 0x-prefixed root of the Merkle tree of every subject anchored in the same extrinsic.
+    pub merkle_root: String,
+    /// Path from the subject's leaf to `merkle_root`. Empty when the subject was anchored alone.
+    pub merkle_path: Vec<MerkleStep>,
+    pub block_hash: String,
+    pub block_number: u64,
+    pub extrinsic_hash: String,
+    pub extrinsic_index: u32,
+    /// SS58 CORD account that signed the extrinsic.
+    pub account: String,
+    /// Unix time in seconds at which the root was anchored.
+    pub anchored_at: u64,
+}
+
+/// A subject waiting to be anchored with the next batch. Its digest is taken when it is queued,
+/// so the receipt proves the subject as it was then.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingProof {
+    /// The ID the receipt gets once the batch is anchored.
+    pub id: String,
+    pub subject: ProofSubject,
+    /// 0x-prefixed digest of the subject when it was queued.
+    pub digest: String,
+    /// Unix time in seconds at which the subject was queued.
+    pub queued_at: u64,
+}
+
+/// The proof receipts issued by the node and the subjects queued for the next batch, shared
+/// through `AppState` and persisted as JSON.
+#[derive(Clone, Default)]
+pub struct ProofRegistry {
+    receipts: Arc<RwLock<BTreeMap<String, ProofReceipt>>>,
+    pending: Arc<RwLock<Vec<PendingProof>>>,
+    storage_path: Option<PathBuf>,
+    queue_path: Option<PathBuf>,
+}
+
+impl ProofRegistry {
+    /// Loads the registry from the data directory at `path`, starting empty if nothing was saved yet.
+    pub async fn load(path: &str) -> anyhow::Result<Self> {
+        let storage_path = PathBuf::from(path).join(PROOFS_FILE);
+        let queue_path = PathBuf::from(path).join(PROOF_QUEUE_FILE);
+
+        let receipts = if fs::try_exists(&storage_path).await? {
+            let content = fs::read_to_string(&storage_path).await?;
+            serde_json::from_str(&content)?
+        } else {
+            BTreeMap::new()
+        };
+        let pending = if fs::try_exists(&queue_path).await? {
+            let content = fs::read_to_string(&queue_path).await?;
+            serde_json::from_str(&content)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            receipts: Arc::new(RwLock::new(receipts)),
+            pending: Arc::new(RwLock::new(pending)),
+            storage_path: Some(storage_path),
+            queue_path: Some(queue_path),
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Option<ProofReceipt> {
+        self.receipts.read().unwrap().get(id).cloned()
+    }
+
+    /// Stores the receipts of one anchoring.
+    pub async fn add(&self, receipts: &[ProofReceipt]) -> anyhow::Result<()> {
+        {
+            let mut stored = self.receipts.write().unwrap();
+            for receipt in receipts {
+                stored.insert(receipt.id.clone(), receipt.clone());
+            }
+        }
+        self.save().await
+    }
+
+    pub fn get_pending(&self, id: &str) -> Option<PendingProof> {
+        self.pending.read().unwrap().iter().find(|pending| pending.id == id).cloned()
+    }
+
+    /// The subjects waiting for the next batch, oldest first.
+    pub fn pending(&self) -> Vec<PendingProof> {
+        self.pending.read().unwrap().clone()
+    }
+
+    /// Queues subjects for the next batch.
+    pub async fn enqueue(&self, pending: &[PendingProof]) -> anyhow::Result<()> {
+        self.pending.write().unwrap().extend_from_slice(pending);
+        self.save_queue().await
+    }
+
+    /// Stores the receipts of an anchored batch and takes their subjects off the queue.
+    pub async fn complete(&self, receipts: &[ProofReceipt]) -> anyhow::Result<()> {
+        self.pending
+            .write()
+            .unwrap()
+            .retain(|pending| !receipts.iter().any(|receipt| receipt.id == pending.id));
+        self.add(receipts).await?;
+        self.save_queue().await
+    }
+
+    async fn save(&self) -> anyhow::Result<()> {
+        let Some(storage_path) = &self.storage_path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&*self.receipts.read().unwrap())?;
+        fs::write(storage_path, json).await?;
+        Ok(())
+    }
+
+    async fn save_queue(&self) -> anyhow::Result<()> {
+        let Some(queue_path) = &self.queue_path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&*self.pending.read().unwrap())?;
+        fs::write(queue_path, json).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{cli::CliArgs, utils::decode_doc_id};
+
+use iroh_blobs::Hash;
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::{IpAddr, Ipv6Addr},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// Past this many clients tracked, the ones back to a full allowance are forgotten, then the
+// least recently seen tenth if that is not enough
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// The documents and blobs published on the `--public-listen` address, shared through `AppState`.
+///
+/// Anyone can read them there without the node ID check. Nothing else of the node is served on
+/// that address.
+#[derive(Debug, Clone, Default)]
+pub struct PublicSettings {
+    /// Documents whose entries are published, see `--public-doc`.
+    pub docs: BTreeSet<String>,
+    /// Blobs published by hash, see `--public-blob`.
+    pub blobs: BTreeSet<Hash>,
+    /// Requests a client may make per minute, see `--public-rate-limit`.
+    pub rate_limit: u32,
+}
+
+impl PublicSettings {
+    /// The settings of the `--public-*` flags of `serve`.
+    pub fn from_args(args: &CliArgs) -> Result<Self, String> {
+        if args.public_listen.is_none() && !(args.public_doc.is_empty() && args.public_blob.is_empty()) {
+            return Err("❌ --public-doc and --public-blob need --public-listen.".to_string());
+        }
+        if args.public_rate_limit == 0 {
+            return Err("❌ --public-rate-limit must be at least 1.".to_string());
+        }
+
+        let mut docs = BTreeSet::new();
+        for doc_id in &args.public_doc {
+            decode_doc_id(doc_id).map_err(|_| format!("❌ --public-doc {} is not a document ID.", doc_id))?;
+            docs.insert(doc_id.clone());
+        }
+        let mut blobs = BTreeSet::new();
+        for hash in &args.public_blob {
+            blobs.insert(Hash::from_str(hash).map_err(|_| format!("❌ --public-blob {} is not a blob hash.", hash))?);
+        }
+
+        Ok(Self { docs, blobs, rate_limit: args.public_rate_limit })
+    }
+
+    pub fn is_public_doc(&self, doc_id: &str) -> bool {
+        self.docs.contains(doc_id)
+    }
+
+    pub fn is_public_blob(&self, hash: &Hash) -> bool {
+        self.blobs.contains(hash)
+    }
+}
+
+// The allowance of one client, refilled continuously up to the limit
+struct Allowance {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Limits the requests each client IP address makes per minute, for routes served to anyone.
+///
+/// A client may spend its whole minute in a burst, then gets one more request each
+/// `60 / per_minute` seconds. IPv6 clients are counted by their /64 prefix, as a single host is
+/// usually given a whole /64 and could otherwise use a new address for every request.
+#[derive(Clone)]
+pub struct RequestRateLimiter {
+    per_minute: u32,
+    clients: Arc<Mutex<HashMap<IpAddr, Allowance>>>,
+}
+
+impl RequestRateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        Self { per_minute: per_minute.max(1), clients: Arc::default() }
+    }
+
+    /// Counts a request of `client`. `Err` holds how long it should wait before the next one
+    /// would be allowed.
+    pub fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        let client = client_key(client);
+        let capacity = self.per_minute as f64;
+        let per_second = capacity / 60.0;
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+
+        if clients.len() >= MAX_TRACKED_CLIENTS && !clients.contains_key(&client) {
+            clients.retain(|_, allowance| {
+                allowance.tokens + now.duration_since(allowance.updated).as_secs_f64() * per_second < capacity
+            });
+        }
+        if clients.len() >= MAX_TRACKED_CLIENTS && !clients.contains_key(&client) {
+            let mut seen: Vec<Instant> = clients.values().map(|allowance| allowance.updated).collect();
+            let evicted = clients.len() + 1 - MAX_TRACKED_CLIENTS * 9 / 10;
+            let (_, cutoff, _) = seen.select_nth_unstable(evicted - 1);
+            let cutoff = *cutoff;
+            let mut left = evicted;
+            clients.retain(|_, allowance| {
+                let evict = left > 0 && allowance.updated <= cutoff;
+                left -= evict as usize;
+                !evict
+            });
+        }
+
+        let allowance = clients.entry(client).or_insert(Allowance { tokens: capacity, updated: now });
+        allowance.tokens = (allowance.tokens + now.duration_since(allowance.updated).as_secs_f64() * per_second).min(capacity);
+        allowance.updated = now;
+        if allowance.tokens >= 1.0 {
+            allowance.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - allowance.tokens) / per_second))
+        }
+    }
+}
+
+// The address a client's requests are counted under
+fn client_key(client: IpAddr) -> IpAddr {
+    match client {
+        IpAddr::V4(_) => client,
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => IpAddr::V6(Ipv6Addr::from(u128::from(v6) & !(u64::MAX as u128))),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_tracks_at_most_max_clients() {
+        // every client spends its only request, so none is back to a full allowance
+        let limiter = RequestRateLimiter::new(1);
+        for n in 0..MAX_TRACKED_CLIENTS as u32 + 10 {
+            let client = IpAddr::from(n.to_be_bytes());
+            assert!(limiter.check(client).is_ok());
+            assert!(limiter.check(client).is_err());
+            assert!(limiter.clients.lock().unwrap().len() <= MAX_TRACKED_CLIENTS);
+        }
+
+        let clients = limiter.clients.lock().unwrap();
+        // the least recently seen clients went first, the latest ones are still limited
+        assert!(!clients.contains_key(&IpAddr::from(0u32.to_be_bytes())));
+        assert!(clients.contains_key(&IpAddr::from((MAX_TRACKED_CLIENTS as u32 + 9).to_be_bytes())));
+    }
+
+    #[test]
+    fn test_rate_limiter_counts_ipv6_clients_by_their_64_prefix() {
+        let limiter = RequestRateLimiter::new(1);
+        let client: IpAddr = "2001:db8:1:2::1".parse().unwrap();
+        assert!(limiter.check(client).is_ok());
+
+        // another address of the same /64 shares the allowance, another /64 has its own
+        assert!(limiter.check("2001:db8:1:2:ffff:ffff:ffff:ffff".parse().unwrap()).is_err());
+        assert!(limiter.check("2001:db8:1:3::1".parse().unwrap()).is_ok());
+
+        // an IPv4-mapped address is its IPv4 client
+        assert!(limiter.check("192.0.2.1".parse().unwrap()).is_ok());
+        assert!(limiter.check("::ffff:192.0.2.1".parse().unwrap()).is_err());
+        assert!(limiter.check("192.0.2.2".parse().unwrap()).is_ok());
+    }
+}
@@ -0,0 +1,147 @@
+use serde::Serialize;
+use std::{collections::{BTreeSet, HashMap}, sync::{Arc, RwLock}, time::{SystemTime, UNIX_EPOCH}};
+
+/// What the node has seen of a document's sync since it started.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SyncActivity {
+    /// Unix time in seconds of the last sync with a peer that succeeded.
+    pub last_sync_at: Option<u64>,
+    /// The peer of that sync.
+    pub last_sync_peer: Option<String>,
+    /// Unix time in seconds of the last sync with a peer that failed.
+    pub last_error_at: Option<u64>,
+    pub last_error: Option<String>,
+    pub syncs_succeeded: u64,
+    pub syncs_failed: u64,
+    /// Peers currently in the document swarm.
+    pub neighbors: BTreeSet<String>,
+}
+
+/// Sync counters of a document since the node started.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SyncMetrics {
+    /// Sync rounds with peers, failed ones included.
+    pub sync_rounds: u64,
+    pub sync_failures: u64,
+    /// Entries inserted by peers, through sync rounds or live gossip.
+    pub entries_received: u64,
+    /// Entries sent to peers in sync rounds.
+    pub entries_sent: u64,
+    /// Content of entries from peers that finished downloading.
+    pub content_received: u64,
+    pub content_bytes_received: u64,
+}
+
+/// The sync activity and counters of every document, shared through `AppState`.
+///
+/// It is filled in by `node::replication_tracker` from the documents' live events and is kept in
+/// memory only, so it starts empty whenever the node starts.
+#[derive(Clone)]
+pub struct ReplicationTracker {
+    activity: Arc<RwLock<HashMap<String, SyncActivity>>>,
+    metrics: Arc<RwLock<HashMap<String, SyncMetrics>>>,
+    // size of the content announced by peers that is still downloading, by document and hash
+    pending: Arc<RwLock<HashMap<String, HashMap<String, u64>>>>,
+    started_at: u64,
+}
+
+impl Default for ReplicationTracker {
+    fn default() -> Self {
+        Self {
+            activity: Arc::default(),
+            metrics: Arc::default(),
+            pending: Arc::default(),
+            started_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        }
+    }
+}
+
+impl ReplicationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Unix time in seconds the counters started from.
+    pub fn started_at(&self) -> u64 {
+        self.started_at
+    }
+
+    /// The counters of `doc_id`, zero if nothing was seen yet.
+    pub fn metrics(&self, doc_id: &str) -> SyncMetrics {
+        self.metrics.read().unwrap().get(doc_id).cloned().unwrap_or_default()
+    }
+
+    /// The counters of every document seen so far.
+    pub fn all_metrics(&self) -> HashMap<String, SyncMetrics> {
+        self.metrics.read().unwrap().clone()
+    }
+
+    /// Records an entry inserted by a peer. Content still to download is counted once it is ready.
+    pub fn record_insert_remote(&self, doc_id: &str, hash: String, content_len: u64, content_complete: bool) {
+        let mut metrics = self.metrics.write().unwrap();
+        let metrics = metrics.entry(doc_id.to_string()).or_default();
+        metrics.entries_received += 1;
+        if !content_complete {
+            self.pending.write().unwrap().entry(doc_id.to_string()).or_default().insert(hash, content_len);
+        }
+    }
+
+    /// Records that the content `hash`, announced by a peer, finished downloading.
+    pub fn record_content_ready(&self, doc_id: &str, hash: &str) {
+        let Some(len) = self.pending.write().unwrap().get_mut(doc_id).and_then(|pending| pending.remove(hash)) else {
+            return;
+        };
+        let mut metrics = self.metrics.write().unwrap();
+        let metrics = metrics.entry(doc_id.to_string()).or_default();
+        metrics.content_received += 1;
+        metrics.content_bytes_received += len;
+    }
+
+    /// The activity of `doc_id`, empty if nothing was seen yet.
+    pub fn get(&self, doc_id: &str) -> SyncActivity {
+        self.activity.read().unwrap().get(doc_id).cloned().unwrap_or_default()
+    }
+
+    /// Records a finished sync of `doc_id` with `peer`, failed if `error` is set.
+    pub fn record_sync(&self, doc_id: &str, peer: String, entries_sent: usize, error: Option<String>) {
+        {
+            let mut metrics = self.metrics.write().unwrap();
+            let metrics = metrics.entry(doc_id.to_string()).or_default();
+            metrics.sync_rounds += 1;
+            metrics.sync_failures += u64::from(error.is_some());
+            metrics.entries_sent += entries_sent as u64;
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let mut activity = self.activity.write().unwrap();
+        let activity = activity.entry(doc_id.to_string()).or_default();
+        match error {
+            None => {
+                activity.last_sync_at = Some(now);
+                activity.last_sync_peer = Some(peer);
+                activity.syncs_succeeded += 1;
+            }
+            Some(error) => {
+                activity.last_error_at = Some(now);
+                activity.last_error = Some(error);
+                activity.syncs_failed += 1;
+            }
+        }
+    }
+
+    pub fn neighbor_up(&self, doc_id: &str, peer: String) {
+        self.activity.write().unwrap().entry(doc_id.to_string()).or_default().neighbors.insert(peer);
+    }
+
+    pub fn neighbor_down(&self, doc_id: &str, peer: &str) {
+        if let Some(activity) = self.activity.write().unwrap().get_mut(doc_id) {
+            activity.neighbors.remove(peer);
+        }
+    }
+
+    /// Forgets `doc_id`, once it is dropped.
+    pub fn forget(&self, doc_id: &str) {
+        self.activity.write().unwrap().remove(doc_id);
+        self.metrics.write().unwrap().remove(doc_id);
+        self.pending.write().unwrap().remove(doc_id);
+    }
+}
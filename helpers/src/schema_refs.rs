@@ -0,0 +1,47 @@
+use serde_json::Value;
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+/// How long a schema fetched from a URL is reused before it is fetched again.
+pub const URL_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Where the `$ref`s of document schemas to other schemas are resolved from.
+///
+/// A `registry:<key>` ref names the entry `<key>` of the schema registry document, see
+/// `--schema-registry-doc`. An `http://` or `https://` ref is fetched only if it starts with one
+/// of the allowed prefixes, see `--schema-ref-url`. Refs within a schema, such as
+/// `#/$defs/address`, need neither.
+#[derive(Clone, Default)]
+pub struct SchemaRefs {
+    registry_doc: Option<String>,
+    allowed_urls: Arc<Vec<String>>,
+    fetched: Arc<Mutex<HashMap<String, (Instant, Value)>>>,
+}
+
+impl SchemaRefs {
+    pub fn new(registry_doc: Option<String>, allowed_urls: Vec<String>) -> Self {
+        Self { registry_doc, allowed_urls: Arc::new(allowed_urls), fetched: Arc::default() }
+    }
+
+    /// The document `registry:` refs are read from.
+    pub fn registry_doc(&self) -> Option<&str> {
+        self.registry_doc.as_deref()
+    }
+
+    /// Whether `url` may be fetched.
+    pub fn is_url_allowed(&self, url: &str) -> bool {
+        self.allowed_urls.iter().any(|prefix| url.starts_with(prefix.as_str()))
+    }
+
+    /// The schema fetched from `url`, if it was fetched within `URL_CACHE_TTL`.
+    pub fn cached(&self, url: &str) -> Option<Value> {
+        let fetched = self.fetched.lock().unwrap();
+        fetched.get(url).filter(|(at, _)| at.elapsed() < URL_CACHE_TTL).map(|(_, schema)| schema.clone())
+    }
+
+    /// Keeps the schema fetched from `url` for `URL_CACHE_TTL`.
+    pub fn cache(&self, url: &str, schema: Value) {
+        let mut fetched = self.fetched.lock().unwrap();
+        fetched.retain(|_, (at, _)| at.elapsed() < URL_CACHE_TTL);
+        fetched.insert(url.to_string(), (Instant::now(), schema));
+    }
+}
@@ -0,0 +1,319 @@
+use crate::entry_index::IndexUpdate;
+use crate::utils::{is_attachment_key, is_history_key, is_trash_key};
+
+use data_encoding::HEXLOWER;
+use serde::Serialize;
+use std::{collections::{HashMap, HashSet}, sync::{Arc, Mutex}};
+use tantivy::{
+    collector::TopDocs,
+    query::{BooleanQuery, Occur, Query, QueryParser, TermQuery},
+    schema::{Field, IndexRecordOption, Schema, Value, STORED, STRING, TEXT},
+    snippet::SnippetGenerator,
+    Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term,
+};
+
+// Memory the writer buffers documents in before flushing them to a segment, the minimum tantivy takes
+const WRITER_MEMORY: usize = 15_000_000;
+
+/// The key under which an author sets the schema of their entries of a document.
+pub const SCHEMA_KEY: &str = "schema";
+
+/// Errors of `SearchIndex::search`.
+#[derive(Debug)]
+pub enum SearchError {
+    /// The query cannot be parsed.
+    InvalidQuery(String),
+    /// The index failed to answer.
+    Index(String),
+}
+
+impl std::fmt::Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchError::InvalidQuery(e) => write!(f, "Invalid search query: {}", e),
+            SearchError::Index(e) => write!(f, "Search failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+/// An entry matching a search, see `SearchIndex::search`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub key: String,
+    pub author: String,
+    /// BM25 relevance of the entry, higher first.
+    pub score: f32,
+    /// HTML fragment of the entry's text with the matched terms in `<b>` tags, the rest escaped.
+    pub snippet: String,
+}
+
+// An entry by author and key as stored
+type EntryId = (String, Vec<u8>);
+
+#[derive(Default)]
+struct DocSearch {
+    // false until the first scan of the document is done
+    ready: bool,
+    // authors that set a schema, whose entries are the ones indexed
+    schema_authors: HashSet<String>,
+    // indexed entries, with the content hash they were indexed from
+    entries: HashMap<EntryId, String>,
+    // content hash -> entries, with their keys, that wait for it
+    pending: HashMap<String, HashMap<EntryId, String>>,
+}
+
+struct Fields {
+    id: Field,
+    doc_id: Field,
+    key: Field,
+    author: Field,
+    text: Field,
+}
+
+struct Inner {
+    index: Index,
+    reader: IndexReader,
+    fields: Fields,
+    // the writer and what is indexed are changed together
+    state: Mutex<(IndexWriter, HashMap<String, DocSearch>)>,
+}
+
+/// Full-text index of the string values of schema-validated entries, shared through `AppState`
+/// and queried by `/docs/:id/search`.
+///
+/// Only the entries of authors who set a schema on the document are indexed, the schema itself
+/// excluded. It is kept up to date by `node::search_indexer` from the documents' live events,
+/// and is kept in memory only, so each document is scanned once whenever the node starts.
+#[derive(Clone, Default)]
+pub struct SearchIndex {
+    inner: Option<Arc<Inner>>,
+}
+
+impl SearchIndex {
+    /// An empty index, or a disabled one that indexes and finds nothing, see `--search`.
+    pub fn new(enabled: bool) -> tantivy::Result<Self> {
+        if !enabled {
+            return Ok(Self::default());
+        }
+        let mut schema = Schema::builder();
+        let fields = Fields {
+            id: schema.add_text_field("id", STRING),
+            doc_id: schema.add_text_field("doc_id", STRING),
+            key: schema.add_text_field("key", STORED),
+            author: schema.add_text_field("author", STORED),
+            text: schema.add_text_field("text", TEXT | STORED),
+        };
+        let index = Index::create_in_ram(schema.build());
+        let writer = index.writer_with_num_threads(1, WRITER_MEMORY)?;
+        let reader = index.reader_builder().reload_policy(ReloadPolicy::Manual).try_into()?;
+        Ok(Self { inner: Some(Arc::new(Inner { index, reader, fields, state: Mutex::new((writer, HashMap::new())) })) })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Drops what is indexed of `doc_id` to rebuild it from a scan of the document.
+    pub fn reset(&self, doc_id: &str) {
+        let Some(inner) = &self.inner else { return };
+        let mut state = inner.state.lock().unwrap();
+        state.0.delete_term(Term::from_field_text(inner.fields.doc_id, doc_id));
+        state.1.insert(doc_id.to_string(), DocSearch::default());
+    }
+
+    /// Marks the scan of `doc_id` as done and makes what it indexed searchable.
+    pub fn mark_ready(&self, doc_id: &str) {
+        let Some(inner) = &self.inner else { return };
+        let mut state = inner.state.lock().unwrap();
+        if let Some(doc) = state.1.get_mut(doc_id) {
+            doc.ready = true;
+        }
+        inner.commit(&mut state.0);
+    }
+
+    /// Records that `author` set a schema on `doc_id`, so their entries recorded from then on are
+    /// indexed.
+    pub fn record_schema(&self, doc_id: &str, author: &str) {
+        let Some(inner) = &self.inner else { return };
+        let mut state = inner.state.lock().unwrap();
+        state.1.entry(doc_id.to_string()).or_default().schema_authors.insert(author.to_string());
+    }
+
+    /// Whether the entries of `author` in `doc_id` are indexed.
+    pub fn indexes(&self, doc_id: &str, author: &str) -> bool {
+        let Some(inner) = &self.inner else { return false };
+        let state = inner.state.lock().unwrap();
+        state.1.get(doc_id).is_some_and(|doc| doc.schema_authors.contains(author))
+    }
+
+    /// Records an entry inserted in `doc_id`. An empty entry is a deletion of every entry of its
+    /// author whose key starts with its key.
+    ///
+    /// `content` is the entry's value if it is available, otherwise it is indexed once
+    /// `record_content` is given the content of its hash.
+    pub fn record_entry(&self, doc_id: &str, update: IndexUpdate, content: Option<&[u8]>) {
+        let Some(inner) = &self.inner else { return };
+        let mut guard = inner.state.lock().unwrap();
+        let (writer, docs) = &mut *guard;
+        let doc = docs.entry(doc_id.to_string()).or_default();
+
+        let removed: Vec<EntryId> = if update.len == 0 {
+            doc.entries
+                .keys()
+                .filter(|(author, raw_key)| *author == update.author && raw_key.starts_with(&update.raw_key))
+                .cloned()
+                .collect()
+        } else {
+            vec![(update.author.clone(), update.raw_key.clone())]
+        };
+        for id in &removed {
+            if let Some(hash) = doc.entries.remove(id) {
+                writer.delete_term(Term::from_field_text(inner.fields.id, &entry_id(doc_id, id)));
+                if let Some(waiting) = doc.pending.get_mut(&hash) {
+                    waiting.remove(id);
+                    if waiting.is_empty() {
+                        doc.pending.remove(&hash);
+                    }
+                }
+            }
+        }
+
+        let id = (update.author.clone(), update.raw_key.clone());
+        if update.len > 0
+            && update.key != SCHEMA_KEY
+            && !is_attachment_key(&update.key)
+            && !is_trash_key(&update.key)
+            && !is_history_key(&update.key)
+            && doc.schema_authors.contains(&update.author)
+        {
+            doc.entries.insert(id.clone(), update.hash.clone());
+            match content {
+                Some(content) => inner.add(writer, doc_id, &id, &update.key, content),
+                None => {
+                    doc.pending.entry(update.hash).or_default().insert(id, update.key);
+                }
+            }
+        }
+        if doc.ready {
+            inner.commit(writer);
+        }
+    }
+
+    /// Indexes the entries of `doc_id` that waited for the content `hash`.
+    pub fn record_content(&self, doc_id: &str, hash: &str, content: &[u8]) {
+        let Some(inner) = &self.inner else { return };
+        let mut guard = inner.state.lock().unwrap();
+        let (writer, docs) = &mut *guard;
+        let Some(doc) = docs.get_mut(doc_id) else { return };
+        let Some(waiting) = doc.pending.remove(hash) else { return };
+        for (id, key) in waiting {
+            if doc.entries.get(&id).is_some_and(|indexed| indexed == hash) {
+                inner.add(writer, doc_id, &id, &key, content);
+            }
+        }
+        if doc.ready {
+            inner.commit(writer);
+        }
+    }
+
+    /// Whether some entry of `doc_id` waits for the content `hash` to be indexed.
+    pub fn is_pending(&self, doc_id: &str, hash: &str) -> bool {
+        let Some(inner) = &self.inner else { return false };
+        let state = inner.state.lock().unwrap();
+        state.1.get(doc_id).is_some_and(|doc| doc.pending.contains_key(hash))
+    }
+
+    /// Up to `limit` entries of `doc_id` matching `query`, most relevant first. `None` if the
+    /// document is not indexed yet.
+    ///
+    /// `query` is in tantivy's query language: words match entries holding any of them, and
+    /// `+word`, `-word`, `"a phrase"`, `AND` and `OR` combine them.
+    pub fn search(&self, doc_id: &str, query: &str, limit: usize) -> Result<Option<Vec<SearchHit>>, SearchError> {
+        let Some(inner) = &self.inner else { return Ok(None) };
+        if !inner.state.lock().unwrap().1.get(doc_id).is_some_and(|doc| doc.ready) {
+            return Ok(None);
+        }
+
+        let parser = QueryParser::for_index(&inner.index, vec![inner.fields.text]);
+        let text_query = parser.parse_query(query).map_err(|e| SearchError::InvalidQuery(e.to_string()))?;
+        let doc_query = TermQuery::new(Term::from_field_text(inner.fields.doc_id, doc_id), IndexRecordOption::Basic);
+        let query = BooleanQuery::new(vec![(Occur::Must, text_query.box_clone()), (Occur::Must, Box::new(doc_query) as Box<dyn Query>)]);
+
+        let searcher = inner.reader.searcher();
+        let index_error = |e: tantivy::TantivyError| SearchError::Index(e.to_string());
+        let top = searcher.search(&query, &TopDocs::with_limit(limit)).map_err(index_error)?;
+        let snippets = SnippetGenerator::create(&searcher, &*text_query, inner.fields.text).map_err(index_error)?;
+
+        let mut hits = Vec::with_capacity(top.len());
+        for (score, address) in top {
+            let found: TantivyDocument = searcher.doc(address).map_err(index_error)?;
+            let stored = |field| found.get_first(field).and_then(|value| value.as_str()).unwrap_or_default().to_string();
+            hits.push(SearchHit {
+                key: stored(inner.fields.key),
+                author: stored(inner.fields.author),
+                score,
+                snippet: snippets.snippet_from_doc(&found).to_html(),
+            });
+        }
+        Ok(Some(hits))
+    }
+
+    /// Forgets `doc_id`, once it is dropped.
+    pub fn forget(&self, doc_id: &str) {
+        let Some(inner) = &self.inner else { return };
+        let mut state = inner.state.lock().unwrap();
+        state.1.remove(doc_id);
+        state.0.delete_term(Term::from_field_text(inner.fields.doc_id, doc_id));
+        inner.commit(&mut state.0);
+    }
+}
+
+impl Inner {
+    fn add(&self, writer: &IndexWriter, doc_id: &str, id: &EntryId, key: &str, content: &[u8]) {
+        let texts = extract_strings(content);
+        if texts.is_empty() {
+            return;
+        }
+        let mut document = TantivyDocument::default();
+        document.add_text(self.fields.id, entry_id(doc_id, id));
+        document.add_text(self.fields.doc_id, doc_id);
+        document.add_text(self.fields.key, key);
+        document.add_text(self.fields.author, &id.0);
+        for text in texts {
+            document.add_text(self.fields.text, text);
+        }
+        if let Err(e) = writer.add_document(document) {
+            tracing::warn!(doc_id = %doc_id, key = %key, error = %e, "failed to index entry for search");
+        }
+    }
+
+    // Makes the changes so far searchable
+    fn commit(&self, writer: &mut IndexWriter) {
+        if let Err(e) = writer.commit().and_then(|_| self.reader.reload()) {
+            tracing::warn!(error = %e, "failed to commit the search index");
+        }
+    }
+}
+
+fn entry_id(doc_id: &str, (author, raw_key): &EntryId) -> String {
+    format!("{}/{}/{}", doc_id, author, HEXLOWER.encode(raw_key))
+}
+
+// Every string of a JSON value, nested ones included. Keys of objects are not indexed.
+fn extract_strings(content: &[u8]) -> Vec<String> {
+    fn walk(value: serde_json::Value, texts: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(text) => texts.push(text),
+            serde_json::Value::Array(values) => values.into_iter().for_each(|value| walk(value, texts)),
+            serde_json::Value::Object(object) => object.into_iter().for_each(|(_, value)| walk(value, texts)),
+            _ => {}
+        }
+    }
+    let mut texts = Vec::new();
+    if let Ok(value) = serde_json::from_slice(content) {
+        walk(value, &mut texts);
+    }
+    texts
+}
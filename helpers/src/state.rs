@@ -1,18 +1,84 @@
 use keystore::keystore::CordKeystoreSigner;
+use keystore::registry::KeyTypeRegistry;
+use keystore::audit::AuditLog;
+use cord::client::ChainClient;
+use cord::backend::ChainAnchor;
+use crate::events::EventBus;
+use crate::webhooks::WebhookRegistry;
+use crate::notifications::WatchRegistry;
+use crate::approvals::ApprovalRegistry;
+use crate::proofs::ProofRegistry;
+use crate::authorizations::AuthorizationRegistry;
+use crate::sync_controls::SyncControlRegistry;
+use crate::backups::BackupSettings;
+use crate::federation::FederationRegistry;
+use crate::replication::ReplicationTracker;
+use crate::write_queue::WriteQueue;
+use crate::downloads::DownloadManager;
+use crate::entry_index::EntryIndex;
+use crate::search::SearchIndex;
+use crate::limits::OperationLimits;
+use crate::jobs::JobRegistry;
+use crate::public::PublicSettings;
+use crate::tenants::TenantRegistry;
+use crate::access_log::AccessLog;
+use crate::stores::{NodeBlobs, NodeDocs};
 
 use std::sync::Arc;
-use iroh_blobs::net_protocol::Blobs;
-use iroh_docs::protocol::Docs;
-use iroh_blobs::store::fs::Store;
 use subxt_rpcs::RpcClient;
 use subxt::client::OnlineClient;
 use subxt::config::PolkadotConfig;
 
+/// Message local writes are rejected with when the node is a read-only replica.
+pub const READ_ONLY_MESSAGE: &str = "This node is a read-only replica and does not accept writes";
+
 #[derive(Clone)]
 pub struct AppState {
-    pub docs: Arc<Docs<Store>>,
-    pub blobs: Arc<Blobs<Store>>,
+    pub docs: Arc<NodeDocs>,
+    pub blobs: Arc<NodeBlobs>,
     // pub cord_client: Arc<RpcClient>,
     pub cord_client: Arc<OnlineClient<PolkadotConfig>>,
-    pub cord_signer: CordKeystoreSigner
+    pub cord_signer: CordKeystoreSigner,
+    /// Submits extrinsics signed with `cord_signer`, sharing its nonce across the node.
+    pub chain: ChainClient,
+    /// The backend documents, revocations and proofs are anchored to, see `--chain-backend`.
+    pub anchor: Arc<dyn ChainAnchor>,
+    pub events: EventBus,
+    pub webhooks: WebhookRegistry,
+    /// Watches on keys and authors notified over webhooks, email or Matrix, see `--smtp-relay`.
+    pub notifications: WatchRegistry,
+    pub key_types: KeyTypeRegistry,
+    pub approvals: ApprovalRegistry,
+    pub audit: AuditLog,
+    pub proofs: ProofRegistry,
+    pub authorizations: AuthorizationRegistry,
+    pub sync_controls: SyncControlRegistry,
+    /// Where snapshots go, see `--backup-dir`.
+    pub backups: BackupSettings,
+    /// The catalog the node replicates documents from, see `--federation-catalog`.
+    pub federation: FederationRegistry,
+    /// The sync activity of each document since the node started.
+    pub replication: ReplicationTracker,
+    /// Local writes not propagated to any peer yet.
+    pub write_queue: WriteQueue,
+    /// Downloads fetching content from several peers, see `--download-connections`.
+    pub downloads: DownloadManager,
+    /// Stats and field indexes of each document, see `--index-field`.
+    pub entry_index: EntryIndex,
+    /// Full-text index of schema-validated entries, see `--search`.
+    pub search: SearchIndex,
+    /// Caps on imports, exports, downloads and scans running at once, see `--max-concurrent-scans`.
+    pub limits: OperationLimits,
+    /// Maintenance jobs, run on their schedules and on request, see `--job`.
+    pub jobs: JobRegistry,
+    /// Tenants with their own documents, authors, quotas and API keys.
+    pub tenants: TenantRegistry,
+    /// Entry and blob reads recorded for audits, see `--access-log`.
+    pub access_log: AccessLog,
+    /// Local writes are rejected, see `--read-only`.
+    pub read_only: bool,
+    /// `/gw/:hash` is served without the node ID check, see `--public-gw`.
+    pub public_gw: bool,
+    /// Documents and blobs published to anyone, see `--public-listen`.
+    pub public: PublicSettings,
 }
\ No newline at end of file
@@ -0,0 +1,76 @@
+use crate::blob_cache::BlobCache;
+use crate::blob_listing::BlobListing;
+use crate::doc_handles::DocHandleCache;
+use crate::schema_refs::SchemaRefs;
+
+use iroh_blobs::net_protocol::Blobs;
+use iroh_blobs::store::fs::Store;
+use iroh_docs::protocol::Docs;
+use std::{ops::Deref, time::Duration};
+
+/// A node's document store, with the document handles and schema refs of that node.
+///
+/// Every node keeps its own caches, so nodes started in the same process, e.g. with
+/// `StarterKitBuilder`, never serve each other's handles or schemas.
+pub struct NodeDocs {
+    docs: Docs<Store>,
+    /// Open document handles kept between requests, see `--doc-cache-size`.
+    pub handles: DocHandleCache,
+    /// Where the `$ref`s of document schemas are resolved from, see `--schema-registry-doc`.
+    pub schema_refs: SchemaRefs,
+}
+
+impl NodeDocs {
+    /// `docs` without a handle cache or schema refs.
+    pub fn new(docs: Docs<Store>) -> Self {
+        Self { docs, handles: DocHandleCache::new(0, Duration::ZERO), schema_refs: SchemaRefs::default() }
+    }
+
+    pub fn with_handles(mut self, handles: DocHandleCache) -> Self {
+        self.handles = handles;
+        self
+    }
+
+    pub fn with_schema_refs(mut self, schema_refs: SchemaRefs) -> Self {
+        self.schema_refs = schema_refs;
+        self
+    }
+}
+
+impl Deref for NodeDocs {
+    type Target = Docs<Store>;
+
+    fn deref(&self) -> &Docs<Store> {
+        &self.docs
+    }
+}
+
+/// A node's blob store, with the blob cache and listing snapshot of that node.
+pub struct NodeBlobs {
+    blobs: Blobs<Store>,
+    /// Small blobs read often, kept in memory, see `--blob-cache-size`.
+    pub cache: BlobCache,
+    /// The snapshot pages of blob listings are served from.
+    pub listing: BlobListing,
+}
+
+impl NodeBlobs {
+    /// `blobs` without a blob cache.
+    pub fn new(blobs: Blobs<Store>) -> Self {
+        Self { blobs, cache: BlobCache::new(0, 0), listing: BlobListing::default() }
+    }
+
+    pub fn with_cache(mut self, cache: BlobCache) -> Self {
+        self.cache = cache;
+        self
+    }
+}
+
+impl Deref for NodeBlobs {
+    type Target = Blobs<Store>;
+
+    fn deref(&self) -> &Blobs<Store> {
+        &self.blobs
+    }
+}
+
@@ -0,0 +1,217 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{collections::{BTreeMap, HashMap}, path::PathBuf, sync::{Arc, RwLock}, time::{SystemTime, UNIX_EPOCH}};
+use tokio::{fs, sync::Notify};
+
+// File the sync controls are persisted to, inside the node's data directory.
+const SYNC_CONTROLS_FILE: &str = "sync_controls.json";
+
+/// A daily time range in UTC, as `HH:MM`, during which a document may sync. A range whose end
+/// is before its start wraps past midnight.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncWindow {
+    pub start: String,
+    pub end: String,
+}
+
+impl SyncWindow {
+    /// Whether the window includes `minute`, the minute of the day in UTC. The end is exclusive.
+    pub fn contains(&self, minute: u32) -> bool {
+        let (Ok(start), Ok(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        if start <= end {
+            (start..end).contains(&minute)
+        } else {
+            minute >= start || minute < end
+        }
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        let (start, end) = (parse_time(&self.start)?, parse_time(&self.end)?);
+        if start == end {
+            return Err(anyhow!("❌ The window {}-{} is empty", self.start, self.end));
+        }
+        Ok(())
+    }
+}
+
+// Minutes since midnight of `HH:MM`
+fn parse_time(time: &str) -> anyhow::Result<u32> {
+    let invalid = || anyhow!("❌ Invalid time '{}'. Expected HH:MM in UTC.", time);
+    let (hours, minutes) = time.split_once(':').ok_or_else(invalid)?;
+    let (hours, minutes): (u32, u32) = (hours.parse().map_err(|_| invalid())?, minutes.parse().map_err(|_| invalid())?);
+    if hours > 23 || minutes > 59 || time.len() != 5 {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+/// How the node syncs a document. Documents without controls sync as iroh-docs does by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncControl {
+    /// Sync is stopped until resumed.
+    #[serde(default)]
+    pub paused: bool,
+    /// Only sync during these windows. Empty to sync at any time.
+    #[serde(default)]
+    pub windows: Vec<SyncWindow>,
+    /// Stop syncing for the rest of the minute once this many bytes of content were announced
+    /// by peers for download in it.
+    #[serde(default)]
+    pub max_bytes_per_minute: Option<u64>,
+}
+
+/// Why the node does not let a document sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncBlock {
+    Paused,
+    OutsideWindow,
+    BandwidthLimit,
+}
+
+/// The controls of a document and whether they let it sync right now.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncControlStatus {
+    pub doc_id: String,
+    #[serde(flatten)]
+    pub control: SyncControl,
+    /// Whether the controls let the document sync right now.
+    pub sync_allowed: bool,
+    pub blocked_by: Option<SyncBlock>,
+    /// Bytes of content announced for download in the current minute.
+    pub bytes_this_minute: u64,
+}
+
+/// Per-document sync controls, shared through `AppState` and persisted as JSON, and the content
+/// each controlled document received in the current minute.
+///
+/// The controls are enforced by `node::sync_scheduler`, which is woken through `changed` whenever
+/// they change.
+#[derive(Clone, Default)]
+pub struct SyncControlRegistry {
+    controls: Arc<RwLock<BTreeMap<String, SyncControl>>>,
+    // minute (unix time / 60) and bytes received in it, per document
+    usage: Arc<RwLock<HashMap<String, (u64, u64)>>>,
+    changed: Arc<Notify>,
+    storage_path: Option<PathBuf>,
+}
+
+impl SyncControlRegistry {
+    /// Loads the controls from the data directory at `path`, starting empty if nothing was saved yet.
+    pub async fn load(path: &str) -> anyhow::Result<Self> {
+        let storage_path = PathBuf::from(path).join(SYNC_CONTROLS_FILE);
+
+        let controls = if fs::try_exists(&storage_path).await? {
+            let content = fs::read_to_string(&storage_path).await?;
+            serde_json::from_str(&content)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            controls: Arc::new(RwLock::new(controls)),
+            usage: Arc::default(),
+            changed: Arc::default(),
+            storage_path: Some(storage_path),
+        })
+    }
+
+    pub fn get(&self, doc_id: &str) -> SyncControl {
+        self.controls.read().unwrap().get(doc_id).cloned().unwrap_or_default()
+    }
+
+    /// The documents with controls.
+    pub fn list(&self) -> BTreeMap<String, SyncControl> {
+        self.controls.read().unwrap().clone()
+    }
+
+    /// Replaces the controls of `doc_id`. Controls equal to the default are removed.
+    pub async fn set(&self, doc_id: &str, control: SyncControl) -> anyhow::Result<()> {
+        for window in &control.windows {
+            window.validate()?;
+        }
+        if control.max_bytes_per_minute == Some(0) {
+            return Err(anyhow!("❌ max_bytes_per_minute must be greater than 0"));
+        }
+
+        {
+            let mut controls = self.controls.write().unwrap();
+            if control == SyncControl::default() {
+                controls.remove(doc_id);
+            } else {
+                controls.insert(doc_id.to_string(), control);
+            }
+        }
+        self.save().await?;
+        self.changed.notify_one();
+        Ok(())
+    }
+
+    /// Notified whenever controls change.
+    pub fn changed(&self) -> Arc<Notify> {
+        self.changed.clone()
+    }
+
+    /// Adds `bytes` of content announced for `doc_id` to the current minute.
+    pub fn record_bytes(&self, doc_id: &str, bytes: u64) {
+        let minute = current_minute();
+        let mut usage = self.usage.write().unwrap();
+        let entry = usage.entry(doc_id.to_string()).or_insert((minute, 0));
+        if entry.0 != minute {
+            *entry = (minute, 0);
+        }
+        entry.1 += bytes;
+    }
+
+    /// Bytes of content announced for `doc_id` in the current minute.
+    pub fn bytes_this_minute(&self, doc_id: &str) -> u64 {
+        let minute = current_minute();
+        match self.usage.read().unwrap().get(doc_id) {
+            Some((recorded, bytes)) if *recorded == minute => *bytes,
+            _ => 0,
+        }
+    }
+
+    /// Why `doc_id` may not sync right now, or `None` if it may.
+    pub fn blocked_by(&self, doc_id: &str) -> Option<SyncBlock> {
+        let control = self.get(doc_id);
+        if control.paused {
+            return Some(SyncBlock::Paused);
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        let minute_of_day = ((now % 86_400) / 60) as u32;
+        if !control.windows.is_empty() && !control.windows.iter().any(|window| window.contains(minute_of_day)) {
+            return Some(SyncBlock::OutsideWindow);
+        }
+        if control.max_bytes_per_minute.is_some_and(|limit| self.bytes_this_minute(doc_id) >= limit) {
+            return Some(SyncBlock::BandwidthLimit);
+        }
+        None
+    }
+
+    pub fn status(&self, doc_id: &str) -> SyncControlStatus {
+        let blocked_by = self.blocked_by(doc_id);
+        SyncControlStatus {
+            doc_id: doc_id.to_string(),
+            control: self.get(doc_id),
+            sync_allowed: blocked_by.is_none(),
+            blocked_by,
+            bytes_this_minute: self.bytes_this_minute(doc_id),
+        }
+    }
+
+    async fn save(&self) -> anyhow::Result<()> {
+        let Some(storage_path) = &self.storage_path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&*self.controls.read().unwrap())?;
+        fs::write(storage_path, json).await?;
+        Ok(())
+    }
+}
+
+fn current_minute() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() / 60).unwrap_or_default()
+}
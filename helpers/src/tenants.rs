@@ -0,0 +1,535 @@
+use crate::entry_index::EntryIndex;
+
+use iroh_blobs::Hash;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::fs;
+
+// File the tenants are persisted to, inside the node's data directory.
+const TENANTS_FILE: &str = "tenants.json";
+
+/// Header carrying a tenant's API key.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Prefix of every API key, to tell them apart from other secrets.
+pub const API_KEY_PREFIX: &str = "skt_";
+
+/// Request extension marking a request that came in over a `--listen-uds` socket. Only the
+/// node's operator can reach those sockets, so it authorizes operator-only endpoints such as
+/// `/keystore/sign-payload`.
+#[derive(Debug, Clone, Copy)]
+pub struct OperatorConnection;
+
+/// What a route lets a tenant do, see `TENANT_ROUTES`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TenantRoute {
+    /// Reads the tenant's documents.
+    Read,
+    /// Changes the tenant's documents as one of its authors, without storing more.
+    Write,
+    /// Stores more in the tenant's documents as one of its authors, within its byte quota.
+    Store,
+}
+
+/// The routes a request with an API key may call, by route template. Every other route answers
+/// `403 Forbidden`, as blobs, the keystore, the chain and node settings are shared by all tenants.
+pub const TENANT_ROUTES: &[(&str, TenantRoute)] = &[
+    ("/docs", TenantRoute::Read),
+    ("/docs/list-docs", TenantRoute::Read),
+    ("/docs/get-document", TenantRoute::Read),
+    ("/docs/get-entry", TenantRoute::Read),
+    ("/docs/get-entries", TenantRoute::Read),
+    ("/docs/get-entry-blob", TenantRoute::Read),
+    ("/docs/find-by-field", TenantRoute::Read),
+    ("/docs/:id/entries/*key", TenantRoute::Read),
+    ("/docs/:id/entries/*key/attachments", TenantRoute::Read),
+    ("/docs/:id/entries/*key/verify", TenantRoute::Read),
+    ("/docs/:id/entries-at", TenantRoute::Read),
+    ("/docs/:id/trash", TenantRoute::Read),
+    ("/docs/:id/stats", TenantRoute::Read),
+    ("/docs/:id/search", TenantRoute::Read),
+    ("/docs/:id/events", TenantRoute::Read),
+    ("/docs/share-doc", TenantRoute::Read),
+    ("/authors/list-authors", TenantRoute::Read),
+    ("/docs/create-document", TenantRoute::Write),
+    ("/docs/drop-doc", TenantRoute::Write),
+    ("/docs/:id", TenantRoute::Write),
+    ("/docs/delete-entry", TenantRoute::Write),
+    ("/docs/empty-trash", TenantRoute::Write),
+    ("/docs/set-entry", TenantRoute::Store),
+    ("/docs/restore-entry", TenantRoute::Store),
+];
+
+/// What `route`, a route template, lets a tenant do. `None` if tenants cannot call it.
+pub fn tenant_route(route: &str) -> Option<TenantRoute> {
+    TENANT_ROUTES.iter().find(|(template, _)| *template == route).map(|(_, access)| *access)
+}
+
+/// Limits on what a tenant keeps on the node. `None` is unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantQuota {
+    pub max_docs: Option<usize>,
+    pub max_authors: Option<usize>,
+    /// Bytes of entry content across the tenant's documents. Checked before each write, so the
+    /// write that crosses it still succeeds.
+    pub max_bytes: Option<u64>,
+}
+
+/// An API key of a tenant. Only its hash is kept, the key itself is shown once when created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub label: Option<String>,
+    /// BLAKE3 hash of the key, as hex.
+    pub hash: String,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+}
+
+/// A department or customer served by the node, with its own documents, authors and API keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tenant {
+    pub id: String,
+    pub name: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    pub docs: BTreeSet<String>,
+    pub authors: BTreeSet<String>,
+    pub quota: TenantQuota,
+    pub api_keys: Vec<ApiKey>,
+}
+
+impl Tenant {
+    pub fn has_doc(&self, doc_id: &str) -> bool {
+        self.docs.contains(doc_id)
+    }
+
+    pub fn has_author(&self, author_id: &str) -> bool {
+        self.authors.contains(author_id)
+    }
+
+    /// Bytes of entry content in the tenant's documents, as far as `index` has counted them.
+    pub fn stored_bytes(&self, index: &EntryIndex) -> u64 {
+        self.docs.iter().filter_map(|doc_id| index.stats(doc_id)).map(|stats| stats.bytes).sum()
+    }
+
+    /// Whether the tenant has used up its byte quota.
+    pub fn is_over_storage(&self, index: &EntryIndex) -> bool {
+        self.quota.max_bytes.is_some_and(|max| self.stored_bytes(index) >= max)
+    }
+
+    /// Checks that a request of the tenant on a route with `access` only names its documents and
+    /// authors: the `author-id` header `caller`, the `:id` path parameters `path_doc_ids`, and
+    /// `doc_id` and `author_id` of a JSON `body`. Storing more also needs room in its byte quota.
+    pub fn check_request(
+        &self,
+        access: TenantRoute,
+        caller: Option<&str>,
+        path_doc_ids: &[&str],
+        body: Option<&serde_json::Value>,
+        index: &EntryIndex,
+    ) -> Result<(), TenantRejection> {
+        if caller.is_some_and(|author_id| !self.has_author(author_id)) {
+            return Err(TenantRejection::ForeignAuthor);
+        }
+        if path_doc_ids.iter().any(|doc_id| !self.has_doc(doc_id)) {
+            return Err(TenantRejection::DocNotFound);
+        }
+        if let Some(body) = body {
+            if body.get("doc_id").and_then(|doc_id| doc_id.as_str()).is_some_and(|doc_id| !self.has_doc(doc_id)) {
+                return Err(TenantRejection::DocNotFound);
+            }
+            // Reads name other authors of the tenant's documents as filters, writes act as them
+            let author_id = body.get("author_id").and_then(|author_id| author_id.as_str());
+            if access != TenantRoute::Read && author_id.is_some_and(|author_id| !self.has_author(author_id)) {
+                return Err(TenantRejection::ForeignAuthor);
+            }
+        }
+        if access == TenantRoute::Store && self.is_over_storage(index) {
+            return Err(TenantRejection::OverStorage);
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the documents a request may list: those of its tenant, or all of them without one.
+pub fn retain_tenant_docs<T>(tenant: Option<&Tenant>, docs: &mut Vec<T>, doc_id: impl Fn(&T) -> &str) {
+    if let Some(tenant) = tenant {
+        docs.retain(|doc| tenant.has_doc(doc_id(doc)));
+    }
+}
+
+/// Keeps the authors a request may list: those of its tenant, or all of them without one.
+pub fn retain_tenant_authors(tenant: Option<&Tenant>, authors: &mut Vec<String>) {
+    if let Some(tenant) = tenant {
+        authors.retain(|author_id| tenant.has_author(author_id));
+    }
+}
+
+/// Why a request with an API key is refused, see `Tenant::check_request`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TenantRejection {
+    /// A document of another tenant, or of no tenant, as if the node did not have it.
+    DocNotFound,
+    ForeignAuthor,
+    OverStorage,
+}
+
+impl fmt::Display for TenantRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TenantRejection::DocNotFound => f.write_str("Document not found"),
+            TenantRejection::ForeignAuthor => f.write_str("The author does not belong to the tenant"),
+            TenantRejection::OverStorage => f.write_str("The tenant is over its storage quota"),
+        }
+    }
+}
+
+/// Errors of `TenantRegistry`.
+#[derive(Debug)]
+pub enum TenantError {
+    NotFound,
+    AlreadyExists,
+    InvalidId,
+    /// The document belongs to the tenant with this ID.
+    DocAssigned(String),
+    /// The author belongs to the tenant with this ID.
+    AuthorAssigned(String),
+    /// The tenant is at its `max_docs` or `max_authors` quota.
+    QuotaExceeded(&'static str),
+    ApiKeyNotFound,
+    Storage(String),
+}
+
+impl fmt::Display for TenantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TenantError::NotFound => f.write_str("Tenant not found"),
+            TenantError::AlreadyExists => f.write_str("A tenant with this ID already exists"),
+            TenantError::InvalidId => f.write_str("A tenant ID is 1 to 64 lowercase letters, digits and dashes"),
+            TenantError::DocAssigned(tenant) => write!(f, "The document belongs to the tenant {}", tenant),
+            TenantError::AuthorAssigned(tenant) => write!(f, "The author belongs to the tenant {}", tenant),
+            TenantError::QuotaExceeded(what) => write!(f, "The tenant is at its quota of {}", what),
+            TenantError::ApiKeyNotFound => f.write_str("API key not found"),
+            TenantError::Storage(e) => write!(f, "Failed to save the tenants: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TenantError {}
+
+/// The tenants of the node, shared through `AppState` and persisted as JSON.
+///
+/// A document or author belongs to one tenant at most. Those of no tenant are only reachable
+/// without an API key.
+#[derive(Clone, Default)]
+pub struct TenantRegistry {
+    tenants: Arc<RwLock<BTreeMap<String, Tenant>>>,
+    storage_path: Option<PathBuf>,
+}
+
+impl TenantRegistry {
+    /// Loads the registry from the data directory at `path`, starting empty if nothing was saved yet.
+    pub async fn load(path: &str) -> anyhow::Result<Self> {
+        let storage_path = PathBuf::from(path).join(TENANTS_FILE);
+
+        let tenants: Vec<Tenant> = if fs::try_exists(&storage_path).await? {
+            let content = fs::read_to_string(&storage_path).await?;
+            serde_json::from_str(&content)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            tenants: Arc::new(RwLock::new(tenants.into_iter().map(|tenant| (tenant.id.clone(), tenant)).collect())),
+            storage_path: Some(storage_path),
+        })
+    }
+
+    pub fn list(&self) -> Vec<Tenant> {
+        self.tenants.read().unwrap().values().cloned().collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Tenant> {
+        self.tenants.read().unwrap().get(id).cloned()
+    }
+
+    /// The tenant holding the API key `key`, if any.
+    pub fn authenticate(&self, key: &str) -> Option<Tenant> {
+        let hash = hash_api_key(key);
+        self.tenants
+            .read()
+            .unwrap()
+            .values()
+            .find(|tenant| tenant.api_keys.iter().any(|api_key| api_key.hash == hash))
+            .cloned()
+    }
+
+    /// The tenant `doc_id` belongs to, if any.
+    pub fn doc_owner(&self, doc_id: &str) -> Option<String> {
+        self.tenants.read().unwrap().values().find(|tenant| tenant.has_doc(doc_id)).map(|tenant| tenant.id.clone())
+    }
+
+    /// The tenant `author_id` belongs to, if any.
+    pub fn author_owner(&self, author_id: &str) -> Option<String> {
+        self.tenants.read().unwrap().values().find(|tenant| tenant.has_author(author_id)).map(|tenant| tenant.id.clone())
+    }
+
+    pub async fn create(&self, id: String, name: Option<String>, quota: TenantQuota) -> Result<Tenant, TenantError> {
+        if !is_valid_tenant_id(&id) {
+            return Err(TenantError::InvalidId);
+        }
+        let tenant = Tenant {
+            id: id.clone(),
+            name,
+            created_at: now(),
+            docs: BTreeSet::new(),
+            authors: BTreeSet::new(),
+            quota,
+            api_keys: Vec::new(),
+        };
+        {
+            let mut tenants = self.tenants.write().unwrap();
+            if tenants.contains_key(&id) {
+                return Err(TenantError::AlreadyExists);
+            }
+            tenants.insert(id, tenant.clone());
+        }
+        self.save().await?;
+        Ok(tenant)
+    }
+
+    /// Removes a tenant. Its documents and authors stay on the node, without a tenant.
+    pub async fn delete(&self, id: &str) -> Result<Tenant, TenantError> {
+        let tenant = self.tenants.write().unwrap().remove(id).ok_or(TenantError::NotFound)?;
+        self.save().await?;
+        Ok(tenant)
+    }
+
+    pub async fn set_quota(&self, id: &str, quota: TenantQuota) -> Result<Tenant, TenantError> {
+        self.update(id, |tenant| {
+            tenant.quota = quota;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Gives `doc_id` to the tenant, within its `max_docs` quota.
+    pub async fn assign_doc(&self, id: &str, doc_id: &str) -> Result<Tenant, TenantError> {
+        if let Some(owner) = self.doc_owner(doc_id).filter(|owner| owner != id) {
+            return Err(TenantError::DocAssigned(owner));
+        }
+        self.update(id, |tenant| {
+            if !tenant.has_doc(doc_id) && tenant.quota.max_docs.is_some_and(|max| tenant.docs.len() >= max) {
+                return Err(TenantError::QuotaExceeded("documents"));
+            }
+            tenant.docs.insert(doc_id.to_string());
+            Ok(())
+        })
+        .await
+    }
+
+    /// Gives `author_id` to the tenant, within its `max_authors` quota.
+    pub async fn assign_author(&self, id: &str, author_id: &str) -> Result<Tenant, TenantError> {
+        if let Some(owner) = self.author_owner(author_id).filter(|owner| owner != id) {
+            return Err(TenantError::AuthorAssigned(owner));
+        }
+        self.update(id, |tenant| {
+            if !tenant.has_author(author_id) && tenant.quota.max_authors.is_some_and(|max| tenant.authors.len() >= max) {
+                return Err(TenantError::QuotaExceeded("authors"));
+            }
+            tenant.authors.insert(author_id.to_string());
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn unassign_doc(&self, id: &str, doc_id: &str) -> Result<Tenant, TenantError> {
+        self.update(id, |tenant| {
+            tenant.docs.remove(doc_id);
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn unassign_author(&self, id: &str, author_id: &str) -> Result<Tenant, TenantError> {
+        self.update(id, |tenant| {
+            tenant.authors.remove(author_id);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Forgets a document dropped from the node, whichever tenant it belonged to.
+    pub async fn forget_doc(&self, doc_id: &str) -> Result<(), TenantError> {
+        match self.doc_owner(doc_id) {
+            Some(owner) => self.unassign_doc(&owner, doc_id).await.map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Forgets an author deleted from the node, whichever tenant it belonged to.
+    pub async fn forget_author(&self, author_id: &str) -> Result<(), TenantError> {
+        match self.author_owner(author_id) {
+            Some(owner) => self.unassign_author(&owner, author_id).await.map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds the API key `key` to the tenant, keeping only its hash.
+    pub async fn add_api_key(&self, id: &str, key_id: String, label: Option<String>, key: &str) -> Result<ApiKey, TenantError> {
+        let api_key = ApiKey { id: key_id, label, hash: hash_api_key(key), created_at: now() };
+        self.update(id, |tenant| {
+            tenant.api_keys.push(api_key.clone());
+            Ok(())
+        })
+        .await?;
+        Ok(api_key)
+    }
+
+    pub async fn revoke_api_key(&self, id: &str, key_id: &str) -> Result<Tenant, TenantError> {
+        self.update(id, |tenant| {
+            let before = tenant.api_keys.len();
+            tenant.api_keys.retain(|api_key| api_key.id != key_id);
+            if tenant.api_keys.len() == before {
+                return Err(TenantError::ApiKeyNotFound);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    // Applies `change` to the tenant `id` and saves the registry if it succeeds
+    async fn update(
+        &self,
+        id: &str,
+        change: impl FnOnce(&mut Tenant) -> Result<(), TenantError>,
+    ) -> Result<Tenant, TenantError> {
+        let tenant = {
+            let mut tenants = self.tenants.write().unwrap();
+            let tenant = tenants.get_mut(id).ok_or(TenantError::NotFound)?;
+            change(tenant)?;
+            tenant.clone()
+        };
+        self.save().await?;
+        Ok(tenant)
+    }
+
+    async fn save(&self) -> Result<(), TenantError> {
+        let Some(storage_path) = &self.storage_path else {
+            return Ok(());
+        };
+        let tenants: Vec<Tenant> = self.list();
+        let json = serde_json::to_string_pretty(&tenants).map_err(|e| TenantError::Storage(e.to_string()))?;
+        fs::write(storage_path, json).await.map_err(|e| TenantError::Storage(e.to_string()))
+    }
+}
+
+/// Whether `id` can name a tenant: 1 to 64 lowercase letters, digits and dashes.
+pub fn is_valid_tenant_id(id: &str) -> bool {
+    (1..=64).contains(&id.len()) && id.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+fn hash_api_key(key: &str) -> String {
+    Hash::new(key.as_bytes()).to_hex()
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry_index::IndexUpdate;
+    use serde_json::json;
+
+    // A tenant with the document "d-own" and the author "a-own", whose documents hold 100 bytes
+    async fn registry(quota: TenantQuota) -> (TenantRegistry, EntryIndex) {
+        let registry = TenantRegistry::default();
+        registry.create("acme".to_string(), None, quota).await.unwrap();
+        registry.assign_doc("acme", "d-own").await.unwrap();
+        registry.assign_author("acme", "a-own").await.unwrap();
+
+        let index = EntryIndex::new(Vec::new());
+        let update = IndexUpdate {
+            raw_key: b"key".to_vec(),
+            key: "key".to_string(),
+            author: "a-own".to_string(),
+            hash: "hash".to_string(),
+            len: 100,
+            timestamp: 1,
+        };
+        index.record_entry("d-own", update, None);
+        index.mark_ready("d-own");
+        (registry, index)
+    }
+
+    #[tokio::test]
+    async fn test_request_may_only_name_the_tenants_docs_and_authors() {
+        let (registry, index) = registry(TenantQuota::default()).await;
+        let tenant = registry.get("acme").unwrap();
+        let check = |access, caller, path: &[&str], body: serde_json::Value| {
+            tenant.check_request(access, caller, path, Some(&body), &index)
+        };
+
+        assert_eq!(check(TenantRoute::Store, Some("a-own"), &["d-own"], json!({ "doc_id": "d-own", "author_id": "a-own" })), Ok(()));
+        assert_eq!(check(TenantRoute::Read, None, &["d-other"], json!({})), Err(TenantRejection::DocNotFound));
+        assert_eq!(check(TenantRoute::Read, None, &[], json!({ "doc_id": "d-other" })), Err(TenantRejection::DocNotFound));
+        assert_eq!(check(TenantRoute::Read, Some("a-other"), &["d-own"], json!({})), Err(TenantRejection::ForeignAuthor));
+        assert_eq!(check(TenantRoute::Write, None, &[], json!({ "doc_id": "d-own", "author_id": "a-other" })), Err(TenantRejection::ForeignAuthor));
+        // reads may filter the tenant's documents by any author
+        assert_eq!(check(TenantRoute::Read, None, &[], json!({ "doc_id": "d-own", "author_id": "a-other" })), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_storage_quota_only_stops_storing_more() {
+        let (registry, index) = registry(TenantQuota { max_bytes: Some(100), ..Default::default() }).await;
+        let tenant = registry.get("acme").unwrap();
+        assert_eq!(tenant.stored_bytes(&index), 100);
+
+        assert_eq!(tenant.check_request(TenantRoute::Store, None, &["d-own"], None, &index), Err(TenantRejection::OverStorage));
+        assert_eq!(tenant.check_request(TenantRoute::Write, None, &["d-own"], None, &index), Ok(()));
+
+        let tenant = registry.set_quota("acme", TenantQuota { max_bytes: Some(101), ..Default::default() }).await.unwrap();
+        assert_eq!(tenant.check_request(TenantRoute::Store, None, &["d-own"], None, &index), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_doc_and_author_quotas_and_ownership() {
+        let (registry, _) = registry(TenantQuota { max_docs: Some(1), max_authors: Some(1), max_bytes: None }).await;
+        assert_eq!(registry.assign_doc("acme", "d-more").await.unwrap_err().to_string(), TenantError::QuotaExceeded("documents").to_string());
+        assert_eq!(registry.assign_author("acme", "a-more").await.unwrap_err().to_string(), TenantError::QuotaExceeded("authors").to_string());
+        // assigning what the tenant already has is not counted again
+        assert!(registry.assign_doc("acme", "d-own").await.is_ok());
+
+        registry.create("other".to_string(), None, TenantQuota::default()).await.unwrap();
+        assert!(matches!(registry.assign_doc("other", "d-own").await, Err(TenantError::DocAssigned(owner)) if owner == "acme"));
+        assert!(matches!(registry.assign_author("other", "a-own").await, Err(TenantError::AuthorAssigned(owner)) if owner == "acme"));
+    }
+
+    #[tokio::test]
+    async fn test_listings_only_keep_the_tenants_own() {
+        let (registry, _) = registry(TenantQuota::default()).await;
+        let tenant = registry.get("acme").unwrap();
+
+        let mut docs = vec![("d-own".to_string(), 1), ("d-other".to_string(), 2)];
+        retain_tenant_docs(Some(&tenant), &mut docs, |(doc_id, _)| doc_id);
+        assert_eq!(docs, vec![("d-own".to_string(), 1)]);
+
+        let mut authors = vec!["a-other".to_string(), "a-own".to_string()];
+        retain_tenant_authors(Some(&tenant), &mut authors);
+        assert_eq!(authors, vec!["a-own".to_string()]);
+
+        // without a tenant everything is listed
+        let mut authors = vec!["a-other".to_string(), "a-own".to_string()];
+        retain_tenant_authors(None, &mut authors);
+        assert_eq!(authors.len(), 2);
+    }
+}
@@ -1,14 +1,18 @@
 use data_encoding::HEXLOWER;
 use iroh_docs::AuthorId;
 use sp_core::{
-    crypto::{Ss58AddressFormat, Ss58Codec},
+    crypto::{AccountId32, Ss58AddressFormat, Ss58Codec},
     ed25519,
     ed25519::Public,
+    sr25519,
+    Pair,
 };
 use anyhow::{anyhow, Result};
+use keystore::keystore::wrap_bytes;
 use iroh_docs::store::{DownloadPolicy, FilterKind};
 use serde_json;
 use regex::Regex;
+use std::collections::BTreeSet;
 use axum::http::{HeaderMap, StatusCode};
 
 /// Encode a byte array into a custom document identifier.
@@ -141,6 +145,175 @@ impl ApiDownloadPolicy {
 
         Ok(ApiDownloadPolicy(download_policy))
     }
+
+    /// A policy that only downloads the content of entries whose key starts with one of
+    /// `prefixes`, and of their signatures. Without prefixes, everything is downloaded.
+    pub fn only_prefixes(prefixes: &[String]) -> Self {
+        if prefixes.is_empty() {
+            return ApiDownloadPolicy(DownloadPolicy::default());
+        }
+        let filters = prefixes
+            .iter()
+            .flat_map(|prefix| [prefix.clone(), signature_key(prefix)])
+            .map(|prefix| FilterKind::Prefix(prefix.into_bytes().into()))
+            .collect();
+        ApiDownloadPolicy(DownloadPolicy::NothingExcept(filters))
+    }
+
+    /// The key prefixes of a policy made by `only_prefixes`, an empty list if everything is
+    /// downloaded, or `None` for any other policy.
+    pub fn prefixes(&self) -> Option<Vec<String>> {
+        let filters = match &self.0 {
+            DownloadPolicy::EverythingExcept(filters) if filters.is_empty() => return Some(vec![]),
+            DownloadPolicy::NothingExcept(filters) if !filters.is_empty() => filters,
+            _ => return None,
+        };
+        let mut prefixes = BTreeSet::new();
+        for filter in filters {
+            let FilterKind::Prefix(bytes) = filter else { return None };
+            prefixes.insert(String::from_utf8(bytes.to_vec()).ok()?);
+        }
+        // leave out the signature prefixes `only_prefixes` adds, but not a `sig:` prefix set on its own
+        let listed = prefixes
+            .iter()
+            .filter(|prefix| {
+                prefix
+                    .strip_prefix(SIGNATURE_KEY_PREFIX)
+                    .is_none_or(|signed| !prefixes.contains(signed))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let expected: BTreeSet<String> = listed.iter().flat_map(|prefix| [prefix.clone(), signature_key(prefix)]).collect();
+        (expected == prefixes).then_some(listed)
+    }
+}
+
+/// Prefix of the entries that hold the detached signature of another entry's value.
+pub const SIGNATURE_KEY_PREFIX: &str = "sig:";
+
+/// The key of the entry holding the signature of the entry at `key`.
+pub fn signature_key(key: &str) -> String {
+    format!("{}{}", SIGNATURE_KEY_PREFIX, key)
+}
+
+/// Prefix of the entries holding files attached to a document, such as `files/contract.pdf`.
+/// Their values are not checked against the document schema, so documents with a schema can
+/// carry files next to their JSON entries.
+pub const ATTACHMENT_KEY_PREFIX: &str = "files/";
+
+/// Whether the entry at `key` is an attachment, see `ATTACHMENT_KEY_PREFIX`.
+pub fn is_attachment_key(key: &str) -> bool {
+    key.len() > ATTACHMENT_KEY_PREFIX.len() && key.starts_with(ATTACHMENT_KEY_PREFIX)
+}
+
+/// Prefix of the entries that keep deleted entries restorable, see `core::trash`.
+pub const TRASH_KEY_PREFIX: &str = "__trash/";
+
+/// The key of the entry keeping the deleted entry at `key`.
+pub fn trash_key(key: &str) -> String {
+    format!("{}{}", TRASH_KEY_PREFIX, key)
+}
+
+/// Whether the entry at `key` is a deleted entry kept in the trash.
+pub fn is_trash_key(key: &str) -> bool {
+    key.starts_with(TRASH_KEY_PREFIX)
+}
+
+/// Prefix of the entries that keep the past versions of entries, see `core::history`.
+pub const HISTORY_KEY_PREFIX: &str = "__history/";
+
+/// The key of the entry keeping the version of `key` written at `written_at`, in microseconds.
+/// The timestamp is zero-padded, so the versions of a key sort in the order they were written.
+pub fn history_key(key: &str, written_at: u64) -> String {
+    format!("{}{}/{:020}", HISTORY_KEY_PREFIX, key, written_at)
+}
+
+/// The key and write time of the version kept at `history_key`, if it is one.
+pub fn parse_history_key(history_key: &str) -> Option<(&str, u64)> {
+    let (key, written_at) = history_key.strip_prefix(HISTORY_KEY_PREFIX)?.rsplit_once('/')?;
+    Some((key, written_at.parse().ok()?))
+}
+
+/// Whether the entry at `key` is a past version of an entry.
+pub fn is_history_key(key: &str) -> bool {
+    key.starts_with(HISTORY_KEY_PREFIX)
+}
+
+/// Key of the entry that holds which nodes download the content of a document, see
+/// `core::pinning`. Its content is always downloaded.
+pub const CONTENT_POLICY_KEY: &str = "pinning:policy";
+
+/// Key of the entry that lists the shards of a sharded document, see `core::sharding`.
+pub const SHARD_MANIFEST_KEY: &str = "sharding:manifest";
+
+/// Prefix of the entries that record where a document was anchored on chain.
+pub const ANCHOR_KEY_PREFIX: &str = "anchor:";
+
+/// The key of the entry recording the anchor made by extrinsic `extrinsic_hash`.
+pub fn anchor_key(extrinsic_hash: &str) -> String {
+    format!("{}{}", ANCHOR_KEY_PREFIX, extrinsic_hash)
+}
+
+/// Prefix of the entries that record the revocation of an anchor or credential.
+pub const REVOCATION_KEY_PREFIX: &str = "revoked:";
+
+/// The key of the entry recording the revocation of the statement `statement_id`.
+pub fn revocation_key(statement_id: &str) -> String {
+    format!("{}{}", REVOCATION_KEY_PREFIX, statement_id)
+}
+
+/// Prefix of the entries that mirror definitions from the CORD chain.
+pub const CORD_KEY_PREFIX: &str = "cord:";
+
+/// The key of the entry mirroring the chain definition `id` of `kind`, e.g. `cord:space:<id>`.
+pub fn cord_definition_key(kind: &str, id: &str) -> String {
+    format!("{}{}:{}", CORD_KEY_PREFIX, kind, id)
+}
+
+/// The key of the entry mirroring the chain event at `event_index` of block `block_number`.
+/// Both are zero-padded, so the entries sort in chain order.
+pub fn cord_event_key(block_number: u64, event_index: u32) -> String {
+    format!("{}event:{:010}-{:05}", CORD_KEY_PREFIX, block_number, event_index)
+}
+
+/// The REST route of an entry. Keys may contain `/`, so the sub-resources of an entry are served
+/// by this route too and told apart by the end of the key, see `EntryResource`.
+pub const ENTRY_ROUTE: &str = "/docs/:id/entries/*key";
+
+/// What `GET /docs/:id/entries/*key` returns about the entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryResource {
+    Entry,
+    Attachments,
+    Verify,
+    InclusionProof,
+}
+
+impl EntryResource {
+    /// The entry key and resource named by the `*key` path of `ENTRY_ROUTE`, e.g.
+    /// `notes/a/verify` is the signature check of `notes/a`. A key ending in one of these
+    /// suffixes itself is read with `POST /docs/get-entry`.
+    pub fn split(path: &str) -> (&str, EntryResource) {
+        let suffixes = [
+            ("/attachments", EntryResource::Attachments),
+            ("/verify", EntryResource::Verify),
+            ("/inclusion-proof", EntryResource::InclusionProof),
+        ];
+        suffixes
+            .into_iter()
+            .find_map(|(suffix, resource)| path.strip_suffix(suffix).filter(|key| !key.is_empty()).map(|key| (key, resource)))
+            .unwrap_or((path, EntryResource::Entry))
+    }
+
+    /// The route template the resource is known by in `TENANT_ROUTES` and `ACCESS_LOGGED_ROUTES`.
+    pub fn route(&self) -> &'static str {
+        match self {
+            EntryResource::Entry => ENTRY_ROUTE,
+            EntryResource::Attachments => "/docs/:id/entries/*key/attachments",
+            EntryResource::Verify => "/docs/:id/entries/*key/verify",
+            EntryResource::InclusionProof => "/docs/:id/entries/*key/inclusion-proof",
+        }
+    }
 }
 
 pub async fn validate_key(
@@ -158,9 +331,80 @@ pub async fn validate_key(
         return Err(anyhow::anyhow!("The key 'schema' is reserved for document operations"));
     }
 
+    if check_reserved && key.starts_with(SIGNATURE_KEY_PREFIX) {
+        return Err(anyhow::anyhow!("Keys starting with '{}' are reserved for entry signatures", SIGNATURE_KEY_PREFIX));
+    }
+
+    if check_reserved && key.starts_with(ANCHOR_KEY_PREFIX) {
+        return Err(anyhow::anyhow!("Keys starting with '{}' are reserved for chain anchors", ANCHOR_KEY_PREFIX));
+    }
+
+    if check_reserved && key.starts_with(REVOCATION_KEY_PREFIX) {
+        return Err(anyhow::anyhow!("Keys starting with '{}' are reserved for revocations", REVOCATION_KEY_PREFIX));
+    }
+
+    if check_reserved && key.starts_with(CORD_KEY_PREFIX) {
+        return Err(anyhow::anyhow!("Keys starting with '{}' are reserved for entries mirrored from the CORD chain", CORD_KEY_PREFIX));
+    }
+
+    if check_reserved && key.starts_with(TRASH_KEY_PREFIX) {
+        return Err(anyhow::anyhow!("Keys starting with '{}' are reserved for deleted entries", TRASH_KEY_PREFIX));
+    }
+
+    if check_reserved && key.starts_with(HISTORY_KEY_PREFIX) {
+        return Err(anyhow::anyhow!("Keys starting with '{}' are reserved for past versions of entries", HISTORY_KEY_PREFIX));
+    }
+
+    if check_reserved && key == CONTENT_POLICY_KEY {
+        return Err(anyhow::anyhow!("The key '{}' is reserved for the content policy", CONTENT_POLICY_KEY));
+    }
+
+    if check_reserved && key == SHARD_MANIFEST_KEY {
+        return Err(anyhow::anyhow!("The key '{}' is reserved for the shard manifest", SHARD_MANIFEST_KEY));
+    }
+
+    Ok(())
+}
+
+/// Validates a key prefix whose entries are deleted at once: it must be a valid key that is not
+/// the start of any reserved key, so that no schema, signature, anchor, trash, history or other
+/// node-managed entry is deleted with it.
+pub async fn validate_key_prefix(prefix: &str) -> anyhow::Result<()> {
+    validate_key(prefix, true).await?;
+
+    let reserved = [
+        "schema",
+        SIGNATURE_KEY_PREFIX,
+        ANCHOR_KEY_PREFIX,
+        REVOCATION_KEY_PREFIX,
+        CORD_KEY_PREFIX,
+        CONTENT_POLICY_KEY,
+        SHARD_MANIFEST_KEY,
+        TRASH_KEY_PREFIX,
+        HISTORY_KEY_PREFIX,
+    ];
+    if let Some(key) = reserved.iter().find(|key| key.to_ascii_lowercase().starts_with(&prefix.to_ascii_lowercase())) {
+        return Err(anyhow::anyhow!("The prefix '{}' would delete the reserved key '{}'", prefix, key));
+    }
+
     Ok(())
 }
 
+/// Whether `signature` is `account`'s sr25519 or ed25519 signature over `message`. Wallets that
+/// sign raw messages wrap them in `<Bytes>...</Bytes>`, so that form is accepted too.
+pub fn verify_account_signature(account: &AccountId32, message: &str, signature: &[u8]) -> bool {
+    let public: &[u8; 32] = account.as_ref();
+    let wrapped = wrap_bytes(message.as_bytes());
+    let verified = [message.as_bytes(), wrapped.as_slice()].into_iter().any(|message| {
+        let sr25519_ok = sr25519::Signature::try_from(signature)
+            .is_ok_and(|signature| sr25519::Pair::verify(&signature, message, &sr25519::Public::from_raw(*public)));
+        sr25519_ok
+            || ed25519::Signature::try_from(signature)
+                .is_ok_and(|signature| ed25519::Pair::verify(&signature, message, &ed25519::Public::from_raw(*public)))
+    });
+    verified
+}
+
 pub fn normalize_domain(input: &str) -> Option<String> {
     let no_scheme = input.trim().trim_start_matches("http://").trim_start_matches("https://");
     no_scheme.split('/').next().map(|s| s.to_lowercase())
@@ -173,4 +417,31 @@ pub fn get_author_id_from_headers(headers: &HeaderMap) -> Result<String, (Status
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string())
         .ok_or((StatusCode::UNAUTHORIZED, "Missing or invalid author-id header".to_string()))
-}
\ No newline at end of file
+}
+// Who is calling, as recorded in the keystore audit log: the author if one is given, otherwise
+// the node ID or origin the gateway admitted
+pub fn get_caller_from_headers(headers: &HeaderMap) -> String {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    match (header("author-id"), header("nodeId"), header("Origin")) {
+        (Some(author_id), _, _) => format!("author:{}", author_id),
+        (None, Some(node_id), _) => format!("node:{}", node_id),
+        (None, None, Some(origin)) => format!("origin:{}", origin),
+        (None, None, None) => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_resource_split() {
+        assert_eq!(EntryResource::split("notes"), ("notes", EntryResource::Entry));
+        assert_eq!(EntryResource::split("notes/2024/a"), ("notes/2024/a", EntryResource::Entry));
+        assert_eq!(EntryResource::split("files/a.pdf/attachments"), ("files/a.pdf", EntryResource::Attachments));
+        assert_eq!(EntryResource::split("notes/a/verify"), ("notes/a", EntryResource::Verify));
+        assert_eq!(EntryResource::split("notes/a/inclusion-proof"), ("notes/a", EntryResource::InclusionProof));
+        // a key that is only the name of a resource is the entry itself
+        assert_eq!(EntryResource::split("verify"), ("verify", EntryResource::Entry));
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::{Arc, RwLock}};
+use tokio::fs;
+
+// File the registered webhooks are persisted to, inside the node's data directory.
+const WEBHOOKS_FILE: &str = "webhooks.json";
+
+/// Event types a webhook can subscribe to.
+pub const WEBHOOK_EVENTS: &[&str] = &["entry.set", "entry.deleted", "doc.synced"];
+
+/// A registered webhook endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// Shared secret used to sign payloads.
+    pub secret: String,
+    /// Document to watch, or `None` for every document on the node.
+    pub doc_id: Option<String>,
+    /// Event types to deliver. Empty means all of `WEBHOOK_EVENTS`.
+    pub events: Vec<String>,
+}
+
+impl Webhook {
+    /// Whether this webhook wants `event` for `doc_id`.
+    pub fn matches(&self, doc_id: &str, event: &str) -> bool {
+        self.doc_id.as_deref().is_none_or(|id| id == doc_id)
+            && (self.events.is_empty() || self.events.iter().any(|e| e == event))
+    }
+}
+
+/// The set of registered webhooks, shared through `AppState` and persisted as JSON.
+#[derive(Clone, Default)]
+pub struct WebhookRegistry {
+    hooks: Arc<RwLock<Vec<Webhook>>>,
+    storage_path: Option<PathBuf>,
+}
+
+impl WebhookRegistry {
+    /// Loads the registry from the data directory at `path`, starting empty if nothing was saved yet.
+    pub async fn load(path: &str) -> anyhow::Result<Self> {
+        let storage_path = PathBuf::from(path).join(WEBHOOKS_FILE);
+
+        let hooks = if fs::try_exists(&storage_path).await? {
+            let content = fs::read_to_string(&storage_path).await?;
+            serde_json::from_str(&content)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            hooks: Arc::new(RwLock::new(hooks)),
+            storage_path: Some(storage_path),
+        })
+    }
+
+    pub fn list(&self) -> Vec<Webhook> {
+        self.hooks.read().unwrap().clone()
+    }
+
+    /// Webhooks that should receive `event` for `doc_id`.
+    pub fn matching(&self, doc_id: &str, event: &str) -> Vec<Webhook> {
+        self.hooks
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|hook| hook.matches(doc_id, event))
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.read().unwrap().is_empty()
+    }
+
+    pub async fn register(&self, webhook: Webhook) -> anyhow::Result<()> {
+        self.hooks.write().unwrap().push(webhook);
+        self.save().await
+    }
+
+    /// Removes a webhook by ID. Returns `false` if no webhook had that ID.
+    pub async fn remove(&self, id: &str) -> anyhow::Result<bool> {
+        let removed = {
+            let mut hooks = self.hooks.write().unwrap();
+            let before = hooks.len();
+            hooks.retain(|hook| hook.id != id);
+            hooks.len() != before
+        };
+        if removed {
+            self.save().await?;
+        }
+        Ok(removed)
+    }
+
+    async fn save(&self) -> anyhow::Result<()> {
+        let Some(storage_path) = &self.storage_path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&*self.hooks.read().unwrap())?;
+        fs::write(storage_path, json).await?;
+        Ok(())
+    }
+}
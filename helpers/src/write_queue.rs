@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf, sync::{Arc, RwLock}};
+use tokio::fs;
+
+// File the queue is persisted to, inside the node's data directory.
+const WRITE_QUEUE_FILE: &str = "write_queue.json";
+
+/// A local write that no peer is known to have yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedWrite {
+    pub key: String,
+    pub author: String,
+    pub hash: String,
+    pub len: u64,
+    /// Timestamp of the entry, in microseconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+/// The local writes of every document that were not propagated to a peer yet, shared through
+/// `AppState` and persisted as JSON, so that a node working offline keeps track of them across
+/// restarts.
+///
+/// Writes are queued by `node::replication_tracker` from the documents' live events, and leave
+/// the queue once a sync round with a peer that started after them succeeds. Only the latest
+/// write of each author and key is kept, as the document does.
+#[derive(Clone, Default)]
+pub struct WriteQueue {
+    pending: Arc<RwLock<BTreeMap<String, Vec<QueuedWrite>>>>,
+    storage_path: Option<PathBuf>,
+}
+
+impl WriteQueue {
+    /// Loads the queue from the data directory at `path`, starting empty if nothing was saved yet.
+    pub async fn load(path: &str) -> anyhow::Result<Self> {
+        let storage_path = PathBuf::from(path).join(WRITE_QUEUE_FILE);
+
+        let pending = if fs::try_exists(&storage_path).await? {
+            let content = fs::read_to_string(&storage_path).await?;
+            serde_json::from_str(&content)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            pending: Arc::new(RwLock::new(pending)),
+            storage_path: Some(storage_path),
+        })
+    }
+
+    /// Pending writes of every document with any, oldest first.
+    pub fn list(&self) -> BTreeMap<String, Vec<QueuedWrite>> {
+        self.pending.read().unwrap().clone()
+    }
+
+    /// Pending writes of `doc_id`, oldest first.
+    pub fn get(&self, doc_id: &str) -> Vec<QueuedWrite> {
+        self.pending.read().unwrap().get(doc_id).cloned().unwrap_or_default()
+    }
+
+    /// Queues a local write to `doc_id`, replacing an earlier one of the same author and key.
+    pub async fn record(&self, doc_id: &str, write: QueuedWrite) -> anyhow::Result<()> {
+        {
+            let mut pending = self.pending.write().unwrap();
+            let writes = pending.entry(doc_id.to_string()).or_default();
+            writes.retain(|queued| queued.key != write.key || queued.author != write.author);
+            writes.push(write);
+        }
+        self.save().await
+    }
+
+    /// Removes the writes to `doc_id` made before `before`, in microseconds since the Unix epoch,
+    /// once a sync round with a peer that started then succeeded.
+    ///
+    /// # Returns
+    /// The number of writes removed.
+    pub async fn confirm(&self, doc_id: &str, before: u64) -> anyhow::Result<usize> {
+        let removed = {
+            let mut pending = self.pending.write().unwrap();
+            let Some(writes) = pending.get_mut(doc_id) else {
+                return Ok(0);
+            };
+            let count = writes.len();
+            writes.retain(|write| write.timestamp >= before);
+            let removed = count - writes.len();
+            if writes.is_empty() {
+                pending.remove(doc_id);
+            }
+            removed
+        };
+        if removed > 0 {
+            self.save().await?;
+        }
+        Ok(removed)
+    }
+
+    /// Forgets the writes to `doc_id`, once it is dropped.
+    pub async fn forget(&self, doc_id: &str) -> anyhow::Result<()> {
+        if self.pending.write().unwrap().remove(doc_id).is_some() {
+            self.save().await?;
+        }
+        Ok(())
+    }
+
+    async fn save(&self) -> anyhow::Result<()> {
+        let Some(storage_path) = &self.storage_path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&*self.pending.read().unwrap())?;
+        fs::write(storage_path, json).await?;
+        Ok(())
+    }
+}
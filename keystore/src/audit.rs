@@ -0,0 +1,277 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sp_core::blake2_256;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Every use of a private key is appended to this file in the keystore directory, one JSON record
+// per line. Each record carries the hash of the line before it, so removing or editing a record
+// breaks the chain and `AuditLog::verify` reports where.
+
+const AUDIT_LOG_FILE: &str = "audit.log";
+
+/// The caller recorded for operations the node performs on its own, e.g. signing chain
+/// transactions.
+pub const NODE_CALLER: &str = "node";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOperation {
+    Sign,
+    Decrypt,
+}
+
+/// What happened, as reported by the code that used the key.
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub operation: AuditOperation,
+    /// Key type name, e.g. `cord`, or `dek` for data encryption keys.
+    pub key_type: String,
+    /// SS58 public key, or `<doc_id>/v<version>` for data encryption keys.
+    pub key: String,
+    pub purpose: Option<String>,
+    /// `author:<id>`, `node:<id>`, `origin:<domain>`, or `node` for the node itself.
+    pub caller: String,
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Position in the log, starting at 1.
+    pub seq: u64,
+    /// Unix time in seconds.
+    pub timestamp: u64,
+    pub operation: AuditOperation,
+    pub key_type: String,
+    pub key: String,
+    pub purpose: Option<String>,
+    pub caller: String,
+    pub success: bool,
+    /// Hex blake2-256 hash of the previous line, or all zeros for the first record.
+    pub prev_hash: String,
+}
+
+/// Narrows `AuditLog::query`. Unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub operation: Option<AuditOperation>,
+    pub key_type: Option<String>,
+    pub key: Option<String>,
+    pub caller: Option<String>,
+    /// Only records at or after this unix time.
+    pub since: Option<u64>,
+    /// Only records at or before this unix time.
+    pub until: Option<u64>,
+}
+
+impl AuditFilter {
+    fn matches(&self, record: &AuditRecord) -> bool {
+        self.operation.is_none_or(|operation| operation == record.operation)
+            && self.key_type.as_ref().is_none_or(|key_type| *key_type == record.key_type)
+            && self.key.as_ref().is_none_or(|key| *key == record.key)
+            && self.caller.as_ref().is_none_or(|caller| *caller == record.caller)
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp <= until)
+    }
+}
+
+struct Tail {
+    file: File,
+    seq: u64,
+    last_hash: [u8; 32],
+}
+
+/// The append-only log of key usage, shared by everything that signs or decrypts.
+#[derive(Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    tail: Arc<Mutex<Tail>>,
+}
+
+impl AuditLog {
+    /// Opens the audit log in the keystore directory at `keystore_dir`, creating it if needed.
+    pub fn open(keystore_dir: &Path) -> Result<Self> {
+        let path = keystore_dir.join(AUDIT_LOG_FILE);
+        let (seq, last_hash) = read_chain(&path)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| anyhow!("❌ Failed to open audit log {:?}: {e}", path))?;
+
+        Ok(Self { path, tail: Arc::new(Mutex::new(Tail { file, seq, last_hash })) })
+    }
+
+    /// Appends `event`. Callers should not hand out the result of an operation whose record
+    /// could not be written.
+    pub fn record(&self, event: AuditEvent) -> Result<AuditRecord> {
+        let mut tail = self.tail.lock().map_err(|_| anyhow!("❌ Audit log lock poisoned"))?;
+        let record = AuditRecord {
+            seq: tail.seq + 1,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            operation: event.operation,
+            key_type: event.key_type,
+            key: event.key,
+            purpose: event.purpose,
+            caller: event.caller,
+            success: event.success,
+            prev_hash: hex::encode(tail.last_hash),
+        };
+        let line = serde_json::to_string(&record)?;
+        writeln!(tail.file, "{}", line)
+            .and_then(|_| tail.file.sync_data())
+            .map_err(|e| anyhow!("❌ Failed to write audit log {:?}: {e}", self.path))?;
+        tail.seq = record.seq;
+        tail.last_hash = blake2_256(line.as_bytes());
+        Ok(record)
+    }
+
+    /// Returns the records matching `filter`, oldest first.
+    pub fn query(&self, filter: &AuditFilter) -> Result<Vec<AuditRecord>> {
+        // hold the lock so a half-written line is never read
+        let _tail = self.tail.lock().map_err(|_| anyhow!("❌ Audit log lock poisoned"))?;
+        let mut records = Vec::new();
+        for line in lines(&self.path)? {
+            let record: AuditRecord = serde_json::from_str(&line?)?;
+            if filter.matches(&record) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Checks that no record was removed, reordered or edited. Returns the number of records.
+    pub fn verify(&self) -> Result<u64> {
+        let _tail = self.tail.lock().map_err(|_| anyhow!("❌ Audit log lock poisoned"))?;
+        read_chain(&self.path).map(|(seq, _)| seq)
+    }
+}
+
+fn lines(path: &Path) -> Result<impl Iterator<Item = std::io::Result<String>>> {
+    let file = File::open(path).map_err(|e| anyhow!("❌ Failed to read audit log {:?}: {e}", path))?;
+    Ok(BufReader::new(file).lines())
+}
+
+// Walks the whole chain and returns the last sequence number and line hash
+fn read_chain(path: &Path) -> Result<(u64, [u8; 32])> {
+    let mut seq = 0;
+    let mut last_hash = [0u8; 32];
+    if !path.exists() {
+        return Ok((seq, last_hash));
+    }
+    for line in lines(path)? {
+        let line = line?;
+        let record: AuditRecord = serde_json::from_str(&line)
+            .map_err(|e| anyhow!("❌ Audit log {:?} has an invalid record after #{}: {e}", path, seq))?;
+        if record.seq != seq + 1 || record.prev_hash != hex::encode(last_hash) {
+            return Err(anyhow!("❌ Audit log {:?} was tampered with: the chain breaks at record #{}", path, record.seq));
+        }
+        seq = record.seq;
+        last_hash = blake2_256(line.as_bytes());
+    }
+    Ok((seq, last_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(key: &str) -> AuditEvent {
+        AuditEvent {
+            operation: AuditOperation::Sign,
+            key_type: "sr25519".to_string(),
+            key: key.to_string(),
+            purpose: None,
+            caller: NODE_CALLER.to_string(),
+            success: true,
+        }
+    }
+
+    // A log of three records, and its lines
+    fn log_of_three(dir: &Path) -> (AuditLog, Vec<String>) {
+        let log = AuditLog::open(dir).unwrap();
+        for key in ["a", "b", "c"] {
+            log.record(event(key)).unwrap();
+        }
+        let lines = std::fs::read_to_string(dir.join(AUDIT_LOG_FILE)).unwrap().lines().map(str::to_string).collect();
+        (log, lines)
+    }
+
+    fn rewrite(dir: &Path, lines: &[String]) {
+        let content: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        std::fs::write(dir.join(AUDIT_LOG_FILE), content).unwrap();
+    }
+
+    #[test]
+    fn test_chain_verifies() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::open(dir.path()).unwrap();
+        assert_eq!(log.verify().unwrap(), 0);
+
+        let first = log.record(event("a")).unwrap();
+        assert_eq!((first.seq, first.prev_hash.as_str()), (1, hex::encode([0u8; 32]).as_str()));
+        let second = log.record(event("b")).unwrap();
+        assert_eq!(second.seq, 2);
+        assert_ne!(second.prev_hash, first.prev_hash);
+        assert_eq!(log.verify().unwrap(), 2);
+
+        let filter = AuditFilter { key: Some("b".to_string()), ..Default::default() };
+        assert_eq!(log.query(&filter).unwrap().iter().map(|record| record.seq).collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn test_tampered_record_is_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let (log, mut lines) = log_of_three(dir.path());
+        lines[1] = lines[1].replace("\"success\":true", "\"success\":false");
+        rewrite(dir.path(), &lines);
+
+        let err = log.verify().unwrap_err().to_string();
+        assert!(err.contains("chain breaks at record #3"), "{}", err);
+        assert!(AuditLog::open(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_reordered_and_removed_records_are_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let (log, lines) = log_of_three(dir.path());
+
+        rewrite(dir.path(), &[lines[0].clone(), lines[2].clone(), lines[1].clone()]);
+        assert!(log.verify().unwrap_err().to_string().contains("chain breaks at record #3"));
+
+        rewrite(dir.path(), &[lines[0].clone(), lines[2].clone()]);
+        assert!(log.verify().unwrap_err().to_string().contains("chain breaks at record #3"));
+
+        rewrite(dir.path(), &lines[1..]);
+        assert!(log.verify().unwrap_err().to_string().contains("chain breaks at record #2"));
+    }
+
+    #[test]
+    fn test_truncated_record_is_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let (log, mut lines) = log_of_three(dir.path());
+        let last = lines.pop().unwrap();
+        lines.push(last[..last.len() / 2].to_string());
+        rewrite(dir.path(), &lines);
+
+        let err = log.verify().unwrap_err().to_string();
+        assert!(err.contains("invalid record after #2"), "{}", err);
+    }
+
+    #[test]
+    fn test_chain_continues_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let (log, _) = log_of_three(dir.path());
+        drop(log);
+
+        let reopened = AuditLog::open(dir.path()).unwrap();
+        assert_eq!(reopened.verify().unwrap(), 3);
+        assert_eq!(reopened.record(event("d")).unwrap().seq, 4);
+        assert_eq!(reopened.verify().unwrap(), 4);
+        let keys: Vec<String> = reopened.query(&AuditFilter::default()).unwrap().into_iter().map(|record| record.key).collect();
+        assert_eq!(keys, vec!["a", "b", "c", "d"]);
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::{anyhow, Result};
+use chrono::{SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use sp_core::hashing::sha2_256;
+use crate::did::{did_key, parse_did_key};
+use crate::keystore::{KeyScheme, StarterkitKeystore};
+use crate::registry::KeyTypeInfo;
+
+// W3C Verifiable Credentials (data model 2.0) with Data Integrity proofs made by keystore keys.
+// Issuers are `did:key` DIDs, so a credential can be checked with nothing but its own contents.
+
+const CREDENTIALS_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+
+/// The standard cryptosuite for ed25519 keys.
+pub const ED25519_CRYPTOSUITE: &str = "eddsa-jcs-2022";
+/// sr25519 has no registered cryptosuite. This one follows `eddsa-jcs-2022` step by step and only
+/// swaps the signature scheme, so verifiers have to support it explicitly.
+pub const SR25519_CRYPTOSUITE: &str = "sr25519-jcs-2022";
+
+/// What a credential says, before it is signed.
+pub struct CredentialClaims {
+    /// e.g. `urn:uuid:...`.
+    pub id: String,
+    /// The claims about the subject. Its `id`, if any, is the holder.
+    pub subject: Value,
+    /// The JSON Schema the subject was validated against.
+    pub schema_id: String,
+}
+
+/// Builds a credential from `claims` and signs it with the keystore key `public`, which must be
+/// allowed to sign documents. Returns the credential with its proof.
+pub fn issue_credential(
+    keystore: &StarterkitKeystore,
+    key_type: &KeyTypeInfo,
+    public: &[u8],
+    claims: CredentialClaims,
+) -> Result<Value> {
+    if key_type.scheme == KeyScheme::Ecdsa {
+        return Err(anyhow!("❌ Credentials can only be signed with ed25519 or sr25519 keys"));
+    }
+    let issuer = did_key(key_type.scheme, public);
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    let mut credential = json!({
+        "@context": [CREDENTIALS_CONTEXT],
+        "id": claims.id,
+        "type": ["VerifiableCredential"],
+        "issuer": issuer,
+        "validFrom": now,
+        "credentialSchema": { "id": claims.schema_id, "type": "JsonSchema" },
+        "credentialSubject": claims.subject,
+    });
+
+    let mut proof = json!({
+        "type": "DataIntegrityProof",
+        "cryptosuite": cryptosuite(key_type.scheme),
+        "created": now,
+        "verificationMethod": verification_method(&issuer),
+        "proofPurpose": "assertionMethod",
+    });
+    let signature = keystore.sign_proof_hash(key_type, public, &hash_data(&credential, &proof)?)?;
+    proof["proofValue"] = Value::String(format!("z{}", bs58::encode(signature).into_string()));
+
+    credential["proof"] = proof;
+    Ok(credential)
+}
+
+/// Checks the proof of a credential made by `issue_credential`, against the key in its issuer's
+/// `did:key`.
+pub fn verify_credential(credential: &Value) -> Result<bool> {
+    let mut credential: Map<String, Value> = credential
+        .as_object()
+        .cloned()
+        .ok_or_else(|| anyhow!("❌ A credential must be a JSON object"))?;
+    let mut proof = credential.remove("proof").ok_or_else(|| anyhow!("❌ The credential has no proof"))?;
+    let proof_value = proof
+        .as_object_mut()
+        .and_then(|proof| proof.remove("proofValue"))
+        .and_then(|value| value.as_str().map(str::to_string))
+        .ok_or_else(|| anyhow!("❌ The proof has no proofValue"))?;
+    let signature = proof_value
+        .strip_prefix('z')
+        .and_then(|encoded| bs58::decode(encoded).into_vec().ok())
+        .ok_or_else(|| anyhow!("❌ proofValue must be base58btc multibase"))?;
+
+    let issuer = credential.get("issuer").and_then(Value::as_str).unwrap_or_default();
+    let (scheme, public) = parse_did_key(issuer)?;
+    if scheme == KeyScheme::Ecdsa {
+        return Err(anyhow!("❌ Unsupported did:key {}", issuer));
+    }
+    if proof.get("cryptosuite").and_then(Value::as_str) != Some(cryptosuite(scheme))
+        || proof.get("verificationMethod").and_then(Value::as_str) != Some(verification_method(issuer).as_str())
+    {
+        return Ok(false);
+    }
+
+    let key_type = KeyTypeInfo { name: String::new(), id: String::new(), scheme, purposes: Vec::new(), builtin: false };
+    StarterkitKeystore::verify_message(&key_type, &public, &hash_data(&Value::Object(credential), &proof)?, &signature)
+}
+
+fn cryptosuite(scheme: KeyScheme) -> &'static str {
+    match scheme {
+        KeyScheme::Sr25519 => SR25519_CRYPTOSUITE,
+        _ => ED25519_CRYPTOSUITE,
+    }
+}
+
+// A did:key has a single key, identified by the DID's own multibase value
+fn verification_method(issuer: &str) -> String {
+    format!("{}#{}", issuer, issuer.trim_start_matches("did:key:"))
+}
+
+// The data a proof signs under `eddsa-jcs-2022`: sha256 of the canonical proof options (with the
+// credential's context) followed by sha256 of the canonical credential
+fn hash_data(credential: &Value, proof: &Value) -> Result<[u8; 64]> {
+    let mut proof_config = proof.clone();
+    proof_config["@context"] = credential["@context"].clone();
+
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(&sha2_256(&canonicalize(&proof_config)?));
+    data[32..].copy_from_slice(&sha2_256(&canonicalize(credential)?));
+    Ok(data)
+}
+
+// JSON Canonicalization (RFC 8785). serde_json keeps object keys sorted and writes no whitespace,
+// which is the canonical form for the ASCII keys and integer values credentials use here.
+fn canonicalize(value: &Value) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(value)?)
+}
@@ -0,0 +1,320 @@
+use anyhow::{anyhow, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+use crate::audit::{AuditEvent, AuditLog, AuditOperation, NODE_CALLER};
+
+// Envelope encryption: every document gets its own data encryption key (DEK), and DEKs are only
+// stored wrapped (encrypted) by the node master key. Rotating a DEK adds a new version and keeps
+// the old ones, so data encrypted before the rotation stays readable.
+
+const NONCE_LEN: usize = 24;
+
+/// The node key that wraps every DEK. See `StarterkitKeystore::get_master_key`.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct MasterKey([u8; 32]);
+
+impl MasterKey {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Encrypts `dek` for storage. The document ID and version are bound as associated data, so a
+    /// wrapped DEK cannot be moved to another document or version.
+    pub fn wrap(&self, doc_id: &str, version: u32, dek: &Dek) -> Result<WrappedDek> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let aad = wrap_aad(doc_id, version);
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, Payload { msg: &dek.0, aad: &aad })
+            .map_err(|_| anyhow!("❌ Failed to wrap data encryption key"))?;
+
+        Ok(WrappedDek {
+            version,
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    /// Decrypts a DEK wrapped by `wrap` for the same document.
+    pub fn unwrap(&self, doc_id: &str, wrapped: &WrappedDek) -> Result<Dek> {
+        let nonce = hex::decode(&wrapped.nonce).map_err(|e| anyhow!("❌ Invalid nonce in wrapped key: {e}"))?;
+        if nonce.len() != NONCE_LEN {
+            return Err(anyhow!("❌ Invalid nonce in wrapped key"));
+        }
+        let ciphertext = hex::decode(&wrapped.ciphertext).map_err(|e| anyhow!("❌ Invalid wrapped key: {e}"))?;
+        let aad = wrap_aad(doc_id, wrapped.version);
+
+        let mut bytes = self
+            .cipher()
+            .decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: &aad })
+            .map_err(|_| anyhow!("❌ Failed to unwrap data encryption key. It was wrapped by another master key or is corrupted."))?;
+        let dek = Dek::from_slice(&bytes);
+        bytes.zeroize();
+        dek
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(Key::from_slice(&self.0))
+    }
+}
+
+fn wrap_aad(doc_id: &str, version: u32) -> Vec<u8> {
+    format!("starter-kit-dek/{}/{}", doc_id, version).into_bytes()
+}
+
+/// A plaintext data encryption key. Only kept in memory, and wiped when dropped.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct Dek([u8; 32]);
+
+impl Dek {
+    pub fn generate() -> Self {
+        Self(XChaCha20Poly1305::generate_key(&mut OsRng).into())
+    }
+
+    fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let bytes: [u8; 32] = bytes.try_into().map_err(|_| anyhow!("❌ A data encryption key must be 32 bytes"))?;
+        Ok(Self(bytes))
+    }
+
+    /// Encrypts `data` with XChaCha20-Poly1305. The output is the 24-byte nonce followed by the
+    /// ciphertext.
+    pub fn encrypt(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(&self.0))
+            .encrypt(&nonce, Payload { msg: data, aad })
+            .map_err(|_| anyhow!("❌ Failed to encrypt data"))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypts the output of `encrypt`, given the same associated data.
+    pub fn decrypt(&self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow!("❌ Encrypted data is too short"));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        XChaCha20Poly1305::new(Key::from_slice(&self.0))
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| anyhow!("❌ Failed to decrypt data. The key is wrong or the data is corrupted."))
+    }
+}
+
+/// A DEK as stored on disk, encrypted by the master key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WrappedDek {
+    pub version: u32,
+    pub created_at: u64,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+// doc_id -> versions of its DEK, oldest first. The last one is current.
+type DekFile = HashMap<String, Vec<WrappedDek>>;
+
+/// Creates, rotates and unwraps the DEKs of a node, persisted wrapped in a JSON file.
+pub struct DekManager {
+    path: PathBuf,
+    master: MasterKey,
+    deks: Mutex<DekFile>,
+    audit: Option<AuditLog>,
+}
+
+impl DekManager {
+    /// Opens the DEK file at `path`, e.g. `<data>/keystore/deks.json`. A missing file is empty.
+    pub fn open(path: impl Into<PathBuf>, master: MasterKey) -> Result<Self> {
+        let path = path.into();
+        let deks = if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow!("❌ Failed to read DEK file {:?}: {e}", path))?;
+            serde_json::from_str(&content).map_err(|e| anyhow!("❌ Invalid DEK file {:?}: {e}", path))?
+        } else {
+            DekFile::new()
+        };
+        Ok(Self { path, master, deks: Mutex::new(deks), audit: None })
+    }
+
+    /// Records every DEK unwrap in `audit`. A DEK is not handed out if its record cannot be
+    /// written.
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Creates the first DEK of `doc_id` and returns its version.
+    pub fn create(&self, doc_id: &str) -> Result<u32> {
+        let mut deks = self.deks.lock().map_err(|_| anyhow!("❌ DEK store lock poisoned"))?;
+        if deks.contains_key(doc_id) {
+            return Err(anyhow!("❌ Document {} already has a data encryption key. Rotate it instead.", doc_id));
+        }
+        let wrapped = self.master.wrap(doc_id, 1, &Dek::generate())?;
+        deks.insert(doc_id.to_string(), vec![wrapped]);
+        save(&self.path, &deks)?;
+        Ok(1)
+    }
+
+    /// Adds a new DEK version for `doc_id` and returns it. Older versions stay available to
+    /// `unwrap`, so existing data can still be decrypted and re-encrypted at leisure.
+    pub fn rotate(&self, doc_id: &str) -> Result<u32> {
+        let mut deks = self.deks.lock().map_err(|_| anyhow!("❌ DEK store lock poisoned"))?;
+        let versions = deks
+            .get_mut(doc_id)
+            .ok_or_else(|| anyhow!("❌ No data encryption key found for document {}", doc_id))?;
+        let version = versions.last().map_or(1, |wrapped| wrapped.version + 1);
+        versions.push(self.master.wrap(doc_id, version, &Dek::generate())?);
+        save(&self.path, &deks)?;
+        Ok(version)
+    }
+
+    /// Returns the current DEK of `doc_id` with its version, for encrypting new data.
+    pub fn current(&self, doc_id: &str) -> Result<(u32, Dek)> {
+        let deks = self.deks.lock().map_err(|_| anyhow!("❌ DEK store lock poisoned"))?;
+        let wrapped = deks
+            .get(doc_id)
+            .and_then(|versions| versions.last())
+            .ok_or_else(|| anyhow!("❌ No data encryption key found for document {}", doc_id))?;
+        let dek = self.master.unwrap(doc_id, wrapped);
+        self.record(doc_id, wrapped.version, dek.is_ok())?;
+        Ok((wrapped.version, dek?))
+    }
+
+    /// Returns the DEK `version` of `doc_id`, for decrypting data encrypted with it.
+    pub fn unwrap(&self, doc_id: &str, version: u32) -> Result<Dek> {
+        let deks = self.deks.lock().map_err(|_| anyhow!("❌ DEK store lock poisoned"))?;
+        let wrapped = deks
+            .get(doc_id)
+            .and_then(|versions| versions.iter().find(|wrapped| wrapped.version == version))
+            .ok_or_else(|| anyhow!("❌ No data encryption key version {} found for document {}", version, doc_id))?;
+        let dek = self.master.unwrap(doc_id, wrapped);
+        self.record(doc_id, version, dek.is_ok())?;
+        dek
+    }
+
+    /// Lists the DEK versions of `doc_id`, oldest first.
+    pub fn versions(&self, doc_id: &str) -> Result<Vec<u32>> {
+        let deks = self.deks.lock().map_err(|_| anyhow!("❌ DEK store lock poisoned"))?;
+        Ok(deks
+            .get(doc_id)
+            .map(|versions| versions.iter().map(|wrapped| wrapped.version).collect())
+            .unwrap_or_default())
+    }
+
+    fn record(&self, doc_id: &str, version: u32, success: bool) -> Result<()> {
+        let Some(audit) = &self.audit else {
+            return Ok(());
+        };
+        audit.record(AuditEvent {
+            operation: AuditOperation::Decrypt,
+            key_type: "dek".to_string(),
+            key: format!("{}/v{}", doc_id, version),
+            purpose: None,
+            caller: NODE_CALLER.to_string(),
+            success,
+        })?;
+        Ok(())
+    }
+}
+
+// Written under a temporary name and renamed, so a crash never leaves a truncated DEK file
+fn save(path: &Path, deks: &DekFile) -> Result<()> {
+    let json = serde_json::to_string_pretty(deks)?;
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    std::fs::write(&partial, json).map_err(|e| anyhow!("❌ Failed to write DEK file {:?}: {e}", path))?;
+    std::fs::rename(&partial, path).map_err(|e| anyhow!("❌ Failed to write DEK file {:?}: {e}", path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC_ID: &str = "doc-a";
+
+    fn manager(dir: &Path, master: [u8; 32]) -> DekManager {
+        DekManager::open(dir.join("deks.json"), MasterKey::from_bytes(master)).unwrap()
+    }
+
+    #[test]
+    fn test_dek_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let deks = manager(dir.path(), [1; 32]);
+        assert_eq!(deks.create(DOC_ID).unwrap(), 1);
+        assert!(deks.create(DOC_ID).is_err());
+
+        let (version, dek) = deks.current(DOC_ID).unwrap();
+        let sealed = dek.encrypt(b"secret", b"entry").unwrap();
+        assert_ne!(&sealed[NONCE_LEN..], b"secret");
+
+        // the DEK is only stored wrapped, and unwraps to the same key after a restart
+        let reopened = manager(dir.path(), [1; 32]);
+        assert_eq!(reopened.unwrap(DOC_ID, version).unwrap().decrypt(&sealed, b"entry").unwrap(), b"secret");
+        assert!(reopened.unwrap(DOC_ID, version).unwrap().decrypt(&sealed, b"other entry").is_err());
+    }
+
+    #[test]
+    fn test_dek_cannot_be_unwrapped_with_another_master_key() {
+        let dir = tempfile::tempdir().unwrap();
+        manager(dir.path(), [1; 32]).create(DOC_ID).unwrap();
+
+        let other = manager(dir.path(), [2; 32]);
+        assert!(other.unwrap(DOC_ID, 1).is_err());
+        assert!(other.current(DOC_ID).is_err());
+
+        // a wrapped DEK is bound to its document and version
+        let master = MasterKey::from_bytes([1; 32]);
+        let wrapped = master.wrap(DOC_ID, 1, &Dek::generate()).unwrap();
+        assert!(master.unwrap(DOC_ID, &wrapped).is_ok());
+        assert!(master.unwrap("doc-b", &wrapped).is_err());
+        assert!(master.unwrap(DOC_ID, &WrappedDek { version: 2, ..wrapped }).is_err());
+    }
+
+    #[test]
+    fn test_tampered_ciphertext_is_rejected() {
+        let dek = Dek::generate();
+        let sealed = dek.encrypt(b"secret", b"").unwrap();
+        for index in [0, NONCE_LEN, sealed.len() - 1] {
+            let mut tampered = sealed.clone();
+            tampered[index] ^= 1;
+            assert!(dek.decrypt(&tampered, b"").is_err(), "byte {} was not authenticated", index);
+        }
+        assert!(dek.decrypt(&sealed[..NONCE_LEN - 1], b"").is_err());
+        assert!(Dek::generate().decrypt(&sealed, b"").is_err());
+
+        let master = MasterKey::from_bytes([1; 32]);
+        let wrapped = master.wrap(DOC_ID, 1, &dek).unwrap();
+        let mut ciphertext = hex::decode(&wrapped.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        let tampered = WrappedDek { ciphertext: hex::encode(ciphertext), ..wrapped };
+        assert!(master.unwrap(DOC_ID, &tampered).is_err());
+    }
+
+    #[test]
+    fn test_rotation_keeps_old_versions_readable() {
+        let dir = tempfile::tempdir().unwrap();
+        let deks = manager(dir.path(), [1; 32]);
+        assert!(deks.rotate(DOC_ID).is_err());
+        deks.create(DOC_ID).unwrap();
+        let (_, old) = deks.current(DOC_ID).unwrap();
+        let old_sealed = old.encrypt(b"before", b"").unwrap();
+
+        assert_eq!(deks.rotate(DOC_ID).unwrap(), 2);
+        let (version, new) = deks.current(DOC_ID).unwrap();
+        assert_eq!(version, 2);
+        assert!(new.decrypt(&old_sealed, b"").is_err());
+
+        let reopened = manager(dir.path(), [1; 32]);
+        assert_eq!(reopened.versions(DOC_ID).unwrap(), vec![1, 2]);
+        assert_eq!(reopened.unwrap(DOC_ID, 1).unwrap().decrypt(&old_sealed, b"").unwrap(), b"before");
+        assert!(reopened.unwrap(DOC_ID, 3).is_err());
+        assert!(reopened.versions("doc-b").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+use crate::keystore::{KeyScheme, StarterkitKeystore, STARTERKIT_KEY_TYPE};
+use crate::registry::{builtin_key_type, KeyPurpose, KeyTypeInfo};
+
+// DID documents for `did:key` DIDs. A `did:key` is its own public key, so peers, authors and
+// credential issuers get DIDs without any registry. The node's DID is the `did:key` of its
+// STARTERKIT key; its document also lists the node's other keystore keys and its services.
+
+const DID_CONTEXT: &str = "https://www.w3.org/ns/did/v1";
+const MULTIKEY_CONTEXT: &str = "https://w3id.org/security/multikey/v1";
+
+// multicodec prefixes of the public keys in a `did:key`
+const ED25519_MULTICODEC: [u8; 2] = [0xed, 0x01];
+const SR25519_MULTICODEC: [u8; 2] = [0xef, 0x01];
+const SECP256K1_MULTICODEC: [u8; 2] = [0xe7, 0x01];
+
+/// A public key in multibase form (`z` + base58btc of the multicodec-prefixed key), as used in
+/// `did:key` and `publicKeyMultibase`. ecdsa keys are secp256k1 and must be compressed.
+pub fn multikey(scheme: KeyScheme, public: &[u8]) -> String {
+    let codec = match scheme {
+        KeyScheme::Ed25519 => ED25519_MULTICODEC,
+        KeyScheme::Sr25519 => SR25519_MULTICODEC,
+        KeyScheme::Ecdsa => SECP256K1_MULTICODEC,
+    };
+    let mut bytes = codec.to_vec();
+    bytes.extend_from_slice(public);
+    format!("z{}", bs58::encode(bytes).into_string())
+}
+
+/// The `did:key` of a public key.
+pub fn did_key(scheme: KeyScheme, public: &[u8]) -> String {
+    format!("did:key:{}", multikey(scheme, public))
+}
+
+/// Returns the scheme and public key of a `did:key`.
+pub fn parse_did_key(did: &str) -> Result<(KeyScheme, Vec<u8>)> {
+    let key = did
+        .strip_prefix("did:key:z")
+        .and_then(|encoded| bs58::decode(encoded).into_vec().ok())
+        .ok_or_else(|| anyhow!("❌ '{}' is not a did:key", did))?;
+    if key.len() < 2 {
+        return Err(anyhow!("❌ Unsupported did:key {}", did));
+    }
+    let (codec, public) = key.split_at(2);
+    let scheme = match (codec, public.len()) {
+        (c, 32) if c == ED25519_MULTICODEC => KeyScheme::Ed25519,
+        (c, 32) if c == SR25519_MULTICODEC => KeyScheme::Sr25519,
+        (c, 33) if c == SECP256K1_MULTICODEC => KeyScheme::Ecdsa,
+        _ => return Err(anyhow!("❌ Unsupported did:key {}. Only ed25519, sr25519 and compressed secp256k1 keys are supported.", did)),
+    };
+    Ok((scheme, public.to_vec()))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMethod {
+    /// `<did>#<multikey>`, like the single key of a `did:key` document.
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub controller: String,
+    pub public_key_multibase: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Service {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub service_endpoint: Value,
+}
+
+/// A DID document, serialized as `application/did+json`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocument {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    pub id: String,
+    pub verification_method: Vec<VerificationMethod>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub authentication: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub assertion_method: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub capability_invocation: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub service: Vec<Service>,
+}
+
+impl DidDocument {
+    /// The document `did` resolves to under the `did:key` method: its one key, usable for every
+    /// verification relationship.
+    pub fn for_did_key(did: &str) -> Result<Self> {
+        let (scheme, public) = parse_did_key(did)?;
+        let mut document = Self::empty(did.to_string());
+        let id = document.add_key(scheme, &public);
+        document.authentication.push(id.clone());
+        document.assertion_method.push(id.clone());
+        document.capability_invocation.push(id);
+        Ok(document)
+    }
+
+    /// The node's document. Its DID is the `did:key` of the STARTERKIT key, which has every
+    /// relationship as in `for_did_key`. The keys of `key_types` follow, with relationships
+    /// from their purposes: `doc-signing` keys are assertion methods, `api-auth` keys
+    /// authenticate and `chain-tx` keys invoke capabilities.
+    pub fn for_node(keystore: &StarterkitKeystore, key_types: &[KeyTypeInfo]) -> Result<Self> {
+        let starterkit = builtin_key_type(STARTERKIT_KEY_TYPE)
+            .ok_or_else(|| anyhow!("❌ The STARTERKIT key type is not built in"))?;
+        let identity = keystore.get_public_key(&starterkit)?;
+        let mut document = Self::for_did_key(&did_key(starterkit.scheme, &identity))?;
+
+        for key_type in key_types {
+            for public in keystore.list_public_keys(key_type) {
+                if key_type.key_type_id() == STARTERKIT_KEY_TYPE && public == identity {
+                    continue;
+                }
+                let id = document.add_key(key_type.scheme, &public);
+                for purpose in &key_type.purposes {
+                    let relationship = match purpose {
+                        KeyPurpose::DocSigning => &mut document.assertion_method,
+                        KeyPurpose::ApiAuth => &mut document.authentication,
+                        KeyPurpose::ChainTx => &mut document.capability_invocation,
+                    };
+                    if !relationship.contains(&id) {
+                        relationship.push(id.clone());
+                    }
+                }
+            }
+        }
+        Ok(document)
+    }
+
+    /// Adds a service with the id `<did>#<fragment>`.
+    pub fn add_service(&mut self, fragment: &str, kind: &str, endpoint: Value) {
+        self.service.push(Service {
+            id: format!("{}#{}", self.id, fragment),
+            kind: kind.to_string(),
+            service_endpoint: endpoint,
+        });
+    }
+
+    fn empty(id: String) -> Self {
+        Self {
+            context: vec![DID_CONTEXT.to_string(), MULTIKEY_CONTEXT.to_string()],
+            id,
+            verification_method: Vec::new(),
+            authentication: Vec::new(),
+            assertion_method: Vec::new(),
+            capability_invocation: Vec::new(),
+            service: Vec::new(),
+        }
+    }
+
+    // adds the key once and returns its verification method id
+    fn add_key(&mut self, scheme: KeyScheme, public: &[u8]) -> String {
+        let multikey = multikey(scheme, public);
+        let id = format!("{}#{}", self.id, multikey);
+        if !self.verification_method.iter().any(|method| method.id == id) {
+            self.verification_method.push(VerificationMethod {
+                id: id.clone(),
+                kind: "Multikey".to_string(),
+                controller: self.id.clone(),
+                public_key_multibase: multikey,
+            });
+        }
+        id
+    }
+}
@@ -1,32 +1,100 @@
 use iroh::SecretKey;
-use sp_core::crypto::{KeyTypeId, SecretString, Pair};
-use sp_core::{blake2_256, ed25519, sr25519};
+use sp_core::crypto::{KeyTypeId, SecretString, Pair, Ss58Codec};
+use sp_core::{blake2_256, ecdsa, ed25519, keccak_256, sr25519};
 use sc_keystore::{Keystore, LocalKeystore};
 use std::{sync::Arc, fmt, path::PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use anyhow::Result;
 use anyhow::anyhow;
+use crate::audit::{AuditEvent, AuditLog, AuditOperation, NODE_CALLER};
+use crate::dek::MasterKey;
+use crate::registry::{KeyPurpose, KeyTypeInfo};
+use serde::{Deserialize, Serialize};
 use tracing::info;
 use subxt::{tx::Signer, config::PolkadotConfig, utils::MultiSignature};
 // use sp_runtime::MultiSignature;
 
 pub const CORD_KEY_TYPE: KeyTypeId = KeyTypeId(*b"cord");
-// why is this not public? 
-const STARTERKIT_KEY_TYPE: KeyTypeId = KeyTypeId(*b"skit");
+pub const STARTERKIT_KEY_TYPE: KeyTypeId = KeyTypeId(*b"skit");
+// extra sr25519 keys generated by operators. The node's own CORD and STARTERKIT keys are
+// looked up as the first key of their type, so new keys never go under those types. The first
+// key of this type signs entries for the node, see `get_doc_signing_public_key`.
+pub const SIGNING_KEY_TYPE: KeyTypeId = KeyTypeId(*b"sign");
+// secp256k1 keys generated by operators, for integrations with EVM-style ecosystems
+pub const ECDSA_KEY_TYPE: KeyTypeId = KeyTypeId(*b"ecds");
+
+/// The signature scheme of the keys stored under a key type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyScheme {
+    Sr25519,
+    Ed25519,
+    /// secp256k1 over the keccak-256 hash of the data, as EVM tooling expects.
+    Ecdsa,
+}
+
+/// The scheme names used by the API.
+pub const KEY_SCHEME_NAMES: [&str; 3] = ["sr25519", "ed25519", "ecdsa"];
+
+impl KeyScheme {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "sr25519" => Ok(Self::Sr25519),
+            "ed25519" => Ok(Self::Ed25519),
+            "ecdsa" => Ok(Self::Ecdsa),
+            _ => Err(anyhow!("❌ Unknown key scheme '{}'. Expected one of: {}.", name, KEY_SCHEME_NAMES.join(", "))),
+        }
+    }
+}
+
+/// Where to find the HSM that holds the node's ed25519 and ecdsa keys.
+#[derive(Debug, Clone)]
+pub struct Pkcs11Config {
+    /// Path of the PKCS#11 module, e.g. `/usr/lib/softhsm/libsofthsm2.so`.
+    pub module: PathBuf,
+    /// Slot of the token. Defaults to the first slot with a token.
+    pub slot: Option<u64>,
+    /// User PIN of the token, if it needs a login.
+    pub pin: Option<String>,
+}
 
-pub trait SecretKeyExt {
+pub trait SecretKeyExt: Sized {
     fn from_seed(seed: [u8; 32]) -> Self;
+    /// Derives the iroh secret key of a node bootstrapped from `suri`, without a keystore. The
+    /// node's whole identity (CORD account, STARTERKIT key and NodeId) follows from the SURI, so
+    /// a node can be recreated from its recovery phrase alone.
+    fn from_suri(suri: &str) -> Result<Self>;
 }
 
 impl SecretKeyExt for SecretKey {
     fn from_seed(seed: [u8; 32]) -> Self {
         SecretKey::from_bytes(&seed)
     }
+
+    fn from_suri(suri: &str) -> Result<Self> {
+        let starterkit_pair = ed25519::Pair::from_string(suri.trim(), None)
+            .map_err(|e| anyhow!("Failed to create ed25519 keypair for StarterKit: {e:?}"))?;
+
+        // ed25519 signatures are deterministic, so this matches what the keystore produces in
+        // `get_starter_kit_seed`
+        let signature = starterkit_pair.sign(STARTERKIT_DERIVATION_PAYLOAD);
+        Ok(secret_key_from_signature(&starterkit_pair.public(), signature.as_ref()))
+    }
 }
 
 #[derive(Clone)]
 pub struct CordKeystoreSigner {
-    pub keystore: Arc<LocalKeystore>, 
+    pub keystore: Arc<dyn Keystore>,
     pub public: sr25519::Public,   // public key of the CORD keypair
+    pub audit: Option<AuditLog>,   // records each transaction signature, see `with_audit`
+}
+
+impl CordKeystoreSigner {
+    /// Records every transaction this signer signs in `audit`, with the node as the caller.
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
 }
 
 impl Signer<PolkadotConfig> for CordKeystoreSigner {
@@ -35,6 +103,21 @@ impl Signer<PolkadotConfig> for CordKeystoreSigner {
     }
 
     fn sign(&self, payload: &[u8]) -> <PolkadotConfig as subxt::Config>::Signature {
+        // subxt gives the signer no way to fail, so a failed record is only logged
+        if let Some(audit) = &self.audit {
+            let event = AuditEvent {
+                operation: AuditOperation::Sign,
+                key_type: "cord".to_string(),
+                key: encode_public(&self.public.0),
+                purpose: Some(KeyPurpose::ChainTx.as_str().to_string()),
+                caller: NODE_CALLER.to_string(),
+                success: true,
+            };
+            if let Err(e) = audit.record(event) {
+                tracing::error!(error = %e, "❌ Failed to record transaction signature in the audit log");
+            }
+        }
+
         let sig = self.keystore
             .sr25519_sign(CORD_KEY_TYPE, &self.public, payload)
             .expect("Failed to sign payload with CORD keypair")
@@ -46,7 +129,8 @@ impl Signer<PolkadotConfig> for CordKeystoreSigner {
 }
 
 pub struct StarterkitKeystore {
-    keystore: Arc<LocalKeystore>,
+    // a `LocalKeystore`, or a `Pkcs11Keystore` wrapping one once a token is attached
+    keystore: Arc<dyn Keystore>,
 }
 
 impl fmt::Debug for StarterkitKeystore {
@@ -73,9 +157,26 @@ impl StarterkitKeystore {
         })
     }
 
+    // a keystore kept in memory only and lost when dropped, holding the keypairs of `suri` as
+    // `initialize_keystore` would (an in-memory keystore ignores `insert`, so they are derived
+    // straight into it)
+    // WHEN TO CALL: for throwaway nodes, see `node::iroh_wrapper::MemoryNode`
+    pub fn in_memory(suri: &str) -> Result<Self> {
+        let keystore = LocalKeystore::in_memory();
+        keystore
+            .sr25519_generate_new(CORD_KEY_TYPE, Some(suri.trim()))
+            .map_err(|e| anyhow!("Failed to create sr25519 keypair for CORD: {e:?}"))?;
+        keystore
+            .ed25519_generate_new(STARTERKIT_KEY_TYPE, Some(suri.trim()))
+            .map_err(|e| anyhow!("Failed to create ed25519 keypair for StarterKit: {e:?}"))?;
+        Ok(Self {
+            keystore: Arc::new(keystore),
+        })
+    }
+
     // get the inner keystore from struct
     // WHEN TO CALL: when you need the inner keystore. eg. to sign tx
-    pub fn inner(&self) -> Arc<LocalKeystore> {
+    pub fn inner(&self) -> Arc<dyn Keystore> {
         Arc::clone(&self.keystore)
     }
 
@@ -221,9 +322,6 @@ impl StarterkitKeystore {
     // We will use the private key to sign a payload and then use the public key and the 
     // signature(of signing the payload) to generate a seed for cyra.
     pub fn get_starter_kit_seed(&self, starter_kit_public: ed25519::Public) -> Result<SecretKey> {
-        // NOTE: this is the message that will be signed using the private key.
-        const PAYLOAD: &[u8] = b"starter_kit_key_derivation";
-
         // NOTE: this line is used to sign the payload with the CYRA keypair and generate a 
         //       ed25519 signature. The CYRA_KEY_TYPE and public key associated to cyra is 
         //       used to find the private key and then to sign the message.
@@ -237,7 +335,7 @@ impl StarterkitKeystore {
         //       IT SHOULD BE NOTED THAT THE PRIVATE KEY IS NOT STORED IN THE KEYSORE.
         let signature = self
             .keystore
-            .ed25519_sign(STARTERKIT_KEY_TYPE, &starter_kit_public, PAYLOAD)
+            .ed25519_sign(STARTERKIT_KEY_TYPE, &starter_kit_public, STARTERKIT_DERIVATION_PAYLOAD)
             .map_err(|e| {
                 anyhow!("❌ Failed to sign payload with STARTERKIT keypair: {e:?}")
             })?
@@ -245,24 +343,669 @@ impl StarterkitKeystore {
                 anyhow!("❌ Failed to sign payload with STARTERKIT keypair. No private key found.")
             })?;
         
-        let mut combined_data = Vec::new();
-        // Append the public key bytes to the combined data
-        combined_data.extend_from_slice(starter_kit_public.as_ref());
-        // Append the signature bytes to the combined data
-        combined_data.extend_from_slice(&signature.as_ref());
+        let secret_key = secret_key_from_signature(&starter_kit_public, signature.as_ref());
 
-        // Generate a seed from the combined data using blake3 hash function. 
-        let seed = blake2_256(&combined_data);
+        Ok(secret_key)
+    }
 
-        // Convert the seed into a SecretKey
-        let secret_key = SecretKey::from_seed(seed);
+    // the master key that wraps the data encryption keys of `dek::DekManager`. Like the iroh
+    // secret key it is derived from a STARTERKIT signature, so it never touches the disk and is
+    // recoverable from the SURI.
+    pub fn get_master_key(&self) -> Result<MasterKey> {
+        let starterkit_public = self.get_starterkit_public_key()?;
+        let signature = self
+            .keystore
+            .ed25519_sign(STARTERKIT_KEY_TYPE, &starterkit_public, MASTER_KEY_DERIVATION_PAYLOAD)
+            .map_err(|e| anyhow!("❌ Failed to sign payload with STARTERKIT keypair: {e:?}"))?
+            .ok_or_else(|| anyhow!("❌ Failed to sign payload with STARTERKIT keypair. No private key found."))?;
 
-        Ok(secret_key)
+        Ok(MasterKey::from_bytes(blake2_256(signature.as_ref())))
+    }
+
+    // the key that signs document entries for the node: the first key under SIGNING_KEY_TYPE,
+    // generated if there is none yet. It is kept apart from the CORD key, so nothing signed for
+    // a document can ever pass for a chain transaction.
+    pub fn get_doc_signing_public_key(&self) -> Result<sr25519::Public> {
+        if let Some(public) = self.keystore.sr25519_public_keys(SIGNING_KEY_TYPE).first() {
+            return Ok(*public);
+        }
+        self.keystore
+            .sr25519_generate_new(SIGNING_KEY_TYPE, None)
+            .map_err(|e| anyhow!("❌ Failed to generate the document signing key: {e:?}"))
     }
 
     pub fn get_cord_signer(&self) -> Result<CordKeystoreSigner> {
         let keystore = self.inner();
         let public = self.get_cord_public_key()?;
-        Ok(CordKeystoreSigner { keystore, public })
+        Ok(CordKeystoreSigner { keystore, public, audit: None })
+    }
+
+    // wrap a keystore that is already open, e.g. the one held by a `CordKeystoreSigner`
+    pub fn from_inner(keystore: Arc<dyn Keystore>) -> Self {
+        Self { keystore }
+    }
+
+    // route ed25519 and ecdsa keys through a PKCS#11 token, keeping the current keystore for
+    // everything the token does not hold (always the sr25519 CORD key)
+    // WHEN TO CALL: right after `open`, when the node is configured with a PKCS#11 module
+    #[cfg(feature = "pkcs11")]
+    pub fn attach_hsm(&mut self, config: &Pkcs11Config) -> Result<()> {
+        let hsm = crate::pkcs11::Pkcs11Keystore::open(config, self.inner())?;
+        self.keystore = Arc::new(hsm);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "pkcs11"))]
+    pub fn attach_hsm(&mut self, _config: &Pkcs11Config) -> Result<()> {
+        Err(anyhow!("❌ A PKCS#11 module is configured, but this binary was built without the `pkcs11` feature."))
+    }
+
+    // get the public key for `key_type`, i.e. the node's CORD or STARTERKIT key
+    pub fn get_public_key(&self, key_type: &KeyTypeInfo) -> Result<Vec<u8>> {
+        match key_type.key_type_id() {
+            CORD_KEY_TYPE => Ok(self.get_cord_public_key()?.0.to_vec()),
+            STARTERKIT_KEY_TYPE => Ok(self.get_starterkit_public_key()?.0.to_vec()),
+            _ => self
+                .list_public_keys(key_type)
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow!("❌ No key of type '{}' found in keystore", key_type.name)),
+        }
+    }
+
+    // list the public keys stored under `key_type`. ecdsa keys are 33 bytes (compressed), the
+    // others 32.
+    pub fn list_public_keys(&self, key_type: &KeyTypeInfo) -> Vec<Vec<u8>> {
+        let id = key_type.key_type_id();
+        match key_type.scheme {
+            KeyScheme::Sr25519 => self.keystore.sr25519_public_keys(id).into_iter().map(|public| public.0.to_vec()).collect(),
+            KeyScheme::Ed25519 => self.keystore.ed25519_public_keys(id).into_iter().map(|public| public.0.to_vec()).collect(),
+            KeyScheme::Ecdsa => self.keystore.ecdsa_public_keys(id).into_iter().map(|public| public.0.to_vec()).collect(),
+        }
+    }
+
+    // check whether the private key of `public` is stored under `key_type`
+    pub fn has_key(&self, key_type: &KeyTypeInfo, public: &[u8]) -> bool {
+        self.keystore.has_keys(&[(public.to_vec(), key_type.key_type_id())])
+    }
+
+    // generate a new key of `key_type` from a fresh phrase and return its public key. With a
+    // derivation path such as `//payments//1`, the stored SURI is the phrase followed by the
+    // path; ecdsa only supports hard (`//`) junctions. Like `initialize_keystore`, the keypair
+    // is derived without the keystore secret.
+    // WHEN TO CALL: when an operator needs another signing key, e.g. to rotate one.
+    pub fn generate_key(&self, key_type: &KeyTypeInfo, derivation_path: Option<&str>) -> Result<Vec<u8>> {
+        if !key_type.is_generatable() {
+            return Err(anyhow!(
+                "❌ Keys cannot be generated under '{}'. The node's own keys are created by `init`.",
+                key_type.name
+            ));
+        }
+        let id = key_type.key_type_id();
+        let Some(path) = derivation_path else {
+            let public = match key_type.scheme {
+                KeyScheme::Sr25519 => self.keystore.sr25519_generate_new(id, None).map(|public| public.0.to_vec()),
+                KeyScheme::Ed25519 => self.keystore.ed25519_generate_new(id, None).map(|public| public.0.to_vec()),
+                KeyScheme::Ecdsa => self.keystore.ecdsa_generate_new(id, None).map(|public| public.0.to_vec()),
+            };
+            return public.map_err(|e| anyhow!("❌ Failed to generate key: {e:?}"));
+        };
+
+        if !path.starts_with('/') {
+            return Err(anyhow!("❌ A derivation path must start with '/' or '//', e.g. //payments//1"));
+        }
+        let (_, phrase, _) = sr25519::Pair::generate_with_phrase(None);
+        let suri = format!("{}{}", phrase, path);
+        let public = match key_type.scheme {
+            KeyScheme::Sr25519 => sr25519::Pair::from_string(&suri, None).map(|pair| pair.public().0.to_vec()),
+            KeyScheme::Ed25519 => ed25519::Pair::from_string(&suri, None).map(|pair| pair.public().0.to_vec()),
+            KeyScheme::Ecdsa => ecdsa::Pair::from_string(&suri, None).map(|pair| pair.public().0.to_vec()),
+        }
+        .map_err(|e| anyhow!("❌ Invalid derivation path {}: {e:?}", path))?;
+        self.keystore
+            .insert(id, &suri, &public)
+            .map_err(|e| anyhow!("❌ Failed to insert key into keystore: {e:?}"))?;
+
+        Ok(public)
+    }
+
+    // sign `data` with the private key of `public`, if `key_type` is registered for `purpose`.
+    // What is signed is `signed_message(data)`, never `data` itself, so no caller can get a
+    // signature over a derivation payload or a chain transaction out of this. Keys sign with
+    // their type's scheme, ecdsa ones with secp256k1 over keccak_256 of the message, so the
+    // signature can be checked with the public key alone. ecdsa signatures are 65 bytes
+    // (r || s || v), the format `ecrecover` expects.
+    // WHEN TO CALL: when data has to be verifiable outside iroh, e.g. entry values.
+    pub fn sign_payload(&self, key_type: &KeyTypeInfo, purpose: KeyPurpose, public: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        self.sign_payload_as(key_type, purpose, public, data, MessageFormat::Raw)
+    }
+
+    // like `sign_payload`, with `signed_message(data)` put in `format` before it is signed
+    pub fn sign_payload_as(
+        &self,
+        key_type: &KeyTypeInfo,
+        purpose: KeyPurpose,
+        public: &[u8],
+        data: &[u8],
+        format: MessageFormat,
+    ) -> Result<Vec<u8>> {
+        key_type.ensure_purpose(purpose)?;
+        self.sign_message(key_type, public, &format.apply(&signed_message(data)))
+    }
+
+    // sign the hash data of a Data Integrity proof as is, as `eddsa-jcs-2022` requires: two
+    // sha-256 digests the node computed itself. No `chain-tx` key signs one, so it cannot stand
+    // in for a transaction, and 64 bytes never match a derivation payload.
+    pub fn sign_proof_hash(&self, key_type: &KeyTypeInfo, public: &[u8], hash: &[u8; 64]) -> Result<Vec<u8>> {
+        key_type.ensure_purpose(KeyPurpose::DocSigning)?;
+        if key_type.purposes.contains(&KeyPurpose::ChainTx) {
+            return Err(anyhow!("❌ Key type '{}' signs chain transactions and cannot sign credentials.", key_type.name));
+        }
+        self.sign_message(key_type, public, hash)
+    }
+
+    // sign `message` exactly as given. Only for messages the node builds itself, see
+    // `sign_payload`.
+    fn sign_message(&self, key_type: &KeyTypeInfo, public: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+        let id = key_type.key_type_id();
+        let signature = match key_type.scheme {
+            KeyScheme::Sr25519 => {
+                let public = sr25519::Public::try_from(public)
+                    .map_err(|_| anyhow!("❌ An sr25519 public key must be 32 bytes"))?;
+                self.keystore
+                    .sr25519_sign(id, &public, message)
+                    .map_err(|e| anyhow!("❌ Failed to sign payload with sr25519 keypair: {e:?}"))?
+                    .map(|signature| signature.0.to_vec())
+            }
+            KeyScheme::Ed25519 => {
+                let public = ed25519::Public::try_from(public)
+                    .map_err(|_| anyhow!("❌ An ed25519 public key must be 32 bytes"))?;
+                self.keystore
+                    .ed25519_sign(id, &public, message)
+                    .map_err(|e| anyhow!("❌ Failed to sign payload with ed25519 keypair: {e:?}"))?
+                    .map(|signature| signature.0.to_vec())
+            }
+            KeyScheme::Ecdsa => {
+                let public = ecdsa::Public::try_from(public)
+                    .map_err(|_| anyhow!("❌ An ecdsa public key must be 33 bytes (compressed)"))?;
+                self.keystore
+                    .ecdsa_sign_prehashed(id, &public, &keccak_256(message))
+                    .map_err(|e| anyhow!("❌ Failed to sign payload with ecdsa keypair: {e:?}"))?
+                    .map(|signature| signature.0.to_vec())
+            }
+        };
+
+        signature.ok_or_else(|| anyhow!("❌ No private key for this public key found in the keystore."))
     }
-}
\ No newline at end of file
+
+    // check a signature made by `sign_payload`. Only the public key is needed, so this works
+    // for signatures from other nodes too.
+    pub fn verify_payload(key_type: &KeyTypeInfo, public: &[u8], data: &[u8], signature: &[u8]) -> Result<bool> {
+        Self::verify_message(key_type, public, &signed_message(data), signature)
+    }
+
+    // check a signature over `message` exactly as given, e.g. one a wallet made
+    pub fn verify_message(key_type: &KeyTypeInfo, public: &[u8], message: &[u8], signature: &[u8]) -> Result<bool> {
+        match key_type.scheme {
+            KeyScheme::Sr25519 => {
+                let public = sr25519::Public::try_from(public)
+                    .map_err(|_| anyhow!("❌ An sr25519 public key must be 32 bytes"))?;
+                let Ok(signature) = sr25519::Signature::try_from(signature) else { return Ok(false) };
+                Ok(sr25519::Pair::verify(&signature, message, &public))
+            }
+            KeyScheme::Ed25519 => {
+                let public = ed25519::Public::try_from(public)
+                    .map_err(|_| anyhow!("❌ An ed25519 public key must be 32 bytes"))?;
+                let Ok(signature) = ed25519::Signature::try_from(signature) else { return Ok(false) };
+                Ok(ed25519::Pair::verify(&signature, message, &public))
+            }
+            KeyScheme::Ecdsa => {
+                let public = ecdsa::Public::try_from(public)
+                    .map_err(|_| anyhow!("❌ An ecdsa public key must be 33 bytes (compressed)"))?;
+                let Ok(signature) = ecdsa::Signature::try_from(signature) else { return Ok(false) };
+                Ok(ecdsa::Pair::verify_prehashed(&signature, &keccak_256(message), &public))
+            }
+        }
+    }
+
+    // sign the blake2-256 hash of `data` together with the current time, so the signature also
+    // vouches for when the node saw the data. Returns the hash, the timestamp (unix seconds) and
+    // the signature over `timestamped_message`.
+    pub fn sign_timestamped(
+        &self,
+        key_type: &KeyTypeInfo,
+        purpose: KeyPurpose,
+        public: &[u8],
+        data: &[u8],
+    ) -> Result<([u8; 32], u64, Vec<u8>)> {
+        key_type.ensure_purpose(purpose)?;
+        let hash = blake2_256(data);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let signature = self.sign_message(key_type, public, &timestamped_message(&hash, timestamp))?;
+        Ok((hash, timestamp, signature))
+    }
+
+    // derive the secret key of an iroh-docs author from a keystore key, so the author can be
+    // recreated from the keystore at any time, like the node key. The key must be allowed to sign
+    // documents and its signatures must be deterministic, which rules out sr25519. The seed is a
+    // KDF over a signature of AUTHOR_DERIVATION_PAYLOAD made by `sign_message`, which no signing
+    // API reaches: they only sign prefixed hashes, see `signed_message`.
+    pub fn derive_author_seed(&self, key_type: &KeyTypeInfo, public: &[u8]) -> Result<[u8; 32]> {
+        if key_type.scheme == KeyScheme::Sr25519 {
+            return Err(anyhow!(
+                "❌ Key type '{}' is sr25519, whose signatures are randomized. Use an ed25519 or ecdsa key.",
+                key_type.name
+            ));
+        }
+        key_type.ensure_purpose(KeyPurpose::DocSigning)?;
+        let signature = self.sign_message(key_type, public, AUTHOR_DERIVATION_PAYLOAD)?;
+        // a token may sign ecdsa with random nonces, which would give a different author each time
+        if self.sign_message(key_type, public, AUTHOR_DERIVATION_PAYLOAD)? != signature {
+            return Err(anyhow!("❌ This key does not sign deterministically, so no author can be derived from it."));
+        }
+        Ok(blake2_256(&[AUTHOR_SEED_CONTEXT, public, &signature].concat()))
+    }
+
+    // check a signature made by `sign_timestamped` over `data` at `timestamp`
+    pub fn verify_timestamped(key_type: &KeyTypeInfo, public: &[u8], data: &[u8], timestamp: u64, signature: &[u8]) -> Result<bool> {
+        Self::verify_message(key_type, public, &timestamped_message(&blake2_256(data), timestamp), signature)
+    }
+
+    // check `signature` against `data` in each of `formats` in turn, returning the first format
+    // it is valid in, if any. A signature is valid in a format if it was made over the format
+    // applied to `signed_message(data)`, as `sign_payload_as` signs, or to `data` itself, as
+    // wallets sign. Lets one endpoint accept both node and wallet signatures.
+    pub fn verify_payload_formats(
+        key_type: &KeyTypeInfo,
+        public: &[u8],
+        data: &[u8],
+        signature: &[u8],
+        formats: &[MessageFormat],
+    ) -> Result<Option<MessageFormat>> {
+        for format in formats {
+            if Self::verify_message(key_type, public, &format.apply(&signed_message(data)), signature)?
+                || Self::verify_message(key_type, public, &format.apply(data), signature)?
+            {
+                return Ok(Some(*format));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Starts every message the keystore signs for a caller, so those signatures can never be
+/// replayed as the node's internal derivation signatures or as chain transactions.
+pub const SIGNED_MESSAGE_PREFIX: &[u8] = b"<starter-kit-signed-message>";
+
+/// The message signed by `StarterkitKeystore::sign_payload`: `SIGNED_MESSAGE_PREFIX` followed by
+/// the 32-byte blake2-256 hash of the data.
+pub fn signed_message(data: &[u8]) -> Vec<u8> {
+    [SIGNED_MESSAGE_PREFIX, &blake2_256(data)].concat()
+}
+
+/// The message signed by `StarterkitKeystore::sign_timestamped`: `SIGNED_MESSAGE_PREFIX`, the
+/// 32-byte blake2-256 hash of the data and the timestamp as 8 big-endian bytes.
+pub fn timestamped_message(hash: &[u8; 32], timestamp: u64) -> Vec<u8> {
+    [SIGNED_MESSAGE_PREFIX, hash, &timestamp.to_be_bytes()].concat()
+}
+
+/// How a payload is turned into the bytes that are signed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageFormat {
+    /// The payload as is.
+    Raw,
+    /// The payload wrapped in `<Bytes>...</Bytes>`, as polkadot.js and Nova wallets sign raw
+    /// messages so they can never be mistaken for a transaction.
+    Bytes,
+}
+
+/// The format names used by the API.
+pub const MESSAGE_FORMAT_NAMES: [&str; 2] = ["raw", "bytes"];
+
+impl MessageFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "raw" => Ok(Self::Raw),
+            "bytes" => Ok(Self::Bytes),
+            _ => Err(anyhow!("❌ Unknown message format '{}'. Expected one of: {}.", name, MESSAGE_FORMAT_NAMES.join(", "))),
+        }
+    }
+
+    /// The bytes signed for `data` in this format.
+    pub fn apply(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Raw => data.to_vec(),
+            Self::Bytes => wrap_bytes(data),
+        }
+    }
+}
+
+const BYTES_PREFIX: &[u8] = b"<Bytes>";
+const BYTES_SUFFIX: &[u8] = b"</Bytes>";
+
+/// Wraps `data` in `<Bytes>...</Bytes>` like polkadot.js `u8aWrapBytes`, leaving data that is
+/// already wrapped unchanged.
+pub fn wrap_bytes(data: &[u8]) -> Vec<u8> {
+    if data.starts_with(BYTES_PREFIX) && data.ends_with(BYTES_SUFFIX) {
+        return data.to_vec();
+    }
+    [BYTES_PREFIX, data, BYTES_SUFFIX].concat()
+}
+
+/// Encodes a public key of any key type as SS58: 32 bytes for sr25519 and ed25519, 33 for ecdsa.
+pub fn encode_public(public: &[u8]) -> String {
+    match ecdsa::Public::try_from(public) {
+        Ok(public) => public.to_ss58check(),
+        Err(_) => sr25519::Public::try_from(public)
+            .map(|public| public.to_ss58check())
+            .unwrap_or_else(|_| format!("0x{}", sp_core::hexdisplay::HexDisplay::from(&public))),
+    }
+}
+
+/// Decodes a public key given as SS58 or as 0x-prefixed hex.
+pub fn decode_public(public: &str) -> Result<Vec<u8>> {
+    if public.starts_with("0x") {
+        let bytes = sp_core::bytes::from_hex(public).map_err(|e| anyhow!("❌ Invalid hex public key: {e}"))?;
+        if bytes.len() != 32 && bytes.len() != 33 {
+            return Err(anyhow!("❌ A public key must be 32 bytes, or 33 for ecdsa"));
+        }
+        return Ok(bytes);
+    }
+    sr25519::Public::from_ss58check(public)
+        .map(|public| public.0.to_vec())
+        .or_else(|_| ecdsa::Public::from_ss58check(public).map(|public| public.0.to_vec()))
+        .map_err(|e| anyhow!("❌ Invalid SS58 public key: {e:?}"))
+}
+
+// Payload signed with the STARTERKIT keypair to derive the iroh secret key.
+const STARTERKIT_DERIVATION_PAYLOAD: &[u8] = b"starter_kit_key_derivation";
+// Payload signed with the STARTERKIT keypair to derive the DEK master key.
+const MASTER_KEY_DERIVATION_PAYLOAD: &[u8] = b"starter_kit_master_key_derivation";
+// signed by a key to derive the author seed in `derive_author_seed`
+const AUTHOR_DERIVATION_PAYLOAD: &[u8] = b"starter_kit_author_derivation";
+// hashed with the key and that signature into the author seed
+const AUTHOR_SEED_CONTEXT: &[u8] = b"starter-kit-author-seed";
+
+// The iroh secret key is blake2_256(public key || signature over STARTERKIT_DERIVATION_PAYLOAD).
+fn secret_key_from_signature(starter_kit_public: &ed25519::Public, signature: &[u8]) -> SecretKey {
+    let mut combined_data = Vec::new();
+    // Append the public key bytes to the combined data
+    combined_data.extend_from_slice(starter_kit_public.as_ref());
+    // Append the signature bytes to the combined data
+    combined_data.extend_from_slice(signature);
+
+    // Generate a seed from the combined data using blake2 hash function.
+    let seed = blake2_256(&combined_data);
+
+    // Convert the seed into a SecretKey
+    SecretKey::from_seed(seed)
+}
+
+/// The keys a node bootstrapped from a SURI will use.
+pub struct DerivedKeys {
+    /// Public key of the CORD (sr25519) keypair.
+    pub cord_public: sr25519::Public,
+    /// Public key of the STARTERKIT (ed25519) keypair.
+    pub starterkit_public: ed25519::Public,
+    /// The iroh secret key, whose public key is the node ID.
+    pub secret_key: SecretKey,
+}
+
+/// Derives the keys for `suri` the same way bootstrapping does, without touching a keystore.
+pub fn derive_keys(suri: &str) -> Result<DerivedKeys> {
+    let cord_pair = sr25519::Pair::from_string(suri.trim(), None)
+        .map_err(|e| anyhow!("Failed to create sr25519 keypair for CORD: {e:?}"))?;
+    let starterkit_pair = ed25519::Pair::from_string(suri.trim(), None)
+        .map_err(|e| anyhow!("Failed to create ed25519 keypair for StarterKit: {e:?}"))?;
+
+    Ok(DerivedKeys {
+        cord_public: cord_pair.public(),
+        starterkit_public: starterkit_pair.public(),
+        secret_key: SecretKey::from_suri(suri)?,
+    })
+}
+
+/// Generates a new random 12-word mnemonic phrase to use as a SURI.
+pub fn generate_suri() -> String {
+    let (_, phrase, _) = sr25519::Pair::generate_with_phrase(None);
+    phrase
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::builtin_key_type;
+
+    const SURI: &str = "//Alice";
+
+    #[test]
+    fn sign_payload_never_signs_a_derivation_payload() {
+        let keystore = StarterkitKeystore::in_memory(SURI).unwrap();
+        let skit = builtin_key_type(STARTERKIT_KEY_TYPE).unwrap();
+        let public = keystore.get_starterkit_public_key().unwrap();
+
+        let signature = keystore
+            .sign_payload(&skit, KeyPurpose::DocSigning, public.as_ref(), STARTERKIT_DERIVATION_PAYLOAD)
+            .unwrap();
+        let derivation = keystore.sign_message(&skit, public.as_ref(), STARTERKIT_DERIVATION_PAYLOAD).unwrap();
+        assert_ne!(signature, derivation);
+        assert!(StarterkitKeystore::verify_payload(&skit, public.as_ref(), STARTERKIT_DERIVATION_PAYLOAD, &signature).unwrap());
+        assert!(!StarterkitKeystore::verify_message(&skit, public.as_ref(), STARTERKIT_DERIVATION_PAYLOAD, &signature).unwrap());
+    }
+
+    #[test]
+    fn node_and_chain_keys_cannot_sign_for_callers() {
+        assert!(builtin_key_type(STARTERKIT_KEY_TYPE).unwrap().ensure_api_signable().is_err());
+        assert!(builtin_key_type(CORD_KEY_TYPE).unwrap().ensure_api_signable().is_err());
+        assert!(builtin_key_type(SIGNING_KEY_TYPE).unwrap().ensure_api_signable().is_ok());
+        assert!(builtin_key_type(ECDSA_KEY_TYPE).unwrap().ensure_api_signable().is_ok());
+    }
+
+    #[test]
+    fn documents_are_signed_with_their_own_key() {
+        let keystore = StarterkitKeystore::in_memory(SURI).unwrap();
+        let public = keystore.get_doc_signing_public_key().unwrap();
+        assert_eq!(keystore.get_doc_signing_public_key().unwrap(), public);
+        assert_ne!(public, keystore.get_cord_public_key().unwrap());
+        assert!(builtin_key_type(CORD_KEY_TYPE).unwrap().ensure_purpose(KeyPurpose::DocSigning).is_err());
+    }
+
+    #[test]
+    fn author_seed_is_out_of_reach_of_the_signing_apis() {
+        let keystore = StarterkitKeystore::in_memory(SURI).unwrap();
+        let skit = builtin_key_type(STARTERKIT_KEY_TYPE).unwrap();
+        let public = keystore.get_starterkit_public_key().unwrap();
+
+        let seed = keystore.derive_author_seed(&skit, public.as_ref()).unwrap();
+        assert_eq!(keystore.derive_author_seed(&skit, public.as_ref()).unwrap(), seed);
+
+        // what a caller could get signed over the derivation payload gives another seed
+        let signature = keystore
+            .sign_payload(&skit, KeyPurpose::DocSigning, public.as_ref(), AUTHOR_DERIVATION_PAYLOAD)
+            .unwrap();
+        assert_ne!(blake2_256(&[AUTHOR_SEED_CONTEXT, public.as_ref(), &signature].concat()), seed);
+        assert_ne!(blake2_256(&signature), seed);
+
+        let sign = builtin_key_type(SIGNING_KEY_TYPE).unwrap();
+        let public = keystore.get_doc_signing_public_key().unwrap();
+        assert!(keystore.derive_author_seed(&sign, public.as_ref()).is_err());
+    }
+
+    // Made by //Alice over `<Bytes>Sign in to the starter kit</Bytes>`, as polkadot.js `signRaw`
+    // signs a message. Ed25519 signatures are deterministic, so polkadot.js gives the same one.
+    const WALLET_MESSAGE: &[u8] = b"Sign in to the starter kit";
+    const ALICE_ED25519: &str = "88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee";
+    const ALICE_SR25519: &str = "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+    const ALICE_ED25519_BYTES_SIGNATURE: &str = "d8a0ba84d0dbbd1dbed893587f0391a8351c7db4528f14e7eaaf89caf5c64577d4c0d1ae89ed259374138a691eeb1f0afac706867298226f34d39c5aa945110c";
+    const ALICE_SR25519_BYTES_SIGNATURE: &str = "f2ad09fa59d62f28e311bdcf2265837fdfee7c6df45cdbb135ab4844cd5ae27c55b3dbaeeddc5420d7426b2032d9d4ae6cf3d8565b29c404e69535348c54df82";
+
+    #[test]
+    fn wallet_signatures_verify_in_the_bytes_format() {
+        let vectors = [
+            (STARTERKIT_KEY_TYPE, ALICE_ED25519, ALICE_ED25519_BYTES_SIGNATURE),
+            (SIGNING_KEY_TYPE, ALICE_SR25519, ALICE_SR25519_BYTES_SIGNATURE),
+        ];
+        for (key_type, public, signature) in vectors {
+            let key_type = builtin_key_type(key_type).unwrap();
+            let public = hex::decode(public).unwrap();
+            let signature = hex::decode(signature).unwrap();
+
+            let verify = |formats: &[MessageFormat]| {
+                StarterkitKeystore::verify_payload_formats(&key_type, &public, WALLET_MESSAGE, &signature, formats).unwrap()
+            };
+            assert_eq!(verify(&[MessageFormat::Bytes]), Some(MessageFormat::Bytes));
+            assert_eq!(verify(&[MessageFormat::Raw, MessageFormat::Bytes]), Some(MessageFormat::Bytes));
+            assert_eq!(verify(&[MessageFormat::Raw]), None);
+
+            // wallets send the message wrapped or not, it is only wrapped once
+            let wrapped = wrap_bytes(WALLET_MESSAGE);
+            assert_eq!(wrap_bytes(&wrapped), wrapped);
+            let verified = StarterkitKeystore::verify_payload_formats(&key_type, &public, &wrapped, &signature, &[MessageFormat::Bytes]);
+            assert_eq!(verified.unwrap(), Some(MessageFormat::Bytes));
+        }
+        assert_eq!(hex::encode(ed25519::Pair::from_string(SURI, None).unwrap().public()), ALICE_ED25519);
+        assert_eq!(hex::encode(sr25519::Pair::from_string(SURI, None).unwrap().public()), ALICE_SR25519);
+    }
+
+    #[test]
+    fn raw_message_signatures_are_rejected_in_the_bytes_format() {
+        let skit = builtin_key_type(STARTERKIT_KEY_TYPE).unwrap();
+        let pair = ed25519::Pair::from_string(SURI, None).unwrap();
+        let raw_signature = pair.sign(WALLET_MESSAGE);
+
+        let verify = |formats: &[MessageFormat]| {
+            StarterkitKeystore::verify_payload_formats(&skit, pair.public().as_ref(), WALLET_MESSAGE, raw_signature.as_ref(), formats).unwrap()
+        };
+        assert_eq!(verify(&[MessageFormat::Bytes]), None);
+        assert_eq!(verify(&[MessageFormat::Raw]), Some(MessageFormat::Raw));
+        assert_eq!(verify(&[MessageFormat::Bytes, MessageFormat::Raw]), Some(MessageFormat::Raw));
+        // nor does a wallet signature over another message verify
+        let other = hex::decode(ALICE_ED25519_BYTES_SIGNATURE).unwrap();
+        let verified = StarterkitKeystore::verify_payload_formats(&skit, pair.public().as_ref(), b"another message", &other, &[MessageFormat::Bytes]);
+        assert_eq!(verified.unwrap(), None);
+    }
+
+    // The ecdsa key of //Alice, as printed by `subkey inspect --scheme ecdsa //Alice`
+    const ALICE_ECDSA: &str = "020a1091341fe5664bfa1782d5e04779689068c916b04cb365ec3153755684d9a1";
+    const ALICE_ECDSA_SS58: &str = "KW39r9CJjAVzmkf9zQ4YDb2hqfAVGdRqn53eRqyruqpxAP5YL";
+
+    #[test]
+    fn ecdsa_signatures_verify_and_recover_the_signer() {
+        let keystore = StarterkitKeystore::in_memory(SURI).unwrap();
+        let ecdsa_type = builtin_key_type(ECDSA_KEY_TYPE).unwrap();
+        let public = keystore.generate_key(&ecdsa_type, None).unwrap();
+        assert_eq!(public.len(), 33);
+        assert!(keystore.has_key(&ecdsa_type, &public));
+        assert_eq!(keystore.list_public_keys(&ecdsa_type), vec![public.clone()]);
+
+        let signature = keystore.sign_payload(&ecdsa_type, KeyPurpose::DocSigning, &public, b"payload").unwrap();
+        assert_eq!(signature.len(), 65);
+        assert!(StarterkitKeystore::verify_payload(&ecdsa_type, &public, b"payload", &signature).unwrap());
+        assert!(!StarterkitKeystore::verify_payload(&ecdsa_type, &public, b"other payload", &signature).unwrap());
+        assert!(!StarterkitKeystore::verify_payload(&ecdsa_type, &public, b"payload", &signature[..64]).unwrap());
+        let other = keystore.generate_key(&ecdsa_type, None).unwrap();
+        assert!(!StarterkitKeystore::verify_payload(&ecdsa_type, &other, b"payload", &signature).unwrap());
+
+        // r || s || v over keccak_256 of the message, so `ecrecover` finds the signer
+        let signature = ecdsa::Signature::try_from(signature.as_slice()).unwrap();
+        let recovered = signature.recover_prehashed(&keccak_256(&signed_message(b"payload"))).unwrap();
+        assert_eq!(recovered.0.to_vec(), public);
+    }
+
+    #[test]
+    fn ecdsa_public_keys_are_encoded_compressed() {
+        let alice = ecdsa::Pair::from_string(SURI, None).unwrap().public();
+        assert_eq!(hex::encode(alice.0), ALICE_ECDSA);
+        assert_eq!(encode_public(&alice.0), ALICE_ECDSA_SS58);
+        assert_eq!(decode_public(ALICE_ECDSA_SS58).unwrap(), alice.0.to_vec());
+        assert_eq!(decode_public(&format!("0x{}", ALICE_ECDSA)).unwrap(), alice.0.to_vec());
+        assert!(decode_public("0x0a1091341fe5664bfa1782d5e04779689068c916b04cb365ec3153755684d9").is_err());
+
+        // 32-byte keys are still sr25519 or ed25519
+        let sr25519 = sr25519::Pair::from_string(SURI, None).unwrap().public();
+        assert_eq!(decode_public(&encode_public(&sr25519.0)).unwrap(), sr25519.0.to_vec());
+    }
+
+    #[test]
+    fn keys_are_dispatched_by_their_type() {
+        let keystore = StarterkitKeystore::in_memory(SURI).unwrap();
+        let ecdsa_type = builtin_key_type(ECDSA_KEY_TYPE).unwrap();
+        let skit = builtin_key_type(STARTERKIT_KEY_TYPE).unwrap();
+        let ecdsa_public = keystore.generate_key(&ecdsa_type, Some("//payments")).unwrap();
+        let ed25519_public = keystore.get_starterkit_public_key().unwrap();
+
+        // a key is only found under its own type
+        assert!(keystore.list_public_keys(&skit).iter().all(|public| public.len() == 32));
+        assert!(!keystore.has_key(&skit, &ecdsa_public));
+
+        // and signs and verifies with its type's scheme
+        let err = keystore.sign_payload(&ecdsa_type, KeyPurpose::DocSigning, ed25519_public.as_ref(), b"payload").unwrap_err();
+        assert!(err.to_string().contains("33 bytes"), "{}", err);
+        assert!(keystore.sign_payload(&skit, KeyPurpose::DocSigning, &ecdsa_public, b"payload").is_err());
+        let ed25519_signature = keystore.sign_payload(&skit, KeyPurpose::DocSigning, ed25519_public.as_ref(), b"payload").unwrap();
+        assert!(StarterkitKeystore::verify_payload(&skit, ed25519_public.as_ref(), b"payload", &ed25519_signature).unwrap());
+        assert!(StarterkitKeystore::verify_payload(&ecdsa_type, ed25519_public.as_ref(), b"payload", &ed25519_signature).is_err());
+        assert!(!StarterkitKeystore::verify_payload(&ecdsa_type, &ecdsa_public, b"payload", &ed25519_signature).unwrap());
+        assert_eq!(KeyScheme::parse("ecdsa").unwrap(), KeyScheme::Ecdsa);
+    }
+
+    // `subkey inspect` outputs for the development phrase, which a SURI without a phrase uses
+    const DEV_PHRASE: &str = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+    const ALICE_STASH_SR25519: &str = "be5ddb1579b72e84524fc29e78609e3caf42e85aa118ebfe0b0ad404b5bdd25f";
+
+    #[test]
+    fn hard_derivation_matches_subkey() {
+        let alice = derive_keys(SURI).unwrap();
+        assert_eq!(hex::encode(alice.cord_public), ALICE_SR25519);
+        assert_eq!(hex::encode(alice.starterkit_public), ALICE_ED25519);
+        assert_eq!(hex::encode(derive_keys("//Alice//stash").unwrap().cord_public), ALICE_STASH_SR25519);
+
+        // the phrase is implied, and surrounding whitespace is ignored
+        let explicit = derive_keys(&format!("  {}//Alice\n", DEV_PHRASE)).unwrap();
+        assert_eq!(explicit.cord_public, alice.cord_public);
+        assert_eq!(explicit.secret_key.public(), alice.secret_key.public());
+        assert_ne!(derive_keys("//Bob").unwrap().secret_key.public(), alice.secret_key.public());
+    }
+
+    #[test]
+    fn node_key_from_suri_matches_the_keystore() {
+        let mut keystore = StarterkitKeystore::in_memory(SURI).unwrap();
+        let (_, starterkit_public) = keystore.initialize_keystore(SURI).unwrap();
+        let from_keystore = keystore.get_starter_kit_seed(starterkit_public).unwrap();
+        assert_eq!(SecretKey::from_suri(SURI).unwrap().public(), from_keystore.public());
+        assert_eq!(SecretKey::from_suri(SURI).unwrap().to_bytes(), from_keystore.to_bytes());
+    }
+
+    #[test]
+    fn soft_derivation_is_only_used_for_the_cord_account() {
+        use sp_core::crypto::{DeriveJunction, Derive};
+
+        // an sr25519 soft junction can be derived from the parent public key alone
+        let parent = sr25519::Pair::from_string(SURI, None).unwrap().public();
+        let soft = sr25519::Pair::from_string("//Alice/soft", None).unwrap().public();
+        assert_eq!(parent.derive([DeriveJunction::soft("soft")].into_iter()), Some(soft));
+        assert_ne!(soft, sr25519::Pair::from_string("//Alice//soft", None).unwrap().public());
+
+        // ed25519 has no soft derivation, so no node identity comes from such a SURI
+        let err = derive_keys("//Alice/soft").err().unwrap();
+        assert!(err.to_string().contains("StarterKit"), "{}", err);
+        assert!(SecretKey::from_suri("//Alice/soft").is_err());
+    }
+
+    #[test]
+    fn invalid_suris_are_rejected() {
+        let invalid = [
+            "not a recovery phrase",
+            // the last word does not match the checksum
+            "bottom drive obey lake curtain smoke basket hold race lonely fit fit",
+            "0xzz",
+            "0x1234",
+        ];
+        for suri in invalid {
+            assert!(derive_keys(suri).is_err(), "{:?}", suri);
+            assert!(SecretKey::from_suri(suri).is_err(), "{:?}", suri);
+        }
+        assert!(derive_keys(&generate_suri()).is_ok());
+    }
+}
@@ -1 +1,8 @@
-pub mod keystore;
\ No newline at end of file
+pub mod keystore;
+pub mod dek;
+pub mod registry;
+pub mod audit;
+pub mod credential;
+pub mod did;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
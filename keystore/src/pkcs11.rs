@@ -0,0 +1,363 @@
+use crate::keystore::Pkcs11Config;
+
+use anyhow::{anyhow, Result};
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::mechanism::eddsa::{EddsaParams, EddsaSignatureScheme};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::slot::Slot;
+use cryptoki::types::AuthPin;
+use sp_core::crypto::KeyTypeId;
+use sp_core::{blake2_256, ecdsa, ed25519, sr25519};
+use sp_keystore::{Error, Keystore};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+// A keystore that keeps ed25519 and ecdsa keys on a PKCS#11 token (an HSM) and falls back to
+// the wrapped local keystore for everything else. No HSM supports sr25519, so CORD account keys
+// always stay local.
+//
+// Keys on the token are matched to key types by their CKA_LABEL, which is the 4-character
+// KeyTypeId, e.g. `skit` for the node key. A public and private key belong together when their
+// CKA_ID is the same.
+
+// DER encoded OIDs of the curves, used as CKA_EC_PARAMS
+const ED25519_PARAMS: &[u8] = &[0x06, 0x03, 0x2b, 0x65, 0x70];
+const SECP256K1_PARAMS: &[u8] = &[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x0a];
+
+// The order of secp256k1 and half of it, to normalize signatures to low S as Ethereum requires
+const SECP256K1_ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+pub struct Pkcs11Keystore {
+    local: Arc<dyn Keystore>,
+    // a session is not Sync, so every token operation goes through this lock
+    session: Mutex<Session>,
+}
+
+impl Pkcs11Keystore {
+    /// Loads the PKCS#11 module, opens a session on the configured slot (or the first one with a
+    /// token) and logs in with the PIN.
+    pub fn open(config: &Pkcs11Config, local: Arc<dyn Keystore>) -> Result<Self> {
+        let pkcs11 = Pkcs11::new(&config.module)
+            .map_err(|e| anyhow!("❌ Failed to load PKCS#11 module {:?}: {e}", config.module))?;
+        pkcs11
+            .initialize(CInitializeArgs::OsThreads)
+            .map_err(|e| anyhow!("❌ Failed to initialize PKCS#11 module: {e}"))?;
+
+        let slot = match config.slot {
+            Some(id) => Slot::try_from(id).map_err(|e| anyhow!("❌ Invalid PKCS#11 slot {id}: {e}"))?,
+            None => *pkcs11
+                .get_slots_with_token()
+                .map_err(|e| anyhow!("❌ Failed to list PKCS#11 slots: {e}"))?
+                .first()
+                .ok_or_else(|| anyhow!("❌ No PKCS#11 slot has a token"))?,
+        };
+
+        let session = pkcs11
+            .open_rw_session(slot)
+            .map_err(|e| anyhow!("❌ Failed to open a session on PKCS#11 slot {}: {e}", slot.id()))?;
+        if let Some(pin) = &config.pin {
+            session
+                .login(UserType::User, Some(&AuthPin::new(pin.clone())))
+                .map_err(|e| anyhow!("❌ Failed to log in to PKCS#11 slot {}: {e}", slot.id()))?;
+        }
+
+        Ok(Self { local, session: Mutex::new(session) })
+    }
+
+    fn session(&self) -> Result<std::sync::MutexGuard<'_, Session>, Error> {
+        self.session.lock().map_err(|_| Error::Unavailable)
+    }
+
+    // (public key, private key handle) of every key pair of `key_type` on the token
+    fn token_keys(&self, key_type: KeyTypeId, kind: KeyType) -> Result<Vec<(Vec<u8>, ObjectHandle)>, Error> {
+        let session = self.session()?;
+        let label = Attribute::Label(key_type.0.to_vec());
+        let publics = session
+            .find_objects(&[Attribute::Class(ObjectClass::PUBLIC_KEY), Attribute::KeyType(kind), label.clone()])
+            .map_err(token_error)?;
+
+        let mut keys = Vec::with_capacity(publics.len());
+        for handle in publics {
+            let mut id = None;
+            let mut point = None;
+            for attribute in session
+                .get_attributes(handle, &[AttributeType::Id, AttributeType::EcPoint])
+                .map_err(token_error)?
+            {
+                match attribute {
+                    Attribute::Id(value) => id = Some(value),
+                    Attribute::EcPoint(value) => point = Some(value),
+                    _ => {}
+                }
+            }
+            let (Some(id), Some(point)) = (id, point) else { continue };
+            let Some(public) = decode_ec_point(kind, &point) else { continue };
+
+            let private = session
+                .find_objects(&[
+                    Attribute::Class(ObjectClass::PRIVATE_KEY),
+                    Attribute::KeyType(kind),
+                    label.clone(),
+                    Attribute::Id(id),
+                ])
+                .map_err(token_error)?;
+            if let Some(private) = private.first() {
+                keys.push((public, *private));
+            }
+        }
+        Ok(keys)
+    }
+
+    fn token_publics(&self, key_type: KeyTypeId, kind: KeyType) -> Vec<Vec<u8>> {
+        match self.token_keys(key_type, kind) {
+            Ok(keys) => keys.into_iter().map(|(public, _)| public).collect(),
+            Err(e) => {
+                warn!("⚠️  Failed to list keys on the PKCS#11 token: {e}");
+                Vec::new()
+            }
+        }
+    }
+
+    fn find_private(&self, key_type: KeyTypeId, kind: KeyType, public: &[u8]) -> Result<Option<ObjectHandle>, Error> {
+        Ok(self
+            .token_keys(key_type, kind)?
+            .into_iter()
+            .find(|(token_public, _)| token_public == public)
+            .map(|(_, private)| private))
+    }
+
+    // generates a key pair on the token that never leaves it, and returns its public key
+    fn generate(&self, key_type: KeyTypeId, kind: KeyType) -> Result<Vec<u8>, Error> {
+        let (mechanism, params) = if kind == KeyType::EC_EDWARDS {
+            (Mechanism::EccEdwardsKeyPairGen, ED25519_PARAMS)
+        } else {
+            (Mechanism::EccKeyPairGen, SECP256K1_PARAMS)
+        };
+
+        let session = self.session()?;
+        let id = session.generate_random_vec(16).map_err(token_error)?;
+        let label = Attribute::Label(key_type.0.to_vec());
+        let (public, _) = session
+            .generate_key_pair(
+                &mechanism,
+                &[
+                    Attribute::Token(true),
+                    Attribute::Private(false),
+                    Attribute::Verify(true),
+                    Attribute::EcParams(params.to_vec()),
+                    label.clone(),
+                    Attribute::Id(id.clone()),
+                ],
+                &[
+                    Attribute::Token(true),
+                    Attribute::Private(true),
+                    Attribute::Sensitive(true),
+                    Attribute::Extractable(false),
+                    Attribute::Sign(true),
+                    label,
+                    Attribute::Id(id),
+                ],
+            )
+            .map_err(token_error)?;
+
+        let point = session
+            .get_attributes(public, &[AttributeType::EcPoint])
+            .map_err(token_error)?
+            .into_iter()
+            .find_map(|attribute| match attribute {
+                Attribute::EcPoint(point) => Some(point),
+                _ => None,
+            })
+            .ok_or_else(|| Error::Other("the token returned no public key".to_string()))?;
+        decode_ec_point(kind, &point).ok_or_else(|| Error::Other("the token returned an invalid public key".to_string()))
+    }
+
+    fn ecdsa_sign_on_token(&self, private: ObjectHandle, public: &ecdsa::Public, msg: &[u8; 32]) -> Result<ecdsa::Signature, Error> {
+        let raw = self.session()?.sign(&Mechanism::Ecdsa, private, msg).map_err(token_error)?;
+        let raw: [u8; 64] = raw
+            .try_into()
+            .map_err(|_| Error::Other("the token returned an invalid ecdsa signature".to_string()))?;
+
+        // The token signs r || s. Substrate and Ethereum expect low S and a recovery ID.
+        let mut signature = [0u8; 65];
+        signature[..32].copy_from_slice(&raw[..32]);
+        let mut s: [u8; 32] = raw[32..].try_into().expect("32 bytes");
+        if s > SECP256K1_HALF_ORDER {
+            s = sub(&SECP256K1_ORDER, &s);
+        }
+        signature[32..64].copy_from_slice(&s);
+        for recovery_id in 0..2 {
+            signature[64] = recovery_id;
+            let candidate = ecdsa::Signature::from_raw(signature);
+            if candidate.recover_prehashed(msg).as_ref() == Some(public) {
+                return Ok(candidate);
+            }
+        }
+        Err(Error::Other("the ecdsa signature from the token does not match its public key".to_string()))
+    }
+}
+
+impl Keystore for Pkcs11Keystore {
+    fn sr25519_public_keys(&self, key_type: KeyTypeId) -> Vec<sr25519::Public> {
+        self.local.sr25519_public_keys(key_type)
+    }
+
+    fn sr25519_generate_new(&self, key_type: KeyTypeId, seed: Option<&str>) -> Result<sr25519::Public, Error> {
+        self.local.sr25519_generate_new(key_type, seed)
+    }
+
+    fn sr25519_sign(&self, key_type: KeyTypeId, public: &sr25519::Public, msg: &[u8]) -> Result<Option<sr25519::Signature>, Error> {
+        self.local.sr25519_sign(key_type, public, msg)
+    }
+
+    fn sr25519_vrf_sign(
+        &self,
+        key_type: KeyTypeId,
+        public: &sr25519::Public,
+        data: &sr25519::vrf::VrfSignData,
+    ) -> Result<Option<sr25519::vrf::VrfSignature>, Error> {
+        self.local.sr25519_vrf_sign(key_type, public, data)
+    }
+
+    fn sr25519_vrf_pre_output(
+        &self,
+        key_type: KeyTypeId,
+        public: &sr25519::Public,
+        input: &sr25519::vrf::VrfInput,
+    ) -> Result<Option<sr25519::vrf::VrfPreOutput>, Error> {
+        self.local.sr25519_vrf_pre_output(key_type, public, input)
+    }
+
+    // token keys come first, so the node key on the token is preferred over one from the SURI
+    fn ed25519_public_keys(&self, key_type: KeyTypeId) -> Vec<ed25519::Public> {
+        let mut keys: Vec<ed25519::Public> = self
+            .token_publics(key_type, KeyType::EC_EDWARDS)
+            .into_iter()
+            .filter_map(|public| ed25519::Public::try_from(public.as_slice()).ok())
+            .collect();
+        keys.extend(self.local.ed25519_public_keys(key_type));
+        keys
+    }
+
+    // keys derived from a seed are local by definition; new random keys are made on the token
+    fn ed25519_generate_new(&self, key_type: KeyTypeId, seed: Option<&str>) -> Result<ed25519::Public, Error> {
+        if seed.is_some() {
+            return self.local.ed25519_generate_new(key_type, seed);
+        }
+        let public = self.generate(key_type, KeyType::EC_EDWARDS)?;
+        ed25519::Public::try_from(public.as_slice())
+            .map_err(|_| Error::Other("the token returned an invalid ed25519 key".to_string()))
+    }
+
+    fn ed25519_sign(&self, key_type: KeyTypeId, public: &ed25519::Public, msg: &[u8]) -> Result<Option<ed25519::Signature>, Error> {
+        let Some(private) = self.find_private(key_type, KeyType::EC_EDWARDS, public.as_ref())? else {
+            return self.local.ed25519_sign(key_type, public, msg);
+        };
+        let mechanism = Mechanism::Eddsa(EddsaParams::new(EddsaSignatureScheme::Pure));
+        let signature = self.session()?.sign(&mechanism, private, msg).map_err(token_error)?;
+        ed25519::Signature::try_from(signature.as_slice())
+            .map(Some)
+            .map_err(|_| Error::Other("the token returned an invalid ed25519 signature".to_string()))
+    }
+
+    fn ecdsa_public_keys(&self, key_type: KeyTypeId) -> Vec<ecdsa::Public> {
+        let mut keys: Vec<ecdsa::Public> = self
+            .token_publics(key_type, KeyType::EC)
+            .into_iter()
+            .filter_map(|public| ecdsa::Public::try_from(public.as_slice()).ok())
+            .collect();
+        keys.extend(self.local.ecdsa_public_keys(key_type));
+        keys
+    }
+
+    fn ecdsa_generate_new(&self, key_type: KeyTypeId, seed: Option<&str>) -> Result<ecdsa::Public, Error> {
+        if seed.is_some() {
+            return self.local.ecdsa_generate_new(key_type, seed);
+        }
+        let public = self.generate(key_type, KeyType::EC)?;
+        ecdsa::Public::try_from(public.as_slice())
+            .map_err(|_| Error::Other("the token returned an invalid ecdsa key".to_string()))
+    }
+
+    // like `ecdsa::Pair::sign`, which signs the blake2-256 hash of the message
+    fn ecdsa_sign(&self, key_type: KeyTypeId, public: &ecdsa::Public, msg: &[u8]) -> Result<Option<ecdsa::Signature>, Error> {
+        self.ecdsa_sign_prehashed(key_type, public, &blake2_256(msg))
+    }
+
+    fn ecdsa_sign_prehashed(&self, key_type: KeyTypeId, public: &ecdsa::Public, msg: &[u8; 32]) -> Result<Option<ecdsa::Signature>, Error> {
+        match self.find_private(key_type, KeyType::EC, public.as_ref())? {
+            Some(private) => self.ecdsa_sign_on_token(private, public, msg).map(Some),
+            None => self.local.ecdsa_sign_prehashed(key_type, public, msg),
+        }
+    }
+
+    fn insert(&self, key_type: KeyTypeId, suri: &str, public: &[u8]) -> Result<(), ()> {
+        self.local.insert(key_type, suri, public)
+    }
+
+    fn keys(&self, key_type: KeyTypeId) -> Result<Vec<Vec<u8>>, Error> {
+        let mut keys = self.token_publics(key_type, KeyType::EC_EDWARDS);
+        keys.extend(self.token_publics(key_type, KeyType::EC));
+        keys.extend(self.local.keys(key_type)?);
+        Ok(keys)
+    }
+
+    fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
+        public_keys.iter().all(|(public, key_type)| {
+            let on_token = [KeyType::EC_EDWARDS, KeyType::EC]
+                .into_iter()
+                .any(|kind| matches!(self.find_private(*key_type, kind, public), Ok(Some(_))));
+            on_token || self.local.has_keys(&[(public.clone(), *key_type)])
+        })
+    }
+}
+
+fn token_error(e: cryptoki::error::Error) -> Error {
+    Error::Other(format!("PKCS#11: {e}"))
+}
+
+// CKA_EC_POINT is usually a DER OCTET STRING around the point, but some tokens return the raw
+// point. ed25519 points are 32 bytes; secp256k1 ones are uncompressed and get compressed to the
+// 33 bytes Substrate uses.
+fn decode_ec_point(kind: KeyType, point: &[u8]) -> Option<Vec<u8>> {
+    let raw_len = if kind == KeyType::EC_EDWARDS { 32 } else { 65 };
+    let raw = match point.len() {
+        len if len == raw_len => point,
+        len if len == raw_len + 2 && point[0] == 0x04 && point[1] as usize == raw_len => &point[2..],
+        _ => return None,
+    };
+    if kind == KeyType::EC_EDWARDS {
+        return Some(raw.to_vec());
+    }
+    if raw[0] != 0x04 {
+        return None;
+    }
+    let mut compressed = Vec::with_capacity(33);
+    compressed.push(if raw[64] & 1 == 0 { 0x02 } else { 0x03 });
+    compressed.extend_from_slice(&raw[1..33]);
+    Some(compressed)
+}
+
+// a - b for big-endian 256-bit numbers with a >= b
+fn sub(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let mut result = [0u8; 32];
+    let mut borrow = 0i16;
+    for i in (0..32).rev() {
+        let mut difference = a[i] as i16 - b[i] as i16 - borrow;
+        borrow = if difference < 0 { 1 } else { 0 };
+        if difference < 0 {
+            difference += 256;
+        }
+        result[i] = difference as u8;
+    }
+    result
+}
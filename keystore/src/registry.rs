@@ -0,0 +1,232 @@
+use crate::keystore::{KeyScheme, CORD_KEY_TYPE, ECDSA_KEY_TYPE, SIGNING_KEY_TYPE, STARTERKIT_KEY_TYPE};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::KeyTypeId;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+// File the registered key types are persisted to, inside the node's keystore directory.
+const KEY_TYPES_FILE: &str = "key_types.json";
+
+/// What the keys of a key type may be used for. Checked when a key is generated or signs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyPurpose {
+    /// Signing document entries and payloads.
+    DocSigning,
+    /// Authenticating API requests, e.g. signing challenges.
+    ApiAuth,
+    /// Signing chain transactions.
+    ChainTx,
+}
+
+/// The purpose names used by the API.
+pub const KEY_PURPOSE_NAMES: [&str; 3] = ["doc-signing", "api-auth", "chain-tx"];
+
+impl KeyPurpose {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "doc-signing" => Ok(Self::DocSigning),
+            "api-auth" => Ok(Self::ApiAuth),
+            "chain-tx" => Ok(Self::ChainTx),
+            _ => Err(anyhow!("❌ Unknown key purpose '{}'. Expected one of: {}.", name, KEY_PURPOSE_NAMES.join(", "))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DocSigning => "doc-signing",
+            Self::ApiAuth => "api-auth",
+            Self::ChainTx => "chain-tx",
+        }
+    }
+}
+
+/// A key type known to the node: its API name, its 4-character `KeyTypeId`, the scheme of its
+/// keys and what they may be used for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyTypeInfo {
+    pub name: String,
+    pub id: String,
+    pub scheme: KeyScheme,
+    pub purposes: Vec<KeyPurpose>,
+    /// Built-in key types are defined by the node and cannot be changed.
+    #[serde(default, skip_deserializing)]
+    pub builtin: bool,
+}
+
+impl KeyTypeInfo {
+    fn builtin(name: &str, id: KeyTypeId, scheme: KeyScheme, purposes: &[KeyPurpose]) -> Self {
+        Self {
+            name: name.to_string(),
+            id: String::from_utf8_lossy(&id.0).to_string(),
+            scheme,
+            purposes: purposes.to_vec(),
+            builtin: true,
+        }
+    }
+
+    pub fn key_type_id(&self) -> KeyTypeId {
+        let mut id = [0u8; 4];
+        id.copy_from_slice(&self.id.as_bytes()[..4]);
+        KeyTypeId(id)
+    }
+
+    /// Fails unless keys of this type may be used for `purpose`.
+    pub fn ensure_purpose(&self, purpose: KeyPurpose) -> Result<()> {
+        if self.purposes.contains(&purpose) {
+            return Ok(());
+        }
+        let purposes: Vec<&str> = self.purposes.iter().map(KeyPurpose::as_str).collect();
+        Err(anyhow!(
+            "❌ Key type '{}' is not registered for {}. Its purposes are: {}.",
+            self.name,
+            purpose.as_str(),
+            purposes.join(", ")
+        ))
+    }
+
+    /// Whether new keys may be generated under this type. The node's own CORD and STARTERKIT
+    /// keys are looked up as the first key of their type, so those are created by `init` only.
+    pub fn is_generatable(&self) -> bool {
+        let id = self.key_type_id();
+        id != CORD_KEY_TYPE && id != STARTERKIT_KEY_TYPE
+    }
+
+    /// Fails unless keys of this type may sign what API callers ask for. The STARTERKIT key
+    /// derives the node's identity and a `chain-tx` key speaks for the node on chain, so neither
+    /// ever signs for a caller.
+    pub fn ensure_api_signable(&self) -> Result<()> {
+        if self.key_type_id() == STARTERKIT_KEY_TYPE {
+            return Err(anyhow!("❌ Key type '{}' holds the node's identity and cannot sign for callers.", self.name));
+        }
+        if self.purposes.contains(&KeyPurpose::ChainTx) {
+            return Err(anyhow!(
+                "❌ Key type '{}' signs chain transactions and cannot sign for callers.",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The key types every node has.
+pub fn builtin_key_types() -> Vec<KeyTypeInfo> {
+    use KeyPurpose::*;
+    vec![
+        KeyTypeInfo::builtin("cord", CORD_KEY_TYPE, KeyScheme::Sr25519, &[ChainTx]),
+        KeyTypeInfo::builtin("skit", STARTERKIT_KEY_TYPE, KeyScheme::Ed25519, &[DocSigning]),
+        KeyTypeInfo::builtin("sign", SIGNING_KEY_TYPE, KeyScheme::Sr25519, &[DocSigning, ApiAuth]),
+        KeyTypeInfo::builtin("ecdsa", ECDSA_KEY_TYPE, KeyScheme::Ecdsa, &[DocSigning]),
+    ]
+}
+
+/// Returns the built-in key type with `id`, e.g. `CORD_KEY_TYPE`.
+pub fn builtin_key_type(id: KeyTypeId) -> Option<KeyTypeInfo> {
+    builtin_key_types().into_iter().find(|info| info.key_type_id() == id)
+}
+
+/// The built-in key types plus those registered by operators, shared through `AppState` and
+/// persisted as JSON. Without storage (the default) it only knows the built-in types.
+#[derive(Clone, Default)]
+pub struct KeyTypeRegistry {
+    registered: Arc<RwLock<Vec<KeyTypeInfo>>>,
+    storage_path: Option<PathBuf>,
+}
+
+impl KeyTypeRegistry {
+    /// Loads the registry from the keystore directory at `keystore_dir`, starting with the
+    /// built-in types only if nothing was registered yet.
+    pub fn load(keystore_dir: &Path) -> Result<Self> {
+        let storage_path = keystore_dir.join(KEY_TYPES_FILE);
+
+        let registered: Vec<KeyTypeInfo> = if storage_path.exists() {
+            let content = std::fs::read_to_string(&storage_path)?;
+            serde_json::from_str(&content)?
+        } else {
+            Vec::new()
+        };
+
+        let mut known = builtin_key_types();
+        for info in &registered {
+            validate(&known, info)?;
+            known.push(info.clone());
+        }
+
+        Ok(Self {
+            registered: Arc::new(RwLock::new(registered)),
+            storage_path: Some(storage_path),
+        })
+    }
+
+    /// All key types, built-in ones first.
+    pub fn list(&self) -> Vec<KeyTypeInfo> {
+        let mut key_types = builtin_key_types();
+        key_types.extend(self.registered.read().unwrap().iter().cloned());
+        key_types
+    }
+
+    /// Looks up a key type by its API name.
+    pub fn get(&self, name: &str) -> Result<KeyTypeInfo> {
+        let key_types = self.list();
+        key_types.iter().find(|info| info.name == name).cloned().ok_or_else(|| {
+            let names: Vec<&str> = key_types.iter().map(|info| info.name.as_str()).collect();
+            anyhow!("❌ Unknown key type '{}'. Expected one of: {}.", name, names.join(", "))
+        })
+    }
+
+    /// Registers a new key type. Its name and ID must not be taken, so existing keys can never
+    /// change meaning.
+    pub fn register(&self, mut info: KeyTypeInfo) -> Result<KeyTypeInfo> {
+        info.builtin = false;
+        let mut purposes = Vec::with_capacity(info.purposes.len());
+        for purpose in info.purposes {
+            if !purposes.contains(&purpose) {
+                purposes.push(purpose);
+            }
+        }
+        info.purposes = purposes;
+        {
+            let mut registered = self.registered.write().unwrap();
+            let mut known = builtin_key_types();
+            known.extend(registered.iter().cloned());
+            validate(&known, &info)?;
+            registered.push(info.clone());
+        }
+        self.save()?;
+        Ok(info)
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.storage_path else { return Ok(()) };
+        let json = serde_json::to_string_pretty(&*self.registered.read().unwrap())?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+fn validate(known: &[KeyTypeInfo], info: &KeyTypeInfo) -> Result<()> {
+    let name_ok = info.name.starts_with(|c: char| c.is_ascii_lowercase())
+        && info.name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !name_ok {
+        return Err(anyhow!(
+            "❌ Invalid key type name '{}'. Use lowercase letters, digits and '-', starting with a letter.",
+            info.name
+        ));
+    }
+    if info.id.len() != 4 || !info.id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(anyhow!("❌ Invalid key type ID '{}'. A KeyTypeId is exactly 4 ASCII letters or digits.", info.id));
+    }
+    if info.purposes.is_empty() {
+        return Err(anyhow!("❌ Key type '{}' needs at least one purpose.", info.name));
+    }
+    if let Some(existing) = known.iter().find(|existing| existing.name == info.name || existing.id == info.id) {
+        return Err(anyhow!(
+            "❌ Key type '{}' ({}) is already registered. Choose another name and ID.",
+            existing.name,
+            existing.id
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use helpers::entry_index::{EntryIndex, IndexUpdate};
+use helpers::stores::{NodeBlobs, NodeDocs};
+use helpers::events::{EventBus, NodeEvent};
+use helpers::utils::{decode_doc_id, decode_key, encode_doc_id, SS58AuthorId};
+
+use futures::{StreamExt, TryStreamExt};
+use iroh_docs::engine::LiveEvent;
+use iroh_docs::store::Query;
+use iroh_docs::{ContentStatus, Entry, NamespaceId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+// Larger values are counted in the stats, but their fields are not indexed
+const MAX_INDEXED_VALUE: u64 = 1024 * 1024;
+
+/// Keeps `index` up to date with every document: each one is scanned once, then its live events
+/// are applied as they come.
+///
+/// Documents created or joined later are picked up from the `EventBus`, and dropped documents
+/// are forgotten.
+pub fn spawn_entry_indexer(
+    docs: Arc<NodeDocs>,
+    blobs: Arc<NodeBlobs>,
+    events: EventBus,
+    index: EntryIndex,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        // Subscribe before listing, so documents created in between are not missed
+        let mut node_events = events.subscribe();
+        let mut indexers: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+        match list_doc_ids(&docs).await {
+            Ok(doc_ids) => {
+                for doc_id in doc_ids {
+                    let indexer = index_doc(docs.clone(), blobs.clone(), doc_id.clone(), index.clone());
+                    indexers.insert(doc_id, indexer);
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to list documents"),
+        }
+
+        loop {
+            match node_events.recv().await {
+                Ok(NodeEvent::DocCreated { doc_id }) | Ok(NodeEvent::DocJoined { doc_id }) => {
+                    if !indexers.get(&doc_id).is_some_and(|indexer| !indexer.is_finished()) {
+                        let indexer = index_doc(docs.clone(), blobs.clone(), doc_id.clone(), index.clone());
+                        indexers.insert(doc_id, indexer);
+                    }
+                }
+                Ok(NodeEvent::DocDropped { doc_id }) => {
+                    if let Some(indexer) = indexers.remove(&doc_id) {
+                        indexer.abort();
+                    }
+                    index.forget(&doc_id);
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!(missed, "fell behind the event bus, some documents may not be indexed");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+async fn list_doc_ids(docs: &NodeDocs) -> anyhow::Result<Vec<String>> {
+    let namespaces: Vec<_> = docs.client().list().await?.try_collect().await?;
+    Ok(namespaces.into_iter().map(|(namespace_id, _)| encode_doc_id(namespace_id.as_bytes())).collect())
+}
+
+// Scans one document into the index, then applies its entry and content events
+fn index_doc(docs: Arc<NodeDocs>, blobs: Arc<NodeBlobs>, doc_id: String, index: EntryIndex) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let subscribed = async {
+            let doc = docs.client()
+                .open(NamespaceId::from(decode_doc_id(&doc_id)?))
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("document not found"))?;
+            // Subscribe before scanning, so entries written in between are not missed
+            let events = doc.subscribe().await?;
+            index.reset(&doc_id);
+            let mut entries = doc.get_many(Query::all()).await?;
+            while let Some(entry) = entries.next().await {
+                record(&blobs, &index, &doc_id, &entry?, true).await;
+            }
+            index.mark_ready(&doc_id);
+            anyhow::Ok(events)
+        };
+        let mut events = match subscribed.await {
+            Ok(events) => events,
+            Err(e) => {
+                tracing::warn!(doc_id = %doc_id, error = %e, "failed to index document");
+                index.forget(&doc_id);
+                return;
+            }
+        };
+
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(LiveEvent::InsertLocal { entry }) => record(&blobs, &index, &doc_id, &entry, true).await,
+                Ok(LiveEvent::InsertRemote { entry, content_status, .. }) => {
+                    record(&blobs, &index, &doc_id, &entry, content_status == ContentStatus::Complete).await;
+                }
+                Ok(LiveEvent::ContentReady { hash }) => {
+                    let hash_str = hash.to_string();
+                    if index.is_pending(&doc_id, &hash_str) {
+                        if let Ok(content) = blobs.client().read_to_bytes(hash).await {
+                            index.record_content(&doc_id, &hash_str, &content);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    })
+}
+
+// Records one entry, reading its value to index its fields when it is available
+async fn record(blobs: &NodeBlobs, index: &EntryIndex, doc_id: &str, entry: &Entry, content_complete: bool) {
+    let Ok(author) = SS58AuthorId::from_author_id(&entry.author()) else {
+        return;
+    };
+    let update = IndexUpdate {
+        raw_key: entry.key().to_vec(),
+        key: String::from_utf8_lossy(&decode_key(entry.key())).into_owned(),
+        author: author.as_ss58().to_string(),
+        hash: entry.content_hash().to_string(),
+        len: entry.content_len(),
+        timestamp: entry.timestamp(),
+    };
+
+    let content = if !index.fields().is_empty() && content_complete && update.len > 0 && update.len <= MAX_INDEXED_VALUE {
+        blobs.client().read_to_bytes(entry.content_hash()).await.ok()
+    } else {
+        None
+    };
+    // Too large values are never indexed, so they do not wait for their content either
+    let content = match content {
+        Some(content) => Some(content),
+        None if update.len > MAX_INDEXED_VALUE => Some(Default::default()),
+        None => None,
+    };
+    index.record_entry(doc_id, update, content.as_deref());
+}
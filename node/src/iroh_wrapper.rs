@@ -1,28 +1,81 @@
 use helpers::cli::CliArgs;
+use helpers::utils::decode_doc_id;
+use helpers::blob_cache::BlobCache;
+use helpers::doc_handles::DocHandleCache;
+use helpers::schema_refs::SchemaRefs;
+use helpers::stores::{NodeBlobs, NodeDocs};
 // use keystore::StarterkitKeystore;
-use keystore::keystore::{StarterkitKeystore, CordKeystoreSigner};
+use keystore::keystore::{generate_suri, StarterkitKeystore, CordKeystoreSigner, Pkcs11Config, SecretKeyExt};
 
-use iroh::{Endpoint, RelayMode, SecretKey, protocol::Router};
+use futures::StreamExt;
+use iroh::{Endpoint, NodeAddr, RelayMap, RelayMode, RelayUrl, SecretKey, protocol::Router};
 use std::error::Error;
 use std::path::PathBuf;
 use std::fs;
 use tokio::fs as tokio_fs;
 use tokio::io::AsyncWriteExt;
 use iroh_blobs::net_protocol::Blobs;
-use iroh_blobs::store::fs::Store as blob_store_fs;
+use iroh_blobs::store::mem::Store as blob_store_mem;
 use iroh_gossip::net::Gossip;
 use iroh_docs::protocol::Docs;
+use iroh_docs::engine::{LiveEvent, SyncEvent};
+use iroh_docs::NamespaceId;
 use std::sync::Arc;
+use std::time::Duration;
 use iroh::PublicKey;
+use crate::replica_digests::{ReplicaDigests, REPLICA_DIGESTS_ALPN};
 
 pub struct IrohNode {
     pub node_id: PublicKey,
     pub router: Router,
-    pub blobs: Arc<Blobs<blob_store_fs>>,
-    pub docs: Arc<Docs<blob_store_fs>>,
+    pub blobs: Arc<NodeBlobs>,
+    pub docs: Arc<NodeDocs>,
     pub cord_signer: CordKeystoreSigner,
 }
 
+impl IrohNode {
+    /// Waits until the node knows its direct addresses, so the tickets it shares let peers
+    /// reach it. Wrap it in a timeout, it waits for as long as no network interface is up.
+    pub async fn wait_ready(&self) -> anyhow::Result<()> {
+        self.router.endpoint().direct_addresses().initialized().await?;
+        Ok(())
+    }
+
+    /// Syncs `doc_id` with `peer`, and waits for that sync to finish and for the content it
+    /// queued to be downloaded. Fails if the sync failed.
+    ///
+    /// The sync is started after subscribing to the document, so it is never missed as a sync
+    /// that already finished would be. Wrap it in a timeout, it waits for as long as the peer
+    /// cannot be reached.
+    pub async fn await_sync(&self, doc_id: &str, peer: PublicKey) -> anyhow::Result<SyncEvent> {
+        let doc = self.docs.client()
+            .open(NamespaceId::from(decode_doc_id(doc_id)?))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("document not found"))?;
+        let mut events = doc.subscribe().await?;
+        doc.start_sync(vec![NodeAddr::new(peer)]).await?;
+
+        let mut synced = None;
+        while let Some(event) = events.next().await {
+            match event? {
+                LiveEvent::SyncFinished(sync) if sync.peer == peer => {
+                    if let Err(e) = &sync.result {
+                        return Err(anyhow::anyhow!("sync with {} failed: {}", peer, e));
+                    }
+                    synced = Some(sync);
+                }
+                LiveEvent::PendingContentReady => {
+                    if let Some(sync) = synced {
+                        return Ok(sync);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(anyhow::anyhow!("the document event stream ended before the sync finished"))
+    }
+}
+
 pub async fn setup_iroh_node(args: CliArgs) -> Result<IrohNode, Box<dyn Error>> {
     // password should always be provided
     if args.password.is_empty() {
@@ -39,165 +92,376 @@ pub async fn setup_iroh_node(args: CliArgs) -> Result<IrohNode, Box<dyn Error>>
         // user could pass the --secret and it will still work.
     }
 
-    let mut path: PathBuf;
-    let mut secret_key: SecretKey;
-    let mut cord_signer: CordKeystoreSigner;
+    let path = args.path
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("data"));
 
-    // it is a bootstrap operation or a restart operation
-    if args.bootstrap {
-        println!("🚀 Bootstrapping process begun...\n");
+    let hsm = pkcs11_config(&args);
 
+    // it is a bootstrap operation or a restart operation
+    let (secret_key, cord_signer) = if args.bootstrap {
         // bootstrap logic here
-        if args.suri.is_none() {
+        let Some(suri) = args.suri.as_deref() else {
             return Err("❌ SURI is required for bootstrapping. Please provide --suri <SURI>.".into());
+        };
+        let bootstrapped = bootstrap_node(&path, &args.password, suri, args.secret.clone())?;
+        // keys are always bootstrapped from the SURI, so reopen to pick up the ones on the token
+        match &hsm {
+            Some(_) => open_node(&path, &args.password, args.secret.clone(), hsm.as_ref())?,
+            None => bootstrapped,
         }
+    } else {
+        open_node(&path, &args.password, args.secret.clone(), hsm.as_ref())?
+    };
 
-        path = args.path
-            .as_ref()
-            .map(|p| PathBuf::from(p))
-            .unwrap_or_else(|| PathBuf::from("data"));
-        let password = args.password.clone();
-        let suri = args.suri.unwrap().clone();
-        let secret = args.secret.clone();
-
-        // if a node is already configured at the path, then throw an error
-        if path.exists() {
-            return Err(format!(
-                "❌ A node is already configured at {:?}.\n\
-                Please choose a different path with --path <PATH> or remove the existing node directory.",
-                path
-            ).into());
-        }
+    let endpoint = Endpoint::builder()
+        .secret_key(secret_key.clone())
+        .relay_mode(relay_mode(&args.relay)?)
+        .discovery_n0()
+        .bind()
+        .await?;
 
-        fs::create_dir_all(&path).map_err(|e| format!("Failed to create directory: {}", e))?;
-        println!("✅ Created directory at {:?}\n", path);
-        
-        // create a file inside path called 'password' and store the hash of the password in it.
-        let mut password_file_path = path.clone();
-        password_file_path.push("password");
-        let password_hash = blake3::hash(password.as_bytes()).to_hex().to_string();
-        fs::write(&password_file_path, password_hash)
-            .map_err(|e| format!("❌ Failed to create directory {:?}: {e}", path))?;
-        println!("✅ Password successfully set for node.\n");
-
-        // create a directory inside path called 'keystore' 
-        let mut keystore_dir = path.clone();
-        keystore_dir.push("keystore");
-        fs::create_dir_all(&keystore_dir)
-            .map_err(|e| format!("❌ Failed to write password file at {:?}: {e}", password_file_path))?;
-
-        // setup the keystore
-        let keystore_secret = StarterkitKeystore::keystore_access(secret)
-            .map_err(|e| format!("❌ Failed to process keystore secret: {e}"))?;
-
-        let mut keystore = StarterkitKeystore::new(&keystore_dir, keystore_secret)
-            .map_err(|e| format!("❌ Failed to initialize keystore: {e}"))?;
-
-        let (cord_pair, starter_kit_pair) = keystore
-            .initialize_keystore(&suri.clone())
-            .map_err(|e| format!("❌ Failed to initialize keypairs in keystore: {e}"))?;
-
-        secret_key = keystore
-            .get_starter_kit_seed(starter_kit_pair)
-            .map_err(|e| format!("❌ Failed to get starter kit seed: {e}"))?;
-        
-        println!("✅ Keystore initialized successfully.\n");
-
-        cord_signer = keystore.get_cord_signer()?;
-
-        println!("🎉 Bootstarpping process completed successfully.\n");
-    } else {
-        println!("🔄 Restarting process begun...\n");
-
-        path = args.path
-            .as_ref()
-            .map(|p| PathBuf::from(p))
-            .unwrap_or_else(|| PathBuf::from("data"));
-
-        if !path.exists() {
-            return Err(format!(
-                "❌ The provided path {:?} does not exist.\n\
-                Please bootstrap a new node first using --bootstrap.",
-                path
-            ).into());
-        }
+    let builder = Router::builder(endpoint.clone());
+
+    let node_id = endpoint.clone().node_id();
+
+    let blobs = Blobs::persistent(path.clone()).await?.build(builder.endpoint());
+    let gossip = Gossip::builder().spawn(builder.endpoint().clone()).await?;
+    let docs = Docs::persistent(path.clone()).spawn(&blobs, &gossip).await?;
+    
+    let router = Router::builder(endpoint.clone())
+        .accept(iroh_blobs::ALPN, blobs.clone())
+        .accept(iroh_gossip::ALPN, gossip)
+        .accept(iroh_docs::ALPN, docs.clone())
+        .accept(REPLICA_DIGESTS_ALPN, ReplicaDigests::new(docs.clone(), gateway::access_control::is_node_id_allowed))
+        .spawn()
+        .await?;
 
-        let password = args.password.clone();
+    // the caches belong to this node, so nodes started in one process do not share them
+    let handles = DocHandleCache::new(args.doc_cache_size, Duration::from_secs(args.doc_cache_idle));
+    let schema_refs = SchemaRefs::new(args.schema_registry_doc.clone(), args.schema_ref_url.clone());
+    let cache = BlobCache::new(args.blob_cache_size, args.blob_cache_max_blob);
 
-        println!("🔑 Checking password for the node at {:?}\n", path);
-        let mut password_file_path = path.clone();
-        password_file_path.push("password");
+    Ok(IrohNode {
+        node_id,
+        router,
+        blobs: Arc::new(NodeBlobs::new(blobs).with_cache(cache)),
+        docs: Arc::new(NodeDocs::new(docs).with_handles(handles).with_schema_refs(schema_refs)),
+        cord_signer,
+    })
+}
+
+/// A node whose blobs, documents and keys are kept in memory only, for tests, doctests and
+/// examples. Nothing is written to disk, there is no password, keystore file or gateway ACL,
+/// and it starts in milliseconds.
+///
+/// It binds its endpoint without relays or discovery, so it only reaches peers by their direct
+/// addresses, such as another `MemoryNode` in the same process. Everything is lost once it is
+/// dropped or shut down.
+///
+/// The `starter_kit_core` functions take the stores of an `IrohNode`, so a `MemoryNode` is used
+/// through the iroh clients of its `blobs` and `docs`.
+///
+/// ```
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// use node::iroh_wrapper::MemoryNode;
+///
+/// let node = MemoryNode::spawn().await?;
+/// let author = node.docs.client().authors().default().await?;
+/// let doc = node.docs.client().create().await?;
+/// doc.set_bytes(author, "greeting", "hello").await?;
+///
+/// let entry = doc.get_exact(author, "greeting", false).await?.expect("entry was just set");
+/// let value = node.blobs.client().read_to_bytes(entry.content_hash()).await?;
+/// assert_eq!(value.as_ref(), b"hello");
+/// node.shutdown().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct MemoryNode {
+    pub node_id: PublicKey,
+    pub router: Router,
+    pub blobs: Arc<Blobs<blob_store_mem>>,
+    pub docs: Arc<Docs<blob_store_mem>>,
+    pub cord_signer: CordKeystoreSigner,
+}
+
+impl MemoryNode {
+    /// Starts a node with keys derived from a newly generated SURI, so every node is distinct.
+    pub async fn spawn() -> anyhow::Result<Self> {
+        Self::spawn_with_suri(&generate_suri()).await
+    }
+
+    /// Starts a node with the keys of `suri`, as `init --suri` would derive them, so its NodeId
+    /// and CORD account are the same on every run.
+    pub async fn spawn_with_suri(suri: &str) -> anyhow::Result<Self> {
+        let keystore = StarterkitKeystore::in_memory(suri)?;
+        let secret_key = keystore.get_starter_kit_seed(keystore.get_starterkit_public_key()?)?;
+        let cord_signer = keystore.get_cord_signer()?;
+
+        let endpoint = Endpoint::builder()
+            .secret_key(secret_key)
+            .relay_mode(RelayMode::Disabled)
+            .bind()
+            .await?;
+
+        let blobs = Blobs::memory().build(&endpoint);
+        let gossip = Gossip::builder().spawn(endpoint.clone()).await?;
+        let docs = Docs::memory().spawn(&blobs, &gossip).await?;
+
+        let router = Router::builder(endpoint.clone())
+            .accept(iroh_blobs::ALPN, blobs.clone())
+            .accept(iroh_gossip::ALPN, gossip)
+            .accept(iroh_docs::ALPN, docs.clone())
+            .spawn()
+            .await?;
+
+        Ok(MemoryNode {
+            node_id: endpoint.node_id(),
+            router,
+            blobs: Arc::new(blobs),
+            docs: Arc::new(docs),
+            cord_signer,
+        })
+    }
 
-        let password_hash = blake3::hash(password.as_bytes()).to_hex().to_string();
+    /// Waits until the node knows its direct addresses, see `IrohNode::wait_ready`.
+    pub async fn wait_ready(&self) -> anyhow::Result<()> {
+        self.router.endpoint().direct_addresses().initialized().await?;
+        Ok(())
+    }
 
-        let stored_password_hash = fs::read_to_string(&password_file_path)
-            .map_err(|e| format!("❌ Failed to read password file: {}", e))?;
+    /// Stops the node. Its stores are dropped with it.
+    pub async fn shutdown(self) -> anyhow::Result<()> {
+        self.router.shutdown().await
+    }
+}
 
-        if password_hash != stored_password_hash {
-            return Err("❌ Incorrect password provided. Please check your --password and try again.".into());
+/// Parses the `--relay` values: empty or `default` for n0's production relays, `staging`
+/// for n0's staging relays, `disabled` for none, or one or more relay server URLs.
+pub fn relay_mode(relay: &[String]) -> Result<RelayMode, Box<dyn Error>> {
+    match relay {
+        [] => Ok(RelayMode::Default),
+        [mode] if mode == "default" => Ok(RelayMode::Default),
+        [mode] if mode == "staging" => Ok(RelayMode::Staging),
+        [mode] if mode == "disabled" => Ok(RelayMode::Disabled),
+        urls => {
+            let mut nodes = Vec::new();
+            for url in urls {
+                let url: RelayUrl = url.parse().map_err(|e| {
+                    format!("❌ Invalid relay '{}': {}. Use default, staging, disabled or relay server URLs.", url, e)
+                })?;
+                nodes.extend(RelayMap::from_url(url).nodes().cloned());
+            }
+            Ok(RelayMode::Custom(RelayMap::from_nodes(nodes)?))
         }
-        println!("✅ Password verified successfully.\n");
+    }
+}
+
+/// The PKCS#11 token set with `--pkcs11-module`, if any.
+pub fn pkcs11_config(args: &CliArgs) -> Option<Pkcs11Config> {
+    args.pkcs11_module.as_ref().map(|module| Pkcs11Config {
+        module: PathBuf::from(module),
+        slot: args.pkcs11_slot,
+        pin: args.pkcs11_pin.clone(),
+    })
+}
+
+/// Creates a new node directory at `path`: stores the password hash and initializes the
+/// keystore with keypairs derived from `suri`.
+///
+/// Returns the iroh secret key and the CORD signer for the new node.
+pub fn bootstrap_node(
+    path: &PathBuf,
+    password: &str,
+    suri: &str,
+    secret: Option<String>,
+) -> Result<(SecretKey, CordKeystoreSigner), Box<dyn Error>> {
+    tracing::info!("🚀 Bootstrapping process begun...");
+
+    if password.is_empty() {
+        return Err("❌ Password is required. Please provide --password <PASSWORD>.".into());
+    }
+
+    // if a node is already configured at the path, then throw an error
+    if path.exists() {
+        return Err(format!(
+            "❌ A node is already configured at {:?}.\n\
+            Please choose a different path with --path <PATH> or remove the existing node directory.",
+            path
+        ).into());
+    }
+
+    fs::create_dir_all(path).map_err(|e| format!("Failed to create directory: {}", e))?;
+    tracing::info!(path = %path.display(), "✅ Created node directory");
+
+    // create a file inside path called 'password' and store the hash of the password in it.
+    let mut password_file_path = path.clone();
+    password_file_path.push("password");
+    let password_hash = blake3::hash(password.as_bytes()).to_hex().to_string();
+    fs::write(&password_file_path, password_hash)
+        .map_err(|e| format!("❌ Failed to write password file at {:?}: {e}", password_file_path))?;
+    tracing::info!("✅ Password successfully set for node.");
+
+    // create a directory inside path called 'keystore'
+    let mut keystore_dir = path.clone();
+    keystore_dir.push("keystore");
+    fs::create_dir_all(&keystore_dir)
+        .map_err(|e| format!("❌ Failed to create directory {:?}: {e}", keystore_dir))?;
+
+    // setup the keystore
+    let keystore_secret = StarterkitKeystore::keystore_access(secret)
+        .map_err(|e| format!("❌ Failed to process keystore secret: {e}"))?;
+
+    let mut keystore = StarterkitKeystore::new(&keystore_dir, keystore_secret)
+        .map_err(|e| format!("❌ Failed to initialize keystore: {e}"))?;
+
+    let (_cord_pair, starter_kit_pair) = keystore
+        .initialize_keystore(suri)
+        .map_err(|e| format!("❌ Failed to initialize keypairs in keystore: {e}"))?;
+
+    let secret_key = keystore
+        .get_starter_kit_seed(starter_kit_pair)
+        .map_err(|e| format!("❌ Failed to get starter kit seed: {e}"))?;
+
+    // The NodeId must be recoverable from the SURI alone, see `SecretKeyExt::from_suri`
+    let recovered = SecretKey::from_suri(suri).map_err(|e| format!("❌ Failed to derive node key from SURI: {e}"))?;
+    if recovered.public() != secret_key.public() {
+        return Err("❌ The keystore derived a different NodeId than the SURI. The node could not be recovered from its SURI.".into());
+    }
+
+    tracing::info!("✅ Keystore initialized successfully.");
+
+    let cord_signer = keystore.get_cord_signer()?;
+
+    tracing::info!("🎉 Bootstrapping process completed successfully.");
+
+    Ok((secret_key, cord_signer))
+}
+
+/// Opens an existing node directory at `path` after checking the password.
+///
+/// With `hsm`, the keys on that PKCS#11 token are used alongside the keystore. If the token
+/// cannot be opened the node falls back to the keystore alone, with a warning.
+///
+/// Returns the iroh secret key and the CORD signer stored in its keystore.
+pub fn open_node(
+    path: &PathBuf,
+    password: &str,
+    secret: Option<String>,
+    hsm: Option<&Pkcs11Config>,
+) -> Result<(SecretKey, CordKeystoreSigner), Box<dyn Error>> {
+    tracing::info!("🔄 Restarting process begun...");
+
+    if !path.exists() {
+        return Err(format!(
+            "❌ The provided path {:?} does not exist.\n\
+            Please bootstrap a new node first using the `init` command.",
+            path
+        ).into());
+    }
+
+    tracing::info!(path = %path.display(), "🔑 Checking password for the node");
+    let mut password_file_path = path.clone();
+    password_file_path.push("password");
 
-        let mut keystore_dir = path.clone();
-        keystore_dir.push("keystore");
+    let password_hash = blake3::hash(password.as_bytes()).to_hex().to_string();
 
-        println!("🔐 Opening keystore at {:?}\n", keystore_dir);
+    let stored_password_hash = fs::read_to_string(&password_file_path)
+        .map_err(|e| format!("❌ Failed to read password file: {}", e))?;
 
-        // NOTE: thought that to restart a node, we would need to take in 'secret', not because 
-        //       it is like a password that is required everytime to open the keystore but as it would
-        //       required to fetch the secret key to start the iroh node. But that is not the case.
-        //       It is still in place, in case someone passes --secret or not, it will still work.
-        //       PROOF:
-        //       After bootsrapping a node with 'secret' argument, if the user tries to restart the 
-        //       node with/without the 'secret' argument, the 'secret_key' variable will be the same.
-        let keystore_secret = StarterkitKeystore::keystore_access(args.secret.clone())
-            .map_err(|e| format!("❌ Failed to process keystore secret: {e}"))?;
+    if password_hash != stored_password_hash {
+        return Err("❌ Incorrect password provided. Please check your --password and try again.".into());
+    }
+    tracing::info!("✅ Password verified successfully.");
+
+    let mut keystore_dir = path.clone();
+    keystore_dir.push("keystore");
+
+    tracing::info!(path = %keystore_dir.display(), "🔐 Opening keystore");
+
+    // NOTE: thought that to restart a node, we would need to take in 'secret', not because 
+    //       it is like a password that is required everytime to open the keystore but as it would
+    //       required to fetch the secret key to start the iroh node. But that is not the case.
+    //       It is still in place, in case someone passes --secret or not, it will still work.
+    //       PROOF:
+    //       After bootsrapping a node with 'secret' argument, if the user tries to restart the 
+    //       node with/without the 'secret' argument, the 'secret_key' variable will be the same.
+    let keystore_secret = StarterkitKeystore::keystore_access(secret)
+        .map_err(|e| format!("❌ Failed to process keystore secret: {e}"))?;
+
+    let mut keystore = StarterkitKeystore::open(&keystore_dir, keystore_secret)
+        .map_err(|e| format!("❌ Failed to open keystore: {e}"))?;
+
+    if let Some(config) = hsm {
+        match keystore.attach_hsm(config) {
+            Ok(()) => tracing::info!(module = %config.module.display(), "🔐 Using keys on the PKCS#11 token"),
+            Err(e) => tracing::warn!(error = %e, "⚠️  Failed to open the PKCS#11 token. Falling back to the local keystore."),
+        }
+    }
 
-        let keystore = StarterkitKeystore::open(&keystore_dir, keystore_secret)
-            .map_err(|e| format!("❌ Failed to open keystore: {e}"))?;
+    let starterkit_public = keystore
+        .get_starterkit_public_key()
+        .map_err(|e| format!("❌ Failed to get starterkit public key: {e}"))?;
 
-        let starterkit_public = keystore
-            .get_starterkit_public_key()
-            .map_err(|e| format!("❌ Failed to get starterkit public key: {e}"))?;
+    let secret_key = keystore
+        .get_starter_kit_seed(starterkit_public)
+        .map_err(|e| format!("❌ Failed to get starterkit seed: {e}"))?;
 
-        secret_key = keystore
-            .get_starter_kit_seed(starterkit_public)
-            .map_err(|e| format!("❌ Failed to get starterkit seed: {e}"))?;
+    tracing::info!("✅ Keystore opened successfully.");
 
-        println!("✅ Keystore opened successfully.\n");
+    let cord_signer = keystore.get_cord_signer()?;
+
+    tracing::info!("🎉 Restarting process completed successfully.");
+
+    Ok((secret_key, cord_signer))
+}
 
-        cord_signer = keystore.get_cord_signer()?;
+/// A node's blob and document stores opened without joining the network.
+pub struct LocalStore {
+    pub router: Router,
+    pub blobs: Arc<NodeBlobs>,
+    pub docs: Arc<NodeDocs>,
+}
 
-        println!("🎉 Restarting process completed successfully.\n");
+/// Opens the stores in an existing node directory for offline maintenance.
+///
+/// The endpoint uses a throwaway key, so the keystore is not needed. Relays are only enabled
+/// with `networked`, which lets the store dial peers, e.g. to sync a joined document; otherwise
+/// no peers can reach it. Fails if the node is running, since it holds the store locks.
+pub async fn open_local_store(path: &PathBuf, networked: bool) -> Result<LocalStore, Box<dyn Error>> {
+    if !path.exists() {
+        return Err(format!("❌ The provided path {:?} does not exist.", path).into());
     }
 
+    let relay_mode = if networked { RelayMode::Default } else { RelayMode::Disabled };
     let endpoint = Endpoint::builder()
-        .secret_key(secret_key.clone())
-        .relay_mode(RelayMode::Default)
-        .discovery_n0()
+        .relay_mode(relay_mode)
         .bind()
         .await?;
 
-    let builder = Router::builder(endpoint.clone());
-
-    let node_id = endpoint.clone().node_id();
-
-    let blobs = Blobs::persistent(path.clone()).await?.build(builder.endpoint());
-    let gossip = Gossip::builder().spawn(builder.endpoint().clone()).await?;
-    let docs = Docs::persistent(path.clone()).spawn(&blobs, &gossip).await?;
-    
-    let router = Router::builder(endpoint.clone())
+    let blobs = Blobs::persistent(path.clone())
+        .await
+        .map_err(|e| format!("❌ Failed to open the blob store at {:?}: {e}. Is the node still running?", path))?
+        .build(&endpoint);
+    let gossip = Gossip::builder().spawn(endpoint.clone()).await?;
+    let docs = Docs::persistent(path.clone())
+        .spawn(&blobs, &gossip)
+        .await
+        .map_err(|e| format!("❌ Failed to open the document store at {:?}: {e}. Is the node still running?", path))?;
+
+    let router = Router::builder(endpoint)
         .accept(iroh_blobs::ALPN, blobs.clone())
         .accept(iroh_gossip::ALPN, gossip)
         .accept(iroh_docs::ALPN, docs.clone())
         .spawn()
         .await?;
 
-    Ok(IrohNode {
-        node_id,
+    Ok(LocalStore {
         router,
-        blobs: Arc::new(blobs),
-        docs: Arc::new(docs),
-        cord_signer,
+        blobs: Arc::new(NodeBlobs::new(blobs)),
+        docs: Arc::new(NodeDocs::new(docs)),
     })
-}
\ No newline at end of file
+}
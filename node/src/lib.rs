@@ -1 +1,7 @@
-pub mod iroh_wrapper;
\ No newline at end of file
+pub mod entry_indexer;
+pub mod iroh_wrapper;
+pub mod replica_digests;
+pub mod replication_tracker;
+pub mod search_indexer;
+pub mod sync_scheduler;
+pub mod tenant_tracker;
@@ -0,0 +1,212 @@
+use helpers::utils::{decode_doc_id, decode_key};
+
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use futures::TryStreamExt;
+use iroh::endpoint::Connecting;
+use iroh::protocol::ProtocolHandler;
+use iroh::{Endpoint, NodeAddr, NodeId};
+use iroh_blobs::store::fs::Store;
+use iroh_docs::protocol::Docs;
+use iroh_docs::store::{Query, SortBy, SortDirection};
+use iroh_docs::NamespaceId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// ALPN of the protocol nodes exchange the entry digests of a document over, to check that
+/// their replicas match.
+pub const REPLICA_DIGESTS_ALPN: &[u8] = b"/starter-kit/replica-digests/1";
+
+// Largest request and response accepted, in bytes
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+const MAX_RESPONSE_SIZE: usize = 64 * 1024 * 1024;
+
+/// The entries of a document are grouped in 256 buckets, by the first byte of the blake3 hash
+/// of their key.
+pub fn bucket_of(key: &[u8]) -> u8 {
+    blake3::hash(key).as_bytes()[0]
+}
+
+/// The entries of one bucket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BucketDigest {
+    /// Keys in the bucket.
+    pub keys: u64,
+    /// Hex blake3 hash over the keys of the bucket and their digests, in key order.
+    pub digest: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DigestRequest {
+    /// The digest of every non-empty bucket of a document.
+    Buckets { doc_id: String },
+    /// The digest of every key in some buckets of a document.
+    Keys { doc_id: String, buckets: BTreeSet<u8> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DigestResponse {
+    Buckets { buckets: BTreeMap<u8, BucketDigest> },
+    /// Hex digest of each key, over every author's entry for it, deletions included.
+    Keys { keys: BTreeMap<String, String> },
+    /// The node does not answer for the document.
+    Refused { reason: String },
+}
+
+/// Answers digest requests from other nodes.
+///
+/// A node only answers for documents it holds, and only to peers it synced the document with
+/// or whose node ID `allowed` accepts, since key digests reveal the keys of the document.
+#[derive(Clone)]
+pub struct ReplicaDigests {
+    docs: Docs<Store>,
+    allowed: fn(&str) -> bool,
+}
+
+impl std::fmt::Debug for ReplicaDigests {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ReplicaDigests").finish_non_exhaustive()
+    }
+}
+
+impl ReplicaDigests {
+    pub fn new(docs: Docs<Store>, allowed: fn(&str) -> bool) -> Self {
+        Self { docs, allowed }
+    }
+
+    async fn respond(&self, peer: NodeId, request: DigestRequest) -> DigestResponse {
+        let (doc_id, buckets) = match request {
+            DigestRequest::Buckets { doc_id } => (doc_id, None),
+            DigestRequest::Keys { doc_id, buckets } => (doc_id, Some(buckets)),
+        };
+        let answered = async {
+            let Some(namespace_id) = self.check_access(peer, &doc_id).await? else {
+                return Ok(DigestResponse::Refused { reason: "DocumentNotShared".to_string() });
+            };
+            Ok::<_, anyhow::Error>(match buckets {
+                None => DigestResponse::Buckets { buckets: bucket_digests(&self.docs, namespace_id).await? },
+                Some(buckets) => DigestResponse::Keys { keys: key_digests(&self.docs, namespace_id, &buckets).await? },
+            })
+        };
+        answered.await.unwrap_or_else(|e| {
+            tracing::warn!(doc_id = %doc_id, peer = %peer, error = %e, "failed to compute replica digests");
+            DigestResponse::Refused { reason: "FailedToReadDocument".to_string() }
+        })
+    }
+
+    // The namespace of `doc_id` if this node holds it and answers `peer` for it
+    async fn check_access(&self, peer: NodeId, doc_id: &str) -> Result<Option<NamespaceId>> {
+        let Ok(namespace_id) = decode_doc_id(doc_id).map(NamespaceId::from) else {
+            return Ok(None);
+        };
+        let Some(doc) = self.docs.client().open(namespace_id).await? else {
+            return Ok(None);
+        };
+        let synced = doc
+            .get_sync_peers()
+            .await?
+            .unwrap_or_default()
+            .iter()
+            .any(|known| known == peer.as_bytes());
+        Ok((synced || (self.allowed)(&peer.to_string())).then_some(namespace_id))
+    }
+}
+
+impl ProtocolHandler for ReplicaDigests {
+    fn accept(&self, conn: Connecting) -> BoxFuture<'static, Result<()>> {
+        let this = self.clone();
+        Box::pin(async move {
+            let connection = conn.await?;
+            let peer = connection.remote_node_id()?;
+            // one request per stream, until the peer closes the connection
+            while let Ok((mut send, mut recv)) = connection.accept_bi().await {
+                let request: DigestRequest = serde_json::from_slice(&recv.read_to_end(MAX_REQUEST_SIZE).await?)?;
+                let response = this.respond(peer, request).await;
+                send.write_all(&serde_json::to_vec(&response)?).await?;
+                send.finish()?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Sends `request` to `peer` and waits for its response.
+pub async fn request_digests(endpoint: &Endpoint, peer: NodeAddr, request: &DigestRequest) -> Result<DigestResponse> {
+    let connection = endpoint.connect(peer, REPLICA_DIGESTS_ALPN).await?;
+    let (mut send, mut recv) = connection.open_bi().await?;
+    send.write_all(&serde_json::to_vec(request)?).await?;
+    send.finish()?;
+    let response = recv.read_to_end(MAX_RESPONSE_SIZE).await?;
+    connection.close(0u32.into(), b"done");
+    serde_json::from_slice(&response).map_err(|e| anyhow!("invalid digest response: {e}"))
+}
+
+/// The digest of every non-empty bucket of the document `namespace_id`.
+pub async fn bucket_digests(docs: &Docs<Store>, namespace_id: NamespaceId) -> Result<BTreeMap<u8, BucketDigest>> {
+    let mut buckets: BTreeMap<u8, (u64, blake3::Hasher)> = BTreeMap::new();
+    for_each_key(docs, namespace_id, |key, digest| {
+        let (keys, hasher) = buckets.entry(bucket_of(key)).or_insert_with(|| (0, blake3::Hasher::new()));
+        *keys += 1;
+        hasher.update(&(key.len() as u64).to_le_bytes());
+        hasher.update(key);
+        hasher.update(digest.as_bytes());
+    })
+    .await?;
+
+    Ok(buckets
+        .into_iter()
+        .map(|(bucket, (keys, hasher))| (bucket, BucketDigest { keys, digest: hasher.finalize().to_hex().to_string() }))
+        .collect())
+}
+
+/// The digest of every key of the document `namespace_id` in `buckets`.
+pub async fn key_digests(
+    docs: &Docs<Store>,
+    namespace_id: NamespaceId,
+    buckets: &BTreeSet<u8>,
+) -> Result<BTreeMap<String, String>> {
+    let mut keys = BTreeMap::new();
+    for_each_key(docs, namespace_id, |key, digest| {
+        if buckets.contains(&bucket_of(key)) {
+            keys.insert(String::from_utf8_lossy(key).into_owned(), digest.to_hex().to_string());
+        }
+    })
+    .await?;
+    Ok(keys)
+}
+
+// Calls `f` with each key of the document, in key order, and the digest of its entries: the
+// author, content hash, length and timestamp of each, in author order
+async fn for_each_key(docs: &Docs<Store>, namespace_id: NamespaceId, mut f: impl FnMut(&[u8], blake3::Hash)) -> Result<()> {
+    let doc = docs
+        .client()
+        .open(namespace_id)
+        .await?
+        .ok_or_else(|| anyhow!("document not found"))?;
+    let query = Query::all().include_empty().sort_by(SortBy::KeyAuthor, SortDirection::Asc);
+    let mut entries = doc.get_many(query).await?;
+
+    let mut current: Option<(Vec<u8>, blake3::Hasher)> = None;
+    while let Some(entry) = entries.try_next().await? {
+        let key = decode_key(entry.key());
+        let hasher = match &mut current {
+            Some((current_key, hasher)) if *current_key == key => hasher,
+            _ => {
+                if let Some((done_key, hasher)) = current.take() {
+                    f(&done_key, hasher.finalize());
+                }
+                &mut current.insert((key, blake3::Hasher::new())).1
+            }
+        };
+        hasher.update(entry.author().as_bytes());
+        hasher.update(entry.content_hash().as_bytes());
+        hasher.update(&entry.content_len().to_le_bytes());
+        hasher.update(&entry.timestamp().to_le_bytes());
+    }
+    if let Some((key, hasher)) = current {
+        f(&key, hasher.finalize());
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use helpers::events::{EventBus, NodeEvent};
+use helpers::stores::NodeDocs;
+use helpers::replication::ReplicationTracker;
+use helpers::utils::{decode_doc_id, decode_key, encode_doc_id, SS58AuthorId};
+use helpers::write_queue::{QueuedWrite, WriteQueue};
+
+use futures::{StreamExt, TryStreamExt};
+use iroh_docs::engine::LiveEvent;
+use iroh_docs::{ContentStatus, NamespaceId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+/// Records the syncs, neighbors and sync counters of every document in `tracker`, for the
+/// replication report and the metrics, and the local writes no peer has yet in `queue`.
+///
+/// Documents created or joined later are picked up from the `EventBus`, and dropped documents
+/// are forgotten.
+pub fn spawn_replication_tracker(
+    docs: Arc<NodeDocs>,
+    events: EventBus,
+    tracker: ReplicationTracker,
+    queue: WriteQueue,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        // Subscribe before listing, so documents created in between are not missed
+        let mut node_events = events.subscribe();
+        let mut watchers: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+        match list_doc_ids(&docs).await {
+            Ok(doc_ids) => {
+                for doc_id in doc_ids {
+                    let watcher = watch_doc(docs.clone(), doc_id.clone(), tracker.clone(), queue.clone());
+                    watchers.insert(doc_id, watcher);
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to list documents"),
+        }
+
+        loop {
+            match node_events.recv().await {
+                Ok(NodeEvent::DocCreated { doc_id }) | Ok(NodeEvent::DocJoined { doc_id }) => {
+                    if !watchers.get(&doc_id).is_some_and(|watcher| !watcher.is_finished()) {
+                        let watcher = watch_doc(docs.clone(), doc_id.clone(), tracker.clone(), queue.clone());
+                        watchers.insert(doc_id, watcher);
+                    }
+                }
+                Ok(NodeEvent::DocDropped { doc_id }) => {
+                    if let Some(watcher) = watchers.remove(&doc_id) {
+                        watcher.abort();
+                    }
+                    tracker.forget(&doc_id);
+                    if let Err(e) = queue.forget(&doc_id).await {
+                        tracing::warn!(doc_id = %doc_id, error = %e, "failed to update the write queue");
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!(missed, "fell behind the event bus, some documents may not be tracked");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+async fn list_doc_ids(docs: &NodeDocs) -> anyhow::Result<Vec<String>> {
+    let namespaces: Vec<_> = docs.client().list().await?.try_collect().await?;
+    Ok(namespaces.into_iter().map(|(namespace_id, _)| encode_doc_id(namespace_id.as_bytes())).collect())
+}
+
+// Records the sync, content and local write events of one document
+fn watch_doc(docs: Arc<NodeDocs>, doc_id: String, tracker: ReplicationTracker, queue: WriteQueue) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let subscribed = async {
+            let doc = docs.client()
+                .open(NamespaceId::from(decode_doc_id(&doc_id)?))
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("document not found"))?;
+            doc.subscribe().await
+        };
+        let mut events = match subscribed.await {
+            Ok(events) => events,
+            Err(e) => {
+                tracing::warn!(doc_id = %doc_id, error = %e, "failed to subscribe to document");
+                return;
+            }
+        };
+
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(LiveEvent::SyncFinished(sync)) => {
+                    let (entries_sent, error) = match sync.result {
+                        Ok(details) => (details.entries_sent, None),
+                        Err(e) => (0, Some(e)),
+                    };
+                    if error.is_none() {
+                        // The peer has every write made before the round started
+                        let started = sync.started.duration_since(UNIX_EPOCH).unwrap_or_default().as_micros() as u64;
+                        if let Err(e) = queue.confirm(&doc_id, started).await {
+                            tracing::warn!(doc_id = %doc_id, error = %e, "failed to update the write queue");
+                        }
+                    }
+                    tracker.record_sync(&doc_id, sync.peer.to_string(), entries_sent, error);
+                }
+                Ok(LiveEvent::InsertLocal { entry }) => {
+                    let Ok(author) = SS58AuthorId::from_author_id(&entry.author()) else {
+                        continue;
+                    };
+                    let write = QueuedWrite {
+                        key: String::from_utf8_lossy(&decode_key(entry.key())).into_owned(),
+                        author: author.as_ss58().to_string(),
+                        hash: entry.content_hash().to_string(),
+                        len: entry.content_len(),
+                        timestamp: entry.timestamp(),
+                    };
+                    if let Err(e) = queue.record(&doc_id, write).await {
+                        tracing::warn!(doc_id = %doc_id, error = %e, "failed to update the write queue");
+                    }
+                }
+                Ok(LiveEvent::InsertRemote { entry, content_status, .. }) => {
+                    let complete = content_status == ContentStatus::Complete;
+                    tracker.record_insert_remote(&doc_id, entry.content_hash().to_string(), entry.content_len(), complete);
+                }
+                Ok(LiveEvent::ContentReady { hash }) => tracker.record_content_ready(&doc_id, &hash.to_string()),
+                Ok(LiveEvent::NeighborUp(peer)) => tracker.neighbor_up(&doc_id, peer.to_string()),
+                Ok(LiveEvent::NeighborDown(peer)) => tracker.neighbor_down(&doc_id, &peer.to_string()),
+                _ => {}
+            }
+        }
+    })
+}
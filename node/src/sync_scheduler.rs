@@ -0,0 +1,125 @@
+use helpers::sync_controls::{SyncBlock, SyncControlRegistry};
+use helpers::stores::NodeDocs;
+use helpers::utils::decode_doc_id;
+
+use futures::StreamExt;
+use iroh_docs::engine::LiveEvent;
+use iroh_docs::{ContentStatus, NamespaceId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+// How often the controls are checked again, so that windows open and close and bandwidth limits
+// lift without waiting for a change.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Enforces the sync controls in `controls`: stops syncing a document while its controls block
+/// it and starts syncing it again once they allow it. Documents without controls are left alone,
+/// except that one the scheduler stopped is started again when its controls are removed.
+///
+/// Documents with `max_bytes_per_minute` are watched for content announced by peers, which is
+/// counted against the limit of the current minute.
+pub fn spawn_sync_scheduler(docs: Arc<NodeDocs>, controls: SyncControlRegistry) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let changed = controls.changed();
+        let mut ticks = tokio::time::interval(CHECK_INTERVAL);
+        // whether each document was last set to sync
+        let mut applied: HashMap<String, bool> = HashMap::new();
+        // tasks counting the content announced for documents with a bandwidth limit
+        let mut meters: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+        loop {
+            tokio::select! {
+                _ = ticks.tick() => {},
+                _ = changed.notified() => {},
+            }
+            let listed = controls.list();
+
+            let mut doc_ids: Vec<String> = listed.keys().cloned().collect();
+            doc_ids.extend(applied.keys().filter(|doc_id| !listed.contains_key(*doc_id)).cloned());
+            for doc_id in doc_ids {
+                let blocked_by = controls.blocked_by(&doc_id);
+                let allowed = blocked_by.is_none();
+                if applied.get(&doc_id) != Some(&allowed) {
+                    match set_syncing(&docs, &doc_id, allowed).await {
+                        Ok(()) => {
+                            tracing::info!(doc_id = %doc_id, ?blocked_by, "🔁 Sync {}", if allowed { "resumed" } else { "stopped" });
+                            applied.insert(doc_id.clone(), allowed);
+                        },
+                        Err(e) => tracing::warn!(doc_id = %doc_id, error = %e, "failed to apply sync controls"),
+                    }
+                }
+                // a document without controls is synced as usual, and no longer the scheduler's
+                if !listed.contains_key(&doc_id) && applied.get(&doc_id) == Some(&true) {
+                    applied.remove(&doc_id);
+                }
+            }
+
+            meters.retain(|doc_id, meter| {
+                let limited = listed.get(doc_id).is_some_and(|control| control.max_bytes_per_minute.is_some());
+                if !limited {
+                    meter.abort();
+                }
+                limited && !meter.is_finished()
+            });
+            for (doc_id, control) in &listed {
+                if control.max_bytes_per_minute.is_none() || meters.contains_key(doc_id) {
+                    continue;
+                }
+                match spawn_meter(&docs, doc_id, controls.clone()).await {
+                    Ok(meter) => {
+                        meters.insert(doc_id.clone(), meter);
+                    },
+                    Err(e) => tracing::warn!(doc_id = %doc_id, error = %e, "failed to watch document bandwidth"),
+                }
+            }
+        }
+    })
+}
+
+// Namespace of the document `doc_id`
+fn namespace_id(doc_id: &str) -> anyhow::Result<NamespaceId> {
+    Ok(NamespaceId::from(decode_doc_id(doc_id)?))
+}
+
+// Starts or stops syncing `doc_id` with its peers
+async fn set_syncing(docs: &NodeDocs, doc_id: &str, syncing: bool) -> anyhow::Result<()> {
+    let doc = docs.client()
+        .open(namespace_id(doc_id)?)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("document not found"))?;
+    if syncing {
+        // resumes with the peers the document last synced with
+        doc.start_sync(vec![]).await
+    } else {
+        doc.leave().await
+    }
+}
+
+// Counts the content peers announce for `doc_id` that still has to be downloaded
+async fn spawn_meter(docs: &NodeDocs, doc_id: &str, controls: SyncControlRegistry) -> anyhow::Result<JoinHandle<()>> {
+    let doc = docs.client()
+        .open(namespace_id(doc_id)?)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("document not found"))?;
+    let mut events = doc.subscribe().await?;
+    let doc_id = doc_id.to_string();
+
+    Ok(tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            let Ok(LiveEvent::InsertRemote { entry, content_status, .. }) = event else {
+                continue;
+            };
+            if content_status == ContentStatus::Complete {
+                continue;
+            }
+            let was_blocked = controls.blocked_by(&doc_id) == Some(SyncBlock::BandwidthLimit);
+            controls.record_bytes(&doc_id, entry.content_len());
+            // stop right away rather than at the next check
+            if !was_blocked && controls.blocked_by(&doc_id) == Some(SyncBlock::BandwidthLimit) {
+                controls.changed().notify_one();
+            }
+        }
+    }))
+}
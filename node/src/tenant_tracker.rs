@@ -0,0 +1,34 @@
+use helpers::events::{EventBus, NodeEvent};
+use helpers::tenants::TenantRegistry;
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+/// Takes dropped documents and deleted authors out of the tenants they belonged to, however they
+/// were removed, so a tenant's quota is freed and a later document or author is not mistaken for
+/// one of its own.
+pub fn spawn_tenant_tracker(events: EventBus, tenants: TenantRegistry) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut node_events = events.subscribe();
+
+        loop {
+            match node_events.recv().await {
+                Ok(NodeEvent::DocDropped { doc_id }) => {
+                    if let Err(e) = tenants.forget_doc(&doc_id).await {
+                        tracing::warn!(doc_id = %doc_id, error = %e, "failed to update the tenants");
+                    }
+                }
+                Ok(NodeEvent::AuthorDeleted { author_id }) => {
+                    if let Err(e) = tenants.forget_author(&author_id).await {
+                        tracing::warn!(author_id = %author_id, error = %e, "failed to update the tenants");
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!(missed, "fell behind the event bus, some tenants may keep removed documents or authors");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
@@ -1 +1,3 @@
+pub mod middleware;
 pub mod router;
+pub mod serve;
\ No newline at end of file
@@ -0,0 +1,430 @@
+use axum::{
+    Router,
+    body::Body,
+    extract::{ConnectInfo, MatchedPath, Query, RawPathParams, State},
+    http::{header, HeaderName, Request, Response, StatusCode},
+    middleware::{from_fn, Next},
+    response::IntoResponse,
+};
+use futures::StreamExt;
+use helpers::{
+    access_log::{AccessEvent, ACCESS_LOGGED_ROUTES},
+    entry_index::EntryIndex,
+    limits::{OperationKind, OperationLimits},
+    public::RequestRateLimiter,
+    state::{AppState, READ_ONLY_MESSAGE},
+    tenants::{tenant_route, Tenant, TenantRegistry, TenantRejection, API_KEY_HEADER},
+    utils::{get_caller_from_headers, EntryResource, ENTRY_ROUTE},
+};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::Span;
+
+/// Header carrying the request ID. A client-supplied value is kept, otherwise a UUID is generated.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Wraps every route with request ID assignment, structured request logging, and
+// echoing of the request ID in the response.
+pub fn with_request_tracing(router: Router) -> Router {
+    let request_id_header = HeaderName::from_static(REQUEST_ID_HEADER);
+
+    router.layer(
+        ServiceBuilder::new()
+            .layer(SetRequestIdLayer::new(request_id_header.clone(), MakeRequestUuid))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(make_span)
+                    .on_request(())
+                    .on_response(on_response)
+                    .on_failure(()),
+            )
+            .layer(PropagateRequestIdLayer::new(request_id_header)),
+    )
+}
+
+// Opens a span per request holding the request ID, route and caller identity
+fn make_span(request: &Request<Body>) -> Span {
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("")
+            .to_string()
+    };
+
+    // Log the route template rather than the raw path, so IDs in paths don't explode cardinality
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    tracing::info_span!(
+        "request",
+        request_id = %header(REQUEST_ID_HEADER),
+        method = %request.method(),
+        route = %route,
+        node_id = %header("nodeId"),
+        origin = %header("Origin"),
+        author_id = %header("author-id"),
+    )
+}
+
+fn on_response(response: &Response<Body>, latency: Duration, _span: &Span) {
+    tracing::info!(
+        status = response.status().as_u16(),
+        latency_ms = latency.as_millis() as u64,
+        "request completed"
+    );
+}
+
+// Rejects local writes with `403 Forbidden` when the node is a read-only replica
+pub async fn reject_on_read_only(State(state): State<AppState>, request: Request<Body>, next: Next) -> Response<Body> {
+    if state.read_only {
+        return (StatusCode::FORBIDDEN, READ_ONLY_MESSAGE).into_response();
+    }
+    next.run(request).await
+}
+
+// Runs the request within the limit of `kind` operations running at once, see `OperationLimits`.
+// The slot is held until the response body is sent, as exports stream their content in it.
+pub async fn limit_operations(
+    State((limits, kind)): State<(OperationLimits, OperationKind)>,
+    request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    let permit = limits.acquire(kind).await;
+    next.run(request).await.map(|body| {
+        Body::from_stream(body.into_data_stream().map(move |chunk| {
+            let _held = &permit;
+            chunk
+        }))
+    })
+}
+
+// Rejects the request with `429 Too Many Requests` once its client IP address is over the limit,
+// see `--public-rate-limit`. Needs the router to be served with `serve_tcp_with_client_addr`.
+pub async fn limit_request_rate(
+    State(limiter): State<RequestRateLimiter>,
+    request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    // without a client address, every request shares one allowance
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+    match limiter.check(client) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, (wait.as_secs_f64().ceil() as u64).max(1).to_string())],
+            "Too many requests, retry later",
+        )
+            .into_response(),
+    }
+}
+
+// Largest JSON body read for the tenant checks and the access log, as for the JSON extractor
+const MAX_TENANT_BODY: usize = 2 * 1024 * 1024;
+
+// Whether the request declares a JSON body, the only kind the JSON extractor accepts, so other
+// bodies can stream through without being read for the tenant checks and the access log
+fn has_json_body<B>(request: &Request<B>) -> bool {
+    let Some(content_type) = request.headers().get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    mime == "application/json" || (mime.starts_with("application/") && mime.ends_with("+json"))
+}
+
+// The route template of a request. The resources of an entry share `ENTRY_ROUTE`, so they are
+// told apart by the end of the key, e.g. `/docs/:id/entries/*key/verify`.
+fn route_template(request: &Request<Body>, path_params: Option<&RawPathParams>) -> Option<&'static str> {
+    let route = request.extensions().get::<MatchedPath>()?.as_str();
+    if route != ENTRY_ROUTE {
+        return None;
+    }
+    let path = path_params?.iter().find(|(name, _)| *name == "key").map(|(_, path)| path)?;
+    Some(EntryResource::split(path).1.route())
+}
+
+// Derives the tenant from the API key of the request, if it has one, and keeps it to the routes,
+// documents and authors of that tenant, see `TENANT_ROUTES`. The tenant is passed on to the
+// handlers as an extension. Requests without a key are left as they are.
+pub async fn resolve_tenant(
+    State((tenants, entry_index)): State<(TenantRegistry, EntryIndex)>,
+    path_params: Option<RawPathParams>,
+    request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    let Some(key) = request.headers().get(API_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+    let Some(tenant) = key.to_str().ok().and_then(|key| tenants.authenticate(key)) else {
+        return (StatusCode::UNAUTHORIZED, "Invalid API key").into_response();
+    };
+    let access = route_template(&request, path_params.as_ref())
+        .or_else(|| request.extensions().get::<MatchedPath>().map(MatchedPath::as_str))
+        .and_then(tenant_route);
+    let Some(access) = access else {
+        return (StatusCode::FORBIDDEN, "This endpoint is not available to tenants").into_response();
+    };
+
+    // Documents and authors named in a JSON body, such as `doc_id` of `/docs/set-entry`
+    let (request, body) = if has_json_body(&request) {
+        let (parts, body) = request.into_parts();
+        let bytes = match axum::body::to_bytes(body, MAX_TENANT_BODY).await {
+            Ok(bytes) => bytes,
+            Err(_) => return (StatusCode::PAYLOAD_TOO_LARGE, "The request body is too large").into_response(),
+        };
+        let json = serde_json::from_slice::<serde_json::Value>(&bytes).ok();
+        (Request::from_parts(parts, Body::from(bytes)), json)
+    } else {
+        (request, None)
+    };
+
+    let caller = request.headers().get("author-id").and_then(|value| value.to_str().ok());
+    let path_doc_ids: Vec<&str> = path_params
+        .iter()
+        .flat_map(|params| params.iter())
+        .filter(|(name, _)| *name == "id")
+        .map(|(_, doc_id)| doc_id)
+        .collect();
+    if let Err(rejection) = tenant.check_request(access, caller, &path_doc_ids, body.as_ref(), &entry_index) {
+        let status = match rejection {
+            TenantRejection::DocNotFound => StatusCode::NOT_FOUND,
+            TenantRejection::ForeignAuthor | TenantRejection::OverStorage => StatusCode::FORBIDDEN,
+        };
+        return (status, rejection.to_string()).into_response();
+    }
+
+    let mut request = request;
+    request.extensions_mut().insert(tenant);
+    next.run(request).await
+}
+
+// Records the successful reads of entries and blobs in the access log, see `ACCESS_LOGGED_ROUTES`.
+// The document, key and blob read are taken from the path, the query or the JSON body, and the
+// tenant from `resolve_tenant`, which runs first.
+pub async fn record_access(
+    State(state): State<AppState>,
+    path_params: Option<RawPathParams>,
+    request: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    let route = route_template(&request, path_params.as_ref())
+        .or_else(|| request.extensions().get::<MatchedPath>().map(MatchedPath::as_str))
+        .map(str::to_string);
+    let Some(route) = route.filter(|route| state.access_log.settings().is_enabled() && ACCESS_LOGGED_ROUTES.contains(&route.as_str())) else {
+        return next.run(request).await;
+    };
+
+    let mut fields: HashMap<String, String> = Query::try_from_uri(request.uri()).map(|query| query.0).unwrap_or_default();
+    for (name, value) in path_params.iter().flat_map(|params| params.iter()) {
+        match name {
+            "id" => fields.insert("doc_id".to_string(), value.to_string()),
+            "key" if route.starts_with(ENTRY_ROUTE) => fields.insert(name.to_string(), EntryResource::split(value).0.to_string()),
+            "key" | "hash" => fields.insert(name.to_string(), value.to_string()),
+            _ => None,
+        };
+    }
+    let request = if has_json_body(&request) {
+        let (parts, body) = request.into_parts();
+        let bytes = match axum::body::to_bytes(body, MAX_TENANT_BODY).await {
+            Ok(bytes) => bytes,
+            Err(_) => return (StatusCode::PAYLOAD_TOO_LARGE, "The request body is too large").into_response(),
+        };
+        if let Ok(json) = serde_json::from_slice::<serde_json::Value>(&bytes) {
+            for name in ["doc_id", "key", "hash"] {
+                if let Some(value) = json.get(name).and_then(|value| value.as_str()) {
+                    fields.entry(name.to_string()).or_insert_with(|| value.to_string());
+                }
+            }
+        }
+        Request::from_parts(parts, Body::from(bytes))
+    } else {
+        request
+    };
+
+    let Some(sample_rate) = state.access_log.sample(&route, fields.get("doc_id").map(String::as_str)) else {
+        return next.run(request).await;
+    };
+    let event = AccessEvent {
+        caller: get_caller_from_headers(request.headers()),
+        tenant: request.extensions().get::<Tenant>().map(|tenant| tenant.id.clone()),
+        route,
+        doc_id: fields.remove("doc_id"),
+        key: fields.remove("key"),
+        hash: fields.remove("hash"),
+        sample_rate,
+    };
+
+    let response = next.run(request).await;
+    if response.status().is_success() {
+        if let Err(e) = state.access_log.record(event) {
+            tracing::error!(error = %e, "failed to record a read in the access log");
+        }
+    }
+    response
+}
+
+// Wraps the router so that requests without a tenant API key are rejected, see `--require-api-key`
+pub fn with_required_api_key(router: Router) -> Router {
+    router.layer(from_fn(require_api_key))
+}
+
+async fn require_api_key(request: Request<Body>, next: Next) -> Response<Body> {
+    if !request.headers().contains_key(API_KEY_HEADER) {
+        return (StatusCode::UNAUTHORIZED, "An API key is required").into_response();
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        middleware::from_fn_with_state,
+        routing::{get, post},
+        Extension,
+    };
+    use helpers::{entry_index::IndexUpdate, tenants::TenantQuota};
+    use tower::ServiceExt;
+
+    const KEY: &str = "skt_test";
+
+    // Routes answering the tenant the request was resolved to and the length of its body
+    async fn app(quota: TenantQuota) -> Router {
+        let tenants = TenantRegistry::default();
+        tenants.create("acme".to_string(), None, quota).await.unwrap();
+        tenants.assign_doc("acme", "d-own").await.unwrap();
+        tenants.assign_author("acme", "a-own").await.unwrap();
+        tenants.add_api_key("acme", "key".to_string(), None, KEY).await.unwrap();
+
+        let entry_index = EntryIndex::new(Vec::new());
+        let update = IndexUpdate {
+            raw_key: b"key".to_vec(),
+            key: "key".to_string(),
+            author: "a-own".to_string(),
+            hash: "hash".to_string(),
+            len: 100,
+            timestamp: 1,
+        };
+        entry_index.record_entry("d-own", update, None);
+        entry_index.mark_ready("d-own");
+
+        let echo = |tenant: Option<Extension<Tenant>>, body: Body| async move {
+            let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+            format!("{}:{}", tenant.map(|Extension(tenant)| tenant.id).unwrap_or_default(), body.len())
+        };
+        Router::new()
+            .route("/docs/:id/stats", get(echo))
+            .route(ENTRY_ROUTE, get(echo))
+            .route("/docs/get-entry", post(echo))
+            .route("/docs/set-entry", post(echo))
+            .route("/blobs/add-blob", post(echo))
+            .layer(from_fn_with_state((tenants, entry_index), resolve_tenant))
+    }
+
+    async fn send(app: &Router, request: Request<Body>) -> (StatusCode, String) {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn request(method: &str, uri: &str, author_id: Option<&str>, json: Option<serde_json::Value>) -> Request<Body> {
+        let mut builder = Request::builder().method(method).uri(uri).header(API_KEY_HEADER, KEY);
+        if let Some(author_id) = author_id {
+            builder = builder.header("author-id", author_id);
+        }
+        match json {
+            Some(json) => builder.header(header::CONTENT_TYPE, "application/json").body(Body::from(json.to_string())),
+            None => builder.body(Body::empty()),
+        }
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tenant_is_kept_to_its_docs_and_authors() {
+        let app = app(TenantQuota::default()).await;
+
+        let (status, body) = send(&app, request("GET", "/docs/d-own/stats", Some("a-own"), None)).await;
+        assert_eq!((status, body.as_str()), (StatusCode::OK, "acme:0"));
+        let without_key = Request::builder().uri("/docs/d-other/stats").body(Body::empty()).unwrap();
+        assert_eq!(send(&app, without_key).await, (StatusCode::OK, ":0".to_string()));
+
+        assert_eq!(send(&app, request("GET", "/docs/d-other/stats", None, None)).await.0, StatusCode::NOT_FOUND);
+        let foreign_doc = serde_json::json!({ "doc_id": "d-other", "key": "k" });
+        assert_eq!(send(&app, request("POST", "/docs/get-entry", None, Some(foreign_doc))).await.0, StatusCode::NOT_FOUND);
+        assert_eq!(send(&app, request("GET", "/docs/d-own/stats", Some("a-other"), None)).await.0, StatusCode::FORBIDDEN);
+        let foreign_writer = serde_json::json!({ "doc_id": "d-own", "author_id": "a-other" });
+        assert_eq!(send(&app, request("POST", "/docs/set-entry", None, Some(foreign_writer))).await.0, StatusCode::FORBIDDEN);
+
+        assert_eq!(send(&app, request("POST", "/blobs/add-blob", None, None)).await.0, StatusCode::FORBIDDEN);
+        let invalid_key = Request::builder().uri("/docs/d-own/stats").header(API_KEY_HEADER, "skt_other").body(Body::empty()).unwrap();
+        assert_eq!(send(&app, invalid_key).await.0, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_access_to_entry_resources() {
+        let app = app(TenantQuota::default()).await;
+
+        for uri in ["/docs/d-own/entries/notes/2024/a", "/docs/d-own/entries/notes/a/attachments", "/docs/d-own/entries/notes/a/verify"] {
+            assert_eq!(send(&app, request("GET", uri, None, None)).await.0, StatusCode::OK, "{}", uri);
+        }
+        assert_eq!(send(&app, request("GET", "/docs/d-other/entries/notes/a", None, None)).await.0, StatusCode::NOT_FOUND);
+        let inclusion_proof = request("GET", "/docs/d-own/entries/notes/a/inclusion-proof", None, None);
+        assert_eq!(send(&app, inclusion_proof).await.0, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_storage_quota_is_enforced() {
+        let set_entry = || request("POST", "/docs/set-entry", None, Some(serde_json::json!({ "doc_id": "d-own" })));
+
+        let full = app(TenantQuota { max_bytes: Some(100), ..Default::default() }).await;
+        let (status, body) = send(&full, set_entry()).await;
+        assert_eq!((status, body.as_str()), (StatusCode::FORBIDDEN, "The tenant is over its storage quota"));
+        // reads still work
+        assert_eq!(send(&full, request("GET", "/docs/d-own/stats", None, None)).await.0, StatusCode::OK);
+
+        let with_room = app(TenantQuota { max_bytes: Some(101), ..Default::default() }).await;
+        assert_eq!(send(&with_room, set_entry()).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_only_json_bodies_are_buffered() {
+        let app = app(TenantQuota::default()).await;
+        let large = vec![b'x'; MAX_TENANT_BODY + 1];
+
+        let other = Request::builder()
+            .method("POST")
+            .uri("/docs/set-entry")
+            .header(API_KEY_HEADER, KEY)
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .body(Body::from(large.clone()))
+            .unwrap();
+        assert_eq!(send(&app, other).await, (StatusCode::OK, format!("acme:{}", large.len())));
+
+        let json = Request::builder()
+            .method("POST")
+            .uri("/docs/set-entry")
+            .header(API_KEY_HEADER, KEY)
+            .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+            .body(Body::from(large))
+            .unwrap();
+        assert_eq!(send(&app, json).await.0, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -1,59 +1,397 @@
 use api::{
+    access_log_handler::*,
+    approvals_handler::*,
     authors_handler::*,
+    backups_handler::*,
+    batch_handler::*,
     blobs_handler::*,
+    debug_handler::*,
+    did_handler::*,
     docs_handler::*,
-    gateway_handler::*
+    federation_handler::*,
+    gateway_handler::*,
+    jobs_handler::*,
+    keystore_handler::*,
+    node_handler::*,
+    notifications_handler::*,
+    proofs_handler::*,
+    public_handler::*,
+    replication_handler::*,
+    s3_handler::*,
+    schemas_handler::*,
+    sharding_handler::*,
+    storage_handler::*,
+    tenants_handler::*,
+    webdav_handler::*,
+    webhooks_handler::*,
+    write_queue_handler::*,
+    ws_handler::*
 };
-use helpers::state::AppState;
+use graphql::schema::{graphql_handler, graphiql_handler};
+use helpers::{limits::OperationKind, public::RequestRateLimiter, state::AppState, utils::ENTRY_ROUTE};
+use crate::middleware::{limit_operations, limit_request_rate, record_access, reject_on_read_only, resolve_tenant, with_request_tracing};
 
-use axum::{Router, routing::{get, post}};
-use tower_http::cors::CorsLayer;
+use axum::{Router, http::{Method, StatusCode}, middleware::from_fn_with_state, routing::{any, get, post, delete}};
+use std::time::Duration;
+use tower_http::{
+    cors::{Any, CorsLayer},
+    timeout::TimeoutLayer,
+    compression::{
+        CompressionLayer,
+        predicate::{NotForContentType, Predicate, SizeAbove},
+    },
+};
 
-pub fn create_router(state: AppState) -> Router {
-    Router::new()
-        .route("/blobs/add-blob-bytes", post(add_blob_bytes_handler))
-        .route("/blobs/add-blob-named", post(add_blob_named_handler))
-        .route("/blobs/add-blob-from-path", post(add_blob_from_path_handler))
-        .route("/blobs/list-blobs", get(list_blobs_handler))
+/// Options for the HTTP middleware stack.
+#[derive(Debug, Clone)]
+pub struct RouterConfig {
+    /// Minimum response size in bytes before compression is applied.
+    pub compression_min_size: u16,
+    /// Time budgets for each group of routes.
+    pub timeouts: RouteTimeouts,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            compression_min_size: 1024,
+            timeouts: RouteTimeouts::default(),
+        }
+    }
+}
+
+/// How long a request may run before it is cancelled with `504 Gateway Timeout`.
+///
+/// The budget covers the handler up to the response headers. Streamed bodies (blob downloads,
+/// SSE and WebSocket connections) are not cut off once they have started.
+#[derive(Debug, Clone)]
+pub struct RouteTimeouts {
+    /// Lookups and listings that only touch the local store.
+    pub read: Duration,
+    /// Writes to documents, blobs, authors and the gateway lists.
+    pub write: Duration,
+    /// Transfers from peers and file imports/exports, which depend on the network or disk.
+    pub download: Duration,
+}
+
+impl Default for RouteTimeouts {
+    fn default() -> Self {
+        Self {
+            read: Duration::from_secs(10),
+            write: Duration::from_secs(30),
+            download: Duration::from_secs(600),
+        }
+    }
+}
+
+fn timeout(budget: Duration) -> TimeoutLayer {
+    TimeoutLayer::with_status_code(StatusCode::GATEWAY_TIMEOUT, budget)
+}
+
+pub fn create_router(state: AppState, config: RouterConfig) -> Router {
+    let reads = Router::new()
         .route("/blobs/get-blob", get(get_blob_handler))
         .route("/blobs/status-blob", get(status_blob_handler))
         .route("/blobs/has-blob", get(has_blob_handler))
-        .route("/blobs/download-blob", post(download_blob_handler))
-        .route("/blobs/download-hash-sequence", post(download_hash_sequence_handler))
-        .route("/blobs/download-with-options", post(download_with_options_handler))
         .route("/blobs/list-tags", get(list_tags_handler))
-        .route("/blobs/delete-tag", post(delete_tag_handler))
-        .route("/blobs/export-blob-to-file", post(export_blob_to_file_handler))
+        .route("/blobs/downloads", get(list_downloads_handler))
+        .route("/blobs/downloads/:id", get(get_download_handler))
         .route("/authors/list-authors", get(list_authors_handler))
         .route("/authors/get-default-author", get(get_default_author_handler))
-        .route("/authors/set-default-author", post(set_default_author_handler))
-        .route("/authors/create-author", post(create_author_handler))
-        .route("/authors/delete-author", post(delete_author_handler))
         .route("/authors/verify-author", post(verify_author_handler))
+        .route("/authors/linked-did", get(get_linked_did_handler))
         .route("/docs/get-document", post(get_document_handler))
         .route("/docs/get-entry-blob", post(get_entry_blob_handler))
-        .route("/docs/create-document", post(create_doc_handler))
         .route("/docs/list-docs", get(list_docs_handler))
+        .route("/docs/get-entry", post(get_entry_handler))
+        .route("/docs/status", get(status_handler))
+        .route("/docs/get-download-policy", get(get_download_policy_handler))
+        .route("/docs/sync-prefixes", get(get_sync_prefixes_handler))
+        .route("/docs/sync-controls", get(get_sync_controls_handler))
+        .route("/docs/content-policy", get(get_content_policy_handler))
+        .route("/admin/backups", get(list_backups_handler))
+        .route("/admin/replication", get(replication_handler))
+        .route("/admin/jobs", get(list_jobs_handler))
+        .route("/admin/jobs/:name", get(get_job_handler))
+        .route("/admin/tenants", get(list_tenants_handler))
+        .route("/admin/tenants/:id", get(get_tenant_handler))
+        .route("/docs/write-queue", get(write_queue_handler))
+        .route("/sharded/document", get(get_sharded_doc_handler))
+        .route("/sharded/get-entry", post(get_sharded_entry_handler))
+        .route("/federation/status", get(federation_status_handler))
+        .route("/docs/get-resolved-entry", post(get_resolved_entry_handler))
+        .route("/docs/list-anchors", get(list_doc_anchors_handler))
+        .route("/docs/statement-status", get(statement_status_handler))
+        .route("/docs/export-cord-schema", get(export_cord_schema_handler))
+        .route("/docs/issue-credential", post(issue_credential_handler))
+        .route("/docs", get(list_docs_handler))
+        .route(ENTRY_ROUTE, get(entry_resource_handler))
+        .route("/docs/verify-inclusion-proof", post(verify_inclusion_proof_handler))
+        .route("/docs/:id/trash", get(list_trash_handler))
+        .route("/docs/:id/entries-at", get(get_entries_at_handler))
+        .route("/docs/:id/metrics", get(doc_metrics_handler))
+        .route("/docs/:id/stats", get(doc_stats_handler))
+        .route("/docs/find-by-field", post(find_entries_by_field_handler))
+        .route("/docs/:id/search", get(search_entries_handler))
+        .route("/blobs/:hash", get(get_blob_rest_handler))
+        .route("/version", get(version_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/did/document", get(did_document_handler))
+        .route("/did/resolve", get(resolve_did_handler))
+        .route("/proofs/:id", get(get_proof_handler))
+        .route("/proofs/:id/verify", get(verify_proof_handler))
+        .route("/schemas", get(list_schemas_handler))
+        .route("/schemas/:name", get(get_latest_schema_handler))
+        .route("/schemas/:name/:version", get(get_schema_handler))
+        .route("/gateway/is-node-id-allowed", get(is_node_id_allowed_handler))
+        .route("/gateway/is-domain-allowed", get(is_domain_allowed_handler))
+        .route("/webhooks/list-webhooks", get(list_webhooks_handler))
+        .route("/notifications/list-watches", get(list_watches_handler))
+        .route("/approvals/list-pending", get(list_pending_operations_handler))
+        .route("/keystore/sign-payload", post(sign_payload_handler))
+        .route("/keystore/sign", post(sign_handler))
+        .route("/keystore/verify-payload", post(verify_payload_handler))
+        .route("/keystore/list-keys", get(list_keys_handler))
+        .route("/keystore/has-key", post(has_key_handler))
+        .route("/keystore/list-key-types", get(list_key_types_handler))
+        .route("/keystore/audit-log", get(audit_log_handler))
+        .layer(timeout(config.timeouts.read));
+
+    // Rejected on a read-only replica, see `--read-only`
+    let writes = Router::new()
+        .route("/blobs/add-blob-bytes", post(add_blob_bytes_handler))
+        .route("/blobs/add-blob-named", post(add_blob_named_handler))
+        .route("/blobs/delete-tag", post(delete_tag_handler))
+        .route("/authors/set-default-author", post(set_default_author_handler))
+        .route("/authors/create-author", post(create_author_handler))
+        .route("/authors/create-author-from-keystore", post(create_author_from_keystore_handler))
+        .route("/authors/delete-author", post(delete_author_handler))
+        .route("/authors/link-did", post(link_did_handler))
+        .route("/authors/unlink-did", post(unlink_did_handler))
+        .route("/docs/create-document", post(create_doc_handler))
         .route("/docs/drop-doc", post(drop_doc_handler))
-        .route("/docs/share-doc", post(share_doc_handler))
-        .route("/docs/join-doc", post(join_doc_handler))
-        .route("/docs/close-doc", post(close_doc_handler))
         .route("/docs/add-doc-schema", post(add_doc_schema_handler))
         .route("/docs/set-entry", post(set_entry_handler))
-        .route("/docs/set-entry-file", post(set_entry_file_handler))
-        .route("/docs/get-entry", post(get_entry_handler))
-        .route("/docs/get-entries", post(get_entries_handler))
+        .route("/docs/set-content-policy", post(set_content_policy_handler))
         .route("/docs/delete-entry", post(delete_entry_handler))
+        .route("/docs/restore-entry", post(restore_entry_handler))
+        .route("/docs/empty-trash", post(empty_trash_handler))
+        .route("/docs/anchor-doc", post(anchor_doc_handler))
+        .route("/docs/revoke-statement", post(revoke_statement_handler))
+        .route("/docs/import-cord-schema", post(import_cord_schema_handler))
+        .route("/schemas/publish", post(publish_schema_handler))
+        .route("/proofs", post(create_proofs_handler))
+        .route("/docs/sync-cord-definitions", post(sync_cord_definitions_handler))
+        .route("/docs/push-schema-to-cord", post(push_schema_to_cord_handler))
+        .route("/docs/:id", delete(drop_doc_rest_handler))
+        .route("/batch", post(batch_handler))
+        .route("/approvals/propose-operation", post(propose_operation_handler))
+        .route("/approvals/approve-operation", post(approve_operation_handler))
+        .route("/federation/catalog/add", post(add_to_catalog_handler))
+        .route("/federation/catalog/remove", post(remove_from_catalog_handler))
+        .route("/sharded/create-document", post(create_sharded_doc_handler))
+        .route("/sharded/set-entry", post(set_sharded_entry_handler))
+        .route("/sharded/delete-entry", post(delete_sharded_entry_handler))
+        .route("/sharded/drop-document", post(drop_sharded_doc_handler))
+        .route("/admin/tenants/:id/authors", post(add_tenant_author_handler))
+        .route("/admin/jobs/:name/run", post(run_job_handler))
+        .route_layer(from_fn_with_state(state.clone(), reject_on_read_only))
+        .layer(timeout(config.timeouts.write));
+
+    // Sync and node settings, which a read-only replica still accepts. GraphQL mutations are
+    // rejected by the schema itself.
+    let settings = Router::new()
+        .route("/docs/share-doc", post(share_doc_handler))
+        .route("/docs/join-doc", post(join_doc_handler))
+        .route("/docs/share-bundle", post(share_doc_bundle_handler))
+        .route("/sharded/share", post(share_sharded_doc_handler))
+        .route("/docs/join-bundle", post(join_doc_bundle_handler))
+        .route("/docs/close-doc", post(close_doc_handler))
         .route("/docs/leave", post(leave_handler))
-        .route("/docs/status", get(status_handler))
         .route("/docs/set-download-policy", post(set_download_policy_handler))
-        .route("/docs/get-download-policy", get(get_download_policy_handler))
-        .route("/gateway/is-node-id-allowed", get(is_node_id_allowed_handler))
-        .route("/gateway/is-domain-allowed", get(is_domain_allowed_handler))
+        .route("/docs/set-sync-prefixes", post(set_sync_prefixes_handler))
+        .route("/docs/pause-sync", post(pause_sync_handler))
+        .route("/docs/resume-sync", post(resume_sync_handler))
+        .route("/docs/set-sync-controls", post(set_sync_controls_handler))
+        .route("/docs/write-queue/flush", post(flush_write_queue_handler))
+        .route("/graphql", get(graphiql_handler).post(graphql_handler))
         .route("/gateway/add-node-id", post(add_node_id_handler))
         .route("/gateway/remove-node-id", post(remove_node_id_handler))
         .route("/gateway/add-domain", post(add_domain_handler))
         .route("/gateway/remove-domain", post(remove_domain_handler))
-        .with_state(state)
+        .route("/webhooks/register-webhook", post(register_webhook_handler))
+        .route("/webhooks/delete-webhook", post(delete_webhook_handler))
+        .route("/notifications/register-watch", post(register_watch_handler))
+        .route("/notifications/delete-watch", post(delete_watch_handler))
+        .route("/admin/tenants", post(create_tenant_handler))
+        .route("/admin/tenants/:id", delete(delete_tenant_handler))
+        .route("/admin/tenants/:id/quota", post(set_tenant_quota_handler))
+        .route("/admin/tenants/:id/docs", post(assign_tenant_doc_handler))
+        .route("/admin/tenants/:id/docs/:doc_id", delete(unassign_tenant_doc_handler))
+        .route("/admin/tenants/:id/authors/:author_id", delete(remove_tenant_author_handler))
+        .route("/admin/tenants/:id/api-keys", post(create_api_key_handler))
+        .route("/admin/tenants/:id/api-keys/:key_id", delete(revoke_api_key_handler))
+        .route("/keystore/generate-key", post(generate_key_handler))
+        .route("/keystore/register-key-type", post(register_key_type_handler))
+        .layer(timeout(config.timeouts.write));
+
+    // Walk a whole document or store, see `--max-concurrent-scans`
+    let scans = Router::new()
+        .route("/blobs/list-blobs", get(list_blobs_handler))
+        .route("/blobs/list-blobs/stream", get(stream_blobs_handler))
+        .route("/authors/list-authors/stream", get(stream_authors_handler))
+        .route("/docs/list-docs/stream", get(stream_docs_handler))
+        .route("/docs/get-entries", post(get_entries_handler))
+        .route("/sharded/get-entries", post(get_sharded_entries_handler))
+        .route("/docs/conflicts", get(list_conflicts_handler))
+        .route("/admin/access-log", get(access_log_handler))
+        .route("/admin/storage-report", get(storage_report_handler))
+        .route_layer(from_fn_with_state((state.limits.clone(), OperationKind::Scan), limit_operations))
+        .layer(timeout(config.timeouts.read));
+
+    let local_imports = Router::new()
+        .route("/blobs/add-blob-from-path", post(add_blob_from_path_handler))
+        .route("/docs/set-entry-file", post(set_entry_file_handler))
+        .route("/docs/import-archive", post(import_doc_archive_handler))
+        .route_layer(from_fn_with_state((state.limits.clone(), OperationKind::Import), limit_operations))
+        .route_layer(from_fn_with_state(state.clone(), reject_on_read_only))
+        .layer(timeout(config.timeouts.download));
+
+    let peer_downloads = Router::new()
+        .route("/blobs/download-blob", post(download_blob_handler))
+        .route("/blobs/download-hash-sequence", post(download_hash_sequence_handler))
+        .route("/blobs/download-with-options", post(download_with_options_handler))
+        .route_layer(from_fn_with_state((state.limits.clone(), OperationKind::Download), limit_operations))
+        .layer(timeout(config.timeouts.download));
+
+    let exports = Router::new()
+        .route("/blobs/export-blob-to-file", post(export_blob_to_file_handler))
+        .route("/blobs/:hash/download", get(download_blob_file_handler))
+        .route("/blobs/:hash/raw", get(raw_blob_handler))
+        .route("/gw/:hash", get(gateway_handler))
+        .route("/gw/:hash/", get(gateway_handler))
+        .route("/gw/:hash/*path", get(gateway_handler))
+        .route("/docs/:id/archive", get(export_doc_archive_handler))
+        .route("/docs/:id/export", get(export_doc_table_handler))
+        .route("/admin/backups", post(create_backup_handler))
+        .route_layer(from_fn_with_state((state.limits.clone(), OperationKind::Export), limit_operations))
+        .layer(timeout(config.timeouts.download));
+
+    // Downloads started here take their slot in the background, see `start_download`
+    let downloads = Router::new()
+        .route("/blobs/downloads", post(start_download_handler))
+        .route("/docs/verify-replica", post(verify_replica_handler))
+        .route("/admin/backups/prune", post(prune_backups_handler))
+        .layer(timeout(config.timeouts.download));
+
+    // Long-lived streams are closed by the client, not by a timeout
+    let streams = Router::new()
+        .route("/docs/:id/events", get(doc_events_sse_handler))
+        .route("/ws", get(ws_handler));
+
+    let router = Router::new()
+        .merge(reads)
+        .merge(scans)
+        .merge(writes)
+        .merge(settings)
+        .merge(local_imports)
+        .merge(peer_downloads)
+        .merge(exports)
+        .merge(downloads)
+        .merge(streams)
+        .layer(from_fn_with_state(state.clone(), record_access))
+        .layer(from_fn_with_state((state.tenants.clone(), state.entry_index.clone()), resolve_tenant))
+        .with_state(state);
+
+    // Event streams, gRPC and images are left uncompressed
+    let compress_when = SizeAbove::new(config.compression_min_size)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::SSE);
+
+    with_request_tracing(router)
+        .layer(CompressionLayer::new().gzip(true).br(true).compress_when(compress_when))
         .layer(CorsLayer::very_permissive())
-}
\ No newline at end of file
+}
+
+/// Routes of the diagnostics endpoints, served on the `--admin-listen` address only.
+///
+/// They answer without the node ID check, timeouts or compression of the API, so they still
+/// respond when the node is stalled, and are meant for operators on a private address.
+pub fn create_admin_router(state: AppState) -> Router {
+    Router::new()
+        .route("/debug/runtime", get(runtime_handler))
+        .route("/debug/memory", get(memory_handler))
+        .route("/debug/probe", get(probe_handler))
+        .with_state(state)
+}
+
+/// Routes of the read-only S3-compatible API, served on the `--s3-listen` address only.
+///
+/// S3 clients cannot send the node ID header, so these routes skip the node ID check and are
+/// meant for a private address. Reads of object bodies count as exports.
+pub fn create_s3_router(state: AppState, config: RouterConfig) -> Router {
+    let listings = Router::new()
+        .route("/", get(list_buckets_handler))
+        .route("/:bucket", get(list_objects_handler))
+        .route("/:bucket/", get(list_objects_handler))
+        .route_layer(from_fn_with_state((state.limits.clone(), OperationKind::Scan), limit_operations))
+        .layer(timeout(config.timeouts.read));
+
+    let objects = Router::new()
+        .route("/:bucket/*key", get(get_object_handler))
+        .route_layer(from_fn_with_state((state.limits.clone(), OperationKind::Export), limit_operations))
+        .layer(timeout(config.timeouts.download));
+
+    with_request_tracing(Router::new().merge(listings).merge(objects).with_state(state))
+}
+
+/// Routes of the read-only WebDAV share of the documents, served on the `--webdav-listen`
+/// address only.
+///
+/// File managers cannot send the node ID header, so these routes skip the node ID check and are
+/// meant for a private address. Every request counts as an export.
+pub fn create_webdav_router(state: AppState, config: RouterConfig) -> Router {
+    let router = Router::new()
+        .route("/", any(webdav_handler))
+        .route("/*path", any(webdav_handler))
+        .route_layer(from_fn_with_state((state.limits.clone(), OperationKind::Export), limit_operations))
+        .layer(timeout(config.timeouts.download))
+        .with_state(state);
+
+    with_request_tracing(router)
+}
+
+/// Routes of the public gateway, served on the `--public-listen` address only.
+///
+/// They answer anyone, without the node ID check, but only for the documents of `--public-doc`
+/// and the blobs of `--public-blob`, and only on GET routes, so nothing on this address changes
+/// the node. Each client IP address is limited to `--public-rate-limit` requests per minute, so
+/// the router must be served with `serve_tcp_with_client_addr`.
+pub fn create_public_router(state: AppState, config: RouterConfig) -> Router {
+    let limiter = RequestRateLimiter::new(state.public.rate_limit);
+
+    let listings = Router::new()
+        .route("/docs", get(list_public_docs_handler))
+        .route("/docs/:id/entries", get(list_public_entries_handler))
+        .route_layer(from_fn_with_state((state.limits.clone(), OperationKind::Scan), limit_operations))
+        .layer(timeout(config.timeouts.read));
+
+    let content = Router::new()
+        .route("/docs/:id/entries/*key", get(get_public_entry_handler))
+        .route("/blobs/:hash", get(get_public_blob_handler))
+        .route_layer(from_fn_with_state((state.limits.clone(), OperationKind::Export), limit_operations))
+        .layer(timeout(config.timeouts.download));
+
+    let router = Router::new()
+        .merge(listings)
+        .merge(content)
+        .layer(from_fn_with_state(limiter, limit_request_rate))
+        .with_state(state);
+
+    let compress_when = SizeAbove::new(config.compression_min_size).and(NotForContentType::IMAGES);
+
+    with_request_tracing(router)
+        .layer(CompressionLayer::new().gzip(true).br(true).compress_when(compress_when))
+        .layer(CorsLayer::new().allow_origin(Any).allow_methods([Method::GET, Method::HEAD]))
+}
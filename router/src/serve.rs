@@ -0,0 +1,91 @@
+use axum::{Extension, Router};
+use helpers::tenants::OperatorConnection;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::{conn::auto::Builder, graceful::GracefulShutdown},
+    service::TowerToHyperService,
+};
+use std::{future::Future, io, net::SocketAddr, path::Path};
+use tokio::net::{TcpListener, UnixListener};
+
+/// Default TCP address used when no `--listen` or `--listen-uds` option is given.
+pub const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:4001";
+
+/// Serves the router on a bound TCP listener until `shutdown` resolves.
+pub async fn serve_tcp(
+    listener: TcpListener,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> io::Result<()> {
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown)
+        .await
+}
+
+/// Serves the router on a bound TCP listener until `shutdown` resolves, giving handlers the
+/// client's address as `ConnectInfo<SocketAddr>`.
+pub async fn serve_tcp_with_client_addr(
+    listener: TcpListener,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> io::Result<()> {
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown)
+        .await
+}
+
+/// Binds a unix domain socket, replacing a stale socket file left by a previous run.
+pub fn bind_uds(path: &Path) -> io::Result<UnixListener> {
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    UnixListener::bind(path)
+}
+
+/// Serves the router on a bound unix domain socket until `shutdown` resolves.
+///
+/// Every request is marked with `OperatorConnection`, since only the operator can reach the
+/// socket. In-flight connections are drained and the socket file is removed on shutdown.
+pub async fn serve_uds(
+    listener: UnixListener,
+    path: &Path,
+    app: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> io::Result<()> {
+    let app = app.layer(Extension(OperatorConnection));
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        tracing::warn!(error = %e, socket = %path.display(), "failed to accept unix socket connection");
+                        continue;
+                    }
+                };
+
+                let service = TowerToHyperService::new(app.clone());
+                let connection = Builder::new(TokioExecutor::new())
+                    .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                    .into_owned();
+                let connection = graceful.watch(connection);
+
+                tokio::spawn(async move {
+                    if let Err(e) = connection.await {
+                        tracing::debug!(error = %e, "unix socket connection closed with error");
+                    }
+                });
+            }
+            _ = &mut shutdown => break,
+        }
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+    let _ = std::fs::remove_file(path);
+
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use helpers::webhooks::Webhook;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Delivery attempts per event, including the first one.
+const MAX_ATTEMPTS: u32 = 6;
+// Wait before the first retry. Doubles after each failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The JSON body POSTed to a webhook URL.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPayload {
+    /// Unique ID of this delivery. Retries of the same event reuse it, so receivers can deduplicate.
+    pub id: String,
+    /// One of `helpers::webhooks::WEBHOOK_EVENTS`.
+    pub event: String,
+    pub doc_id: String,
+    /// Seconds since the Unix epoch when the event was observed.
+    pub timestamp: u64,
+    pub data: Value,
+}
+
+impl WebhookPayload {
+    pub fn new(event: &str, doc_id: &str, data: Value) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            event: event.to_string(),
+            doc_id: doc_id.to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            data,
+        }
+    }
+}
+
+/// Signs a payload as hex-encoded HMAC-SHA256 of `"{timestamp}.{body}"` keyed with the webhook secret.
+///
+/// Receivers recompute this over the raw request body and the `X-Webhook-Timestamp` header and
+/// compare it with `X-Webhook-Signature`. Including the timestamp lets them reject replays.
+pub fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Builds the shared HTTP client for deliveries.
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("default reqwest client configuration is valid")
+}
+
+/// POSTs `payload` to `webhook`, retrying as `send_with_retries` does.
+pub async fn deliver(client: &reqwest::Client, webhook: &Webhook, payload: &WebhookPayload) -> Result<(), String> {
+    let body = serde_json::to_vec(payload).map_err(|e| e.to_string())?;
+    let signature = sign(&webhook.secret, payload.timestamp, &body);
+
+    send_with_retries(|| {
+        client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Webhook-Id", &payload.id)
+            .header("X-Webhook-Event", &payload.event)
+            .header("X-Webhook-Timestamp", payload.timestamp.to_string())
+            .header("X-Webhook-Signature", format!("sha256={}", signature))
+            .body(body.clone())
+    })
+    .await
+}
+
+/// Sends the request `build` makes, retrying with exponential backoff on network errors,
+/// `429 Too Many Requests` and `5xx` responses.
+///
+/// Gives up after `MAX_ATTEMPTS`, or straight away on any other `4xx`, since resending the
+/// same request will not change the answer.
+pub async fn send_with_retries(build: impl Fn() -> reqwest::RequestBuilder) -> Result<(), String> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut last_error = String::new();
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        match build().send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                last_error = format!("endpoint responded with {}", status);
+                if !(status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS) {
+                    return Err(last_error);
+                }
+            }
+            Err(e) => last_error = e.to_string(),
+        }
+
+        if attempt < MAX_ATTEMPTS {
+            tracing::debug!(attempt, error = %last_error, "delivery failed, retrying");
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    Err(last_error)
+}
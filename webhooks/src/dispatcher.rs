@@ -0,0 +1,160 @@
+use crate::delivery::{self, WebhookPayload};
+use crate::notifications::{Notifier, WatchedEvent};
+use core::docs::{list_docs, subscribe_doc, DocEvent};
+use helpers::{events::{EventBus, NodeEvent}, utils::{is_history_key, is_trash_key}, webhooks::WebhookRegistry};
+use helpers::stores::NodeDocs;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::{collections::HashMap, sync::Arc};
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
+
+/// Watches every document on the node, POSTs matching events to the registered webhooks and
+/// queues entry events for the watches of `notifier`.
+///
+/// Documents created or joined later are picked up from the `EventBus`, and dropped documents
+/// stop being watched. Each delivery runs on its own task, so a slow endpoint never delays
+/// events for other webhooks or documents.
+pub fn spawn_dispatcher(
+    docs: Arc<NodeDocs>,
+    events: EventBus,
+    registry: WebhookRegistry,
+    notifier: Notifier,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let client = delivery::client();
+        // Subscribe before listing, so documents created in between are not missed
+        let mut node_events = events.subscribe();
+        let mut watchers: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+        match list_docs(docs.clone()).await {
+            Ok(doc_list) => {
+                for (doc_id, _) in doc_list {
+                    let watcher = watch_doc(docs.clone(), doc_id.clone(), registry.clone(), notifier.clone(), client.clone());
+                    watchers.insert(doc_id, watcher);
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to list documents"),
+        }
+
+        loop {
+            match node_events.recv().await {
+                Ok(NodeEvent::DocCreated { doc_id }) | Ok(NodeEvent::DocJoined { doc_id }) => {
+                    if !watchers.get(&doc_id).is_some_and(|watcher| !watcher.is_finished()) {
+                        let watcher = watch_doc(docs.clone(), doc_id.clone(), registry.clone(), notifier.clone(), client.clone());
+                        watchers.insert(doc_id, watcher);
+                    }
+                }
+                Ok(NodeEvent::DocDropped { doc_id }) => {
+                    if let Some(watcher) = watchers.remove(&doc_id) {
+                        watcher.abort();
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!(missed, "fell behind the event bus, some documents may not be watched");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+// Forwards the live events of one document to the webhooks and watches that match them
+fn watch_doc(
+    docs: Arc<NodeDocs>,
+    doc_id: String,
+    registry: WebhookRegistry,
+    notifier: Notifier,
+    client: reqwest::Client,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut doc_events = match subscribe_doc(docs, doc_id.clone()).await {
+            Ok(stream) => Box::pin(stream),
+            Err(e) => {
+                tracing::warn!(doc_id = %doc_id, error = %e, "failed to subscribe to document");
+                return;
+            }
+        };
+
+        while let Some(event) = doc_events.next().await {
+            let Ok(event) = event else { continue };
+            if let Some(watched) = watched_event(&doc_id, &event) {
+                notifier.notify(watched);
+            }
+            let Some((event_name, data)) = webhook_event(event) else { continue };
+
+            for webhook in registry.matching(&doc_id, event_name) {
+                let payload = WebhookPayload::new(event_name, &doc_id, data.clone());
+                let client = client.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = delivery::deliver(&client, &webhook, &payload).await {
+                        tracing::warn!(
+                            webhook_id = %webhook.id,
+                            delivery_id = %payload.id,
+                            error = %e,
+                            "webhook delivery failed"
+                        );
+                    }
+                });
+            }
+        }
+    })
+}
+
+// Maps a document event to a webhook event name and payload data.
+// Entries with no content are deletions. Other live events are not sent to webhooks.
+fn webhook_event(event: DocEvent) -> Option<(&'static str, Value)> {
+    let (entry, origin) = match event {
+        DocEvent::InsertLocal { entry } => (entry, None),
+        DocEvent::InsertRemote { from, entry, .. } => (entry, Some(from)),
+        DocEvent::SyncFinished { peer, entries_received, entries_sent, error } => {
+            return Some((
+                "doc.synced",
+                json!({
+                    "peer": peer,
+                    "entries_received": entries_received,
+                    "entries_sent": entries_sent,
+                    "error": error,
+                }),
+            ));
+        }
+        _ => return None,
+    };
+
+    let event_name = if entry.record.len == 0 { "entry.deleted" } else { "entry.set" };
+    Some((
+        event_name,
+        json!({
+            "key": entry.namespace.key,
+            "author": entry.namespace.author,
+            "hash": entry.record.hash,
+            "len": entry.record.len,
+            "timestamp": entry.record.timestamp,
+            // `null` for local writes, otherwise the node ID of the peer the entry came from
+            "from": origin,
+        }),
+    ))
+}
+
+// Maps a document event to the entry event watches are matched against. Entries the node keeps
+// in the trash or history are left out, as they only mirror changes to other keys.
+fn watched_event(doc_id: &str, event: &DocEvent) -> Option<WatchedEvent> {
+    let entry = match event {
+        DocEvent::InsertLocal { entry } | DocEvent::InsertRemote { entry, .. } => entry,
+        _ => return None,
+    };
+    if is_trash_key(&entry.namespace.key) || is_history_key(&entry.namespace.key) {
+        return None;
+    }
+
+    Some(WatchedEvent {
+        event: if entry.record.len == 0 { "entry.deleted" } else { "entry.set" }.to_string(),
+        doc_id: doc_id.to_string(),
+        key: entry.namespace.key.clone(),
+        author: entry.namespace.author.clone(),
+        hash: entry.record.hash.clone(),
+        len: entry.record.len,
+        timestamp: entry.record.timestamp,
+    })
+}
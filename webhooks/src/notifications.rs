@@ -0,0 +1,298 @@
+use crate::delivery::{self, sign};
+use helpers::notifications::{EmailSettings, NotificationChannel, Watch, WatchRegistry};
+
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+// Events kept in one notification. Later ones are only counted.
+const MAX_BATCH_EVENTS: usize = 100;
+// Period `Watch::max_per_hour` is counted over.
+const RATE_PERIOD: Duration = Duration::from_secs(3600);
+// Time allowed for a whole SMTP exchange.
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An event on an entry that a watch matched.
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchedEvent {
+    /// One of `helpers::notifications::NOTIFICATION_EVENTS`.
+    pub event: String,
+    pub doc_id: String,
+    pub key: String,
+    pub author: String,
+    pub hash: String,
+    pub len: u64,
+    /// Microseconds since the Unix epoch, like entry timestamps.
+    pub timestamp: u64,
+}
+
+/// The events a watch collected, sent together as one notification.
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    /// Unique ID of this notification. Retries reuse it, so receivers can deduplicate.
+    pub id: String,
+    pub watch_id: String,
+    /// Seconds since the Unix epoch when the notification was sent.
+    pub timestamp: u64,
+    pub events: Vec<WatchedEvent>,
+    /// Events left out of `events` because the notification was full.
+    pub omitted: usize,
+}
+
+/// Routes entry events to the watches they match. Each watch has its own task that batches its
+/// events, keeps to its rate limit and sends them, so a slow channel never delays other watches.
+#[derive(Clone)]
+pub struct Notifier {
+    registry: WatchRegistry,
+    client: reqwest::Client,
+    queues: Arc<Mutex<HashMap<String, mpsc::UnboundedSender<WatchedEvent>>>>,
+}
+
+impl Notifier {
+    pub fn new(registry: WatchRegistry) -> Self {
+        Self {
+            registry,
+            client: delivery::client(),
+            queues: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Queues `event` for every watch it matches.
+    pub fn notify(&self, event: WatchedEvent) {
+        let watches = self.registry.matching(&event.doc_id, &event.event, &event.key, &event.author);
+        let mut queues = self.queues.lock().unwrap();
+        // Dropping the queue of a removed watch stops its task
+        queues.retain(|id, _| self.registry.get(id).is_some());
+
+        for watch in watches {
+            let queue = queues.entry(watch.id.clone()).or_insert_with(|| {
+                let (sender, receiver) = mpsc::unbounded_channel();
+                tokio::spawn(run_watch(watch.id.clone(), receiver, self.registry.clone(), self.client.clone()));
+                sender
+            });
+            let _ = queue.send(event.clone());
+        }
+    }
+}
+
+// Sends the events queued for one watch, a batch at a time, until its queue is dropped
+async fn run_watch(
+    watch_id: String,
+    mut queue: mpsc::UnboundedReceiver<WatchedEvent>,
+    registry: WatchRegistry,
+    client: reqwest::Client,
+) {
+    // When each notification of the last `RATE_PERIOD` was sent
+    let mut sent: VecDeque<Instant> = VecDeque::new();
+
+    while let Some(first) = queue.recv().await {
+        let Some(watch) = registry.get(&watch_id) else { break };
+        let mut events = vec![first];
+        let mut omitted = 0;
+
+        let window_end = Instant::now() + Duration::from_secs(watch.batch_window);
+        let mut open = collect(&mut queue, &mut events, &mut omitted, window_end).await;
+
+        // Over the rate limit, events keep joining the batch until a notification may be sent
+        loop {
+            while sent.front().is_some_and(|at| at.elapsed() >= RATE_PERIOD) {
+                sent.pop_front();
+            }
+            if sent.len() < watch.max_per_hour as usize {
+                break;
+            }
+            let allowed_at = sent[0] + RATE_PERIOD;
+            if open {
+                open = collect(&mut queue, &mut events, &mut omitted, allowed_at).await;
+            } else {
+                tokio::time::sleep_until(allowed_at).await;
+            }
+        }
+
+        // The watch may have been removed while its events were collected
+        let Some(watch) = registry.get(&watch_id) else { break };
+        sent.push_back(Instant::now());
+        let notification = Notification {
+            id: uuid::Uuid::new_v4().to_string(),
+            watch_id: watch.id.clone(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            events,
+            omitted,
+        };
+        if let Err(e) = send(&client, registry.email(), &watch, &notification).await {
+            tracing::warn!(
+                watch_id = %watch.id,
+                channel = watch.channel.kind(),
+                notification_id = %notification.id,
+                error = %e,
+                "notification failed"
+            );
+        }
+
+        if !open {
+            break;
+        }
+    }
+}
+
+// Adds the events queued until `deadline` to `events`, counting those past `MAX_BATCH_EVENTS`
+// in `omitted`. Returns `false` once the queue is dropped.
+async fn collect(
+    queue: &mut mpsc::UnboundedReceiver<WatchedEvent>,
+    events: &mut Vec<WatchedEvent>,
+    omitted: &mut usize,
+    deadline: Instant,
+) -> bool {
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => return true,
+            event = queue.recv() => match event {
+                Some(event) if events.len() < MAX_BATCH_EVENTS => events.push(event),
+                Some(_) => *omitted += 1,
+                None => return false,
+            },
+        }
+    }
+}
+
+/// Sends `notification` through the channel of `watch`, retrying webhook and Matrix requests as
+/// webhook deliveries are.
+pub async fn send(
+    client: &reqwest::Client,
+    email: Option<&EmailSettings>,
+    watch: &Watch,
+    notification: &Notification,
+) -> Result<(), String> {
+    match &watch.channel {
+        NotificationChannel::Webhook { url, secret } => {
+            let body = serde_json::to_vec(notification).map_err(|e| e.to_string())?;
+            let signature = sign(secret, notification.timestamp, &body);
+            delivery::send_with_retries(|| {
+                client
+                    .post(url)
+                    .header("Content-Type", "application/json")
+                    .header("X-Webhook-Id", &notification.id)
+                    .header("X-Webhook-Event", "notification")
+                    .header("X-Webhook-Timestamp", notification.timestamp.to_string())
+                    .header("X-Webhook-Signature", format!("sha256={}", signature))
+                    .body(body.clone())
+            })
+            .await
+        }
+        NotificationChannel::Matrix { homeserver, room_id, access_token } => {
+            // The notification ID is the transaction ID, so the homeserver ignores retries of a
+            // message it already posted
+            let mut url = reqwest::Url::parse(homeserver).map_err(|e| e.to_string())?;
+            url.path_segments_mut()
+                .map_err(|_| "homeserver is not a base URL".to_string())?
+                .pop_if_empty()
+                .extend(["_matrix", "client", "v3", "rooms", room_id, "send", "m.room.message", &notification.id]);
+            let body = json!({
+                "msgtype": "m.text",
+                "body": format!("{}\n\n{}", subject(notification), summary(watch, notification)),
+            });
+            delivery::send_with_retries(|| client.put(url.clone()).bearer_auth(access_token).json(&body)).await
+        }
+        NotificationChannel::Email { to } => {
+            let email = email.ok_or("email notifications need --smtp-relay")?;
+            tokio::time::timeout(SMTP_TIMEOUT, send_mail(email, to, &subject(notification), &summary(watch, notification)))
+                .await
+                .map_err(|_| "SMTP relay timed out".to_string())?
+        }
+    }
+}
+
+fn subject(notification: &Notification) -> String {
+    let count = notification.events.len() + notification.omitted;
+    match count {
+        1 => "1 change to watched entries".to_string(),
+        count => format!("{} changes to watched entries", count),
+    }
+}
+
+// A line per event, for the text channels
+fn summary(watch: &Watch, notification: &Notification) -> String {
+    let mut lines: Vec<String> = notification
+        .events
+        .iter()
+        .map(|event| {
+            format!(
+                "{} {} in {} by {} at {}",
+                event.event, event.key, event.doc_id, event.author, event.timestamp
+            )
+        })
+        .collect();
+    if notification.omitted > 0 {
+        lines.push(format!("... and {} more", notification.omitted));
+    }
+    lines.push(String::new());
+    lines.push(format!("Watch {}", watch.id));
+    lines.join("\n")
+}
+
+// Hands a plain-text mail to the relay, without TLS or authentication, see `--smtp-relay`
+async fn send_mail(email: &EmailSettings, to: &str, subject: &str, body: &str) -> Result<(), String> {
+    let stream = TcpStream::connect(&email.relay).await.map_err(|e| e.to_string())?;
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let helo_domain = email.from.rsplit_once('@').map_or("localhost", |(_, domain)| domain);
+
+    expect_reply(&mut reader, "220").await?;
+    for (command, code) in [
+        (format!("EHLO {}", helo_domain), "250"),
+        (format!("MAIL FROM:<{}>", email.from), "250"),
+        (format!("RCPT TO:<{}>", to), "250"),
+        ("DATA".to_string(), "354"),
+    ] {
+        write_line(&mut writer, &command).await?;
+        expect_reply(&mut reader, code).await?;
+    }
+
+    write_line(&mut writer, &format!("From: <{}>", email.from)).await?;
+    write_line(&mut writer, &format!("To: <{}>", to)).await?;
+    write_line(&mut writer, &format!("Subject: {}", subject)).await?;
+    write_line(&mut writer, "Content-Type: text/plain; charset=utf-8").await?;
+    write_line(&mut writer, "Content-Transfer-Encoding: 8bit").await?;
+    write_line(&mut writer, "").await?;
+    for line in body.lines() {
+        // A line starting with a dot would otherwise end the message or lose the dot
+        let line = if line.starts_with('.') { format!(".{}", line) } else { line.to_string() };
+        write_line(&mut writer, &line).await?;
+    }
+    write_line(&mut writer, ".").await?;
+    expect_reply(&mut reader, "250").await?;
+
+    write_line(&mut writer, "QUIT").await?;
+    Ok(())
+}
+
+async fn write_line(writer: &mut (impl AsyncWrite + Unpin), line: &str) -> Result<(), String> {
+    writer.write_all(format!("{}\r\n", line).as_bytes()).await.map_err(|e| e.to_string())
+}
+
+// Reads a reply, which may span several lines, and checks its code
+async fn expect_reply(reader: &mut (impl AsyncBufReadExt + Unpin), code: &str) -> Result<(), String> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await.map_err(|e| e.to_string())? == 0 {
+            return Err("SMTP relay closed the connection".to_string());
+        }
+        // `250-` continues a reply, `250 ` ends it
+        if line.as_bytes().get(3) == Some(&b'-') {
+            continue;
+        }
+        if line.starts_with(code) {
+            return Ok(());
+        }
+        return Err(format!("SMTP relay replied: {}", line.trim_end()));
+    }
+}